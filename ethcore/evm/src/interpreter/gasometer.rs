@@ -121,6 +121,9 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
 				Request::Gas(Gas::from(1))
 			},
 			instructions::SSTORE => {
+				if schedule.eip1706 && self.current_gas <= Gas::from(schedule.call_stipend) {
+					return Err(vm::Error::OutOfGas);
+				}
 				let address = H256::from(stack.peek(0));
 				let newval = stack.peek(1);
 				let val = U256::from(&*ext.storage_at(&address)?);
@@ -369,10 +372,12 @@ fn to_word_size<Gas: evm::CostType>(value: Gas) -> (Gas, bool) {
 
 #[inline]
 fn calculate_eip1283_sstore_gas<Gas: evm::CostType>(schedule: &Schedule, original: &U256, current: &U256, new: &U256) -> Gas {
+	let sstore_dirty_gas = schedule.sstore_dirty_gas.unwrap_or(schedule.sload_gas);
+
 	Gas::from(
 		if current == new {
 			// 1. If current value equals new value (this is a no-op), 200 gas is deducted.
-			schedule.sload_gas
+			sstore_dirty_gas
 		} else {
 			// 2. If current value does not equal new value
 			if original == current {
@@ -388,7 +393,7 @@ fn calculate_eip1283_sstore_gas<Gas: evm::CostType>(schedule: &Schedule, origina
 				}
 			} else {
 				// 2.2. If original value does not equal current value (this storage slot is dirty), 200 gas is deducted. Apply both of the following clauses.
-				schedule.sload_gas
+				sstore_dirty_gas
 
 				// 2.2.1. If original value is not 0
 				// 2.2.1.1. If current value is 0 (also means that new value is not 0), remove 15000 gas from refund counter. We can prove that refund counter will never go below 0.
@@ -404,6 +409,7 @@ fn calculate_eip1283_sstore_gas<Gas: evm::CostType>(schedule: &Schedule, origina
 
 pub fn handle_eip1283_sstore_clears_refund(ext: &mut vm::Ext, original: &U256, current: &U256, new: &U256) {
 	let sstore_clears_schedule = U256::from(ext.schedule().sstore_refund_gas);
+	let sstore_dirty_gas = ext.schedule().sstore_dirty_gas.unwrap_or(ext.schedule().sload_gas);
 
 	if current == new {
 		// 1. If current value equals new value (this is a no-op), 200 gas is deducted.
@@ -438,11 +444,11 @@ pub fn handle_eip1283_sstore_clears_refund(ext: &mut vm::Ext, original: &U256, c
 				// 2.2.2. If original value equals new value (this storage slot is reset)
 				if original.is_zero() {
 					// 2.2.2.1. If original value is 0, add 19800 gas to refund counter.
-					let refund = U256::from(ext.schedule().sstore_set_gas - ext.schedule().sload_gas);
+					let refund = U256::from(ext.schedule().sstore_set_gas - sstore_dirty_gas);
 					ext.add_sstore_refund(refund);
 				} else {
 					// 2.2.2.2. Otherwise, add 4800 gas to refund counter.
-					let refund = U256::from(ext.schedule().sstore_reset_gas - ext.schedule().sload_gas);
+					let refund = U256::from(ext.schedule().sstore_reset_gas - sstore_dirty_gas);
 					ext.add_sstore_refund(refund);
 				}
 			}
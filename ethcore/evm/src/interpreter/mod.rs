@@ -421,7 +421,9 @@ impl<Cost: CostType> Interpreter<Cost> {
 			((instruction == instructions::RETURNDATACOPY || instruction == instructions::RETURNDATASIZE) && !schedule.have_return_data) ||
 			(instruction == instructions::REVERT && !schedule.have_revert) ||
 			((instruction == instructions::SHL || instruction == instructions::SHR || instruction == instructions::SAR) && !schedule.have_bitwise_shifting) ||
-			(instruction == instructions::EXTCODEHASH && !schedule.have_extcodehash)
+			(instruction == instructions::EXTCODEHASH && !schedule.have_extcodehash) ||
+			(instruction == instructions::CHAINID && !schedule.have_chain_id) ||
+			(instruction == instructions::SELFBALANCE && !schedule.have_selfbalance)
 		{
 			return Err(vm::Error::BadInstruction {
 				instruction: instruction as u8
@@ -836,6 +838,12 @@ impl<Cost: CostType> Interpreter<Cost> {
 			instructions::GASLIMIT => {
 				self.stack.push(ext.env_info().gas_limit.clone());
 			},
+			instructions::CHAINID => {
+				self.stack.push(U256::from(ext.chain_id()));
+			},
+			instructions::SELFBALANCE => {
+				self.stack.push(ext.balance(&self.params.address)?);
+			},
 
 			// Stack instructions
 
@@ -1029,6 +1029,122 @@ fn test_sar(factory: super::Factory) {
 		"0000000000000000000000000000000000000000000000000000000000000000");
 }
 
+evm_test!{test_chain_id: test_chain_id_int}
+fn test_chain_id(factory: super::Factory) {
+	// CHAINID PUSH1 0 SSTORE
+	let code = "46600055".from_hex().unwrap();
+
+	let mut params = ActionParams::default();
+	params.gas = U256::from(100_000);
+	params.code = Some(Arc::new(code));
+	let mut ext = FakeExt::new_istanbul();
+	ext.chain_id = 9;
+
+	let gas_left = {
+		let mut vm = factory.create(params, ext.schedule(), ext.depth());
+		test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap()
+	};
+
+	assert_eq!(gas_left, U256::from(79_995));
+	assert_store(&ext, 0, "0000000000000000000000000000000000000000000000000000000000000009");
+}
+
+evm_test!{test_selfbalance: test_selfbalance_int}
+fn test_selfbalance(factory: super::Factory) {
+	// SELFBALANCE PUSH1 0 SSTORE
+	let code = "47600055".from_hex().unwrap();
+
+	let address = Address::from(0x155);
+	let mut params = ActionParams::default();
+	params.gas = U256::from(100_000);
+	params.code = Some(Arc::new(code));
+	params.address = address.clone();
+	let mut ext = FakeExt::new_istanbul();
+	ext.balances.insert(address, U256::from(0x401));
+
+	let gas_left = {
+		let mut vm = factory.create(params, ext.schedule(), ext.depth());
+		test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap()
+	};
+
+	assert_eq!(gas_left, U256::from(79_992));
+	assert_store(&ext, 0, "0000000000000000000000000000000000000000000000000000000000000401");
+}
+
+evm_test!{test_istanbul_opcodes_not_activated: test_istanbul_opcodes_not_activated_int}
+fn test_istanbul_opcodes_not_activated(factory: super::Factory) {
+	for code in &["46", "47"] {
+		let mut params = ActionParams::default();
+		params.gas = U256::from(100_000);
+		params.code = Some(Arc::new(code.from_hex().unwrap()));
+		let mut ext = FakeExt::new_constantinople();
+
+		let err = {
+			let mut vm = factory.create(params, ext.schedule(), ext.depth());
+			test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap_err()
+		};
+
+		assert_eq!(err, vm::Error::BadInstruction { instruction: code.from_hex().unwrap()[0] });
+	}
+}
+
+evm_test!{test_sstore_stipend_sentry: test_sstore_stipend_sentry_int}
+fn test_sstore_stipend_sentry(factory: super::Factory) {
+	// PUSH1 0 PUSH1 0 SSTORE: a no-op store costing `sstore_dirty_gas`.
+	let code = "6000600055".from_hex().unwrap();
+
+	// 2300 gas left when reaching SSTORE.
+	let mut params = ActionParams::default();
+	params.gas = U256::from(2306);
+	params.code = Some(Arc::new(code.clone()));
+	let mut ext = FakeExt::new_istanbul();
+
+	let err = {
+		let mut vm = factory.create(params, ext.schedule(), ext.depth());
+		test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap_err()
+	};
+	assert_eq!(err, vm::Error::OutOfGas);
+
+	// 2301 gas left when reaching SSTORE.
+	let mut params = ActionParams::default();
+	params.gas = U256::from(2307);
+	params.code = Some(Arc::new(code));
+	let mut ext = FakeExt::new_istanbul();
+
+	let gas_left = {
+		let mut vm = factory.create(params, ext.schedule(), ext.depth());
+		test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap()
+	};
+	assert_eq!(gas_left, U256::from(2301 - 800));
+}
+
+evm_test!{test_eip2200_sstore_gas: test_eip2200_sstore_gas_int}
+fn test_eip2200_sstore_gas(factory: super::Factory) {
+	// (code, gas used, refund) for an original value of 0, from the EIP-2200 test cases.
+	let cases = [
+		("60006000556000600055", 1612, 0),
+		("60006000556001600055", 20812, 0),
+		("60016000556000600055", 20812, 19200),
+		("60016000556002600055", 20812, 0),
+		("60016000556001600055", 20812, 0),
+	];
+
+	for &(code, gas_used, refund) in cases.iter() {
+		let mut params = ActionParams::default();
+		params.gas = U256::from(100_000);
+		params.code = Some(Arc::new(code.from_hex().unwrap()));
+		let mut ext = FakeExt::new_istanbul();
+
+		let gas_left = {
+			let mut vm = factory.create(params, ext.schedule(), ext.depth());
+			test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap()
+		};
+
+		assert_eq!(gas_left, U256::from(100_000 - gas_used), "gas used by {}", code);
+		assert_eq!(ext.sstore_clears, U256::from(refund), "refund of {}", code);
+	}
+}
+
 fn push_two_pop_one_constantinople_test(factory: &super::Factory, opcode: u8, push1: &str, push2: &str, result: &str) {
 	let mut push1 = push1.from_hex().unwrap();
 	let mut push2 = push2.from_hex().unwrap();
@@ -283,6 +283,12 @@ fn guess_capabilities(requests: &[CheckedRequest]) -> Capabilities {
 			CheckedRequest::Account(ref req, _) => if let Ok(ref hdr) = req.header.as_ref() {
 				update_since(&mut caps.serve_state_since, hdr.number());
 			},
+			CheckedRequest::AccountProof(ref req, _) => if let Ok(ref hdr) = req.header.as_ref() {
+				update_since(&mut caps.serve_state_since, hdr.number());
+			},
+			CheckedRequest::Storage(ref req, _) => if let Ok(ref hdr) = req.header.as_ref() {
+				update_since(&mut caps.serve_state_since, hdr.number());
+			},
 			CheckedRequest::Code(ref req, _) => if let Ok(ref hdr) = req.header.as_ref() {
 				update_since(&mut caps.serve_state_since, hdr.number());
 			},
@@ -58,6 +58,10 @@ pub enum Request {
 	Body(Body),
 	/// A request for an account.
 	Account(Account),
+	/// A request for an account along with its merkle proof.
+	AccountProof(AccountProof),
+	/// A request for a storage value along with its merkle proof.
+	Storage(Storage),
 	/// A request for a contract's code.
 	Code(Code),
 	/// A request for proof of execution.
@@ -144,6 +148,8 @@ impl_single!(TransactionIndex, TransactionIndex, net_request::TransactionIndexRe
 impl_single!(Receipts, BlockReceipts, Vec<Receipt>);
 impl_single!(Body, Body, encoded::Block);
 impl_single!(Account, Account, Option<BasicAccount>);
+impl_single!(AccountProof, AccountProof, (Vec<Bytes>, Option<BasicAccount>));
+impl_single!(Storage, Storage, (Vec<Bytes>, H256));
 impl_single!(Code, Code, Bytes);
 impl_single!(Execution, TransactionProof, super::ExecutionResult);
 impl_single!(Signal, Signal, Vec<u8>);
@@ -255,6 +261,8 @@ pub enum CheckedRequest {
 	Receipts(BlockReceipts, net_request::IncompleteReceiptsRequest),
 	Body(Body, net_request::IncompleteBodyRequest),
 	Account(Account, net_request::IncompleteAccountRequest),
+	AccountProof(AccountProof, net_request::IncompleteAccountRequest),
+	Storage(Storage, net_request::IncompleteStorageRequest),
 	Code(Code, net_request::IncompleteCodeRequest),
 	Execution(TransactionProof, net_request::IncompleteExecutionRequest),
 	Signal(Signal, net_request::IncompleteSignalRequest)
@@ -319,6 +327,23 @@ impl From<Request> for CheckedRequest {
 				trace!(target: "on_demand", "Account Request, {:?}", net_req);
 				CheckedRequest::Account(req, net_req)
 			}
+			Request::AccountProof(req) => {
+				let net_req = net_request::IncompleteAccountRequest {
+					block_hash: req.header.field(),
+					address_hash: ::hash::keccak(&req.address).into(),
+				};
+				trace!(target: "on_demand", "AccountProof Request, {:?}", net_req);
+				CheckedRequest::AccountProof(req, net_req)
+			}
+			Request::Storage(req) => {
+				let net_req = net_request::IncompleteStorageRequest {
+					block_hash: req.header.field(),
+					address_hash: ::hash::keccak(&req.address).into(),
+					key_hash: ::hash::keccak(&req.key).into(),
+				};
+				trace!(target: "on_demand", "Storage Request, {:?}", net_req);
+				CheckedRequest::Storage(req, net_req)
+			}
 			Request::Code(req) => {
 				let net_req = net_request::IncompleteCodeRequest {
 					block_hash: req.header.field(),
@@ -364,6 +389,8 @@ impl CheckedRequest {
 			CheckedRequest::Receipts(_, req) => NetRequest::Receipts(req),
 			CheckedRequest::Body(_, req) => NetRequest::Body(req),
 			CheckedRequest::Account(_, req) => NetRequest::Account(req),
+			CheckedRequest::AccountProof(_, req) => NetRequest::Account(req),
+			CheckedRequest::Storage(_, req) => NetRequest::Storage(req),
 			CheckedRequest::Code(_, req) => NetRequest::Code(req),
 			CheckedRequest::Execution(_, req) => NetRequest::Execution(req),
 			CheckedRequest::Signal(_, req) => NetRequest::Signal(req),
@@ -379,6 +406,8 @@ impl CheckedRequest {
 			CheckedRequest::Receipts(ref x, _) => x.0.needs_header(),
 			CheckedRequest::Body(ref x, _) => x.0.needs_header(),
 			CheckedRequest::Account(ref x, _) => x.header.needs_header(),
+			CheckedRequest::AccountProof(ref x, _) => x.header.needs_header(),
+			CheckedRequest::Storage(ref x, _) => x.header.needs_header(),
 			CheckedRequest::Code(ref x, _) => x.header.needs_header(),
 			CheckedRequest::Execution(ref x, _) => x.header.needs_header(),
 			_ => None,
@@ -393,6 +422,8 @@ impl CheckedRequest {
 			CheckedRequest::Receipts(ref mut x, _) => x.0 = HeaderRef::Stored(header),
 			CheckedRequest::Body(ref mut x, _) => x.0 = HeaderRef::Stored(header),
 			CheckedRequest::Account(ref mut x, _) => x.header = HeaderRef::Stored(header),
+			CheckedRequest::AccountProof(ref mut x, _) => x.header = HeaderRef::Stored(header),
+			CheckedRequest::Storage(ref mut x, _) => x.header = HeaderRef::Stored(header),
 			CheckedRequest::Code(ref mut x, _) => x.header = HeaderRef::Stored(header),
 			CheckedRequest::Execution(ref mut x, _) => x.header = HeaderRef::Stored(header),
 			_ => {},
@@ -509,6 +540,8 @@ macro_rules! match_me {
 			CheckedRequest::Receipts($check, $req) => $e,
 			CheckedRequest::Body($check, $req) => $e,
 			CheckedRequest::Account($check, $req) => $e,
+			CheckedRequest::AccountProof($check, $req) => $e,
+			CheckedRequest::Storage($check, $req) => $e,
 			CheckedRequest::Code($check, $req) => $e,
 			CheckedRequest::Execution($check, $req) => $e,
 			CheckedRequest::Signal($check, $req) => $e,
@@ -547,6 +580,8 @@ impl IncompleteRequest for CheckedRequest {
 			CheckedRequest::Receipts(_, ref req) => req.check_outputs(f),
 			CheckedRequest::Body(_, ref req) => req.check_outputs(f),
 			CheckedRequest::Account(_, ref req) => req.check_outputs(f),
+			CheckedRequest::AccountProof(_, ref req) => req.check_outputs(f),
+			CheckedRequest::Storage(_, ref req) => req.check_outputs(f),
 			CheckedRequest::Code(_, ref req) => req.check_outputs(f),
 			CheckedRequest::Execution(_, ref req) => req.check_outputs(f),
 			CheckedRequest::Signal(_, ref req) => req.check_outputs(f),
@@ -591,6 +626,14 @@ impl IncompleteRequest for CheckedRequest {
 				trace!(target: "on_demand", "Account request completed {:?}", req);
 				req.complete().map(CompleteRequest::Account)
 			}
+			CheckedRequest::AccountProof(_, req) => {
+				trace!(target: "on_demand", "AccountProof request completed {:?}", req);
+				req.complete().map(CompleteRequest::Account)
+			}
+			CheckedRequest::Storage(_, req) => {
+				trace!(target: "on_demand", "Storage request completed {:?}", req);
+				req.complete().map(CompleteRequest::Storage)
+			}
 			CheckedRequest::Code(_, req) => {
 				trace!(target: "on_demand", "Code request completed {:?}", req);
 				req.complete().map(CompleteRequest::Code)
@@ -653,6 +696,12 @@ impl net_request::CheckedRequest for CheckedRequest {
 			CheckedRequest::Account(ref prover, _) =>
 				expect!((&NetResponse::Account(ref res), _) =>
 					prover.check_response(cache, &res.proof).map(Response::Account)),
+			CheckedRequest::AccountProof(ref prover, _) =>
+				expect!((&NetResponse::Account(ref res), _) =>
+					prover.check_response(cache, &res.proof).map(Response::AccountProof)),
+			CheckedRequest::Storage(ref prover, _) =>
+				expect!((&NetResponse::Storage(ref res), _) =>
+					prover.check_response(cache, &res.proof).map(Response::Storage)),
 			CheckedRequest::Code(ref prover, _) =>
 				expect!((&NetResponse::Code(ref res), &CompleteRequest::Code(ref req)) =>
 					prover.check_response(cache, &req.code_hash, &res.code).map(Response::Code)),
@@ -685,6 +734,12 @@ pub enum Response {
 	/// Response to an Account request.
 	// TODO: `unwrap_or(engine_defaults)`
 	Account(Option<BasicAccount>),
+	/// Response to an account proof request.
+	/// Returns the raw trie nodes and the account, if it exists.
+	AccountProof((Vec<Bytes>, Option<BasicAccount>)),
+	/// Response to a storage request.
+	/// Returns the raw trie nodes and the stored value.
+	Storage((Vec<Bytes>, H256)),
 	/// Response to a request for code.
 	Code(Vec<u8>),
 	/// Response to a request for proved execution.
@@ -697,14 +752,15 @@ impl net_request::ResponseLike for Response {
 	fn fill_outputs<F>(&self, mut f: F) where F: FnMut(usize, Output) {
 		match *self {
 			Response::HeaderProof((ref hash, _)) => f(0, Output::Hash(*hash)),
-			Response::Account(None) => {
+			Response::Account(None) | Response::AccountProof((_, None)) => {
 				f(0, Output::Hash(KECCAK_EMPTY)); // code hash
 				f(1, Output::Hash(KECCAK_NULL_RLP)); // storage root.
 			}
-			Response::Account(Some(ref acc)) => {
+			Response::Account(Some(ref acc)) | Response::AccountProof((_, Some(ref acc))) => {
 				f(0, Output::Hash(acc.code_hash));
 				f(1, Output::Hash(acc.storage_root));
 			}
+			Response::Storage((_, ref value)) => f(0, Output::Hash(*value)),
 			_ => {}
 		}
 	}
@@ -1002,6 +1058,56 @@ impl Account {
 	}
 }
 
+/// Request for an account structure, keeping the proof around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountProof {
+	/// Header for verification.
+	pub header: HeaderRef,
+	/// Address requested.
+	pub address: Address,
+}
+
+impl AccountProof {
+	/// Check a response with an account against the stored header, yielding
+	/// the proof alongside the account.
+	pub fn check_response(&self, cache: &Mutex<::cache::Cache>, proof: &[Bytes]) -> Result<(Vec<Bytes>, Option<BasicAccount>), Error> {
+		let account = Account {
+			header: self.header.clone(),
+			address: self.address,
+		}.check_response(cache, proof)?;
+
+		Ok((proof.to_vec(), account))
+	}
+}
+
+/// Request for an account's storage value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storage {
+	/// Header for verification.
+	pub header: HeaderRef,
+	/// Address of the account.
+	pub address: Address,
+	/// Storage key requested.
+	pub key: H256,
+	/// Storage root of the account, as proven at the same header.
+	pub storage_root: H256,
+}
+
+impl Storage {
+	/// Check a response with a storage value against the account's storage root.
+	pub fn check_response(&self, _: &Mutex<::cache::Cache>, proof: &[Bytes]) -> Result<(Vec<Bytes>, H256), Error> {
+		let mut db = MemoryDB::new();
+		for node in proof { db.insert(&node[..]); }
+
+		let value = match TrieDB::new(&db, &self.storage_root).and_then(|t| t.get(&keccak(&self.key)))? {
+			Some(val) => ::rlp::decode::<U256>(&val)?.into(),
+			None => H256::zero(),
+		};
+
+		Ok((proof.to_vec(), value))
+	}
+}
+
 /// Request for account code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Code {
@@ -1326,6 +1432,47 @@ mod tests {
 		assert!(req.check_response(&cache, &proof[..]).is_ok());
 	}
 
+	#[test]
+	fn check_storage_proof() {
+		let mut root = H256::default();
+		let mut db = MemoryDB::new();
+		let key = H256::from(7);
+		let value = U256::from(0xdead);
+
+		{
+			let mut trie = SecTrieDBMut::new(&mut db, &mut root);
+			for i in 0..100u64 {
+				trie.insert(&*H256::from(i + 100), &::rlp::encode(&U256::from(i + 1))).unwrap();
+			}
+
+			trie.insert(&*key, &::rlp::encode(&value)).unwrap();
+		}
+
+		let proof = {
+			let trie = SecTrieDB::new(&db, &root).unwrap();
+			let mut recorder = Recorder::new();
+
+			trie.get_with(&*key, &mut recorder).unwrap().unwrap();
+
+			recorder.drain().into_iter().map(|r| r.data).collect::<Vec<_>>()
+		};
+
+		let req = Storage {
+			header: encoded::Header::new(::rlp::encode(&Header::new()).into_vec()).into(),
+			address: Address::random(),
+			key: key,
+			storage_root: root,
+		};
+
+		let cache = Mutex::new(make_cache());
+		let (returned_proof, returned_value) = req.check_response(&cache, &proof[..]).unwrap();
+		assert_eq!(returned_proof, proof);
+		assert_eq!(returned_value, H256::from(value));
+
+		let bad_req = Storage { storage_root: H256::random(), ..req };
+		assert!(bad_req.check_response(&cache, &proof[..]).is_err());
+	}
+
 	#[test]
 	fn check_code() {
 		let code = vec![1u8; 256];
@@ -599,6 +599,18 @@ impl Provider where {
 		let value = self.client.call_contract(block, *address, data)?;
 		decoder.decode(&value).map_err(|e| ErrorKind::Call(format!("Contract call failed {:?}", e)).into())
 	}
+
+	/// Returns private validators for the contract an encoded private transaction is addressed to.
+	pub fn private_transaction_validators(&self, rlp: &[u8]) -> Result<Vec<Address>, Error> {
+		let private_tx: PrivateTransaction = Rlp::new(rlp).as_val()?;
+		self.get_validators(BlockId::Latest, &private_tx.contract())
+	}
+
+	/// Returns the hash of the private transaction an encoded signature belongs to.
+	pub fn signed_private_transaction_hash(&self, rlp: &[u8]) -> Result<H256, Error> {
+		let signed_tx: SignedPrivateTransaction = Rlp::new(rlp).as_val()?;
+		Ok(signed_tx.private_transaction_hash())
+	}
 }
 
 pub trait Importer {
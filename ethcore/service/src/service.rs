@@ -21,7 +21,7 @@ use std::path::Path;
 use std::time::Duration;
 
 use ansi_term::Colour;
-use ethereum_types::H256;
+use ethereum_types::{H256, Address};
 use io::{IoContext, TimerToken, IoHandler, IoService, IoError};
 use stop_guard::StopGuard;
 
@@ -74,6 +74,14 @@ impl PrivateTxHandler for PrivateTxService {
 			}
 		}
 	}
+
+	fn private_transaction_validators(&self, rlp: &[u8]) -> Result<Vec<Address>, String> {
+		self.provider.private_transaction_validators(rlp).map_err(|err| err.to_string())
+	}
+
+	fn signed_private_transaction_hash(&self, rlp: &[u8]) -> Result<H256, String> {
+		self.provider.signed_private_transaction_hash(rlp).map_err(|err| err.to_string())
+	}
 }
 
 /// Client service setup. Creates and registers client and network services with the IO subsystem.
@@ -290,6 +290,12 @@ impl<'x> OpenBlock<'x> {
 		Ok(r)
 	}
 
+	/// Collect the state changes committed from now on, see `State::committed_changes`.
+	/// Nothing is committed while the block is being opened, so none of its changes are missed.
+	pub fn track_state_changes(&mut self) {
+		self.block.state.track_changes();
+	}
+
 	/// Alter the timestamp of the block.
 	pub fn set_timestamp(&mut self, timestamp: u64) {
 		self.block.header.set_timestamp(timestamp);
@@ -556,6 +562,7 @@ fn enact(
 	uncles: Vec<Header>,
 	engine: &EthEngine,
 	tracing: bool,
+	state_history: bool,
 	db: StateDB,
 	parent: &Header,
 	last_hashes: Arc<LastHashes>,
@@ -585,6 +592,10 @@ fn enact(
 		ancestry,
 	)?;
 
+	if state_history {
+		b.track_state_changes();
+	}
+
 	b.populate_from(&header);
 	b.push_transactions(transactions)?;
 
@@ -595,11 +606,13 @@ fn enact(
 	b.close_and_lock()
 }
 
-/// Enact the block given by `block_bytes` using `engine` on the database `db` with given `parent` block header
+/// Enact the block given by `block_bytes` using `engine` on the database `db` with given `parent` block header.
+/// With `state_history` the state changes committed by the block are collected.
 pub fn enact_verified(
 	block: PreverifiedBlock,
 	engine: &EthEngine,
 	tracing: bool,
+	state_history: bool,
 	db: StateDB,
 	parent: &Header,
 	last_hashes: Arc<LastHashes>,
@@ -614,6 +627,7 @@ pub fn enact_verified(
 		block.uncles,
 		engine,
 		tracing,
+		state_history,
 		db,
 		parent,
 		last_hashes,
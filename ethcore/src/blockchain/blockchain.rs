@@ -16,8 +16,8 @@
 
 //! Blockchain database.
 
-use std::collections::{HashMap, HashSet};
-use std::{mem, io};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::{cmp, mem, io};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -26,11 +26,14 @@ use blockchain::{CacheSize, ImportRoute, Config};
 use blockchain::best_block::{BestBlock, BestAncientBlock};
 use blockchain::block_info::{BlockInfo, BlockLocation, BranchBecomingCanonChainData};
 use blockchain::extras::{BlockReceipts, BlockDetails, TransactionAddress, EPOCH_KEY_PREFIX, EpochTransitions};
+use blockchain::log_index::{
+	self, LogField, LogIndexUpdate, LOG_INDEX_FROM_KEY, INDEXED_TOPICS, LOG_INDEX_MIN_ENTRIES, LOG_INDEX_SELECTIVITY,
+};
 use blockchain::update::{ExtrasUpdate, ExtrasInsert};
 use blooms_db;
 use bytes::Bytes;
 use cache_manager::CacheManager;
-use db::{self, Writable, Readable, CacheUpdatePolicy};
+use db::{self, Key, Writable, Readable, CacheUpdatePolicy, ItemPosition};
 use encoded;
 use engines::epoch::{Transition as EpochTransition, PendingTransition as PendingEpochTransition};
 use engines::ForkChoice;
@@ -45,9 +48,10 @@ use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 use receipt::Receipt;
 use rlp_compress::{compress, decompress, blocks_swapper};
-use rlp::RlpStream;
+use rlp::{self, RlpStream};
 use transaction::*;
 use types::blockchain_info::BlockChainInfo;
+use types::filter::Filter;
 use types::tree_route::TreeRoute;
 use views::{BlockView, HeaderView};
 
@@ -225,6 +229,10 @@ pub struct BlockChain {
 	transaction_addresses: RwLock<HashMap<H256, TransactionAddress>>,
 	block_receipts: RwLock<HashMap<H256, BlockReceipts>>,
 
+	// Lowest block number from which the canonical chain is covered by the log index.
+	// `None` if the log index is disabled.
+	log_index_from: RwLock<Option<BlockNumber>>,
+
 	db: Arc<BlockChainDB>,
 
 	cache_man: Mutex<CacheManager<CacheId>>,
@@ -536,6 +544,7 @@ impl BlockChain {
 			block_hashes: RwLock::new(HashMap::new()),
 			transaction_addresses: RwLock::new(HashMap::new()),
 			block_receipts: RwLock::new(HashMap::new()),
+			log_index_from: RwLock::new(None),
 			db: db.clone(),
 			cache_man: Mutex::new(cache_man),
 			pending_best_block: RwLock::new(None),
@@ -654,6 +663,29 @@ impl BlockChain {
 			}
 		}
 
+		{
+			let mut batch = DBTransaction::new();
+			if config.log_index {
+				let from = bc.db.key_value().get(db::COL_LOG_INDEX, LOG_INDEX_FROM_KEY)
+					.expect("Low level database error when fetching log index. Some issue with disk?")
+					.map(|from| rlp::decode(&from).expect("decode error: the db is corrupted or the data structure has changed"));
+				let from = match from {
+					Some(from) => from,
+					None => {
+						// only blocks imported from now on are indexed until the index is backfilled.
+						let from = bc.best_block_number() + 1;
+						batch.put(db::COL_LOG_INDEX, LOG_INDEX_FROM_KEY, &rlp::encode(&from));
+						from
+					}
+				};
+				*bc.log_index_from.write() = Some(from);
+			} else {
+				// blocks imported from now on are not indexed, so the index has to be rebuilt once it is enabled again.
+				batch.delete(db::COL_LOG_INDEX, LOG_INDEX_FROM_KEY);
+			}
+			bc.db.key_value().write(batch).expect("Low level database error when writing log index. Some issue with disk?");
+		}
+
 		bc
 	}
 
@@ -800,6 +832,7 @@ impl BlockChain {
 			self.prepare_update(batch, ExtrasUpdate {
 				block_hashes: self.prepare_block_hashes_update(&info),
 				block_details: self.prepare_block_details_update(block_parent_hash, &info, false),
+				log_index: self.prepare_log_index_update(&receipts, &info),
 				block_receipts: self.prepare_block_receipts_update(receipts, &info),
 				blocks_blooms: self.prepare_block_blooms_update(block.header_view().log_bloom(), &info),
 				transactions_addresses: self.prepare_transaction_addresses_update(block.view().transaction_hashes(), &info),
@@ -850,6 +883,7 @@ impl BlockChain {
 			self.prepare_update(batch, ExtrasUpdate {
 				block_hashes: self.prepare_block_hashes_update(&info),
 				block_details: update,
+				log_index: self.prepare_log_index_update(&receipts, &info),
 				block_receipts: self.prepare_block_receipts_update(receipts, &info),
 				blocks_blooms: self.prepare_block_blooms_update(block.header_view().log_bloom(), &info),
 				transactions_addresses: self.prepare_transaction_addresses_update(block.view().transaction_hashes(), &info),
@@ -1009,6 +1043,7 @@ impl BlockChain {
 		self.prepare_update(batch, ExtrasUpdate {
 			block_hashes: self.prepare_block_hashes_update(&info),
 			block_details: self.prepare_block_details_update(parent_hash, &info, extras.is_finalized),
+			log_index: self.prepare_log_index_update(&receipts, &info),
 			block_receipts: self.prepare_block_receipts_update(receipts, &info),
 			blocks_blooms: self.prepare_block_blooms_update(block.header_view().log_bloom(), &info),
 			transactions_addresses: self.prepare_transaction_addresses_update(block.view().transaction_hashes(), &info),
@@ -1087,6 +1122,17 @@ impl BlockChain {
 				.expect("Low level database error when updating blooms. Some issue with disk?");
 		}
 
+		if let Some(log_index) = update.log_index {
+			// entries of retracted blocks must be removed before entries of enacted blocks
+			// which may share their keys are written.
+			for key in &log_index.deleted {
+				batch.delete(db::COL_LOG_INDEX, &key.key());
+			}
+			for (key, positions) in &log_index.inserted {
+				batch.write(db::COL_LOG_INDEX, key, positions);
+			}
+		}
+
 		// These cached values must be updated last with all four locks taken to avoid
 		// cache decoherence
 		{
@@ -1362,6 +1408,199 @@ impl BlockChain {
 		}
 	}
 
+	/// This function returns modified log index entries.
+	///
+	/// Only canonical blocks are indexed. When a branch becomes canonical, entries of
+	/// retracted blocks are removed and entries of enacted blocks are added.
+	fn prepare_log_index_update(&self, receipts: &[Receipt], info: &BlockInfo) -> Option<LogIndexUpdate> {
+		if self.log_index_from.read().is_none() {
+			return None;
+		}
+
+		match info.location {
+			BlockLocation::Branch => None,
+			BlockLocation::CanonChain => Some(LogIndexUpdate {
+				deleted: Vec::new(),
+				inserted: log_index::block_entries(info.number, info.hash, receipts),
+			}),
+			BlockLocation::BranchBecomingCanonChain(ref data) => {
+				let block_entries = |hash: &H256| {
+					let number = self.block_number(hash)
+						.expect("hash belongs to a block in the route; block number of an inserted block is always available; qed");
+					let receipts = self.block_receipts(hash)
+						.expect("hash belongs to a block in the route; receipts of an inserted block are always available; qed");
+					log_index::block_entries(number, *hash, &receipts.receipts)
+				};
+
+				let deleted = data.retracted.iter()
+					.flat_map(|hash| block_entries(hash))
+					.map(|(key, _)| key)
+					.collect();
+
+				let mut inserted: Vec<_> = data.enacted.iter()
+					.flat_map(|hash| block_entries(hash))
+					.collect();
+				inserted.extend(log_index::block_entries(info.number, info.hash, receipts));
+
+				Some(LogIndexUpdate { deleted, inserted })
+			}
+		}
+	}
+
+	/// Returns the lowest block number from which the canonical chain is covered by the log index,
+	/// or `None` if the index is disabled.
+	pub fn log_index_from(&self) -> Option<BlockNumber> {
+		*self.log_index_from.read()
+	}
+
+	/// Indexes logs of up to `max_blocks` canonical blocks below the current log index coverage.
+	///
+	/// Returns the number of blocks which remain to be indexed, or `None` if the index is disabled
+	/// or the blocks below the coverage are not available.
+	pub fn backfill_log_index(&self, max_blocks: u64) -> Option<BlockNumber> {
+		let mut from = self.log_index_from.write();
+		let end = (*from)?;
+		// genesis has no receipts.
+		if end <= 1 {
+			return Some(0);
+		}
+
+		let start = cmp::max(end.saturating_sub(max_blocks), 1);
+		let mut batch = DBTransaction::new();
+		for number in start..end {
+			let hash = self.block_hash(number)?;
+			let receipts = self.block_receipts(&hash)?;
+			for (key, positions) in log_index::block_entries(number, hash, &receipts.receipts) {
+				batch.write(db::COL_LOG_INDEX, &key, &positions);
+			}
+		}
+
+		batch.put(db::COL_LOG_INDEX, LOG_INDEX_FROM_KEY, &rlp::encode(&start));
+		self.db.key_value().write(batch).expect("Low level database error when writing log index. Some issue with disk?");
+		*from = Some(start);
+
+		Some(start - 1)
+	}
+
+	/// Removes all log index entries. Only blocks imported from now on are covered by the index
+	/// until it is backfilled again.
+	pub fn clear_log_index(&self) {
+		let mut from = self.log_index_from.write();
+		if from.is_none() {
+			return;
+		}
+
+		let mut batch = DBTransaction::new();
+		for (key, _) in self.db.key_value().iter(db::COL_LOG_INDEX) {
+			batch.delete(db::COL_LOG_INDEX, &key);
+		}
+
+		let next = self.best_block_number() + 1;
+		batch.put(db::COL_LOG_INDEX, LOG_INDEX_FROM_KEY, &rlp::encode(&next));
+		self.db.key_value().write(batch).expect("Low level database error when writing log index. Some issue with disk?");
+		*from = Some(next);
+	}
+
+	/// Returns logs matching given filter within the given range of canonical blocks using the log index.
+	///
+	/// Returns `None` if the index is disabled, does not cover the range or the filter isn't selective,
+	/// i.e. it constrains neither addresses nor topics or each of its constraints matches too many blocks.
+	/// Logs are returned in the same order and with the same limit as `logs`.
+	pub fn indexed_logs(&self, filter: &Filter, from_block: BlockNumber, to_block: BlockNumber) -> Option<Vec<LocalizedLogEntry>> {
+		match self.log_index_from() {
+			Some(from) if cmp::max(from_block, 1) >= from => (),
+			_ => return None,
+		}
+
+		// every matching log carries one of the searched values in each constrained dimension,
+		// so the entries of any single dimension are enough to find them.
+		let mut dimensions: Vec<Vec<LogField>> = Vec::new();
+		if let Some(ref addresses) = filter.address {
+			dimensions.push(addresses.iter().cloned().map(LogField::Address).collect());
+		}
+		for (i, topics) in filter.topics.iter().take(INDEXED_TOPICS).enumerate() {
+			if let Some(ref topics) = *topics {
+				dimensions.push(topics.iter().map(|topic| LogField::Topic(i, *topic)).collect());
+			}
+		}
+
+		let max_entries = cmp::max(LOG_INDEX_MIN_ENTRIES, (to_block - from_block + 1) / LOG_INDEX_SELECTIVITY);
+		let positions = dimensions.into_iter()
+			.filter(|fields| !fields.is_empty())
+			.filter_map(|fields| self.log_positions(&fields, from_block, to_block, max_entries))
+			.next()?;
+
+		let mut logs = Vec::new();
+		for ((number, hash), positions) in positions {
+			// entries of blocks which are no longer canonical may linger when the index was disabled during a reorg.
+			if self.block_hash(number) != Some(hash) {
+				continue;
+			}
+
+			let receipts = self.block_receipts(&hash)?.receipts;
+			let tx_hashes = self.block_body(&hash)?.transaction_hashes();
+			let first_log_indices: Vec<usize> = receipts.iter()
+				.scan(0, |sum, receipt| {
+					let first = *sum;
+					*sum += receipt.logs.len();
+					Some(first)
+				})
+				.collect();
+
+			for position in positions {
+				let (tx, i) = (position.transaction as usize, position.index as usize);
+				let entry = match receipts.get(tx).and_then(|receipt| receipt.logs.get(i)) {
+					Some(entry) => entry,
+					None => continue,
+				};
+				if !filter.matches(entry) {
+					continue;
+				}
+
+				logs.push(LocalizedLogEntry {
+					entry: entry.clone(),
+					block_hash: hash,
+					block_number: number,
+					transaction_hash: tx_hashes[tx],
+					transaction_index: tx,
+					transaction_log_index: i,
+					log_index: first_log_indices[tx] + i,
+				});
+			}
+		}
+
+		// like `logs`, keep the most recent entries.
+		if let Some(limit) = filter.limit {
+			let skip = logs.len().saturating_sub(limit);
+			logs.drain(..skip);
+		}
+
+		Some(logs)
+	}
+
+	/// Collects log positions indexed by any of given values within the given range of blocks.
+	///
+	/// Returns `None` once more than `max_entries` index entries are found.
+	fn log_positions(&self, fields: &[LogField], from_block: BlockNumber, to_block: BlockNumber, max_entries: u64) -> Option<BTreeMap<(BlockNumber, H256), BTreeSet<ItemPosition>>> {
+		let mut positions = BTreeMap::new();
+		let mut entries = 0;
+		for field in fields {
+			let start = (*field, from_block).key();
+			for (number, entry) in db::block_index_entries(&**self.db.key_value(), db::COL_LOG_INDEX, &start, to_block) {
+				entries += 1;
+				if entries > max_entries {
+					return None;
+				}
+
+				positions.entry((number, entry.block_hash))
+					.or_insert_with(BTreeSet::new)
+					.extend(entry.positions);
+			}
+		}
+
+		Some(positions)
+	}
+
 	/// Get best block hash.
 	pub fn best_block_hash(&self) -> H256 {
 		self.best_block.read().header.hash()
@@ -1488,9 +1727,11 @@ mod tests {
 	};
 	use blockchain::generator::{BlockGenerator, BlockBuilder, BlockOptions};
 	use blockchain::extras::TransactionAddress;
-	use transaction::{Transaction, Action};
+	use transaction::{Transaction, Action, SignedTransaction};
 	use log_entry::{LogEntry, LocalizedLogEntry};
 	use ethkey::Secret;
+	use types::filter::Filter;
+	use types::ids::BlockId;
 	use test_helpers::new_db;
 	use encoded;
 
@@ -2156,6 +2397,220 @@ mod tests {
 		]);
 	}
 
+	fn log_receipt(logs: Vec<LogEntry>) -> Receipt {
+		Receipt {
+			outcome: TransactionOutcome::StateRoot(H256::default()),
+			gas_used: 10_000.into(),
+			log_bloom: Default::default(),
+			logs: logs,
+		}
+	}
+
+	fn create_transaction(value: u64) -> SignedTransaction {
+		Transaction {
+			nonce: 0.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Create,
+			value: value.into(),
+			data: "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap(),
+		}.sign(&secret(), None)
+	}
+
+	fn log_filter(address: Option<Vec<Address>>, topics: Vec<Option<Vec<H256>>>, limit: Option<usize>) -> Filter {
+		Filter {
+			from_block: BlockId::Earliest,
+			to_block: BlockId::Latest,
+			address: address,
+			topics: topics,
+			limit: limit,
+		}
+	}
+
+	#[test]
+	fn test_indexed_logs() {
+		let t1 = create_transaction(101);
+		let t2 = create_transaction(102);
+		let t3 = create_transaction(103);
+
+		let genesis = BlockBuilder::genesis();
+		let b1 = genesis.add_block_with_transactions(vec![t1, t2]);
+		let b2 = b1.add_block_with_transactions(iter::once(t3));
+		let b1_hash = b1.last().hash();
+		let b2_hash = b2.last().hash();
+
+		let db = new_db();
+		let config = Config { log_index: true, ..Config::default() };
+		let bc = BlockChain::new(config, genesis.last().encoded().raw(), db.clone());
+		insert_block(&db, &bc, b1.last().encoded(), vec![
+			log_receipt(vec![
+				LogEntry { address: 1.into(), topics: vec![10.into()], data: vec![1] },
+				LogEntry { address: 2.into(), topics: vec![10.into(), 20.into()], data: vec![2] },
+			]),
+			log_receipt(vec![
+				LogEntry { address: 1.into(), topics: vec![20.into()], data: vec![3] },
+			]),
+		]);
+		insert_block(&db, &bc, b2.last().encoded(), vec![
+			log_receipt(vec![
+				LogEntry { address: 1.into(), topics: vec![10.into(), 20.into()], data: vec![4] },
+			]),
+		]);
+
+		let filters = vec![
+			log_filter(Some(vec![1.into()]), vec![], None),
+			log_filter(Some(vec![1.into(), 2.into()]), vec![Some(vec![10.into()])], None),
+			log_filter(None, vec![None, Some(vec![20.into()])], None),
+			log_filter(None, vec![Some(vec![10.into(), 20.into()])], Some(2)),
+		];
+		for filter in filters {
+			let expected = bc.logs(vec![b1_hash, b2_hash], |entry| filter.matches(entry), filter.limit);
+			assert!(!expected.is_empty());
+			assert_eq!(bc.indexed_logs(&filter, 0, 2), Some(expected));
+		}
+
+		let entries = bc.indexed_logs(&log_filter(Some(vec![1.into()]), vec![], None), 2, 2).unwrap();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].entry.data, vec![4]);
+
+		// filters which are not selective are answered by blooms.
+		assert_eq!(bc.indexed_logs(&log_filter(None, vec![None, None], None), 0, 2), None);
+	}
+
+	#[test]
+	fn test_indexed_logs_first_match_after_range_start() {
+		let t1 = create_transaction(101);
+		let t3 = create_transaction(103);
+
+		let genesis = BlockBuilder::genesis();
+		let b1 = genesis.add_block_with_transactions(iter::once(t1));
+		let b2 = b1.add_block();
+		let b3 = b2.add_block_with_transactions(iter::once(t3));
+
+		let db = new_db();
+		let config = Config { log_index: true, ..Config::default() };
+		let bc = BlockChain::new(config, genesis.last().encoded().raw(), db.clone());
+		insert_block(&db, &bc, b1.last().encoded(), vec![
+			log_receipt(vec![LogEntry { address: 1.into(), topics: vec![10.into()], data: vec![1] }]),
+		]);
+		insert_block(&db, &bc, b2.last().encoded(), vec![]);
+		insert_block(&db, &bc, b3.last().encoded(), vec![
+			log_receipt(vec![LogEntry { address: 1.into(), topics: vec![10.into()], data: vec![3] }]),
+		]);
+
+		// there is no index entry at block 2, the first match is in block 3.
+		for filter in vec![log_filter(Some(vec![1.into()]), vec![], None), log_filter(None, vec![Some(vec![10.into()])], None)] {
+			let entries = bc.indexed_logs(&filter, 2, 3).unwrap();
+			assert_eq!(entries.len(), 1);
+			assert_eq!(entries[0].entry.data, vec![3]);
+			assert_eq!(entries[0].block_number, 3);
+		}
+	}
+
+	#[test]
+	fn test_indexed_logs_unselective_filter() {
+		let genesis = BlockBuilder::genesis();
+		let db = new_db();
+		let config = Config { log_index: true, ..Config::default() };
+		let bc = BlockChain::new(config, genesis.last().encoded().raw(), db.clone());
+
+		// every block has a log of the same address, each with a different topic.
+		let mut parent = genesis;
+		for number in 1..7u64 {
+			let block = parent.add_block_with_transactions(iter::once(create_transaction(100 + number)));
+			insert_block(&db, &bc, block.last().encoded(), vec![
+				log_receipt(vec![LogEntry { address: 1.into(), topics: vec![(10 + number).into()], data: vec![number as u8] }]),
+			]);
+			parent = block;
+		}
+
+		let by_address = log_filter(Some(vec![1.into()]), vec![], None);
+		assert_eq!(bc.indexed_logs(&by_address, 0, 6), None);
+		assert_eq!(bc.indexed_logs(&by_address, 3, 6).map(|logs| logs.len()), Some(4));
+
+		// the topic is selective even though the address isn't.
+		let by_topic = log_filter(Some(vec![1.into()]), vec![Some(vec![13.into()])], None);
+		let logs = bc.indexed_logs(&by_topic, 0, 6).unwrap();
+		assert_eq!(logs.len(), 1);
+		assert_eq!(logs[0].entry.data, vec![3]);
+	}
+
+	#[test]
+	fn test_indexed_logs_reorg() {
+		let t1 = create_transaction(101);
+		let t2 = create_transaction(102);
+
+		let genesis = BlockBuilder::genesis();
+		let b1a = genesis.add_block_with_transactions(iter::once(t1));
+		let b1b = genesis.add_block_with(|| BlockOptions {
+			transactions: vec![t2.clone()],
+			difficulty: 9.into(),
+			..Default::default()
+		});
+		let b2 = b1b.add_block();
+		let b1b_hash = b1b.last().hash();
+
+		let db = new_db();
+		let config = Config { log_index: true, ..Config::default() };
+		let bc = BlockChain::new(config, genesis.last().encoded().raw(), db.clone());
+		let by_address = |address: u64| log_filter(Some(vec![address.into()]), vec![], None);
+
+		insert_block(&db, &bc, b1a.last().encoded(), vec![
+			log_receipt(vec![LogEntry { address: 1.into(), topics: vec![], data: vec![] }]),
+		]);
+		insert_block(&db, &bc, b1b.last().encoded(), vec![
+			log_receipt(vec![LogEntry { address: 2.into(), topics: vec![], data: vec![] }]),
+		]);
+		assert_eq!(bc.indexed_logs(&by_address(1), 0, 1).map(|logs| logs.len()), Some(1));
+		assert_eq!(bc.indexed_logs(&by_address(2), 0, 1), Some(vec![]));
+
+		insert_block(&db, &bc, b2.last().encoded(), vec![]);
+		assert_eq!(bc.indexed_logs(&by_address(1), 0, 2), Some(vec![]));
+		let logs = bc.indexed_logs(&by_address(2), 0, 2).unwrap();
+		assert_eq!(logs.len(), 1);
+		assert_eq!(logs[0].block_hash, b1b_hash);
+		assert_eq!(logs[0].transaction_hash, t2.hash());
+	}
+
+	#[test]
+	fn test_backfill_log_index() {
+		let genesis = BlockBuilder::genesis();
+		let b1 = genesis.add_block_with_transactions(iter::once(create_transaction(101)));
+		let b2 = b1.add_block_with_transactions(iter::once(create_transaction(102)));
+		let receipts = || vec![log_receipt(vec![LogEntry { address: 1.into(), topics: vec![], data: vec![] }])];
+		let filter = log_filter(Some(vec![1.into()]), vec![], None);
+
+		let db = new_db();
+		{
+			let bc = new_chain(genesis.last().encoded(), db.clone());
+			insert_block(&db, &bc, b1.last().encoded(), receipts());
+			insert_block(&db, &bc, b2.last().encoded(), receipts());
+			assert_eq!(bc.log_index_from(), None);
+			assert_eq!(bc.indexed_logs(&filter, 0, 2), None);
+		}
+
+		let config = Config { log_index: true, ..Config::default() };
+		let bc = BlockChain::new(config.clone(), genesis.last().encoded().raw(), db.clone());
+		// only blocks imported from now on are covered.
+		assert_eq!(bc.log_index_from(), Some(3));
+		assert_eq!(bc.indexed_logs(&filter, 0, 2), None);
+
+		assert_eq!(bc.backfill_log_index(1), Some(1));
+		assert_eq!(bc.indexed_logs(&filter, 2, 2).map(|logs| logs.len()), Some(1));
+		assert_eq!(bc.indexed_logs(&filter, 0, 2), None);
+		assert_eq!(bc.backfill_log_index(1), Some(0));
+		assert_eq!(bc.indexed_logs(&filter, 0, 2).map(|logs| logs.len()), Some(2));
+
+		// the coverage is persisted.
+		let bc = BlockChain::new(config, genesis.last().encoded().raw(), db.clone());
+		assert_eq!(bc.log_index_from(), Some(1));
+
+		bc.clear_log_index();
+		assert_eq!(bc.log_index_from(), Some(3));
+		assert_eq!(bc.backfill_log_index(10), Some(0));
+		assert_eq!(bc.indexed_logs(&filter, 0, 2).map(|logs| logs.len()), Some(2));
+	}
+
 	#[test]
 	fn test_bloom_filter_simple() {
 		let bloom_b1: Bloom = "00000020000000000000000000000000000000000000000002000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000040000000000000010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000008000400000000000000000000002000".into();
@@ -21,7 +21,8 @@ use std::ops;
 
 use db::Key;
 use engines::epoch::{Transition as EpochTransition};
-use ethereum_types::{H256, H264, U256};
+use bytes::Bytes;
+use ethereum_types::{H256, H264, U256, Address};
 use header::BlockNumber;
 use heapsize::HeapSizeOf;
 use kvdb::PREFIX_LEN as DB_PREFIX_LEN;
@@ -43,6 +44,10 @@ pub enum ExtrasIndex {
 	EpochTransitions = 5,
 	/// Pending epoch transition data index.
 	PendingEpochTransition = 6,
+	/// Reverse state diff index.
+	StateHistory = 7,
+	/// Index of blocks modifying an account.
+	AccountHistory = 8,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -108,6 +113,60 @@ impl Key<::engines::epoch::PendingTransition> for H256 {
 	}
 }
 
+impl Key<StateHistory> for H256 {
+	type Target = H264;
+
+	fn key(&self) -> H264 {
+		with_index(self, ExtrasIndex::StateHistory)
+	}
+}
+
+/// Number of blocks covered by a single `AccountHistory` entry.
+pub const ACCOUNT_HISTORY_RANGE: BlockNumber = 1024;
+
+/// Position of an `AccountHistory` entry.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct AccountHistoryPosition {
+	/// Account address
+	pub address: Address,
+	/// Index of the range of blocks covered by the entry
+	pub range: u64,
+}
+
+impl AccountHistoryPosition {
+	/// Position of the entry covering given block.
+	pub fn new(address: Address, block: BlockNumber) -> Self {
+		AccountHistoryPosition {
+			address,
+			range: block / ACCOUNT_HISTORY_RANGE,
+		}
+	}
+}
+
+pub struct AccountHistoryKey([u8; 29]);
+
+impl ops::Deref for AccountHistoryKey {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl Key<AccountHistory> for AccountHistoryPosition {
+	type Target = AccountHistoryKey;
+
+	fn key(&self) -> Self::Target {
+		let mut result = [0u8; 29];
+		result[0] = ExtrasIndex::AccountHistory as u8;
+		result[1..21].copy_from_slice(&self.address);
+		for i in 0..8 {
+			result[21 + i] = (self.range >> ((7 - i) * 8)) as u8;
+		}
+		AccountHistoryKey(result)
+	}
+}
+
 /// length of epoch keys.
 pub const EPOCH_KEY_LEN: usize = DB_PREFIX_LEN + 16;
 
@@ -241,11 +300,82 @@ pub struct EpochTransitions {
 	pub candidates: Vec<EpochTransition>,
 }
 
+/// Storage value from before a block was applied.
+#[derive(Debug, PartialEq, Clone, RlpEncodable, RlpDecodable)]
+pub struct PriorStorage {
+	/// Storage key
+	pub key: H256,
+	/// Value before the block
+	pub value: H256,
+}
+
+/// Account values from before a block was applied.
+#[derive(Debug, PartialEq, Clone, RlpEncodable, RlpDecodable)]
+pub struct PriorAccount {
+	/// Account address
+	pub address: Address,
+	/// Balance before the block
+	pub balance: U256,
+	/// Nonce before the block
+	pub nonce: U256,
+	/// Whether the block changed the code of the account
+	pub code_changed: bool,
+	/// Code before the block. Empty if `code_changed` is false.
+	pub code: Bytes,
+	/// Whether the account was killed within the block, dropping storage not listed in `storage`.
+	pub storage_reset: bool,
+	/// Values of the storage slots the block changed
+	pub storage: Vec<PriorStorage>,
+}
+
+impl PriorAccount {
+	/// Value of the storage slot before the block. Outer `None` if the block didn't touch the slot,
+	/// inner `None` if the value is unknown.
+	pub fn storage_at(&self, key: &H256) -> Option<Option<H256>> {
+		match self.storage.iter().find(|s| &s.key == key) {
+			Some(s) => Some(Some(s.value)),
+			None if self.storage_reset => Some(None),
+			None => None,
+		}
+	}
+}
+
+/// Reverse state diff of a block: values of every account it modified, from before it was applied.
+#[derive(Debug, PartialEq, Clone, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct StateHistory {
+	/// Modified accounts, ordered by address.
+	pub accounts: Vec<PriorAccount>,
+}
+
+impl StateHistory {
+	/// Prior values of given account, if it was modified by the block.
+	pub fn account(&self, address: &Address) -> Option<&PriorAccount> {
+		self.accounts.binary_search_by(|a| a.address.cmp(address)).ok().map(|i| &self.accounts[i])
+	}
+}
+
+/// Numbers of the blocks within a range of `ACCOUNT_HISTORY_RANGE` which have a reverse state diff
+/// modifying an account, in ascending order. Blocks which are not canonical are included too.
+#[derive(Debug, Default, PartialEq, Clone, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct AccountHistory {
+	/// Block numbers
+	pub blocks: Vec<BlockNumber>,
+}
+
+impl AccountHistory {
+	/// Note that the block with given number modified the account.
+	pub fn insert(&mut self, block: BlockNumber) {
+		if let Err(i) = self.blocks.binary_search(&block) {
+			self.blocks.insert(i, block);
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use rlp::*;
 
-	use super::BlockReceipts;
+	use super::{BlockReceipts, StateHistory, PriorAccount, PriorStorage};
 
 	#[test]
 	fn encode_block_receipts() {
@@ -258,4 +388,25 @@ mod tests {
 		assert!(s.is_finished(), "List should be finished now");
 		s.out();
 	}
+
+	#[test]
+	fn state_history_roundtrip() {
+		let history = StateHistory {
+			accounts: vec![PriorAccount {
+				address: 1.into(),
+				balance: 10.into(),
+				nonce: 1.into(),
+				code_changed: false,
+				code: vec![],
+				storage_reset: false,
+				storage: vec![PriorStorage { key: 2.into(), value: 3.into() }],
+			}],
+		};
+
+		let decoded: StateHistory = decode(&encode(&history)).unwrap();
+		assert_eq!(decoded, history);
+		assert_eq!(decoded.account(&1.into()).unwrap().storage_at(&2.into()), Some(Some(3.into())));
+		assert_eq!(decoded.account(&1.into()).unwrap().storage_at(&4.into()), None);
+		assert!(decoded.account(&2.into()).is_none());
+	}
 }
@@ -0,0 +1,171 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Optional index of canonical logs by emitting address and topic.
+//!
+//! Every log of a canonical block is referenced once for its address and
+//! once for each of its (up to four) topics. Entries are keyed by the indexed
+//! value followed by the big-endian block number, so that all blocks touching
+//! a value can be found with a single prefix iteration.
+
+use std::collections::HashMap;
+
+use db::{Key, BlockIndexKey, BlockIndexEntry, ItemPosition};
+use ethereum_types::{H256, Address};
+use header::BlockNumber;
+use receipt::Receipt;
+
+/// Key of the lowest block number from which the canonical chain is indexed.
+pub const LOG_INDEX_FROM_KEY: &'static [u8] = b"from";
+
+/// Number of topics of a log which are indexed.
+pub const INDEXED_TOPICS: usize = 4;
+
+/// Filters whose index entries cover more than one in `LOG_INDEX_SELECTIVITY` blocks of the
+/// queried range aren't selective, they are answered faster by bloom filters.
+pub const LOG_INDEX_SELECTIVITY: u64 = 8;
+
+/// Number of index entries which are read before a filter is considered not selective,
+/// regardless of the length of the queried range.
+#[cfg(not(test))]
+pub const LOG_INDEX_MIN_ENTRIES: u64 = 1024;
+#[cfg(test)]
+pub const LOG_INDEX_MIN_ENTRIES: u64 = 4;
+
+/// Length of a key prefix shared by all entries of a single indexed value.
+pub const LOG_INDEX_PREFIX_LEN: usize = 33;
+
+/// A value by which logs are indexed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LogField {
+	/// Address of the contract which emitted the log.
+	Address(Address),
+	/// Topic at the given position.
+	Topic(usize, H256),
+}
+
+impl LogField {
+	/// Returns the key prefix shared by entries of this value.
+	pub fn prefix(&self) -> [u8; LOG_INDEX_PREFIX_LEN] {
+		let (kind, value) = match *self {
+			LogField::Address(ref address) => (0, H256::from(address)),
+			LogField::Topic(position, ref topic) => (position as u8 + 1, *topic),
+		};
+
+		let mut result = [0u8; LOG_INDEX_PREFIX_LEN];
+		result[0] = kind;
+		result[1..].copy_from_slice(&value);
+		result
+	}
+}
+
+impl Key<BlockIndexEntry> for (LogField, BlockNumber) {
+	type Target = BlockIndexKey;
+
+	fn key(&self) -> BlockIndexKey {
+		BlockIndexKey::new(&self.0.prefix(), self.1)
+	}
+}
+
+/// Log index changes caused by a block insertion.
+#[derive(Debug, Default)]
+pub struct LogIndexUpdate {
+	/// Entries of blocks which are no longer canonical.
+	pub deleted: Vec<(LogField, BlockNumber)>,
+	/// Entries of blocks which became canonical.
+	pub inserted: Vec<((LogField, BlockNumber), BlockIndexEntry)>,
+}
+
+/// Groups log positions of a block by the values they are indexed by.
+pub fn log_fields(receipts: &[Receipt]) -> HashMap<LogField, Vec<ItemPosition>> {
+	let mut result: HashMap<LogField, Vec<ItemPosition>> = HashMap::new();
+	for (tx_number, receipt) in receipts.iter().enumerate() {
+		for (log_number, log) in receipt.logs.iter().enumerate() {
+			let position = ItemPosition { transaction: tx_number as u64, index: log_number as u64 };
+			let topics = log.topics.iter()
+				.take(INDEXED_TOPICS)
+				.enumerate()
+				.map(|(i, topic)| LogField::Topic(i, *topic));
+			for field in Some(LogField::Address(log.address)).into_iter().chain(topics) {
+				result.entry(field).or_insert_with(Vec::new).push(position);
+			}
+		}
+	}
+	result
+}
+
+/// Returns log index entries of a canonical block.
+pub fn block_entries(number: BlockNumber, block_hash: H256, receipts: &[Receipt]) -> Vec<((LogField, BlockNumber), BlockIndexEntry)> {
+	log_fields(receipts).into_iter()
+		.map(|(field, positions)| ((field, number), BlockIndexEntry { block_hash, positions }))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use db::{Key, ItemPosition, block_index_number};
+	use log_entry::LogEntry;
+	use receipt::{Receipt, TransactionOutcome};
+	use super::{LogField, log_fields};
+
+	#[test]
+	fn keys_of_a_field_share_prefix_and_sort_by_block_number() {
+		let field = LogField::Topic(2, 5.into());
+		let key1 = Key::key(&(field, 1u64));
+		let key2 = Key::key(&(field, 256u64));
+
+		assert!(key1.starts_with(&field.prefix()));
+		assert!(key2.starts_with(&field.prefix()));
+		assert!(*key1 < *key2);
+		assert_eq!(block_index_number(&key2), 256);
+		assert!(!key1.starts_with(&LogField::Topic(1, 5.into()).prefix()));
+	}
+
+	#[test]
+	fn groups_logs_by_address_and_topics() {
+		let log = |address: u64, topics: Vec<u64>| LogEntry {
+			address: address.into(),
+			topics: topics.into_iter().map(Into::into).collect(),
+			data: vec![],
+		};
+		let receipt = |logs| Receipt {
+			outcome: TransactionOutcome::Unknown,
+			gas_used: 0.into(),
+			log_bloom: Default::default(),
+			logs: logs,
+		};
+
+		let fields = log_fields(&[
+			receipt(vec![log(1, vec![10, 11]), log(2, vec![10])]),
+			receipt(vec![]),
+			receipt(vec![log(1, vec![1, 2, 3, 4, 5])]),
+		]);
+
+		assert_eq!(fields[&LogField::Address(1.into())], vec![
+			ItemPosition { transaction: 0, index: 0 },
+			ItemPosition { transaction: 2, index: 0 },
+		]);
+		assert_eq!(fields[&LogField::Topic(0, 10.into())], vec![
+			ItemPosition { transaction: 0, index: 0 },
+			ItemPosition { transaction: 0, index: 1 },
+		]);
+		assert_eq!(fields[&LogField::Topic(1, 11.into())], vec![ItemPosition { transaction: 0, index: 0 }]);
+		assert_eq!(fields[&LogField::Topic(3, 4.into())], vec![ItemPosition { transaction: 2, index: 0 }]);
+		// only the first four topics are indexed.
+		assert_eq!(fields.len(), 2 + 2 + 1 + 4);
+	}
+}
@@ -23,6 +23,7 @@ mod cache;
 mod config;
 mod extras;
 mod import_route;
+mod log_index;
 mod update;
 
 #[cfg(test)]
@@ -31,7 +32,10 @@ pub mod generator;
 pub use self::blockchain::{BlockProvider, BlockChain, BlockChainDB, BlockChainDBHandler};
 pub use self::cache::CacheSize;
 pub use self::config::Config;
-pub use self::extras::{BlockReceipts, BlockDetails, TransactionAddress};
+pub use self::extras::{
+	BlockReceipts, BlockDetails, TransactionAddress, StateHistory, PriorAccount, PriorStorage,
+	AccountHistory, AccountHistoryPosition, ACCOUNT_HISTORY_RANGE,
+};
 pub use self::import_route::ImportRoute;
 pub use self::update::ExtrasInsert;
 pub use types::tree_route::TreeRoute;
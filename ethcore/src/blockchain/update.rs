@@ -19,6 +19,7 @@ use ethereum_types::{H256, Bloom};
 use header::BlockNumber;
 use blockchain::block_info::BlockInfo;
 use blockchain::extras::{BlockDetails, BlockReceipts, TransactionAddress};
+use blockchain::log_index::LogIndexUpdate;
 use encoded::Block;
 
 /// Block extras update info.
@@ -37,6 +38,8 @@ pub struct ExtrasUpdate {
 	pub blocks_blooms: Option<(u64, Vec<Bloom>)>,
 	/// Modified transaction addresses (None signifies removed transactions).
 	pub transactions_addresses: HashMap<H256, Option<TransactionAddress>>,
+	/// Modified log index entries (None if the index is disabled or unchanged).
+	pub log_index: Option<LogIndexUpdate>,
 }
 
 /// Extra information in block insertion.
@@ -19,7 +19,7 @@
 use std::cmp::{max, min};
 use std::io::{self, Read};
 
-use byteorder::{ByteOrder, BigEndian};
+use byteorder::{ByteOrder, BigEndian, LittleEndian};
 use parity_crypto::digest;
 use num::{BigUint, Zero, One};
 
@@ -87,6 +87,21 @@ impl Pricer for AltBn128PairingPricer {
 	}
 }
 
+/// A BLAKE2b compression pricing model. This computes a price using a cost per round.
+struct Blake2FPricer {
+	gas_per_round: u64,
+}
+
+impl Pricer for Blake2FPricer {
+	fn cost(&self, input: &[u8]) -> U256 {
+		if input.len() < 4 {
+			return U256::zero();
+		}
+		let rounds = BigEndian::read_u32(&input[..4]);
+		U256::from(self.gas_per_round) * U256::from(rounds)
+	}
+}
+
 impl Pricer for ModexpPricer {
 	fn cost(&self, input: &[u8]) -> U256 {
 		let mut reader = input.chain(io::repeat(0));
@@ -200,6 +215,11 @@ impl From<ethjson::spec::Builtin> for Builtin {
 					pair: pricer.pair,
 				})
 			}
+			ethjson::spec::Pricing::Blake2F(pricer) => {
+				Box::new(Blake2FPricer {
+					gas_per_round: pricer.gas_per_round,
+				})
+			}
 		};
 
 		Builtin {
@@ -221,6 +241,7 @@ pub fn ethereum_builtin(name: &str) -> Box<Impl> {
 		"alt_bn128_add" => Box::new(Bn128AddImpl) as Box<Impl>,
 		"alt_bn128_mul" => Box::new(Bn128MulImpl) as Box<Impl>,
 		"alt_bn128_pairing" => Box::new(Bn128PairingImpl) as Box<Impl>,
+		"blake2_f" => Box::new(Blake2F) as Box<Impl>,
 		_ => panic!("invalid builtin name: {}", name),
 	}
 }
@@ -232,6 +253,7 @@ pub fn ethereum_builtin(name: &str) -> Box<Impl> {
 // - sha256
 // - ripemd160
 // - modexp (EIP198)
+// - blake2_f (EIP152)
 
 #[derive(Debug)]
 struct Identity;
@@ -257,6 +279,9 @@ struct Bn128MulImpl;
 #[derive(Debug)]
 struct Bn128PairingImpl;
 
+#[derive(Debug)]
+struct Blake2F;
+
 impl Impl for Identity {
 	fn execute(&self, input: &[u8], output: &mut BytesRef) -> Result<(), Error> {
 		output.write(0, input);
@@ -556,9 +581,114 @@ impl Bn128PairingImpl {
 	}
 }
 
+const BLAKE2B_IV: [u64; 8] = [
+	0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+	0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+	[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+	[14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+	[11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+	[7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+	[9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+	[2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+	[12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+	[13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+	[6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+	[10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+// BLAKE2b mixing function G (RFC 7693, section 3.1)
+fn blake2_g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+	v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+	v[d] = (v[d] ^ v[a]).rotate_right(32);
+	v[c] = v[c].wrapping_add(v[d]);
+	v[b] = (v[b] ^ v[c]).rotate_right(24);
+	v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+	v[d] = (v[d] ^ v[a]).rotate_right(16);
+	v[c] = v[c].wrapping_add(v[d]);
+	v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+// BLAKE2b compression function F (RFC 7693, section 3.2) with a configurable number of rounds.
+fn blake2_f(h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], f: bool, rounds: u32) {
+	let mut v = [0u64; 16];
+	v[..8].copy_from_slice(h);
+	v[8..].copy_from_slice(&BLAKE2B_IV);
+
+	v[12] ^= t[0];
+	v[13] ^= t[1];
+	if f {
+		v[14] = !v[14];
+	}
+
+	for i in 0..rounds as usize {
+		let s = &BLAKE2B_SIGMA[i % 10];
+		blake2_g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+		blake2_g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+		blake2_g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+		blake2_g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+		blake2_g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+		blake2_g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+		blake2_g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+		blake2_g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+	}
+
+	for i in 0..8 {
+		h[i] ^= v[i] ^ v[i + 8];
+	}
+}
+
+impl Impl for Blake2F {
+	/// Can fail if:
+	///     - input length is not exactly 213 bytes
+	///     - the final block indicator flag is neither 0 nor 1
+	fn execute(&self, input: &[u8], output: &mut BytesRef) -> Result<(), Error> {
+		const BLAKE2_F_ARG_LEN: usize = 213;
+
+		if input.len() != BLAKE2_F_ARG_LEN {
+			trace!(target: "builtin", "input length for Blake2 F precompile should be exactly 213 bytes, was {}", input.len());
+			return Err("input length for Blake2 F precompile should be exactly 213 bytes".into())
+		}
+
+		// rounds: 4 bytes, big-endian
+		let rounds = BigEndian::read_u32(&input[..4]);
+
+		// state vector h: 8 words, little-endian
+		let mut h = [0u64; 8];
+		LittleEndian::read_u64_into(&input[4..68], &mut h);
+
+		// message block m: 16 words, little-endian
+		let mut m = [0u64; 16];
+		LittleEndian::read_u64_into(&input[68..196], &mut m);
+
+		// offset counters t: 2 words, little-endian
+		let t = [LittleEndian::read_u64(&input[196..204]), LittleEndian::read_u64(&input[204..212])];
+
+		// final block indicator flag
+		let f = match input[212] {
+			1 => true,
+			0 => false,
+			_ => {
+				trace!(target: "builtin", "incorrect final block indicator flag, was: {}", input[212]);
+				return Err("incorrect final block indicator flag".into())
+			}
+		};
+
+		blake2_f(&mut h, &m, t, f, rounds);
+
+		let mut output_buf = [0u8; 64];
+		LittleEndian::write_u64_into(&h, &mut output_buf);
+		output.write(0, &output_buf);
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{Builtin, Linear, ethereum_builtin, Pricer, ModexpPricer, modexp as me};
+	use super::{Builtin, Linear, ethereum_builtin, Pricer, ModexpPricer, Blake2FPricer, modexp as me};
 	use ethjson;
 	use ethereum_types::U256;
 	use bytes::BytesRef;
@@ -1059,4 +1189,107 @@ mod tests {
 		b.execute(&i[..], &mut BytesRef::Fixed(&mut o[..])).expect("Builtin should not fail");
 		assert_eq!(i, o);
 	}
+
+	fn builtin_blake2_f() -> Builtin {
+		Builtin {
+			pricer: Box::new(Blake2FPricer { gas_per_round: 1 }),
+			native: ethereum_builtin("blake2_f"),
+			activate_at: 0,
+		}
+	}
+
+	#[test]
+	fn blake2_f_invalid_input() {
+		// EIP-152 test vectors 0-3
+		let f = builtin_blake2_f();
+
+		// empty input
+		error_test(builtin_blake2_f(), &[], Some("input length for Blake2 F precompile should be exactly 213 bytes"));
+
+		// input too short
+		error_test(
+			builtin_blake2_f(),
+			&bytes("00000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001"),
+			Some("input length for Blake2 F precompile should be exactly 213 bytes"),
+		);
+
+		// input too long
+		error_test(
+			builtin_blake2_f(),
+			&bytes("000000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001"),
+			Some("input length for Blake2 F precompile should be exactly 213 bytes"),
+		);
+
+		// final block indicator flag is neither 0 nor 1
+		let input = bytes("0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000002");
+		let mut output = [0u8; 64];
+		let res = f.execute(&input[..], &mut BytesRef::Fixed(&mut output[..]));
+		assert!(res.unwrap_err().0.contains("incorrect final block indicator flag"));
+	}
+
+	#[test]
+	fn blake2_f() {
+		// EIP-152 test vectors 4-7
+		let f = builtin_blake2_f();
+		let vectors = [
+			(
+				"0000000048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+				"08c9bcf367e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d282e6ad7f520e511f6c3e2b8c68059b9442be0454267ce079217e1319cde05b",
+				0,
+			),
+			(
+				"0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+				"ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+				12,
+			),
+			(
+				"0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000000",
+				"75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d2875298743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735",
+				12,
+			),
+			(
+				"0000000148c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+				"b63a380cb2897d521994a85234ee2c181b5f844d2c624c002677e9703449d2fba551b3a8333bcdf5f2f7e08993d53923de3d64fcc68c034e717b9293fed7a421",
+				1,
+			),
+		];
+
+		for &(input, expected, rounds) in vectors.iter() {
+			let input = bytes(input);
+			assert_eq!(f.cost(&input[..]), U256::from(rounds));
+
+			let mut output = [0u8; 64];
+			f.execute(&input[..], &mut BytesRef::Fixed(&mut output[..])).expect("Builtin should not fail");
+			assert_eq!(&output[..], &bytes(expected)[..]);
+		}
+	}
+
+	#[test]
+	#[ignore]
+	fn blake2_f_max_rounds() {
+		// EIP-152 test vector 8, 2^32 - 1 rounds take a while.
+		let f = builtin_blake2_f();
+		let input = bytes("ffffffff48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001");
+		assert_eq!(f.cost(&input[..]), U256::from(0xffff_ffffu64));
+
+		let mut output = [0u8; 64];
+		f.execute(&input[..], &mut BytesRef::Fixed(&mut output[..])).expect("Builtin should not fail");
+		assert_eq!(&output[..], &bytes("fc59093aafa9ab43daae0e914c57635c5402d8e3d2130eb9b3cc181de7f0ecf9b22bf99a7815ce16419e200e01846e6b5df8cc7703041bbceb571de6631d2615")[..]);
+	}
+
+	#[test]
+	fn blake2_f_from_json() {
+		let b = Builtin::from(ethjson::spec::Builtin {
+			name: "blake2_f".to_owned(),
+			pricing: ethjson::spec::Pricing::Blake2F(ethjson::spec::builtin::Blake2F {
+				gas_per_round: 123,
+			}),
+			activate_at: Some(ethjson::uint::Uint(1_000.into())),
+		});
+
+		assert!(!b.is_active(999));
+		assert!(b.is_active(1_000));
+		assert_eq!(b.cost(&bytes("0000000c")[..]), U256::from(123 * 12));
+		assert_eq!(b.cost(&[]), U256::zero());
+	}
 }
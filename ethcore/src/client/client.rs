@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashSet, BTreeMap, VecDeque};
+use std::collections::{HashSet, BTreeMap, BTreeSet, VecDeque};
 use std::cmp;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering as AtomicOrdering};
@@ -28,22 +28,29 @@ use itertools::Itertools;
 use journaldb;
 use trie::{TrieSpec, TrieFactory, Trie};
 use kvdb::{DBValue, KeyValueDB, DBTransaction};
+use rlp::Rlp;
+use triehash::ordered_trie_root;
+use unexpected::Mismatch;
+use db::{Readable, Writable};
 
 // other
 use ethereum_types::{H256, Address, U256};
 use block::{IsBlock, LockedBlock, Drain, ClosedBlock, OpenBlock, enact_verified, SealedBlock};
-use blockchain::{BlockChain, BlockChainDB, BlockProvider, TreeRoute, ImportRoute, TransactionAddress, ExtrasInsert};
+use blockchain::{
+	BlockChain, BlockChainDB, BlockProvider, TreeRoute, ImportRoute, TransactionAddress, ExtrasInsert,
+	StateHistory, PriorAccount, PriorStorage, AccountHistory, AccountHistoryPosition, ACCOUNT_HISTORY_RANGE,
+};
 use client::ancient_import::AncientVerifier;
 use client::{
 	Nonce, Balance, ChainInfo, BlockInfo, CallContract, TransactionInfo,
 	RegistryInfo, ReopenBlock, PrepareOpenBlock, ScheduleInfo, ImportSealedBlock,
-	BroadcastProposalBlock, ImportBlock, StateOrBlock, StateInfo, StateClient, Call,
+	BroadcastProposalBlock, ImportBlock, StateOrBlock, StateInfo, StateClient, Call, SimulatedTransaction,
 	AccountData, BlockChain as BlockChainTrait, BlockProducer, SealedBlockImporter,
 	ClientIoMessage,
 };
 use client::{
 	BlockId, TransactionId, UncleId, TraceId, ClientConfig, BlockChainClient,
-	TraceFilter, CallAnalytics, Mode,
+	TraceFilter, CallAnalytics, StateOverride, AccountOverride, Mode,
 	ChainNotify, ChainRoute, PruningInfo, ProvingBlockChainClient, EngineInfo, ChainMessageType,
 	IoClient, BadBlocks,
 };
@@ -62,10 +69,11 @@ use header::{BlockNumber, Header, ExtendedHeader};
 use io::IoChannel;
 use log_entry::LocalizedLogEntry;
 use miner::{Miner, MinerService};
+use pod_account::PodAccount;
 use ethcore_miner::pool::VerifiedTransaction;
 use parking_lot::{Mutex, RwLock};
 use rand::OsRng;
-use receipt::{Receipt, LocalizedReceipt};
+use receipt::{Receipt, LocalizedReceipt, RichReceipt, TransactionOutcome};
 use snapshot::{self, io as snapshot_io};
 use spec::Spec;
 use state_db::StateDB;
@@ -93,6 +101,8 @@ const MAX_ANCIENT_BLOCKS_QUEUE_SIZE: usize = 4096;
 const MAX_ANCIENT_BLOCKS_TO_IMPORT: usize = 4;
 const MAX_QUEUE_SIZE_TO_SLEEP_ON: usize = 2;
 const MIN_HISTORY_SIZE: u64 = 8;
+// Number of blocks added to the trace address index on each tick.
+const TRACE_ADDRESS_INDEX_BACKFILL_BLOCKS: u64 = 2048;
 
 /// Report on the status of a client.
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
@@ -406,6 +416,7 @@ impl Importer {
 			block,
 			engine,
 			client.tracedb.read().tracing_enabled(),
+			client.config.state_history,
 			db,
 			&parent,
 			last_hashes,
@@ -517,6 +528,33 @@ impl Importer {
 			self.engine.fork_choice(&new, &best)
 		};
 
+		if client.config.state_history {
+			// `state_at` would re-acquire the chain lock, so open the parent state directly.
+			let history = chain.block_header_data(parent)
+				.and_then(|parent_header| State::from_existing(
+					client.state_db.read().boxed_clone(),
+					parent_header.state_root(),
+					self.engine.account_start_nonce(number - 1),
+					client.factories.clone(),
+				).ok())
+				.ok_or_else(|| "Parent state is not available".to_owned())
+				.and_then(|parent_state| Self::state_history(&block.state, &parent_state));
+
+			match history {
+				Ok(history) => {
+					let db = client.db.read();
+					for account in &history.accounts {
+						let position = AccountHistoryPosition::new(account.address, number);
+						let mut index: AccountHistory = db.key_value().read(::db::COL_EXTRA, &position).unwrap_or_default();
+						index.insert(number);
+						batch.write(::db::COL_EXTRA, &position, &index);
+					}
+					batch.write(::db::COL_EXTRA, hash, &history);
+				},
+				Err(e) => warn!(target: "client", "Failed to record state history of #{} ({}): {}", number, hash, e),
+			}
+		}
+
 		// CHECK! I *think* this is fine, even if the state_root is equal to another
 		// already-imported block of the same number.
 		// TODO: Prove it with a test.
@@ -571,6 +609,47 @@ impl Importer {
 		route
 	}
 
+	// build the reverse state diff of a block from its post-state and the state of its parent.
+	fn state_history(state: &State<StateDB>, parent: &State<StateDB>) -> Result<StateHistory, String> {
+		let changes = state.committed_changes().ok_or_else(|| "State changes were not tracked".to_owned())?;
+		Self::collect_state_history(state, parent, changes).map_err(|e| e.to_string())
+	}
+
+	fn collect_state_history(
+		state: &State<StateDB>,
+		parent: &State<StateDB>,
+		changes: &BTreeMap<Address, BTreeSet<H256>>,
+	) -> ::ethtrie::Result<StateHistory> {
+		let mut accounts = Vec::with_capacity(changes.len());
+		for (address, keys) in changes {
+			let code_changed = parent.code_hash(address)? != state.code_hash(address)?;
+			let code = match code_changed {
+				true => parent.code(address)?.map_or_else(Vec::new, |c| (*c).clone()),
+				false => Vec::new(),
+			};
+
+			let mut storage = Vec::with_capacity(keys.len());
+			for key in keys {
+				storage.push(PriorStorage {
+					key: *key,
+					value: parent.storage_at(address, key)?,
+				});
+			}
+
+			accounts.push(PriorAccount {
+				address: *address,
+				balance: parent.balance(address)?,
+				nonce: parent.nonce(address)?,
+				code_changed,
+				code,
+				storage_reset: state.was_killed(address),
+				storage,
+			});
+		}
+
+		Ok(StateHistory { accounts })
+	}
+
 	// check for epoch end signal and write pending transition if it occurs.
 	// state for the given block must be available.
 	fn check_epoch_end_signal(
@@ -714,6 +793,10 @@ impl Client {
 			accountdb: Default::default(),
 		};
 
+		if ::pruning::is_converting(&**db.key_value())? {
+			return Err("Conversion of the database to another pruning algorithm was interrupted. Resume it before using the database.".into());
+		}
+
 		let journal_db = journaldb::new(db.key_value().clone(), config.pruning, ::db::COL_STATE);
 		let mut state_db = StateDB::new(journal_db, config.state_cache_size);
 		if state_db.journal_db().is_empty() {
@@ -1073,10 +1156,24 @@ impl Client {
 		report
 	}
 
+	/// Indexes logs of up to `max_blocks` canonical blocks below the log index coverage.
+	///
+	/// Returns the number of blocks which remain to be indexed, or `None` if the log index
+	/// is disabled or the blocks below its coverage are not available.
+	pub fn backfill_log_index(&self, max_blocks: u64) -> Option<BlockNumber> {
+		self.chain.read().backfill_log_index(max_blocks)
+	}
+
+	/// Removes all log index entries, so that the index can be rebuilt with `backfill_log_index`.
+	pub fn clear_log_index(&self) {
+		self.chain.read().clear_log_index();
+	}
+
 	/// Tick the client.
 	// TODO: manage by real events.
 	pub fn tick(&self, prevent_sleep: bool) {
 		self.check_garbage();
+		self.check_trace_address_index();
 		if !prevent_sleep {
 			self.check_snooze();
 		}
@@ -1088,6 +1185,15 @@ impl Client {
 		self.tracedb.read().collect_garbage();
 	}
 
+	fn check_trace_address_index(&self) {
+		if !self.tracedb.read().address_index_enabled() {
+			return;
+		}
+
+		let best_block = self.chain.read().best_block_number();
+		self.tracedb.read().backfill_address_index(best_block, TRACE_ADDRESS_INDEX_BACKFILL_BLOCKS);
+	}
+
 	fn check_snooze(&self) {
 		let mode = self.mode.lock().clone();
 		match mode {
@@ -1124,7 +1230,13 @@ impl Client {
 
 	/// Take a snapshot at the given block.
 	/// If the ID given is "latest", this will default to 1000 blocks behind.
-	pub fn take_snapshot<W: snapshot_io::SnapshotWriter + Send>(&self, writer: W, at: BlockId, p: &snapshot::Progress) -> Result<(), EthcoreError> {
+	pub fn take_snapshot<W: snapshot_io::SnapshotWriter + Send>(
+		&self,
+		writer: W,
+		at: BlockId,
+		p: &snapshot::Progress,
+		previous: Option<&(snapshot_io::SnapshotReader + Sync)>,
+	) -> Result<(), EthcoreError> {
 		let db = self.state_db.read().journal_db().boxed_clone();
 		let best_block_number = self.chain_info().best_block_number;
 		let block_number = self.block_number(at).ok_or(snapshot::Error::InvalidStartingBlock(at))?;
@@ -1154,7 +1266,7 @@ impl Client {
 		};
 
 		let processing_threads = self.config.snapshot.processing_threads;
-		snapshot::take_snapshot(&*self.engine, &self.chain.read(), start_hash, db.as_hashdb(), writer, p, processing_threads)?;
+		snapshot::take_snapshot(&*self.engine, &self.chain.read(), start_hash, db.as_hashdb(), writer, p, processing_threads, previous)?;
 
 		Ok(())
 	}
@@ -1220,6 +1332,31 @@ impl Client {
 		}.fake_sign(from)
 	}
 
+	fn do_virtual_call_with_options<T, V>(
+		machine: &::machine::EthereumMachine,
+		env_info: &EnvInfo,
+		state: &mut State<StateDB>,
+		transaction: &SignedTransaction,
+		state_diff: bool,
+		options: TransactOptions<T, V>,
+	) -> Result<Executed<T::Output, V::Output>, CallError> where
+		T: trace::Tracer,
+		V: trace::VMTracer,
+	{
+		let options = options
+			.dont_check_nonce()
+			.save_output_from_contract();
+		let original_state = if state_diff { Some(state.clone()) } else { None };
+		let schedule = machine.schedule(env_info.number);
+
+		let mut ret = Executive::new(state, env_info, &machine, &schedule).transact_virtual(transaction, options)?;
+
+		if let Some(original) = original_state {
+			ret.state_diff = Some(state.diff_from(original).map_err(ExecutionError::from)?);
+		}
+		Ok(ret)
+	}
+
 	fn do_virtual_call(
 		machine: &::machine::EthereumMachine,
 		env_info: &EnvInfo,
@@ -1227,39 +1364,135 @@ impl Client {
 		t: &SignedTransaction,
 		analytics: CallAnalytics,
 	) -> Result<Executed, CallError> {
-		fn call<V, T>(
-			state: &mut State<StateDB>,
-			env_info: &EnvInfo,
-			machine: &::machine::EthereumMachine,
-			state_diff: bool,
-			transaction: &SignedTransaction,
-			options: TransactOptions<T, V>,
-		) -> Result<Executed<T::Output, V::Output>, CallError> where
-			T: trace::Tracer,
-			V: trace::VMTracer,
-		{
-			let options = options
-				.dont_check_nonce()
-				.save_output_from_contract();
-			let original_state = if state_diff { Some(state.clone()) } else { None };
-			let schedule = machine.schedule(env_info.number);
+		let state_diff = analytics.state_diffing;
+
+		match (analytics.transaction_tracing, analytics.vm_tracing) {
+			(true, true) => Self::do_virtual_call_with_options(machine, env_info, state, t, state_diff, TransactOptions::with_tracing_and_vm_tracing()),
+			(true, false) => Self::do_virtual_call_with_options(machine, env_info, state, t, state_diff, TransactOptions::with_tracing()),
+			(false, true) => Self::do_virtual_call_with_options(machine, env_info, state, t, state_diff, TransactOptions::with_vm_tracing()),
+			(false, false) => Self::do_virtual_call_with_options(machine, env_info, state, t, state_diff, TransactOptions::with_no_tracing()),
+		}
+	}
+
+	/// Executes the transaction, returning the gas it used together with the state right before
+	/// its execution of every account it reads or modifies.
+	fn do_virtual_call_prestate(
+		machine: &::machine::EthereumMachine,
+		env_info: &EnvInfo,
+		state: &mut State<StateDB>,
+		t: &SignedTransaction,
+	) -> Result<(U256, BTreeMap<Address, PodAccount>), CallError> {
+		const STATE_PROOF: &'static str = "Prestate is read from the same database the transaction was replayed against; qed";
+
+		// accounts served by the shared account cache never reach the local one, so accesses are
+		// tracked by the state itself rather than read back from its cache.
+		state.commit().map_err(|_| CallError::StateCorrupt)?;
+		let original = state.clone();
+		state.track_accesses();
+
+		let executed = Self::do_virtual_call_with_options(machine, env_info, state, t, false, TransactOptions::with_no_tracing())?;
+		let prestate = state.accessed().into_iter()
+			.map(|(address, keys)| {
+				let account = PodAccount {
+					balance: original.balance(&address).expect(STATE_PROOF),
+					nonce: original.nonce(&address).expect(STATE_PROOF),
+					code: original.code(&address).expect(STATE_PROOF).map(|code| (*code).clone()),
+					storage: keys.into_iter()
+						.map(|key| (key, original.storage_at(&address, &key).expect(STATE_PROOF)))
+						.collect(),
+				};
+				(address, account)
+			})
+			.collect();
+
+		Ok((executed.gas_used, prestate))
+	}
+
+	/// Replays the transactions preceding the given one in its block, without tracing.
+	/// Returns the state and environment to execute the transaction in, and the transaction itself.
+	fn replay_preceding(&self, address: &TransactionAddress) -> Result<(State<StateDB>, EnvInfo, SignedTransaction), CallError> {
+		let block = BlockId::Hash(address.block_hash);
+		let mut env_info = self.env_info(block).ok_or(CallError::StatePruned)?;
+		let body = self.block_body(block).ok_or(CallError::StatePruned)?;
+		let mut state = self.state_at_beginning(block).ok_or(CallError::StatePruned)?;
+		let mut txs = body.transactions();
+		let machine = self.engine.machine();
+
+		const PROOF: &'static str = "Transactions fetched from blockchain; blockchain transactions are valid; qed";
+
+		if address.index >= txs.len() {
+			return Err(CallError::TransactionNotFound);
+		}
+		let transaction = SignedTransaction::new(txs.swap_remove(address.index)).expect(PROOF);
+		txs.truncate(address.index);
+
+		for t in txs {
+			let t = SignedTransaction::new(t).expect(PROOF);
+			let x = Self::do_virtual_call_with_options(machine, &env_info, &mut state, &t, false, TransactOptions::with_no_tracing())?;
+			env_info.gas_used = env_info.gas_used + x.gas_used;
+		}
+
+		Ok((state, env_info, transaction))
+	}
+
+	/// Look up a value of an account at a block whose state has been pruned.
+	///
+	/// Walks reverse state diffs of subsequent canonical blocks which modified the account, as listed
+	/// by the account history index, falling back to the earliest available state. `from_history`
+	/// returns `None` to continue the walk and `Some(None)` if the value can't be determined.
+	fn state_history_lookup<T, F, G>(&self, address: &Address, id: BlockId, from_history: F, from_state: G) -> Option<T> where
+		F: Fn(&PriorAccount) -> Option<Option<T>>,
+		G: FnOnce(&State<StateDB>) -> Option<T>,
+	{
+		if !self.config.state_history {
+			return None;
+		}
 
-			let mut ret = Executive::new(state, env_info, &machine, &schedule).transact_virtual(transaction, options)?;
+		let number = self.block_number_ref(&id)?;
+		let earliest = self.pruning_info().earliest_state;
+		if number >= earliest {
+			return None;
+		}
 
-			if let Some(original) = original_state {
-				ret.state_diff = Some(state.diff_from(original).map_err(ExecutionError::from)?);
+		{
+			let db = self.db.read();
+			let mut range = AccountHistoryPosition::new(*address, number + 1).range;
+			while range * ACCOUNT_HISTORY_RANGE <= earliest {
+				let index: Option<AccountHistory> = db.key_value().read(::db::COL_EXTRA, &AccountHistoryPosition { address: *address, range });
+				let blocks = index.map_or_else(Vec::new, |index| index.blocks);
+				for n in blocks.into_iter().filter(|n| *n > number && *n <= earliest) {
+					let hash = self.chain.read().block_hash(n)?;
+					let history: StateHistory = db.key_value().read(::db::COL_EXTRA, &hash)?;
+					if let Some(value) = history.account(address).and_then(&from_history) {
+						return value;
+					}
+				}
+				range += 1;
 			}
-			Ok(ret)
 		}
 
-		let state_diff = analytics.state_diffing;
+		from_state(&self.state_at(BlockId::Number(earliest))?)
+	}
 
-		match (analytics.transaction_tracing, analytics.vm_tracing) {
-			(true, true) => call(state, env_info, machine, state_diff, t, TransactOptions::with_tracing_and_vm_tracing()),
-			(true, false) => call(state, env_info, machine, state_diff, t, TransactOptions::with_tracing()),
-			(false, true) => call(state, env_info, machine, state_diff, t, TransactOptions::with_vm_tracing()),
-			(false, false) => call(state, env_info, machine, state_diff, t, TransactOptions::with_no_tracing()),
+	fn override_account(state: &mut State<StateDB>, address: &Address, account: &AccountOverride) -> ::ethtrie::Result<()> {
+		if let Some(balance) = account.balance {
+			state.set_balance(address, balance)?;
 		}
+		if let Some(nonce) = account.nonce {
+			state.set_nonce(address, nonce)?;
+		}
+		if let Some(ref code) = account.code {
+			state.reset_code(address, code.clone())?;
+		}
+		if let Some(ref storage) = account.state {
+			state.reset_storage(address, storage.iter().map(|(k, v)| (*k, *v)).collect())?;
+		}
+		if let Some(ref storage) = account.state_diff {
+			for (key, value) in storage {
+				state.set_storage(address, *key, *value)?;
+			}
+		}
+		Ok(())
 	}
 
 	fn block_number_ref(&self, id: &BlockId) -> Option<BlockNumber> {
@@ -1312,6 +1545,7 @@ impl snapshot::DatabaseRestore for Client {
 impl Nonce for Client {
 	fn nonce(&self, address: &Address, id: BlockId) -> Option<U256> {
 		self.state_at(id).and_then(|s| s.nonce(address).ok())
+			.or_else(|| self.state_history_lookup(address, id, |a| Some(Some(a.nonce)), |s| s.nonce(address).ok()))
 	}
 }
 
@@ -1320,6 +1554,7 @@ impl Balance for Client {
 		match state {
 			StateOrBlock::State(s) => s.balance(address).ok(),
 			StateOrBlock::Block(id) => self.state_at(id).and_then(|s| s.balance(address).ok())
+				.or_else(|| self.state_history_lookup(address, id, |a| Some(Some(a.balance)), |s| s.balance(address).ok())),
 		}
 	}
 }
@@ -1548,6 +1783,75 @@ impl Call for Client {
 		trace!(target: "estimate_gas", "estimate_gas chopping {} .. {}", lower, upper);
 		binary_chop(lower, upper, cond)
 	}
+
+	fn child_header(&self, parent: &Header) -> Header {
+		let mut header = Header::new();
+		header.set_parent_hash(parent.hash());
+		header.set_number(parent.number() + 1);
+		header.set_author(*parent.author());
+		header.set_timestamp(self.engine.open_block_header_timestamp(parent.timestamp()));
+		header.set_difficulty(*parent.difficulty());
+		header.set_gas_limit(*parent.gas_limit());
+		header
+	}
+
+	fn simulate_transactions(&self, transactions: &[SignedTransaction], state: &mut Self::State, header: &Header) -> Result<Vec<SimulatedTransaction>, CallError> {
+		let mut env_info = EnvInfo {
+			number: header.number(),
+			author: header.author().clone(),
+			timestamp: header.timestamp(),
+			difficulty: header.difficulty().clone(),
+			last_hashes: self.build_last_hashes(header.parent_hash()),
+			gas_used: U256::default(),
+			gas_limit: *header.gas_limit(),
+		};
+
+		let machine = self.engine.machine();
+		let schedule = machine.schedule(env_info.number);
+		let mut results = Vec::with_capacity(transactions.len());
+
+		for (index, t) in transactions.iter().enumerate() {
+			let original = state.clone();
+			let balance_before = state.balance(&env_info.author).map_err(|_| CallError::StateCorrupt)?;
+
+			let mut executed = Executive::new(state, &env_info, machine, &schedule).transact(t, TransactOptions::with_no_tracing())?;
+			executed.state_diff = Some(state.diff_from(original).map_err(ExecutionError::from)?);
+
+			let balance_after = state.balance(&env_info.author).map_err(|_| CallError::StateCorrupt)?;
+			env_info.gas_used = executed.cumulative_gas_used;
+
+			let outcome = TransactionOutcome::StatusCode(if executed.exception.is_some() { 0 } else { 1 });
+			let receipt = Receipt::new(outcome, executed.cumulative_gas_used, executed.logs.clone());
+			let contract_address = match t.action {
+				Action::Call(_) => None,
+				Action::Create => Some(contract_address(self.engine.create_address_scheme(env_info.number), &t.sender(), &t.nonce, &t.data).0),
+			};
+
+			results.push(SimulatedTransaction {
+				receipt: RichReceipt {
+					transaction_hash: t.hash(),
+					transaction_index: index,
+					cumulative_gas_used: executed.cumulative_gas_used,
+					gas_used: executed.gas_used,
+					contract_address,
+					logs: receipt.logs,
+					log_bloom: receipt.log_bloom,
+					outcome: receipt.outcome,
+				},
+				executed,
+				coinbase_profit: balance_after.saturating_sub(balance_before),
+			});
+		}
+
+		Ok(results)
+	}
+
+	fn override_state(&self, state: &mut Self::State, overrides: &StateOverride) -> Result<(), CallError> {
+		for (address, account) in overrides {
+			Self::override_account(state, address, account).map_err(|_| CallError::StateCorrupt)?;
+		}
+		Ok(())
+	}
 }
 
 impl EngineInfo for Client {
@@ -1565,10 +1869,21 @@ impl BadBlocks for Client {
 impl BlockChainClient for Client {
 	fn replay(&self, id: TransactionId, analytics: CallAnalytics) -> Result<Executed, CallError> {
 		let address = self.transaction_address(id).ok_or(CallError::TransactionNotFound)?;
-		let block = BlockId::Hash(address.block_hash);
+		let (mut state, env_info, transaction) = self.replay_preceding(&address)?;
+		Self::do_virtual_call(self.engine.machine(), &env_info, &mut state, &transaction, analytics)
+	}
+
+	fn replay_with_struct_logs(&self, id: TransactionId, config: trace::StructLoggerConfig) -> Result<Executed<trace::FlatTrace, Vec<trace::StructLog>>, CallError> {
+		let address = self.transaction_address(id).ok_or(CallError::TransactionNotFound)?;
+		let (mut state, env_info, transaction) = self.replay_preceding(&address)?;
+		let options = TransactOptions::with_tracing_and_struct_logging(config);
+		Self::do_virtual_call_with_options(self.engine.machine(), &env_info, &mut state, &transaction, false, options)
+	}
 
-		const PROOF: &'static str = "The transaction address contains a valid index within block; qed";
-		Ok(self.replay_block_transactions(block, analytics)?.nth(address.index).expect(PROOF).1)
+	fn replay_prestate(&self, id: TransactionId) -> Result<BTreeMap<Address, PodAccount>, CallError> {
+		let address = self.transaction_address(id).ok_or(CallError::TransactionNotFound)?;
+		let (mut state, env_info, transaction) = self.replay_preceding(&address)?;
+		Self::do_virtual_call_prestate(self.engine.machine(), &env_info, &mut state, &transaction).map(|(_, prestate)| prestate)
 	}
 
 	fn replay_block_transactions(&self, block: BlockId, analytics: CallAnalytics) -> Result<Box<Iterator<Item = (H256, Executed)>>, CallError> {
@@ -1592,6 +1907,49 @@ impl BlockChainClient for Client {
 			})))
 	}
 
+	fn replay_block_transactions_with_struct_logs(&self, block: BlockId, config: trace::StructLoggerConfig) -> Result<Box<Iterator<Item = (H256, Executed<trace::FlatTrace, Vec<trace::StructLog>>)>>, CallError> {
+		let mut env_info = self.env_info(block).ok_or(CallError::StatePruned)?;
+		let body = self.block_body(block).ok_or(CallError::StatePruned)?;
+		let mut state = self.state_at_beginning(block).ok_or(CallError::StatePruned)?;
+		let txs = body.transactions();
+		let engine = self.engine.clone();
+
+		const PROOF: &'static str = "Transactions fetched from blockchain; blockchain transactions are valid; qed";
+		const EXECUTE_PROOF: &'static str = "Transaction replayed; qed";
+
+		Ok(Box::new(txs.into_iter()
+			.map(move |t| {
+				let transaction_hash = t.hash();
+				let t = SignedTransaction::new(t).expect(PROOF);
+				let machine = engine.machine();
+				let options = TransactOptions::with_tracing_and_struct_logging(config);
+				let x = Self::do_virtual_call_with_options(machine, &env_info, &mut state, &t, false, options).expect(EXECUTE_PROOF);
+				env_info.gas_used = env_info.gas_used + x.gas_used;
+				(transaction_hash, x)
+			})))
+	}
+
+	fn replay_block_transactions_prestate(&self, block: BlockId) -> Result<Box<Iterator<Item = (H256, BTreeMap<Address, PodAccount>)>>, CallError> {
+		let mut env_info = self.env_info(block).ok_or(CallError::StatePruned)?;
+		let body = self.block_body(block).ok_or(CallError::StatePruned)?;
+		let mut state = self.state_at_beginning(block).ok_or(CallError::StatePruned)?;
+		let txs = body.transactions();
+		let engine = self.engine.clone();
+
+		const PROOF: &'static str = "Transactions fetched from blockchain; blockchain transactions are valid; qed";
+		const EXECUTE_PROOF: &'static str = "Transaction replayed; qed";
+
+		Ok(Box::new(txs.into_iter()
+			.map(move |t| {
+				let transaction_hash = t.hash();
+				let t = SignedTransaction::new(t).expect(PROOF);
+				let machine = engine.machine();
+				let (gas_used, prestate) = Self::do_virtual_call_prestate(machine, &env_info, &mut state, &t).expect(EXECUTE_PROOF);
+				env_info.gas_used = env_info.gas_used + gas_used;
+				(transaction_hash, prestate)
+			})))
+	}
+
 	fn mode(&self) -> Mode {
 		let r = self.mode.lock().clone().into();
 		trace!(target: "mode", "Asked for mode = {:?}. returning {:?}", &*self.mode.lock(), r);
@@ -1679,6 +2037,16 @@ impl BlockChainClient for Client {
 		let result = match state {
 			StateOrBlock::State(s) => s.code(address).ok(),
 			StateOrBlock::Block(id) => self.state_at(id).and_then(|s| s.code(address).ok())
+				.or_else(|| self.state_history_lookup(
+					address,
+					id,
+					|a| match a.code_changed {
+						true if a.code.is_empty() => Some(Some(None)),
+						true => Some(Some(Some(Arc::new(a.code.clone())))),
+						false => None,
+					},
+					|s| s.code(address).ok(),
+				)),
 		};
 
 		// Converting from `Option<Option<Arc<Bytes>>>` to `Option<Option<Bytes>>`
@@ -1689,6 +2057,7 @@ impl BlockChainClient for Client {
 		match state {
 			StateOrBlock::State(s) => s.storage_at(address, position).ok(),
 			StateOrBlock::Block(id) => self.state_at(id).and_then(|s| s.storage_at(address, position).ok())
+				.or_else(|| self.state_history_lookup(address, id, |a| a.storage_at(position), |s| s.storage_at(address, position).ok())),
 		}
 	}
 
@@ -1852,6 +2221,53 @@ impl BlockChainClient for Client {
 		self.state_db.read().journal_db().state(hash)
 	}
 
+	fn state_node_known(&self, account: Option<&H256>, hash: &H256) -> bool {
+		let state_db = self.state_db.read();
+		match account {
+			Some(address_hash) => self.factories.accountdb.readonly(state_db.as_hashdb(), *address_hash).contains(hash),
+			None => state_db.as_hashdb().contains(hash),
+		}
+	}
+
+	fn import_state_nodes(&self, nodes: Vec<(Option<H256>, Bytes)>, accounts: &[H256]) -> EthcoreResult<()> {
+		let db = self.db.read();
+		let mut batch = DBTransaction::new();
+		self.state_db.write().inject_nodes(&mut batch, nodes, accounts)?;
+		db.key_value().write(batch)?;
+		Ok(())
+	}
+
+	fn import_state_pivot(&self, unverified: Unverified, receipts_bytes: Bytes, parent_total_difficulty: U256) -> EthcoreResult<()> {
+		trace_time!("import_state_pivot");
+
+		self.engine.verify_block_basic(&unverified.header)?;
+		let receipts_root = ordered_trie_root(Rlp::new(&receipts_bytes).iter().map(|r| r.as_raw()));
+		if receipts_root != *unverified.header.receipts_root() {
+			bail!(EthcoreErrorKind::Block(BlockError::InvalidReceiptsRoot(Mismatch { expected: *unverified.header.receipts_root(), found: receipts_root })));
+		}
+		if !self.state_node_known(None, unverified.header.state_root()) {
+			bail!("State of the pivot block is incomplete");
+		}
+		let receipts = Rlp::new(&receipts_bytes).as_list()?;
+
+		let _import_lock = self.importer.import_lock.lock();
+		let db = self.db.read();
+		{
+			let chain = self.chain.read();
+			let mut batch = DBTransaction::new();
+			chain.insert_unordered_block(&mut batch, encoded::Block::new(unverified.bytes), receipts, Some(parent_total_difficulty), true, false);
+			db.key_value().write_buffered(batch);
+			chain.commit();
+		}
+		db.key_value().flush()?;
+
+		// Reopen the chain so that the blocks below the pivot are reported as missing.
+		let mut chain = self.chain.write();
+		*chain = Arc::new(BlockChain::new(self.config.blockchain.clone(), &[], db.clone()));
+		*self.tracedb.write() = TraceDB::new(self.config.tracing.clone(), db.clone(), chain.clone());
+		Ok(())
+	}
+
 	fn encoded_block_receipts(&self, hash: &H256) -> Option<Bytes> {
 		self.chain.read().block_receipts(hash).map(|receipts| ::rlp::encode(&receipts).into_vec())
 	}
@@ -1904,6 +2320,11 @@ impl BlockChainClient for Client {
 				return Err(filter.to_block.clone());
 			}
 
+			// Selective filters are answered from the log index when it covers the range.
+			if let Some(logs) = chain.indexed_logs(&filter, from, to) {
+				return Ok(logs);
+			}
+
 			chain.blocks_with_bloom(&filter.bloom_possibilities(), from, to)
 				.into_iter()
 				.filter_map(|n| chain.block_hash(n))
@@ -2047,6 +2468,10 @@ impl BlockChainClient for Client {
 		self.importer.miner.ready_transactions(self, max_len, ::miner::PendingOrdering::Priority)
 	}
 
+	fn pooled_transaction(&self, hash: &H256) -> Option<Arc<VerifiedTransaction>> {
+		self.importer.miner.transaction(hash)
+	}
+
 	fn signing_chain_id(&self) -> Option<u64> {
 		self.engine.signing_chain_id(&self.latest_env_info())
 	}
@@ -2241,6 +2666,10 @@ impl PrepareOpenBlock for Client {
 			&mut chain.ancestry_with_metadata_iter(best_header.hash()),
 		)?;
 
+		if self.config.state_history {
+			open_block.track_state_changes();
+		}
+
 		// Add uncles
 		chain
 			.find_uncle_headers(&h, engine.maximum_uncle_age())
@@ -95,6 +95,8 @@ pub struct ClientConfig {
 	pub vm_type: VMType,
 	/// Fat DB enabled?
 	pub fat_db: bool,
+	/// Keep reverse state diffs of imported blocks to answer queries about pruned state?
+	pub state_history: bool,
 	/// The JournalDB ("pruning") algorithm to use.
 	pub pruning: journaldb::Algorithm,
 	/// The name of the client instance.
@@ -134,6 +136,7 @@ impl Default for ClientConfig {
 			tracing: Default::default(),
 			vm_type: Default::default(),
 			fat_db: false,
+			state_history: false,
 			pruning: journaldb::Algorithm::OverlayRecent,
 			name: "default".into(),
 			db_cache_size: None,
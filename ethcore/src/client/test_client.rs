@@ -39,26 +39,28 @@ use client::{
 	PrepareOpenBlock, BlockChainClient, BlockChainInfo, BlockStatus, BlockId, Mode,
 	TransactionId, UncleId, TraceId, TraceFilter, LastHashes, CallAnalytics,
 	ProvingBlockChainClient, ScheduleInfo, ImportSealedBlock, BroadcastProposalBlock, ImportBlock, StateOrBlock,
-	Call, StateClient, EngineInfo, AccountData, BlockChain, BlockProducer, SealedBlockImporter, IoClient,
+	Call, SimulatedTransaction, StateClient, EngineInfo, AccountData, BlockChain, BlockProducer, SealedBlockImporter, IoClient,
 	BadBlocks,
 };
 use db::{NUM_COLUMNS, COL_STATE};
 use header::{Header as BlockHeader, BlockNumber};
 use filter::Filter;
 use log_entry::LocalizedLogEntry;
-use receipt::{Receipt, LocalizedReceipt, TransactionOutcome};
+use receipt::{Receipt, LocalizedReceipt, RichReceipt, TransactionOutcome};
 use error::{Error, EthcoreResult};
 use vm::Schedule;
 use miner::{self, Miner, MinerService};
 use spec::Spec;
 use types::basic_account::BasicAccount;
 use types::pruning_info::PruningInfo;
+use types::state_override::StateOverride;
 use verification::queue::QueueInfo;
 use verification::queue::kind::blocks::Unverified;
 use block::{OpenBlock, SealedBlock, ClosedBlock};
 use executive::Executed;
 use error::CallError;
-use trace::LocalizedTrace;
+use trace::{LocalizedTrace, FlatTrace, StructLog, StructLoggerConfig};
+use pod_account::PodAccount;
 use state_db::StateDB;
 use header::Header;
 use encoded;
@@ -115,6 +117,10 @@ pub struct TestBlockChainClient {
 	pub history: RwLock<Option<u64>>,
 	/// Is disabled
 	pub disabled: AtomicBool,
+	/// State trie nodes and code written by state sync, keyed by owning account and hash.
+	pub state_nodes: RwLock<HashMap<(Option<H256>, H256), Bytes>>,
+	/// Pivot block imported by state sync and its parent total difficulty.
+	pub state_pivot: RwLock<Option<(H256, U256)>>,
 }
 
 /// Used for generating test client blocks.
@@ -181,6 +187,8 @@ impl TestBlockChainClient {
 			traces: RwLock::new(None),
 			history: RwLock::new(None),
 			disabled: AtomicBool::new(false),
+			state_nodes: RwLock::new(HashMap::new()),
+			state_pivot: RwLock::new(None),
 			error_on_logs: RwLock::new(None),
 		};
 
@@ -587,6 +595,43 @@ impl Call for TestBlockChainClient {
 	fn estimate_gas(&self, _t: &SignedTransaction, _state: &Self::State, _header: &Header) -> Result<U256, CallError> {
 		Ok(21000.into())
 	}
+
+	fn child_header(&self, parent: &BlockHeader) -> BlockHeader {
+		let mut header = BlockHeader::new();
+		header.set_parent_hash(parent.hash());
+		header.set_number(parent.number() + 1);
+		header.set_author(*parent.author());
+		header.set_timestamp(parent.timestamp() + 1);
+		header.set_difficulty(*parent.difficulty());
+		header.set_gas_limit(*parent.gas_limit());
+		header
+	}
+
+	fn simulate_transactions(&self, transactions: &[SignedTransaction], _state: &mut Self::State, _header: &BlockHeader) -> Result<Vec<SimulatedTransaction>, CallError> {
+		let mut res = Vec::with_capacity(transactions.len());
+		for (index, tx) in transactions.iter().enumerate() {
+			let executed = self.execution_result.read().clone().unwrap()?;
+			res.push(SimulatedTransaction {
+				receipt: RichReceipt {
+					transaction_hash: tx.hash(),
+					transaction_index: index,
+					cumulative_gas_used: executed.cumulative_gas_used,
+					gas_used: executed.gas_used,
+					contract_address: None,
+					logs: executed.logs.clone(),
+					log_bloom: Default::default(),
+					outcome: TransactionOutcome::StatusCode(1),
+				},
+				executed,
+				coinbase_profit: 0.into(),
+			});
+		}
+		Ok(res)
+	}
+
+	fn override_state(&self, _state: &mut Self::State, _overrides: &StateOverride) -> Result<(), CallError> {
+		Ok(())
+	}
 }
 
 impl StateInfo for () {
@@ -637,6 +682,22 @@ impl BlockChainClient for TestBlockChainClient {
 		Ok(Box::new(self.traces.read().clone().unwrap().into_iter().map(|t| t.transaction_hash.unwrap_or(H256::new())).zip(self.execution_result.read().clone().unwrap().into_iter())))
 	}
 
+	fn replay_with_struct_logs(&self, _id: TransactionId, _config: StructLoggerConfig) -> Result<Executed<FlatTrace, Vec<StructLog>>, CallError> {
+		Err(CallError::StatePruned)
+	}
+
+	fn replay_prestate(&self, _id: TransactionId) -> Result<BTreeMap<Address, PodAccount>, CallError> {
+		Err(CallError::StatePruned)
+	}
+
+	fn replay_block_transactions_with_struct_logs(&self, _block: BlockId, _config: StructLoggerConfig) -> Result<Box<Iterator<Item = (H256, Executed<FlatTrace, Vec<StructLog>>)>>, CallError> {
+		Err(CallError::StatePruned)
+	}
+
+	fn replay_block_transactions_prestate(&self, _block: BlockId) -> Result<Box<Iterator<Item = (H256, BTreeMap<Address, PodAccount>)>>, CallError> {
+		Err(CallError::StatePruned)
+	}
+
 	fn block_total_difficulty(&self, _id: BlockId) -> Option<U256> {
 		Some(U256::zero())
 	}
@@ -789,6 +850,23 @@ impl BlockChainClient for TestBlockChainClient {
 		None
 	}
 
+	fn state_node_known(&self, account: Option<&H256>, hash: &H256) -> bool {
+		self.state_nodes.read().contains_key(&(account.cloned(), *hash))
+	}
+
+	fn import_state_nodes(&self, nodes: Vec<(Option<H256>, Bytes)>, _accounts: &[H256]) -> EthcoreResult<()> {
+		let mut state_nodes = self.state_nodes.write();
+		for (account, node) in nodes {
+			state_nodes.insert((account, keccak(&node)), node);
+		}
+		Ok(())
+	}
+
+	fn import_state_pivot(&self, unverified: Unverified, _receipts: Bytes, parent_total_difficulty: U256) -> EthcoreResult<()> {
+		*self.state_pivot.write() = Some((unverified.hash(), parent_total_difficulty));
+		Ok(())
+	}
+
 	fn encoded_block_receipts(&self, hash: &H256) -> Option<Bytes> {
 		// starts with 'f' ?
 		if *hash > H256::from("f000000000000000000000000000000000000000000000000000000000000000") {
@@ -841,6 +919,10 @@ impl BlockChainClient for TestBlockChainClient {
 		self.miner.ready_transactions(self, 4096, miner::PendingOrdering::Priority)
 	}
 
+	fn pooled_transaction(&self, hash: &H256) -> Option<Arc<VerifiedTransaction>> {
+		self.miner.transaction(hash)
+	}
+
 	fn signing_chain_id(&self) -> Option<u64> { None }
 
 	fn mode(&self) -> Mode { Mode::Active }
@@ -30,8 +30,9 @@ use executive::Executed;
 use filter::Filter;
 use header::{BlockNumber};
 use log_entry::LocalizedLogEntry;
-use receipt::LocalizedReceipt;
-use trace::LocalizedTrace;
+use receipt::{LocalizedReceipt, RichReceipt};
+use pod_account::PodAccount;
+use trace::{LocalizedTrace, FlatTrace, StructLog, StructLoggerConfig};
 use transaction::{self, LocalizedTransaction, SignedTransaction};
 use verification::queue::QueueInfo as BlockQueueInfo;
 use verification::queue::kind::blocks::Unverified;
@@ -48,10 +49,21 @@ use types::ids::*;
 use types::basic_account::BasicAccount;
 use types::trace_filter::Filter as TraceFilter;
 use types::call_analytics::CallAnalytics;
+use types::state_override::StateOverride;
 use types::blockchain_info::BlockChainInfo;
 use types::block_status::BlockStatus;
 use types::pruning_info::PruningInfo;
 
+/// Outcome of a transaction executed as part of a simulated bundle.
+pub struct SimulatedTransaction {
+	/// Receipt the transaction would produce.
+	pub receipt: RichReceipt,
+	/// Execution details, including the state diff.
+	pub executed: Executed,
+	/// Balance gained by the block author.
+	pub coinbase_profit: U256,
+}
+
 /// State information to be used during client query
 pub enum StateOrBlock {
 	/// State to be used, may be pending
@@ -191,6 +203,18 @@ pub trait Call {
 
 	/// Estimates how much gas will be necessary for a call.
 	fn estimate_gas(&self, t: &SignedTransaction, state: &Self::State, header: &Header) -> Result<U256, CallError>;
+
+	/// Header of a new block on top of `parent`, for simulating transactions in.
+	/// Author, difficulty and gas limit are inherited from the parent.
+	fn child_header(&self, parent: &Header) -> Header;
+
+	/// Executes signed transactions in sequence, enforcing nonces, gas payment and the block gas limit.
+	/// Changes are made to `state` only and never committed to the chain.
+	fn simulate_transactions(&self, transactions: &[SignedTransaction], state: &mut Self::State, header: &Header) -> Result<Vec<SimulatedTransaction>, CallError>;
+
+	/// Overrides balance, nonce, code and storage of accounts in given state.
+	/// Used to simulate calls against a modified state.
+	fn override_state(&self, state: &mut Self::State, overrides: &StateOverride) -> Result<(), CallError>;
 }
 
 /// Provides `engine` method
@@ -294,6 +318,18 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// Get latest state node
 	fn state_data(&self, hash: &H256) -> Option<Bytes>;
 
+	/// Check if a state trie node or contract code is in the database. Storage trie nodes and code are
+	/// looked up under the address hash of the account owning them.
+	fn state_node_known(&self, account: Option<&H256>, hash: &H256) -> bool;
+
+	/// Write state trie nodes and contract code downloaded by state sync to the database.
+	/// `accounts` are the address hashes of all accounts found in the downloaded nodes.
+	fn import_state_nodes(&self, nodes: Vec<(Option<H256>, Bytes)>, accounts: &[H256]) -> EthcoreResult<()>;
+
+	/// Make a block whose state has been downloaded by state sync the best block.
+	/// Blocks between the genesis and this block are then missing and may be downloaded as ancient blocks.
+	fn import_state_pivot(&self, block: Unverified, receipts: Bytes, parent_total_difficulty: U256) -> EthcoreResult<()>;
+
 	/// Get raw block receipts data by block header hash.
 	fn encoded_block_receipts(&self, hash: &H256) -> Option<Bytes>;
 
@@ -315,6 +351,22 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// Replays all the transactions in a given block for inspection.
 	fn replay_block_transactions(&self, block: BlockId, analytics: CallAnalytics) -> Result<Box<Iterator<Item = (H256, Executed)>>, CallError>;
 
+	/// Replays a given transaction with the geth-style struct logger. Transactions preceding it in
+	/// its block are replayed without tracing.
+	fn replay_with_struct_logs(&self, id: TransactionId, config: StructLoggerConfig) -> Result<Executed<FlatTrace, Vec<StructLog>>, CallError>;
+
+	/// Replays a given transaction, yielding the state of the accounts it reads or modifies as it
+	/// was right before its execution.
+	fn replay_prestate(&self, id: TransactionId) -> Result<BTreeMap<Address, PodAccount>, CallError>;
+
+	/// Replays all the transactions in a given block with the geth-style struct logger.
+	/// Call traces are recorded alongside the struct logs.
+	fn replay_block_transactions_with_struct_logs(&self, block: BlockId, config: StructLoggerConfig) -> Result<Box<Iterator<Item = (H256, Executed<FlatTrace, Vec<StructLog>>)>>, CallError>;
+
+	/// Replays all the transactions in a given block, yielding for each of them the state of
+	/// the accounts it reads or modifies as it was right before its execution.
+	fn replay_block_transactions_prestate(&self, block: BlockId) -> Result<Box<Iterator<Item = (H256, BTreeMap<Address, PodAccount>)>>, CallError>;
+
 	/// Returns traces matching given filter.
 	fn filter_traces(&self, filter: TraceFilter) -> Option<Vec<LocalizedTrace>>;
 
@@ -333,6 +385,9 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// List all ready transactions that should be propagated to other peers.
 	fn transactions_to_propagate(&self) -> Vec<Arc<VerifiedTransaction>>;
 
+	/// Get a transaction from the queue by hash.
+	fn pooled_transaction(&self, hash: &H256) -> Option<Arc<VerifiedTransaction>>;
+
 	/// Sorted list of transaction gas prices from at least last sample_size blocks.
 	fn gas_price_corpus(&self, sample_size: usize) -> ::stats::Corpus<U256> {
 		let mut h = self.chain_info().best_block_hash;
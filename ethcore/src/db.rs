@@ -19,6 +19,9 @@
 use std::ops::Deref;
 use std::hash::Hash;
 use std::collections::HashMap;
+use byteorder::{BigEndian, ByteOrder};
+use ethereum_types::H256;
+use header::BlockNumber;
 use parking_lot::RwLock;
 use kvdb::{DBTransaction, KeyValueDB};
 
@@ -41,8 +44,13 @@ pub const COL_ACCOUNT_BLOOM: Option<u32> = Some(5);
 pub const COL_NODE_INFO: Option<u32> = Some(6);
 /// Column for the light client chain.
 pub const COL_LIGHT_CHAIN: Option<u32> = Some(7);
+/// Column for the optional log index.
+pub const COL_LOG_INDEX: Option<u32> = Some(8);
 /// Number of columns in DB
-pub const NUM_COLUMNS: Option<u32> = Some(8);
+pub const NUM_COLUMNS: Option<u32> = Some(9);
+
+/// Length of the block number which ends the keys of block indexes.
+const BLOCK_INDEX_NUMBER_LEN: usize = 8;
 
 /// Modes for updating caches.
 #[derive(Clone, Copy)]
@@ -237,3 +245,64 @@ impl<KVDB: KeyValueDB + ?Sized> Readable for KVDB {
 		}
 	}
 }
+
+/// Key of an entry of an index of canonical blocks by value, like the log index or the trace address index.
+///
+/// Keys are the prefix of the indexed value followed by the big-endian block number. Entries of a value are
+/// sorted by block number, so a range of blocks is read by seeking to the key of its first block.
+pub struct BlockIndexKey(Vec<u8>);
+
+impl BlockIndexKey {
+	/// Returns the key of the entry of given block.
+	pub fn new(prefix: &[u8], number: BlockNumber) -> Self {
+		let mut key = vec![0u8; prefix.len() + BLOCK_INDEX_NUMBER_LEN];
+		key[..prefix.len()].copy_from_slice(prefix);
+		BigEndian::write_u64(&mut key[prefix.len()..], number);
+		BlockIndexKey(key)
+	}
+}
+
+impl Deref for BlockIndexKey {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+/// Positions of the items of a canonical block which match an indexed value.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct BlockIndexEntry {
+	/// Hash of the block.
+	pub block_hash: H256,
+	/// Positions of the matching items within the block.
+	pub positions: Vec<ItemPosition>,
+}
+
+/// Position of a log or a trace within a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RlpEncodable, RlpDecodable)]
+pub struct ItemPosition {
+	/// Index of the transaction.
+	pub transaction: u64,
+	/// Index of the item within the items of the transaction.
+	pub index: u64,
+}
+
+/// Reads the block number from the key of a block index entry.
+pub fn block_index_number(key: &[u8]) -> BlockNumber {
+	BigEndian::read_u64(&key[key.len() - BLOCK_INDEX_NUMBER_LEN..])
+}
+
+/// Returns the entries of a block index value from the block of `start` key up to block `to`, inclusive.
+///
+/// Entries of blocks which are no longer canonical may linger, their block hash has to be checked.
+pub fn block_index_entries<'a>(db: &'a KeyValueDB, col: Option<u32>, start: &'a BlockIndexKey, to: BlockNumber) -> Box<Iterator<Item=(BlockNumber, BlockIndexEntry)> + 'a> {
+	let prefix = &start[..start.len() - BLOCK_INDEX_NUMBER_LEN];
+	// iterator may continue beyond values beginning with this prefix.
+	Box::new(db.iter_from_prefix(col, start)
+		.take_while(move |&(ref key, _)| key.starts_with(prefix) && block_index_number(key) <= to)
+		.map(|(key, value)| {
+			let entry = rlp::decode(&value).expect("decode error: the db is corrupted or the data structure has changed");
+			(block_index_number(&key), entry)
+		}))
+}
@@ -153,6 +153,18 @@ impl TransactOptions<trace::ExecutiveTracer, trace::ExecutiveVMTracer> {
 	}
 }
 
+impl TransactOptions<trace::ExecutiveTracer, trace::StructLogger> {
+	/// Creates new `TransactOptions` with default tracing and geth-style struct logging.
+	pub fn with_tracing_and_struct_logging(config: trace::StructLoggerConfig) -> Self {
+		TransactOptions {
+			tracer: trace::ExecutiveTracer::default(),
+			vm_tracer: trace::StructLogger::new(config),
+			check_nonce: true,
+			output_from_init_contract: false,
+		}
+	}
+}
+
 impl TransactOptions<trace::ExecutiveTracer, trace::NoopVMTracer> {
 	/// Creates new `TransactOptions` with default tracing and no VM tracing.
 	pub fn with_tracing() -> Self {
@@ -701,7 +713,7 @@ impl<'a> CallCreateExecutive<'a> {
 				},
 				Some((_, _, Err(TrapError::Call(subparams, resume)))) => {
 					tracer.prepare_trace_call(&subparams, resume.depth + 1, resume.machine.builtin(&subparams.address, resume.info.number).is_some());
-					vm_tracer.prepare_subtrace(subparams.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
+					vm_tracer.prepare_subtrace(&subparams.code_address, subparams.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
 
 					let sub_exec = CallCreateExecutive::new_call_raw(
 						subparams,
@@ -720,7 +732,7 @@ impl<'a> CallCreateExecutive<'a> {
 				},
 				Some((_, _, Err(TrapError::Create(subparams, address, resume)))) => {
 					tracer.prepare_trace_create(&subparams);
-					vm_tracer.prepare_subtrace(subparams.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
+					vm_tracer.prepare_subtrace(&subparams.address, subparams.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
 
 					let sub_exec = CallCreateExecutive::new_create_raw(
 						subparams,
@@ -924,7 +936,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 		vm_tracer: &mut V
 	) -> vm::Result<FinalizationResult> where T: Tracer, V: VMTracer {
 		tracer.prepare_trace_call(&params, self.depth, self.machine.builtin(&params.address, self.info.number).is_some());
-		vm_tracer.prepare_subtrace(params.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
+		vm_tracer.prepare_subtrace(&params.code_address, params.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
 
 		let gas = params.gas;
 
@@ -1006,7 +1018,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 		vm_tracer: &mut V,
 	) -> vm::Result<FinalizationResult> where T: Tracer, V: VMTracer {
 		tracer.prepare_trace_create(&params);
-		vm_tracer.prepare_subtrace(params.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
+		vm_tracer.prepare_subtrace(&params.address, params.code.as_ref().map_or_else(|| &[] as &[u8], |d| &*d as &[u8]));
 
 		let address = params.address;
 		let gas = params.gas;
@@ -210,6 +210,10 @@ impl<'a, T: 'a, V: 'a, B: 'a> Ext for TestExt<'a, T, V, B>
 		self.ext.env_info()
 	}
 
+	fn chain_id(&self) -> u64 {
+		self.ext.chain_id()
+	}
+
 	fn depth(&self) -> usize {
 		0
 	}
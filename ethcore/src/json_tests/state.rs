@@ -138,6 +138,8 @@ mod state_tests {
 	declare_test!{GeneralStateTest_stCallCreateCallCodeTest, "GeneralStateTests/stCallCreateCallCodeTest/"}
 	declare_test!{GeneralStateTest_stCallDelegateCodesCallCodeHomestead, "GeneralStateTests/stCallDelegateCodesCallCodeHomestead/"}
 	declare_test!{GeneralStateTest_stCallDelegateCodesHomestead, "GeneralStateTests/stCallDelegateCodesHomestead/"}
+	// fixtures are only in the Istanbul release of the tests, newer than the pinned submodule revision.
+	declare_test!{ignore => GeneralStateTest_stChainId, "GeneralStateTests/stChainId/"}
 	declare_test!{GeneralStateTest_stChangedEIP150, "GeneralStateTests/stChangedEIP150/"}
 	declare_test!{GeneralStateTest_stCodeCopyTest, "GeneralStateTests/stCodeCopyTest/"}
 	declare_test!{GeneralStateTest_stCodeSizeLimit, "GeneralStateTests/stCodeSizeLimit/"}
@@ -165,9 +167,15 @@ mod state_tests {
 	declare_test!{GeneralStateTest_stRefundTest, "GeneralStateTests/stRefundTest/"}
 	declare_test!{GeneralStateTest_stReturnDataTest, "GeneralStateTests/stReturnDataTest/"}
 	declare_test!{GeneralStateTest_stRevertTest, "GeneralStateTests/stRevertTest/"}
+	// fixtures are only in the Istanbul release of the tests, newer than the pinned submodule revision.
+	declare_test!{ignore => GeneralStateTest_stSelfBalance, "GeneralStateTests/stSelfBalance/"}
 	declare_test!{GeneralStateTest_stShift, "GeneralStateTests/stShift/"}
+	// fixtures are only in the Istanbul release of the tests, newer than the pinned submodule revision.
+	declare_test!{ignore => GeneralStateTest_stSLoadTest, "GeneralStateTests/stSLoadTest/"}
 	declare_test!{GeneralStateTest_stSolidityTest, "GeneralStateTests/stSolidityTest/"}
 	declare_test!{GeneralStateTest_stSpecialTest, "GeneralStateTests/stSpecialTest/"}
+	// fixtures are only in the Istanbul release of the tests, newer than the pinned submodule revision.
+	declare_test!{ignore => GeneralStateTest_stSStoreTest, "GeneralStateTests/stSStoreTest/"}
 	declare_test!{GeneralStateTest_stStackTests, "GeneralStateTests/stStackTests/"}
 	declare_test!{GeneralStateTest_stStaticCall, "GeneralStateTests/stStaticCall/"}
 	declare_test!{GeneralStateTest_stSystemOperationsTest, "GeneralStateTests/stSystemOperationsTest/"}
@@ -81,7 +81,6 @@ extern crate hashdb;
 extern crate itertools;
 extern crate kvdb;
 extern crate kvdb_memorydb;
-extern crate kvdb_rocksdb;
 extern crate lru_cache;
 extern crate num_cpus;
 extern crate num;
@@ -112,6 +111,8 @@ extern crate memory_cache;
 extern crate journaldb;
 #[cfg(any(test, feature = "json-tests", feature = "test-helpers"))]
 extern crate tempdir;
+#[cfg(any(test, feature = "test-helpers"))]
+extern crate kvdb_rocksdb;
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows", target_os = "android"))]
 extern crate hardware_wallet;
@@ -160,6 +161,7 @@ pub mod machine;
 pub mod miner;
 pub mod pod_state;
 pub mod pod_account;
+pub mod pruning;
 pub mod snapshot;
 pub mod spec;
 pub mod state;
@@ -0,0 +1,767 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion of the state database between pruning algorithms.
+//!
+//! An archive database keeps every state node ever written. Converting it to `OverlayRecentDB`
+//! keeps the state of the latest blocks only: the journal of these blocks is rebuilt by diffing
+//! their state tries and every node which isn't reachable from their state roots is removed.
+
+use std::cell::Cell;
+use std::mem;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use account_db::combine_key;
+use blockchain::{BlockChain, BlockChainDB, BlockProvider};
+use db::COL_STATE;
+use error::Error;
+use ethereum_types::H256;
+use ethtrie::{RlpCodec, TrieError};
+use hash::{KECCAK_EMPTY, KECCAK_NULL_RLP};
+use hashdb::HashDB;
+use header::BlockNumber;
+use journaldb::{self, Algorithm};
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use rlp::{self, Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use trie::NodeCodec;
+use trie::node::Node;
+use basic_account::BasicAccount;
+use byteorder::{BigEndian, ByteOrder};
+
+/// Number of changes written to a database at once.
+const BATCH_SIZE: usize = 100_000;
+
+/// Key of the conversion marker in the state column. It holds the blocks being kept and is
+/// present from the moment unreachable state is removed until the journal is written.
+const CONVERSION_KEY: &'static [u8] = b"pruning-conversion";
+
+/// Number of columns of the database holding the progress of a conversion.
+pub const NUM_PROGRESS_COLUMNS: Option<u32> = Some(2);
+/// Column of the state nodes which are kept.
+const COL_MARKED: Option<u32> = Some(0);
+/// Column of the state diffs of the kept blocks, and of the blocks themselves once marking is done.
+const COL_DIFFS: Option<u32> = Some(1);
+/// Key of the kept blocks in the diffs column.
+const BLOCKS_KEY: &'static [u8] = b"blocks";
+
+/// State root of a block which is kept after conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetainedBlock {
+	/// Block number.
+	pub number: BlockNumber,
+	/// Block hash.
+	pub hash: H256,
+	/// State root of the block.
+	pub state_root: H256,
+}
+
+impl Encodable for RetainedBlock {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		s.append(&self.number);
+		s.append(&self.hash);
+		s.append(&self.state_root);
+	}
+}
+
+impl Decodable for RetainedBlock {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(RetainedBlock {
+			number: rlp.val_at(0)?,
+			hash: rlp.val_at(1)?,
+			state_root: rlp.val_at(2)?,
+		})
+	}
+}
+
+/// Returns true if a conversion of the state in `db` was interrupted. The database can't be
+/// used until the conversion is finished.
+pub fn is_converting(db: &KeyValueDB) -> Result<bool, Error> {
+	Ok(db.get(COL_STATE, CONVERSION_KEY)?.is_some())
+}
+
+/// Converts an archive database to `OverlayRecentDB` pruning, keeping the state of the
+/// latest `history` blocks and of the ancient block preceding them.
+///
+/// `progress` is an empty database with `NUM_PROGRESS_COLUMNS` columns, or the one used by an
+/// interrupted conversion, which is then resumed.
+pub fn archive_to_overlay_recent(db: Arc<BlockChainDB>, progress: &KeyValueDB, genesis: &[u8], history: u64) -> Result<(), Error> {
+	let chain = BlockChain::new(Default::default(), genesis, db.clone());
+	let best = chain.best_block_number();
+
+	let blocks = (best.saturating_sub(history)..best + 1)
+		.map(|number| -> Result<RetainedBlock, Error> {
+			let hash = chain.block_hash(number)
+				.ok_or_else(|| format!("Missing hash of block #{}", number))?;
+			let header = chain.block_header_data(&hash)
+				.ok_or_else(|| format!("Missing header of block #{}", number))?;
+			Ok(RetainedBlock { number, hash, state_root: header.state_root() })
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+
+	convert_archive(db.key_value(), progress, &blocks)
+}
+
+/// Converts the archive state in `db` to `OverlayRecentDB`, keeping the state of given blocks.
+///
+/// The first block becomes ancient state, all later blocks are journaled. Blocks must be
+/// consecutive and sorted by number.
+///
+/// Nodes which are kept and the journal being rebuilt are stored in `progress`, so that memory
+/// use doesn't grow with the size of the state. A conversion interrupted at any point is resumed
+/// by calling this function again with the same `progress` database.
+pub fn convert_archive(db: &Arc<KeyValueDB>, progress: &KeyValueDB, blocks: &[RetainedBlock]) -> Result<(), Error> {
+	let mut journal_db = journaldb::new(db.clone(), Algorithm::OverlayRecent, COL_STATE);
+	let nodes = StateNodes { db: &**db };
+
+	let blocks: Vec<RetainedBlock> = match db.get(COL_STATE, CONVERSION_KEY)? {
+		// unreachable state is being removed already, so it can't be marked again.
+		Some(marker) => {
+			info!("Resuming interrupted conversion");
+			if progress.get(COL_DIFFS, BLOCKS_KEY)?.as_ref() != Some(&marker) {
+				return Err("Progress of the interrupted pruning conversion is missing, the database can't be recovered.".into());
+			}
+			Rlp::new(&marker).as_list()?
+		},
+		// the journal is written together with the removal of the marker, its presence
+		// means an earlier conversion has finished.
+		None if journal_db.earliest_era().is_some() => return Ok(()),
+		None if blocks.is_empty() => return Ok(()),
+		None => {
+			let marker = rlp::encode_list(blocks).into_vec();
+			if progress.get(COL_DIFFS, BLOCKS_KEY)?.as_ref().map(|stored| &**stored) != Some(&*marker) {
+				mark(&nodes, progress, blocks)?;
+			}
+
+			let mut batch = DBTransaction::new();
+			batch.put(COL_STATE, CONVERSION_KEY, &marker);
+			db.write(batch)?;
+			db.flush()?;
+			blocks.to_vec()
+		},
+	};
+
+	let marks = Marks { db: progress, marked: Cell::new(0) };
+	info!("Sweeping unreachable state");
+	let mut batch = DBTransaction::new();
+	let mut removed = 0;
+	for (key, _) in db.iter(COL_STATE) {
+		// other keys belong to the journal
+		if key.len() != 32 || marks.contains(&H256::from_slice(&key))? {
+			continue;
+		}
+
+		batch.delete(COL_STATE, &key);
+		removed += 1;
+		if removed % BATCH_SIZE == 0 {
+			db.write(batch)?;
+			batch = DBTransaction::new();
+			info!("Removed {} unreachable nodes", removed);
+		}
+	}
+	db.write(batch)?;
+	info!("Removed {} unreachable nodes", removed);
+
+	let mut batch = DBTransaction::new();
+	for block in &blocks[1..] {
+		let diff = progress.get(COL_DIFFS, &encode_number(block.number))?
+			.ok_or_else(|| format!("Missing state diff of block #{}", block.number))?;
+		let diff: StateDiff = rlp::decode(&diff)?;
+
+		for key in diff.inserts {
+			let value = nodes.node(&key)?;
+			journal_db.emplace(key, value);
+		}
+		for key in &diff.deletes {
+			journal_db.remove(key);
+		}
+		journal_db.journal_under(&mut batch, block.number, &block.hash)?;
+	}
+	batch.delete(COL_STATE, CONVERSION_KEY);
+	db.write(batch)?;
+	db.flush()?;
+
+	Ok(())
+}
+
+/// Marks the state of the first block and nodes inserted by later blocks in `progress`,
+/// together with the state diffs of later blocks.
+fn mark(nodes: &StateNodes, progress: &KeyValueDB, blocks: &[RetainedBlock]) -> Result<(), Error> {
+	let first = &blocks[0];
+
+	// subtrees of marked nodes are assumed to be marked, which doesn't hold for the leftovers
+	// of an interrupted marking.
+	for col in &[COL_MARKED, COL_DIFFS] {
+		let mut batch = DBTransaction::new();
+		for (i, (key, _)) in progress.iter(*col).enumerate() {
+			batch.delete(*col, &key);
+			if (i + 1) % BATCH_SIZE == 0 {
+				progress.write(mem::replace(&mut batch, DBTransaction::new()))?;
+			}
+		}
+		progress.write(batch)?;
+	}
+
+	let marks = Marks { db: progress, marked: Cell::new(0) };
+
+	// the state is marked first, subtrees of inserted nodes may be shared with it.
+	info!("Marking state reachable from block #{}", first.number);
+	nodes.mark_state(&first.state_root, &marks)?;
+
+	info!("Rebuilding journal of blocks #{}..#{}", first.number, first.number + blocks.len() as u64 - 1);
+	for pair in blocks.windows(2) {
+		let mut diff = StateDiff::default();
+		nodes.diff_state(&pair[0].state_root, &pair[1].state_root, &mut diff)?;
+		for key in &diff.inserts {
+			marks.insert(key)?;
+		}
+
+		let mut batch = DBTransaction::new();
+		batch.put(COL_DIFFS, &encode_number(pair[1].number), &rlp::encode(&diff));
+		progress.write_buffered(batch);
+	}
+
+	let mut batch = DBTransaction::new();
+	batch.put(COL_DIFFS, BLOCKS_KEY, &rlp::encode_list(blocks));
+	progress.write(batch)?;
+	progress.flush()?;
+	info!("Keeping {} nodes", marks.marked.get());
+
+	Ok(())
+}
+
+fn encode_number(number: BlockNumber) -> [u8; 8] {
+	let mut key = [0u8; 8];
+	BigEndian::write_u64(&mut key, number);
+	key
+}
+
+/// Set of marked state nodes, stored in a database.
+struct Marks<'a> {
+	db: &'a KeyValueDB,
+	/// Number of nodes marked so far.
+	marked: Cell<usize>,
+}
+
+impl<'a> Marks<'a> {
+	fn contains(&self, key: &H256) -> Result<bool, Error> {
+		Ok(self.db.get(COL_MARKED, key)?.is_some())
+	}
+
+	/// Marks given node. Returns false if it was marked already.
+	fn insert(&self, key: &H256) -> Result<bool, Error> {
+		if self.contains(key)? {
+			return Ok(false);
+		}
+
+		let mut batch = DBTransaction::new();
+		batch.put(COL_MARKED, key, &[]);
+		self.db.write_buffered(batch);
+
+		self.marked.set(self.marked.get() + 1);
+		if self.marked.get() % BATCH_SIZE == 0 {
+			self.db.flush()?;
+		}
+
+		Ok(true)
+	}
+}
+
+/// Keys of state nodes added and removed by a block.
+#[derive(Default)]
+struct StateDiff {
+	inserts: Vec<H256>,
+	deletes: Vec<H256>,
+}
+
+impl Encodable for StateDiff {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(2);
+		s.append_list(&self.inserts);
+		s.append_list(&self.deletes);
+	}
+}
+
+impl Decodable for StateDiff {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(StateDiff {
+			inserts: rlp.list_at(0)?,
+			deletes: rlp.list_at(1)?,
+		})
+	}
+}
+
+/// Reference to a trie node.
+#[derive(Debug, Clone, PartialEq)]
+enum NodeRef {
+	/// Node stored in the database under its hash.
+	Hash(H256),
+	/// Node encoded inline in its parent.
+	Inline(Vec<u8>),
+}
+
+impl NodeRef {
+	fn from_raw(raw: &[u8]) -> Option<NodeRef> {
+		if let Some(hash) = RlpCodec::try_decode_hash(raw) {
+			Some(NodeRef::Hash(hash))
+		} else if RlpCodec::is_empty_node(raw) {
+			None
+		} else {
+			Some(NodeRef::Inline(raw.to_vec()))
+		}
+	}
+}
+
+/// Trie of the state.
+#[derive(Clone, Copy)]
+enum TrieKind {
+	Accounts,
+	/// Storage of the account with given address hash.
+	Storage(H256),
+}
+
+impl TrieKind {
+	fn db_key(&self, hash: &H256) -> H256 {
+		match *self {
+			TrieKind::Accounts => *hash,
+			TrieKind::Storage(ref address_hash) => combine_key(address_hash, hash),
+		}
+	}
+}
+
+/// Decoded trie node.
+#[derive(Default)]
+struct Expanded {
+	/// Database key of the node, if it's not inline.
+	key: Option<H256>,
+	/// Children with their paths.
+	children: Vec<(Vec<u8>, NodeRef)>,
+	/// Leaf with its full path.
+	leaf: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Reads raw state nodes from the database.
+struct StateNodes<'a> {
+	db: &'a KeyValueDB,
+}
+
+impl<'a> StateNodes<'a> {
+	fn node(&self, key: &H256) -> Result<DBValue, Error> {
+		match self.db.get(COL_STATE, key)? {
+			Some(value) => Ok(value),
+			None => Err(TrieError::IncompleteDatabase(*key).into()),
+		}
+	}
+
+	fn expand(&self, kind: TrieKind, node: &NodeRef, path: &[u8]) -> Result<Expanded, Error> {
+		let (key, data) = match *node {
+			NodeRef::Hash(ref hash) => {
+				let key = kind.db_key(hash);
+				(Some(key), self.node(&key)?.to_vec())
+			},
+			NodeRef::Inline(ref data) => (None, data.clone()),
+		};
+
+		let decoded = RlpCodec::decode(&data)
+			.map_err(|e| TrieError::DecoderError(key.unwrap_or_default(), e))?;
+
+		let mut expanded = Expanded { key, ..Default::default() };
+		match decoded {
+			Node::Empty => {},
+			Node::Leaf(partial, value) => {
+				let mut leaf_path = path.to_vec();
+				leaf_path.extend((0..partial.len()).map(|i| partial.at(i)));
+				expanded.leaf = Some((leaf_path, value.to_vec()));
+			},
+			Node::Extension(partial, child) => {
+				if let Some(child) = NodeRef::from_raw(child) {
+					let mut child_path = path.to_vec();
+					child_path.extend((0..partial.len()).map(|i| partial.at(i)));
+					expanded.children.push((child_path, child));
+				}
+			},
+			Node::Branch(children, _) => {
+				for (nibble, child) in children.iter().enumerate() {
+					if let Some(child) = NodeRef::from_raw(child) {
+						let mut child_path = path.to_vec();
+						child_path.push(nibble as u8);
+						expanded.children.push((child_path, child));
+					}
+				}
+			},
+		}
+
+		Ok(expanded)
+	}
+
+	/// Marks all nodes of the state with given root, including storage and code.
+	fn mark_state(&self, root: &H256, marks: &Marks) -> Result<(), Error> {
+		self.mark_trie(TrieKind::Accounts, root, marks, Some(&mut |path: Vec<u8>, value: Vec<u8>| -> Result<(), Error> {
+			let address_hash = path_to_hash(&path)?;
+			let account: BasicAccount = rlp::decode(&value)?;
+			self.mark_trie(TrieKind::Storage(address_hash), &account.storage_root, marks, None)?;
+			if account.code_hash != KECCAK_EMPTY {
+				marks.insert(&combine_key(&address_hash, &account.code_hash))?;
+			}
+			Ok(())
+		}))
+	}
+
+	/// Marks all nodes of the trie with given root, optionally visiting its leaves.
+	fn mark_trie(&self, kind: TrieKind, root: &H256, marks: &Marks, mut on_leaf: Option<&mut FnMut(Vec<u8>, Vec<u8>) -> Result<(), Error>>) -> Result<(), Error> {
+		if *root == KECCAK_NULL_RLP {
+			return Ok(());
+		}
+
+		let mut stack = vec![(Vec::new(), NodeRef::Hash(*root))];
+		while let Some((path, node)) = stack.pop() {
+			// subtrees of marked nodes are marked already, but the same subtree may hold
+			// different leaves when found under a different path.
+			if let NodeRef::Hash(ref hash) = node {
+				if !marks.insert(&kind.db_key(hash))? && on_leaf.is_none() {
+					continue;
+				}
+			}
+
+			let expanded = self.expand(kind, &node, &path)?;
+			if let (Some(on_leaf), Some((path, value))) = (on_leaf.as_mut(), expanded.leaf) {
+				on_leaf(path, value)?;
+			}
+			stack.extend(expanded.children);
+		}
+
+		Ok(())
+	}
+
+	/// Collects state nodes added and removed between two states.
+	fn diff_state(&self, old_root: &H256, new_root: &H256, diff: &mut StateDiff) -> Result<(), Error> {
+		let (old_accounts, new_accounts) = self.diff_trie(TrieKind::Accounts, old_root, new_root, diff)?;
+
+		let paths: BTreeSet<_> = old_accounts.keys().chain(new_accounts.keys()).collect();
+		for path in paths {
+			let (old, new) = (old_accounts.get(path), new_accounts.get(path));
+			if old == new {
+				continue;
+			}
+
+			let address_hash = path_to_hash(path)?;
+			let decode = |value: Option<&Vec<u8>>| -> Result<BasicAccount, Error> {
+				match value {
+					Some(value) => Ok(rlp::decode(value)?),
+					None => Ok(BasicAccount {
+						nonce: 0.into(),
+						balance: 0.into(),
+						storage_root: KECCAK_NULL_RLP,
+						code_hash: KECCAK_EMPTY,
+					}),
+				}
+			};
+			let (old, new) = (decode(old)?, decode(new)?);
+
+			self.diff_trie(TrieKind::Storage(address_hash), &old.storage_root, &new.storage_root, diff)?;
+			if old.code_hash != new.code_hash {
+				if old.code_hash != KECCAK_EMPTY {
+					diff.deletes.push(combine_key(&address_hash, &old.code_hash));
+				}
+				if new.code_hash != KECCAK_EMPTY {
+					diff.inserts.push(combine_key(&address_hash, &new.code_hash));
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Walks both tries in parallel, skipping subtrees which are the same in both of them.
+	/// Returns leaves of the differing parts of the tries.
+	fn diff_trie(
+		&self,
+		kind: TrieKind,
+		old_root: &H256,
+		new_root: &H256,
+		diff: &mut StateDiff,
+	) -> Result<(BTreeMap<Vec<u8>, Vec<u8>>, BTreeMap<Vec<u8>, Vec<u8>>), Error> {
+		let mut old = BTreeMap::new();
+		let mut new = BTreeMap::new();
+		let mut old_leaves = BTreeMap::new();
+		let mut new_leaves = BTreeMap::new();
+
+		if old_root == new_root {
+			return Ok((old_leaves, new_leaves));
+		}
+		if *old_root != KECCAK_NULL_RLP {
+			old.insert(Vec::new(), NodeRef::Hash(*old_root));
+		}
+		if *new_root != KECCAK_NULL_RLP {
+			new.insert(Vec::new(), NodeRef::Hash(*new_root));
+		}
+
+		// nodes are visited ordered by depth, so that nodes at the same path in both tries
+		// are compared before either of them is expanded.
+		let mut pending = BTreeSet::new();
+		pending.insert((0, Vec::new()));
+
+		while let Some((depth, path)) = pending.iter().next().cloned() {
+			pending.remove(&(depth, path.clone()));
+
+			let (old_node, new_node) = (old.remove(&path), new.remove(&path));
+			if old_node.is_some() && old_node == new_node {
+				continue;
+			}
+
+			let sides = vec![
+				(old_node, &mut old, &mut old_leaves, &mut diff.deletes),
+				(new_node, &mut new, &mut new_leaves, &mut diff.inserts),
+			];
+			for (node, nodes, leaves, keys) in sides {
+				let node = match node {
+					Some(node) => node,
+					None => continue,
+				};
+
+				let expanded = self.expand(kind, &node, &path)?;
+				keys.extend(expanded.key);
+				leaves.extend(expanded.leaf);
+				for (child_path, child) in expanded.children {
+					pending.insert((child_path.len(), child_path.clone()));
+					nodes.insert(child_path, child);
+				}
+			}
+		}
+
+		Ok((old_leaves, new_leaves))
+	}
+}
+
+fn path_to_hash(path: &[u8]) -> Result<H256, Error> {
+	if path.len() != 64 {
+		return Err(format!("Invalid account path length: {}", path.len()).into());
+	}
+
+	let mut hash = H256::default();
+	for (byte, nibbles) in hash.iter_mut().zip(path.chunks(2)) {
+		*byte = (nibbles[0] << 4) | nibbles[1];
+	}
+
+	Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::ops::Range;
+	use std::sync::Arc;
+	use account_db::{AccountDB, AccountDBMut};
+	use basic_account::BasicAccount;
+	use db::{COL_STATE, NUM_COLUMNS};
+	use ethereum_types::H256;
+	use ethtrie::{SecTrieDBMut, TrieDB, TrieDBMut};
+	use hash::{keccak, KECCAK_EMPTY, KECCAK_NULL_RLP};
+	use hashdb::HashDB;
+	use journaldb::{self, Algorithm, JournalDB};
+	use keccak_hasher::KeccakHasher;
+	use kvdb::{DBTransaction, KeyValueDB};
+	use kvdb_memorydb;
+	use rlp;
+	use trie::{Trie, TrieMut};
+	use super::{
+		convert_archive, is_converting, mark, encode_number, RetainedBlock, StateNodes,
+		CONVERSION_KEY, COL_MARKED, COL_DIFFS, NUM_PROGRESS_COLUMNS,
+	};
+
+	/// Changes balance, storage and code of some accounts, returns the new state root.
+	fn tick(db: &mut HashDB<KeccakHasher>, root: H256, era: u64) -> H256 {
+		let mut accounts = Vec::new();
+		for i in (0..20u8).filter(|i| *i as u64 % (era + 1) == 0) {
+			let address_hash = keccak(&[i]);
+			let value = match root == KECCAK_NULL_RLP {
+				true => None,
+				false => TrieDB::new(&*db, &root).unwrap().get(&address_hash).unwrap(),
+			};
+			let account = match value {
+				Some(value) => rlp::decode(&value).unwrap(),
+				None => BasicAccount {
+					nonce: 0.into(),
+					balance: 0.into(),
+					storage_root: KECCAK_NULL_RLP,
+					code_hash: KECCAK_EMPTY,
+				},
+			};
+			accounts.push((address_hash, account));
+		}
+
+		for &mut (address_hash, ref mut account) in &mut accounts {
+			account.balance = account.balance + era.into();
+			let mut account_db = AccountDBMut::from_hash(&mut *db, address_hash);
+			if account.code_hash == KECCAK_EMPTY && era % 2 == 0 {
+				account.code_hash = account_db.insert(&[era as u8; 40]);
+			}
+
+			let mut storage_root = account.storage_root;
+			{
+				let mut storage = match storage_root == KECCAK_NULL_RLP {
+					true => SecTrieDBMut::new(&mut account_db, &mut storage_root),
+					false => SecTrieDBMut::from_existing(&mut account_db, &mut storage_root).unwrap(),
+				};
+				storage.insert(&[era as u8], &[era as u8 + 1; 33]).unwrap();
+				storage.insert(&[0], &[era as u8; 3]).unwrap();
+			}
+			account.storage_root = storage_root;
+		}
+
+		let mut root = root;
+		{
+			let mut trie = match root == KECCAK_NULL_RLP {
+				true => TrieDBMut::new(&mut *db, &mut root),
+				false => TrieDBMut::from_existing(&mut *db, &mut root).unwrap(),
+			};
+			for (address_hash, account) in accounts {
+				trie.insert(&address_hash, &rlp::encode(&account)).unwrap();
+			}
+		}
+
+		root
+	}
+
+	/// Checks that every account, storage item and code of the state is available.
+	fn check_state(db: &HashDB<KeccakHasher>, root: &H256) {
+		let trie = TrieDB::new(db, root).unwrap();
+		for item in trie.iter().unwrap() {
+			let (address_hash, value) = item.unwrap();
+			let account: BasicAccount = rlp::decode(&value).unwrap();
+			let account_db = AccountDB::from_hash(db, H256::from_slice(&address_hash));
+
+			let storage = TrieDB::new(&account_db, &account.storage_root).unwrap();
+			for item in storage.iter().unwrap() {
+				item.unwrap();
+			}
+
+			if account.code_hash != KECCAK_EMPTY {
+				assert!(account_db.get(&account.code_hash).is_some());
+			}
+		}
+	}
+
+	fn commit(journal_db: &mut Box<JournalDB>, db: &Arc<KeyValueDB>, era: u64, canonical: Option<u64>) {
+		let mut batch = DBTransaction::new();
+		journal_db.journal_under(&mut batch, era, &H256::from(era)).unwrap();
+		if let Some(canonical) = canonical {
+			journal_db.mark_canonical(&mut batch, canonical, &H256::from(canonical)).unwrap();
+		}
+		db.write(batch).unwrap();
+	}
+
+	/// Creates an archive database with the state of 10 blocks, returns their state roots.
+	fn archive() -> (Arc<KeyValueDB>, Vec<H256>) {
+		let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+		let mut archive = journaldb::new(db.clone(), Algorithm::Archive, COL_STATE);
+
+		let mut roots = Vec::new();
+		let mut root = KECCAK_NULL_RLP;
+		for era in 0..10 {
+			root = tick(archive.as_hashdb_mut(), root, era);
+			commit(&mut archive, &db, era, None);
+			roots.push(root);
+		}
+
+		(db, roots)
+	}
+
+	fn retained(roots: &[H256], numbers: Range<u64>) -> Vec<RetainedBlock> {
+		numbers.map(|number| RetainedBlock {
+			number,
+			hash: H256::from(number),
+			state_root: roots[number as usize],
+		}).collect()
+	}
+
+	/// Checks that the state of blocks 6..10 is kept and journaled.
+	fn check_converted(db: &Arc<KeyValueDB>, roots: &[H256]) {
+		let fast = journaldb::new(db.clone(), Algorithm::OverlayRecent, COL_STATE);
+		assert_eq!(fast.earliest_era(), Some(7));
+		assert_eq!(fast.latest_era(), Some(9));
+		for root in &roots[6..] {
+			check_state(fast.as_hashdb(), root);
+		}
+		assert!(TrieDB::new(fast.as_hashdb(), &roots[3]).is_err());
+		assert!(!is_converting(&**db).unwrap());
+	}
+
+	#[test]
+	fn archive_to_overlay_recent() {
+		let (db, roots) = archive();
+		let progress = kvdb_memorydb::create(NUM_PROGRESS_COLUMNS.unwrap());
+		let blocks = retained(&roots, 6..10);
+		convert_archive(&db, &progress, &blocks).unwrap();
+		check_converted(&db, &roots);
+
+		// converting again is a no-op.
+		convert_archive(&db, &progress, &blocks).unwrap();
+
+		// keep importing blocks on top of the rebuilt journal.
+		let mut fast = journaldb::new(db.clone(), Algorithm::OverlayRecent, COL_STATE);
+		let mut root = roots[9];
+		for era in 10..16 {
+			root = tick(fast.as_hashdb_mut(), root, era);
+			commit(&mut fast, &db, era, Some(era - 3));
+			check_state(fast.as_hashdb(), &root);
+		}
+
+		let fast = journaldb::new(db.clone(), Algorithm::OverlayRecent, COL_STATE);
+		check_state(fast.as_hashdb(), &root);
+	}
+
+	#[test]
+	fn interrupted_sweep_is_resumed() {
+		let (db, roots) = archive();
+		let progress = kvdb_memorydb::create(NUM_PROGRESS_COLUMNS.unwrap());
+		let blocks = retained(&roots, 6..10);
+
+		// interrupt the conversion halfway through the sweep.
+		mark(&StateNodes { db: &*db }, &progress, &blocks).unwrap();
+		let mut batch = DBTransaction::new();
+		batch.put(COL_STATE, CONVERSION_KEY, &rlp::encode_list(&blocks));
+		let unmarked: Vec<_> = db.iter(COL_STATE)
+			.filter(|&(ref key, _)| key.len() == 32 && progress.get(COL_MARKED, key).unwrap().is_none())
+			.map(|(key, _)| key)
+			.collect();
+		assert!(!unmarked.is_empty());
+		for key in &unmarked[..unmarked.len() / 2] {
+			batch.delete(COL_STATE, key);
+		}
+		db.write(batch).unwrap();
+		assert!(is_converting(&**db).unwrap());
+
+		// blocks of the interrupted conversion are kept, even if others are given.
+		convert_archive(&db, &progress, &retained(&roots, 8..10)).unwrap();
+		check_converted(&db, &roots);
+	}
+
+	#[test]
+	fn interrupted_marking_is_restarted() {
+		let (db, roots) = archive();
+		let progress = kvdb_memorydb::create(NUM_PROGRESS_COLUMNS.unwrap());
+
+		// the root is marked, but none of the nodes below it.
+		let mut batch = DBTransaction::new();
+		batch.put(COL_MARKED, &roots[6], &[]);
+		batch.put(COL_DIFFS, &encode_number(7), &[0xc0]);
+		progress.write(batch).unwrap();
+
+		convert_archive(&db, &progress, &retained(&roots, 6..10)).unwrap();
+		check_converted(&db, &roots);
+	}
+}
@@ -156,6 +156,36 @@ pub fn from_fat_rlp(
 		return Ok((ACC_EMPTY, None));
 	}
 
+	let (mut acc, new_code, storage) = decode_fat_rlp(acct_db, rlp)?;
+
+	{
+		let mut storage_trie = if storage_root.is_zero() {
+			TrieDBMut::new(acct_db, &mut storage_root)
+		} else {
+			TrieDBMut::from_existing(acct_db, &mut storage_root)?
+		};
+		for (k, v) in storage {
+			storage_trie.insert(&k, &v)?;
+		}
+	}
+
+	acc.storage_root = storage_root;
+	Ok((acc, new_code))
+}
+
+// decode a fat rlp without rebuilding the storage trie.
+// returns the account structure with an empty storage root, its newly recovered
+// code, if it exists, and the storage records.
+pub fn decode_fat_rlp(
+	acct_db: &mut AccountDBMut,
+	rlp: Rlp,
+) -> Result<(BasicAccount, Option<Bytes>, Vec<(Bytes, Bytes)>), Error> {
+
+	// check for special case of empty account.
+	if rlp.is_empty() {
+		return Ok((ACC_EMPTY, None, Vec::new()));
+	}
+
 	let nonce = rlp.val_at(0)?;
 	let balance = rlp.val_at(1)?;
 	let code_state: CodeState = {
@@ -179,29 +209,23 @@ pub fn from_fat_rlp(
 		}
 	};
 
-	{
-		let mut storage_trie = if storage_root.is_zero() {
-			TrieDBMut::new(acct_db, &mut storage_root)
-		} else {
-			TrieDBMut::from_existing(acct_db, &mut storage_root)?
-		};
-		let pairs = rlp.at(4)?;
-		for pair_rlp in pairs.iter() {
-			let k: Bytes  = pair_rlp.val_at(0)?;
-			let v: Bytes = pair_rlp.val_at(1)?;
+	let pairs = rlp.at(4)?;
+	let mut storage = Vec::with_capacity(pairs.item_count()?);
+	for pair_rlp in pairs.iter() {
+		let k: Bytes  = pair_rlp.val_at(0)?;
+		let v: Bytes = pair_rlp.val_at(1)?;
 
-			storage_trie.insert(&k, &v)?;
-		}
+		storage.push((k, v));
 	}
 
 	let acc = BasicAccount {
 		nonce: nonce,
 		balance: balance,
-		storage_root: storage_root,
+		storage_root: KECCAK_NULL_RLP,
 		code_hash: code_hash,
 	};
 
-	Ok((acc, new_code))
+	Ok((acc, new_code, storage))
 }
 
 #[cfg(test)]
@@ -47,6 +47,8 @@ pub enum Error {
 	UnrecognizedCodeState(u8),
 	/// Restoration aborted.
 	RestorationAborted,
+	/// Storage of accounts spanning several chunks was not inserted.
+	UnfinishedSplitAccounts(Vec<H256>),
 	/// Trie error.
 	Trie(TrieError),
 	/// Decoder error.
@@ -65,6 +67,8 @@ pub enum Error {
 	BadEpochProof(u64),
 	/// Wrong chunk format.
 	WrongChunkFormat(String),
+	/// Chunk data doesn't match its hash in the manifest.
+	ChunkHashMismatch(H256, H256),
 }
 
 impl fmt::Display for Error {
@@ -82,6 +86,7 @@ impl fmt::Display for Error {
 			Error::MissingCode(ref missing) => write!(f, "Incomplete snapshot: {} contract codes not found.", missing.len()),
 			Error::UnrecognizedCodeState(state) => write!(f, "Unrecognized code encoding ({})", state),
 			Error::RestorationAborted => write!(f, "Snapshot restoration aborted."),
+			Error::UnfinishedSplitAccounts(ref accounts) => write!(f, "Incomplete restoration: storage of {} accounts not inserted.", accounts.len()),
 			Error::Io(ref err) => err.fmt(f),
 			Error::Decoder(ref err) => err.fmt(f),
 			Error::Trie(ref err) => err.fmt(f),
@@ -91,6 +96,7 @@ impl fmt::Display for Error {
 			Error::SnapshotsUnsupported => write!(f, "Snapshots unsupported by consensus engine."),
 			Error::BadEpochProof(i) => write!(f, "Bad epoch proof for transition to epoch {}", i),
 			Error::WrongChunkFormat(ref msg) => write!(f, "Wrong chunk format: {}", msg),
+			Error::ChunkHashMismatch(ref expected, ref found) => write!(f, "Mismatched chunk hash. Expected {:?}, got {:?}", expected, found),
 		}
 	}
 }
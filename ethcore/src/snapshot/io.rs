@@ -27,12 +27,41 @@ use std::path::{Path, PathBuf};
 
 use bytes::Bytes;
 use ethereum_types::H256;
-use rlp::{RlpStream, Rlp};
+use rlp::{RlpStream, Rlp, Encodable, Decodable, DecoderError};
 
 use super::ManifestData;
 
 const SNAPSHOT_VERSION: u64 = 2;
 
+/// Name of the file listing the state parts of a loose snapshot.
+const STATE_PARTS_FILE: &'static str = "STATE_PARTS";
+
+/// State chunks created from a single subpart of the account trie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatePart {
+	/// Root of the account subtrie the chunks were created from.
+	pub root: H256,
+	/// Hashes of the chunks.
+	pub chunks: Vec<H256>,
+}
+
+impl Encodable for StatePart {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(2);
+		s.append(&self.root);
+		s.append_list(&self.chunks);
+	}
+}
+
+impl Decodable for StatePart {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(StatePart {
+			root: rlp.val_at(0)?,
+			chunks: rlp.list_at(1)?,
+		})
+	}
+}
+
 /// Something which can write snapshots.
 /// Writing the same chunk multiple times will lead to implementation-defined
 /// behavior, and is not advised.
@@ -43,6 +72,16 @@ pub trait SnapshotWriter {
 	/// Write a compressed block chunk.
 	fn write_block_chunk(&mut self, hash: H256, chunk: &[u8]) -> io::Result<()>;
 
+	/// Write a compressed state chunk taken from a previous snapshot.
+	fn reuse_state_chunk(&mut self, hash: H256, previous: &SnapshotReader) -> io::Result<()> {
+		let chunk = previous.chunk(hash)?;
+		self.write_state_chunk(hash, &chunk)
+	}
+
+	/// Write the state parts, in order of the account trie subparts, so that
+	/// the next snapshot can reuse the chunks of unchanged parts.
+	fn write_state_parts(&mut self, parts: &[StatePart]) -> io::Result<()>;
+
 	/// Complete writing. The manifest's chunk lists must be consistent
 	/// with the chunks written.
 	fn finish(self, manifest: ManifestData) -> io::Result<()> where Self: Sized;
@@ -66,6 +105,7 @@ pub struct PackedWriter {
 	file: File,
 	state_hashes: Vec<ChunkInfo>,
 	block_hashes: Vec<ChunkInfo>,
+	state_parts: Vec<StatePart>,
 	cur_len: u64,
 }
 
@@ -76,6 +116,7 @@ impl PackedWriter {
 			file: File::create(path)?,
 			state_hashes: Vec::new(),
 			block_hashes: Vec::new(),
+			state_parts: Vec::new(),
 			cur_len: 0,
 		})
 	}
@@ -102,17 +143,24 @@ impl SnapshotWriter for PackedWriter {
 		Ok(())
 	}
 
+	fn write_state_parts(&mut self, parts: &[StatePart]) -> io::Result<()> {
+		self.state_parts = parts.to_vec();
+		Ok(())
+	}
+
 	fn finish(mut self, manifest: ManifestData) -> io::Result<()> {
 		// we ignore the hashes fields of the manifest under the assumption that
 		// they are consistent with ours.
-		let mut stream = RlpStream::new_list(6);
+		// the state parts are appended last, so readers unaware of them ignore them.
+		let mut stream = RlpStream::new_list(7);
 		stream
 			.append(&SNAPSHOT_VERSION)
 			.append_list(&self.state_hashes)
 			.append_list(&self.block_hashes)
 			.append(&manifest.state_root)
 			.append(&manifest.block_number)
-			.append(&manifest.block_hash);
+			.append(&manifest.block_hash)
+			.append_list(&self.state_parts);
 
 		let manifest_rlp = stream.out();
 
@@ -172,6 +220,27 @@ impl SnapshotWriter for LooseWriter {
 		self.write_chunk(hash, chunk)
 	}
 
+	fn reuse_state_chunk(&mut self, hash: H256, previous: &SnapshotReader) -> io::Result<()> {
+		// chunks are immutable, so link them instead of copying when possible.
+		if let Some(path) = previous.chunk_path(hash) {
+			if fs::hard_link(&path, self.dir.join(format!("{:x}", hash))).is_ok() {
+				return Ok(());
+			}
+		}
+
+		let chunk = previous.chunk(hash)?;
+		self.write_chunk(hash, &chunk)
+	}
+
+	fn write_state_parts(&mut self, parts: &[StatePart]) -> io::Result<()> {
+		let mut stream = RlpStream::new();
+		stream.append_list(parts);
+
+		let mut file = File::create(self.dir.join(STATE_PARTS_FILE))?;
+		file.write_all(&stream.out())?;
+		Ok(())
+	}
+
 	fn finish(self, manifest: ManifestData) -> io::Result<()> {
 		let rlp = manifest.into_rlp();
 		let mut path = self.dir.clone();
@@ -192,6 +261,12 @@ pub trait SnapshotReader {
 	/// Get raw chunk data by hash. implementation defined behavior
 	/// if a chunk not in the manifest is requested.
 	fn chunk(&self, hash: H256) -> io::Result<Bytes>;
+
+	/// Get the state parts of this snapshot, empty if they were not recorded.
+	fn state_parts(&self) -> &[StatePart];
+
+	/// Get the path of the file holding only the given chunk, if there is one.
+	fn chunk_path(&self, _hash: H256) -> Option<PathBuf> { None }
 }
 
 /// Packed snapshot reader.
@@ -199,6 +274,7 @@ pub struct PackedReader {
 	file: File,
 	state_hashes: HashMap<H256, (u64, u64)>, // len, offset
 	block_hashes: HashMap<H256, (u64, u64)>, // len, offset
+	state_parts: Vec<StatePart>,
 	manifest: ManifestData,
 }
 
@@ -261,10 +337,17 @@ impl PackedReader {
 			block_hash: rlp.val_at(4 + start)?,
 		};
 
+		let state_parts = if rlp.item_count()? > 5 + start {
+			rlp.list_at(5 + start)?
+		} else {
+			Vec::new()
+		};
+
 		Ok(Some(PackedReader {
 			file: file,
 			state_hashes: state.into_iter().map(|c| (c.0, (c.1, c.2))).collect(),
 			block_hashes: blocks.into_iter().map(|c| (c.0, (c.1, c.2))).collect(),
+			state_parts: state_parts,
 			manifest: manifest
 		}))
 	}
@@ -288,11 +371,16 @@ impl SnapshotReader for PackedReader {
 
 		Ok(buf)
 	}
+
+	fn state_parts(&self) -> &[StatePart] {
+		&self.state_parts
+	}
 }
 
 /// reader for "loose" snapshots
 pub struct LooseReader {
 	dir: PathBuf,
+	state_parts: Vec<StatePart>,
 	manifest: ManifestData,
 }
 
@@ -310,8 +398,19 @@ impl LooseReader {
 
 		dir.pop();
 
+		// snapshots taken before state parts were recorded don't have the file.
+		let state_parts = match File::open(dir.join(STATE_PARTS_FILE)) {
+			Ok(mut file) => {
+				let mut parts_buf = Vec::new();
+				file.read_to_end(&mut parts_buf)?;
+				Rlp::new(&parts_buf).as_list()?
+			},
+			Err(_) => Vec::new(),
+		};
+
 		Ok(LooseReader {
 			dir: dir,
+			state_parts: state_parts,
 			manifest: manifest,
 		})
 	}
@@ -329,6 +428,14 @@ impl SnapshotReader for LooseReader {
 		file.read_to_end(&mut buf)?;
 		Ok(buf)
 	}
+
+	fn state_parts(&self) -> &[StatePart] {
+		&self.state_parts
+	}
+
+	fn chunk_path(&self, hash: H256) -> Option<PathBuf> {
+		Some(self.dir.join(format!("{:x}", hash)))
+	}
 }
 
 #[cfg(test)]
@@ -337,7 +444,7 @@ mod tests {
 	use hash::keccak;
 
 	use snapshot::ManifestData;
-	use super::{SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, SNAPSHOT_VERSION};
+	use super::{SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, StatePart, SNAPSHOT_VERSION};
 
 	const STATE_CHUNKS: &'static [&'static [u8]] = &[b"dog", b"cat", b"hello world", b"hi", b"notarealchunk"];
 	const BLOCK_CHUNKS: &'static [&'static [u8]] = &[b"hello!", b"goodbye!", b"abcdefg", b"hijklmnop", b"qrstuvwxy", b"and", b"z"];
@@ -420,4 +527,56 @@ mod tests {
 			reader.chunk(hash.clone()).unwrap();
 		}
 	}
+
+	#[test]
+	fn state_parts_and_reused_chunks() {
+		let tempdir = TempDir::new("").unwrap();
+		let mut writer = LooseWriter::new(tempdir.path().join("old")).unwrap();
+
+		let state_hashes: Vec<_> = STATE_CHUNKS.iter().map(keccak).collect();
+		for (hash, chunk) in state_hashes.iter().zip(STATE_CHUNKS) {
+			writer.write_state_chunk(*hash, chunk).unwrap();
+		}
+
+		let parts = vec![
+			StatePart { root: keccak(b"first"), chunks: state_hashes[..2].to_vec() },
+			StatePart { root: keccak(b"second"), chunks: state_hashes[2..].to_vec() },
+		];
+		writer.write_state_parts(&parts).unwrap();
+
+		let manifest = ManifestData {
+			version: SNAPSHOT_VERSION,
+			state_hashes: state_hashes.clone(),
+			block_hashes: Vec::new(),
+			state_root: keccak(b"notarealroot"),
+			block_number: 12345678987654321,
+			block_hash: keccak(b"notarealblock"),
+		};
+		writer.finish(manifest.clone()).unwrap();
+
+		let previous = LooseReader::new(tempdir.path().join("old")).unwrap();
+		assert_eq!(previous.state_parts(), &parts[..]);
+
+		let path = tempdir.path().join("packed");
+		let mut packed = PackedWriter::new(&path).unwrap();
+		let mut loose = LooseWriter::new(tempdir.path().join("new")).unwrap();
+		for hash in &state_hashes {
+			packed.reuse_state_chunk(*hash, &previous).unwrap();
+			loose.reuse_state_chunk(*hash, &previous).unwrap();
+		}
+		packed.write_state_parts(&parts).unwrap();
+		loose.write_state_parts(&parts).unwrap();
+		packed.finish(manifest.clone()).unwrap();
+		loose.finish(manifest.clone()).unwrap();
+
+		let packed = PackedReader::new(&path).unwrap().unwrap();
+		let loose = LooseReader::new(tempdir.path().join("new")).unwrap();
+		for reader in &[&packed as &SnapshotReader, &loose] {
+			assert_eq!(reader.manifest(), &manifest);
+			assert_eq!(reader.state_parts(), &parts[..]);
+			for (hash, chunk) in state_hashes.iter().zip(STATE_CHUNKS) {
+				assert_eq!(&reader.chunk(*hash).unwrap()[..], *chunk);
+			}
+		}
+	}
 }
@@ -19,7 +19,7 @@
 //! Documentation of the format can be found at
 //! https://wiki.parity.io/Warp-Sync-Snapshot-Format
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::cmp;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -35,6 +35,7 @@ use ethereum_types::{H256, U256};
 use hashdb::HashDB;
 use keccak_hasher::KeccakHasher;
 use kvdb::DBValue;
+use memorydb::MemoryDB;
 use snappy;
 use bytes::Bytes;
 use parking_lot::Mutex;
@@ -46,7 +47,7 @@ use rlp::{RlpStream, Rlp};
 use bloom_journal::Bloom;
 use num_cpus;
 
-use self::io::SnapshotWriter;
+use self::io::{SnapshotReader, SnapshotWriter, StatePart};
 
 use super::state_db::StateDB;
 use super::state::Account as StateAccount;
@@ -57,7 +58,7 @@ use rand::{Rng, OsRng};
 pub use self::error::Error;
 
 pub use self::consensus::*;
-pub use self::service::{Service, DatabaseRestore};
+pub use self::service::{Service, DatabaseRestore, verify_snapshot};
 pub use self::traits::SnapshotService;
 pub use self::watcher::Watcher;
 pub use types::snapshot_manifest::ManifestData;
@@ -94,6 +95,12 @@ const STATE_CHUNK_VERSION: u64 = 2;
 const SNAPSHOT_SUBPARTS: usize = 16;
 /// Maximum number of snapshot subparts (must be a multiple of `SNAPSHOT_SUBPARTS`)
 const MAX_SNAPSHOT_SUBPARTS: usize = 256;
+/// Prefix of the storage records of restored accounts which may span several chunks.
+/// They are kept in the state column until all chunks are in.
+const SPLIT_STORAGE_PREFIX: &'static [u8] = b"split-storage";
+/// Number of storage records of a split account inserted before its partial storage trie is
+/// written out, bounding the memory used by large accounts.
+const SPLIT_STORAGE_BATCH: usize = 16 * 1024;
 
 /// Configuration for the Snapshot service
 #[derive(Debug, Clone, PartialEq)]
@@ -148,6 +155,8 @@ impl Progress {
 
 }
 /// Take a snapshot using the given blockchain, starting block hash, and database, writing into the given writer.
+/// State chunks of the account trie subparts which did not change since the `previous` snapshot
+/// are taken from it instead of being created again.
 pub fn take_snapshot<W: SnapshotWriter + Send>(
 	engine: &EthEngine,
 	chain: &BlockChain,
@@ -156,6 +165,7 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	writer: W,
 	p: &Progress,
 	processing_threads: usize,
+	previous: Option<&(SnapshotReader + Sync)>,
 ) -> Result<(), Error> {
 	let start_header = chain.block_header_data(&block_at)
 		.ok_or(Error::InvalidStartingBlock(BlockId::Hash(block_at)))?;
@@ -167,7 +177,22 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	let writer = Mutex::new(writer);
 	let chunker = engine.snapshot_components().ok_or(Error::SnapshotsUnsupported)?;
 	let snapshot_version = chunker.current_version();
-	let (state_hashes, block_hashes) = scope(|scope| -> Result<(Vec<H256>, Vec<H256>), Error> {
+
+	let part_roots = state_part_roots(state_db, &state_root)?;
+	// chunks of a different format can't be mixed into this snapshot.
+	let previous_parts = previous
+		.filter(|previous| previous.manifest().version == snapshot_version)
+		.map(|previous| previous.state_parts())
+		.filter(|parts| parts.len() == SNAPSHOT_SUBPARTS)
+		.unwrap_or(&[]);
+	let part_roots = &part_roots;
+
+	// parts are handed out to whichever thread is free. chunk boundaries only
+	// depend on the part, so the snapshot doesn't depend on the scheduling.
+	let next_part = AtomicUsize::new(0);
+	let next_part = &next_part;
+
+	let (state_parts, block_hashes) = scope(|scope| -> Result<(Vec<StatePart>, Vec<H256>), Error> {
 		let writer = &writer;
 		let block_guard = scope.spawn(move || chunk_secondary(chunker, chain, block_at, writer, p));
 
@@ -179,34 +204,59 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 		let mut state_guards = Vec::with_capacity(num_threads as usize);
 
 		for thread_idx in 0..num_threads {
-			let state_guard = scope.spawn(move || -> Result<Vec<H256>, Error> {
-				let mut chunk_hashes = Vec::new();
+			let state_guard = scope.spawn(move || -> Result<Vec<(usize, StatePart)>, Error> {
+				let mut parts = Vec::new();
+
+				loop {
+					let part = next_part.fetch_add(1, Ordering::SeqCst);
+					if part >= SNAPSHOT_SUBPARTS { break }
+
+					let root = part_roots.get(part).cloned().unwrap_or_default();
+					let reusable = previous_parts.get(part).filter(|previous_part| part_roots.len() == SNAPSHOT_SUBPARTS && previous_part.root == root);
 
-				for part in (thread_idx..SNAPSHOT_SUBPARTS).step_by(num_threads) {
-					debug!(target: "snapshot", "Chunking part {} in thread {}", part, thread_idx);
-					let mut hashes = chunk_state(state_db, &state_root, writer, p, Some(part))?;
-					chunk_hashes.append(&mut hashes);
+					let chunks = match (reusable, previous) {
+						(Some(previous_part), Some(previous)) => {
+							debug!(target: "snapshot", "Reusing {} chunks of unchanged part {} in thread {}", previous_part.chunks.len(), part, thread_idx);
+							for hash in &previous_part.chunks {
+								writer.lock().reuse_state_chunk(*hash, previous)?;
+							}
+							previous_part.chunks.clone()
+						},
+						_ => {
+							debug!(target: "snapshot", "Chunking part {} in thread {}", part, thread_idx);
+							chunk_state(state_db, &state_root, writer, p, Some(part))?
+						},
+					};
+
+					parts.push((part, StatePart { root, chunks }));
 				}
 
-				Ok(chunk_hashes)
+				Ok(parts)
 			});
 			state_guards.push(state_guard);
 		}
 
 		let block_hashes = block_guard.join()?;
-		let mut state_hashes = Vec::new();
+		let mut state_parts = Vec::with_capacity(SNAPSHOT_SUBPARTS);
 
 		for guard in state_guards {
-			let part_state_hashes = guard.join()?;
-			state_hashes.extend(part_state_hashes);
+			let thread_parts = guard.join()?;
+			state_parts.extend(thread_parts);
 		}
+		state_parts.sort_by_key(|&(part, _)| part);
 
 		debug!(target: "snapshot", "Took a snapshot of {} accounts", p.accounts.load(Ordering::SeqCst));
-		Ok((state_hashes, block_hashes))
+		Ok((state_parts.into_iter().map(|(_, state_part)| state_part).collect(), block_hashes))
 	})?;
 
+	let state_hashes: Vec<H256> = state_parts.iter().flat_map(|part| part.chunks.iter().cloned()).collect();
 	info!(target: "snapshot", "produced {} state chunks and {} block chunks.", state_hashes.len(), block_hashes.len());
 
+	// parts are only comparable between snapshots if they map to subtries of the account trie.
+	if part_roots.len() == SNAPSHOT_SUBPARTS {
+		writer.lock().write_state_parts(&state_parts)?;
+	}
+
 	let manifest_data = ManifestData {
 		version: snapshot_version,
 		state_hashes: state_hashes,
@@ -261,6 +311,33 @@ pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a Bloc
 	Ok(chunk_hashes)
 }
 
+/// Roots of the account subtries chunked as separate parts, indexed by part.
+/// Empty if the state trie root is not a branch node.
+fn state_part_roots(db: &HashDB<KeccakHasher>, root: &H256) -> Result<Vec<H256>, Error> {
+	// with 16 subparts every part covers the accounts under one child of the root branch.
+	let node = match db.get(root) {
+		Some(node) => node,
+		None => return Ok(Vec::new()),
+	};
+
+	let rlp = Rlp::new(&node);
+	if !rlp.is_list() || rlp.item_count()? != 17 {
+		return Ok(Vec::new());
+	}
+
+	(0..SNAPSHOT_SUBPARTS).map(|part| -> Result<H256, Error> {
+		let child = rlp.at(part)?;
+		Ok(if child.is_empty() {
+			KECCAK_NULL_RLP
+		} else if child.is_data() && child.size() == 32 {
+			child.as_val()?
+		} else {
+			// nodes shorter than a hash are inlined.
+			keccak(child.as_raw())
+		})
+	}).collect()
+}
+
 /// State trie chunker.
 struct StateChunker<'a> {
 	hashes: Vec<H256>,
@@ -383,13 +460,32 @@ pub fn chunk_state<'a>(db: &HashDB<KeccakHasher>, root: &H256, writer: &Mutex<Sn
 }
 
 /// Used to rebuild the state trie piece by piece.
+///
+/// Chunks can be prepared concurrently with `StateRebuilder::prepare` and committed
+/// in any order afterwards; `feed` does both at once. Once all chunks are committed,
+/// `commit_split_accounts` completes the accounts which may span several chunks.
 pub struct StateRebuilder {
 	db: Box<JournalDB>,
 	state_root: H256,
 	known_code: HashMap<H256, H256>, // code hashes mapped to first account with this code.
 	missing_code: HashMap<H256, Vec<H256>>, // maps code hashes to lists of accounts missing that code.
 	bloom: Bloom,
-	known_storage_roots: HashMap<H256, H256>, // maps account hashes to last known storage root. Only filled for last account per chunk.
+	split_accounts: HashMap<H256, BasicAccount>, // first and last accounts of chunks, inserted once their storage is complete.
+}
+
+/// A state chunk with the storage tries of its accounts rebuilt, ready to be
+/// committed into a `StateRebuilder`.
+pub struct PreparedStateChunk {
+	// storage trie nodes and inline code of the accounts.
+	nodes: MemoryDB<KeccakHasher>,
+	// account hashes and thin account RLPs of the accounts contained in this chunk only.
+	accounts: Vec<(H256, Bytes)>,
+	// first and last accounts, which may continue in other chunks, with their storage records.
+	split_accounts: Vec<(H256, BasicAccount, Vec<(Bytes, Bytes)>)>,
+	// new code that's become available. (code_hash, code, addr_hash)
+	new_code: Vec<(H256, Bytes, H256)>,
+	// accounts referring to code by hash. (addr_hash, code_hash)
+	code_refs: Vec<(H256, H256)>,
 }
 
 impl StateRebuilder {
@@ -401,34 +497,78 @@ impl StateRebuilder {
 			known_code: HashMap::new(),
 			missing_code: HashMap::new(),
 			bloom: StateDB::load_bloom(&*db),
-			known_storage_roots: HashMap::new(),
+			split_accounts: HashMap::new(),
 		}
 	}
 
 	/// Feed an uncompressed state chunk into the rebuilder.
 	pub fn feed(&mut self, chunk: &[u8], flag: &AtomicBool) -> Result<(), ::error::Error> {
+		let prepared = StateRebuilder::prepare(chunk, flag)?;
+		self.commit(prepared, flag)
+	}
+
+	/// Rebuild the accounts of an uncompressed state chunk and their storage,
+	/// independently of any rebuilder.
+	///
+	/// The storage of the first and last accounts, which may span multiple chunks,
+	/// is only decoded and rebuilt by `commit_split_accounts`.
+	pub fn prepare(chunk: &[u8], flag: &AtomicBool) -> Result<PreparedStateChunk, ::error::Error> {
 		let rlp = Rlp::new(chunk);
+		let mut prepared = PreparedStateChunk {
+			nodes: MemoryDB::new(),
+			accounts: Vec::with_capacity(rlp.item_count()?),
+			split_accounts: Vec::new(),
+			new_code: Vec::new(),
+			code_refs: Vec::new(),
+		};
+
+		rebuild_accounts(rlp, &mut prepared, flag)?;
+		Ok(prepared)
+	}
+
+	/// Commit a prepared state chunk into the account trie.
+	pub fn commit(&mut self, mut prepared: PreparedStateChunk, flag: &AtomicBool) -> Result<(), ::error::Error> {
 		let empty_rlp = StateAccount::new_basic(U256::zero(), U256::zero()).rlp();
-		let mut pairs = Vec::with_capacity(rlp.item_count()?);
-
-		// initialize the pairs vector with empty values so we have slots to write into.
-		pairs.resize(rlp.item_count()?, (H256::new(), Vec::new()));
-
-		let status = rebuild_accounts(
-			self.db.as_hashdb_mut(),
-			rlp,
-			&mut pairs,
-			&self.known_code,
-			&mut self.known_storage_roots,
-			flag
-		)?;
 
-		for (addr_hash, code_hash) in status.missing_code {
-			self.missing_code.entry(code_hash).or_insert_with(Vec::new).push(addr_hash);
+		for (key, (value, rc)) in prepared.nodes.drain() {
+			if rc > 0 {
+				self.db.as_hashdb_mut().emplace(key, value);
+			}
+		}
+
+		let backing = self.db.backing().clone();
+		let mut batch = backing.transaction();
+
+		// partial storage tries of accounts would be garbage once the accounts are complete,
+		// so the records are kept aside instead.
+		for (hash, acc, storage) in prepared.split_accounts {
+			for (key, value) in storage {
+				batch.put(::db::COL_STATE, &split_storage_key(&hash, &key), &value);
+			}
+			self.split_accounts.insert(hash, acc);
+		}
+
+		for (addr_hash, code_hash) in prepared.code_refs {
+			if !flag.load(Ordering::SeqCst) { return Err(Error::RestorationAborted.into()) }
+
+			// see if this code has already been included inline
+			match self.known_code.get(&code_hash) {
+				Some(&first_with) => {
+					// if so, load it from the database.
+					let code = AccountDB::from_hash(self.db.as_hashdb(), first_with)
+						.get(&code_hash)
+						.ok_or_else(|| Error::MissingCode(vec![first_with]))?;
+
+					// and write it again under a different mangled key
+					AccountDBMut::from_hash(self.db.as_hashdb_mut(), addr_hash).emplace(code_hash, code);
+				}
+				// if not, queue it up to be filled later
+				None => self.missing_code.entry(code_hash).or_insert_with(Vec::new).push(addr_hash),
+			}
 		}
 
 		// patch up all missing code. must be done after collecting all new missing code entries.
-		for (code_hash, code, first_with) in status.new_code {
+		for (code_hash, code, first_with) in prepared.new_code {
 			for addr_hash in self.missing_code.remove(&code_hash).unwrap_or_else(Vec::new) {
 				let mut db = AccountDBMut::from_hash(self.db.as_hashdb_mut(), addr_hash);
 				db.emplace(code_hash, DBValue::from_slice(&code));
@@ -437,8 +577,6 @@ impl StateRebuilder {
 			self.known_code.insert(code_hash, first_with);
 		}
 
-		let backing = self.db.backing().clone();
-
 		// batch trie writes
 		{
 			let mut account_trie = if self.state_root != KECCAK_NULL_RLP {
@@ -447,7 +585,7 @@ impl StateRebuilder {
 				TrieDBMut::new(self.db.as_hashdb_mut(), &mut self.state_root)
 			};
 
-			for (hash, thin_rlp) in pairs {
+			for (hash, thin_rlp) in prepared.accounts {
 				if !flag.load(Ordering::SeqCst) { return Err(Error::RestorationAborted.into()) }
 
 				if &thin_rlp[..] != &empty_rlp[..] {
@@ -458,7 +596,6 @@ impl StateRebuilder {
 		}
 
 		let bloom_journal = self.bloom.drain_journal();
-		let mut batch = backing.transaction();
 		StateDB::commit_bloom(&mut batch, bloom_journal)?;
 		self.db.inject(&mut batch)?;
 		backing.write_buffered(batch);
@@ -466,6 +603,80 @@ impl StateRebuilder {
 		Ok(())
 	}
 
+	/// Rebuild the storage of the accounts which may span several chunks and insert
+	/// them into the account trie. Must be called once all chunks are committed.
+	pub fn commit_split_accounts(&mut self, flag: &AtomicBool) -> Result<(), ::error::Error> {
+		let empty_rlp = StateAccount::new_basic(U256::zero(), U256::zero()).rlp();
+		let backing = self.db.backing().clone();
+		backing.flush()?;
+
+		let mut accounts: Vec<_> = self.split_accounts.drain().collect();
+		accounts.sort_by_key(|&(hash, _)| hash);
+
+		// every account is written on its own, so only one storage trie is in memory at a time.
+		for (hash, mut acc) in accounts {
+			if !flag.load(Ordering::SeqCst) { return Err(Error::RestorationAborted.into()) }
+
+			let prefix = split_storage_key(&hash, &[]);
+			// iterator may continue beyond values beginning with this prefix.
+			let mut records = backing.iter_from_prefix(::db::COL_STATE, &prefix)
+				.take_while(|&(ref key, _)| key.starts_with(&prefix));
+			acc.storage_root = KECCAK_NULL_RLP;
+
+			// the storage trie of a large account is written out in parts. this leaves behind the
+			// few nodes on the path to the last record of each part, which are changed by the next one.
+			let mut batch = loop {
+				if !flag.load(Ordering::SeqCst) { return Err(Error::RestorationAborted.into()) }
+
+				let mut batch = backing.transaction();
+				let mut inserted = 0;
+				{
+					let mut acct_db = AccountDBMut::from_hash(self.db.as_hashdb_mut(), hash);
+					let mut storage_trie = if acc.storage_root != KECCAK_NULL_RLP {
+						TrieDBMut::from_existing(&mut acct_db, &mut acc.storage_root)?
+					} else {
+						TrieDBMut::new(&mut acct_db, &mut acc.storage_root)
+					};
+
+					for (key, value) in records.by_ref().take(SPLIT_STORAGE_BATCH) {
+						storage_trie.insert(&key[prefix.len()..], &value)?;
+						batch.delete(::db::COL_STATE, &key);
+						inserted += 1;
+					}
+				}
+
+				if inserted < SPLIT_STORAGE_BATCH {
+					break batch;
+				}
+
+				self.db.inject(&mut batch)?;
+				backing.write_buffered(batch);
+			};
+
+			let thin_rlp = ::rlp::encode(&acc);
+			if &thin_rlp[..] != &empty_rlp[..] {
+				self.bloom.set(&*hash);
+			}
+
+			{
+				let mut account_trie = if self.state_root != KECCAK_NULL_RLP {
+					TrieDBMut::from_existing(self.db.as_hashdb_mut(), &mut self.state_root)?
+				} else {
+					TrieDBMut::new(self.db.as_hashdb_mut(), &mut self.state_root)
+				};
+				account_trie.insert(&hash, &thin_rlp)?;
+			}
+
+			let bloom_journal = self.bloom.drain_journal();
+			StateDB::commit_bloom(&mut batch, bloom_journal)?;
+			self.db.inject(&mut batch)?;
+			backing.write_buffered(batch);
+		}
+
+		trace!(target: "snapshot", "state root with split accounts: {:?}", self.state_root);
+		Ok(())
+	}
+
 	/// Finalize the restoration. Check for accounts missing code and make a dummy
 	/// journal entry.
 	/// Once all chunks have been fed, there should be nothing missing.
@@ -473,6 +684,16 @@ impl StateRebuilder {
 		let missing = self.missing_code.keys().cloned().collect::<Vec<_>>();
 		if !missing.is_empty() { return Err(Error::MissingCode(missing).into()) }
 
+		// storage records of split accounts are deleted as `commit_split_accounts` inserts them.
+		let backing = self.db.backing().clone();
+		backing.flush()?;
+		let unfinished = backing.iter_from_prefix(::db::COL_STATE, SPLIT_STORAGE_PREFIX)
+			.take_while(|&(ref key, _)| key.starts_with(SPLIT_STORAGE_PREFIX))
+			.map(|(key, _)| H256::from_slice(&key[SPLIT_STORAGE_PREFIX.len()..SPLIT_STORAGE_PREFIX.len() + 32]))
+			.chain(self.split_accounts.keys().cloned())
+			.collect::<BTreeSet<_>>();
+		if !unfinished.is_empty() { return Err(Error::UnfinishedSplitAccounts(unfinished.into_iter().collect()).into()) }
+
 		let mut batch = self.db.backing().transaction();
 		self.db.journal_under(&mut batch, era, &id)?;
 		self.db.backing().write_buffered(batch);
@@ -484,75 +705,57 @@ impl StateRebuilder {
 	pub fn state_root(&self) -> H256 { self.state_root }
 }
 
-#[derive(Default)]
-struct RebuiltStatus {
-	// new code that's become available. (code_hash, code, addr_hash)
-	new_code: Vec<(H256, Bytes, H256)>,
-	missing_code: Vec<(H256, H256)>, // accounts that are missing code.
-}
-
-// rebuild a set of accounts and their storage.
-// returns a status detailing newly-loaded code and accounts missing code.
+// rebuild a set of accounts and their storage, each storage trie starting out empty.
+// the storage of the first and last accounts is only decoded, as they may continue in other chunks.
 fn rebuild_accounts(
-	db: &mut HashDB<KeccakHasher>,
 	account_fat_rlps: Rlp,
-	out_chunk: &mut [(H256, Bytes)],
-	known_code: &HashMap<H256, H256>,
-	known_storage_roots: &mut HashMap<H256, H256>,
+	out: &mut PreparedStateChunk,
 	abort_flag: &AtomicBool,
-) -> Result<RebuiltStatus, ::error::Error> {
-	let mut status = RebuiltStatus::default();
-	for (account_rlp, out) in account_fat_rlps.into_iter().zip(out_chunk.iter_mut()) {
+) -> Result<(), ::error::Error> {
+	let last = account_fat_rlps.item_count()?.saturating_sub(1);
+	for (idx, account_rlp) in account_fat_rlps.into_iter().enumerate() {
 		if !abort_flag.load(Ordering::SeqCst) { return Err(Error::RestorationAborted.into()) }
 
 		let hash: H256 = account_rlp.val_at(0)?;
 		let fat_rlp = account_rlp.at(1)?;
-
-		let thin_rlp = {
-
-			// fill out the storage trie and code while decoding.
-			let (acc, maybe_code) = {
-				let mut acct_db = AccountDBMut::from_hash(db, hash);
-				let storage_root = known_storage_roots.get(&hash).cloned().unwrap_or(H256::zero());
-				account::from_fat_rlp(&mut acct_db, fat_rlp, storage_root)?
-			};
-
-			let code_hash = acc.code_hash.clone();
-			match maybe_code {
-				// new inline code
-				Some(code) => status.new_code.push((code_hash, code, hash)),
-				None => {
-					if code_hash != KECCAK_EMPTY {
-						// see if this code has already been included inline
-						match known_code.get(&code_hash) {
-							Some(&first_with) => {
-								// if so, load it from the database.
-								let code = AccountDB::from_hash(db, first_with)
-									.get(&code_hash)
-									.ok_or_else(|| Error::MissingCode(vec![first_with]))?;
-
-								// and write it again under a different mangled key
-								AccountDBMut::from_hash(db, hash).emplace(code_hash, code);
-							}
-							// if not, queue it up to be filled later
-							None => status.missing_code.push((hash, code_hash)),
-						}
-					}
-				}
+		let split = idx == 0 || idx == last;
+
+		// fill out the storage trie and code while decoding.
+		let (acc, maybe_code, storage) = {
+			let mut acct_db = AccountDBMut::from_hash(&mut out.nodes, hash);
+			match split {
+				true => account::decode_fat_rlp(&mut acct_db, fat_rlp)?,
+				false => {
+					let (acc, maybe_code) = account::from_fat_rlp(&mut acct_db, fat_rlp, H256::zero())?;
+					(acc, maybe_code, Vec::new())
+				},
 			}
-
-			::rlp::encode(&acc).into_vec()
 		};
 
-		*out = (hash, thin_rlp);
-	}
-	if let Some(&(ref hash, ref rlp)) = out_chunk.iter().last() {
-		known_storage_roots.insert(*hash, ::rlp::decode::<BasicAccount>(rlp)?.storage_root);
-	}
-	if let Some(&(ref hash, ref rlp)) = out_chunk.iter().next() {
-		known_storage_roots.insert(*hash, ::rlp::decode::<BasicAccount>(rlp)?.storage_root);
+		let code_hash = acc.code_hash.clone();
+		match maybe_code {
+			// new inline code
+			Some(code) => out.new_code.push((code_hash, code, hash)),
+			None if code_hash != KECCAK_EMPTY => out.code_refs.push((hash, code_hash)),
+			None => {},
+		}
+
+		match split {
+			true => out.split_accounts.push((hash, acc, storage)),
+			false => out.accounts.push((hash, ::rlp::encode(&acc).into_vec())),
+		}
 	}
-	Ok(status)
+
+	Ok(())
+}
+
+// key of a storage record of an account which may span several chunks.
+fn split_storage_key(addr_hash: &H256, key: &[u8]) -> Vec<u8> {
+	let mut db_key = Vec::with_capacity(SPLIT_STORAGE_PREFIX.len() + 32 + key.len());
+	db_key.extend_from_slice(SPLIT_STORAGE_PREFIX);
+	db_key.extend_from_slice(&addr_hash[..]);
+	db_key.extend_from_slice(key);
+	db_key
 }
 
 /// Proportion of blocks which we will verify `PoW` for.
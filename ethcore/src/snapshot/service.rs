@@ -23,7 +23,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-use super::{ManifestData, StateRebuilder, Rebuilder, RestorationStatus, SnapshotService, MAX_CHUNK_SIZE};
+use super::{ManifestData, StateRebuilder, PreparedStateChunk, Rebuilder, RestorationStatus, SnapshotService, MAX_CHUNK_SIZE};
 use super::io::{SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
 
 use blockchain::{BlockChain, BlockChainDB, BlockChainDBHandler};
@@ -48,7 +48,6 @@ struct Guard(bool, PathBuf);
 impl Guard {
 	fn new(path: PathBuf) -> Self { Guard(true, path) }
 
-	#[cfg(test)]
 	fn benign() -> Self { Guard(false, PathBuf::default()) }
 
 	fn disarm(mut self) { self.0 = false }
@@ -127,14 +126,17 @@ impl Restoration {
 	// feeds a state chunk, aborts early if `flag` becomes false.
 	fn feed_state(&mut self, hash: H256, chunk: &[u8], flag: &AtomicBool) -> Result<(), Error> {
 		if self.state_chunks_left.contains(&hash) {
-			let expected_len = snappy::decompressed_len(chunk)?;
-			if expected_len > MAX_CHUNK_SIZE {
-				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
-				return Err(::snapshot::Error::ChunkTooLarge.into());
-			}
-			let len = snappy::decompress_into(chunk, &mut self.snappy_buffer)?;
+			let prepared = prepare_state_chunk(chunk, flag)?;
+			self.commit_state(hash, chunk, prepared, flag)?;
+		}
+
+		Ok(())
+	}
 
-			self.state.feed(&self.snappy_buffer[..len], flag)?;
+	// commits a state chunk prepared with `prepare_state_chunk`.
+	fn commit_state(&mut self, hash: H256, chunk: &[u8], prepared: PreparedStateChunk, flag: &AtomicBool) -> Result<(), Error> {
+		if self.state_chunks_left.contains(&hash) {
+			self.state.commit(prepared, flag)?;
 
 			if let Some(ref mut writer) = self.writer.as_mut() {
 				writer.write_state_chunk(hash, chunk)?;
@@ -168,11 +170,13 @@ impl Restoration {
 	}
 
 	// finish up restoration.
-	fn finalize(mut self, engine: &EthEngine) -> Result<(), Error> {
+	fn finalize(mut self, engine: &EthEngine, flag: &AtomicBool) -> Result<(), Error> {
 		use trie::TrieError;
 
 		if !self.is_done() { return Ok(()) }
 
+		self.state.commit_split_accounts(flag)?;
+
 		// verify final state root.
 		let root = self.state.state_root();
 		if root != self.final_state_root {
@@ -200,6 +204,73 @@ impl Restoration {
 	}
 }
 
+// decompresses a state chunk and rebuilds its accounts, which doesn't need
+// the restoration and can be done for multiple chunks at once.
+fn prepare_state_chunk(chunk: &[u8], flag: &AtomicBool) -> Result<PreparedStateChunk, Error> {
+	let expected_len = snappy::decompressed_len(chunk)?;
+	if expected_len > MAX_CHUNK_SIZE {
+		trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
+		return Err(::snapshot::Error::ChunkTooLarge.into());
+	}
+
+	let raw = snappy::decompress(chunk)?;
+	StateRebuilder::prepare(&raw, flag)
+}
+
+/// Verify a snapshot by restoring it into the given database, which is left with
+/// the restored state and blocks but is never used as a client database.
+///
+/// Every chunk is checked against its hash in the manifest, the state is rebuilt
+/// and checked against the manifest's state root, and the block chunks are fed
+/// to the engine's rebuilder which checks the integrity of the chain.
+/// `chunks_done` is incremented after each verified chunk.
+pub fn verify_snapshot(
+	reader: &SnapshotReader,
+	engine: &EthEngine,
+	genesis: &[u8],
+	pruning: Algorithm,
+	db: Arc<BlockChainDB>,
+	chunks_done: &AtomicUsize,
+) -> Result<(), Error> {
+	let manifest = reader.manifest().clone();
+	let flag = AtomicBool::new(true);
+
+	let mut restoration = Restoration::new(RestorationParams {
+		manifest: manifest.clone(),
+		pruning: pruning,
+		db: db,
+		writer: None,
+		genesis: genesis,
+		guard: Guard::benign(),
+		engine: engine,
+	})?;
+
+	let chunks = manifest.state_hashes.iter().map(|hash| (hash, true))
+		.chain(manifest.block_hashes.iter().map(|hash| (hash, false)));
+
+	for (&hash, is_state) in chunks {
+		let chunk = reader.chunk(hash)?;
+
+		let found = keccak(&chunk);
+		if found != hash {
+			return Err(SnapshotError::ChunkHashMismatch(hash, found).into());
+		}
+
+		match is_state {
+			true => restoration.feed_state(hash, &chunk, &flag)?,
+			false => restoration.feed_blocks(hash, &chunk, engine, &flag)?,
+		}
+
+		chunks_done.fetch_add(1, Ordering::SeqCst);
+	}
+
+	restoration.db.key_value().flush()?;
+
+	// every chunk in the manifest was fed, so the restoration is done
+	// and finalizing runs all of the final checks.
+	restoration.finalize(engine, &flag)
+}
+
 /// Type alias for client io channel.
 pub type Channel = IoChannel<ClientIoMessage>;
 
@@ -374,8 +445,12 @@ impl Service {
 
 		let writer = LooseWriter::new(temp_dir.clone())?;
 
+		// chunks of state parts which didn't change are carried over from the current snapshot.
+		let previous = LooseReader::new(snapshot_dir.clone()).ok();
+
 		let guard = Guard::new(temp_dir.clone());
-		let res = client.take_snapshot(writer, BlockId::Number(num), &self.progress);
+		let res = client.take_snapshot(writer, BlockId::Number(num), &self.progress, previous.as_ref().map(|reader| reader as &(SnapshotReader + Sync)));
+		drop(previous);
 
 		self.taking_snapshot.store(false, Ordering::SeqCst);
 		if let Err(e) = res {
@@ -480,12 +555,15 @@ impl Service {
 		// Import previous chunks, continue if it fails
 		self.import_prev_chunks(&mut res, manifest).ok();
 
-		*self.status.lock() = RestorationStatus::Ongoing {
-			state_chunks: state_chunks as u32,
-			block_chunks: block_chunks as u32,
-			state_chunks_done: self.state_chunks.load(Ordering::SeqCst) as u32,
-			block_chunks_done: self.block_chunks.load(Ordering::SeqCst) as u32,
-		};
+		// the previous chunks may have completed and finalized the restoration already.
+		if res.is_some() {
+			*self.status.lock() = RestorationStatus::Ongoing {
+				state_chunks: state_chunks as u32,
+				block_chunks: block_chunks as u32,
+				state_chunks_done: self.state_chunks.load(Ordering::SeqCst) as u32,
+				block_chunks_done: self.block_chunks.load(Ordering::SeqCst) as u32,
+			};
+		}
 
 		Ok(())
 	}
@@ -539,7 +617,7 @@ impl Service {
 			return Ok(false);
 		};
 
-		self.feed_chunk_with_restoration(restoration, hash, &buffer, is_state)?;
+		self.feed_chunk_with_restoration(restoration, hash, &buffer, is_state, None)?;
 
 		trace!(target: "snapshot", "Fed chunk {:?}", hash);
 
@@ -556,7 +634,7 @@ impl Service {
 
 		// destroy the restoration before replacing databases and snapshot.
 		rest.take()
-			.map(|r| r.finalize(&*self.engine))
+			.map(|r| r.finalize(&*self.engine, &self.restoring_snapshot))
 			.unwrap_or(Ok(()))?;
 
 		self.replace_client_db()?;
@@ -587,8 +665,12 @@ impl Service {
 	/// Feed a chunk of either kind (block or state). no-op if no restoration or status is wrong.
 	fn feed_chunk(&self, hash: H256, chunk: &[u8], is_state: bool) {
 		// TODO: be able to process block chunks and state chunks at same time?
-		let mut restoration = self.restoration.lock();
-		match self.feed_chunk_with_restoration(&mut restoration, hash, chunk, is_state) {
+		let res = self.prepare_chunk(hash, chunk, is_state).and_then(|prepared| {
+			let mut restoration = self.restoration.lock();
+			self.feed_chunk_with_restoration(&mut restoration, hash, chunk, is_state, prepared)
+		});
+
+		match res {
 			Ok(()) |
 			Err(Error(SnapshotErrorKind::Snapshot(SnapshotError::RestorationAborted), _)) => (),
 			Err(e) => {
@@ -600,8 +682,20 @@ impl Service {
 		}
 	}
 
-	/// Feed a chunk with the Restoration
-	fn feed_chunk_with_restoration(&self, restoration: &mut Option<Restoration>, hash: H256, chunk: &[u8], is_state: bool) -> Result<(), Error> {
+	/// Prepare a state chunk still needed by the restoration without holding the
+	/// restoration lock, so that chunks fed from multiple threads are rebuilt concurrently.
+	fn prepare_chunk(&self, hash: H256, chunk: &[u8], is_state: bool) -> Result<Option<PreparedStateChunk>, Error> {
+		let needed = is_state && self.restoration.lock().as_ref()
+			.map_or(false, |rest| rest.state_chunks_left.contains(&hash));
+
+		match needed {
+			true => prepare_state_chunk(chunk, &self.restoring_snapshot).map(Some),
+			false => Ok(None),
+		}
+	}
+
+	/// Feed a chunk with the Restoration, using the prepared state chunk if there is one.
+	fn feed_chunk_with_restoration(&self, restoration: &mut Option<Restoration>, hash: H256, chunk: &[u8], is_state: bool, prepared: Option<PreparedStateChunk>) -> Result<(), Error> {
 		let (result, db) = {
 			match self.status() {
 				RestorationStatus::Inactive | RestorationStatus::Failed => {
@@ -615,9 +709,10 @@ impl Service {
 							None => return Ok(()),
 						};
 
-						(match is_state {
-							true => rest.feed_state(hash, chunk, &self.restoring_snapshot),
-							false => rest.feed_blocks(hash, chunk, &*self.engine, &self.restoring_snapshot),
+						(match (is_state, prepared) {
+							(true, Some(prepared)) => rest.commit_state(hash, chunk, prepared, &self.restoring_snapshot),
+							(true, None) => rest.feed_state(hash, chunk, &self.restoring_snapshot),
+							(false, _) => rest.feed_blocks(hash, chunk, &*self.engine, &self.restoring_snapshot),
 						}.map(|_| rest.is_done()), rest.db.clone())
 					};
 
@@ -150,7 +150,7 @@ pub fn snap(client: &Client) -> (Box<SnapshotReader>, TempDir) {
 	let progress = Default::default();
 
 	let hash = client.chain_info().best_block_hash;
-	client.take_snapshot(writer, BlockId::Hash(hash), &progress).unwrap();
+	client.take_snapshot(writer, BlockId::Hash(hash), &progress, None).unwrap();
 
 	let reader = PackedReader::new(&path).unwrap().unwrap();
 
@@ -196,6 +196,7 @@ pub fn restore(
 	}
 
 	trace!(target: "snapshot", "finalizing");
+	state.commit_split_accounts(&flag)?;
 	state.finalize(manifest.block_number, manifest.block_hash)?;
 	secondary.finalize(engine)
 }
@@ -22,7 +22,8 @@ use tempdir::TempDir;
 use client::{Client, BlockInfo};
 use ids::BlockId;
 use snapshot::service::{Service, ServiceParams};
-use snapshot::{self, ManifestData, SnapshotService};
+use snapshot::{self, ManifestData, Progress, SnapshotService};
+use snapshot::io::{LooseReader, LooseWriter, SnapshotReader};
 use spec::Spec;
 use test_helpers::{generate_dummy_client_with_spec_and_data, restoration_db_handler};
 
@@ -104,6 +105,49 @@ fn restored_is_equivalent() {
 	}
 }
 
+#[test]
+fn retaking_snapshot_reuses_state_parts() {
+	// block 21 only touches the accounts of its sender and of the contract it creates.
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, 21, 1, &[1.into()]);
+	let tempdir = TempDir::new("").unwrap();
+
+	let first_progress = Progress::default();
+	let writer = LooseWriter::new(tempdir.path().join("first")).unwrap();
+	client.take_snapshot(writer, BlockId::Number(20), &first_progress, None).unwrap();
+	let first = LooseReader::new(tempdir.path().join("first")).unwrap();
+
+	let second_progress = Progress::default();
+	let writer = LooseWriter::new(tempdir.path().join("second")).unwrap();
+	client.take_snapshot(writer, BlockId::Number(21), &second_progress, Some(&first)).unwrap();
+	let second = LooseReader::new(tempdir.path().join("second")).unwrap();
+
+	let (first_parts, second_parts) = (first.state_parts(), second.state_parts());
+	assert_eq!(first_parts.len(), snapshot::SNAPSHOT_SUBPARTS);
+	assert_eq!(second_parts.len(), snapshot::SNAPSHOT_SUBPARTS);
+
+	let changed: Vec<_> = (0..snapshot::SNAPSHOT_SUBPARTS)
+		.filter(|&part| first_parts[part].root != second_parts[part].root)
+		.collect();
+	assert!(!changed.is_empty());
+	assert!(changed.len() < snapshot::SNAPSHOT_SUBPARTS);
+
+	for (part, (first_part, second_part)) in first_parts.iter().zip(second_parts).enumerate() {
+		if changed.contains(&part) {
+			assert!(first_part.chunks != second_part.chunks);
+		} else {
+			assert_eq!(first_part.chunks, second_part.chunks);
+		}
+		for hash in &second_part.chunks {
+			assert!(second.chunk(*hash).is_ok());
+		}
+	}
+
+	// the accounts of unchanged parts are not chunked again.
+	assert!(second_progress.accounts() < first_progress.accounts());
+	let part_hashes: Vec<_> = second_parts.iter().flat_map(|part| part.chunks.iter().cloned()).collect();
+	assert_eq!(part_hashes, second.manifest().state_hashes);
+}
+
 #[test]
 fn guards_delete_folders() {
 	let spec = Spec::new_null();
@@ -146,3 +190,65 @@ fn guards_delete_folders() {
 	assert!(!path.join("db").exists());
 	assert!(path.join("temp").exists());
 }
+
+#[test]
+fn verifies_snapshot_without_client_db() {
+	use std::io;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use bytes::Bytes;
+	use ethereum_types::H256;
+	use snapshot::io::StatePart;
+
+	// a reader which corrupts one of the chunks of another.
+	struct CorruptReader<'a>(&'a SnapshotReader, H256);
+
+	impl<'a> SnapshotReader for CorruptReader<'a> {
+		fn manifest(&self) -> &ManifestData { self.0.manifest() }
+
+		fn chunk(&self, hash: H256) -> io::Result<Bytes> {
+			let mut chunk = self.0.chunk(hash)?;
+			if hash == self.1 {
+				chunk[0] ^= 1;
+			}
+			Ok(chunk)
+		}
+
+		fn state_parts(&self) -> &[StatePart] { self.0.state_parts() }
+	}
+
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, 20, 5, &[1.into(), 2.into()]);
+
+	let tempdir = TempDir::new("").unwrap();
+	let spec = Spec::new_null();
+	let restoration = restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS));
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().join("snapshot"),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let service = Service::new(service_params).unwrap();
+	service.take_snapshot(&client, 20).unwrap();
+
+	let reader = service.reader();
+	let reader = reader.as_ref().unwrap();
+	let manifest = reader.manifest().clone();
+
+	let chunks_done = AtomicUsize::new(0);
+	let db = restoration.open(&tempdir.path().join("verified")).unwrap();
+	snapshot::verify_snapshot(reader, &*spec.engine, &spec.genesis_block(), ::journaldb::Algorithm::Archive, db, &chunks_done).unwrap();
+	assert_eq!(chunks_done.load(Ordering::SeqCst), manifest.state_hashes.len() + manifest.block_hashes.len());
+
+	let corrupt = CorruptReader(reader, manifest.block_hashes[0]);
+	let db = restoration.open(&tempdir.path().join("corrupt")).unwrap();
+	let res = snapshot::verify_snapshot(&corrupt, &*spec.engine, &spec.genesis_block(), ::journaldb::Algorithm::Archive, db, &AtomicUsize::new(0));
+	match res {
+		Err(::error::Error(::error::ErrorKind::Snapshot(snapshot::Error::ChunkHashMismatch(expected, _)), _)) =>
+			assert_eq!(expected, manifest.block_hashes[0]),
+		other => panic!("unexpected verification result: {:?}", other),
+	}
+}
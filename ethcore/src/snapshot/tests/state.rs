@@ -82,6 +82,7 @@ fn snap_and_restore() {
 
 			rebuilder.feed(&chunk, &flag).unwrap();
 		}
+		rebuilder.commit_split_accounts(&flag).unwrap();
 
 		assert_eq!(rebuilder.state_root(), state_root);
 		rebuilder.finalize(1000, H256::default()).unwrap();
@@ -143,6 +144,7 @@ fn get_code_from_prev_chunk() {
 
 		rebuilder.feed(&chunk1, &flag).unwrap();
 		rebuilder.feed(&chunk2, &flag).unwrap();
+		rebuilder.commit_split_accounts(&flag).unwrap();
 
 		rebuilder.finalize(1000, H256::random()).unwrap();
 	}
@@ -151,6 +153,54 @@ fn get_code_from_prev_chunk() {
 	assert_eq!(state_db.earliest_era(), Some(1000));
 }
 
+#[test]
+fn finalize_requires_split_accounts() {
+	use std::collections::HashSet;
+	use rlp::RlpStream;
+	use ethereum_types::U256;
+	use hash::KECCAK_EMPTY;
+	use trie::TrieMut;
+	use ethtrie::TrieDBMut;
+
+	use account_db::{AccountDBMut, AccountDB};
+
+	let hash = keccak(b"account");
+	let mut db = MemoryDB::new();
+	let mut storage_root = KECCAK_NULL_RLP;
+	{
+		let mut acct_db = AccountDBMut::from_hash(&mut db, hash);
+		let mut storage_trie = TrieDBMut::new(&mut acct_db, &mut storage_root);
+		for i in 0..3u8 {
+			storage_trie.insert(&keccak(&[i]), &[i + 1; 32]).unwrap();
+		}
+	}
+
+	let acc = BasicAccount {
+		nonce: U256::from(1),
+		balance: U256::from(1000),
+		storage_root: storage_root,
+		code_hash: KECCAK_EMPTY,
+	};
+
+	// the only account of a chunk may continue in other chunks.
+	let fat_rlps = account::to_fat_rlps(&hash, &acc, &AccountDB::from_hash(&db, hash), &mut HashSet::new(), usize::max_value(), usize::max_value()).unwrap();
+	let mut stream = RlpStream::new_list(1);
+	stream.append_raw(&fat_rlps[0], 1);
+
+	let tempdir = TempDir::new("").unwrap();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let new_db = Arc::new(Database::open(&db_cfg, tempdir.path().to_str().unwrap()).unwrap());
+
+	let mut rebuilder = StateRebuilder::new(new_db, Algorithm::Archive);
+	let flag = AtomicBool::new(true);
+	rebuilder.feed(&stream.out(), &flag).unwrap();
+
+	match rebuilder.finalize(1000, H256::random()) {
+		Err(Error(ErrorKind::Snapshot(SnapshotError::UnfinishedSplitAccounts(ref accounts)), _)) => assert_eq!(accounts, &vec![hash]),
+		_ => panic!("expected the unfinished split account to be reported"),
+	}
+}
+
 #[test]
 fn checks_flag() {
 	let mut producer = StateProducer::new();
@@ -199,3 +249,158 @@ fn checks_flag() {
 		}
 	}
 }
+
+#[test]
+fn storage_spanning_chunks_prepared_concurrently() {
+	use std::collections::HashSet;
+	use std::thread;
+	use rlp::RlpStream;
+	use ethereum_types::U256;
+	use hash::KECCAK_EMPTY;
+	use trie::TrieMut;
+	use ethtrie::TrieDBMut;
+
+	use account_db::{AccountDBMut, AccountDB};
+
+	let hash = H256::random();
+	let mut db = MemoryDB::new();
+	let mut storage_root = KECCAK_NULL_RLP;
+	{
+		let mut acct_db = AccountDBMut::from_hash(&mut db, hash);
+		let mut storage_trie = TrieDBMut::new(&mut acct_db, &mut storage_root);
+		for i in 0..200u8 {
+			storage_trie.insert(&keccak(&[i]), &[i.wrapping_add(1); 32]).unwrap();
+		}
+	}
+
+	let acc = BasicAccount {
+		nonce: U256::from(1),
+		balance: U256::from(1000),
+		storage_root: storage_root,
+		code_hash: KECCAK_EMPTY,
+	};
+
+	let mut state_root = KECCAK_NULL_RLP;
+	{
+		let mut account_trie = TrieDBMut::new(&mut db, &mut state_root);
+		account_trie.insert(&hash, &::rlp::encode(&acc)).unwrap();
+	}
+
+	// one chunk per part of the account's storage.
+	let fat_rlps = account::to_fat_rlps(&hash, &acc, &AccountDB::from_hash(&db, hash), &mut HashSet::new(), 1000, 1000).unwrap();
+	assert!(fat_rlps.len() > 2);
+	let chunks: Vec<_> = fat_rlps.into_iter().map(|fat_rlp| {
+		let mut stream = RlpStream::new_list(1);
+		stream.append_raw(&fat_rlp, 1);
+		stream.out()
+	}).collect();
+
+	let prepared: Vec<_> = chunks.into_iter()
+		.map(|chunk| thread::spawn(move || StateRebuilder::prepare(&chunk, &AtomicBool::new(true)).unwrap()))
+		.collect::<Vec<_>>()
+		.into_iter()
+		.map(|handle| handle.join().unwrap())
+		.collect();
+
+	let tempdir = TempDir::new("").unwrap();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let new_db = Arc::new(Database::open(&db_cfg, tempdir.path().to_str().unwrap()).unwrap());
+
+	let mut rebuilder = StateRebuilder::new(new_db.clone(), Algorithm::Archive);
+	let flag = AtomicBool::new(true);
+	for chunk in prepared.into_iter().rev() {
+		rebuilder.commit(chunk, &flag).unwrap();
+	}
+	rebuilder.commit_split_accounts(&flag).unwrap();
+
+	assert_eq!(rebuilder.state_root(), state_root);
+	rebuilder.finalize(1000, H256::random()).unwrap();
+}
+
+#[test]
+fn split_storage_leaves_only_reachable_nodes() {
+	use std::collections::HashSet;
+	use rlp::RlpStream;
+	use ethereum_types::U256;
+	use hash::KECCAK_EMPTY;
+	use hashdb::HashDB;
+	use kvdb::KeyValueDB;
+	use trie::TrieMut;
+	use ethtrie::TrieDBMut;
+
+	use account_db::{AccountDBMut, AccountDB};
+
+	// a large account between two small ones.
+	let mut db = MemoryDB::new();
+	let mut accounts = Vec::new();
+	for (i, &storage_items) in [2u8, 200, 3].iter().enumerate() {
+		let hash = keccak(&[i as u8]);
+		let mut storage_root = KECCAK_NULL_RLP;
+		{
+			let mut acct_db = AccountDBMut::from_hash(&mut db, hash);
+			let mut storage_trie = TrieDBMut::new(&mut acct_db, &mut storage_root);
+			for j in 0..storage_items {
+				storage_trie.insert(&keccak(&[j]), &[j.wrapping_add(1); 32]).unwrap();
+			}
+		}
+
+		accounts.push((hash, BasicAccount {
+			nonce: U256::from(i as u64),
+			balance: U256::from(1000),
+			storage_root: storage_root,
+			code_hash: KECCAK_EMPTY,
+		}));
+	}
+
+	let mut state_root = KECCAK_NULL_RLP;
+	{
+		let mut account_trie = TrieDBMut::new(&mut db, &mut state_root);
+		for &(ref hash, ref acc) in &accounts {
+			account_trie.insert(hash, &::rlp::encode(acc)).unwrap();
+		}
+	}
+
+	let fat_rlps: Vec<_> = accounts.iter().map(|&(ref hash, ref acc)| {
+		account::to_fat_rlps(hash, acc, &AccountDB::from_hash(&db, *hash), &mut HashSet::new(), 1000, 1000).unwrap()
+	}).collect();
+	let large = &fat_rlps[1];
+	assert!(large.len() > 3);
+
+	// the large account ends the first chunk, fills the middle ones and starts the last one.
+	let mut chunks = vec![vec![&fat_rlps[0][0], &large[0]]];
+	chunks.extend(large[1..large.len() - 1].iter().map(|fat_rlp| vec![fat_rlp]));
+	chunks.push(vec![&large[large.len() - 1], &fat_rlps[2][0]]);
+
+	let tempdir = TempDir::new("").unwrap();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let new_db = Arc::new(Database::open(&db_cfg, tempdir.path().to_str().unwrap()).unwrap());
+
+	let mut rebuilder = StateRebuilder::new(new_db.clone(), Algorithm::Archive);
+	let flag = AtomicBool::new(true);
+	for items in chunks.into_iter().rev() {
+		let mut stream = RlpStream::new_list(items.len());
+		for fat_rlp in items {
+			stream.append_raw(fat_rlp, 1);
+		}
+		rebuilder.feed(&stream.out(), &flag).unwrap();
+	}
+	rebuilder.commit_split_accounts(&flag).unwrap();
+
+	assert_eq!(rebuilder.state_root(), state_root);
+	rebuilder.finalize(1000, H256::random()).unwrap();
+	new_db.flush().unwrap();
+
+	let reachable: HashSet<H256> = db.keys().into_iter()
+		.filter(|&(_, rc)| rc > 0)
+		.map(|(key, _)| key)
+		.collect();
+	let stored: Vec<_> = KeyValueDB::iter(&*new_db, ::db::COL_STATE).map(|(key, _)| key).collect();
+	let nodes: HashSet<H256> = stored.iter()
+		.filter(|key| key.len() == 32)
+		.map(|key| H256::from_slice(key))
+		.collect();
+
+	assert_eq!(nodes, reachable);
+	// besides the nodes, only the era of the archive journal is left.
+	assert_eq!(stored.len(), nodes.len() + 1);
+}
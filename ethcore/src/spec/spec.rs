@@ -16,7 +16,7 @@
 
 //! Parameters for a block chain.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Read;
 use std::path::Path;
 use std::sync::Arc;
@@ -122,6 +122,12 @@ pub struct CommonParams {
 	pub eip1283_transition: BlockNumber,
 	/// Number of first block where EIP-1014 rules begin.
 	pub eip1014_transition: BlockNumber,
+	/// Number of first block where EIP-1344 rules begin.
+	pub eip1344_transition: BlockNumber,
+	/// Number of first block where EIP-1884 rules begin.
+	pub eip1884_transition: BlockNumber,
+	/// Number of first block where EIP-2200 rules begin.
+	pub eip2200_transition: BlockNumber,
 	/// Number of first block where dust cleanup rules (EIP-168 and EIP169) begin.
 	pub dust_protection_transition: BlockNumber,
 	/// Nonce cap increase per block. Nonce cap is only checked if dust protection is enabled.
@@ -189,6 +195,18 @@ impl CommonParams {
 		schedule.have_bitwise_shifting = block_number >= self.eip145_transition;
 		schedule.have_extcodehash = block_number >= self.eip1052_transition;
 		schedule.eip1283 = block_number >= self.eip1283_transition;
+		schedule.have_chain_id = block_number >= self.eip1344_transition;
+		if block_number >= self.eip1884_transition {
+			schedule.have_selfbalance = true;
+			schedule.sload_gas = 800;
+			schedule.balance_gas = 700;
+			schedule.extcodehash_gas = 700;
+		}
+		if block_number >= self.eip2200_transition {
+			schedule.eip1283 = true;
+			schedule.eip1706 = true;
+			schedule.sstore_dirty_gas = Some(800);
+		}
 		if block_number >= self.eip210_transition {
 			schedule.blockhash_gas = 800;
 		}
@@ -300,6 +318,18 @@ impl From<ethjson::spec::Params> for CommonParams {
 				BlockNumber::max_value,
 				Into::into,
 			),
+			eip1344_transition: p.eip1344_transition.map_or_else(
+				BlockNumber::max_value,
+				Into::into,
+			),
+			eip1884_transition: p.eip1884_transition.map_or_else(
+				BlockNumber::max_value,
+				Into::into,
+			),
+			eip2200_transition: p.eip2200_transition.map_or_else(
+				BlockNumber::max_value,
+				Into::into,
+			),
 			dust_protection_transition: p.dust_protection_transition.map_or_else(
 				BlockNumber::max_value,
 				Into::into,
@@ -405,6 +435,9 @@ pub struct Spec {
 	/// Hardcoded synchronization. Allows the light client to immediately jump to a specific block.
 	pub hardcoded_sync: Option<SpecHardcodedSync>,
 
+	/// Block numbers at which the consensus rules change, in ascending order.
+	pub hard_forks: BTreeSet<BlockNumber>,
+
 	/// Contract constructors to be executed on genesis.
 	constructors: Vec<(Address, Bytes)>,
 
@@ -434,6 +467,7 @@ impl Clone for Spec {
 			extra_data: self.extra_data.clone(),
 			seal_rlp: self.seal_rlp.clone(),
 			hardcoded_sync: self.hardcoded_sync.clone(),
+			hard_forks: self.hard_forks.clone(),
 			constructors: self.constructors.clone(),
 			state_root_memo: RwLock::new(*self.state_root_memo.read()),
 			genesis_state: self.genesis_state.clone(),
@@ -487,6 +521,65 @@ fn load_machine_from(s: ethjson::spec::Spec) -> EthereumMachine {
 	Spec::machine(&s.engine, params, builtins)
 }
 
+/// Transitions configured at or above this block number are considered disabled.
+const DISABLED_TRANSITION: BlockNumber = 0x7fffffffffffff;
+
+/// Collect block numbers of all transitions configured in the spec, excluding genesis
+/// and disabled transitions.
+fn hard_forks(engine_spec: &ethjson::spec::Engine, params: &CommonParams) -> BTreeSet<BlockNumber> {
+	let mut forks = vec![
+		params.eip150_transition,
+		params.eip160_transition,
+		params.eip161abc_transition,
+		params.eip161d_transition,
+		params.eip98_transition,
+		params.eip658_transition,
+		params.eip155_transition,
+		params.eip140_transition,
+		params.eip210_transition,
+		params.eip211_transition,
+		params.eip214_transition,
+		params.eip145_transition,
+		params.eip1052_transition,
+		params.eip1283_transition,
+		params.eip1014_transition,
+		params.eip1344_transition,
+		params.eip1884_transition,
+		params.eip2200_transition,
+		params.dust_protection_transition,
+		params.wasm_activation_transition,
+		params.kip4_transition,
+		params.kip6_transition,
+		params.max_code_size_transition,
+	];
+
+	if let ethjson::spec::Engine::Ethash(ref ethash) = *engine_spec {
+		let p = &ethash.params;
+		forks.extend([
+			p.homestead_transition,
+			p.dao_hardfork_transition,
+			p.difficulty_hardfork_transition,
+			p.bomb_defuse_transition,
+			p.eip100b_transition,
+			p.ecip1010_pause_transition,
+			p.ecip1010_continue_transition,
+			p.expip2_transition,
+			p.block_reward_contract_transition,
+		].iter().filter_map(|t| t.map(Into::into)));
+
+		if let Some(ref delays) = p.difficulty_bomb_delays {
+			forks.extend(delays.keys().map(|&block| block.into()));
+		}
+		if let Some(ethjson::spec::BlockReward::Multi(ref rewards)) = p.block_reward {
+			forks.extend(rewards.keys().map(|&block| block.into()));
+		}
+	}
+
+	forks.into_iter()
+		.filter(|&block| block != 0 && block < DISABLED_TRANSITION)
+		.collect()
+}
+
 /// Load from JSON object.
 fn load_from(spec_params: SpecParams, s: ethjson::spec::Spec) -> Result<Spec, Error> {
 	let builtins = s.accounts
@@ -497,6 +590,7 @@ fn load_from(spec_params: SpecParams, s: ethjson::spec::Spec) -> Result<Spec, Er
 	let g = Genesis::from(s.genesis);
 	let GenericSeal(seal_rlp) = g.seal.into();
 	let params = CommonParams::from(s.params);
+	let hard_forks = hard_forks(&s.engine, &params);
 
 	let hardcoded_sync = if let Some(ref hs) = s.hardcoded_sync {
 		if let Ok(header) = hs.header.from_hex() {
@@ -531,6 +625,7 @@ fn load_from(spec_params: SpecParams, s: ethjson::spec::Spec) -> Result<Spec, Er
 		extra_data: g.extra_data,
 		seal_rlp: seal_rlp,
 		hardcoded_sync: hardcoded_sync,
+		hard_forks: hard_forks,
 		constructors: s.accounts
 			.constructors()
 			.into_iter()
@@ -206,6 +206,14 @@ impl Account {
 		self.storage_changes = storage;
 	}
 
+	/// Drop all storage of this account, replacing it with given values.
+	pub fn reset_storage(&mut self, storage: HashMap<H256, H256>) {
+		self.storage_root = KECCAK_NULL_RLP;
+		self.storage_cache = Self::empty_storage_cache();
+		self.original_storage_cache = None;
+		self.storage_changes = storage;
+	}
+
 	/// Set (and cache) the contents of the trie's storage at `key` to `value`.
 	pub fn set_storage(&mut self, key: H256, value: H256) {
 		self.storage_changes.insert(key, value);
@@ -465,6 +473,16 @@ impl Account {
 		self.balance = self.balance + *x;
 	}
 
+	/// Overwrite account balance.
+	pub fn set_balance(&mut self, x: U256) {
+		self.balance = x;
+	}
+
+	/// Overwrite account nonce.
+	pub fn set_nonce(&mut self, x: U256) {
+		self.nonce = x;
+	}
+
 	/// Decrease account balance.
 	/// Panics if balance is less than `x`
 	pub fn sub_balance(&mut self, x: &U256) {
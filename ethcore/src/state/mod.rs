@@ -315,6 +315,21 @@ pub struct State<B> {
 	checkpoints: RefCell<Vec<HashMap<Address, Option<AccountEntry>>>>,
 	account_start_nonce: U256,
 	factories: Factories,
+	// Changes committed since `track_changes` was called.
+	changes: Option<CommittedChanges>,
+	// Accounts and storage keys read or written since `track_accesses` was called.
+	accesses: Option<RefCell<BTreeMap<Address, BTreeSet<H256>>>>,
+}
+
+// Accounts, storage keys and account kills committed to the trie.
+#[derive(Default, Clone)]
+struct CommittedChanges {
+	accounts: BTreeMap<Address, BTreeSet<H256>>,
+	killed: BTreeSet<Address>,
+	// Kills not committed yet, reverted together with the checkpoint they were made in.
+	pending_kills: Vec<Address>,
+	// Length of `pending_kills` when each of the active checkpoints was created.
+	checkpoints: Vec<usize>,
 }
 
 #[derive(Copy, Clone)]
@@ -377,6 +392,8 @@ impl<B: Backend> State<B> {
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: account_start_nonce,
 			factories: factories,
+			changes: None,
+			accesses: None,
 		}
 	}
 
@@ -392,7 +409,9 @@ impl<B: Backend> State<B> {
 			cache: RefCell::new(HashMap::new()),
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: account_start_nonce,
-			factories: factories
+			factories: factories,
+			changes: None,
+			accesses: None,
 		};
 
 		Ok(state)
@@ -405,6 +424,9 @@ impl<B: Backend> State<B> {
 
 	/// Create a recoverable checkpoint of this state. Return the checkpoint index.
 	pub fn checkpoint(&mut self) -> usize {
+		if let Some(ref mut changes) = self.changes {
+			changes.checkpoints.push(changes.pending_kills.len());
+		}
 		let checkpoints = self.checkpoints.get_mut();
 		let index = checkpoints.len();
 		checkpoints.push(HashMap::new());
@@ -413,6 +435,9 @@ impl<B: Backend> State<B> {
 
 	/// Merge last checkpoint with previous.
 	pub fn discard_checkpoint(&mut self) {
+		if let Some(ref mut changes) = self.changes {
+			changes.checkpoints.pop();
+		}
 		// merge with previous checkpoint
 		let last = self.checkpoints.get_mut().pop();
 		if let Some(mut checkpoint) = last {
@@ -430,6 +455,11 @@ impl<B: Backend> State<B> {
 
 	/// Revert to the last checkpoint and discard it.
 	pub fn revert_to_checkpoint(&mut self) {
+		if let Some(ref mut changes) = self.changes {
+			if let Some(len) = changes.checkpoints.pop() {
+				changes.pending_kills.truncate(len);
+			}
+		}
 		if let Some(mut checkpoint) = self.checkpoints.get_mut().pop() {
 			for (k, v) in checkpoint.drain() {
 				match v {
@@ -507,9 +537,49 @@ impl<B: Backend> State<B> {
 
 	/// Remove an existing account.
 	pub fn kill_account(&mut self, account: &Address) {
+		if let Some(ref mut changes) = self.changes {
+			changes.pending_kills.push(*account);
+		}
 		self.insert_cache(account, AccountEntry::new_dirty(None));
 	}
 
+	/// Start collecting the accounts and storage keys committed from now on.
+	pub fn track_changes(&mut self) {
+		self.changes = Some(Default::default());
+	}
+
+	/// Accounts committed since `track_changes` was called, with the storage keys committed for each of them.
+	/// `None` if changes are not tracked.
+	pub fn committed_changes(&self) -> Option<&BTreeMap<Address, BTreeSet<H256>>> {
+		self.changes.as_ref().map(|changes| &changes.accounts)
+	}
+
+	/// Start collecting the accounts and storage keys read or written from now on.
+	pub fn track_accesses(&mut self) {
+		self.accesses = Some(RefCell::new(BTreeMap::new()));
+	}
+
+	/// Accounts read or written since `track_accesses` was called, with the storage keys accessed in
+	/// each of them. Includes reads served by the global account cache. Empty if accesses are not tracked.
+	pub fn accessed(&self) -> BTreeMap<Address, BTreeSet<H256>> {
+		self.accesses.as_ref().map_or_else(BTreeMap::new, |accesses| accesses.borrow().clone())
+	}
+
+	fn note_access(&self, address: &Address, key: Option<&H256>) {
+		if let Some(ref accesses) = self.accesses {
+			let mut accesses = accesses.borrow_mut();
+			let keys = accesses.entry(*address).or_insert_with(BTreeSet::new);
+			if let Some(key) = key {
+				keys.insert(*key);
+			}
+		}
+	}
+
+	/// Whether a kill of account `a` was committed since `track_changes` was called.
+	pub fn was_killed(&self, a: &Address) -> bool {
+		self.changes.as_ref().map_or(false, |changes| changes.killed.contains(a))
+	}
+
 	/// Determine whether an account exists.
 	pub fn exists(&self, a: &Address) -> TrieResult<bool> {
 		// Bloom filter does not contain empty accounts, so it is important here to
@@ -621,6 +691,8 @@ impl<B: Backend> State<B> {
 		FCachedStorageAt: Fn(&Account, &H256) -> Option<H256>,
 		FStorageAt: Fn(&Account, &HashDB<KeccakHasher>, &H256) -> TrieResult<H256>
 	{
+		self.note_access(address, Some(key));
+
 		// Storage key search and update works like this:
 		// 1. If there's an entry for the account in the local cache check for the key and return it if found.
 		// 2. If there's an entry for the account in the global cache check for the key or load it into that account.
@@ -758,6 +830,22 @@ impl<B: Backend> State<B> {
 		self.require(a, false).map(|mut x| x.inc_nonce())
 	}
 
+	/// Overwrite the balance of account `a`.
+	pub fn set_balance(&mut self, a: &Address, balance: U256) -> TrieResult<()> {
+		trace!(target: "state", "set_balance({}, {}): {}", a, balance, self.balance(a)?);
+		self.require(a, false).map(|mut x| x.set_balance(balance))
+	}
+
+	/// Overwrite the nonce of account `a`.
+	pub fn set_nonce(&mut self, a: &Address, nonce: U256) -> TrieResult<()> {
+		self.require(a, false).map(|mut x| x.set_nonce(nonce))
+	}
+
+	/// Replace the whole storage of account `a` with given values.
+	pub fn reset_storage(&mut self, a: &Address, storage: HashMap<H256, H256>) -> TrieResult<()> {
+		self.require(a, false).map(|mut x| x.reset_storage(storage))
+	}
+
 	/// Mutate storage of account `a` so that it is `value` for `key`.
 	pub fn set_storage(&mut self, a: &Address, key: H256, value: H256) -> TrieResult<()> {
 		trace!(target: "state", "set_storage({}:{:x} to {:x})", a, key, value);
@@ -865,6 +953,12 @@ impl<B: Backend> State<B> {
 		// first, commit the sub trees.
 		let mut accounts = self.cache.borrow_mut();
 		for (address, ref mut a) in accounts.iter_mut().filter(|&(_, ref a)| a.is_dirty()) {
+			if let Some(ref mut changes) = self.changes {
+				let keys = changes.accounts.entry(*address).or_insert_with(BTreeSet::new);
+				if let Some(ref account) = a.account {
+					keys.extend(account.storage_changes().keys().cloned());
+				}
+			}
 			if let Some(ref mut account) = a.account {
 				let addr_hash = account.address_hash(address);
 				{
@@ -893,6 +987,10 @@ impl<B: Backend> State<B> {
 			}
 		}
 
+		if let Some(ref mut changes) = self.changes {
+			changes.killed.extend(changes.pending_kills.drain(..));
+		}
+
 		Ok(())
 	}
 
@@ -1055,6 +1153,8 @@ impl<B: Backend> State<B> {
 	/// Populates local cache if nothing found.
 	fn ensure_cached<F, U>(&self, a: &Address, require: RequireCache, check_null: bool, f: F) -> TrieResult<U>
 		where F: Fn(Option<&Account>) -> U {
+		self.note_access(a, None);
+
 		// check local cache first
 		if let Some(ref mut maybe_acc) = self.cache.borrow_mut().get_mut(a) {
 			if let Some(ref mut account) = maybe_acc.account {
@@ -1110,6 +1210,8 @@ impl<B: Backend> State<B> {
 	fn require_or_from<'a, F, G>(&'a self, a: &Address, require_code: bool, default: F, not_default: G) -> TrieResult<RefMut<'a, Account>>
 		where F: FnOnce() -> Account, G: FnOnce(&mut Account),
 	{
+		self.note_access(a, None);
+
 		let contains_key = self.cache.borrow().contains_key(a);
 		if !contains_key {
 			match self.db.get_cached_account(a) {
@@ -1233,6 +1335,8 @@ impl Clone for State<StateDB> {
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: self.account_start_nonce.clone(),
 			factories: self.factories.clone(),
+			changes: self.changes.clone(),
+			accesses: self.accesses.clone(),
 		}
 	}
 }
@@ -2248,6 +2352,28 @@ mod tests {
 		assert_eq!(state.nonce(&a).unwrap(), U256::from(3u64));
 	}
 
+	#[test]
+	fn override_balance_nonce_and_storage() {
+		let mut state = get_temp_state();
+		let a = Address::zero();
+		state.add_balance(&a, &U256::from(69u64), CleanupMode::NoEmpty).unwrap();
+		state.set_storage(&a, H256::from(1u64), H256::from(1u64)).unwrap();
+		state.set_storage(&a, H256::from(2u64), H256::from(2u64)).unwrap();
+		state.commit().unwrap();
+
+		state.set_balance(&a, U256::from(42u64)).unwrap();
+		state.set_nonce(&a, U256::from(7u64)).unwrap();
+		assert_eq!(state.balance(&a).unwrap(), U256::from(42u64));
+		assert_eq!(state.nonce(&a).unwrap(), U256::from(7u64));
+
+		state.reset_storage(&a, vec![(H256::from(2u64), H256::from(3u64))].into_iter().collect()).unwrap();
+		assert_eq!(state.storage_at(&a, &H256::from(1u64)).unwrap(), H256::new());
+		assert_eq!(state.storage_at(&a, &H256::from(2u64)).unwrap(), H256::from(3u64));
+		state.commit().unwrap();
+		assert_eq!(state.storage_at(&a, &H256::from(1u64)).unwrap(), H256::new());
+		assert_eq!(state.storage_at(&a, &H256::from(2u64)).unwrap(), H256::from(3u64));
+	}
+
 	#[test]
 	fn balance_nonce() {
 		let mut state = get_temp_state();
@@ -2268,6 +2394,40 @@ mod tests {
 		assert_eq!(*state.root(), "0ce23f3c809de377b008a4a3ee94a0834aac8bec1f86e28ffe4fdb5a15b0c785".into());
 	}
 
+	#[test]
+	fn accessed_includes_global_cache_hits() {
+		let a = Address::from(1);
+		let b = Address::from(2);
+		let key = H256::from(3);
+		let state_db = get_temp_state_db();
+		let parent = H256::random();
+		let h0 = H256::random();
+
+		let mut state = State::new(state_db.boxed_clone_canon(&parent), U256::zero(), Default::default());
+		state.add_balance(&a, &U256::from(69u64), CleanupMode::NoEmpty).unwrap();
+		state.set_storage(&b, key, H256::from(42)).unwrap();
+		state.commit().unwrap();
+		let (root, mut db) = state.drop();
+		let mut batch = ::kvdb::DBTransaction::new();
+		db.journal_under(&mut batch, 0, &h0).unwrap();
+		db.journal_db().backing().write(batch).unwrap();
+		db.sync_cache(&[], &[], true);
+
+		let mut state = State::from_existing(state_db.boxed_clone_canon(&h0), root, U256::zero(), Default::default()).unwrap();
+		assert!(state.db.get_cached_account(&a).is_some());
+		assert!(state.db.get_cached_account(&b).is_some());
+
+		state.track_accesses();
+		assert_eq!(state.balance(&a).unwrap(), U256::from(69u64));
+		assert_eq!(state.storage_at(&b, &key).unwrap(), H256::from(42));
+		assert!(state.cache.borrow().is_empty());
+
+		let mut expected = BTreeMap::new();
+		expected.insert(a, BTreeSet::new());
+		expected.insert(b, vec![key].into_iter().collect());
+		assert_eq!(state.accessed(), expected);
+	}
+
 	#[test]
 	fn checkpoint_basic() {
 		let mut state = get_temp_state();
@@ -2453,6 +2613,37 @@ mod tests {
 		assert_eq!(state.storage_at(&a, &k).unwrap(), H256::from(U256::from(1)));
 	}
 
+	#[test]
+	fn tracks_committed_changes_only_when_enabled() {
+		let a: Address = 1.into();
+		let b: Address = 2.into();
+		let k = H256::from(U256::from(1));
+
+		let mut state = get_temp_state();
+		state.set_storage(&a, k, H256::from(U256::from(1))).unwrap();
+		state.kill_account(&b);
+		state.commit().unwrap();
+		assert!(state.committed_changes().is_none());
+		assert!(!state.was_killed(&b));
+
+		let mut state = get_temp_state();
+		state.track_changes();
+		state.set_storage(&a, k, H256::from(U256::from(1))).unwrap();
+		state.checkpoint();
+		state.kill_account(&a);
+		state.revert_to_checkpoint();
+		state.checkpoint();
+		state.kill_account(&b);
+		state.discard_checkpoint();
+		state.commit().unwrap();
+
+		let changes = state.committed_changes().unwrap();
+		assert_eq!(changes.get(&a), Some(&vec![k].into_iter().collect()));
+		assert!(changes.contains_key(&b));
+		assert!(!state.was_killed(&a));
+		assert!(state.was_killed(&b));
+	}
+
 	#[test]
 	fn create_contract_fail() {
 		let mut state = get_temp_state();
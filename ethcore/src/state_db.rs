@@ -20,6 +20,7 @@ use std::collections::{VecDeque, HashSet};
 use std::io;
 use std::sync::Arc;
 
+use account_db::AccountDBMut;
 use bloom_journal::{Bloom, BloomJournal};
 use byteorder::{LittleEndian, ByteOrder};
 use db::COL_ACCOUNT_BLOOM;
@@ -208,6 +209,26 @@ impl StateDB {
 		Ok(records)
 	}
 
+	/// Write state trie nodes and code straight to the backing database, bypassing the journal.
+	/// Nodes owned by an account are keyed by its address hash, like `AccountDB` does.
+	/// `accounts` are noted in the account bloom.
+	pub fn inject_nodes(&mut self, batch: &mut DBTransaction, nodes: Vec<(Option<H256>, Vec<u8>)>, accounts: &[H256]) -> io::Result<u32> {
+		{
+			let mut bloom_lock = self.account_bloom.lock();
+			for account in accounts {
+				bloom_lock.set(&**account);
+			}
+			Self::commit_bloom(batch, bloom_lock.drain_journal())?;
+		}
+		for (account, node) in nodes {
+			match account {
+				Some(address_hash) => { AccountDBMut::from_hash(self.db.as_hashdb_mut(), address_hash).insert(&node); },
+				None => { self.db.as_hashdb_mut().insert(&node); },
+			}
+		}
+		self.db.inject(batch)
+	}
+
 	/// Mark a given candidate from an ancient era as canonical, enacting its removals from the
 	/// backing database and reverting any non-canonical historical commit's insertions.
 	pub fn mark_canonical(&mut self, batch: &mut DBTransaction, end_era: u64, canon_id: &H256) -> io::Result<u32> {
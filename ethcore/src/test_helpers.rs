@@ -40,7 +40,8 @@ use state::*;
 use transaction::{Action, Transaction, SignedTransaction};
 use views::BlockView;
 use blooms_db;
-use kvdb::KeyValueDB;
+use kvdb::{KeyValueDB, DBTransaction, DBValue};
+use kvdb_memorydb;
 use kvdb_rocksdb;
 use tempdir::TempDir;
 use verification::queue::kind::blocks::Unverified;
@@ -263,6 +264,42 @@ pub fn get_test_client_with_blocks(blocks: Vec<Bytes>) -> Arc<Client> {
 	client
 }
 
+/// In-memory key-value store which seeks like RocksDB.
+///
+/// `iter_from_prefix` of `kvdb_memorydb` only starts at a key beginning with the prefix. RocksDB starts
+/// at the first key which isn't lower than the prefix, which is what indexes keyed by block number rely on.
+struct SeekingMemoryDB(kvdb_memorydb::InMemory);
+
+impl KeyValueDB for SeekingMemoryDB {
+	fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+		self.0.get(col, key)
+	}
+
+	fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.0.get_by_prefix(col, prefix)
+	}
+
+	fn write_buffered(&self, transaction: DBTransaction) {
+		self.0.write_buffered(transaction)
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		self.0.flush()
+	}
+
+	fn iter<'a>(&'a self, col: Option<u32>) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		self.0.iter(col)
+	}
+
+	fn iter_from_prefix<'a>(&'a self, col: Option<u32>, prefix: &'a [u8]) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		Box::new(self.0.iter(col).skip_while(move |&(ref key, _)| &**key < prefix))
+	}
+
+	fn restore(&self, new_db: &str) -> io::Result<()> {
+		self.0.restore(new_db)
+	}
+}
+
 /// Creates new test instance of `BlockChainDB`
 pub fn new_db() -> Arc<BlockChainDB> {
 	struct TestBlockChainDB {
@@ -295,7 +332,7 @@ pub fn new_db() -> Arc<BlockChainDB> {
 		trace_blooms: blooms_db::Database::open(trace_blooms_dir.path()).unwrap(),
 		_blooms_dir: blooms_dir,
 		_trace_blooms_dir: trace_blooms_dir,
-		key_value: Arc::new(::kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()))
+		key_value: Arc::new(SeekingMemoryDB(kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()))),
 	};
 
 	Arc::new(db)
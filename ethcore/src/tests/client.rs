@@ -18,7 +18,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 use hash::keccak;
 use io::IoChannel;
-use client::{BlockChainClient, Client, ClientConfig, BlockId, ChainInfo, BlockInfo, PrepareOpenBlock, ImportSealedBlock, ImportBlock};
+use client::{BlockChainClient, Client, ClientConfig, BlockId, ChainInfo, BlockInfo, PrepareOpenBlock, ImportSealedBlock, ImportBlock, Nonce, Balance};
 use state::{self, State, CleanupMode};
 use executive::{Executive, TransactOptions};
 use ethereum;
@@ -28,7 +28,7 @@ use test_helpers::{
 	generate_dummy_client_with_data, get_good_dummy_block, get_bad_state_dummy_block
 };
 use types::filter::Filter;
-use ethereum_types::{U256, Address};
+use ethereum_types::{U256, H256, Address};
 use miner::{Miner, PendingOrdering};
 use spec::Spec;
 use views::BlockView;
@@ -365,3 +365,159 @@ fn transaction_proof() {
 	assert_eq!(state.balance(&Address::default()).unwrap(), 5.into());
 	assert_eq!(state.balance(&address).unwrap(), 95.into());
 }
+
+#[test]
+fn replay_prestate_includes_read_only_accounts() {
+	use client::TransactionId;
+
+	let client = generate_dummy_client(0);
+	let test_spec = Spec::new_test();
+	let kp = KeyPair::from_secret_slice(&keccak("")).unwrap();
+	let contract = Address::from(0x100);
+	let read = Address::from(0x200);
+	let paid = Address::from(0x300);
+
+	// PUSH20 <read> BALANCE POP STOP
+	let mut code = vec![0x73];
+	code.extend_from_slice(&read);
+	code.extend_from_slice(&[0x31, 0x50, 0x00]);
+
+	let mut b = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+	{
+		let state = b.block_mut().state_mut();
+		state.add_balance(&kp.address(), &1000.into(), CleanupMode::NoEmpty).unwrap();
+		state.add_balance(&read, &7.into(), CleanupMode::NoEmpty).unwrap();
+		state.init_code(&contract, code).unwrap();
+		state.commit().unwrap();
+	}
+	let b = b.close_and_lock().unwrap().seal(&*test_spec.engine, vec![]).unwrap();
+	client.import_sealed_block(b).unwrap();
+
+	let transaction = |nonce: u64, to: Address, value: u64| Transaction {
+		nonce: nonce.into(),
+		gas_price: 0.into(),
+		gas: 100000.into(),
+		action: Action::Call(to),
+		value: value.into(),
+		data: Vec::new(),
+	}.sign(kp.secret(), Some(test_spec.chain_id()));
+
+	let mut b = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+	b.push_transaction(transaction(0, paid, 5), None).unwrap();
+	b.push_transaction(transaction(1, contract, 0), None).unwrap();
+	let b = b.close_and_lock().unwrap().seal(&*test_spec.engine, vec![]).unwrap();
+	client.import_sealed_block(b).unwrap();
+
+	let prestate = client.replay_prestate(TransactionId::Location(BlockId::Latest, 1)).unwrap();
+	assert_eq!(prestate[&read].balance, 7.into());
+	assert_eq!(prestate[&kp.address()].balance, 995.into());
+	assert_eq!(prestate[&kp.address()].nonce, 1.into());
+	assert!(prestate[&contract].code.as_ref().map_or(false, |code| !code.is_empty()));
+	// touched by the preceding transaction only.
+	assert!(!prestate.contains_key(&paid));
+
+	let block: Vec<_> = client.replay_block_transactions_prestate(BlockId::Latest).unwrap().collect();
+	assert_eq!(block[1].1, prestate);
+	assert!(block[0].1.contains_key(&paid));
+	assert!(!block[0].1.contains_key(&read));
+}
+
+#[test]
+fn simulates_transactions_in_child_block() {
+	use client::Call;
+
+	let client = generate_dummy_client(2);
+	let test_spec = Spec::new_test();
+	let kp = KeyPair::from_secret_slice(&keccak("")).unwrap();
+	let contract = Address::from(0x100);
+
+	// [0] = NUMBER, [1] = BLOCKHASH(NUMBER - 1)
+	let code = vec![0x43, 0x60, 0x00, 0x55, 0x60, 0x01, 0x43, 0x03, 0x40, 0x60, 0x01, 0x55, 0x00];
+
+	let mut b = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+	b.block_mut().state_mut().add_balance(&kp.address(), &1000.into(), CleanupMode::NoEmpty).unwrap();
+	b.block_mut().state_mut().init_code(&contract, code).unwrap();
+	b.block_mut().state_mut().commit().unwrap();
+	let b = b.close_and_lock().unwrap().seal(&*test_spec.engine, vec![]).unwrap();
+	client.import_sealed_block(b).unwrap();
+
+	let parent = client.best_block_header().decode().unwrap();
+	let header = client.child_header(&parent);
+	assert_eq!(header.number(), parent.number() + 1);
+	assert_eq!(*header.parent_hash(), parent.hash());
+	assert!(header.timestamp() > parent.timestamp());
+
+	let transaction = Transaction {
+		nonce: 0.into(),
+		gas_price: 1.into(),
+		gas: 100000.into(),
+		action: Action::Call(contract),
+		value: 0.into(),
+		data: Vec::new(),
+	}.sign(kp.secret(), Some(test_spec.chain_id()));
+
+	let mut state = client.state_at(BlockId::Latest).unwrap();
+	let simulated = client.simulate_transactions(&[transaction], &mut state, &header).unwrap();
+	assert_eq!(simulated.len(), 1);
+	assert!(simulated[0].executed.exception.is_none());
+
+	let gas_used = simulated[0].receipt.gas_used;
+	assert_eq!(state.storage_at(&contract, &0.into()).unwrap(), U256::from(header.number()).into());
+	assert_eq!(state.storage_at(&contract, &1.into()).unwrap(), parent.hash());
+	assert_eq!(state.balance(&kp.address()).unwrap(), U256::from(1000) - gas_used);
+	assert_eq!(state.nonce(&kp.address()).unwrap(), 1.into());
+
+	// nothing is committed to the chain.
+	let state = client.state_at(BlockId::Latest).unwrap();
+	assert_eq!(state.storage_at(&contract, &0.into()).unwrap(), 0.into());
+}
+
+#[test]
+fn answers_queries_about_pruned_state_from_history() {
+	let test_spec = Spec::new_null();
+	let mut config = ClientConfig::default();
+	config.state_history = true;
+	config.history = 2;
+	config.history_mem = 0;
+
+	let client = Client::new(
+		config,
+		&test_spec,
+		test_helpers::new_db(),
+		Arc::new(Miner::new_for_tests(&test_spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+
+	let address = Address::from(1);
+	let other = Address::from(2);
+	let key = H256::from(1);
+	for n in 1..11u64 {
+		let mut b = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+		{
+			let state = b.block_mut().state_mut();
+			if n <= 2 {
+				state.add_balance(&address, &5.into(), CleanupMode::NoEmpty).unwrap();
+				state.set_storage(&address, key, H256::from(n)).unwrap();
+			} else {
+				state.add_balance(&other, &1.into(), CleanupMode::NoEmpty).unwrap();
+			}
+			state.commit().unwrap();
+		}
+		let b = b.close_and_lock().unwrap().seal(&*test_spec.engine, vec![]).unwrap();
+		client.import_sealed_block(b).unwrap();
+	}
+
+	assert!(client.pruning_info().earliest_state > 3);
+	assert!(client.state_at(BlockId::Number(1)).is_none());
+
+	// the value comes from the reverse diff of the next block modifying the account.
+	assert_eq!(client.balance(&address, BlockId::Number(0).into()), Some(0.into()));
+	assert_eq!(client.balance(&address, BlockId::Number(1).into()), Some(5.into()));
+	assert_eq!(client.storage_at(&address, &key, BlockId::Number(1).into()), Some(H256::from(1)));
+	assert_eq!(client.nonce(&address, BlockId::Number(1)), Some(0.into()));
+
+	// no later block modified the account, so the value comes from the earliest available state.
+	assert_eq!(client.balance(&address, BlockId::Number(3).into()), Some(10.into()));
+	assert_eq!(client.storage_at(&address, &key, BlockId::Number(3).into()), Some(H256::from(2)));
+	assert_eq!(client.balance(&other, BlockId::Number(3).into()), Some(1.into()));
+}
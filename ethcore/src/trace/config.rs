@@ -26,6 +26,8 @@ pub struct Config {
 	pub pref_cache_size: usize,
 	/// Max cache-size.
 	pub max_cache_size: usize,
+	/// Maintain an index of traces by address to speed up filtering.
+	pub address_index: bool,
 }
 
 impl Default for Config {
@@ -34,6 +36,7 @@ impl Default for Config {
 			enabled: false,
 			pref_cache_size: 15 * 1024 * 1024,
 			max_cache_size: 20 * 1024 * 1024,
+			address_index: false,
 		}
 	}
 }
@@ -15,25 +15,34 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Trace database.
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 use blockchain::{BlockChainDB};
 use heapsize::HeapSizeOf;
-use ethereum_types::{H256, H264};
+use ethereum_types::{H256, H264, Address};
 use kvdb::{DBTransaction};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use rlp;
 use header::BlockNumber;
 use trace::{LocalizedTrace, Config, Filter, Database as TraceDatabase, ImportRequest, DatabaseExtras};
-use db::{self, Key, Writable, Readable, CacheUpdatePolicy};
+use db::{self, Key, Writable, Readable, CacheUpdatePolicy, BlockIndexKey, BlockIndexEntry, ItemPosition};
 use super::flat::{FlatTrace, FlatBlockTraces, FlatTransactionTraces};
 use cache_manager::CacheManager;
 
 const TRACE_DB_VER: &'static [u8] = b"1.0";
 
+/// Key of the address index backfill cursor.
+const ADDRESS_INDEX_CURSOR_KEY: &'static [u8] = b"address_index";
+
+const ADDRESS_INDEX_PREFIX_LEN: usize = 21;
+
 #[derive(Debug, Copy, Clone)]
 enum TraceDBIndex {
 	/// Block traces index.
 	BlockTraces = 0,
+	/// Address traces index.
+	AddressTraces = 1,
 }
 
 impl Key<FlatBlockTraces> for H256 {
@@ -47,6 +56,39 @@ impl Key<FlatBlockTraces> for H256 {
 	}
 }
 
+fn address_index_prefix(address: &Address) -> [u8; ADDRESS_INDEX_PREFIX_LEN] {
+	let mut result = [0u8; ADDRESS_INDEX_PREFIX_LEN];
+	result[0] = TraceDBIndex::AddressTraces as u8;
+	result[1..].copy_from_slice(address);
+	result
+}
+
+impl Key<BlockIndexEntry> for (Address, BlockNumber) {
+	type Target = BlockIndexKey;
+
+	fn key(&self) -> BlockIndexKey {
+		BlockIndexKey::new(&address_index_prefix(&self.0), self.1)
+	}
+}
+
+/// Groups trace positions of a block by the addresses they touch.
+fn address_traces(traces: FlatBlockTraces) -> HashMap<Address, Vec<ItemPosition>> {
+	let mut result: HashMap<Address, Vec<ItemPosition>> = HashMap::new();
+	let tx_traces: Vec<FlatTransactionTraces> = traces.into();
+	for (tx_number, tx_traces) in tx_traces.into_iter().enumerate() {
+		let traces: Vec<FlatTrace> = tx_traces.into();
+		for (trace_number, trace) in traces.into_iter().enumerate() {
+			let position = ItemPosition { transaction: tx_number as u64, index: trace_number as u64 };
+			let mut addresses = trace.addresses();
+			addresses.dedup();
+			for address in addresses {
+				result.entry(address).or_insert_with(Vec::new).push(position);
+			}
+		}
+	}
+	result
+}
+
 /// Database to store transaction execution trace.
 ///
 /// Whenever a transaction is executed by EVM it's execution trace is stored
@@ -64,6 +106,11 @@ pub struct TraceDB<T> where T: DatabaseExtras {
 	enabled: bool,
 	/// extras
 	extras: Arc<T>,
+	/// address index enabled
+	address_index: bool,
+	/// highest block number which may be missing from the address index.
+	/// `None` if the index has not been started yet.
+	address_index_cursor: Mutex<Option<BlockNumber>>,
 }
 
 impl<T> TraceDB<T> where T: DatabaseExtras {
@@ -74,6 +121,18 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 			.expect("Genesis block is always inserted upon extras db creation qed");
 		batch.write(db::COL_TRACE, &genesis, &FlatBlockTraces::default());
 		batch.put(db::COL_TRACE, b"version", TRACE_DB_VER);
+
+		let address_index = config.enabled && config.address_index;
+		let address_index_cursor = match address_index {
+			true => db.key_value().get(db::COL_TRACE, ADDRESS_INDEX_CURSOR_KEY)
+				.expect("Low level database error. Some issue with disk?")
+				.map(|cursor| rlp::decode(&cursor).expect("decode error: the db is corrupted or the data structure has changed")),
+			false => {
+				// blocks imported from now on are not indexed, so the index has to be rebuilt once it is enabled again.
+				batch.delete(db::COL_TRACE, ADDRESS_INDEX_CURSOR_KEY);
+				None
+			},
+		};
 		db.key_value().write(batch).expect("failed to update version");
 
 		TraceDB {
@@ -82,9 +141,171 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 			db,
 			enabled: config.enabled,
 			extras: extras,
+			address_index,
+			address_index_cursor: Mutex::new(address_index_cursor),
 		}
 	}
 
+	/// Returns true if traces are indexed by address.
+	pub fn address_index_enabled(&self) -> bool {
+		self.address_index
+	}
+
+	/// Adds up to `max_blocks` canonical blocks which were imported before the address index
+	/// had been enabled to the index, starting from the newest ones.
+	///
+	/// Returns the number of blocks which remain to be indexed.
+	pub fn backfill_address_index(&self, best_block: BlockNumber, max_blocks: u64) -> BlockNumber {
+		if !self.address_index {
+			return 0;
+		}
+
+		let mut cursor = self.address_index_cursor.lock();
+		let end = *cursor.get_or_insert(best_block);
+		if end == 0 {
+			return 0;
+		}
+
+		// the genesis block has no traces.
+		let start = end.saturating_sub(max_blocks) + 1;
+		let mut batch = DBTransaction::new();
+		for number in start..end + 1 {
+			let block_hash = match self.extras.block_hash(number) {
+				Some(hash) => hash,
+				None => continue,
+			};
+			if let Some(traces) = self.traces(&block_hash) {
+				for (address, positions) in address_traces(traces) {
+					batch.write(db::COL_TRACE, &(address, number), &BlockIndexEntry { block_hash, positions });
+				}
+			}
+		}
+
+		let next = start - 1;
+		batch.put(db::COL_TRACE, ADDRESS_INDEX_CURSOR_KEY, &rlp::encode(&next));
+		self.db.key_value().write(batch).expect("Low level database error. Some issue with disk?");
+		*cursor = Some(next);
+
+		match next {
+			0 => info!(target: "trace", "Finished indexing traces by address"),
+			_ => debug!(target: "trace", "Indexed traces of blocks #{}..#{} by address", start, end),
+		}
+
+		next
+	}
+
+	/// Updates the address index with enacted blocks and removes entries of the retracted ones.
+	fn import_address_index(&self, batch: &mut DBTransaction, request: &ImportRequest) {
+		let ancestor = request.block_number - request.enacted.len() as u64;
+
+		{
+			let mut cursor = self.address_index_cursor.lock();
+			if cursor.is_none() {
+				*cursor = Some(ancestor);
+				batch.put(db::COL_TRACE, ADDRESS_INDEX_CURSOR_KEY, &rlp::encode(&ancestor));
+			}
+		}
+
+		// retracted blocks are still canonical in extras at this point and they occupy
+		// the numbers following the common ancestor.
+		for number in (ancestor + 1)..(ancestor + 1 + request.retracted as u64) {
+			let traces = self.extras.block_hash(number).and_then(|hash| self.traces(&hash));
+			if let Some(traces) = traces {
+				for address in address_traces(traces).keys() {
+					batch.delete(db::COL_TRACE, &(*address, number).key());
+				}
+			}
+		}
+
+		for (i, block_hash) in request.enacted.iter().enumerate() {
+			let number = ancestor + 1 + i as u64;
+			let traces = if block_hash == &request.block_hash {
+				request.traces.clone()
+			} else {
+				self.traces(block_hash).expect("Traces database is incomplete.")
+			};
+
+			for (address, positions) in address_traces(traces) {
+				batch.write(db::COL_TRACE, &(address, number), &BlockIndexEntry { block_hash: *block_hash, positions });
+			}
+		}
+	}
+
+	/// Filters traces using the address index.
+	///
+	/// Returns `None` if the filter does not constrain addresses or if the index does not
+	/// cover the requested range yet.
+	fn filter_by_address_index(&self, filter: &Filter) -> Option<Vec<LocalizedTrace>> {
+		if !self.address_index {
+			return None;
+		}
+
+		// every matching trace touches one of the searched addresses on each constrained side.
+		let addresses = match (filter.from_address.matches_all(), filter.to_address.matches_all()) {
+			(true, true) => return None,
+			(false, _) => filter.from_address.addresses(),
+			(true, false) => filter.to_address.addresses(),
+		};
+
+		let start = filter.range.start as BlockNumber;
+		let end = filter.range.end as BlockNumber;
+		match *self.address_index_cursor.lock() {
+			Some(cursor) if cmp::max(start, 1) > cursor => (),
+			_ => return None,
+		}
+
+		// entries are grouped by block hash, a stale entry of one address mustn't hide the entries
+		// of other addresses in the canonical block with the same number.
+		let mut positions: BTreeMap<(BlockNumber, H256), BTreeSet<ItemPosition>> = BTreeMap::new();
+		for address in addresses {
+			let first = (*address, start).key();
+			for (number, entry) in db::block_index_entries(&**self.db.key_value(), db::COL_TRACE, &first, end) {
+				positions.entry((number, entry.block_hash))
+					.or_insert_with(BTreeSet::new)
+					.extend(entry.positions);
+			}
+		}
+
+		let traces = positions.into_iter()
+			.filter(|&((number, block_hash), _)| self.extras.block_hash(number) == Some(block_hash))
+			.flat_map(|((number, block_hash), positions)| {
+				let tx_traces: Vec<FlatTransactionTraces> = self.traces(&block_hash)
+					.expect("Expected to find a trace. Db is probably corrupted.")
+					.into();
+				let tx_traces: Vec<Vec<FlatTrace>> = tx_traces.into_iter().map(Into::into).collect();
+
+				positions.into_iter()
+					.filter_map(|position| {
+						let trace = tx_traces.get(position.transaction as usize)?.get(position.index as usize)?;
+						if !filter.matches(trace) {
+							return None;
+						}
+
+						let tx_number = position.transaction as usize;
+						let (trace_tx_number, trace_tx_hash) = match self.extras.transaction_hash(number, tx_number) {
+							Some(hash) => (Some(tx_number), Some(hash)),
+							//None means trace without transaction (reward)
+							None => (None, None),
+						};
+
+						Some(LocalizedTrace {
+							action: trace.action.clone(),
+							result: trace.result.clone(),
+							subtraces: trace.subtraces,
+							trace_address: trace.trace_address.iter().cloned().collect(),
+							transaction_number: trace_tx_number,
+							transaction_hash: trace_tx_hash,
+							block_number: number,
+							block_hash: block_hash,
+						})
+					})
+					.collect::<Vec<_>>()
+			})
+			.collect();
+
+		Some(traces)
+	}
+
 	fn cache_size(&self) -> usize {
 		self.traces.read().heap_size_of_children()
 	}
@@ -213,6 +434,10 @@ impl<T> TraceDatabase for TraceDB<T> where T: DatabaseExtras {
 			self.db.trace_blooms()
 				.insert_blooms(range_start, enacted_blooms.iter())
 				.expect("Low level database error. Some issue with disk?");
+
+			if self.address_index {
+				self.import_address_index(batch, &request);
+			}
 		}
 
 		// insert new block traces into the cache and the database
@@ -309,6 +534,10 @@ impl<T> TraceDatabase for TraceDB<T> where T: DatabaseExtras {
 	}
 
 	fn filter(&self, filter: &Filter) -> Vec<LocalizedTrace> {
+		if let Some(traces) = self.filter_by_address_index(filter) {
+			return traces;
+		}
+
 		let possibilities = filter.bloom_possibilities();
 		let numbers = self.db.trace_blooms()
 			.filter(filter.range.start as u64, filter.range.end as u64, &possibilities)
@@ -340,6 +569,7 @@ mod tests {
 	use trace::flat::{FlatTrace, FlatBlockTraces, FlatTransactionTraces};
 	use evm::CallType;
 	use test_helpers::new_db;
+	use db::{self, Readable, Writable, BlockIndexEntry, ItemPosition};
 
 	struct NoopExtras;
 
@@ -455,6 +685,32 @@ mod tests {
 		}
 	}
 
+	fn create_call_import_request(block_number: BlockNumber, block_hash: H256, from: Address, to: Address, retracted: usize) -> ImportRequest {
+		ImportRequest {
+			traces: FlatBlockTraces::from(vec![FlatTransactionTraces::from(vec![FlatTrace {
+				trace_address: Default::default(),
+				subtraces: 0,
+				action: Action::Call(Call {
+					from: from,
+					to: to,
+					value: 3.into(),
+					gas: 4.into(),
+					input: vec![],
+					call_type: CallType::Call,
+				}),
+				result: Res::FailedCall(TraceError::OutOfGas),
+			}])]),
+			block_hash: block_hash.clone(),
+			block_number: block_number,
+			enacted: vec![block_hash],
+			retracted: retracted,
+		}
+	}
+
+	fn address_traces(db: &Arc<::blockchain::BlockChainDB>, address: Address, block_number: BlockNumber) -> Option<BlockIndexEntry> {
+		db.key_value().read(db::COL_TRACE, &(address, block_number))
+	}
+
 	fn create_simple_localized_trace(block_number: BlockNumber, block_hash: H256, tx_hash: H256) -> LocalizedTrace {
 		LocalizedTrace {
 			action: Action::Call(Call {
@@ -632,4 +888,174 @@ mod tests {
 
 		assert_eq!(traces.len(), 0);
 	}
+
+	#[test]
+	fn test_address_index() {
+		let db = new_db();
+		let mut config = Config::default();
+		config.enabled = true;
+		config.address_index = true;
+		let block_1 = H256::from(0xa1);
+		let block_2 = H256::from(0xa2);
+		let tx_1 = H256::from(0xff);
+		let tx_2 = H256::from(0xaf);
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, H256::default());
+		extras.block_hashes.insert(1, block_1.clone());
+		extras.block_hashes.insert(2, block_2.clone());
+		extras.transaction_hashes.insert(1, vec![tx_1.clone()]);
+		extras.transaction_hashes.insert(2, vec![tx_2.clone()]);
+
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+
+		for (number, hash) in vec![(1, block_1.clone()), (2, block_2.clone())] {
+			let request = create_simple_import_request(number, hash);
+			let mut batch = DBTransaction::new();
+			tracedb.import(&mut batch, request);
+			db.key_value().write(batch).unwrap();
+		}
+
+		assert_eq!(address_traces(&db, Address::from(1), 1).unwrap().block_hash, block_1);
+		assert_eq!(address_traces(&db, Address::from(2), 2).unwrap().block_hash, block_2);
+		assert!(address_traces(&db, Address::from(3), 1).is_none());
+
+		let filter = Filter {
+			range: (1..2),
+			from_address: AddressesFilter::from(vec![Address::from(1)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+
+		let traces = tracedb.filter(&filter);
+		assert_eq!(traces.len(), 2);
+		assert_eq!(traces[0], create_simple_localized_trace(1, block_1.clone(), tx_1.clone()));
+		assert_eq!(traces[1], create_simple_localized_trace(2, block_2.clone(), tx_2.clone()));
+
+		let filter = Filter {
+			range: (2..2),
+			from_address: AddressesFilter::from(vec![]),
+			to_address: AddressesFilter::from(vec![Address::from(2)]),
+		};
+
+		let traces = tracedb.filter(&filter);
+		assert_eq!(traces, vec![create_simple_localized_trace(2, block_2.clone(), tx_2.clone())]);
+
+		let filter = Filter {
+			range: (1..2),
+			from_address: AddressesFilter::from(vec![Address::from(2)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+
+		assert!(tracedb.filter(&filter).is_empty());
+
+		// a lingering entry of a block which is no longer canonical doesn't hide canonical entries
+		// of other addresses in a block with the same number.
+		let mut batch = DBTransaction::new();
+		batch.write(db::COL_TRACE, &(Address::from(3), 2u64), &BlockIndexEntry {
+			block_hash: H256::from(0xb2),
+			positions: vec![ItemPosition { transaction: 0, index: 0 }],
+		});
+		db.key_value().write(batch).unwrap();
+
+		let filter = Filter {
+			range: (0..2),
+			from_address: AddressesFilter::from(vec![Address::from(3), Address::from(1)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+
+		let traces = tracedb.filter(&filter);
+		assert_eq!(traces, vec![
+			create_simple_localized_trace(1, block_1.clone(), tx_1.clone()),
+			create_simple_localized_trace(2, block_2.clone(), tx_2.clone()),
+		]);
+	}
+
+	#[test]
+	fn test_address_index_reorg() {
+		let db = new_db();
+		let mut config = Config::default();
+		config.enabled = true;
+		config.address_index = true;
+		let block_1 = H256::from(0xa1);
+		let block_2 = H256::from(0xa2);
+		let block_2b = H256::from(0xb2);
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, H256::default());
+		extras.block_hashes.insert(1, block_1.clone());
+		extras.block_hashes.insert(2, block_2.clone());
+
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+
+		for request in vec![
+			create_call_import_request(1, block_1.clone(), 1.into(), 2.into(), 0),
+			create_call_import_request(2, block_2.clone(), 1.into(), 2.into(), 0),
+			create_call_import_request(2, block_2b.clone(), 3.into(), 4.into(), 1),
+		] {
+			let mut batch = DBTransaction::new();
+			tracedb.import(&mut batch, request);
+			db.key_value().write(batch).unwrap();
+		}
+
+		assert_eq!(address_traces(&db, Address::from(1), 1).unwrap().block_hash, block_1);
+		assert!(address_traces(&db, Address::from(1), 2).is_none());
+		assert!(address_traces(&db, Address::from(2), 2).is_none());
+		assert_eq!(address_traces(&db, Address::from(3), 2).unwrap().block_hash, block_2b);
+		assert_eq!(address_traces(&db, Address::from(4), 2).unwrap().block_hash, block_2b);
+	}
+
+	#[test]
+	fn test_address_index_backfill() {
+		let db = new_db();
+		let mut config = Config::default();
+		config.enabled = true;
+		let block_1 = H256::from(0xa1);
+		let block_2 = H256::from(0xa2);
+		let tx_1 = H256::from(0xff);
+		let tx_2 = H256::from(0xaf);
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, H256::default());
+		extras.block_hashes.insert(1, block_1.clone());
+		extras.block_hashes.insert(2, block_2.clone());
+		extras.transaction_hashes.insert(1, vec![tx_1.clone()]);
+		extras.transaction_hashes.insert(2, vec![tx_2.clone()]);
+
+		{
+			let tracedb = TraceDB::new(config.clone(), db.clone(), Arc::new(extras.clone()));
+			for (number, hash) in vec![(1, block_1.clone()), (2, block_2.clone())] {
+				let request = create_simple_import_request(number, hash);
+				let mut batch = DBTransaction::new();
+				tracedb.import(&mut batch, request);
+				db.key_value().write(batch).unwrap();
+			}
+		}
+
+		assert!(address_traces(&db, Address::from(1), 1).is_none());
+
+		config.address_index = true;
+		let tracedb = TraceDB::new(config.clone(), db.clone(), Arc::new(extras.clone()));
+
+		assert_eq!(tracedb.backfill_address_index(2, 1), 1);
+		assert!(address_traces(&db, Address::from(1), 1).is_none());
+		assert_eq!(address_traces(&db, Address::from(1), 2).unwrap().block_hash, block_2);
+
+		assert_eq!(tracedb.backfill_address_index(2, 1), 0);
+		assert_eq!(address_traces(&db, Address::from(1), 1).unwrap().block_hash, block_1);
+
+		// the backfill cursor is persisted.
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+		assert_eq!(tracedb.backfill_address_index(2, 1), 0);
+
+		let filter = Filter {
+			range: (1..2),
+			from_address: AddressesFilter::from(vec![Address::from(1)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+
+		let traces = tracedb.filter(&filter);
+		assert_eq!(traces.len(), 2);
+		assert_eq!(traces[0], create_simple_localized_trace(1, block_1, tx_1));
+		assert_eq!(traces[1], create_simple_localized_trace(2, block_2, tx_2));
+	}
 }
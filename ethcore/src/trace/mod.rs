@@ -21,12 +21,14 @@ mod db;
 mod executive_tracer;
 mod import;
 mod noop_tracer;
+mod struct_logger;
 mod types;
 
 pub use self::config::Config;
 pub use self::db::TraceDB;
 pub use self::noop_tracer::{NoopTracer, NoopVMTracer};
 pub use self::executive_tracer::{ExecutiveTracer, ExecutiveVMTracer};
+pub use self::struct_logger::{StructLog, StructLogger, StructLoggerConfig};
 pub use self::import::ImportRequest;
 pub use self::localized::LocalizedTrace;
 
@@ -88,8 +90,8 @@ pub trait VMTracer: Send {
 	/// Trace the finalised execution of a single valid instruction.
 	fn trace_executed(&mut self, _gas_used: U256, _stack_push: &[U256], _mem: &[u8]) {}
 
-	/// Spawn subtracer which will be used to trace deeper levels of execution.
-	fn prepare_subtrace(&mut self, _code: &[u8]) {}
+	/// Spawn subtracer which will be used to trace deeper levels of execution of the code at `code_address`.
+	fn prepare_subtrace(&mut self, _code_address: &Address, _code: &[u8]) {}
 
 	/// Finalize subtracer.
 	fn done_subtrace(&mut self) {}
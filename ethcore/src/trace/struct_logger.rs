@@ -0,0 +1,240 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Geth-compatible struct logger.
+
+use std::collections::BTreeMap;
+use std::mem;
+use ethereum_types::{H256, U256, Address};
+use evm::Instruction;
+use trace::VMTracer;
+
+/// Configuration of the struct logger; mirrors geth's `LogConfig`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StructLoggerConfig {
+	/// Do not record the stack.
+	pub disable_stack: bool,
+	/// Do not record the memory.
+	pub disable_memory: bool,
+	/// Do not record the storage.
+	pub disable_storage: bool,
+}
+
+/// A single step of the struct logger, captured before the instruction is executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLog {
+	/// Program counter.
+	pub pc: usize,
+	/// The instruction.
+	pub instruction: u8,
+	/// Gas remaining before the instruction is executed.
+	pub gas: U256,
+	/// Gas cost of the instruction.
+	pub gas_cost: U256,
+	/// Call depth, starting at 1 for the outermost call.
+	pub depth: usize,
+	/// Stack before execution, bottom first.
+	pub stack: Option<Vec<U256>>,
+	/// Memory before execution.
+	pub memory: Option<Vec<u8>>,
+	/// Storage of the executing contract touched so far.
+	pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+impl StructLog {
+	/// Mnemonic name of the instruction, or `None` if it is invalid.
+	pub fn op_name(&self) -> Option<&'static str> {
+		Instruction::from_u8(self.instruction).map(|i| i.info().name)
+	}
+}
+
+// Interpreter state of a single call frame, reconstructed from the tracer hooks.
+#[derive(Default)]
+struct Frame {
+	stack: Vec<U256>,
+	memory: Vec<u8>,
+	storage: BTreeMap<H256, H256>,
+	// instruction being executed, the index of its log and the key of an `SLOAD`.
+	pending: Option<(u8, usize, Option<H256>)>,
+}
+
+/// VM tracer producing geth-style struct logs.
+pub struct StructLogger {
+	config: StructLoggerConfig,
+	logs: Vec<StructLog>,
+	frames: Vec<Frame>,
+	current_gas: U256,
+}
+
+impl StructLogger {
+	/// Create a new top-level instance.
+	pub fn new(config: StructLoggerConfig) -> Self {
+		StructLogger {
+			config,
+			logs: Vec::new(),
+			frames: Vec::new(),
+			current_gas: U256::zero(),
+		}
+	}
+}
+
+impl VMTracer for StructLogger {
+	type Output = Vec<StructLog>;
+
+	fn trace_next_instruction(&mut self, _pc: usize, _instruction: u8, current_gas: U256) -> bool {
+		self.current_gas = current_gas;
+		true
+	}
+
+	fn trace_prepare_execute(&mut self, pc: usize, instruction: u8, gas_cost: U256, _mem_written: Option<(usize, usize)>, store_written: Option<(U256, U256)>) {
+		let depth = self.frames.len();
+		let config = self.config;
+		let index = self.logs.len();
+		let frame = match self.frames.last_mut() {
+			Some(frame) => frame,
+			None => return,
+		};
+
+		if let Some((key, value)) = store_written {
+			frame.storage.insert(key.into(), value.into());
+		}
+
+		let sload_key = match Instruction::from_u8(instruction) {
+			Some(Instruction::SLOAD) => frame.stack.last().map(|key| H256::from(*key)),
+			_ => None,
+		};
+
+		self.logs.push(StructLog {
+			pc,
+			instruction,
+			gas: self.current_gas,
+			gas_cost,
+			depth,
+			stack: if config.disable_stack { None } else { Some(frame.stack.clone()) },
+			memory: if config.disable_memory { None } else { Some(frame.memory.clone()) },
+			storage: if config.disable_storage { None } else { Some(frame.storage.clone()) },
+		});
+		frame.pending = Some((instruction, index, sload_key));
+	}
+
+	fn trace_executed(&mut self, _gas_used: U256, stack_push: &[U256], mem: &[u8]) {
+		let frame = match self.frames.last_mut() {
+			Some(frame) => frame,
+			None => return,
+		};
+		let (instruction, index, sload_key) = match frame.pending.take() {
+			Some(pending) => pending,
+			None => return,
+		};
+
+		let args = Instruction::from_u8(instruction).map_or(0, |i| i.info().args);
+		let len = frame.stack.len();
+		frame.stack.truncate(len.saturating_sub(args));
+		frame.stack.extend_from_slice(stack_push);
+
+		if !self.config.disable_memory {
+			frame.memory = mem.to_vec();
+		}
+
+		// geth records the loaded value in the same step as the `SLOAD`.
+		if let (Some(key), Some(value)) = (sload_key, stack_push.first()) {
+			let value = H256::from(*value);
+			frame.storage.insert(key, value);
+			if let Some(storage) = self.logs[index].storage.as_mut() {
+				storage.insert(key, value);
+			}
+		}
+	}
+
+	fn prepare_subtrace(&mut self, _code_address: &Address, _code: &[u8]) {
+		self.frames.push(Frame::default());
+	}
+
+	fn done_subtrace(&mut self) {
+		self.frames.pop();
+	}
+
+	fn drain(mut self) -> Option<Vec<StructLog>> {
+		Some(mem::replace(&mut self.logs, Vec::new()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use evm::Instruction;
+
+	#[test]
+	fn should_reconstruct_stack_memory_and_storage() {
+		let mut logger = StructLogger::new(Default::default());
+		logger.prepare_subtrace(&Address::default(), &[]);
+
+		// PUSH1 0x2a
+		assert!(logger.trace_next_instruction(0, Instruction::PUSH1 as u8, 100.into()));
+		logger.trace_prepare_execute(0, Instruction::PUSH1 as u8, 3.into(), None, None);
+		logger.trace_executed(97.into(), &[0x2a.into()], &[]);
+
+		// PUSH1 0x01
+		logger.trace_next_instruction(2, Instruction::PUSH1 as u8, 97.into());
+		logger.trace_prepare_execute(2, Instruction::PUSH1 as u8, 3.into(), None, None);
+		logger.trace_executed(94.into(), &[1.into()], &[]);
+
+		// SSTORE [0x01] = 0x2a
+		logger.trace_next_instruction(4, Instruction::SSTORE as u8, 94.into());
+		logger.trace_prepare_execute(4, Instruction::SSTORE as u8, 20000.into(), None, Some((1.into(), 0x2a.into())));
+		logger.trace_executed(0.into(), &[], &[0u8; 32]);
+
+		// STOP
+		logger.trace_next_instruction(5, Instruction::STOP as u8, 0.into());
+		logger.trace_prepare_execute(5, Instruction::STOP as u8, 0.into(), None, None);
+		logger.done_subtrace();
+
+		let logs = logger.drain().unwrap();
+		assert_eq!(logs.len(), 4);
+		assert_eq!(logs[0].op_name(), Some("PUSH1"));
+		assert_eq!(logs[0].depth, 1);
+		assert_eq!(logs[0].gas, 100.into());
+		assert_eq!(logs[0].stack, Some(vec![]));
+		assert_eq!(logs[2].stack, Some(vec![0x2a.into(), 1.into()]));
+		assert_eq!(logs[2].storage.as_ref().unwrap().get(&H256::from(1)), Some(&H256::from(0x2a)));
+		assert_eq!(logs[3].stack, Some(vec![]));
+		assert_eq!(logs[3].memory, Some(vec![0u8; 32]));
+	}
+
+	#[test]
+	fn should_track_depth_and_respect_config() {
+		let mut logger = StructLogger::new(StructLoggerConfig {
+			disable_stack: true,
+			disable_memory: true,
+			disable_storage: true,
+		});
+		logger.prepare_subtrace(&Address::default(), &[]);
+
+		logger.trace_next_instruction(0, Instruction::CALL as u8, 1000.into());
+		logger.trace_prepare_execute(0, Instruction::CALL as u8, 700.into(), None, None);
+		logger.prepare_subtrace(&Address::default(), &[]);
+		logger.trace_next_instruction(0, Instruction::STOP as u8, 300.into());
+		logger.trace_prepare_execute(0, Instruction::STOP as u8, 0.into(), None, None);
+		logger.done_subtrace();
+		logger.trace_executed(600.into(), &[1.into()], &[]);
+		logger.done_subtrace();
+
+		let logs = logger.drain().unwrap();
+		assert_eq!(logs.iter().map(|l| l.depth).collect::<Vec<_>>(), vec![1, 2]);
+		assert!(logs.iter().all(|l| l.stack.is_none() && l.memory.is_none() && l.storage.is_none()));
+	}
+}
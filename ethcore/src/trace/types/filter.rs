@@ -46,6 +46,11 @@ impl AddressesFilter {
 		self.list.is_empty()
 	}
 
+	/// Returns searched addresses.
+	pub fn addresses(&self) -> &[Address] {
+		&self.list
+	}
+
 	/// Returns blooms of this addresses filter.
 	pub fn blooms(&self) -> Vec<Bloom> {
 		match self.list.is_empty() {
@@ -18,7 +18,7 @@
 
 use rlp::{Rlp, RlpStream, Decodable, Encodable, DecoderError};
 use heapsize::HeapSizeOf;
-use ethereum_types::Bloom;
+use ethereum_types::{Address, Bloom};
 use super::trace::{Action, Res};
 
 /// Trace localized in vector of traces produced by a single transaction.
@@ -43,6 +43,17 @@ impl FlatTrace {
 	pub fn bloom(&self) -> Bloom {
 		self.action.bloom() | self.result.bloom()
 	}
+
+	/// Returns addresses which can be matched by trace filters.
+	pub fn addresses(&self) -> Vec<Address> {
+		match (&self.action, &self.result) {
+			(&Action::Call(ref call), _) => vec![call.from, call.to],
+			(&Action::Create(ref create), &Res::Create(ref result)) => vec![create.from, result.address],
+			(&Action::Create(ref create), _) => vec![create.from],
+			(&Action::Suicide(ref suicide), _) => vec![suicide.address, suicide.refund_address],
+			(&Action::Reward(ref reward), _) => vec![reward.author],
+		}
+	}
 }
 
 impl HeapSizeOf for FlatTrace {
@@ -23,7 +23,7 @@ use bytes::Bytes;
 use devp2p::NetworkService;
 use network::{NetworkProtocolHandler, NetworkContext, PeerId, ProtocolId,
 	NetworkConfiguration as BasicNetworkConfiguration, NonReservedPeerMode, Error, ErrorKind,
-	ConnectionFilter};
+	ConnectionFilter, BandwidthInfo};
 
 use types::pruning_info::PruningInfo;
 use ethereum_types::{H256, H512, U256};
@@ -37,9 +37,8 @@ use chain::{ChainSync, SyncStatus as EthSyncStatus};
 use std::net::{SocketAddr, AddrParseError};
 use std::str::FromStr;
 use parking_lot::RwLock;
-use chain::{ETH_PROTOCOL_VERSION_63, ETH_PROTOCOL_VERSION_62,
-	PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3,
-	PRIVATE_TRANSACTION_PACKET, SIGNED_PRIVATE_TRANSACTION_PACKET};
+use chain::{ETH_PROTOCOL_VERSION_65, ETH_PROTOCOL_VERSION_64, ETH_PROTOCOL_VERSION_63, ETH_PROTOCOL_VERSION_62,
+	PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3};
 use light::client::AsLightClient;
 use light::Provider;
 use light::net::{
@@ -47,7 +46,6 @@ use light::net::{
 	Capabilities, Handler as LightHandler, EventContext, SampleStore,
 };
 use network::IpFilter;
-use private_tx::PrivateTxHandler;
 use transaction::UnverifiedTransaction;
 
 /// Parity sync protocol
@@ -93,7 +91,7 @@ impl WarpSync {
 }
 
 /// Sync configuration
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct SyncConfig {
 	/// Max blocks to download ahead
 	pub max_download_ahead_blocks: usize,
@@ -107,8 +105,13 @@ pub struct SyncConfig {
 	pub light_subprotocol_name: [u8; 3],
 	/// Fork block to check
 	pub fork_block: Option<(BlockNumber, H256)>,
+	/// Block numbers of the chain's hard forks, used to compute the eth/64 fork identifier.
+	pub hard_forks: Vec<BlockNumber>,
 	/// Enable snapshot sync
 	pub warp_sync: WarpSync,
+	/// Enable state sync by trie node download. With warp sync enabled as well
+	/// it is used only when no snapshots are available.
+	pub state_sync: bool,
 	/// Enable light client server.
 	pub serve_light: bool,
 }
@@ -122,7 +125,9 @@ impl Default for SyncConfig {
 			subprotocol_name: ETH_PROTOCOL,
 			light_subprotocol_name: LIGHT_PROTOCOL,
 			fork_block: None,
+			hard_forks: Vec::new(),
 			warp_sync: WarpSync::Disabled,
+			state_sync: false,
 			serve_light: false,
 		}
 	}
@@ -139,6 +144,12 @@ pub trait SyncProvider: Send + Sync {
 	/// Get the enode if available.
 	fn enode(&self) -> Option<String>;
 
+	/// Get our node record in its "enr:" text form if available.
+	fn enr(&self) -> Option<String>;
+
+	/// Get bandwidth usage of the network if it is running.
+	fn bandwidth(&self) -> Option<BandwidthInfo>;
+
 	/// Returns propagation count for pending transactions.
 	fn transactions_stats(&self) -> BTreeMap<H256, TransactionStats>;
 }
@@ -165,6 +176,10 @@ pub struct PeerInfo {
 	pub remote_address: String,
 	/// Local endpoint address
 	pub local_address: String,
+	/// Node record of the peer, if known
+	pub enr: Option<String>,
+	/// Reputation score of the peer
+	pub reputation: Option<i32>,
 	/// Eth protocol info.
 	pub eth_info: Option<EthProtocolInfo>,
 	/// Light protocol info.
@@ -236,8 +251,6 @@ pub struct Params {
 	pub chain: Arc<BlockChainClient>,
 	/// Snapshot service.
 	pub snapshot_service: Arc<SnapshotService>,
-	/// Private tx service.
-	pub private_tx_handler: Arc<PrivateTxHandler>,
 	/// Light data provider.
 	pub provider: Arc<::light::Provider>,
 	/// Network layer configuration.
@@ -315,7 +328,7 @@ impl EthSync {
 			})
 		};
 
-		let chain_sync = ChainSync::new(params.config, &*params.chain, params.private_tx_handler.clone());
+		let chain_sync = ChainSync::new(params.config.clone(), &*params.chain);
 		let service = NetworkService::new(params.network_config.clone().into_basic()?, connection_filter)?;
 
 		let sync = Arc::new(EthSync {
@@ -361,6 +374,8 @@ impl SyncProvider for EthSync {
 					capabilities: session_info.peer_capabilities.into_iter().map(|c| c.to_string()).collect(),
 					remote_address: session_info.remote_address,
 					local_address: session_info.local_address,
+					enr: session_info.id.and_then(|id| self.network.node_record(&id)),
+					reputation: session_info.id.and_then(|id| self.network.node_reputation(&id)),
 					eth_info: eth_sync.peer_info(&peer_id),
 					pip_info: light_proto.as_ref().and_then(|lp| lp.peer_status(peer_id)).map(Into::into),
 				})
@@ -372,6 +387,14 @@ impl SyncProvider for EthSync {
 		self.network.external_url()
 	}
 
+	fn enr(&self) -> Option<String> {
+		self.network.external_node_record()
+	}
+
+	fn bandwidth(&self) -> Option<BandwidthInfo> {
+		self.network.bandwidth()
+	}
+
 	fn transactions_stats(&self) -> BTreeMap<H256, TransactionStats> {
 		let sync = self.eth_handler.sync.read();
 		sync.transactions_stats()
@@ -497,7 +520,7 @@ impl ChainNotify for EthSync {
 			_ => {},
 		}
 
-		self.network.register_protocol(self.eth_handler.clone(), self.subprotocol_name, &[ETH_PROTOCOL_VERSION_62, ETH_PROTOCOL_VERSION_63])
+		self.network.register_protocol(self.eth_handler.clone(), self.subprotocol_name, &[ETH_PROTOCOL_VERSION_62, ETH_PROTOCOL_VERSION_63, ETH_PROTOCOL_VERSION_64, ETH_PROTOCOL_VERSION_65])
 			.unwrap_or_else(|e| warn!("Error registering ethereum protocol: {:?}", e));
 		// register the warp sync subprotocol
 		self.network.register_protocol(self.eth_handler.clone(), WARP_SYNC_PROTOCOL_ID, &[PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3])
@@ -523,10 +546,8 @@ impl ChainNotify for EthSync {
 			let mut sync_io = NetSyncIo::new(context, &*self.eth_handler.chain, &*self.eth_handler.snapshot_service, &self.eth_handler.overlay);
 			match message_type {
 				ChainMessageType::Consensus(message) => self.eth_handler.sync.write().propagate_consensus_packet(&mut sync_io, message),
-				ChainMessageType::PrivateTransaction(transaction_hash, message) =>
-					self.eth_handler.sync.write().propagate_private_transaction(&mut sync_io, transaction_hash, PRIVATE_TRANSACTION_PACKET, message),
-				ChainMessageType::SignedPrivateTransaction(transaction_hash, message) =>
-					self.eth_handler.sync.write().propagate_private_transaction(&mut sync_io, transaction_hash, SIGNED_PRIVATE_TRANSACTION_PACKET, message),
+				// private transactions are only sent over the `ptx` protocol
+				ChainMessageType::PrivateTransaction(..) | ChainMessageType::SignedPrivateTransaction(..) => {},
 			}
 		});
 	}
@@ -630,8 +651,10 @@ pub struct NetworkConfiguration {
 	pub nat_enabled: bool,
 	/// Enable discovery
 	pub discovery_enabled: bool,
-	/// List of initial node addresses
+	/// List of initial node addresses, either `enode://` urls or `enrtree://` DNS node lists
 	pub boot_nodes: Vec<String>,
+	/// Zone file answering TXT queries for `enrtree://` node lists
+	pub dns_zone_file: Option<String>,
 	/// Use provided node key instead of default
 	pub use_secret: Option<Secret>,
 	/// Max number of connected peers to maintain
@@ -650,6 +673,18 @@ pub struct NetworkConfiguration {
 	pub ip_filter: IpFilter,
 	/// Client version string
 	pub client_version: String,
+	/// Custom key/value pairs advertised in our node record
+	pub node_record_entries: BTreeMap<String, Vec<u8>>,
+	/// Key/value pairs a node record must contain for a non-reserved node to be connected to
+	pub node_record_filter: BTreeMap<String, Vec<u8>>,
+	/// Maximum total upload rate in bytes per second
+	pub max_upload_rate: Option<u64>,
+	/// Maximum total download rate in bytes per second
+	pub max_download_rate: Option<u64>,
+	/// Maximum upload rate to a single peer in bytes per second
+	pub max_peer_upload_rate: Option<u64>,
+	/// Maximum download rate from a single peer in bytes per second
+	pub max_peer_download_rate: Option<u64>,
 }
 
 impl NetworkConfiguration {
@@ -674,6 +709,7 @@ impl NetworkConfiguration {
 			nat_enabled: self.nat_enabled,
 			discovery_enabled: self.discovery_enabled,
 			boot_nodes: self.boot_nodes,
+			dns_zone_file: self.dns_zone_file,
 			use_secret: self.use_secret,
 			max_peers: self.max_peers,
 			min_peers: self.min_peers,
@@ -683,6 +719,12 @@ impl NetworkConfiguration {
 			ip_filter: self.ip_filter,
 			non_reserved_mode: if self.allow_non_reserved { NonReservedPeerMode::Accept } else { NonReservedPeerMode::Deny },
 			client_version: self.client_version,
+			node_record_entries: self.node_record_entries,
+			node_record_filter: self.node_record_filter,
+			max_upload_rate: self.max_upload_rate,
+			max_download_rate: self.max_download_rate,
+			max_peer_upload_rate: self.max_peer_upload_rate,
+			max_peer_download_rate: self.max_peer_download_rate,
 		})
 	}
 }
@@ -698,6 +740,7 @@ impl From<BasicNetworkConfiguration> for NetworkConfiguration {
 			nat_enabled: other.nat_enabled,
 			discovery_enabled: other.discovery_enabled,
 			boot_nodes: other.boot_nodes,
+			dns_zone_file: other.dns_zone_file,
 			use_secret: other.use_secret,
 			max_peers: other.max_peers,
 			min_peers: other.min_peers,
@@ -707,6 +750,12 @@ impl From<BasicNetworkConfiguration> for NetworkConfiguration {
 			ip_filter: other.ip_filter,
 			allow_non_reserved: match other.non_reserved_mode { NonReservedPeerMode::Accept => true, _ => false } ,
 			client_version: other.client_version,
+			node_record_entries: other.node_record_entries,
+			node_record_filter: other.node_record_filter,
+			max_upload_rate: other.max_upload_rate,
+			max_download_rate: other.max_download_rate,
+			max_peer_upload_rate: other.max_peer_upload_rate,
+			max_peer_download_rate: other.max_peer_download_rate,
 		}
 	}
 }
@@ -749,6 +798,12 @@ pub trait LightSyncProvider {
 	/// Get the enode if available.
 	fn enode(&self) -> Option<String>;
 
+	/// Get our node record in its "enr:" text form if available.
+	fn enr(&self) -> Option<String>;
+
+	/// Get bandwidth usage of the network if it is running.
+	fn bandwidth(&self) -> Option<BandwidthInfo>;
+
 	/// Returns propagation count for pending transactions.
 	fn transactions_stats(&self) -> BTreeMap<H256, TransactionStats>;
 }
@@ -921,6 +976,8 @@ impl LightSyncProvider for LightSync {
 					capabilities: session_info.peer_capabilities.into_iter().map(|c| c.to_string()).collect(),
 					remote_address: session_info.remote_address,
 					local_address: session_info.local_address,
+					enr: session_info.id.and_then(|id| self.network.node_record(&id)),
+					reputation: session_info.id.and_then(|id| self.network.node_reputation(&id)),
 					eth_info: None,
 					pip_info: self.proto.peer_status(peer_id).map(Into::into),
 				})
@@ -932,6 +989,14 @@ impl LightSyncProvider for LightSync {
 		self.network.external_url()
 	}
 
+	fn enr(&self) -> Option<String> {
+		self.network.external_node_record()
+	}
+
+	fn bandwidth(&self) -> Option<BandwidthInfo> {
+		self.network.bandwidth()
+	}
+
 	fn network_id(&self) -> u64 {
 		self.network_id
 	}
@@ -20,6 +20,7 @@
 
 use std::collections::{HashSet, VecDeque};
 use std::cmp;
+use std::time::Duration;
 use heapsize::HeapSizeOf;
 use ethereum_types::H256;
 use rlp::{self, Rlp};
@@ -32,18 +33,21 @@ use blocks::{BlockCollection, SyncBody, SyncHeader};
 const MAX_HEADERS_TO_REQUEST: usize = 128;
 const MAX_BODIES_TO_REQUEST: usize = 32;
 const MAX_RECEPITS_TO_REQUEST: usize = 128;
+const MIN_ITEMS_TO_REQUEST: usize = 4;
 const SUBCHAIN_SIZE: u64 = 256;
-const MAX_ROUND_PARENTS: usize = 16;
-const MAX_PARALLEL_SUBCHAIN_DOWNLOAD: usize = 5;
+const MAX_IMPORTED_PARENTS: usize = 16;
+// Responses faster than this let the request size of a peer grow, slower ones shrink it.
+const FAST_RESPONSE: Duration = Duration::from_secs(2);
+const SLOW_RESPONSE: Duration = Duration::from_secs(5);
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 /// Downloader state
 pub enum State {
 	/// No active downloads.
 	Idle,
-	/// Downloading subchain heads
+	/// Downloading the header skeleton, i.e. the subchain heads
 	ChainHead,
-	/// Downloading blocks
+	/// Filling the skeleton with headers, bodies and receipts
 	Blocks,
 	/// Download is complete
 	Complete,
@@ -64,6 +68,74 @@ pub enum BlockRequest {
 	},
 }
 
+/// Kind of data requested from a peer.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RequestKind {
+	Headers,
+	Bodies,
+	Receipts,
+}
+
+/// Number of items to ask a peer for at once. Starts out at the maximum and adapts to
+/// how quickly and how completely the peer serves requests.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RequestSizes {
+	headers: usize,
+	bodies: usize,
+	receipts: usize,
+}
+
+impl Default for RequestSizes {
+	fn default() -> Self {
+		RequestSizes {
+			headers: MAX_HEADERS_TO_REQUEST,
+			bodies: MAX_BODIES_TO_REQUEST,
+			receipts: MAX_RECEPITS_TO_REQUEST,
+		}
+	}
+}
+
+impl RequestSizes {
+	/// Adapt to the time it took the peer to answer a request.
+	pub fn served(&mut self, kind: RequestKind, elapsed: Duration) {
+		let max = RequestSizes::default().get(kind);
+		let size = self.get_mut(kind);
+		if elapsed < FAST_RESPONSE {
+			*size = cmp::min(*size * 2, max);
+		} else if elapsed > SLOW_RESPONSE {
+			*size = cmp::max(*size / 2, MIN_ITEMS_TO_REQUEST);
+		}
+	}
+
+	/// The peer delivered only `delivered` items, probably because of its own response size limit.
+	pub fn capped(&mut self, kind: RequestKind, delivered: usize) {
+		let size = self.get_mut(kind);
+		*size = cmp::max(cmp::min(*size, delivered), MIN_ITEMS_TO_REQUEST);
+	}
+
+	/// The peer did not answer in time.
+	pub fn timed_out(&mut self, kind: RequestKind) {
+		*self.get_mut(kind) = MIN_ITEMS_TO_REQUEST;
+	}
+
+	/// Number of items to request.
+	pub fn get(&self, kind: RequestKind) -> usize {
+		match kind {
+			RequestKind::Headers => self.headers,
+			RequestKind::Bodies => self.bodies,
+			RequestKind::Receipts => self.receipts,
+		}
+	}
+
+	fn get_mut(&mut self, kind: RequestKind) -> &mut usize {
+		match kind {
+			RequestKind::Headers => &mut self.headers,
+			RequestKind::Bodies => &mut self.bodies,
+			RequestKind::Receipts => &mut self.receipts,
+		}
+	}
+}
+
 /// Indicates sync action
 pub enum DownloadAction {
 	/// Do nothing
@@ -76,6 +148,8 @@ pub enum DownloadAction {
 pub enum BlockDownloaderImportError {
 	/// Imported data is rejected as invalid. Peer should be dropped.
 	Invalid,
+	/// Imported block is rejected as invalid. Peer should be dropped.
+	BadBlock,
 	/// Imported data is valid but rejected cause the downloader does not need it.
 	Useless,
 }
@@ -99,13 +173,10 @@ pub struct BlockDownloader {
 	last_imported_block: BlockNumber,
 	/// Last impoted block hash
 	last_imported_hash: H256,
-	/// Number of blocks imported this round
-	imported_this_round: Option<usize>,
-	/// Block number the last round started with.
-	last_round_start: BlockNumber,
-	last_round_start_hash: H256,
-	/// Block parents imported this round (hash, parent)
-	round_parents: VecDeque<(H256, H256)>,
+	/// Block the current header skeleton starts at.
+	skeleton_start: H256,
+	/// Parents of the recently imported blocks (hash, parent)
+	imported_parents: VecDeque<(H256, H256)>,
 	/// Do we need to download block recetips.
 	download_receipts: bool,
 	/// Sync up to the block with this hash.
@@ -114,6 +185,8 @@ pub struct BlockDownloader {
 	retract_step: u64,
 	/// Whether reorg should be limited.
 	limit_reorg: bool,
+	/// Whether the header skeleton is being downloaded.
+	skeleton_requested: bool,
 }
 
 impl BlockDownloader {
@@ -125,15 +198,14 @@ impl BlockDownloader {
 			highest_block: None,
 			last_imported_block: start_number,
 			last_imported_hash: start_hash.clone(),
-			last_round_start: start_number,
-			last_round_start_hash: start_hash.clone(),
+			skeleton_start: start_hash.clone(),
 			blocks: BlockCollection::new(sync_receipts),
-			imported_this_round: None,
-			round_parents: VecDeque::new(),
+			imported_parents: VecDeque::new(),
 			download_receipts: sync_receipts,
 			target_hash: None,
 			retract_step: 1,
 			limit_reorg: true,
+			skeleton_requested: false,
 		}
 	}
 
@@ -144,21 +216,21 @@ impl BlockDownloader {
 			highest_block: None,
 			last_imported_block: start_number,
 			last_imported_hash: start_hash.clone(),
-			last_round_start: start_number,
-			last_round_start_hash: start_hash.clone(),
+			skeleton_start: start_hash.clone(),
 			blocks: BlockCollection::new(sync_receipts),
-			imported_this_round: None,
-			round_parents: VecDeque::new(),
+			imported_parents: VecDeque::new(),
 			download_receipts: sync_receipts,
 			target_hash: None,
 			retract_step: 1,
 			limit_reorg: false,
+			skeleton_requested: false,
 		}
 	}
 
 	/// Reset sync. Clear all local downloaded data.
 	pub fn reset(&mut self) {
 		self.blocks.clear();
+		self.skeleton_requested = false;
 		self.state = State::Idle;
 	}
 
@@ -167,9 +239,6 @@ impl BlockDownloader {
 		if number >= self.last_imported_block + 1 {
 			self.last_imported_block = number;
 			self.last_imported_hash = hash.clone();
-			self.imported_this_round = Some(self.imported_this_round.unwrap_or(0) + 1);
-			self.last_round_start = number;
-			self.last_round_start_hash = hash.clone();
 		}
 	}
 
@@ -190,6 +259,9 @@ impl BlockDownloader {
 
 	/// Unmark header as being downloaded.
 	pub fn clear_header_download(&mut self, hash: &H256) {
+		if self.state == State::ChainHead && *hash == self.last_imported_hash {
+			self.skeleton_requested = false;
+		}
 		self.blocks.clear_header_download(hash)
 	}
 
@@ -202,16 +274,17 @@ impl BlockDownloader {
 	pub fn clear_receipt_download(&mut self, hashes: &[H256]) {
 		self.blocks.clear_receipt_download(hashes)
 	}
-	/// Reset collection for a new sync round with given subchain block hashes.
+	/// Reset collection for a new header skeleton with given subchain block hashes.
 	pub fn reset_to(&mut self, hashes: Vec<H256>) {
 		self.reset();
 		self.blocks.reset_to(hashes);
+		self.skeleton_start = self.last_imported_hash.clone();
 		self.state = State::Blocks;
 	}
 
 	/// Returns used heap memory size.
 	pub fn heap_size(&self) -> usize {
-		self.blocks.heap_size() + self.round_parents.heap_size_of_children()
+		self.blocks.heap_size() + self.imported_parents.heap_size_of_children()
 	}
 
 	/// Returns best imported block number.
@@ -262,7 +335,7 @@ impl BlockDownloader {
 				}
 			};
 
-			// Disable the peer for this syncing round if it gives invalid chain
+			// Disable the peer if it gives invalid chain
 			if !valid_response {
 				debug!(target: "sync", "Invalid headers response");
 				return Err(BlockDownloaderImportError::Invalid);
@@ -314,6 +387,9 @@ impl BlockDownloader {
 						trace!(target: "sync", "No common block, disabling peer");
 						return Err(BlockDownloaderImportError::Invalid);
 					}
+					// The peer doesn't know the skeleton start, so it is not on its chain.
+					self.retract(io);
+					self.reset();
 				}
 			},
 			State::Blocks => {
@@ -389,66 +465,61 @@ impl BlockDownloader {
 		Ok(())
 	}
 
-	fn start_sync_round(&mut self, io: &mut SyncIo) {
-		self.state = State::ChainHead;
-		trace!(target: "sync", "Starting round (last imported count = {:?}, last started = {}, block = {:?}", self.imported_this_round, self.last_round_start, self.last_imported_block);
-		// Check if need to retract to find the common block. The problem is that the peers still return headers by hash even
-		// from the non-canonical part of the tree. So we also retract if nothing has been imported last round.
-		let start = self.last_round_start;
-		let start_hash = self.last_round_start_hash;
-		match self.imported_this_round {
-			Some(n) if n == 0 && start > 0 => {
-				// nothing was imported last round, step back to a previous block
-				// search parent in last round known parents first
-				if let Some(&(_, p)) = self.round_parents.iter().find(|&&(h, _)| h == start_hash) {
-					self.last_imported_block = start - 1;
-					self.last_imported_hash = p.clone();
-					trace!(target: "sync", "Searching common header from the last round {} ({})", self.last_imported_block, self.last_imported_hash);
-				} else {
-					let best = io.chain().chain_info().best_block_number;
-					let oldest_reorg = io.chain().pruning_info().earliest_state;
-					if self.limit_reorg && best > start && start < oldest_reorg {
-						debug!(target: "sync", "Could not revert to previous ancient block, last: {} ({})", start, start_hash);
+	/// Steps back from the last imported block to search for the common block with the peers.
+	/// Called when a header skeleton yields no blocks past its start, as peers still return
+	/// headers by hash even from the non-canonical part of the tree.
+	fn retract(&mut self, io: &mut SyncIo) {
+		let start = self.last_imported_block;
+		let start_hash = self.last_imported_hash;
+		if start == 0 {
+			return;
+		}
+
+		// search parent in recently imported parents first
+		if let Some(&(_, p)) = self.imported_parents.iter().find(|&&(h, _)| h == start_hash) {
+			self.last_imported_block = start - 1;
+			self.last_imported_hash = p.clone();
+			trace!(target: "sync", "Searching common header from the imported blocks {} ({})", self.last_imported_block, self.last_imported_hash);
+		} else {
+			let best = io.chain().chain_info().best_block_number;
+			let oldest_reorg = io.chain().pruning_info().earliest_state;
+			if self.limit_reorg && best > start && start < oldest_reorg {
+				debug!(target: "sync", "Could not revert to previous ancient block, last: {} ({})", start, start_hash);
+				self.reset();
+			} else {
+				let n = start - cmp::min(self.retract_step, start);
+				self.retract_step *= 2;
+				match io.chain().block_hash(BlockId::Number(n)) {
+					Some(h) => {
+						self.last_imported_block = n;
+						self.last_imported_hash = h;
+						trace!(target: "sync", "Searching common header in the blockchain {} ({})", start, self.last_imported_hash);
+					}
+					None => {
+						debug!(target: "sync", "Could not revert to previous block, last: {} ({})", start, self.last_imported_hash);
 						self.reset();
-					} else {
-						let n = start - cmp::min(self.retract_step, start);
-						self.retract_step *= 2;
-						match io.chain().block_hash(BlockId::Number(n)) {
-							Some(h) => {
-								self.last_imported_block = n;
-								self.last_imported_hash = h;
-								trace!(target: "sync", "Searching common header in the blockchain {} ({})", start, self.last_imported_hash);
-							}
-							None => {
-								debug!(target: "sync", "Could not revert to previous block, last: {} ({})", start, self.last_imported_hash);
-								self.reset();
-							}
-						}
 					}
 				}
-			},
-			_ => {
-				self.retract_step = 1;
-			},
+			}
 		}
-		self.last_round_start = self.last_imported_block;
-		self.last_round_start_hash = self.last_imported_hash;
-		self.imported_this_round = None;
 	}
 
-	/// Find some headers or blocks to download for a peer.
-	pub fn request_blocks(&mut self, io: &mut SyncIo, num_active_peers: usize) -> Option<BlockRequest> {
+	/// Find some headers or blocks to download for a peer. The header skeleton is only requested
+	/// from the best peer, everything else is spread over all peers in `sizes` chunks.
+	pub fn request_blocks(&mut self, io: &mut SyncIo, sizes: &RequestSizes, best_peer: bool) -> Option<BlockRequest> {
 		match self.state {
 			State::Idle => {
-				self.start_sync_round(io);
-				if self.state == State::ChainHead {
-					return self.request_blocks(io, num_active_peers);
-				}
+				trace!(target: "sync", "Starting header skeleton at {} ({})", self.last_imported_block, self.last_imported_hash);
+				self.state = State::ChainHead;
+				self.skeleton_requested = false;
+				return self.request_blocks(io, sizes, best_peer);
 			},
 			State::ChainHead => {
-				if num_active_peers < MAX_PARALLEL_SUBCHAIN_DOWNLOAD {
+				if best_peer && !self.skeleton_requested {
 					// Request subchain headers
 					trace!(target: "sync", "Starting sync with better chain");
+					self.skeleton_requested = true;
+					self.skeleton_start = self.last_imported_hash.clone();
 					// Request MAX_HEADERS_TO_REQUEST - 2 headers apart so that
 					// MAX_HEADERS_TO_REQUEST would include headers for neighbouring subchains
 					return Some(BlockRequest::Headers {
@@ -460,7 +531,7 @@ impl BlockDownloader {
 			},
 			State::Blocks => {
 				// check to see if we need to download any block bodies first
-				let needed_bodies = self.blocks.needed_bodies(MAX_BODIES_TO_REQUEST, false);
+				let needed_bodies = self.blocks.needed_bodies(sizes.get(RequestKind::Bodies), false);
 				if !needed_bodies.is_empty() {
 					return Some(BlockRequest::Bodies {
 						hashes: needed_bodies,
@@ -468,7 +539,7 @@ impl BlockDownloader {
 				}
 
 				if self.download_receipts {
-					let needed_receipts = self.blocks.needed_receipts(MAX_RECEPITS_TO_REQUEST, false);
+					let needed_receipts = self.blocks.needed_receipts(sizes.get(RequestKind::Receipts), false);
 					if !needed_receipts.is_empty() {
 						return Some(BlockRequest::Receipts {
 							hashes: needed_receipts,
@@ -477,7 +548,7 @@ impl BlockDownloader {
 				}
 
 				// find subchain to download
-				if let Some((h, count)) = self.blocks.needed_headers(MAX_HEADERS_TO_REQUEST, false) {
+				if let Some((h, count)) = self.blocks.needed_headers(sizes.get(RequestKind::Headers), false) {
 					return Some(BlockRequest::Headers {
 						start: h,
 						count: count as u64,
@@ -553,15 +624,20 @@ impl BlockDownloader {
 			}
 		}
 		trace!(target: "sync", "Imported {} of {}", imported.len(), count);
-		self.imported_this_round = Some(self.imported_this_round.unwrap_or(0) + imported.len());
 
 		if bad {
 			return Err(BlockDownloaderImportError::Invalid);
 		}
 
 		if self.blocks.is_empty() {
-			// complete sync round
-			trace!(target: "sync", "Sync round complete");
+			if self.state == State::Blocks {
+				trace!(target: "sync", "Header skeleton complete");
+				if self.last_imported_hash == self.skeleton_start {
+					self.retract(io);
+				} else {
+					self.retract_step = 1;
+				}
+			}
 			self.reset();
 		}
 		Ok(())
@@ -570,9 +646,9 @@ impl BlockDownloader {
 	fn block_imported(&mut self, hash: &H256, number: BlockNumber, parent: &H256) {
 		self.last_imported_block = number;
 		self.last_imported_hash = hash.clone();
-		self.round_parents.push_back((hash.clone(), parent.clone()));
-		if self.round_parents.len() > MAX_ROUND_PARENTS {
-			self.round_parents.pop_front();
+		self.imported_parents.push_back((hash.clone(), parent.clone()));
+		if self.imported_parents.len() > MAX_IMPORTED_PARENTS {
+			self.imported_parents.pop_front();
 		}
 	}
 }
@@ -595,7 +671,7 @@ fn all_expected<A, B, F>(values: &[A], expected_values: &[B], is_expected: F) ->
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use ethcore::client::TestBlockChainClient;
+	use ethcore::client::{TestBlockChainClient, BlockChainClient, EachBlockWith};
 	use ethcore::header::Header as BlockHeader;
 	use ethcore::spec::Spec;
 	use ethkey::{Generator,Random};
@@ -623,6 +699,83 @@ mod tests {
 		Transaction::default().sign(keypair.secret(), None)
 	}
 
+	#[test]
+	fn request_sizes_adapt() {
+		let mut sizes = RequestSizes::default();
+		sizes.served(RequestKind::Bodies, Duration::from_secs(1));
+		assert_eq!(sizes.get(RequestKind::Bodies), MAX_BODIES_TO_REQUEST);
+
+		sizes.served(RequestKind::Bodies, Duration::from_secs(6));
+		assert_eq!(sizes.get(RequestKind::Bodies), MAX_BODIES_TO_REQUEST / 2);
+		sizes.capped(RequestKind::Bodies, 10);
+		assert_eq!(sizes.get(RequestKind::Bodies), 10);
+		sizes.served(RequestKind::Bodies, Duration::from_secs(1));
+		assert_eq!(sizes.get(RequestKind::Bodies), 20);
+
+		sizes.timed_out(RequestKind::Receipts);
+		assert_eq!(sizes.get(RequestKind::Receipts), MIN_ITEMS_TO_REQUEST);
+		assert_eq!(sizes.get(RequestKind::Headers), MAX_HEADERS_TO_REQUEST);
+	}
+
+	#[test]
+	fn skeleton_is_requested_from_best_peer_once() {
+		let spec = Spec::new_test();
+		let genesis_hash = spec.genesis_header().hash();
+
+		let mut chain = TestBlockChainClient::new();
+		let snapshot_service = TestSnapshotService::new();
+		let queue = RwLock::new(VecDeque::new());
+		let mut io = TestIo::new(&mut chain, &snapshot_service, &queue, None);
+
+		let sizes = RequestSizes::default();
+		let mut downloader = BlockDownloader::new(false, &genesis_hash, 0);
+		assert!(downloader.request_blocks(&mut io, &sizes, false).is_none());
+		assert_eq!(downloader.state, State::ChainHead);
+
+		match downloader.request_blocks(&mut io, &sizes, true) {
+			Some(BlockRequest::Headers { start, skip, .. }) => {
+				assert_eq!(start, genesis_hash);
+				assert_eq!(skip, (MAX_HEADERS_TO_REQUEST - 2) as u64);
+			},
+			_ => panic!("expected skeleton request"),
+		}
+		assert!(downloader.request_blocks(&mut io, &sizes, true).is_none());
+
+		// The request failed, another peer may try.
+		downloader.clear_header_download(&genesis_hash);
+		assert!(downloader.request_blocks(&mut io, &sizes, true).is_some());
+	}
+
+	#[test]
+	fn retracts_when_skeleton_start_is_unknown() {
+		let mut chain = TestBlockChainClient::new();
+		chain.add_blocks(10, EachBlockWith::Nothing);
+		let start_hash = chain.block_hash(BlockId::Number(5)).unwrap();
+		let snapshot_service = TestSnapshotService::new();
+		let queue = RwLock::new(VecDeque::new());
+		let mut io = TestIo::new(&mut chain, &snapshot_service, &queue, None);
+
+		let sizes = RequestSizes::default();
+		let mut downloader = BlockDownloader::new(false, &start_hash, 5);
+		let empty = RlpStream::new_list(0).out();
+
+		// The peer doesn't know the skeleton start, the search steps back exponentially.
+		for &expected in &[4, 2] {
+			assert!(downloader.request_blocks(&mut io, &sizes, true).is_some());
+			match downloader.import_headers(&mut io, &Rlp::new(&empty), start_hash) {
+				Ok(DownloadAction::None) => (),
+				_ => panic!("expected empty skeleton to be accepted"),
+			}
+			assert_eq!(downloader.state, State::Idle);
+			assert_eq!(downloader.last_imported_block_number(), expected);
+		}
+
+		match downloader.request_blocks(&mut io, &sizes, true) {
+			Some(BlockRequest::Headers { start, .. }) => assert_eq!(Some(start), io.chain().block_hash(BlockId::Number(2))),
+			_ => panic!("expected skeleton request"),
+		}
+	}
+
 	#[test]
 	fn import_headers_in_chain_head_state() {
 		::env_logger::try_init().ok();
@@ -25,6 +25,7 @@ use network;
 use ethcore::header::Header as BlockHeader;
 use ethcore::verification::queue::kind::blocks::Unverified;
 use transaction::UnverifiedTransaction;
+use HashState;
 
 known_heap_size!(0, HeaderId);
 
@@ -108,7 +109,7 @@ impl HeapSizeOf for SyncBlock {
 	}
 }
 
-fn unverified_from_sync(header: SyncHeader, body: Option<SyncBody>) -> Unverified {
+pub fn unverified_from_sync(header: SyncHeader, body: Option<SyncBody>) -> Unverified {
 	let mut stream = RlpStream::new_list(3);
 	stream.append_raw(&header.bytes, 1);
 	let body = body.unwrap_or_else(SyncBody::empty_body);
@@ -152,9 +153,9 @@ pub struct BlockCollection {
 	/// Downloaded blocks by parent.
 	parents: HashMap<H256, H256>,
 	/// Used to map body to header.
-	header_ids: HashMap<HeaderId, H256>,
+	header_ids: HashMap<HeaderId, H256, HashState>,
 	/// Used to map receipts root to headers.
-	receipt_ids: HashMap<H256, Vec<H256>>,
+	receipt_ids: HashMap<H256, Vec<H256>, HashState>,
 	/// First block in `blocks`.
 	head: Option<H256>,
 	/// Set of block header hashes being downloaded
@@ -171,8 +172,8 @@ impl BlockCollection {
 		BlockCollection {
 			need_receipts: download_receipts,
 			blocks: HashMap::new(),
-			header_ids: HashMap::new(),
-			receipt_ids: HashMap::new(),
+			header_ids: HashMap::default(),
+			receipt_ids: HashMap::default(),
 			heads: Vec::new(),
 			parents: HashMap::new(),
 			head: None,
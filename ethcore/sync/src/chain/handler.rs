@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use api::WARP_SYNC_PROTOCOL_ID;
-use block_sync::{BlockDownloaderImportError as DownloaderImportError, DownloadAction};
+use block_sync::{BlockDownloaderImportError as DownloaderImportError, DownloadAction, RequestKind, RequestSizes};
 use bytes::Bytes;
 use ethcore::client::{BlockId, BlockStatus};
 use ethcore::error::{Error as EthcoreError, ErrorKind as EthcoreErrorKind, ImportErrorKind, BlockError};
@@ -23,14 +23,15 @@ use ethcore::header::BlockNumber;
 use ethcore::snapshot::{ManifestData, RestorationStatus};
 use ethcore::verification::queue::kind::blocks::Unverified;
 use ethereum_types::{H256, U256};
+use fork_id::ForkId;
 use hash::keccak;
-use network::PeerId;
+use network::{PeerId, PeerBehaviour};
 use rlp::Rlp;
 use snapshot::ChunkType;
+use state_sync;
 use std::cmp;
 use std::mem;
 use std::collections::HashSet;
-use std::time::Instant;
 use sync_io::SyncIo;
 
 use super::{
@@ -44,19 +45,24 @@ use super::{
 	SyncState,
 	ETH_PROTOCOL_VERSION_62,
 	ETH_PROTOCOL_VERSION_63,
+	ETH_PROTOCOL_VERSION_64,
+	ETH_PROTOCOL_VERSION_65,
 	MAX_NEW_BLOCK_AGE,
 	MAX_NEW_HASHES,
+	MAX_TRANSACTION_HASHES_TO_ANNOUNCE,
 	PAR_PROTOCOL_VERSION_1,
 	PAR_PROTOCOL_VERSION_3,
 	BLOCK_BODIES_PACKET,
 	BLOCK_HEADERS_PACKET,
 	NEW_BLOCK_HASHES_PACKET,
 	NEW_BLOCK_PACKET,
-	PRIVATE_TRANSACTION_PACKET,
+	NEW_POOLED_TRANSACTION_HASHES_PACKET,
+	NODE_DATA_PACKET,
+	POOLED_TRANSACTIONS_PACKET,
 	RECEIPTS_PACKET,
-	SIGNED_PRIVATE_TRANSACTION_PACKET,
 	SNAPSHOT_DATA_PACKET,
 	SNAPSHOT_MANIFEST_PACKET,
+	SNAPSHOT_RESTORE_THRESHOLD,
 	STATUS_PACKET,
 	TRANSACTIONS_PACKET,
 };
@@ -75,15 +81,16 @@ impl SyncHandler {
 		let result = match packet_id {
 			STATUS_PACKET => SyncHandler::on_peer_status(sync, io, peer, &rlp),
 			TRANSACTIONS_PACKET => SyncHandler::on_peer_transactions(sync, io, peer, &rlp),
+			NEW_POOLED_TRANSACTION_HASHES_PACKET => SyncHandler::on_peer_transaction_hashes(sync, io, peer, &rlp),
+			POOLED_TRANSACTIONS_PACKET => SyncHandler::on_peer_pooled_transactions(sync, io, peer, &rlp),
 			BLOCK_HEADERS_PACKET => SyncHandler::on_peer_block_headers(sync, io, peer, &rlp),
 			BLOCK_BODIES_PACKET => SyncHandler::on_peer_block_bodies(sync, io, peer, &rlp),
 			RECEIPTS_PACKET => SyncHandler::on_peer_block_receipts(sync, io, peer, &rlp),
+			NODE_DATA_PACKET => SyncHandler::on_node_data(sync, io, peer, &rlp),
 			NEW_BLOCK_PACKET => SyncHandler::on_peer_new_block(sync, io, peer, &rlp),
 			NEW_BLOCK_HASHES_PACKET => SyncHandler::on_peer_new_hashes(sync, io, peer, &rlp),
 			SNAPSHOT_MANIFEST_PACKET => SyncHandler::on_snapshot_manifest(sync, io, peer, &rlp),
 			SNAPSHOT_DATA_PACKET => SyncHandler::on_snapshot_data(sync, io, peer, &rlp),
-			PRIVATE_TRANSACTION_PACKET => SyncHandler::on_private_transaction(sync, io, peer, &rlp),
-			SIGNED_PRIVATE_TRANSACTION_PACKET => SyncHandler::on_signed_private_transaction(sync, io, peer, &rlp),
 			_ => {
 				debug!(target: "sync", "{}: Unknown packet {}", peer, packet_id);
 				Ok(())
@@ -93,10 +100,16 @@ impl SyncHandler {
 		match result {
 			Err(DownloaderImportError::Invalid) => {
 				debug!(target:"sync", "{} -> Invalid packet {}", peer, packet_id);
-				io.disable_peer(peer);
+				io.report_peer(peer, PeerBehaviour::InvalidResponse);
+				sync.deactivate_peer(io, peer);
+			},
+			Err(DownloaderImportError::BadBlock) => {
+				debug!(target:"sync", "{} -> Bad block in packet {}", peer, packet_id);
+				io.report_peer(peer, PeerBehaviour::BadBlock);
 				sync.deactivate_peer(io, peer);
 			},
 			Err(DownloaderImportError::Useless) => {
+				io.report_peer(peer, PeerBehaviour::UselessResponse);
 				sync.deactivate_peer(io, peer);
 			},
 			Ok(()) => {
@@ -124,19 +137,9 @@ impl SyncHandler {
 			sync.clear_peer_download(peer_id);
 			sync.peers.remove(&peer_id);
 			sync.active_peers.remove(&peer_id);
-
-			if sync.state == SyncState::SnapshotManifest {
-				// Check if we are asking other peers for
-				// the snapshot manifest as well.
-				// If not, return to initial state
-				let still_asking_manifest = sync.peers.iter()
-					.filter(|&(id, p)| sync.active_peers.contains(id) && p.asking == PeerAsking::SnapshotManifest)
-					.next().is_none();
-
-				if still_asking_manifest {
-					sync.state = ChainSync::get_init_state(sync.warp_sync, io.chain());
-				}
-			}
+			sync.transactions_fetcher.peer_disconnected(peer_id);
+			sync.fetch_announced_transactions(io);
+			sync.check_snapshot_manifest_peers(io);
 			sync.continue_sync(io);
 		}
 	}
@@ -148,7 +151,8 @@ impl SyncHandler {
 			debug!(target:"sync", "Error sending status request: {:?}", e);
 			io.disconnect_peer(peer);
 		} else {
-			sync.handshaking_peers.insert(peer, Instant::now());
+			let now = sync.clock.now();
+			sync.handshaking_peers.insert(peer, now);
 		}
 	}
 
@@ -190,6 +194,7 @@ impl SyncHandler {
 				trace!(target: "sync", "New block already queued {:?}", hash);
 			},
 			Ok(_) => {
+				io.report_peer(peer_id, PeerBehaviour::GoodBlock);
 				// abort current download of the same block
 				sync.complete_sync(io);
 				sync.new_blocks.mark_as_known(&hash, number);
@@ -201,7 +206,7 @@ impl SyncHandler {
 			},
 			Err(e) => {
 				debug!(target: "sync", "Bad new block {:?} : {:?}", hash, e);
-				return Err(DownloaderImportError::Invalid);
+				return Err(DownloaderImportError::BadBlock);
 			}
 		};
 		if unknown {
@@ -289,14 +294,26 @@ impl SyncHandler {
 
 	/// Called by peer once it has new block bodies
 	fn on_peer_block_bodies(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if sync.peers.get(&peer_id).map_or(false, |p| p.asking == PeerAsking::StatePivotBody) {
+			return SyncHandler::on_state_pivot_body(sync, io, peer_id, r);
+		}
 		sync.clear_peer_download(peer_id);
 		let block_set = sync.peers.get(&peer_id)
 			.and_then(|p| p.block_set)
 			.unwrap_or(BlockSet::NewBlocks);
+		let now = sync.clock.now();
+		let (expired, elapsed) = match sync.peers.get(&peer_id) {
+			Some(peer) => (peer.expired, now - peer.ask_time),
+			None => (false, Default::default()),
+		};
 		if !sync.reset_peer_asking(peer_id, PeerAsking::BlockBodies) {
 			trace!(target: "sync", "{}: Ignored unexpected bodies", peer_id);
 			return Ok(());
 		}
+		if expired {
+			trace!(target: "sync", "{}: Ignored expired bodies", peer_id);
+			return Ok(());
+		}
 		let expected_blocks = match sync.peers.get_mut(&peer_id) {
 			Some(peer) => mem::replace(&mut peer.asking_blocks, Vec::new()),
 			None => {
@@ -312,6 +329,7 @@ impl SyncHandler {
 			trace!(target: "sync", "Ignored block bodies while waiting");
 			Ok(())
 		} else {
+			sync.note_response(peer_id, RequestKind::Bodies, expected_blocks.len(), item_count, elapsed);
 			{
 				let downloader = match block_set {
 					BlockSet::NewBlocks => &mut sync.new_blocks,
@@ -371,11 +389,16 @@ impl SyncHandler {
 		if is_fork_header_request {
 			return SyncHandler::on_peer_fork_header(sync, io, peer_id, r);
 		}
+		if sync.peers.get(&peer_id).map_or(false, |p| p.asking == PeerAsking::StatePivot) {
+			return SyncHandler::on_state_pivot_headers(sync, io, peer_id, r);
+		}
 
 		sync.clear_peer_download(peer_id);
 		let expected_hash = sync.peers.get(&peer_id).and_then(|p| p.asking_hash);
 		let allowed = sync.peers.get(&peer_id).map(|p| p.is_allowed()).unwrap_or(false);
 		let block_set = sync.peers.get(&peer_id).and_then(|p| p.block_set).unwrap_or(BlockSet::NewBlocks);
+		let now = sync.clock.now();
+		let elapsed = sync.peers.get(&peer_id).map_or(Default::default(), |p| now - p.ask_time);
 
 		if !sync.reset_peer_asking(peer_id, PeerAsking::BlockHeaders) {
 			debug!(target: "sync", "{}: Ignored unexpected headers", peer_id);
@@ -404,6 +427,8 @@ impl SyncHandler {
 			return Ok(());
 		}
 
+		// Fewer headers than requested only mean that the peer's chain ends there.
+		sync.note_response(peer_id, RequestKind::Headers, item_count, item_count, elapsed);
 		let result = {
 			let downloader = match block_set {
 				BlockSet::NewBlocks => &mut sync.new_blocks,
@@ -432,14 +457,97 @@ impl SyncHandler {
 		Ok(())
 	}
 
+	/// Called by peer once it has the headers back to the state sync pivot
+	fn on_state_pivot_headers(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		let (expected_hash, latest_hash, difficulty) = match sync.peers.get(&peer_id) {
+			Some(peer) => (peer.asking_hash, peer.latest_hash, peer.difficulty),
+			None => return Ok(()),
+		};
+		sync.reset_peer_asking(peer_id, PeerAsking::StatePivot);
+		if sync.state != SyncState::StatePivot {
+			trace!(target: "sync", "{}: Ignored unexpected pivot headers", peer_id);
+			return Ok(());
+		}
+		// The total difficulty is only known for the peer's current best block.
+		let (expected_hash, difficulty) = match (expected_hash, difficulty) {
+			(Some(hash), Some(difficulty)) if hash == latest_hash => (hash, difficulty),
+			_ => {
+				trace!(target: "sync", "{}: Ignored outdated pivot headers", peer_id);
+				return Ok(());
+			}
+		};
+
+		let item_count = r.item_count()?;
+		let (pivot, parent_total_difficulty) = state_sync::pivot_from_headers(r, &expected_hash, difficulty)?;
+		let number = pivot.header.number();
+		trace!(target: "sync", "{} -> BlockHeaders ({} entries), state sync pivot #{} ({:?})", peer_id, item_count, number, pivot.header.hash());
+		let peer_best = number + item_count as BlockNumber - 1;
+		if peer_best > sync.highest_block.unwrap_or(0) {
+			sync.highest_block = Some(peer_best);
+		}
+
+		let best_block = io.chain().chain_info().best_block_number;
+		if sync.state_trie.pivot().is_none() && number < best_block + SNAPSHOT_RESTORE_THRESHOLD {
+			debug!(target: "sync", "Chain is too short for state sync, starting full sync");
+			sync.state_sync = false;
+			sync.state = SyncState::Idle;
+			return Ok(());
+		}
+		sync.state_trie.set_pivot(io.chain(), pivot, parent_total_difficulty);
+		sync.state = SyncState::StateNodes;
+		Ok(())
+	}
+
+	/// Called by peer once it has the state sync pivot block body
+	fn on_state_pivot_body(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		sync.reset_peer_asking(peer_id, PeerAsking::StatePivotBody);
+		trace!(target: "sync", "{} -> BlockBodies ({} entries), state sync pivot", peer_id, r.item_count()?);
+		sync.state_trie.import_body(peer_id, r)?;
+		sync.maybe_complete_state_sync(io);
+		Ok(())
+	}
+
+	/// Called by peer once it has the state sync pivot block receipts
+	fn on_state_pivot_receipts(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		sync.reset_peer_asking(peer_id, PeerAsking::StatePivotReceipts);
+		trace!(target: "sync", "{} -> BlockReceipts ({} entries), state sync pivot", peer_id, r.item_count()?);
+		sync.state_trie.import_receipts(peer_id, r)?;
+		sync.maybe_complete_state_sync(io);
+		Ok(())
+	}
+
+	/// Called by peer once it has state trie nodes
+	fn on_node_data(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if !sync.reset_peer_asking(peer_id, PeerAsking::NodeData) {
+			trace!(target: "sync", "{}: Ignored unexpected node data", peer_id);
+			return Ok(());
+		}
+		let imported = sync.state_trie.import_nodes(io.chain(), peer_id, r)?;
+		trace!(target: "sync", "{} -> NodeData ({} entries), {} imported, {} pending", peer_id, r.item_count()?, imported, sync.state_trie.pending_nodes());
+		sync.maybe_complete_state_sync(io);
+		Ok(())
+	}
+
 	/// Called by peer once it has new block receipts
 	fn on_peer_block_receipts(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if sync.peers.get(&peer_id).map_or(false, |p| p.asking == PeerAsking::StatePivotReceipts) {
+			return SyncHandler::on_state_pivot_receipts(sync, io, peer_id, r);
+		}
 		sync.clear_peer_download(peer_id);
 		let block_set = sync.peers.get(&peer_id).and_then(|p| p.block_set).unwrap_or(BlockSet::NewBlocks);
+		let now = sync.clock.now();
+		let (expired, elapsed) = match sync.peers.get(&peer_id) {
+			Some(peer) => (peer.expired, now - peer.ask_time),
+			None => (false, Default::default()),
+		};
 		if !sync.reset_peer_asking(peer_id, PeerAsking::BlockReceipts) {
 			trace!(target: "sync", "{}: Ignored unexpected receipts", peer_id);
 			return Ok(());
 		}
+		if expired {
+			trace!(target: "sync", "{}: Ignored expired receipts", peer_id);
+			return Ok(());
+		}
 		let expected_blocks = match sync.peers.get_mut(&peer_id) {
 			Some(peer) => mem::replace(&mut peer.asking_blocks, Vec::new()),
 			None => {
@@ -455,6 +563,7 @@ impl SyncHandler {
 			trace!(target: "sync", "Ignored block receipts while waiting");
 			Ok(())
 		} else {
+			sync.note_response(peer_id, RequestKind::Receipts, expected_blocks.len(), item_count, elapsed);
 			{
 				let downloader = match block_set {
 					BlockSet::NewBlocks => &mut sync.new_blocks,
@@ -578,15 +687,15 @@ impl SyncHandler {
 			asking: PeerAsking::Nothing,
 			asking_blocks: Vec::new(),
 			asking_hash: None,
-			ask_time: Instant::now(),
+			ask_time: sync.clock.now(),
 			last_sent_transactions: HashSet::new(),
-			last_sent_private_transactions: HashSet::new(),
 			expired: false,
 			confirmation: if sync.fork_block.is_none() { ForkConfirmation::Confirmed } else { ForkConfirmation::Unconfirmed },
 			asking_snapshot_data: None,
 			snapshot_hash: if warp_protocol { Some(r.val_at(5)?) } else { None },
 			snapshot_number: if warp_protocol { Some(r.val_at(6)?) } else { None },
 			block_set: None,
+			request_sizes: RequestSizes::default(),
 		};
 
 		trace!(target: "sync", "New peer {} (protocol: {}, network: {:?}, difficulty: {:?}, latest:{}, genesis:{}, snapshot:{:?})",
@@ -612,14 +721,22 @@ impl SyncHandler {
 
 		if false
 			|| (warp_protocol && (peer.protocol_version < PAR_PROTOCOL_VERSION_1.0 || peer.protocol_version > PAR_PROTOCOL_VERSION_3.0))
-			|| (!warp_protocol && (peer.protocol_version < ETH_PROTOCOL_VERSION_62.0 || peer.protocol_version > ETH_PROTOCOL_VERSION_63.0))
+			|| (!warp_protocol && (peer.protocol_version < ETH_PROTOCOL_VERSION_62.0 || peer.protocol_version > ETH_PROTOCOL_VERSION_65.0))
 		{
 			trace!(target: "sync", "Peer {} unsupported eth protocol ({})", peer_id, peer.protocol_version);
 			return Err(DownloaderImportError::Invalid);
 		}
 
+		if !warp_protocol && peer.protocol_version >= ETH_PROTOCOL_VERSION_64.0 {
+			let fork_id: ForkId = r.val_at(5)?;
+			if !sync.fork_filter.is_compatible(chain_info.best_block_number, &fork_id) {
+				trace!(target: "sync", "Peer {} incompatible fork (ours: {:?}, theirs: {:?})", peer_id, sync.fork_filter.current(chain_info.best_block_number), fork_id);
+				return Err(DownloaderImportError::Invalid);
+			}
+		}
+
 		if sync.sync_start_time.is_none() {
-			sync.sync_start_time = Some(Instant::now());
+			sync.sync_start_time = Some(sync.clock.now());
 		}
 
 		sync.peers.insert(peer_id.clone(), peer);
@@ -635,82 +752,102 @@ impl SyncHandler {
 		Ok(())
 	}
 
-	/// Called when peer sends us new transactions
-	fn on_peer_transactions(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+	/// Checks if transactions from the peer should be imported.
+	fn accepts_transactions(sync: &ChainSync, io: &SyncIo, peer_id: PeerId) -> bool {
 		// Accept transactions only when fully synced
 		if !io.is_chain_queue_empty() || (sync.state != SyncState::Idle && sync.state != SyncState::NewBlocks) {
 			trace!(target: "sync", "{} Ignoring transactions while syncing", peer_id);
-			return Ok(());
+			return false;
 		}
 		if !sync.peers.get(&peer_id).map_or(false, |p| p.can_sync()) {
 			trace!(target: "sync", "{} Ignoring transactions from unconfirmed/unknown peer", peer_id);
+			return false;
+		}
+		true
+	}
+
+	/// Called when peer sends us new transactions
+	fn on_peer_transactions(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if !SyncHandler::accepts_transactions(sync, io, peer_id) {
 			return Ok(());
 		}
 
 		let item_count = r.item_count()?;
 		trace!(target: "sync", "{:02} -> Transactions ({} entries)", peer_id, item_count);
 		let mut transactions = Vec::with_capacity(item_count);
+		let mut hashes = HashSet::with_capacity(item_count);
 		for i in 0 .. item_count {
 			let rlp = r.at(i)?;
 			let tx = rlp.as_raw().to_vec();
+			hashes.insert(keccak(&tx));
 			transactions.push(tx);
 		}
+		sync.transactions_fetcher.received(&hashes);
 		io.chain().queue_transactions(transactions, peer_id);
 		Ok(())
 	}
 
-	/// Called when peer sends us signed private transaction packet
-	fn on_signed_private_transaction(sync: &mut ChainSync, _io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
-		if !sync.peers.get(&peer_id).map_or(false, |p| p.can_sync()) {
-			trace!(target: "sync", "{} Ignoring packet from unconfirmed/unknown peer", peer_id);
+	/// Called when peer announces new transactions by hash
+	fn on_peer_transaction_hashes(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if !SyncHandler::accepts_transactions(sync, io, peer_id) {
 			return Ok(());
 		}
 
-		trace!(target: "sync", "Received signed private transaction packet from {:?}", peer_id);
-		match sync.private_tx_handler.import_signed_private_transaction(r.as_raw()) {
-			Ok(transaction_hash) => {
-				//don't send the packet back
-				if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
-					peer.last_sent_private_transactions.insert(transaction_hash);
-				}
-			},
-			Err(e) => {
-				trace!(target: "sync", "Ignoring the message, error queueing: {}", e);
+		let item_count = r.item_count()?;
+		trace!(target: "sync", "{:02} -> NewPooledTransactionHashes ({} entries)", peer_id, item_count);
+		if item_count > MAX_TRANSACTION_HASHES_TO_ANNOUNCE {
+			trace!(target: "sync", "{} Too many transaction hashes announced", peer_id);
+			return Err(DownloaderImportError::Invalid);
+		}
+		let mut unknown = Vec::new();
+		for i in 0 .. item_count {
+			let hash: H256 = r.val_at(i)?;
+			if let Some(peer) = sync.peers.get_mut(&peer_id) {
+				// the peer has the transaction, no need to send it back
+				peer.last_sent_transactions.insert(hash);
 			}
- 		}
+			if io.chain().pooled_transaction(&hash).is_none() {
+				unknown.push(hash);
+			}
+		}
+		sync.transactions_fetcher.announced(peer_id, unknown);
+		SyncRequester::request_pooled_transactions(sync, io, peer_id);
 		Ok(())
 	}
 
-	/// Called when peer sends us new private transaction packet
-	fn on_private_transaction(sync: &mut ChainSync, _io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
-		if !sync.peers.get(&peer_id).map_or(false, |p| p.can_sync()) {
-			trace!(target: "sync", "{} Ignoring packet from unconfirmed/unknown peer", peer_id);
-			return Ok(());
+	/// Called when peer sends us requested transactions
+	fn on_peer_pooled_transactions(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		let item_count = r.item_count()?;
+		trace!(target: "sync", "{:02} -> PooledTransactions ({} entries)", peer_id, item_count);
+		let mut transactions = Vec::with_capacity(item_count);
+		let mut hashes = HashSet::with_capacity(item_count);
+		for i in 0 .. item_count {
+			let rlp = r.at(i)?;
+			let tx = rlp.as_raw().to_vec();
+			hashes.insert(keccak(&tx));
+			transactions.push(tx);
 		}
-
-		trace!(target: "sync", "Received private transaction packet from {:?}", peer_id);
-
-		match sync.private_tx_handler.import_private_transaction(r.as_raw()) {
-			Ok(transaction_hash) => {
-				//don't send the packet back
-				if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
-					peer.last_sent_private_transactions.insert(transaction_hash);
-				}
-			},
-			Err(e) => {
-				trace!(target: "sync", "Ignoring the message, error queueing: {}", e);
-			}
- 		}
+		if !sync.transactions_fetcher.response(peer_id, &hashes) {
+			trace!(target: "sync", "{} Unexpected pooled transactions", peer_id);
+		}
+		if let Some(peer) = sync.peers.get_mut(&peer_id) {
+			peer.last_sent_transactions.extend(hashes);
+		}
+		if SyncHandler::accepts_transactions(sync, io, peer_id) {
+			io.chain().queue_transactions(transactions, peer_id);
+		}
+		sync.fetch_announced_transactions(io);
 		Ok(())
 	}
 }
 
 #[cfg(test)]
 mod tests {
-	use ethcore::client::{ChainInfo, EachBlockWith, TestBlockChainClient};
+	use ethcore::client::{BlockChainClient, ChainInfo, EachBlockWith, TestBlockChainClient};
 	use parking_lot::RwLock;
-	use rlp::{Rlp};
+	use rlp::{Rlp, RlpStream};
 	use std::collections::{VecDeque};
+	use api::SyncConfig;
 	use tests::helpers::{TestIo};
 	use tests::snapshot::TestSnapshotService;
 
@@ -720,6 +857,7 @@ mod tests {
 		get_dummy_block,
 		get_dummy_blocks,
 		get_dummy_hashes,
+		insert_dummy_peer,
 	};
 
 	#[test]
@@ -739,6 +877,42 @@ mod tests {
 		assert!(result.is_ok());
 	}
 
+	#[test]
+	fn rejects_peer_with_incompatible_fork_id() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let queue = RwLock::new(VecDeque::new());
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+		io.eth_protocol_versions.insert(1, ETH_PROTOCOL_VERSION_64.0);
+		io.eth_protocol_versions.insert(2, ETH_PROTOCOL_VERSION_64.0);
+		io.par_protocol_versions.insert(1, 0);
+		io.par_protocol_versions.insert(2, 0);
+
+		let chain_info = io.chain().chain_info();
+		let status = |fork_id: ForkId| {
+			let mut packet = RlpStream::new_list(6);
+			packet.append(&ETH_PROTOCOL_VERSION_64.0);
+			packet.append(&SyncConfig::default().network_id);
+			packet.append(&chain_info.total_difficulty);
+			packet.append(&chain_info.best_block_hash);
+			packet.append(&chain_info.genesis_hash);
+			packet.append(&fork_id);
+			packet.out()
+		};
+
+		let foreign = status(ForkId { hash: !sync.fork_filter.current(chain_info.best_block_number).hash, next: 0 });
+		let result = SyncHandler::on_peer_status(&mut sync, &mut io, 1, &Rlp::new(&foreign));
+		assert!(result.is_err());
+		assert!(!sync.peers.contains_key(&1));
+
+		let ours = status(sync.fork_filter.current(chain_info.best_block_number));
+		let result = SyncHandler::on_peer_status(&mut sync, &mut io, 2, &Rlp::new(&ours));
+		assert!(result.is_ok());
+		assert!(sync.peers.contains_key(&2));
+	}
+
 	#[test]
 	fn handles_peer_new_block_malformed() {
 		let mut client = TestBlockChainClient::new();
@@ -776,6 +950,7 @@ mod tests {
 		let result = SyncHandler::on_peer_new_block(&mut sync, &mut io, 0, &block);
 
 		assert!(result.is_ok());
+		assert_eq!(io.reported, vec![(0, PeerBehaviour::GoodBlock)]);
 	}
 
 	#[test]
@@ -811,4 +986,43 @@ mod tests {
 
 		assert!(result.is_ok());
 	}
+
+	#[test]
+	fn fetches_announced_transactions_from_another_peer() {
+		let remote = TestBlockChainClient::new();
+		let tx_hash = remote.insert_transaction_to_queue();
+		let tx = ::rlp::encode(remote.pooled_transaction(&tx_hash).unwrap().signed()).into_vec();
+
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let queue = RwLock::new(VecDeque::new());
+		let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(1), &client);
+		insert_dummy_peer(&mut sync, 1, client.block_hash_delta_minus(1));
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+
+		let mut announcement = RlpStream::new_list(1);
+		announcement.append(&tx_hash);
+		let announcement = announcement.out();
+		SyncHandler::on_peer_transaction_hashes(&mut sync, &mut io, 0, &Rlp::new(&announcement)).unwrap();
+		SyncHandler::on_peer_transaction_hashes(&mut sync, &mut io, 1, &Rlp::new(&announcement)).unwrap();
+
+		// the transaction is requested from the first peer only
+		assert_eq!(1, io.packets.len());
+		assert_eq!(0x09, io.packets[0].packet_id); // GET_POOLED_TRANSACTIONS_PACKET
+		assert_eq!(0, io.packets[0].recipient);
+
+		// and from the second one once the first does not deliver it
+		let empty = RlpStream::new_list(0).out();
+		SyncHandler::on_peer_pooled_transactions(&mut sync, &mut io, 0, &Rlp::new(&empty)).unwrap();
+		assert_eq!(2, io.packets.len());
+		assert_eq!(0x09, io.packets[1].packet_id); // GET_POOLED_TRANSACTIONS_PACKET
+		assert_eq!(1, io.packets[1].recipient);
+
+		let mut response = RlpStream::new_list(1);
+		response.append_raw(&tx, 1);
+		SyncHandler::on_peer_pooled_transactions(&mut sync, &mut io, 1, &Rlp::new(&response.out())).unwrap();
+		assert_eq!(0, sync.transactions_fetcher.pending());
+		assert!(sync.peers[&1].last_sent_transactions.contains(&tx_hash));
+	}
 }
@@ -92,9 +92,9 @@ mod propagator;
 mod requester;
 mod supplier;
 
-use std::sync::Arc;
 use std::collections::{HashSet, HashMap};
 use std::cmp;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use hash::keccak;
 use heapsize::HeapSizeOf;
@@ -103,19 +103,22 @@ use fastmap::H256FastMap;
 use parking_lot::RwLock;
 use bytes::Bytes;
 use rlp::{Rlp, RlpStream, DecoderError};
-use network::{self, PeerId, PacketId};
+use network::{self, PeerId, PacketId, PeerBehaviour};
 use ethcore::header::{BlockNumber};
 use ethcore::client::{BlockChainClient, BlockStatus, BlockId, BlockChainInfo, BlockQueueInfo};
 use ethcore::snapshot::{RestorationStatus};
 use sync_io::SyncIo;
 use super::{WarpSync, SyncConfig};
-use block_sync::{BlockDownloader, BlockDownloaderImportError as DownloaderImportError};
+use block_sync::{BlockDownloader, BlockDownloaderImportError as DownloaderImportError, RequestKind, RequestSizes};
 use rand::Rng;
 use snapshot::{Snapshot};
+use state_sync::StateSync;
 use api::{EthProtocolInfo as PeerInfoDigest, WARP_SYNC_PROTOCOL_ID};
-use private_tx::PrivateTxHandler;
 use transactions_stats::{TransactionsStats, Stats as TransactionStats};
+use transactions_fetcher::TransactionsFetcher;
+use fork_id::ForkFilter;
 use transaction::UnverifiedTransaction;
+use HashState;
 
 use self::handler::SyncHandler;
 use self::propagator::SyncPropagator;
@@ -126,6 +129,10 @@ known_heap_size!(0, PeerInfo);
 
 pub type PacketDecodeError = DecoderError;
 
+/// 65 version of Ethereum protocol (transaction hash announcements added).
+pub const ETH_PROTOCOL_VERSION_65: (u8, u8) = (65, 0x11);
+/// 64 version of Ethereum protocol (fork identifier added to status).
+pub const ETH_PROTOCOL_VERSION_64: (u8, u8) = (64, 0x11);
 /// 63 version of Ethereum protocol.
 pub const ETH_PROTOCOL_VERSION_63: (u8, u8) = (63, 0x11);
 /// 62 version of Ethereum protocol.
@@ -135,6 +142,8 @@ pub const PAR_PROTOCOL_VERSION_1: (u8, u8) = (1, 0x15);
 /// 2 version of Parity protocol (consensus messages added).
 pub const PAR_PROTOCOL_VERSION_2: (u8, u8) = (2, 0x16);
 /// 3 version of Parity protocol (private transactions messages added).
+/// Private transactions are now exchanged over the dedicated `ptx` protocol and
+/// packets `0x16` and `0x17` are ignored.
 pub const PAR_PROTOCOL_VERSION_3: (u8, u8) = (3, 0x18);
 
 pub const MAX_BODIES_TO_SEND: usize = 256;
@@ -142,6 +151,10 @@ pub const MAX_HEADERS_TO_SEND: usize = 512;
 pub const MAX_NODE_DATA_TO_SEND: usize = 1024;
 pub const MAX_RECEIPTS_TO_SEND: usize = 1024;
 pub const MAX_RECEIPTS_HEADERS_TO_SEND: usize = 256;
+pub const MAX_POOLED_TRANSACTIONS_TO_SEND: usize = 256;
+const MAX_POOLED_TRANSACTIONS_TO_REQUEST: usize = 256;
+const MAX_TRANSACTION_HASHES_TO_ANNOUNCE: usize = 4096;
+const MAX_NODE_DATA_TO_REQUEST: usize = 384;
 const MIN_PEERS_PROPAGATION: usize = 4;
 const MAX_PEERS_PROPAGATION: usize = 128;
 const MAX_PEER_LAG_PROPAGATION: BlockNumber = 20;
@@ -162,6 +175,9 @@ pub const BLOCK_HEADERS_PACKET: u8 = 0x04;
 pub const GET_BLOCK_BODIES_PACKET: u8 = 0x05;
 const BLOCK_BODIES_PACKET: u8 = 0x06;
 const NEW_BLOCK_PACKET: u8 = 0x07;
+const NEW_POOLED_TRANSACTION_HASHES_PACKET: u8 = 0x08;
+pub const GET_POOLED_TRANSACTIONS_PACKET: u8 = 0x09;
+pub const POOLED_TRANSACTIONS_PACKET: u8 = 0x0a;
 
 pub const GET_NODE_DATA_PACKET: u8 = 0x0d;
 pub const NODE_DATA_PACKET: u8 = 0x0e;
@@ -173,8 +189,6 @@ pub const SNAPSHOT_MANIFEST_PACKET: u8 = 0x12;
 pub const GET_SNAPSHOT_DATA_PACKET: u8 = 0x13;
 pub const SNAPSHOT_DATA_PACKET: u8 = 0x14;
 pub const CONSENSUS_DATA_PACKET: u8 = 0x15;
-pub const PRIVATE_TRANSACTION_PACKET: u8 = 0x16;
-pub const SIGNED_PRIVATE_TRANSACTION_PACKET: u8 = 0x17;
 
 const MAX_SNAPSHOT_CHUNKS_DOWNLOAD_AHEAD: usize = 3;
 
@@ -186,6 +200,8 @@ const RECEIPTS_TIMEOUT: Duration = Duration::from_secs(10);
 const FORK_HEADER_TIMEOUT: Duration = Duration::from_secs(3);
 const SNAPSHOT_MANIFEST_TIMEOUT: Duration = Duration::from_secs(5);
 const SNAPSHOT_DATA_TIMEOUT: Duration = Duration::from_secs(120);
+const NODE_DATA_TIMEOUT: Duration = Duration::from_secs(10);
+const POOLED_TRANSACTIONS_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 /// Sync state
@@ -198,6 +214,10 @@ pub enum SyncState {
 	SnapshotData,
 	/// Waiting for snapshot restoration progress.
 	SnapshotWaiting,
+	/// Waiting for the header of the state sync pivot block
+	StatePivot,
+	/// Downloading the state trie of the pivot block
+	StateNodes,
 	/// Downloading new blocks
 	Blocks,
 	/// Initial chain sync complete. Waiting for new packets
@@ -279,6 +299,10 @@ pub enum PeerAsking {
 	BlockReceipts,
 	SnapshotManifest,
 	SnapshotData,
+	StatePivot,
+	StatePivotBody,
+	StatePivotReceipts,
+	NodeData,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -324,8 +348,6 @@ pub struct PeerInfo {
 	ask_time: Instant,
 	/// Holds a set of transactions recently sent to this peer to avoid spamming.
 	last_sent_transactions: HashSet<H256>,
-	/// Holds a set of private transactions and their signatures recently sent to this peer to avoid spamming.
-	last_sent_private_transactions: HashSet<H256>,
 	/// Pending request is expired and result should be ignored
 	expired: bool,
 	/// Peer fork confirmation status
@@ -336,6 +358,8 @@ pub struct PeerInfo {
 	snapshot_number: Option<BlockNumber>,
 	/// Block set requested
 	block_set: Option<BlockSet>,
+	/// Number of headers, bodies and receipts to request at once
+	request_sizes: RequestSizes,
 }
 
 impl PeerInfo {
@@ -355,10 +379,6 @@ impl PeerInfo {
 			self.expired = true;
 		}
 	}
-
-	fn reset_private_stats(&mut self) {
-		self.last_sent_private_transactions.clear();
-	}
 }
 
 #[cfg(not(test))]
@@ -368,12 +388,36 @@ pub mod random {
 }
 #[cfg(test)]
 pub mod random {
+	use std::cell::Cell;
 	use rand::{self, SeedableRng};
-	pub fn new() -> rand::XorShiftRng { rand::XorShiftRng::from_seed([0, 1, 2, 3]) }
+
+	thread_local!(static SEED: Cell<[u32; 4]> = Cell::new([0, 1, 2, 3]));
+
+	/// Seed the generators returned by `new` on the current thread.
+	pub fn set_seed(seed: [u32; 4]) {
+		SEED.with(|s| s.set(seed));
+	}
+
+	pub fn new() -> rand::XorShiftRng { rand::XorShiftRng::from_seed(SEED.with(|s| s.get())) }
+}
+
+/// Source of the current time for request timeouts.
+pub trait Clock: Send + Sync {
+	/// Current time.
+	fn now(&self) -> Instant;
+}
+
+/// Wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
 }
 
 pub type RlpResponseResult = Result<Option<(PacketId, RlpStream)>, PacketDecodeError>;
-pub type Peers = HashMap<PeerId, PeerInfo>;
+pub type Peers = HashMap<PeerId, PeerInfo, HashState>;
 
 /// Blockchain sync handler.
 /// See module documentation for more details.
@@ -387,7 +431,7 @@ pub struct ChainSync {
 	/// All connected peers
 	peers: Peers,
 	/// Peers active for current sync round
-	active_peers: HashSet<PeerId>,
+	active_peers: HashSet<PeerId, HashState>,
 	/// Block download process for new blocks
 	new_blocks: BlockDownloader,
 	/// Block download process for ancient blocks
@@ -398,58 +442,77 @@ pub struct ChainSync {
 	network_id: u64,
 	/// Optional fork block to check
 	fork_block: Option<(BlockNumber, H256)>,
+	/// EIP-2124 fork identifier of the chain
+	fork_filter: ForkFilter,
 	/// Snapshot downloader.
 	snapshot: Snapshot,
+	/// State trie downloader.
+	state_trie: StateSync,
 	/// Connected peers pending Status message.
 	/// Value is request timestamp.
-	handshaking_peers: HashMap<PeerId, Instant>,
+	handshaking_peers: HashMap<PeerId, Instant, HashState>,
 	/// Sync start timestamp. Measured when first peer is connected
 	sync_start_time: Option<Instant>,
 	/// Transactions propagation statistics
 	transactions_stats: TransactionsStats,
+	/// Transactions announced by peers and not received yet
+	transactions_fetcher: TransactionsFetcher,
 	/// Enable ancient block downloading
 	download_old_blocks: bool,
-	/// Shared private tx service.
-	private_tx_handler: Arc<PrivateTxHandler>,
 	/// Enable warp sync.
 	warp_sync: WarpSync,
+	/// Enable state sync. Cleared once the state of a pivot block is downloaded or the chain is too short.
+	state_sync: bool,
+	/// Source of the current time.
+	clock: Arc<Clock>,
 }
 
 impl ChainSync {
 	/// Create a new instance of syncing strategy.
-	pub fn new(config: SyncConfig, chain: &BlockChainClient, private_tx_handler: Arc<PrivateTxHandler>) -> ChainSync {
+	pub fn new(config: SyncConfig, chain: &BlockChainClient) -> ChainSync {
 		let chain_info = chain.chain_info();
 		let best_block = chain.chain_info().best_block_number;
-		let state = ChainSync::get_init_state(config.warp_sync, chain);
+		let state = ChainSync::get_init_state(config.warp_sync, config.state_sync, chain);
 
 		let mut sync = ChainSync {
 			state,
 			starting_block: best_block,
 			highest_block: None,
-			peers: HashMap::new(),
-			handshaking_peers: HashMap::new(),
-			active_peers: HashSet::new(),
+			peers: Peers::default(),
+			handshaking_peers: HashMap::default(),
+			active_peers: HashSet::default(),
 			new_blocks: BlockDownloader::new(false, &chain_info.best_block_hash, chain_info.best_block_number),
 			old_blocks: None,
 			last_sent_block_number: 0,
 			network_id: config.network_id,
 			fork_block: config.fork_block,
+			fork_filter: ForkFilter::new(&chain_info.genesis_hash, &config.hard_forks),
 			download_old_blocks: config.download_old_blocks,
 			snapshot: Snapshot::new(),
+			state_trie: StateSync::new(),
 			sync_start_time: None,
 			transactions_stats: TransactionsStats::default(),
-			private_tx_handler,
+			transactions_fetcher: TransactionsFetcher::new(),
 			warp_sync: config.warp_sync,
+			state_sync: config.state_sync,
+			clock: Arc::new(SystemClock),
 		};
 		sync.update_targets(chain);
 		sync
 	}
 
-	fn get_init_state(warp_sync: WarpSync, chain: &BlockChainClient) -> SyncState {
+	/// Replace the clock used for request timeouts.
+	#[cfg(test)]
+	pub fn set_clock(&mut self, clock: Arc<Clock>) {
+		self.clock = clock;
+	}
+
+	fn get_init_state(warp_sync: WarpSync, state_sync: bool, chain: &BlockChainClient) -> SyncState {
 		let best_block = chain.chain_info().best_block_number;
 		match warp_sync {
 			WarpSync::Enabled => SyncState::WaitingPeers,
 			WarpSync::OnlyAndAfter(block) if block > best_block => SyncState::WaitingPeers,
+			_ if state_sync && best_block == 0 => SyncState::WaitingPeers,
 			_ => SyncState::Idle,
 		}
 	}
@@ -505,6 +568,7 @@ impl ChainSync {
 	pub fn abort(&mut self, io: &mut SyncIo) {
 		self.reset_and_continue(io);
 		self.peers.clear();
+		self.transactions_fetcher.clear();
 	}
 
 	/// Reset sync. Clear all downloaded data but keep the queue
@@ -520,7 +584,7 @@ impl ChainSync {
 				}
 			}
 		}
-		self.state = ChainSync::get_init_state(self.warp_sync, io.chain());
+		self.state = ChainSync::get_init_state(self.warp_sync, self.state_sync, io.chain());
 		// Reactivate peers only if some progress has been made
 		// since the last sync round of if starting fresh.
 		self.active_peers = self.peers.keys().cloned().collect();
@@ -534,6 +598,7 @@ impl ChainSync {
 			io.snapshot_service().abort_restore();
 		}
 		self.snapshot.clear();
+		self.state_trie.clear();
 		self.reset(io);
 		self.continue_sync(io);
 	}
@@ -595,7 +660,8 @@ impl ChainSync {
 			(best_hash, max_peers, snapshot_peers)
 		};
 
-		let timeout = (self.state == SyncState::WaitingPeers) && self.sync_start_time.map_or(false, |t| t.elapsed() > WAIT_PEERS_TIMEOUT);
+		let now = self.clock.now();
+		let timeout = (self.state == SyncState::WaitingPeers) && self.sync_start_time.map_or(false, |t| now - t > WAIT_PEERS_TIMEOUT);
 
 		if let (Some(hash), Some(peers)) = (best_hash, best_hash.map_or(None, |h| snapshot_peers.get(&h))) {
 			if max_peers >= SNAPSHOT_MIN_PEERS {
@@ -606,12 +672,48 @@ impl ChainSync {
 				self.start_snapshot_sync(io, peers);
 			}
 		} else if timeout && !self.warp_sync.is_warp_only() {
-			trace!(target: "sync", "No snapshots found, starting full sync");
-			self.state = SyncState::Idle;
+			if self.state_sync {
+				trace!(target: "sync", "No snapshots found, starting state sync");
+				self.state = SyncState::StatePivot;
+			} else {
+				trace!(target: "sync", "No snapshots found, starting full sync");
+				self.state = SyncState::Idle;
+			}
 			self.continue_sync(io);
 		}
 	}
 
+	fn maybe_start_state_sync(&mut self, io: &mut SyncIo) {
+		// With warp sync enabled state sync is only started if there are no snapshots.
+		if !self.state_sync || self.warp_sync.is_enabled() || self.state != SyncState::WaitingPeers {
+			return;
+		}
+		let peers = self.peers.values().filter(|p| p.can_sync()).count();
+		let now = self.clock.now();
+		let timeout = self.sync_start_time.map_or(false, |t| now - t > WAIT_PEERS_TIMEOUT);
+		if peers >= SNAPSHOT_MIN_PEERS || (timeout && peers > 0) {
+			trace!(target: "sync", "Starting state sync with {} peers", peers);
+			self.state = SyncState::StatePivot;
+			self.continue_sync(io);
+		}
+	}
+
+	/// Make the pivot block the best block once its state is downloaded and continue with regular sync.
+	fn maybe_complete_state_sync(&mut self, io: &mut SyncIo) {
+		if self.state != SyncState::StateNodes {
+			return;
+		}
+		if let Some((block, receipts, parent_total_difficulty)) = self.state_trie.take_pivot() {
+			let (number, hash) = (block.header.number(), block.header.hash());
+			match io.chain().import_state_pivot(block, receipts, parent_total_difficulty) {
+				Ok(()) => debug!(target: "sync", "State sync complete at #{} ({:?}), {} nodes", number, hash, self.state_trie.imported_nodes()),
+				Err(e) => warn!(target: "sync", "Error importing state sync pivot #{} ({:?}): {}", number, hash, e),
+			}
+			self.state_sync = false;
+			self.restart(io);
+		}
+	}
+
 	fn start_snapshot_sync(&mut self, io: &mut SyncIo, peers: &[PeerId]) {
 		if !self.snapshot.have_manifest() {
 			for p in peers {
@@ -714,20 +816,26 @@ impl ChainSync {
 			trace!(target: "sync", "Skipping deactivated peer {}", peer_id);
 			return;
 		}
-		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_snapshot_hash) = {
+		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_snapshot_hash, request_sizes) = {
 			if let Some(peer) = self.peers.get_mut(&peer_id) {
 				if peer.asking != PeerAsking::Nothing || !peer.can_sync() {
 					trace!(target: "sync", "Skipping busy peer {}", peer_id);
 					return;
 				}
-				(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned().unwrap_or(0), peer.snapshot_hash.as_ref().cloned())
+				(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned().unwrap_or(0), peer.snapshot_hash.as_ref().cloned(), peer.request_sizes)
 			} else {
 				return;
 			}
 		};
 		let chain_info = io.chain().chain_info();
 		let syncing_difficulty = chain_info.pending_total_difficulty;
-		let num_active_peers = self.peers.values().filter(|p| p.asking != PeerAsking::Nothing).count();
+		// The header skeleton is downloaded from the idle peer with the highest difficulty which has blocks
+		// for us, the skeleton is then filled from all peers in parallel.
+		let best_peer = !self.peers.iter().any(|(id, p)|
+			*id != peer_id && self.active_peers.contains(id) && p.asking == PeerAsking::Nothing && p.can_sync() &&
+				p.difficulty > peer_difficulty && p.difficulty.map_or(true, |pd| pd > syncing_difficulty) &&
+				io.chain().block_status(BlockId::Hash(p.latest_hash)) == BlockStatus::Unknown
+		);
 
 		let higher_difficulty = peer_difficulty.map_or(true, |pd| pd > syncing_difficulty);
 		if force || higher_difficulty || self.old_blocks.is_some() {
@@ -741,6 +849,31 @@ impl ChainSync {
 						peer_id
 					);
 					self.maybe_start_snapshot_sync(io);
+					self.maybe_start_state_sync(io);
+				},
+				SyncState::StatePivot => {
+					// The pivot is taken from the peer with the highest difficulty.
+					if best_peer && peer_difficulty.is_some() && !self.peers.values().any(|p| p.asking == PeerAsking::StatePivot) {
+						SyncRequester::request_state_pivot(self, io, peer_id, &peer_latest);
+					}
+				},
+				SyncState::StateNodes => {
+					if self.state_trie.is_pivot_stale(self.highest_block) {
+						debug!(target: "sync", "State sync pivot {:?} is stale, choosing a new one", self.state_trie.pivot());
+						self.state_trie.clear_pivot();
+						self.state = SyncState::StatePivot;
+						return;
+					}
+					if let Some(hash) = self.state_trie.request_body(peer_id) {
+						SyncRequester::request_state_pivot_body(self, io, peer_id, hash);
+					} else if let Some(hash) = self.state_trie.request_receipts(peer_id) {
+						SyncRequester::request_state_pivot_receipts(self, io, peer_id, hash);
+					} else {
+						let hashes = self.state_trie.request_nodes(peer_id, MAX_NODE_DATA_TO_REQUEST);
+						if !hashes.is_empty() {
+							SyncRequester::request_node_data(self, io, peer_id, hashes);
+						}
+					}
 				},
 				SyncState::Idle | SyncState::Blocks | SyncState::NewBlocks => {
 					if io.chain().queue_info().is_full() {
@@ -753,7 +886,7 @@ impl ChainSync {
 					if !have_latest && (higher_difficulty || force || self.state == SyncState::NewBlocks) {
 						// check if got new blocks to download
 						trace!(target: "sync", "Syncing with peer {}, force={}, td={:?}, our td={}, state={:?}", peer_id, force, peer_difficulty, syncing_difficulty, self.state);
-						if let Some(request) = self.new_blocks.request_blocks(io, num_active_peers) {
+						if let Some(request) = self.new_blocks.request_blocks(io, &request_sizes, best_peer) {
 							SyncRequester::request_blocks(self, io, peer_id, request, BlockSet::NewBlocks);
 							if self.state == SyncState::Idle {
 								self.state = SyncState::Blocks;
@@ -768,7 +901,7 @@ impl ChainSync {
 					});
 
 					if force || last_imported_old_block_difficulty.map_or(true, |ld| peer_difficulty.map_or(true, |pd| pd > ld)) {
-						if let Some(request) = self.old_blocks.as_mut().and_then(|d| d.request_blocks(io, num_active_peers)) {
+						if let Some(request) = self.old_blocks.as_mut().and_then(|d| d.request_blocks(io, &request_sizes, best_peer)) {
 							SyncRequester::request_blocks(self, io, peer_id, request, BlockSet::OldBlocks);
 							return;
 						}
@@ -846,11 +979,72 @@ impl ChainSync {
 						self.snapshot.clear_chunk_download(&hash);
 					}
 				},
+				PeerAsking::StatePivotBody | PeerAsking::StatePivotReceipts | PeerAsking::NodeData => {
+					self.state_trie.clear_download(peer_id);
+				},
 				_ => (),
 			}
 		}
 	}
 
+	/// Adapt the request sizes of a peer to a response of `delivered` out of `requested` items.
+	fn note_response(&mut self, peer_id: PeerId, kind: RequestKind, requested: usize, delivered: usize, elapsed: Duration) {
+		if let Some(peer) = self.peers.get_mut(&peer_id) {
+			peer.request_sizes.served(kind, elapsed);
+			if delivered < requested {
+				peer.request_sizes.capped(kind, delivered);
+			}
+		}
+	}
+
+	/// Give up on the request of a peer which didn't answer it at all. The peer is left out of the
+	/// current sync round, it is only disconnected if the host deems its reputation too bad.
+	fn abandon_peer_request(&mut self, io: &mut SyncIo, peer_id: PeerId) {
+		self.clear_peer_download(peer_id);
+		if let Some(peer) = self.peers.get_mut(&peer_id) {
+			peer.asking = PeerAsking::Nothing;
+			peer.asking_blocks.clear();
+			peer.asking_hash = None;
+			peer.asking_snapshot_data = None;
+			peer.expired = false;
+		}
+		self.deactivate_peer(io, peer_id);
+		self.check_snapshot_manifest_peers(io);
+		self.continue_sync(io);
+	}
+
+	/// Return to the initial state if no active peer is asked for the snapshot manifest anymore.
+	fn check_snapshot_manifest_peers(&mut self, io: &mut SyncIo) {
+		if self.state == SyncState::SnapshotManifest {
+			let still_asking_manifest = self.peers.iter()
+				.filter(|&(id, p)| self.active_peers.contains(id) && p.asking == PeerAsking::SnapshotManifest)
+				.next().is_none();
+
+			if still_asking_manifest {
+				self.state = ChainSync::get_init_state(self.warp_sync, self.state_sync, io.chain());
+			}
+		}
+	}
+
+	/// Hand the blocks a slow peer was asked for over to other peers. The peer gets another timeout
+	/// period to answer before it is disconnected, the late answer is ignored.
+	fn release_peer_request(&mut self, peer_id: PeerId) {
+		self.clear_peer_download(peer_id);
+		let now = self.clock.now();
+		if let Some(peer) = self.peers.get_mut(&peer_id) {
+			let kind = match peer.asking {
+				PeerAsking::BlockHeaders => RequestKind::Headers,
+				PeerAsking::BlockBodies => RequestKind::Bodies,
+				_ => RequestKind::Receipts,
+			};
+			peer.request_sizes.timed_out(kind);
+			peer.asking_blocks.clear();
+			peer.asking_hash = None;
+			peer.expired = true;
+			peer.ask_time = now;
+		}
+	}
+
 	/// Checks if there are blocks fully downloaded that can be imported into the blockchain and does the import.
 	fn collect_blocks(&mut self, io: &mut SyncIo, block_set: BlockSet) {
 		match block_set {
@@ -891,15 +1085,26 @@ impl ChainSync {
 	fn send_status(&mut self, io: &mut SyncIo, peer: PeerId) -> Result<(), network::Error> {
 		let warp_protocol_version = io.protocol_version(&WARP_SYNC_PROTOCOL_ID, peer);
 		let warp_protocol = warp_protocol_version != 0;
-		let protocol = if warp_protocol { warp_protocol_version } else { ETH_PROTOCOL_VERSION_63.0 };
+		let eth_protocol_version = io.eth_protocol_version(peer);
+		let fork_id_protocol = !warp_protocol && eth_protocol_version >= ETH_PROTOCOL_VERSION_64.0;
+		let protocol = if warp_protocol {
+			warp_protocol_version
+		} else if fork_id_protocol {
+			cmp::min(eth_protocol_version, ETH_PROTOCOL_VERSION_65.0)
+		} else {
+			ETH_PROTOCOL_VERSION_63.0
+		};
 		trace!(target: "sync", "Sending status to {}, protocol version {}", peer, protocol);
-		let mut packet = RlpStream::new_list(if warp_protocol { 7 } else { 5 });
+		let mut packet = RlpStream::new_list(if warp_protocol { 7 } else if fork_id_protocol { 6 } else { 5 });
 		let chain = io.chain().chain_info();
 		packet.append(&(protocol as u32));
 		packet.append(&self.network_id);
 		packet.append(&chain.total_difficulty);
 		packet.append(&chain.best_block_hash);
 		packet.append(&chain.genesis_hash);
+		if fork_id_protocol {
+			packet.append(&self.fork_filter.current(chain.best_block_number));
+		}
 		if warp_protocol {
 			let manifest = io.snapshot_service().manifest();
 			let block_number = manifest.as_ref().map_or(0, |m| m.block_number);
@@ -911,8 +1116,9 @@ impl ChainSync {
 	}
 
 	pub fn maintain_peers(&mut self, io: &mut SyncIo) {
-		let tick = Instant::now();
+		let tick = self.clock.now();
 		let mut aborting = Vec::new();
+		let mut retrying = Vec::new();
 		for (peer_id, peer) in &self.peers {
 			let elapsed = tick - peer.ask_time;
 			let timeout = match peer.asking {
@@ -923,24 +1129,56 @@ impl ChainSync {
 				PeerAsking::ForkHeader => elapsed > FORK_HEADER_TIMEOUT,
 				PeerAsking::SnapshotManifest => elapsed > SNAPSHOT_MANIFEST_TIMEOUT,
 				PeerAsking::SnapshotData => elapsed > SNAPSHOT_DATA_TIMEOUT,
+				PeerAsking::StatePivot => elapsed > HEADERS_TIMEOUT,
+				PeerAsking::StatePivotBody => elapsed > BODIES_TIMEOUT,
+				PeerAsking::StatePivotReceipts => elapsed > RECEIPTS_TIMEOUT,
+				PeerAsking::NodeData => elapsed > NODE_DATA_TIMEOUT,
 			};
 			if timeout {
-				debug!(target:"sync", "Timeout {}", peer_id);
-				io.disconnect_peer(*peer_id);
-				aborting.push(*peer_id);
+				match peer.asking {
+					PeerAsking::BlockHeaders | PeerAsking::BlockBodies | PeerAsking::BlockReceipts if !peer.expired => {
+						debug!(target:"sync", "Timeout {}, requesting from other peers", peer_id);
+						io.report_peer(*peer_id, PeerBehaviour::SlowResponse);
+						retrying.push(*peer_id);
+					},
+					_ => {
+						debug!(target:"sync", "Timeout {}", peer_id);
+						io.report_peer(*peer_id, PeerBehaviour::Unresponsive);
+						aborting.push(*peer_id);
+					},
+				}
 			}
 		}
 		for p in aborting {
-			SyncHandler::on_peer_aborting(self, io, p);
+			self.abandon_peer_request(io, p);
+		}
+		if !retrying.is_empty() {
+			for p in retrying {
+				self.release_peer_request(p);
+			}
+			self.continue_sync(io);
 		}
 
-		// Check for handshake timeouts
-		for (peer, &ask_time) in &self.handshaking_peers {
-			let elapsed = (tick - ask_time) / 1_000_000_000;
-			if elapsed > STATUS_TIMEOUT {
-				trace!(target:"sync", "Status timeout {}", peer);
-				io.disconnect_peer(*peer);
+		// Announced transactions which are not delivered in time are fetched from other peers
+		let slow_peers = self.transactions_fetcher.expire(tick - POOLED_TRANSACTIONS_TIMEOUT);
+		if !slow_peers.is_empty() {
+			for p in slow_peers {
+				debug!(target:"sync", "Transactions request timeout {}", p);
+				io.report_peer(p, PeerBehaviour::SlowResponse);
 			}
+			self.fetch_announced_transactions(io);
+		}
+
+		// Check for handshake timeouts
+		let unresponsive: Vec<_> = self.handshaking_peers.iter()
+			.filter(|&(_, &ask_time)| (tick - ask_time) / 1_000_000_000 > STATUS_TIMEOUT)
+			.map(|(peer, _)| *peer)
+			.collect();
+		for peer in unresponsive {
+			trace!(target:"sync", "Status timeout {}", peer);
+			io.report_peer(peer, PeerBehaviour::Unresponsive);
+			// keep waiting, unless the host disconnects the peer
+			self.handshaking_peers.insert(peer, tick);
 		}
 	}
 
@@ -1058,24 +1296,21 @@ impl ChainSync {
 		peers
 	}
 
-	fn get_consensus_peers(&self) -> Vec<PeerId> {
-		self.peers.iter().filter_map(|(id, p)| if p.protocol_version >= PAR_PROTOCOL_VERSION_2.0 { Some(*id) } else { None }).collect()
+	/// Request announced transactions from peers which are not busy with another transactions request.
+	fn fetch_announced_transactions(&mut self, io: &mut SyncIo) {
+		for peer_id in self.transactions_fetcher.idle_announcers() {
+			SyncRequester::request_pooled_transactions(self, io, peer_id);
+		}
 	}
 
-	fn get_private_transaction_peers(&self, transaction_hash: &H256) -> Vec<PeerId> {
-		self.peers.iter().filter_map(
-			|(id, p)| if p.protocol_version >= PAR_PROTOCOL_VERSION_3.0
-				&& !p.last_sent_private_transactions.contains(transaction_hash) {
-					Some(*id)
-				} else {
-					None
-				}
-		).collect()
+	fn get_consensus_peers(&self) -> Vec<PeerId> {
+		self.peers.iter().filter_map(|(id, p)| if p.protocol_version >= PAR_PROTOCOL_VERSION_2.0 { Some(*id) } else { None }).collect()
 	}
 
 	/// Maintain other peers. Send out any new blocks and transactions
 	pub fn maintain_sync(&mut self, io: &mut SyncIo) {
 		self.maybe_start_snapshot_sync(io);
+		self.maybe_start_state_sync(io);
 		self.check_resume(io);
 	}
 
@@ -1098,11 +1333,7 @@ impl ChainSync {
 			// Select random peer to re-broadcast transactions to.
 			let peer = random::new().gen_range(0, self.peers.len());
 			trace!(target: "sync", "Re-broadcasting transactions to a random peer.");
-			self.peers.values_mut().nth(peer).map(|peer_info| {
-					peer_info.last_sent_transactions.clear();
-					peer_info.reset_private_stats()
-				}
-			);
+			self.peers.values_mut().nth(peer).map(|peer_info| peer_info.last_sent_transactions.clear());
 		}
 	}
 
@@ -1140,11 +1371,6 @@ impl ChainSync {
 	pub fn propagate_consensus_packet(&mut self, io: &mut SyncIo, packet: Bytes) {
 		SyncPropagator::propagate_consensus_packet(self, io, packet);
 	}
-
-	/// Broadcast private transaction message to peers.
-	pub fn propagate_private_transaction(&mut self, io: &mut SyncIo, transaction_hash: H256, packet_id: PacketId, packet: Bytes) {
-		SyncPropagator::propagate_private_transaction(self, io, transaction_hash, packet_id, packet);
-	}
 }
 
 #[cfg(test)]
@@ -1164,7 +1390,6 @@ pub mod tests {
 	use ethcore::header::*;
 	use ethcore::client::{BlockChainClient, EachBlockWith, TestBlockChainClient, ChainInfo, BlockInfo};
 	use ethcore::miner::{MinerService, PendingOrdering};
-	use private_tx::NoopPrivateTxHandler;
 
 	pub fn get_dummy_block(order: u32, parent_hash: H256) -> Bytes {
 		let mut header = Header::new();
@@ -1248,7 +1473,7 @@ pub mod tests {
 	}
 
 	pub fn dummy_sync_with_peer(peer_latest_hash: H256, client: &BlockChainClient) -> ChainSync {
-		let mut sync = ChainSync::new(SyncConfig::default(), client, Arc::new(NoopPrivateTxHandler));
+		let mut sync = ChainSync::new(SyncConfig::default(), client);
 		insert_dummy_peer(&mut sync, 0, peer_latest_hash);
 		sync
 	}
@@ -1266,13 +1491,13 @@ pub mod tests {
 				asking_hash: None,
 				ask_time: Instant::now(),
 				last_sent_transactions: HashSet::new(),
-				last_sent_private_transactions: HashSet::new(),
 				expired: false,
 				confirmation: super::ForkConfirmation::Confirmed,
 				snapshot_number: None,
 				snapshot_hash: None,
 				asking_snapshot_data: None,
 				block_set: None,
+				request_sizes: RequestSizes::default(),
 			});
 
 	}
@@ -1289,6 +1514,79 @@ pub mod tests {
 		assert_eq!(1, lagging_peers.len());
 	}
 
+	#[test]
+	fn releases_timed_out_block_request() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Uncle);
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+		{
+			let peer = sync.peers.get_mut(&0).unwrap();
+			peer.asking = PeerAsking::BlockBodies;
+			peer.asking_blocks = vec![H256::from(1u64)];
+			peer.ask_time = Instant::now() - BODIES_TIMEOUT - Duration::from_secs(1);
+		}
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+
+		// The peer stays connected while the bodies can be requested from other peers.
+		sync.maintain_peers(&mut io);
+		assert!(io.to_disconnect.is_empty());
+		{
+			let peer = &sync.peers[&0];
+			assert!(peer.expired);
+			assert!(peer.asking_blocks.is_empty());
+			assert!(peer.request_sizes.get(RequestKind::Bodies) < RequestSizes::default().get(RequestKind::Bodies));
+		}
+
+		// No answer for another timeout period.
+		sync.peers.get_mut(&0).unwrap().ask_time = Instant::now() - BODIES_TIMEOUT - Duration::from_secs(1);
+		sync.maintain_peers(&mut io);
+		assert_eq!(io.reported, vec![(0, PeerBehaviour::SlowResponse), (0, PeerBehaviour::Unresponsive)]);
+		// Disconnecting the peer is left to the host, the request is abandoned.
+		assert_eq!(sync.peers[&0].asking, PeerAsking::Nothing);
+	}
+
+	#[test]
+	fn state_sync_falls_back_to_full_sync_on_short_chain() {
+		let mut client = TestBlockChainClient::new();
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let mut config = SyncConfig::default();
+		config.state_sync = true;
+		let mut sync = ChainSync::new(config, &client);
+		assert_eq!(sync.state, SyncState::WaitingPeers);
+
+		let mut first = Header::new();
+		first.set_number(1);
+		first.set_difficulty(100.into());
+		first.set_parent_hash(client.chain_info().genesis_hash);
+		let mut second = Header::new();
+		second.set_number(2);
+		second.set_difficulty(100.into());
+		second.set_parent_hash(first.hash());
+
+		insert_dummy_peer(&mut sync, 0, second.hash());
+		sync.peers.get_mut(&0).unwrap().difficulty = Some(U256::from(1_000_000_000u64));
+		sync.active_peers.insert(0);
+		sync.state = SyncState::StatePivot;
+
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+		sync.continue_sync(&mut io);
+		assert_eq!(sync.peers[&0].asking, PeerAsking::StatePivot);
+		assert_eq!(io.packets[0].packet_id, GET_BLOCK_HEADERS_PACKET);
+
+		let mut headers = RlpStream::new_list(2);
+		headers.append(&second);
+		headers.append(&first);
+		SyncHandler::on_packet(&mut sync, &mut io, 0, BLOCK_HEADERS_PACKET, &headers.out());
+
+		// A two block chain is not worth a state sync.
+		assert!(!sync.state_sync);
+		assert!(sync.state_trie.pivot().is_none());
+		assert!(sync.state != SyncState::StatePivot && sync.state != SyncState::StateNodes);
+	}
+
 	#[test]
 	fn calculates_tree_for_lagging_peer() {
 		let mut client = TestBlockChainClient::new();
@@ -29,6 +29,8 @@ use transaction::SignedTransaction;
 use super::{
 	random,
 	ChainSync,
+	ETH_PROTOCOL_VERSION_65,
+	MAX_TRANSACTION_HASHES_TO_ANNOUNCE,
 	MAX_TRANSACTION_PACKET_SIZE,
 	MAX_PEER_LAG_PROPAGATION,
 	MAX_PEERS_PROPAGATION,
@@ -36,6 +38,7 @@ use super::{
 	CONSENSUS_DATA_PACKET,
 	NEW_BLOCK_HASHES_PACKET,
 	NEW_BLOCK_PACKET,
+	NEW_POOLED_TRANSACTION_HASHES_PACKET,
 	TRANSACTIONS_PACKET,
 };
 
@@ -62,6 +65,11 @@ fn accepts_service_transaction(client_id: &str) -> bool {
 	ver.len() == 2 && (ver[0] > SERVICE_TRANSACTIONS_VERSION.0 || (ver[0] == SERVICE_TRANSACTIONS_VERSION.0 && ver[1] >= SERVICE_TRANSACTIONS_VERSION.1))
 }
 
+/// Checks if peer fetches transactions announced by hash instead of receiving them in full
+fn accepts_transaction_hashes(io: &SyncIo, peer_id: PeerId) -> bool {
+	io.eth_protocol_version(peer_id) >= ETH_PROTOCOL_VERSION_65.0
+}
+
 /// The Chain Sync Propagator: propagates data to peers
 pub struct SyncPropagator;
 
@@ -126,11 +134,15 @@ impl SyncPropagator {
 			.map(|tx| tx.signed())
 			.partition(|tx| !tx.gas_price.is_zero());
 
-		// usual transactions could be propagated to all peers
+		// usual transactions could be propagated to all peers,
+		// the ones fetching announced transactions get the hashes only
 		let mut affected_peers = HashSet::new();
 		if !transactions.is_empty() {
-			let peers = SyncPropagator::select_peers_for_transactions(sync, |_| true);
+			let peers = SyncPropagator::select_peers_for_transactions(sync, |peer_id| !accepts_transaction_hashes(io, *peer_id));
+			let announcement_peers = sync.peers.keys().cloned().filter(|peer_id| accepts_transaction_hashes(io, *peer_id)).collect();
+			let announced_peers = SyncPropagator::propagate_transaction_hashes_to_peers(sync, io, announcement_peers, &transactions);
 			affected_peers = SyncPropagator::propagate_transactions_to_peers(sync, io, peers, transactions);
+			affected_peers.extend(&announced_peers);
 		}
 
 		// most of times service_transactions will be empty
@@ -246,6 +258,55 @@ impl SyncPropagator {
 		peers
 	}
 
+	fn propagate_transaction_hashes_to_peers(sync: &mut ChainSync, io: &mut SyncIo, peers: Vec<PeerId>, transactions: &[&SignedTransaction]) -> HashSet<PeerId> {
+		let all_transactions_hashes = transactions.iter()
+			.map(|tx| tx.hash())
+			.collect::<HashSet<H256>>();
+		let block_number = io.chain().chain_info().best_block_number;
+
+		let mut announced = HashSet::new();
+		for peer_id in peers {
+			let (packet, sent) = {
+				let stats = &mut sync.transactions_stats;
+				let peer_info = match sync.peers.get_mut(&peer_id) {
+					Some(peer_info) => peer_info,
+					None => continue,
+				};
+
+				let to_announce = transactions.iter()
+					.map(|tx| tx.hash())
+					.filter(|hash| !peer_info.last_sent_transactions.contains(hash))
+					.take(MAX_TRANSACTION_HASHES_TO_ANNOUNCE)
+					.collect::<Vec<_>>();
+				if to_announce.is_empty() {
+					continue;
+				}
+
+				let id = io.peer_session_info(peer_id).and_then(|info| info.id);
+				let mut packet = RlpStream::new_list(to_announce.len());
+				for hash in &to_announce {
+					stats.propagated(hash, id, block_number);
+					packet.append(hash);
+				}
+
+				peer_info.last_sent_transactions = all_transactions_hashes
+					.intersection(&peer_info.last_sent_transactions)
+					.chain(&to_announce)
+					.cloned()
+					.collect();
+				(packet.out(), to_announce.len())
+			};
+
+			SyncPropagator::send_packet(io, peer_id, NEW_POOLED_TRANSACTION_HASHES_PACKET, packet);
+			trace!(target: "sync", "{:02} <- NewPooledTransactionHashes ({} entries)", peer_id, sent);
+			announced.insert(peer_id);
+		}
+		if !announced.is_empty() {
+			debug!(target: "sync", "Announced transactions to {} peers.", announced.len());
+		}
+		announced
+	}
+
 	pub fn propagate_latest_blocks(sync: &mut ChainSync, io: &mut SyncIo, sealed: &[H256]) {
 		let chain_info = io.chain().chain_info();
 		if (((chain_info.best_block_number as i64) - (sync.last_sent_block_number as i64)).abs() as BlockNumber) < MAX_PEER_LAG_PROPAGATION {
@@ -290,18 +351,6 @@ impl SyncPropagator {
 		}
 	}
 
-	/// Broadcast private transaction message to peers.
-	pub fn propagate_private_transaction(sync: &mut ChainSync, io: &mut SyncIo, transaction_hash: H256, packet_id: PacketId, packet: Bytes) {
-		let lucky_peers = ChainSync::select_random_peers(&sync.get_private_transaction_peers(&transaction_hash));
-		trace!(target: "sync", "Sending private transaction packet to {:?}", lucky_peers);
-		for peer_id in lucky_peers {
-			if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
-				peer.last_sent_private_transactions.insert(transaction_hash);
-			}
-			SyncPropagator::send_packet(io, peer_id, packet_id, packet.clone());
-		}
-	}
-
 	fn select_peers_for_transactions<F>(sync: &ChainSync, filter: F) -> Vec<PeerId>
 		where F: Fn(&PeerId) -> bool {
 		// sqrt(x)/x scaled to max u32
@@ -330,7 +379,6 @@ impl SyncPropagator {
 mod tests {
 	use ethcore::client::{BlockInfo, ChainInfo, EachBlockWith, TestBlockChainClient};
 	use parking_lot::RwLock;
-	use private_tx::NoopPrivateTxHandler;
 	use rlp::{Rlp};
 	use std::collections::{VecDeque};
 	use tests::helpers::{TestIo};
@@ -406,7 +454,7 @@ mod tests {
 		client.add_blocks(2, EachBlockWith::Uncle);
 		let queue = RwLock::new(VecDeque::new());
 		let block = client.block(BlockId::Latest).unwrap().into_inner();
-		let mut sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
 		sync.peers.insert(0,
 			PeerInfo {
 				// Messaging protocol
@@ -420,13 +468,13 @@ mod tests {
 				asking_hash: None,
 				ask_time: Instant::now(),
 				last_sent_transactions: HashSet::new(),
-				last_sent_private_transactions: HashSet::new(),
 				expired: false,
 				confirmation: ForkConfirmation::Confirmed,
 				snapshot_number: None,
 				snapshot_hash: None,
 				asking_snapshot_data: None,
 				block_set: None,
+				request_sizes: RequestSizes::default(),
 			});
 		let ss = TestSnapshotService::new();
 		let mut io = TestIo::new(&mut client, &ss, &queue, None);
@@ -494,7 +542,7 @@ mod tests {
 		client.add_blocks(100, EachBlockWith::Uncle);
 		client.insert_transaction_to_queue();
 		// Sync with no peers
-		let mut sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
 		let queue = RwLock::new(VecDeque::new());
 		let ss = TestSnapshotService::new();
 		let mut io = TestIo::new(&mut client, &ss, &queue, None);
@@ -564,7 +612,7 @@ mod tests {
 		let mut client = TestBlockChainClient::new();
 		client.insert_transaction_with_gas_price_to_queue(U256::zero());
 		let block_hash = client.block_hash_delta_minus(1);
-		let mut sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
 		let queue = RwLock::new(VecDeque::new());
 		let ss = TestSnapshotService::new();
 		let mut io = TestIo::new(&mut client, &ss, &queue, None);
@@ -597,7 +645,7 @@ mod tests {
 		let tx1_hash = client.insert_transaction_to_queue();
 		let tx2_hash = client.insert_transaction_with_gas_price_to_queue(U256::zero());
 		let block_hash = client.block_hash_delta_minus(1);
-		let mut sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
 		let queue = RwLock::new(VecDeque::new());
 		let ss = TestSnapshotService::new();
 		let mut io = TestIo::new(&mut client, &ss, &queue, None);
@@ -631,4 +679,35 @@ mod tests {
 		assert!(sent_transactions.iter().any(|tx| tx.hash() == tx1_hash));
 		assert!(sent_transactions.iter().any(|tx| tx.hash() == tx2_hash));
 	}
+
+	#[test]
+	fn announces_transactions_to_peers_fetching_by_hash() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(100, EachBlockWith::Uncle);
+		let tx_hash = client.insert_transaction_to_queue();
+		let block_hash = client.block_hash_delta_minus(1);
+		let mut sync = ChainSync::new(SyncConfig::default(), &client);
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+
+		// when peer#1 speaks eth/63
+		insert_dummy_peer(&mut sync, 1, block_hash);
+		// and peer#2 speaks eth/65
+		insert_dummy_peer(&mut sync, 2, block_hash);
+		io.eth_protocol_versions.insert(2, ETH_PROTOCOL_VERSION_65.0);
+
+		let peer_count = SyncPropagator::propagate_new_transactions(&mut sync, &mut io);
+		let peer_count2 = SyncPropagator::propagate_new_transactions(&mut sync, &mut io);
+
+		assert_eq!(2, peer_count);
+		assert_eq!(0, peer_count2);
+		assert_eq!(2, io.packets.len());
+		// peer#1 receives the full transaction
+		assert!(io.packets.iter().any(|p| p.packet_id == 0x02 && p.recipient == 1)); // TRANSACTIONS_PACKET
+		// peer#2 receives the hash only
+		let announcement = io.packets.iter().find(|p| p.recipient == 2).unwrap();
+		assert_eq!(0x08, announcement.packet_id); // NEW_POOLED_TRANSACTION_HASHES_PACKET
+		assert_eq!(Rlp::new(&*announcement.data).as_list::<H256>().unwrap(), vec![tx_hash]);
+	}
 }
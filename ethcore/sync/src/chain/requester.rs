@@ -21,7 +21,7 @@ use ethcore::header::BlockNumber;
 use ethereum_types::H256;
 use network::{PeerId, PacketId};
 use rlp::RlpStream;
-use std::time::Instant;
+use state_sync::PIVOT_DISTANCE;
 use sync_io::SyncIo;
 
 use super::{
@@ -31,9 +31,12 @@ use super::{
 	ETH_PROTOCOL_VERSION_63,
 	GET_BLOCK_BODIES_PACKET,
 	GET_BLOCK_HEADERS_PACKET,
+	GET_NODE_DATA_PACKET,
+	GET_POOLED_TRANSACTIONS_PACKET,
 	GET_RECEIPTS_PACKET,
 	GET_SNAPSHOT_DATA_PACKET,
 	GET_SNAPSHOT_MANIFEST_PACKET,
+	MAX_POOLED_TRANSACTIONS_TO_REQUEST,
 };
 
 /// The Chain Sync Requester: requesting data to other peers
@@ -124,6 +127,65 @@ impl SyncRequester {
 		peer.block_set = Some(set);
 	}
 
+	/// Request the headers from a peer's best block back to the state sync pivot.
+	pub fn request_state_pivot(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, latest: &H256) {
+		trace!(target: "sync", "{} <- GetBlockHeaders: {} entries back from state sync peer best {}", peer_id, PIVOT_DISTANCE + 1, latest);
+		let mut rlp = RlpStream::new_list(4);
+		rlp.append(latest);
+		rlp.append(&(PIVOT_DISTANCE + 1));
+		rlp.append(&0u32);
+		rlp.append(&1u32);
+		SyncRequester::send_request(sync, io, peer_id, PeerAsking::StatePivot, GET_BLOCK_HEADERS_PACKET, rlp.out());
+		if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
+			peer.asking_hash = Some(latest.clone());
+		}
+	}
+
+	/// Request the state sync pivot block body from a peer.
+	pub fn request_state_pivot_body(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, hash: H256) {
+		trace!(target: "sync", "{} <- GetBlockBodies: state sync pivot {}", peer_id, hash);
+		let mut rlp = RlpStream::new_list(1);
+		rlp.append(&hash);
+		SyncRequester::send_request(sync, io, peer_id, PeerAsking::StatePivotBody, GET_BLOCK_BODIES_PACKET, rlp.out());
+	}
+
+	/// Request the state sync pivot block receipts from a peer.
+	pub fn request_state_pivot_receipts(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, hash: H256) {
+		trace!(target: "sync", "{} <- GetBlockReceipts: state sync pivot {}", peer_id, hash);
+		let mut rlp = RlpStream::new_list(1);
+		rlp.append(&hash);
+		SyncRequester::send_request(sync, io, peer_id, PeerAsking::StatePivotReceipts, GET_RECEIPTS_PACKET, rlp.out());
+	}
+
+	/// Request state trie nodes and code from a peer.
+	pub fn request_node_data(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, hashes: Vec<H256>) {
+		trace!(target: "sync", "{} <- GetNodeData: {} entries starting from {:?}", peer_id, hashes.len(), hashes.first());
+		let mut rlp = RlpStream::new_list(hashes.len());
+		for h in &hashes {
+			rlp.append(h);
+		}
+		SyncRequester::send_request(sync, io, peer_id, PeerAsking::NodeData, GET_NODE_DATA_PACKET, rlp.out());
+	}
+
+	/// Request announced transactions which are not being fetched from other peers. Transaction requests
+	/// are tracked by the transactions fetcher and may be sent while the peer is busy with a sync request.
+	pub fn request_pooled_transactions(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId) {
+		let now = sync.clock.now();
+		let hashes = sync.transactions_fetcher.request(peer_id, MAX_POOLED_TRANSACTIONS_TO_REQUEST, now);
+		if hashes.is_empty() {
+			return;
+		}
+		trace!(target: "sync", "{} <- GetPooledTransactions: {} entries", peer_id, hashes.len());
+		let mut rlp = RlpStream::new_list(hashes.len());
+		for h in &hashes {
+			rlp.append(h);
+		}
+		if let Err(e) = io.send(peer_id, GET_POOLED_TRANSACTIONS_PACKET, rlp.out()) {
+			debug!(target:"sync", "Error sending request: {:?}", e);
+			io.disconnect_peer(peer_id);
+		}
+	}
+
 	/// Request snapshot chunk from a peer.
 	fn request_snapshot_chunk(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, chunk: &H256) {
 		trace!(target: "sync", "{} <- GetSnapshotData {:?}", peer_id, chunk);
@@ -134,12 +196,13 @@ impl SyncRequester {
 
 	/// Generic request sender
 	fn send_request(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, asking: PeerAsking,  packet_id: PacketId, packet: Bytes) {
+		let now = sync.clock.now();
 		if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
 			if peer.asking != PeerAsking::Nothing {
 				warn!(target:"sync", "Asking {:?} while requesting {:?}", peer.asking, asking);
 			}
 			peer.asking = asking;
-			peer.ask_time = Instant::now();
+			peer.ask_time = now;
 			// TODO [ToDr] This seems quite fragile. Be careful when protocol is updated.
 			let result = if packet_id >= ETH_PROTOCOL_VERSION_63.1 {
 				io.send_protocol(WARP_SYNC_PROTOCOL_ID, peer_id, packet_id, packet)
@@ -35,15 +35,19 @@ use super::{
 	GET_BLOCK_BODIES_PACKET,
 	GET_BLOCK_HEADERS_PACKET,
 	GET_NODE_DATA_PACKET,
+	GET_POOLED_TRANSACTIONS_PACKET,
 	GET_RECEIPTS_PACKET,
 	GET_SNAPSHOT_DATA_PACKET,
 	GET_SNAPSHOT_MANIFEST_PACKET,
 	MAX_BODIES_TO_SEND,
 	MAX_HEADERS_TO_SEND,
 	MAX_NODE_DATA_TO_SEND,
+	MAX_POOLED_TRANSACTIONS_TO_SEND,
 	MAX_RECEIPTS_HEADERS_TO_SEND,
 	MAX_RECEIPTS_TO_SEND,
+	MAX_TRANSACTION_PACKET_SIZE,
 	NODE_DATA_PACKET,
+	POOLED_TRANSACTIONS_PACKET,
 	RECEIPTS_PACKET,
 	SNAPSHOT_DATA_PACKET,
 	SNAPSHOT_MANIFEST_PACKET,
@@ -73,6 +77,10 @@ impl SyncSupplier {
 				SyncSupplier::return_node_data,
 				|e| format!("Error sending nodes: {:?}", e)),
 
+			GET_POOLED_TRANSACTIONS_PACKET => SyncSupplier::return_rlp(io, &rlp, peer,
+				SyncSupplier::return_pooled_transactions,
+				|e| format!("Error sending pooled transactions: {:?}", e)),
+
 			GET_SNAPSHOT_MANIFEST_PACKET => SyncSupplier::return_rlp(io, &rlp, peer,
 				SyncSupplier::return_snapshot_manifest,
 				|e| format!("Error sending snapshot manifest: {:?}", e)),
@@ -238,6 +246,33 @@ impl SyncSupplier {
 		Ok(Some((RECEIPTS_PACKET, rlp_result)))
 	}
 
+	/// Respond to GetPooledTransactions request
+	fn return_pooled_transactions(io: &SyncIo, r: &Rlp, peer_id: PeerId) -> RlpResponseResult {
+		let mut count = r.item_count().unwrap_or(0);
+		trace!(target: "sync", "{} -> GetPooledTransactions: {} entries", peer_id, count);
+		if count == 0 {
+			debug!(target: "sync", "Empty GetPooledTransactions request, ignoring.");
+			return Ok(None);
+		}
+		count = cmp::min(count, MAX_POOLED_TRANSACTIONS_TO_SEND);
+		let mut added = 0usize;
+		let mut data = Bytes::new();
+		for i in 0..count {
+			if let Some(tx) = io.chain().pooled_transaction(&r.val_at::<H256>(i)?) {
+				let tx_rlp = ::rlp::encode(tx.signed());
+				if !data.is_empty() && data.len() + tx_rlp.len() > MAX_TRANSACTION_PACKET_SIZE {
+					break;
+				}
+				data.extend_from_slice(&tx_rlp);
+				added += 1;
+			}
+		}
+		trace!(target: "sync", "{} -> GetPooledTransactions: return {} entries", peer_id, added);
+		let mut rlp = RlpStream::new_list(added);
+		rlp.append_raw(&data, added);
+		Ok(Some((POOLED_TRANSACTIONS_PACKET, rlp)))
+	}
+
 	/// Respond to GetSnapshotManifest request
 	fn return_snapshot_manifest(io: &SyncIo, r: &Rlp, peer_id: PeerId) -> RlpResponseResult {
 		let count = r.item_count().unwrap_or(0);
@@ -308,6 +343,7 @@ mod test {
 	use rlp::{Rlp, RlpStream};
 	use super::{*, super::tests::*};
 	use blocks::SyncHeader;
+	use hash::keccak;
 	use ethcore::client::{BlockChainClient, EachBlockWith, TestBlockChainClient};
 
 	#[test]
@@ -407,6 +443,28 @@ mod test {
 		assert_eq!(1, io.packets.len());
 	}
 
+	#[test]
+	fn return_pooled_transactions() {
+		let mut client = TestBlockChainClient::new();
+		let tx_hash = client.insert_transaction_to_queue();
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let io = TestIo::new(&mut client, &ss, &queue, None);
+
+		let mut request = RlpStream::new_list(2);
+		request.append(&tx_hash);
+		request.append(&H256::from("ffffffffffffffffffffffffffffffffffffffffffffaaaaaaaaaaaaaaaaaaaa"));
+		let result = SyncSupplier::return_pooled_transactions(&io, &Rlp::new(&request.out()), 0);
+
+		// only the queued transaction is returned
+		let (packet_id, rlp) = result.unwrap().unwrap();
+		assert_eq!(POOLED_TRANSACTIONS_PACKET, packet_id);
+		let rlp = rlp.out();
+		let rlp = Rlp::new(&rlp);
+		assert_eq!(Ok(1), rlp.item_count());
+		assert_eq!(tx_hash, keccak(rlp.at(0).unwrap().as_raw()));
+	}
+
 	#[test]
 	fn return_receipts_empty() {
 		let mut client = TestBlockChainClient::new();
@@ -0,0 +1,206 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! EIP-2124 fork identifier, exchanged in the eth/64 status message.
+//!
+//! The identifier is a CRC32 checksum of the genesis hash and the fork blocks
+//! the chain has already passed, accompanied by the next fork block known
+//! to the node. It allows peers on incompatible forks of the chain to be
+//! rejected during the handshake.
+
+use ethcore::header::BlockNumber;
+use ethereum_types::H256;
+use rlp::{Encodable, Decodable, DecoderError, RlpStream, Rlp};
+
+/// Fork identifier of a chain at a given head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForkId {
+	/// CRC32 checksum of the genesis hash and the passed fork blocks.
+	pub hash: u32,
+	/// Next upcoming fork block, or zero if no upcoming fork is known.
+	pub next: BlockNumber,
+}
+
+impl Encodable for ForkId {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		let hash = [(self.hash >> 24) as u8, (self.hash >> 16) as u8, (self.hash >> 8) as u8, self.hash as u8];
+		s.begin_list(2);
+		s.append(&&hash[..]);
+		s.append(&self.next);
+	}
+}
+
+impl Decodable for ForkId {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		if rlp.item_count()? != 2 {
+			return Err(DecoderError::RlpIncorrectListLen);
+		}
+
+		let hash: Vec<u8> = rlp.val_at(0)?;
+		if hash.len() != 4 {
+			return Err(DecoderError::RlpInvalidLength);
+		}
+
+		Ok(ForkId {
+			hash: hash.iter().fold(0, |acc, byte| (acc << 8) | *byte as u32),
+			next: rlp.val_at(1)?,
+		})
+	}
+}
+
+/// Computes fork identifiers of the local chain and validates identifiers announced by peers.
+#[derive(Debug, Clone)]
+pub struct ForkFilter {
+	/// Fork blocks in ascending order.
+	forks: Vec<BlockNumber>,
+	/// Checksums of the genesis hash followed by the first `i` fork blocks.
+	sums: Vec<u32>,
+}
+
+impl ForkFilter {
+	/// Create a new filter for a chain with given genesis hash and fork blocks.
+	pub fn new(genesis_hash: &H256, forks: &[BlockNumber]) -> Self {
+		let mut forks: Vec<BlockNumber> = forks.iter().cloned().filter(|&fork| fork != 0).collect();
+		forks.sort();
+		forks.dedup();
+
+		let mut sum = crc32(0, genesis_hash);
+		let mut sums = Vec::with_capacity(forks.len() + 1);
+		sums.push(sum);
+		for fork in &forks {
+			let bytes = [
+				(fork >> 56) as u8, (fork >> 48) as u8, (fork >> 40) as u8, (fork >> 32) as u8,
+				(fork >> 24) as u8, (fork >> 16) as u8, (fork >> 8) as u8, *fork as u8,
+			];
+			sum = crc32(sum, &bytes);
+			sums.push(sum);
+		}
+
+		ForkFilter { forks, sums }
+	}
+
+	/// Number of forks passed at given head.
+	fn passed(&self, head: BlockNumber) -> usize {
+		self.forks.iter().take_while(|&&fork| fork <= head).count()
+	}
+
+	/// Fork identifier of the local chain at given head.
+	pub fn current(&self, head: BlockNumber) -> ForkId {
+		let passed = self.passed(head);
+		ForkId {
+			hash: self.sums[passed],
+			next: self.forks.get(passed).cloned().unwrap_or(0),
+		}
+	}
+
+	/// Checks whether a peer announcing the given fork identifier is on a chain compatible with ours.
+	pub fn is_compatible(&self, head: BlockNumber, remote: &ForkId) -> bool {
+		let passed = self.passed(head);
+		match self.sums.iter().position(|&sum| sum == remote.hash) {
+			// same set of passed forks; reject peers announcing a fork we have already passed without it.
+			Some(i) if i == passed => remote.next == 0 || head < remote.next,
+			// peer is behind us; it has to be aware of the fork following its state.
+			Some(i) if i < passed => remote.next == self.forks[i],
+			// peer is ahead of us, on forks we know about.
+			Some(_) => true,
+			None => false,
+		}
+	}
+}
+
+/// Extends a CRC32 (IEEE) checksum with given data.
+fn crc32(sum: u32, data: &[u8]) -> u32 {
+	let mut crc = !sum;
+	for byte in data {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+		}
+	}
+	!crc
+}
+
+#[cfg(test)]
+mod tests {
+	use rlp;
+	use super::{ForkId, ForkFilter};
+
+	const MAINNET_FORKS: [u64; 6] = [1_150_000, 1_920_000, 2_463_000, 2_675_000, 4_370_000, 7_280_000];
+
+	fn mainnet() -> ForkFilter {
+		ForkFilter::new(&"d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3".into(), &MAINNET_FORKS)
+	}
+
+	#[test]
+	fn computes_mainnet_fork_ids() {
+		let filter = mainnet();
+		let cases = [
+			(0, 0xfc64ec04, 1_150_000),
+			(1_149_999, 0xfc64ec04, 1_150_000),
+			(1_150_000, 0x97c2c34c, 1_920_000),
+			(1_920_000, 0x91d1f948, 2_463_000),
+			(2_463_000, 0x7a64da13, 2_675_000),
+			(2_675_000, 0x3edd5b10, 4_370_000),
+			(4_370_000, 0xa00bc324, 7_280_000),
+			(7_279_999, 0xa00bc324, 7_280_000),
+			(7_280_000, 0x668db0af, 0),
+			(7_987_396, 0x668db0af, 0),
+		];
+
+		for &(head, hash, next) in cases.iter() {
+			assert_eq!(filter.current(head), ForkId { hash, next }, "head {}", head);
+		}
+	}
+
+	#[test]
+	fn validates_remote_fork_ids() {
+		let filter = mainnet();
+		let fork_id = |hash, next| ForkId { hash, next };
+		let cases = [
+			// same fork, no upcoming fork announced.
+			(7_987_396, fork_id(0x668db0af, 0), true),
+			(7_279_999, fork_id(0xa00bc324, 0), true),
+			// same fork, remote knows about a future fork.
+			(7_987_396, fork_id(0x668db0af, u64::max_value()), true),
+			// same fork, remote announces a fork we have already passed.
+			(7_987_396, fork_id(0x668db0af, 7_987_396), false),
+			// remote is behind, but aware of the fork that follows.
+			(7_987_396, fork_id(0xa00bc324, 7_280_000), true),
+			(7_987_396, fork_id(0x3edd5b10, 4_370_000), true),
+			// remote is behind and not aware of the fork that follows.
+			(7_987_396, fork_id(0xa00bc324, 0), false),
+			// remote is ahead of us on known forks.
+			(4_369_999, fork_id(0xa00bc324, 0), true),
+			(4_369_999, fork_id(0x668db0af, 0), true),
+			// remote is on an unknown fork.
+			(7_987_396, fork_id(0x5cddc0e1, 0), false),
+			(7_279_999, fork_id(0xafec6b27, 0), false),
+		];
+
+		for &(head, ref remote, compatible) in cases.iter() {
+			assert_eq!(filter.is_compatible(head, remote), compatible, "head {}, remote {:?}", head, remote);
+		}
+	}
+
+	#[test]
+	fn rlp_roundtrip() {
+		let fork_id = ForkId { hash: 0x668db0af, next: 7_280_000 };
+		let encoded = rlp::encode(&fork_id);
+		assert_eq!(&encoded[..], &[0xc9, 0x84, 0x66, 0x8d, 0xb0, 0xaf, 0x83, 0x6f, 0x15, 0x80][..]);
+		assert_eq!(rlp::decode::<ForkId>(&encoded).unwrap(), fork_id);
+	}
+}
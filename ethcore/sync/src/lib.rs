@@ -17,7 +17,7 @@
 #![warn(missing_docs)]
 
 //! Blockchain sync module
-//! Implements ethereum protocol version 63 as specified here:
+//! Implements ethereum protocol versions 63 and 64 as specified here:
 //! https://github.com/ethereum/wiki/wiki/Ethereum-Wire-Protocol
 //!
 
@@ -36,10 +36,10 @@ extern crate parking_lot;
 extern crate rlp;
 extern crate keccak_hash as hash;
 extern crate triehash_ethereum;
+extern crate ethkey;
 
 extern crate ethcore_light as light;
 
-#[cfg(test)] extern crate ethkey;
 #[cfg(test)] extern crate kvdb_memorydb;
 #[cfg(test)] extern crate rustc_hex;
 #[cfg(test)] extern crate ethcore_private_tx;
@@ -58,8 +58,12 @@ mod blocks;
 mod block_sync;
 mod sync_io;
 mod private_tx;
+mod private_tx_protocol;
 mod snapshot;
+mod state_sync;
 mod transactions_stats;
+mod transactions_fetcher;
+mod fork_id;
 
 pub mod light_sync;
 
@@ -68,8 +72,16 @@ mod tests;
 
 mod api;
 
+/// Hash state of the maps and sets whose iteration order drives the sync.
+/// Tests use a fixed one, so that network simulations replay identically.
+#[cfg(not(test))]
+pub(crate) type HashState = ::std::collections::hash_map::RandomState;
+#[cfg(test)]
+pub(crate) type HashState = ::std::hash::BuildHasherDefault<::std::collections::hash_map::DefaultHasher>;
+
 pub use api::*;
 pub use chain::{SyncStatus, SyncState};
-pub use devp2p::validate_node_url;
-pub use network::{NonReservedPeerMode, Error, ErrorKind, ConnectionFilter, ConnectionDirection};
+pub use devp2p::{validate_node_url, validate_node_list_url};
+pub use network::{NonReservedPeerMode, Error, ErrorKind, ConnectionFilter, ConnectionDirection, BandwidthInfo, TrafficStats};
 pub use private_tx::{PrivateTxHandler, NoopPrivateTxHandler, SimplePrivateTxHandler};
+pub use private_tx_protocol::{PrivateTxProtocol, PrivateTxStats, PRIVATE_TX_PROTOCOL_ID, PRIVATE_TX_PROTOCOL_VERSION_1};
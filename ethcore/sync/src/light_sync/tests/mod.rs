@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use tests::helpers::TestNet;
+use tests::simulator::{SimNet, LinkConfig};
 
 use ethcore::client::{BlockInfo, BlockId, EachBlockWith};
 
@@ -31,6 +32,22 @@ fn basic_sync() {
 	assert!(net.peer(0).light_chain().block_header(BlockId::Number(6000)).is_some());
 }
 
+#[test]
+fn sync_over_simulated_network() {
+	let mut net = SimNet::new(TestNet::light(1, 2), 3);
+	net.peer(1).chain().add_blocks(300, EachBlockWith::Nothing);
+	net.peer(2).chain().add_blocks(400, EachBlockWith::Nothing);
+	net.set_default_link(LinkConfig {
+		latency: 80,
+		jitter: 40,
+		..Default::default()
+	});
+
+	net.sync();
+
+	assert!(net.peer(0).light_chain().block_header(BlockId::Number(400)).is_some());
+}
+
 #[test]
 fn fork_post_cht() {
 	const CHAIN_LENGTH: u64 = 50; // shouldn't be longer than ::light::cht::size();
@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use parking_lot::Mutex;
-use ethereum_types::H256;
+use ethereum_types::{H256, Address};
 
 /// Trait which should be implemented by a private transaction handler.
 pub trait PrivateTxHandler: Send + Sync + 'static {
@@ -26,6 +26,14 @@ pub trait PrivateTxHandler: Send + Sync + 'static {
 	/// Function called on new signed private transaction received.
 	/// Returns the hash of the imported transaction
 	fn import_signed_private_transaction(&self, rlp: &[u8]) -> Result<H256, String>;
+
+	/// Function called before a private transaction is propagated.
+	/// Returns the validators of the private contract the transaction is addressed to.
+	fn private_transaction_validators(&self, rlp: &[u8]) -> Result<Vec<Address>, String>;
+
+	/// Function called before a signed private transaction is propagated.
+	/// Returns the hash of the private transaction the signature belongs to.
+	fn signed_private_transaction_hash(&self, rlp: &[u8]) -> Result<H256, String>;
 }
 
 /// Nonoperative private transaction handler.
@@ -39,6 +47,14 @@ impl PrivateTxHandler for NoopPrivateTxHandler {
 	fn import_signed_private_transaction(&self, _rlp: &[u8]) -> Result<H256, String> {
 		Ok(H256::default())
 	}
+
+	fn private_transaction_validators(&self, _rlp: &[u8]) -> Result<Vec<Address>, String> {
+		Ok(Vec::new())
+	}
+
+	fn signed_private_transaction_hash(&self, _rlp: &[u8]) -> Result<H256, String> {
+		Ok(H256::default())
+	}
 }
 
 /// Simple private transaction handler. Used for tests.
@@ -48,6 +64,8 @@ pub struct SimplePrivateTxHandler {
 	pub txs: Mutex<Vec<Vec<u8>>>,
 	/// imported signed private transactions
 	pub signed_txs: Mutex<Vec<Vec<u8>>>,
+	/// validators returned for every private transaction
+	pub validators: Mutex<Vec<Address>>,
 }
 
 impl PrivateTxHandler for SimplePrivateTxHandler {
@@ -60,4 +78,12 @@ impl PrivateTxHandler for SimplePrivateTxHandler {
 		self.signed_txs.lock().push(rlp.to_vec());
 		Ok(H256::default())
 	}
+
+	fn private_transaction_validators(&self, _rlp: &[u8]) -> Result<Vec<Address>, String> {
+		Ok(self.validators.lock().clone())
+	}
+
+	fn signed_private_transaction_hash(&self, _rlp: &[u8]) -> Result<H256, String> {
+		Ok(H256::default())
+	}
 }
@@ -0,0 +1,424 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dedicated `ptx` subprotocol for private transactions.
+//!
+//! Private transactions are only sent to peers whose node id maps to one of the
+//! validators of the private contract; signatures are sent to the same validators
+//! and to the peer the private transaction was received from. Nothing is relayed
+//! over the public `par` protocol.
+//!
+//! The protocol assumes that every validator runs its node with the key of its
+//! validator account, i.e. that the validator address equals
+//! `public_to_address(node_id)`. Validators using a different network key can't
+//! be recognised and won't receive any private transactions.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Weak};
+
+use bytes::Bytes;
+use ethcore::client::{ChainNotify, ChainMessageType};
+use ethereum_types::{H256, Address};
+use ethkey::public_to_address;
+use network::{NetworkProtocolHandler, NetworkContext, PeerId, PacketId, ProtocolId};
+use parking_lot::{Mutex, RwLock};
+
+use api::ManageNetwork;
+use private_tx::PrivateTxHandler;
+
+/// Private transactions subprotocol name.
+pub const PRIVATE_TX_PROTOCOL_ID: ProtocolId = *b"ptx";
+/// 1 version of private transactions protocol and the packet count.
+pub const PRIVATE_TX_PROTOCOL_VERSION_1: (u8, u8) = (1, 0x02);
+
+const PRIVATE_TRANSACTION_PACKET: PacketId = 0x00;
+const SIGNED_PRIVATE_TRANSACTION_PACKET: PacketId = 0x01;
+
+/// Maximum number of private transactions remembered for routing their signatures.
+const MAX_ROUTES: usize = 1024;
+
+/// Private transactions delivery statistics.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PrivateTxStats {
+	/// Packets sent to peers.
+	pub sent: usize,
+	/// Packets which could not be sent.
+	pub send_failures: usize,
+	/// Private transactions and signatures which had no connected recipient.
+	pub undelivered: usize,
+	/// Packets received from peers.
+	pub received: usize,
+	/// Received packets imported by the private transactions handler.
+	pub imported: usize,
+	/// Received packets rejected by the private transactions handler.
+	pub rejected: usize,
+}
+
+struct PrivateTxPeer {
+	/// Account address derived from the peer's node id.
+	address: Option<Address>,
+	/// Hashes of the private transactions and signatures this peer already has.
+	known: HashSet<H256>,
+}
+
+/// Recipients of the signatures of a private transaction.
+#[derive(Clone)]
+struct PrivateTxRoute {
+	/// Validators of the private contract.
+	validators: HashSet<Address>,
+	/// Peer the private transaction was received from, `None` if it was created locally.
+	origin: Option<PeerId>,
+}
+
+/// Routes of the most recent private transactions, keyed by private transaction hash.
+#[derive(Default)]
+struct PrivateTxRoutes {
+	routes: HashMap<H256, PrivateTxRoute>,
+	order: VecDeque<H256>,
+}
+
+impl PrivateTxRoutes {
+	fn insert(&mut self, hash: H256, route: PrivateTxRoute) {
+		if self.routes.insert(hash, route).is_none() {
+			self.order.push_back(hash);
+		}
+		while self.order.len() > MAX_ROUTES {
+			if let Some(oldest) = self.order.pop_front() {
+				self.routes.remove(&oldest);
+			}
+		}
+	}
+
+	fn get(&self, hash: &H256) -> Option<PrivateTxRoute> {
+		self.routes.get(hash).cloned()
+	}
+}
+
+/// Private transactions protocol handler.
+pub struct PrivateTxProtocol {
+	handler: Arc<PrivateTxHandler>,
+	network: RwLock<Option<Weak<ManageNetwork>>>,
+	peers: RwLock<HashMap<PeerId, PrivateTxPeer>>,
+	routes: Mutex<PrivateTxRoutes>,
+	stats: Mutex<PrivateTxStats>,
+}
+
+impl PrivateTxProtocol {
+	/// Create a new protocol handler importing received packets into `handler`.
+	pub fn new(handler: Arc<PrivateTxHandler>) -> Self {
+		PrivateTxProtocol {
+			handler,
+			network: RwLock::new(None),
+			peers: RwLock::new(HashMap::new()),
+			routes: Mutex::new(PrivateTxRoutes::default()),
+			stats: Mutex::new(PrivateTxStats::default()),
+		}
+	}
+
+	/// Set the network used to broadcast private transactions.
+	pub fn set_network(&self, network: Weak<ManageNetwork>) {
+		*self.network.write() = Some(network);
+	}
+
+	/// Returns delivery statistics.
+	pub fn stats(&self) -> PrivateTxStats {
+		self.stats.lock().clone()
+	}
+
+	/// Send a private transaction packet or a signed private transaction packet
+	/// to the validators of the private contract. Signatures are also sent to
+	/// the peer the private transaction was received from.
+	pub fn propagate(&self, io: &NetworkContext, transaction_hash: H256, packet_id: PacketId, packet: Bytes) {
+		let route = if packet_id == PRIVATE_TRANSACTION_PACKET {
+			match self.handler.private_transaction_validators(&packet) {
+				Ok(validators) => {
+					let route = PrivateTxRoute {
+						validators: validators.into_iter().collect(),
+						origin: None,
+					};
+					self.routes.lock().insert(transaction_hash, route.clone());
+					route
+				},
+				Err(e) => {
+					warn!(target: "privatetx", "Unable to get validators of private transaction {:?}: {}", transaction_hash, e);
+					self.stats.lock().undelivered += 1;
+					return;
+				}
+			}
+		} else {
+			let route = match self.handler.signed_private_transaction_hash(&packet) {
+				Ok(private_hash) => self.routes.lock().get(&private_hash),
+				Err(e) => {
+					warn!(target: "privatetx", "Unable to decode signed private transaction {:?}: {}", transaction_hash, e);
+					None
+				}
+			};
+			match route {
+				Some(route) => route,
+				None => {
+					debug!(target: "privatetx", "Unknown private transaction for signature {:?}", transaction_hash);
+					self.stats.lock().undelivered += 1;
+					return;
+				}
+			}
+		};
+
+		let mut peers = self.peers.write();
+		let candidates: Vec<PeerId> = peers.iter()
+			.filter(|&(id, peer)| route.origin == Some(*id) ||
+				peer.address.as_ref().map_or(false, |address| route.validators.contains(address)))
+			.map(|(id, _)| *id)
+			.collect();
+
+		let mut stats = self.stats.lock();
+		if candidates.is_empty() {
+			debug!(target: "privatetx", "No recipient connected for private transaction packet {:?}", transaction_hash);
+			stats.undelivered += 1;
+		}
+
+		let recipients: Vec<PeerId> = candidates.into_iter()
+			.filter(|id| peers.get(id).map_or(false, |peer| !peer.known.contains(&transaction_hash)))
+			.collect();
+
+		trace!(target: "privatetx", "Sending private transaction packet {} to {:?}", packet_id, recipients);
+		for peer_id in recipients {
+			match io.send(peer_id, packet_id, packet.clone()) {
+				Ok(()) => {
+					stats.sent += 1;
+					if let Some(peer) = peers.get_mut(&peer_id) {
+						peer.known.insert(transaction_hash);
+					}
+				},
+				Err(e) => {
+					debug!(target: "privatetx", "Error sending private transaction packet to {}: {:?}", peer_id, e);
+					stats.send_failures += 1;
+				}
+			}
+		}
+	}
+
+	fn broadcast_packet(&self, transaction_hash: H256, packet_id: PacketId, packet: Bytes) {
+		let network = match self.network.read().as_ref().and_then(Weak::upgrade) {
+			Some(network) => network,
+			None => {
+				warn!(target: "privatetx", "Network is not available for private transaction {:?}", transaction_hash);
+				return;
+			}
+		};
+
+		let mut packet = Some(packet);
+		network.with_proto_context(PRIVATE_TX_PROTOCOL_ID, &mut |io| {
+			if let Some(packet) = packet.take() {
+				self.propagate(io, transaction_hash, packet_id, packet);
+			}
+		});
+	}
+}
+
+impl NetworkProtocolHandler for PrivateTxProtocol {
+	fn read(&self, io: &NetworkContext, peer: &PeerId, packet_id: u8, data: &[u8]) {
+		let result = match packet_id {
+			PRIVATE_TRANSACTION_PACKET => self.handler.import_private_transaction(data),
+			SIGNED_PRIVATE_TRANSACTION_PACKET => self.handler.import_signed_private_transaction(data),
+			_ => {
+				debug!(target: "privatetx", "{}: Unknown packet {}", peer, packet_id);
+				io.disconnect_peer(*peer);
+				return;
+			}
+		};
+
+		self.stats.lock().received += 1;
+		match result {
+			Ok(transaction_hash) => {
+				self.stats.lock().imported += 1;
+				if packet_id == PRIVATE_TRANSACTION_PACKET {
+					// signatures go back to the sender and to the other validators
+					let validators = self.handler.private_transaction_validators(data).unwrap_or_default();
+					self.routes.lock().insert(transaction_hash, PrivateTxRoute {
+						validators: validators.into_iter().collect(),
+						origin: Some(*peer),
+					});
+				}
+				// don't send the packet back
+				if let Some(peer) = self.peers.write().get_mut(peer) {
+					peer.known.insert(transaction_hash);
+				}
+			},
+			Err(e) => {
+				self.stats.lock().rejected += 1;
+				trace!(target: "privatetx", "Ignoring the message from {}, error queueing: {}", peer, e);
+			}
+		}
+	}
+
+	fn connected(&self, io: &NetworkContext, peer: &PeerId) {
+		// validators are expected to use their account key as the node key
+		let address = io.session_info(*peer).and_then(|info| info.id).map(|id| public_to_address(&id));
+		trace!(target: "privatetx", "Connected {} with address {:?}", peer, address);
+		self.peers.write().insert(*peer, PrivateTxPeer {
+			address,
+			known: HashSet::new(),
+		});
+	}
+
+	fn disconnected(&self, _io: &NetworkContext, peer: &PeerId) {
+		trace!(target: "privatetx", "Disconnected {}", peer);
+		self.peers.write().remove(peer);
+	}
+}
+
+impl ChainNotify for PrivateTxProtocol {
+	fn broadcast(&self, message_type: ChainMessageType) {
+		match message_type {
+			ChainMessageType::PrivateTransaction(transaction_hash, message) =>
+				self.broadcast_packet(transaction_hash, PRIVATE_TRANSACTION_PACKET, message),
+			ChainMessageType::SignedPrivateTransaction(transaction_hash, message) =>
+				self.broadcast_packet(transaction_hash, SIGNED_PRIVATE_TRANSACTION_PACKET, message),
+			ChainMessageType::Consensus(_) => {},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use std::sync::Arc;
+	use std::time::Duration;
+	use ethereum_types::{H256, Address};
+	use ethkey::{Generator, Random, Public, public_to_address};
+	use network::{NetworkProtocolHandler, NetworkContext, PeerId, PacketId, ProtocolId,
+		SessionInfo, PeerBehaviour, TimerToken, Error};
+	use parking_lot::Mutex;
+	use private_tx::SimplePrivateTxHandler;
+	use super::{PrivateTxProtocol, PrivateTxStats, PRIVATE_TX_PROTOCOL_ID,
+		PRIVATE_TRANSACTION_PACKET, SIGNED_PRIVATE_TRANSACTION_PACKET};
+
+	#[derive(Default)]
+	struct TestContext {
+		node_ids: HashMap<PeerId, Public>,
+		sent: Mutex<Vec<(PeerId, PacketId)>>,
+	}
+
+	impl NetworkContext for TestContext {
+		fn send(&self, peer: PeerId, packet_id: PacketId, _data: Vec<u8>) -> Result<(), Error> {
+			self.sent.lock().push((peer, packet_id));
+			Ok(())
+		}
+		fn send_protocol(&self, _protocol: ProtocolId, peer: PeerId, packet_id: PacketId, data: Vec<u8>) -> Result<(), Error> {
+			self.send(peer, packet_id, data)
+		}
+		fn respond(&self, _packet_id: PacketId, _data: Vec<u8>) -> Result<(), Error> { Ok(()) }
+		fn disable_peer(&self, _peer: PeerId) {}
+		fn disconnect_peer(&self, _peer: PeerId) {}
+		fn report_peer(&self, _peer: PeerId, _behaviour: PeerBehaviour) {}
+		fn is_expired(&self) -> bool { false }
+		fn register_timer(&self, _token: TimerToken, _delay: Duration) -> Result<(), Error> { Ok(()) }
+		fn peer_client_version(&self, _peer: PeerId) -> String { String::new() }
+		fn session_info(&self, peer: PeerId) -> Option<SessionInfo> {
+			self.node_ids.get(&peer).map(|id| SessionInfo {
+				id: Some(id.clone()),
+				client_version: String::new(),
+				protocol_version: 5,
+				capabilities: Vec::new(),
+				peer_capabilities: Vec::new(),
+				ping: None,
+				originated: false,
+				remote_address: String::new(),
+				local_address: String::new(),
+				traffic: Default::default(),
+			})
+		}
+		fn protocol_version(&self, _protocol: ProtocolId, _peer: PeerId) -> Option<u8> { Some(1) }
+		fn subprotocol_name(&self) -> ProtocolId { PRIVATE_TX_PROTOCOL_ID }
+		fn is_reserved_peer(&self, _peer: PeerId) -> bool { false }
+	}
+
+	fn connect_peers(protocol: &PrivateTxProtocol, count: usize) -> (TestContext, Vec<Address>) {
+		let mut io = TestContext::default();
+		let mut addresses = Vec::new();
+		for peer in 0..count {
+			let public = Random.generate().unwrap().public().clone();
+			addresses.push(public_to_address(&public));
+			io.node_ids.insert(peer, public);
+		}
+		for peer in 0..count {
+			protocol.connected(&io, &peer);
+		}
+		(io, addresses)
+	}
+
+	#[test]
+	fn sends_private_transactions_to_validators_only() {
+		let handler = Arc::new(SimplePrivateTxHandler::default());
+		let protocol = PrivateTxProtocol::new(handler.clone());
+		let (io, addresses) = connect_peers(&protocol, 3);
+		*handler.validators.lock() = vec![addresses[1], Address::from(5u64)];
+
+		protocol.propagate(&io, H256::from(1u64), PRIVATE_TRANSACTION_PACKET, vec![0xc0]);
+		protocol.propagate(&io, H256::from(1u64), PRIVATE_TRANSACTION_PACKET, vec![0xc0]);
+
+		assert_eq!(*io.sent.lock(), vec![(1, PRIVATE_TRANSACTION_PACKET)]);
+		assert_eq!(protocol.stats(), PrivateTxStats { sent: 1, ..Default::default() });
+	}
+
+	#[test]
+	fn counts_private_transactions_without_connected_validators() {
+		let handler = Arc::new(SimplePrivateTxHandler::default());
+		let protocol = PrivateTxProtocol::new(handler.clone());
+		let (io, _) = connect_peers(&protocol, 2);
+		*handler.validators.lock() = vec![Address::from(5u64)];
+
+		protocol.propagate(&io, H256::from(1u64), PRIVATE_TRANSACTION_PACKET, vec![0xc0]);
+
+		assert!(io.sent.lock().is_empty());
+		assert_eq!(protocol.stats(), PrivateTxStats { undelivered: 1, ..Default::default() });
+	}
+
+	#[test]
+	fn sends_signatures_to_validators_and_sender_only() {
+		let handler = Arc::new(SimplePrivateTxHandler::default());
+		let protocol = PrivateTxProtocol::new(handler.clone());
+		let (io, addresses) = connect_peers(&protocol, 4);
+		*handler.validators.lock() = vec![addresses[1], addresses[2]];
+
+		protocol.read(&io, &3, PRIVATE_TRANSACTION_PACKET, &[0xc0]);
+		protocol.propagate(&io, H256::from(2u64), SIGNED_PRIVATE_TRANSACTION_PACKET, vec![0xc0]);
+
+		let mut sent = io.sent.lock().clone();
+		sent.sort();
+		assert_eq!(sent, vec![
+			(1, SIGNED_PRIVATE_TRANSACTION_PACKET),
+			(2, SIGNED_PRIVATE_TRANSACTION_PACKET),
+			(3, SIGNED_PRIVATE_TRANSACTION_PACKET),
+		]);
+		assert_eq!(handler.txs.lock().len(), 1);
+		assert_eq!(protocol.stats(), PrivateTxStats { sent: 3, received: 1, imported: 1, ..Default::default() });
+	}
+
+	#[test]
+	fn does_not_send_signatures_of_unknown_private_transactions() {
+		let handler = Arc::new(SimplePrivateTxHandler::default());
+		let protocol = PrivateTxProtocol::new(handler.clone());
+		let (io, _) = connect_peers(&protocol, 3);
+
+		protocol.read(&io, &0, SIGNED_PRIVATE_TRANSACTION_PACKET, &[0xc0]);
+		protocol.propagate(&io, H256::from(2u64), SIGNED_PRIVATE_TRANSACTION_PACKET, vec![0xc0]);
+
+		assert!(io.sent.lock().is_empty());
+		assert_eq!(handler.signed_txs.lock().len(), 1);
+		assert_eq!(protocol.stats(), PrivateTxStats { undelivered: 1, received: 1, imported: 1, ..Default::default() });
+	}
+}
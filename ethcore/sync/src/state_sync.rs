@@ -0,0 +1,680 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! State sync downloads the state of a recent block, the pivot, trie node by trie node with `GetNodeData`
+//! requests. A node is written to the database only once all of its children are there, so any node found
+//! in the database has a complete sub-trie. When peers stop serving the state of the pivot a newer one is
+//! chosen and only the parts of the trie which changed in between are downloaded again.
+
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+
+use block_sync::BlockDownloaderImportError as DownloaderImportError;
+use blocks::{SyncHeader, SyncBody, unverified_from_sync};
+use bytes::Bytes;
+use ethcore::client::BlockChainClient;
+use ethcore::header::BlockNumber;
+use ethcore::verification::queue::kind::blocks::Unverified;
+use ethereum_types::{H256, U256};
+use hash::{keccak, KECCAK_EMPTY, KECCAK_NULL_RLP};
+use network::PeerId;
+use rlp::{Rlp, DecoderError};
+use triehash_ethereum::ordered_trie_root;
+
+/// Number of blocks the pivot is behind the best block of the peer it is taken from.
+pub const PIVOT_DISTANCE: BlockNumber = 64;
+/// A new pivot is chosen once the chain is that many blocks ahead of the current one.
+const MAX_PIVOT_AGE: BlockNumber = 2 * PIVOT_DISTANCE;
+/// A new pivot is chosen after that many node data responses in a row without any requested node.
+const MAX_EMPTY_RESPONSES: usize = 8;
+
+/// Address hash of the owning account for storage trie nodes and code, and the hash of the node.
+pub type NodeKey = (Option<H256>, H256);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+	/// Account trie node.
+	Account,
+	/// Storage trie node.
+	Storage,
+	/// Contract code.
+	Code,
+}
+
+/// Trie node referenced by a downloaded node.
+struct Child {
+	key: NodeKey,
+	kind: NodeKind,
+	path: Vec<u8>,
+}
+
+/// Trie node which is requested or waiting for its children.
+struct Node {
+	kind: NodeKind,
+	/// Nibble path from the root of the account trie, empty for other nodes.
+	path: Vec<u8>,
+	/// Node data, `None` until the node is downloaded.
+	data: Option<Bytes>,
+	/// Address hash if the node is an account trie leaf.
+	account: Option<H256>,
+	/// Number of children not in the database yet.
+	missing: usize,
+	/// Nodes waiting for this one.
+	parents: Vec<NodeKey>,
+}
+
+impl Node {
+	fn new(kind: NodeKind, path: Vec<u8>, parent: Option<NodeKey>) -> Node {
+		Node {
+			kind,
+			path,
+			data: None,
+			account: None,
+			missing: 0,
+			parents: parent.into_iter().collect(),
+		}
+	}
+}
+
+/// Nodes ready to be written to the database.
+#[derive(Default)]
+struct NodeBatch {
+	nodes: Vec<(Option<H256>, Bytes)>,
+	accounts: Vec<H256>,
+	written: HashSet<NodeKey>,
+}
+
+/// Block whose state is downloaded.
+struct Pivot {
+	header: SyncHeader,
+	parent_total_difficulty: U256,
+	body: Option<SyncBody>,
+	receipts: Option<Bytes>,
+}
+
+/// State trie downloader.
+pub struct StateSync {
+	pivot: Option<Pivot>,
+	nodes: HashMap<NodeKey, Node>,
+	/// Nodes to request. The last one is requested first so that the trie is walked depth first
+	/// and sub-tries are completed and written early.
+	queue: Vec<NodeKey>,
+	downloading: HashMap<PeerId, Vec<NodeKey>>,
+	body_peer: Option<PeerId>,
+	receipts_peer: Option<PeerId>,
+	root_done: bool,
+	empty_responses: usize,
+	imported_nodes: usize,
+}
+
+impl StateSync {
+	/// Create a new instance.
+	pub fn new() -> StateSync {
+		StateSync {
+			pivot: None,
+			nodes: HashMap::new(),
+			queue: Vec::new(),
+			downloading: HashMap::new(),
+			body_peer: None,
+			receipts_peer: None,
+			root_done: false,
+			empty_responses: 0,
+			imported_nodes: 0,
+		}
+	}
+
+	/// Clear everything.
+	pub fn clear(&mut self) {
+		self.clear_pivot();
+		self.imported_nodes = 0;
+	}
+
+	/// Drop the pivot and all nodes which are not in the database yet.
+	pub fn clear_pivot(&mut self) {
+		self.pivot = None;
+		self.nodes.clear();
+		self.queue.clear();
+		self.downloading.clear();
+		self.body_peer = None;
+		self.receipts_peer = None;
+		self.root_done = false;
+		self.empty_responses = 0;
+	}
+
+	/// Start downloading the state of a new pivot block.
+	pub fn set_pivot(&mut self, chain: &BlockChainClient, header: SyncHeader, parent_total_difficulty: U256) {
+		self.clear_pivot();
+		let root = *header.header.state_root();
+		self.root_done = root == KECCAK_NULL_RLP || chain.state_node_known(None, &root);
+		if !self.root_done {
+			self.nodes.insert((None, root), Node::new(NodeKind::Account, Vec::new(), None));
+			self.queue.push((None, root));
+		}
+		self.pivot = Some(Pivot {
+			header,
+			parent_total_difficulty,
+			body: None,
+			receipts: None,
+		});
+	}
+
+	/// Number and hash of the pivot block.
+	pub fn pivot(&self) -> Option<(BlockNumber, H256)> {
+		self.pivot.as_ref().map(|p| (p.header.header.number(), p.header.header.hash()))
+	}
+
+	/// Check if peers are unlikely to still have the state of the pivot.
+	pub fn is_pivot_stale(&self, highest_block: Option<BlockNumber>) -> bool {
+		match self.pivot {
+			Some(ref pivot) => {
+				self.empty_responses >= MAX_EMPTY_RESPONSES ||
+					highest_block.map_or(false, |highest| highest > pivot.header.header.number() + MAX_PIVOT_AGE)
+			},
+			None => false,
+		}
+	}
+
+	/// Check if the state, body and receipts of the pivot have been downloaded.
+	pub fn is_complete(&self) -> bool {
+		self.root_done && self.pivot.as_ref().map_or(false, |p| p.body.is_some() && p.receipts.is_some())
+	}
+
+	/// Number of nodes written to the database.
+	pub fn imported_nodes(&self) -> usize {
+		self.imported_nodes
+	}
+
+	/// Number of nodes requested or waiting for their children.
+	pub fn pending_nodes(&self) -> usize {
+		self.nodes.len()
+	}
+
+	/// Get the pivot hash if its body should be requested from the given peer.
+	pub fn request_body(&mut self, peer_id: PeerId) -> Option<H256> {
+		match self.pivot {
+			Some(ref pivot) if pivot.body.is_none() && self.body_peer.is_none() => {
+				self.body_peer = Some(peer_id);
+				Some(pivot.header.header.hash())
+			},
+			_ => None,
+		}
+	}
+
+	/// Get the pivot hash if its receipts should be requested from the given peer.
+	pub fn request_receipts(&mut self, peer_id: PeerId) -> Option<H256> {
+		match self.pivot {
+			Some(ref pivot) if pivot.receipts.is_none() && self.receipts_peer.is_none() => {
+				self.receipts_peer = Some(peer_id);
+				Some(pivot.header.header.hash())
+			},
+			_ => None,
+		}
+	}
+
+	/// Get up to `count` node hashes to request from the given peer.
+	pub fn request_nodes(&mut self, peer_id: PeerId, count: usize) -> Vec<H256> {
+		let at = self.queue.len().saturating_sub(count);
+		let keys = self.queue.split_off(at);
+		let hashes = keys.iter().rev().map(|&(_, hash)| hash).collect();
+		self.downloading.entry(peer_id).or_insert_with(Vec::new).extend(keys);
+		hashes
+	}
+
+	/// Hand everything requested from the given peer back to the queue.
+	pub fn clear_download(&mut self, peer_id: PeerId) {
+		if let Some(keys) = self.downloading.remove(&peer_id) {
+			self.queue.extend(keys);
+		}
+		if self.body_peer == Some(peer_id) {
+			self.body_peer = None;
+		}
+		if self.receipts_peer == Some(peer_id) {
+			self.receipts_peer = None;
+		}
+	}
+
+	/// Import the pivot block body.
+	pub fn import_body(&mut self, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if self.body_peer != Some(peer_id) {
+			trace!(target: "sync", "{}: Ignored unexpected pivot body", peer_id);
+			return Ok(());
+		}
+		self.body_peer = None;
+		let pivot = match self.pivot {
+			Some(ref mut pivot) => pivot,
+			None => return Ok(()),
+		};
+		if r.item_count()? == 0 {
+			return Err(DownloaderImportError::Useless);
+		}
+		let body = SyncBody::from_rlp(r.at(0)?.as_raw())?;
+		let transactions_root = ordered_trie_root(Rlp::new(&body.transactions_bytes).iter().map(|r| r.as_raw()));
+		if transactions_root != *pivot.header.header.transactions_root() || keccak(&body.uncles_bytes) != *pivot.header.header.uncles_hash() {
+			return Err(DownloaderImportError::Invalid);
+		}
+		pivot.body = Some(body);
+		Ok(())
+	}
+
+	/// Import the pivot block receipts.
+	pub fn import_receipts(&mut self, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
+		if self.receipts_peer != Some(peer_id) {
+			trace!(target: "sync", "{}: Ignored unexpected pivot receipts", peer_id);
+			return Ok(());
+		}
+		self.receipts_peer = None;
+		let pivot = match self.pivot {
+			Some(ref mut pivot) => pivot,
+			None => return Ok(()),
+		};
+		if r.item_count()? == 0 {
+			return Err(DownloaderImportError::Useless);
+		}
+		let receipts = r.at(0)?;
+		let receipts_root = ordered_trie_root(receipts.iter().map(|r| r.as_raw()));
+		if receipts_root != *pivot.header.header.receipts_root() {
+			return Err(DownloaderImportError::Invalid);
+		}
+		pivot.receipts = Some(receipts.as_raw().to_vec());
+		Ok(())
+	}
+
+	/// Import nodes delivered by the given peer and write the complete ones to the database.
+	/// Returns the number of requested nodes found in the response.
+	pub fn import_nodes(&mut self, chain: &BlockChainClient, peer_id: PeerId, r: &Rlp) -> Result<usize, DownloaderImportError> {
+		if !self.downloading.contains_key(&peer_id) {
+			trace!(target: "sync", "{}: Ignored unexpected node data", peer_id);
+			return Ok(0);
+		}
+		let delivered = match r.iter().map(|item| item.as_val::<Bytes>().map(|data| (keccak(&data), data))).collect::<Result<HashMap<_, _>, _>>() {
+			Ok(delivered) => delivered,
+			Err(e) => {
+				self.clear_download(peer_id);
+				return Err(e.into());
+			},
+		};
+		let requested = self.downloading.remove(&peer_id).unwrap_or_else(Vec::new);
+
+		let mut received = Vec::new();
+		for key in requested {
+			match delivered.get(&key.1) {
+				Some(data) => received.push((key, data.clone())),
+				None => self.queue.push(key),
+			}
+		}
+		if received.is_empty() {
+			self.empty_responses += 1;
+			return Ok(0);
+		}
+		self.empty_responses = 0;
+
+		let count = received.len();
+		let mut batch = NodeBatch::default();
+		for (key, data) in received {
+			self.insert_node(chain, key, data, &mut batch)?;
+		}
+		if !batch.nodes.is_empty() {
+			trace!(target: "sync", "Writing {} state nodes, {} pending", batch.nodes.len(), self.nodes.len());
+			if let Err(e) = chain.import_state_nodes(batch.nodes, &batch.accounts) {
+				warn!(target: "sync", "Error writing state nodes: {}", e);
+			}
+		}
+		Ok(count)
+	}
+
+	/// Take the pivot block, its receipts and parent total difficulty once the download is complete.
+	pub fn take_pivot(&mut self) -> Option<(Unverified, Bytes, U256)> {
+		if !self.is_complete() {
+			return None;
+		}
+		self.pivot.take().map(|pivot| {
+			let receipts = pivot.receipts.expect("is_complete checks that receipts are downloaded; qed");
+			(unverified_from_sync(pivot.header, pivot.body), receipts, pivot.parent_total_difficulty)
+		})
+	}
+
+	fn insert_node(&mut self, chain: &BlockChainClient, key: NodeKey, data: Bytes, batch: &mut NodeBatch) -> Result<(), DownloaderImportError> {
+		let (kind, path) = match self.nodes.get(&key) {
+			Some(node) if node.data.is_none() => (node.kind, node.path.clone()),
+			_ => return Ok(()),
+		};
+		let (children, account) = match kind {
+			NodeKind::Code => (Vec::new(), None),
+			_ => node_children(kind, key.0, &path, &data)?,
+		};
+
+		let mut missing = 0;
+		for child in children {
+			if batch.written.contains(&child.key) || chain.state_node_known(child.key.0.as_ref(), &child.key.1) {
+				continue;
+			}
+			missing += 1;
+			match self.nodes.entry(child.key) {
+				Entry::Occupied(mut entry) => entry.get_mut().parents.push(key),
+				Entry::Vacant(entry) => {
+					entry.insert(Node::new(child.kind, child.path, Some(key)));
+					self.queue.push(child.key);
+				},
+			}
+		}
+		{
+			let node = self.nodes.get_mut(&key).expect("node is checked to be present above; qed");
+			node.data = Some(data);
+			node.account = account;
+			node.missing = missing;
+		}
+		if missing == 0 {
+			self.commit(key, batch);
+		}
+		Ok(())
+	}
+
+	/// Move a node with all children present to the batch, together with any parents this completes.
+	fn commit(&mut self, key: NodeKey, batch: &mut NodeBatch) {
+		let root = self.pivot.as_ref().map(|p| *p.header.header.state_root());
+		let mut ready = vec![key];
+		while let Some(key) = ready.pop() {
+			let node = match self.nodes.remove(&key) {
+				Some(node) => node,
+				None => continue,
+			};
+			if let Some(account) = node.account {
+				batch.accounts.push(account);
+			}
+			batch.nodes.push((key.0, node.data.expect("only downloaded nodes are committed; qed")));
+			batch.written.insert(key);
+			self.imported_nodes += 1;
+			if key.0.is_none() && Some(key.1) == root {
+				self.root_done = true;
+			}
+			for parent in node.parents {
+				if let Some(parent_node) = self.nodes.get_mut(&parent) {
+					parent_node.missing -= 1;
+					if parent_node.missing == 0 {
+						ready.push(parent);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Check that `headers` is the chain going back from the peer's best block and take the oldest header
+/// as the pivot. Returns the pivot and its parent total difficulty.
+pub fn pivot_from_headers(headers: &Rlp, best_hash: &H256, best_total_difficulty: U256) -> Result<(SyncHeader, U256), DownloaderImportError> {
+	let mut expected_hash = *best_hash;
+	let mut difficulty = U256::zero();
+	let mut pivot = None;
+	for item in headers.iter() {
+		let header = SyncHeader::from_rlp(item.as_raw().to_vec())?;
+		if header.header.hash() != expected_hash {
+			trace!(target: "sync", "Pivot header chain is broken at {:?}", expected_hash);
+			return Err(DownloaderImportError::Invalid);
+		}
+		expected_hash = *header.header.parent_hash();
+		difficulty = difficulty.saturating_add(*header.header.difficulty());
+		pivot = Some(header);
+	}
+	match pivot {
+		Some(_) if difficulty > best_total_difficulty => Err(DownloaderImportError::Invalid),
+		Some(pivot) => Ok((pivot, best_total_difficulty - difficulty)),
+		None => Err(DownloaderImportError::Useless),
+	}
+}
+
+/// Decode a trie node and get the nodes it references. Children inlined in the node are part
+/// of its data and are skipped. Returns the address hash as well if the node is an account leaf.
+fn node_children(kind: NodeKind, owner: Option<H256>, path: &[u8], data: &[u8]) -> Result<(Vec<Child>, Option<H256>), DecoderError> {
+	let rlp = Rlp::new(data);
+	let mut children = Vec::new();
+	let mut account = None;
+	match rlp.item_count()? {
+		17 => {
+			for i in 0..16 {
+				if let Some(hash) = child_hash(&rlp.at(i)?)? {
+					children.push(child_node(kind, owner, hash, path, &[i as u8]));
+				}
+			}
+		},
+		2 => {
+			let (partial, is_leaf) = decode_partial(rlp.at(0)?.data()?)?;
+			if !is_leaf {
+				if let Some(hash) = child_hash(&rlp.at(1)?)? {
+					children.push(child_node(kind, owner, hash, path, &partial));
+				}
+			} else if kind == NodeKind::Account {
+				let mut full_path = path.to_vec();
+				full_path.extend(partial);
+				let address_hash = nibbles_to_hash(&full_path)?;
+				let value = rlp.at(1)?;
+				let basic_account = Rlp::new(value.data()?);
+				let storage_root: H256 = basic_account.val_at(2)?;
+				let code_hash: H256 = basic_account.val_at(3)?;
+				if storage_root != KECCAK_NULL_RLP {
+					children.push(Child { key: (Some(address_hash), storage_root), kind: NodeKind::Storage, path: Vec::new() });
+				}
+				if code_hash != KECCAK_EMPTY {
+					children.push(Child { key: (Some(address_hash), code_hash), kind: NodeKind::Code, path: Vec::new() });
+				}
+				account = Some(address_hash);
+			}
+		},
+		_ => return Err(DecoderError::RlpIncorrectListLen),
+	}
+	Ok((children, account))
+}
+
+fn child_node(kind: NodeKind, owner: Option<H256>, hash: H256, path: &[u8], nibbles: &[u8]) -> Child {
+	let path = match kind {
+		NodeKind::Account => path.iter().chain(nibbles).cloned().collect(),
+		_ => Vec::new(),
+	};
+	Child { key: (owner, hash), kind, path }
+}
+
+/// Hash of a node referenced by a branch or an extension, `None` if empty or inlined.
+fn child_hash(item: &Rlp) -> Result<Option<H256>, DecoderError> {
+	if item.is_data() && item.size() == 32 {
+		Ok(Some(item.as_val()?))
+	} else {
+		Ok(None)
+	}
+}
+
+/// Decode a hex-prefix encoded partial path into nibbles and the leaf flag.
+fn decode_partial(encoded: &[u8]) -> Result<(Vec<u8>, bool), DecoderError> {
+	let first = *encoded.first().ok_or(DecoderError::RlpIsTooShort)?;
+	let is_leaf = first & 0x20 != 0;
+	let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+	if first & 0x10 != 0 {
+		nibbles.push(first & 0x0f);
+	}
+	for byte in &encoded[1..] {
+		nibbles.push(byte >> 4);
+		nibbles.push(byte & 0x0f);
+	}
+	Ok((nibbles, is_leaf))
+}
+
+fn nibbles_to_hash(nibbles: &[u8]) -> Result<H256, DecoderError> {
+	if nibbles.len() != 64 {
+		return Err(DecoderError::Custom("Account trie leaf path is not 32 bytes long"));
+	}
+	let mut bytes = [0u8; 32];
+	for (i, pair) in nibbles.chunks(2).enumerate() {
+		bytes[i] = (pair[0] << 4) | pair[1];
+	}
+	Ok(H256::from(bytes))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use ethcore::client::TestBlockChainClient;
+	use ethcore::header::Header;
+	use rlp::RlpStream;
+
+	fn account_leaf(address_hash: &H256, storage_root: H256, code_hash: H256) -> Bytes {
+		let mut account = RlpStream::new_list(4);
+		account.append(&U256::zero()).append(&U256::from(1)).append(&storage_root).append(&code_hash);
+		// The first nibble of the address hash is taken by the branch above.
+		let mut path = vec![0x30 | (address_hash[0] & 0x0f)];
+		path.extend_from_slice(&address_hash[1..]);
+		let mut leaf = RlpStream::new_list(2);
+		leaf.append(&path).append(&account.out());
+		leaf.out()
+	}
+
+	fn storage_leaf() -> Bytes {
+		let mut path = vec![0x20];
+		path.extend_from_slice(&keccak(b"key"));
+		let mut leaf = RlpStream::new_list(2);
+		leaf.append(&path).append(&::rlp::encode(&U256::from(42)).into_vec());
+		leaf.out()
+	}
+
+	fn branch(children: &[(u8, &Bytes)]) -> Bytes {
+		let mut branch = RlpStream::new_list(17);
+		for i in 0..16 {
+			match children.iter().find(|&&(nibble, _)| nibble == i) {
+				Some(&(_, child)) => { branch.append(&keccak(child)); },
+				None => { branch.append_empty_data(); },
+			}
+		}
+		branch.append_empty_data();
+		branch.out()
+	}
+
+	fn node_data(nodes: &[&Bytes]) -> Bytes {
+		let mut rlp = RlpStream::new_list(nodes.len());
+		for node in nodes {
+			rlp.append(*node);
+		}
+		rlp.out()
+	}
+
+	fn pivot_header(state_root: H256) -> SyncHeader {
+		let mut header = Header::new();
+		header.set_number(100);
+		header.set_state_root(state_root);
+		SyncHeader::from_rlp(::rlp::encode(&header).into_vec()).unwrap()
+	}
+
+	fn import(state_sync: &mut StateSync, client: &TestBlockChainClient, nodes: &[&Bytes]) -> usize {
+		let data = node_data(nodes);
+		state_sync.import_nodes(client, 0, &Rlp::new(&data)).unwrap()
+	}
+
+	#[test]
+	fn writes_nodes_once_their_children_are_present() {
+		let client = TestBlockChainClient::new();
+		let contract = H256::from("30000000000000000000000000000000000000000000000000000000000000aa");
+		let other = H256::from("40000000000000000000000000000000000000000000000000000000000000bb");
+		let code = vec![0x60, 0x00];
+		let storage = storage_leaf();
+		let contract_leaf = account_leaf(&contract, keccak(&storage), keccak(&code));
+		let other_leaf = account_leaf(&other, KECCAK_NULL_RLP, KECCAK_EMPTY);
+		let root = branch(&[(3, &contract_leaf), (4, &other_leaf)]);
+
+		let mut state_sync = StateSync::new();
+		state_sync.set_pivot(&client, pivot_header(keccak(&root)), U256::zero());
+		assert_eq!(state_sync.request_nodes(0, 16), vec![keccak(&root)]);
+		assert_eq!(import(&mut state_sync, &client, &[&root]), 1);
+		assert!(client.state_nodes.read().is_empty());
+
+		let mut requested = state_sync.request_nodes(0, 16);
+		requested.sort();
+		let mut expected = vec![keccak(&contract_leaf), keccak(&other_leaf)];
+		expected.sort();
+		assert_eq!(requested, expected);
+		assert_eq!(import(&mut state_sync, &client, &[&contract_leaf, &other_leaf]), 2);
+		assert_eq!(client.state_nodes.read().len(), 1);
+		assert!(client.state_node_known(None, &keccak(&other_leaf)));
+
+		assert_eq!(state_sync.request_nodes(0, 16).len(), 2);
+		assert_eq!(import(&mut state_sync, &client, &[&storage, &code]), 2);
+		assert!(client.state_node_known(Some(&contract), &keccak(&storage)));
+		assert!(client.state_node_known(Some(&contract), &keccak(&code)));
+		assert!(client.state_node_known(None, &keccak(&root)));
+		assert_eq!(state_sync.imported_nodes(), 5);
+		assert_eq!(state_sync.pending_nodes(), 0);
+		assert!(state_sync.root_done);
+		assert!(!state_sync.is_complete());
+	}
+
+	#[test]
+	fn undelivered_nodes_are_requested_again() {
+		let client = TestBlockChainClient::new();
+		let account = H256::from("30000000000000000000000000000000000000000000000000000000000000aa");
+		let leaf = account_leaf(&account, KECCAK_NULL_RLP, KECCAK_EMPTY);
+		let root = branch(&[(3, &leaf)]);
+
+		let mut state_sync = StateSync::new();
+		state_sync.set_pivot(&client, pivot_header(keccak(&root)), U256::zero());
+		state_sync.request_nodes(0, 16);
+		assert_eq!(import(&mut state_sync, &client, &[]), 0);
+		assert_eq!(state_sync.request_nodes(1, 16), vec![keccak(&root)]);
+		state_sync.clear_download(1);
+		assert_eq!(state_sync.request_nodes(0, 16), vec![keccak(&root)]);
+	}
+
+	#[test]
+	fn new_pivot_downloads_only_changed_nodes() {
+		let client = TestBlockChainClient::new();
+		let unchanged = H256::from("30000000000000000000000000000000000000000000000000000000000000aa");
+		let created = H256::from("40000000000000000000000000000000000000000000000000000000000000bb");
+		let unchanged_leaf = account_leaf(&unchanged, KECCAK_NULL_RLP, KECCAK_EMPTY);
+		let created_leaf = account_leaf(&created, KECCAK_NULL_RLP, KECCAK_EMPTY);
+		let old_root = branch(&[(3, &unchanged_leaf)]);
+		let new_root = branch(&[(3, &unchanged_leaf), (4, &created_leaf)]);
+
+		let mut state_sync = StateSync::new();
+		state_sync.set_pivot(&client, pivot_header(keccak(&old_root)), U256::zero());
+		state_sync.request_nodes(0, 16);
+		import(&mut state_sync, &client, &[&old_root]);
+		state_sync.request_nodes(0, 16);
+		import(&mut state_sync, &client, &[&unchanged_leaf]);
+		assert!(client.state_node_known(None, &keccak(&old_root)));
+
+		state_sync.set_pivot(&client, pivot_header(keccak(&new_root)), U256::zero());
+		state_sync.request_nodes(0, 16);
+		import(&mut state_sync, &client, &[&new_root]);
+		assert_eq!(state_sync.request_nodes(0, 16), vec![keccak(&created_leaf)]);
+	}
+
+	#[test]
+	fn pivot_is_oldest_of_linked_headers() {
+		let mut pivot = Header::new();
+		pivot.set_number(10);
+		pivot.set_difficulty(100.into());
+		let mut best = Header::new();
+		best.set_number(11);
+		best.set_difficulty(200.into());
+		best.set_parent_hash(pivot.hash());
+
+		let mut headers = RlpStream::new_list(2);
+		headers.append(&best).append(&pivot);
+		let headers = headers.out();
+		let (header, parent_total_difficulty) = pivot_from_headers(&Rlp::new(&headers), &best.hash(), 1000.into()).unwrap();
+		assert_eq!(header.header.hash(), pivot.hash());
+		assert_eq!(parent_total_difficulty, 700.into());
+
+		let mut unlinked = RlpStream::new_list(2);
+		unlinked.append(&best).append(&best);
+		let unlinked = unlinked.out();
+		assert!(pivot_from_headers(&Rlp::new(&unlinked), &best.hash(), 1000.into()).is_err());
+	}
+}
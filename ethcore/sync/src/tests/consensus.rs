@@ -27,7 +27,7 @@ use transaction::{Action, PendingTransaction, Transaction};
 use super::helpers::*;
 use SyncConfig;
 
-fn new_tx(secret: &Secret, nonce: U256, chain_id: u64) -> PendingTransaction {
+pub fn new_tx(secret: &Secret, nonce: U256, chain_id: u64) -> PendingTransaction {
 	let signed = Transaction {
 		nonce: nonce.into(),
 		gas_price: 0.into(),
@@ -20,7 +20,7 @@ use std::time::Duration;
 use ethereum_types::H256;
 use parking_lot::{RwLock, Mutex};
 use bytes::Bytes;
-use network::{self, PeerId, ProtocolId, PacketId, SessionInfo};
+use network::{self, PeerId, ProtocolId, PacketId, SessionInfo, PeerBehaviour};
 use tests::snapshot::*;
 use ethcore::client::{TestBlockChainClient, BlockChainClient, Client as EthcoreClient,
 	ClientConfig, ChainNotify, ChainRoute, ChainMessageType, ClientIoMessage};
@@ -33,9 +33,8 @@ use ethcore::test_helpers;
 use sync_io::SyncIo;
 use io::{IoChannel, IoContext, IoHandler};
 use api::WARP_SYNC_PROTOCOL_ID;
-use chain::{ChainSync, ETH_PROTOCOL_VERSION_63, PAR_PROTOCOL_VERSION_3, PRIVATE_TRANSACTION_PACKET, SIGNED_PRIVATE_TRANSACTION_PACKET};
+use chain::{ChainSync, Clock, ETH_PROTOCOL_VERSION_63, PAR_PROTOCOL_VERSION_3};
 use SyncConfig;
-use private_tx::SimplePrivateTxHandler;
 
 pub trait FlushingBlockChainClient: BlockChainClient {
 	fn flush(&self) {}
@@ -55,8 +54,11 @@ pub struct TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 	pub queue: &'p RwLock<VecDeque<TestPacket>>,
 	pub sender: Option<PeerId>,
 	pub to_disconnect: HashSet<PeerId>,
+	pub reported: Vec<(PeerId, PeerBehaviour)>,
 	pub packets: Vec<TestPacket>,
 	pub peers_info: HashMap<PeerId, String>,
+	pub eth_protocol_versions: HashMap<PeerId, u8>,
+	pub par_protocol_versions: HashMap<PeerId, u8>,
 	overlay: RwLock<HashMap<BlockNumber, Bytes>>,
 }
 
@@ -68,9 +70,12 @@ impl<'p, C> TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 			queue: queue,
 			sender: sender,
 			to_disconnect: HashSet::new(),
+			reported: Vec::new(),
 			overlay: RwLock::new(HashMap::new()),
 			packets: Vec::new(),
 			peers_info: HashMap::new(),
+			eth_protocol_versions: HashMap::new(),
+			par_protocol_versions: HashMap::new(),
 		}
 	}
 }
@@ -90,6 +95,15 @@ impl<'p, C> SyncIo for TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 		self.to_disconnect.insert(peer_id);
 	}
 
+	fn report_peer(&mut self, peer_id: PeerId, behaviour: PeerBehaviour) {
+		self.reported.push((peer_id, behaviour));
+		// the host disconnects peers without a reputation for these.
+		match behaviour {
+			PeerBehaviour::InvalidResponse | PeerBehaviour::BadBlock | PeerBehaviour::Unresponsive => self.disconnect_peer(peer_id),
+			PeerBehaviour::GoodBlock | PeerBehaviour::SlowResponse | PeerBehaviour::UselessResponse => {},
+		}
+	}
+
 	fn is_expired(&self) -> bool {
 		false
 	}
@@ -134,12 +148,18 @@ impl<'p, C> SyncIo for TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 		None
 	}
 
-	fn eth_protocol_version(&self, _peer: PeerId) -> u8 {
-		ETH_PROTOCOL_VERSION_63.0
+	fn eth_protocol_version(&self, peer_id: PeerId) -> u8 {
+		self.eth_protocol_versions.get(&peer_id)
+			.cloned()
+			.unwrap_or(ETH_PROTOCOL_VERSION_63.0)
 	}
 
 	fn protocol_version(&self, protocol: &ProtocolId, peer_id: PeerId) -> u8 {
-		if protocol == &WARP_SYNC_PROTOCOL_ID { PAR_PROTOCOL_VERSION_3.0 } else { self.eth_protocol_version(peer_id) }
+		if protocol == &WARP_SYNC_PROTOCOL_ID {
+			self.par_protocol_versions.get(&peer_id).cloned().unwrap_or(PAR_PROTOCOL_VERSION_3.0)
+		} else {
+			self.eth_protocol_version(peer_id)
+		}
 	}
 
 	fn chain_overlay(&self) -> &RwLock<HashMap<BlockNumber, Bytes>> {
@@ -161,6 +181,9 @@ struct NewBlockMessage {
 pub trait Message {
 	/// The intended recipient of this message.
 	fn recipient(&self) -> PeerId;
+
+	/// Size of the message payload in bytes.
+	fn size(&self) -> usize;
 }
 
 /// Mock subprotocol packet
@@ -172,6 +195,8 @@ pub struct TestPacket {
 
 impl Message for TestPacket {
 	fn recipient(&self) -> PeerId { self.recipient }
+
+	fn size(&self) -> usize { self.data.len() }
 }
 
 /// A peer which can be a member of the `TestNet`.
@@ -204,6 +229,9 @@ pub trait Peer {
 
 	/// Process the queue of new block messages
 	fn process_all_new_block_messages(&self);
+
+	/// Use `clock` for the request timeouts.
+	fn set_clock(&self, _clock: Arc<Clock>) {}
 }
 
 pub struct EthPeer<C> where C: FlushingBlockChainClient {
@@ -212,7 +240,11 @@ pub struct EthPeer<C> where C: FlushingBlockChainClient {
 	pub snapshot_service: Arc<TestSnapshotService>,
 	pub sync: RwLock<ChainSync>,
 	pub queue: RwLock<VecDeque<TestPacket>>,
-	pub private_tx_handler: Arc<SimplePrivateTxHandler>,
+	/// Private transactions broadcast by this peer. They are sent over the `ptx`
+	/// protocol, which is not part of the test network.
+	pub private_txs: RwLock<Vec<Vec<u8>>>,
+	/// Signed private transactions broadcast by this peer.
+	pub signed_private_txs: RwLock<Vec<Vec<u8>>>,
 	pub io_queue: RwLock<VecDeque<ChainMessageType>>,
 	new_blocks_queue: RwLock<VecDeque<NewBlockMessage>>,
 }
@@ -230,10 +262,8 @@ impl<C> EthPeer<C> where C: FlushingBlockChainClient {
 		let mut io = TestIo::new(&*self.chain, &self.snapshot_service, &self.queue, None);
 		match message {
 			ChainMessageType::Consensus(data) => self.sync.write().propagate_consensus_packet(&mut io, data),
-			ChainMessageType::PrivateTransaction(transaction_hash, data) =>
-				self.sync.write().propagate_private_transaction(&mut io, transaction_hash, PRIVATE_TRANSACTION_PACKET, data),
-			ChainMessageType::SignedPrivateTransaction(transaction_hash, data) =>
-				self.sync.write().propagate_private_transaction(&mut io, transaction_hash, SIGNED_PRIVATE_TRANSACTION_PACKET, data),
+			ChainMessageType::PrivateTransaction(_, data) => self.private_txs.write().push(data),
+			ChainMessageType::SignedPrivateTransaction(_, data) => self.signed_private_txs.write().push(data),
 		}
 	}
 
@@ -311,6 +341,10 @@ impl<C: FlushingBlockChainClient> Peer for EthPeer<C> {
 			}
 		}
 	}
+
+	fn set_clock(&self, clock: Arc<Clock>) {
+		self.sync.write().set_clock(clock);
+	}
 }
 
 pub struct TestNet<P> {
@@ -339,15 +373,15 @@ impl TestNet<EthPeer<TestBlockChainClient>> {
 		for _ in 0..n {
 			let chain = TestBlockChainClient::new();
 			let ss = Arc::new(TestSnapshotService::new());
-			let private_tx_handler = Arc::new(SimplePrivateTxHandler::default());
-			let sync = ChainSync::new(config.clone(), &chain, private_tx_handler.clone());
+			let sync = ChainSync::new(config.clone(), &chain);
 			net.peers.push(Arc::new(EthPeer {
 				sync: RwLock::new(sync),
 				snapshot_service: ss,
 				chain: Arc::new(chain),
 				miner: Arc::new(Miner::new_for_tests(&Spec::new_test(), None)),
 				queue: RwLock::new(VecDeque::new()),
-				private_tx_handler,
+				private_txs: RwLock::new(Vec::new()),
+				signed_private_txs: RwLock::new(Vec::new()),
 				io_queue: RwLock::new(VecDeque::new()),
 				new_blocks_queue: RwLock::new(VecDeque::new()),
 			}));
@@ -392,16 +426,16 @@ impl TestNet<EthPeer<EthcoreClient>> {
 			channel.clone()
 		).unwrap();
 
-		let private_tx_handler = Arc::new(SimplePrivateTxHandler::default());
 		let ss = Arc::new(TestSnapshotService::new());
-		let sync = ChainSync::new(config, &*client, private_tx_handler.clone());
+		let sync = ChainSync::new(config, &*client);
 		let peer = Arc::new(EthPeer {
 			sync: RwLock::new(sync),
 			snapshot_service: ss,
 			chain: client,
 			miner,
 			queue: RwLock::new(VecDeque::new()),
-			private_tx_handler,
+			private_txs: RwLock::new(Vec::new()),
+			signed_private_txs: RwLock::new(Vec::new()),
 			io_queue: RwLock::new(VecDeque::new()),
 			new_blocks_queue: RwLock::new(VecDeque::new()),
 		});
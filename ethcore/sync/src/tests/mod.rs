@@ -16,9 +16,11 @@
 
 pub mod helpers;
 pub mod snapshot;
+pub mod simulator;
 mod chain;
 mod consensus;
 mod private;
+mod simulation;
 
 #[cfg(feature = "ipc")]
 mod rpc;
@@ -124,26 +124,24 @@ fn send_private_transaction() {
 	let private_tx = private_tx.sign(&s0.secret(), None);
 	assert!(pm0.create_private_transaction(private_tx).is_ok());
 
-	//send private transaction message to validator
+	//broadcast private transaction message to validator
 	net.sync();
 
-	let validator_handler = net.peer(1).private_tx_handler.clone();
-	let received_private_transactions = validator_handler.txs.lock().clone();
-	assert_eq!(received_private_transactions.len(), 1);
+	let sent_private_transactions = net.peer(0).private_txs.read().clone();
+	assert_eq!(sent_private_transactions.len(), 1);
 
 	//process received private transaction message
-	let private_transaction = received_private_transactions[0].clone();
+	let private_transaction = sent_private_transactions[0].clone();
 	assert!(pm1.import_private_transaction(&private_transaction).is_ok());
 
 	//send signed response
 	net.sync();
 
-	let sender_handler = net.peer(0).private_tx_handler.clone();
-	let received_signed_private_transactions = sender_handler.signed_txs.lock().clone();
-	assert_eq!(received_signed_private_transactions.len(), 1);
+	let sent_signed_private_transactions = net.peer(1).signed_private_txs.read().clone();
+	assert_eq!(sent_signed_private_transactions.len(), 1);
 
 	//process signed response
-	let signed_private_transaction = received_signed_private_transactions[0].clone();
+	let signed_private_transaction = sent_signed_private_transactions[0].clone();
 	assert!(pm0.import_signed_private_transaction(&signed_private_transaction).is_ok());
 	let signature: SignedPrivateTransaction = Rlp::new(&signed_private_transaction).as_val().unwrap();
 	assert!(pm0.process_signature(&signature).is_ok());
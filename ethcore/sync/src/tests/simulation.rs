@@ -0,0 +1,313 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use hash::keccak;
+use io::{IoHandler, IoChannel};
+use ethcore::client::{TestBlockChainClient, Client as EthcoreClient, BlockId, BlockInfo, ChainInfo, ClientIoMessage, EachBlockWith};
+use ethcore::spec::Spec;
+use ethcore::miner::MinerService;
+use ethcore::account_provider::AccountProvider;
+use ethkey::KeyPair;
+use super::consensus::new_tx;
+use super::helpers::*;
+use super::simulator::*;
+use SyncConfig;
+
+fn chain_net(blocks: usize, seed: u64) -> SimNet<EthPeer<TestBlockChainClient>> {
+	let net = TestNet::new(2);
+	net.peer(1).chain.add_blocks(blocks, EachBlockWith::Uncle);
+	SimNet::new(net, seed)
+}
+
+#[test]
+fn syncs_over_slow_links() {
+	::env_logger::try_init().ok();
+	let mut net = SimNet::new(TestNet::new(3), 1);
+	net.peer(1).chain.add_blocks(100, EachBlockWith::Uncle);
+	net.peer(2).chain.add_blocks(100, EachBlockWith::Uncle);
+	net.set_default_link(LinkConfig {
+		latency: 100,
+		jitter: 20,
+		bandwidth: Some(1024 * 1024),
+		loss: 0.0,
+	});
+
+	let elapsed = net.sync();
+
+	assert!(elapsed >= 200);
+	assert!(net.peer(0).chain.block(BlockId::Number(100)).is_some());
+	assert_eq!(*net.peer(0).chain.blocks.read(), *net.peer(1).chain.blocks.read());
+	assert_eq!(net.stats.sent, net.stats.delivered);
+}
+
+#[test]
+fn bandwidth_delays_delivery() {
+	let mut fast = chain_net(100, 1);
+	let mut slow = chain_net(100, 1);
+	slow.set_default_link(LinkConfig {
+		bandwidth: Some(10 * 1024),
+		..Default::default()
+	});
+
+	let fast_elapsed = fast.sync();
+	let slow_elapsed = slow.sync();
+
+	assert!(slow_elapsed > fast_elapsed);
+	assert_eq!(*slow.peer(0).chain.blocks.read(), *slow.peer(1).chain.blocks.read());
+}
+
+#[test]
+fn same_seed_replays_identically() {
+	let mut first = chain_net(50, 42);
+	let mut second = chain_net(50, 42);
+	for net in [&mut first, &mut second].iter_mut() {
+		net.set_default_link(LinkConfig {
+			latency: 30,
+			jitter: 30,
+			bandwidth: None,
+			loss: 0.2,
+		});
+		net.run_for(2000);
+	}
+
+	assert!(first.stats.sent > 0);
+	assert_eq!(first.stats, second.stats);
+	assert_eq!(*first.peer(0).chain.numbers.read(), *second.peer(0).chain.numbers.read());
+}
+
+#[test]
+fn same_seed_replays_identically_with_many_peers() {
+	let run = || {
+		let net = TestNet::new(5);
+		net.peer(1).chain.add_blocks(60, EachBlockWith::Uncle);
+		net.peer(3).chain.add_blocks(80, EachBlockWith::Nothing);
+		let mut net = SimNet::new(net, 1234);
+		net.set_default_link(LinkConfig {
+			latency: 40,
+			jitter: 60,
+			bandwidth: Some(256 * 1024),
+			loss: 0.1,
+		});
+		net.run_for(3000);
+		net
+	};
+	let first = run();
+	let second = run();
+
+	assert!(first.stats.sent > 0);
+	assert_eq!(first.stats, second.stats);
+	for i in 0..5 {
+		assert_eq!(*first.peer(i).chain.numbers.read(), *second.peer(i).chain.numbers.read());
+	}
+}
+
+#[test]
+fn request_timeouts_run_on_virtual_clock() {
+	let mut net = chain_net(2000, 3);
+	net.set_default_link(LinkConfig::with_latency(50));
+	assert!(net.run_until(10_000, |net| net.peer(0).chain.chain_info().best_block_number > 0));
+
+	// Requests of peer 0 are answered no more.
+	net.set_link(1, 0, LinkConfig {
+		loss: 1.0,
+		..LinkConfig::with_latency(50)
+	});
+	let start = net.elapsed();
+	assert!(net.run_until(60_000, |net| net.peer(0).sync.read().status().num_peers == 0));
+
+	assert!(net.elapsed() - start >= 10_000);
+	assert!(net.peer(0).chain.chain_info().best_block_number < 2000);
+}
+
+#[test]
+fn partitioned_peers_reorg_after_heal() {
+	::env_logger::try_init().ok();
+	let net = TestNet::new(4);
+	for i in 0..4 {
+		net.peer(i).chain.add_blocks(10, EachBlockWith::Nothing);
+	}
+	net.peer(0).chain.add_blocks(5, EachBlockWith::Nothing);
+	net.peer(2).chain.add_blocks(10, EachBlockWith::Uncle);
+	let peer0_chain = net.peer(0).chain.numbers.read().clone();
+	let peer2_chain = net.peer(2).chain.numbers.read().clone();
+
+	let mut net = SimNet::new(net, 7);
+	net.set_default_link(LinkConfig::with_latency(50));
+	net.partition(&[&[0, 1], &[2, 3]]);
+	net.sync();
+
+	assert_eq!(&*net.peer(1).chain.numbers.read(), &peer0_chain);
+	assert_eq!(&*net.peer(3).chain.numbers.read(), &peer2_chain);
+
+	net.heal();
+	net.sync();
+
+	for i in 0..4 {
+		assert_eq!(&*net.peer(i).chain.numbers.read(), &peer2_chain);
+	}
+}
+
+#[test]
+fn reorg_storm_converges() {
+	::env_logger::try_init().ok();
+	let net = TestNet::new(4);
+	for i in 0..4 {
+		net.peer(i).chain.add_blocks(10, EachBlockWith::Nothing);
+	}
+	let mut net = SimNet::new(net, 11);
+	net.set_default_link(LinkConfig {
+		latency: 50,
+		jitter: 20,
+		bandwidth: None,
+		loss: 0.0,
+	});
+
+	for round in 0..4 {
+		let long = round % 4;
+		let short = (round + 2) % 4;
+		// Both leaders fork off the common chain, the long one further.
+		net.peer(short).chain.add_blocks(2 + round, EachBlockWith::Nothing);
+		net.peer(long).chain.add_blocks(4 + round, EachBlockWith::Uncle);
+		let short_chain = net.peer(short).chain.numbers.read().clone();
+		let long_chain = net.peer(long).chain.numbers.read().clone();
+
+		net.partition(&[&[long, (long + 1) % 4], &[short, (short + 1) % 4]]);
+		net.net.trigger_chain_new_blocks(long);
+		net.net.trigger_chain_new_blocks(short);
+		net.sync();
+
+		assert_eq!(&*net.peer((long + 1) % 4).chain.numbers.read(), &long_chain);
+		assert_eq!(&*net.peer((short + 1) % 4).chain.numbers.read(), &short_chain);
+
+		net.heal();
+		net.sync();
+
+		for i in 0..4 {
+			assert_eq!(&*net.peer(i).chain.numbers.read(), &long_chain, "peer {} in round {}", i, round);
+		}
+	}
+}
+
+struct ValidatorNet {
+	net: SimNet<EthPeer<EthcoreClient>>,
+	keys: [KeyPair; 2],
+	chain_id: u64,
+	_io_handlers: Vec<Arc<IoHandler<ClientIoMessage>>>,
+}
+
+fn validator_net(spec_factory: fn() -> Spec, seed: u64) -> ValidatorNet {
+	let keys = [
+		KeyPair::from_secret_slice(&keccak("1")).unwrap(),
+		KeyPair::from_secret_slice(&keccak("0")).unwrap(),
+	];
+	let ap = Arc::new(AccountProvider::transient_provider());
+	for key in &keys {
+		ap.insert_account(key.secret().clone(), &"".into()).unwrap();
+	}
+
+	let chain_id = spec_factory().chain_id();
+	let net = TestNet::with_spec_and_accounts(2, SyncConfig::default(), spec_factory, Some(ap));
+	let mut io_handlers = Vec::new();
+	for (i, key) in keys.iter().enumerate() {
+		let peer = net.peer(i);
+		let io_handler: Arc<IoHandler<ClientIoMessage>> = Arc::new(TestIoHandler::new(peer.chain.clone()));
+		peer.miner.set_author(key.address(), Some("".into())).unwrap();
+		peer.chain.engine().register_client(Arc::downgrade(&peer.chain) as _);
+		peer.chain.set_io_channel(IoChannel::to_handler(Arc::downgrade(&io_handler)));
+		io_handlers.push(io_handler);
+	}
+
+	let mut net = SimNet::new(net, seed);
+	net.set_default_link(LinkConfig {
+		latency: 150,
+		jitter: 100,
+		bandwidth: Some(64 * 1024),
+		loss: 0.0,
+	});
+	ValidatorNet { net, keys, chain_id, _io_handlers: io_handlers }
+}
+
+impl ValidatorNet {
+	fn import_txs(&self, nonce: u64) {
+		for i in 0..2 {
+			let peer = self.net.peer(i);
+			let tx = new_tx(self.keys[i].secret(), nonce.into(), self.chain_id);
+			peer.miner.import_own_transaction(&*peer.chain, tx).unwrap();
+		}
+	}
+
+	fn step(&self) {
+		for i in 0..2 {
+			self.net.peer(i).chain.engine().step();
+		}
+	}
+
+	fn assert_best_block(&self, number: u64) {
+		let ci0 = self.net.peer(0).chain.chain_info();
+		let ci1 = self.net.peer(1).chain.chain_info();
+		assert_eq!(ci0.best_block_number, number);
+		assert_eq!(ci1.best_block_number, number);
+		assert_eq!(ci0.best_block_hash, ci1.best_block_hash);
+	}
+}
+
+#[test]
+fn authority_round_stays_live_over_slow_links() {
+	let mut v = validator_net(Spec::new_test_round, 5);
+	v.net.sync();
+
+	// Only the primary of the current step seals.
+	v.import_txs(0);
+	v.net.sync();
+	v.assert_best_block(1);
+
+	// Every step hands over to the other validator.
+	for nonce in 1..5 {
+		v.import_txs(nonce);
+		v.step();
+		v.net.sync();
+		v.assert_best_block(nonce + 1);
+	}
+}
+
+#[test]
+fn tendermint_stays_live_over_slow_links() {
+	let mut v = validator_net(Spec::new_test_tendermint, 9);
+	v.net.sync();
+
+	// Propose
+	let tx = new_tx(v.keys[0].secret(), 0.into(), v.chain_id);
+	v.net.peer(0).miner.import_own_transaction(&*v.net.peer(0).chain, tx).unwrap();
+	v.net.sync();
+	// Propose timeout
+	v.step();
+	// Prevote, precommit and commit
+	v.net.sync();
+	v.assert_best_block(1);
+
+	let tx = new_tx(v.keys[1].secret(), 0.into(), v.chain_id);
+	v.net.peer(1).miner.import_own_transaction(&*v.net.peer(1).chain, tx).unwrap();
+	// Commit timeout
+	v.step();
+	// Propose
+	v.net.sync();
+	// Propose timeout
+	v.step();
+	// Prevote, precommit and commit
+	v.net.sync();
+	v.assert_best_block(2);
+}
@@ -0,0 +1,349 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic network simulation on top of `TestNet`.
+//!
+//! Packets are delivered according to a virtual clock and per-link latency, jitter,
+//! bandwidth and loss. All randomness comes from a seeded generator, so a scenario
+//! replays identically for the same seed. A partition tears down the sessions between
+//! peers of different groups, the same way a real split does once the sessions time out,
+//! and healing it reconnects them.
+//!
+//! Request timeouts of the peers run on the same virtual clock and their random
+//! peer choices are seeded from the simulation seed. Consensus engines still step
+//! on their own, tests drive them explicitly.
+
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chain::{Clock, random};
+use network::PeerId;
+use parking_lot::RwLock;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use super::helpers::{TestNet, Peer, Message};
+
+/// Virtual time advanced by a single tick, in milliseconds.
+const DEFAULT_TICK_MS: u64 = 10;
+/// Virtual time after which `SimNet::sync` gives up, in milliseconds.
+const MAX_SYNC_TIME_MS: u64 = 60 * 60 * 1000;
+
+/// Properties of a directed link between two peers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConfig {
+	/// One-way delay in milliseconds.
+	pub latency: u64,
+	/// Upper bound of a random delay added to `latency`, in milliseconds.
+	pub jitter: u64,
+	/// Bytes per second, `None` for unlimited.
+	pub bandwidth: Option<u64>,
+	/// Probability of a packet being dropped.
+	pub loss: f64,
+}
+
+impl Default for LinkConfig {
+	fn default() -> Self {
+		LinkConfig {
+			latency: 0,
+			jitter: 0,
+			bandwidth: None,
+			loss: 0.0,
+		}
+	}
+}
+
+impl LinkConfig {
+	/// Link with the given latency and no other restrictions.
+	pub fn with_latency(latency: u64) -> Self {
+		LinkConfig {
+			latency,
+			..Default::default()
+		}
+	}
+}
+
+/// Packet counters of a simulated network.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SimStats {
+	/// Packets sent by peers.
+	pub sent: usize,
+	/// Packets delivered to their recipients.
+	pub delivered: usize,
+	/// Packets dropped by lossy links.
+	pub lost: usize,
+	/// Packets dropped because the session between the peers was down.
+	pub cut: usize,
+}
+
+#[derive(Default)]
+struct LinkState {
+	/// Time until which the link is busy transmitting previous packets.
+	busy_until: u64,
+	/// Delivery time of the last packet, packets of a session are never reordered.
+	last_delivery: u64,
+}
+
+/// Clock advanced by the simulation ticks.
+pub struct VirtualClock {
+	start: Instant,
+	elapsed: RwLock<u64>,
+}
+
+impl VirtualClock {
+	fn new() -> Self {
+		VirtualClock {
+			start: Instant::now(),
+			elapsed: RwLock::new(0),
+		}
+	}
+
+	fn set(&self, elapsed: u64) {
+		*self.elapsed.write() = elapsed;
+	}
+}
+
+impl Clock for VirtualClock {
+	fn now(&self) -> Instant {
+		self.start + Duration::from_millis(*self.elapsed.read())
+	}
+}
+
+fn session(a: PeerId, b: PeerId) -> (PeerId, PeerId) {
+	(cmp::min(a, b), cmp::max(a, b))
+}
+
+/// `TestNet` driven by a virtual clock over simulated links.
+pub struct SimNet<P: Peer> {
+	/// Simulated peers.
+	pub net: TestNet<P>,
+	/// Packet counters.
+	pub stats: SimStats,
+	tick: u64,
+	now: u64,
+	clock: Arc<VirtualClock>,
+	seq: u64,
+	rng: XorShiftRng,
+	default_link: LinkConfig,
+	links: HashMap<(PeerId, PeerId), LinkConfig>,
+	link_states: HashMap<(PeerId, PeerId), LinkState>,
+	disconnected: HashSet<(PeerId, PeerId)>,
+	cut: Vec<(PeerId, PeerId)>,
+	in_flight: BTreeMap<(u64, u64), (PeerId, P::Message)>,
+}
+
+impl<P: Peer> SimNet<P> {
+	/// Simulate `net` with all random decisions derived from `seed`.
+	/// Peers of `net` are switched to the virtual clock.
+	pub fn new(net: TestNet<P>, seed: u64) -> Self {
+		let clock = Arc::new(VirtualClock::new());
+		for peer in &net.peers {
+			peer.set_clock(clock.clone());
+		}
+		random::set_seed([seed as u32, (seed >> 32) as u32, 0x2545_f491, 0x6c07_8965]);
+
+		SimNet {
+			net,
+			stats: SimStats::default(),
+			tick: DEFAULT_TICK_MS,
+			now: 0,
+			clock,
+			seq: 0,
+			rng: XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9e37_79b9, 0x7f4a_7c15]),
+			default_link: LinkConfig::default(),
+			links: HashMap::new(),
+			link_states: HashMap::new(),
+			disconnected: HashSet::new(),
+			cut: Vec::new(),
+			in_flight: BTreeMap::new(),
+		}
+	}
+
+	/// Get a peer of the network.
+	pub fn peer(&self, i: usize) -> &P {
+		self.net.peer(i)
+	}
+
+	/// Virtual time elapsed since the simulation started, in milliseconds.
+	pub fn elapsed(&self) -> u64 {
+		self.now
+	}
+
+	/// Set the virtual time advanced by a single tick, in milliseconds.
+	pub fn set_tick(&mut self, tick: u64) {
+		assert!(tick > 0, "tick must advance the clock");
+		self.tick = tick;
+	}
+
+	/// Set the configuration of all links without an explicit one.
+	pub fn set_default_link(&mut self, config: LinkConfig) {
+		self.default_link = config;
+	}
+
+	/// Set the configuration of the link from `from` to `to`.
+	pub fn set_link(&mut self, from: PeerId, to: PeerId, config: LinkConfig) {
+		self.links.insert((from, to), config);
+	}
+
+	/// Split the network into `groups`, disconnecting peers of different groups.
+	/// Peers not listed in any group form a group of their own.
+	/// Replaces any previous partition.
+	pub fn partition(&mut self, groups: &[&[PeerId]]) {
+		self.net.start();
+		self.heal();
+
+		let group_of = |peer: PeerId| groups.iter().position(|group| group.contains(&peer));
+		let count = self.net.peers.len();
+		for a in 0..count {
+			for b in (a + 1)..count {
+				if group_of(a) != group_of(b) && self.disconnect(a, b) {
+					self.cut.push((a, b));
+				}
+			}
+		}
+	}
+
+	/// Reconnect peers separated by the current partition.
+	pub fn heal(&mut self) {
+		let cut: Vec<_> = self.cut.drain(..).collect();
+		for (a, b) in cut {
+			self.disconnected.remove(&(a, b));
+			self.net.peers[a].on_connect(b);
+			self.net.peers[b].on_connect(a);
+		}
+	}
+
+	/// Advance the clock by a single tick: send out pending packets, deliver
+	/// the packets due by the new time and run a sync step on every peer.
+	pub fn tick(&mut self) {
+		self.net.start();
+		for from in 0..self.net.peers.len() {
+			while let Some(packet) = self.net.peers[from].pending_message() {
+				self.schedule(from, packet);
+			}
+		}
+
+		self.now += self.tick;
+		self.clock.set(self.now);
+		loop {
+			let key = match self.in_flight.keys().next() {
+				Some(&key) if key.0 <= self.now => key,
+				_ => break,
+			};
+			let (from, packet) = self.in_flight.remove(&key).expect("key was just read from the map; qed");
+			self.deliver(from, packet);
+		}
+
+		for peer in &self.net.peers {
+			peer.sync_step();
+		}
+		self.net.deliver_io_messages();
+		self.net.deliver_new_block_messages();
+	}
+
+	/// Run for `duration` milliseconds of virtual time.
+	pub fn run_for(&mut self, duration: u64) {
+		let end = self.now + duration;
+		while self.now < end {
+			self.tick();
+		}
+	}
+
+	/// Run until `condition` holds, for at most `limit` milliseconds of virtual time.
+	/// Returns whether the condition was met.
+	pub fn run_until<F>(&mut self, limit: u64, mut condition: F) -> bool where F: FnMut(&Self) -> bool {
+		let end = self.now + limit;
+		while !condition(self) {
+			if self.now >= end {
+				return false;
+			}
+			self.tick();
+		}
+		true
+	}
+
+	/// Run until no packets are pending or in flight.
+	/// Returns the virtual time it took, in milliseconds.
+	pub fn sync(&mut self) -> u64 {
+		self.net.start();
+		let start = self.now;
+		while !self.done() {
+			assert!(self.now - start < MAX_SYNC_TIME_MS, "network did not settle within {}ms", MAX_SYNC_TIME_MS);
+			self.tick();
+		}
+		self.now - start
+	}
+
+	/// Whether no packets are pending or in flight.
+	pub fn done(&self) -> bool {
+		self.in_flight.is_empty() && self.net.done()
+	}
+
+	fn schedule(&mut self, from: PeerId, packet: P::Message) {
+		let to = packet.recipient();
+		self.stats.sent += 1;
+		if self.disconnected.contains(&session(from, to)) {
+			self.stats.cut += 1;
+			return;
+		}
+
+		let config = self.links.get(&(from, to)).cloned().unwrap_or(self.default_link);
+		if config.loss > 0.0 && self.rng.gen::<f64>() < config.loss {
+			trace!(target: "sync", "--- {} -> {} lost ---", from, to);
+			self.stats.lost += 1;
+			return;
+		}
+		let jitter = if config.jitter > 0 { self.rng.gen_range(0, config.jitter + 1) } else { 0 };
+		let transmission = config.bandwidth.map_or(0, |bandwidth| packet.size() as u64 * 1000 / cmp::max(bandwidth, 1));
+
+		let state = self.link_states.entry((from, to)).or_insert_with(LinkState::default);
+		let departure = cmp::max(self.now, state.busy_until) + transmission;
+		state.busy_until = departure;
+		let delivery = cmp::max(departure + config.latency + jitter, state.last_delivery);
+		state.last_delivery = delivery;
+
+		self.seq += 1;
+		self.in_flight.insert((delivery, self.seq), (from, packet));
+	}
+
+	fn deliver(&mut self, from: PeerId, packet: P::Message) {
+		let to = packet.recipient();
+		if self.disconnected.contains(&session(from, to)) {
+			self.stats.cut += 1;
+			return;
+		}
+
+		trace!(target: "sync", "--- {} -> {} at {}ms ---", from, to, self.now);
+		self.stats.delivered += 1;
+		let mut to_disconnect: Vec<_> = self.net.peers[to].receive_message(from, packet).into_iter().collect();
+		to_disconnect.sort();
+		for peer in to_disconnect {
+			if self.disconnect(to, peer) {
+				self.net.disconnect_events.push((to, peer));
+			}
+		}
+	}
+
+	fn disconnect(&mut self, a: PeerId, b: PeerId) -> bool {
+		if !self.disconnected.insert(session(a, b)) {
+			return false;
+		}
+		self.net.peers[a].on_disconnect(b);
+		self.net.peers[b].on_disconnect(a);
+		true
+	}
+}
@@ -0,0 +1,271 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Fetching of transactions announced by peers with `NewPooledTransactionHashes`. Each announced
+//! transaction is requested from one peer at a time; if that peer does not deliver it in time it is
+//! requested from the next peer which announced it.
+
+use std::collections::{HashMap, HashSet};
+use HashState;
+use std::time::Instant;
+use ethereum_types::H256;
+use network::PeerId;
+
+/// Maximum number of announced transactions waiting to be fetched.
+const MAX_ANNOUNCED_TRANSACTIONS: usize = 16384;
+
+struct Announcement {
+	/// Peers which announced the transaction and were not asked for it yet.
+	announcers: Vec<PeerId>,
+	/// Peer the transaction is currently requested from.
+	fetching: Option<PeerId>,
+}
+
+struct Request {
+	hashes: Vec<H256>,
+	time: Instant,
+}
+
+/// Keeps track of announced transactions and of the peers they are requested from.
+#[derive(Default)]
+pub struct TransactionsFetcher {
+	announced: HashMap<H256, Announcement, HashState>,
+	requests: HashMap<PeerId, Request, HashState>,
+}
+
+impl TransactionsFetcher {
+	/// Create a new instance.
+	pub fn new() -> TransactionsFetcher {
+		TransactionsFetcher::default()
+	}
+
+	/// Forget all announcements and pending requests.
+	pub fn clear(&mut self) {
+		self.announced.clear();
+		self.requests.clear();
+	}
+
+	/// Number of announced transactions which are not received yet.
+	pub fn pending(&self) -> usize {
+		self.announced.len()
+	}
+
+	/// Note transactions announced by a peer. Callers filter out transactions which are already known.
+	pub fn announced<I: IntoIterator<Item=H256>>(&mut self, peer_id: PeerId, hashes: I) {
+		for hash in hashes {
+			if let Some(announcement) = self.announced.get_mut(&hash) {
+				if announcement.fetching != Some(peer_id) && !announcement.announcers.contains(&peer_id) {
+					announcement.announcers.push(peer_id);
+				}
+				continue;
+			}
+			if self.announced.len() >= MAX_ANNOUNCED_TRANSACTIONS {
+				trace!(target: "sync", "{}: Too many announced transactions, ignoring the rest", peer_id);
+				break;
+			}
+			self.announced.insert(hash, Announcement {
+				announcers: vec![peer_id],
+				fetching: None,
+			});
+		}
+	}
+
+	/// Pick up to `max` transactions announced by the peer and not requested from anyone else.
+	/// Nothing is picked while a request to the peer is pending. The request is sent at `now`.
+	pub fn request(&mut self, peer_id: PeerId, max: usize, now: Instant) -> Vec<H256> {
+		if self.requests.contains_key(&peer_id) {
+			return Vec::new();
+		}
+		let mut hashes = Vec::new();
+		for (hash, announcement) in &mut self.announced {
+			if hashes.len() == max {
+				break;
+			}
+			if announcement.fetching.is_none() && announcement.announcers.contains(&peer_id) {
+				announcement.fetching = Some(peer_id);
+				announcement.announcers.retain(|p| *p != peer_id);
+				hashes.push(*hash);
+			}
+		}
+		if !hashes.is_empty() {
+			self.requests.insert(peer_id, Request { hashes: hashes.clone(), time: now });
+		}
+		hashes
+	}
+
+	/// Peers without a pending request which announced transactions nobody is asked for.
+	pub fn idle_announcers(&self) -> Vec<PeerId> {
+		let peers: HashSet<PeerId, HashState> = self.announced.values()
+			.filter(|a| a.fetching.is_none())
+			.flat_map(|a| a.announcers.iter().cloned())
+			.filter(|p| !self.requests.contains_key(p))
+			.collect();
+		peers.into_iter().collect()
+	}
+
+	/// Note transactions received from any peer, requested or not.
+	pub fn received(&mut self, hashes: &HashSet<H256>) {
+		for hash in hashes {
+			self.announced.remove(hash);
+		}
+	}
+
+	/// Note a response to a transactions request. Requested transactions missing from the response are
+	/// left to the other peers which announced them. Returns `false` if nothing was requested from the peer.
+	pub fn response(&mut self, peer_id: PeerId, hashes: &HashSet<H256>) -> bool {
+		self.received(hashes);
+		match self.requests.remove(&peer_id) {
+			Some(request) => {
+				self.release(request.hashes);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Drop requests sent before `deadline`, their transactions are left to other peers.
+	/// Returns peers which did not respond in time.
+	pub fn expire(&mut self, deadline: Instant) -> Vec<PeerId> {
+		let expired: Vec<PeerId> = self.requests.iter()
+			.filter(|&(_, r)| r.time < deadline)
+			.map(|(p, _)| *p)
+			.collect();
+		for peer_id in &expired {
+			if let Some(request) = self.requests.remove(peer_id) {
+				self.release(request.hashes);
+			}
+		}
+		expired
+	}
+
+	/// Forget a disconnected peer.
+	pub fn peer_disconnected(&mut self, peer_id: PeerId) {
+		if let Some(request) = self.requests.remove(&peer_id) {
+			self.release(request.hashes);
+		}
+		for announcement in self.announced.values_mut() {
+			announcement.announcers.retain(|p| *p != peer_id);
+		}
+		self.announced.retain(|_, a| a.fetching.is_some() || !a.announcers.is_empty());
+	}
+
+	/// Make requested transactions which are still not received available to other announcers.
+	fn release(&mut self, hashes: Vec<H256>) {
+		for hash in hashes {
+			let forget = match self.announced.get_mut(&hash) {
+				Some(announcement) => {
+					announcement.fetching = None;
+					announcement.announcers.is_empty()
+				},
+				None => false,
+			};
+			if forget {
+				trace!(target: "sync", "No more peers to fetch transaction {:?} from", hash);
+				self.announced.remove(&hash);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::*;
+
+	fn hashes(items: &[H256]) -> HashSet<H256> {
+		items.iter().cloned().collect()
+	}
+
+	#[test]
+	fn requests_each_transaction_from_one_peer() {
+		let mut fetcher = TransactionsFetcher::new();
+		let (a, b) = (H256::from(1u64), H256::from(2u64));
+		fetcher.announced(1, vec![a, b]);
+		fetcher.announced(2, vec![a, b]);
+
+		let mut requested = fetcher.request(1, 16, Instant::now());
+		requested.sort();
+		assert_eq!(requested, vec![a, b]);
+		assert!(fetcher.request(2, 16, Instant::now()).is_empty());
+		// a second request waits for the response
+		fetcher.announced(1, vec![H256::from(3u64)]);
+		assert!(fetcher.request(1, 16, Instant::now()).is_empty());
+		assert_eq!(fetcher.pending(), 3);
+	}
+
+	#[test]
+	fn fetches_undelivered_transactions_from_other_announcers() {
+		let mut fetcher = TransactionsFetcher::new();
+		let (a, b) = (H256::from(1u64), H256::from(2u64));
+		fetcher.announced(1, vec![a, b]);
+		fetcher.announced(2, vec![a, b]);
+		fetcher.request(1, 16, Instant::now());
+
+		assert!(fetcher.response(1, &hashes(&[a])));
+		assert_eq!(fetcher.idle_announcers(), vec![2]);
+		assert_eq!(fetcher.request(2, 16, Instant::now()), vec![b]);
+		assert!(fetcher.response(2, &hashes(&[b])));
+		assert_eq!(fetcher.pending(), 0);
+		assert!(!fetcher.response(2, &hashes(&[b])));
+	}
+
+	#[test]
+	fn forgets_transactions_nobody_else_announced() {
+		let mut fetcher = TransactionsFetcher::new();
+		let a = H256::from(1u64);
+		fetcher.announced(1, vec![a]);
+		fetcher.request(1, 16, Instant::now());
+		fetcher.response(1, &HashSet::new());
+		assert_eq!(fetcher.pending(), 0);
+		assert!(fetcher.idle_announcers().is_empty());
+	}
+
+	#[test]
+	fn expires_slow_requests() {
+		let mut fetcher = TransactionsFetcher::new();
+		let a = H256::from(1u64);
+		fetcher.announced(1, vec![a]);
+		fetcher.announced(2, vec![a]);
+		fetcher.request(1, 16, Instant::now());
+
+		assert!(fetcher.expire(Instant::now() - Duration::from_secs(5)).is_empty());
+		assert_eq!(fetcher.expire(Instant::now() + Duration::from_secs(1)), vec![1]);
+		assert_eq!(fetcher.request(2, 16, Instant::now()), vec![a]);
+	}
+
+	#[test]
+	fn drops_disconnected_peers() {
+		let mut fetcher = TransactionsFetcher::new();
+		let (a, b) = (H256::from(1u64), H256::from(2u64));
+		fetcher.announced(1, vec![a, b]);
+		fetcher.announced(2, vec![b]);
+		fetcher.request(1, 1, Instant::now());
+
+		fetcher.peer_disconnected(1);
+		assert_eq!(fetcher.pending(), 1);
+		assert_eq!(fetcher.request(2, 16, Instant::now()), vec![b]);
+	}
+
+	#[test]
+	fn received_transactions_are_not_fetched() {
+		let mut fetcher = TransactionsFetcher::new();
+		let a = H256::from(1u64);
+		fetcher.announced(1, vec![a]);
+		fetcher.received(&hashes(&[a]));
+		assert!(fetcher.request(1, 16, Instant::now()).is_empty());
+	}
+}
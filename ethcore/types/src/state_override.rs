@@ -0,0 +1,41 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! State override types used when simulating calls.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+
+/// Values overriding the state of a single account for the duration of a call.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AccountOverride {
+	/// Replacement balance.
+	pub balance: Option<U256>,
+	/// Replacement nonce.
+	pub nonce: Option<U256>,
+	/// Replacement code.
+	pub code: Option<Bytes>,
+	/// Replacement for the entire storage of the account.
+	pub state: Option<BTreeMap<H256, H256>>,
+	/// Storage slots to change, leaving all other slots intact.
+	pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+/// Per-address overrides applied on top of the state a call is executed against.
+pub type StateOverride = BTreeMap<Address, AccountOverride>;
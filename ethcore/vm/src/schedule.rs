@@ -28,6 +28,10 @@ pub struct Schedule {
 	pub have_revert: bool,
 	/// Does it have a EXTCODEHASH instruction
 	pub have_extcodehash: bool,
+	/// Does it have a CHAINID instruction
+	pub have_chain_id: bool,
+	/// Does it have a SELFBALANCE instruction
+	pub have_selfbalance: bool,
 	/// VM stack limit
 	pub stack_limit: usize,
 	/// Max number of nested calls/creates
@@ -50,6 +54,8 @@ pub struct Schedule {
 	pub sstore_reset_gas: usize,
 	/// Gas refund for `SSTORE` clearing (when `storage!=0`, `new==0`)
 	pub sstore_refund_gas: usize,
+	/// Gas price for `SSTORE` of a dirty or unchanged slot under net gas metering. Falls back to `sload_gas` if `None`.
+	pub sstore_dirty_gas: Option<usize>,
 	/// Gas price for `JUMPDEST` opcode
 	pub jumpdest_gas: usize,
 	/// Gas price for `LOG*`
@@ -119,6 +125,8 @@ pub struct Schedule {
 	pub kill_dust: CleanDustMode,
 	/// Enable EIP-1283 rules
 	pub eip1283: bool,
+	/// Enable EIP-1706 rules (`SSTORE` fails if gas left is not above the call stipend)
+	pub eip1706: bool,
 	/// VM execution does not increase null signed address nonce if this field is true.
 	pub keep_unsigned_nonce: bool,
 	/// Wasm extra schedule settings, if wasm activated
@@ -210,6 +218,8 @@ impl Schedule {
 			have_return_data: false,
 			have_bitwise_shifting: false,
 			have_extcodehash: false,
+			have_chain_id: false,
+			have_selfbalance: false,
 			stack_limit: 1024,
 			max_depth: 1024,
 			tier_step_gas: [0, 2, 3, 5, 8, 10, 20, 0],
@@ -221,6 +231,7 @@ impl Schedule {
 			sstore_set_gas: 20000,
 			sstore_reset_gas: 5000,
 			sstore_refund_gas: 15000,
+			sstore_dirty_gas: None,
 			jumpdest_gas: 1,
 			log_gas: 375,
 			log_data_gas: 8,
@@ -253,6 +264,7 @@ impl Schedule {
 			have_static_call: false,
 			kill_dust: CleanDustMode::Off,
 			eip1283: false,
+			eip1706: false,
 			keep_unsigned_nonce: false,
 			wasm: None,
 		}
@@ -275,6 +287,20 @@ impl Schedule {
 		schedule
 	}
 
+	/// Schedule for the Istanbul fork of the Ethereum main net.
+	pub fn new_istanbul() -> Schedule {
+		let mut schedule = Self::new_constantinople();
+		schedule.have_chain_id = true; // EIP 1344
+		schedule.sload_gas = 800; // EIP 1884
+		schedule.balance_gas = 700; // EIP 1884
+		schedule.extcodehash_gas = 700; // EIP 1884
+		schedule.have_selfbalance = true; // EIP 1884
+		schedule.eip1283 = true; // EIP 2200
+		schedule.eip1706 = true; // EIP 2200
+		schedule.sstore_dirty_gas = Some(800); // EIP 2200
+		schedule
+	}
+
 	fn new(efcd: bool, hdc: bool, tcg: usize) -> Schedule {
 		Schedule {
 			exceptional_failed_code_deposit: efcd,
@@ -284,6 +310,8 @@ impl Schedule {
 			have_return_data: false,
 			have_bitwise_shifting: false,
 			have_extcodehash: false,
+			have_chain_id: false,
+			have_selfbalance: false,
 			stack_limit: 1024,
 			max_depth: 1024,
 			tier_step_gas: [0, 2, 3, 5, 8, 10, 20, 0],
@@ -295,6 +323,7 @@ impl Schedule {
 			sstore_set_gas: 20000,
 			sstore_reset_gas: 5000,
 			sstore_refund_gas: 15000,
+			sstore_dirty_gas: None,
 			jumpdest_gas: 1,
 			log_gas: 375,
 			log_data_gas: 8,
@@ -327,6 +356,7 @@ impl Schedule {
 			have_static_call: false,
 			kill_dust: CleanDustMode::Off,
 			eip1283: false,
+			eip1706: false,
 			keep_unsigned_nonce: false,
 			wasm: None,
 		}
@@ -67,6 +67,7 @@ pub struct FakeExt {
 	pub balances: HashMap<Address, U256>,
 	pub tracing: bool,
 	pub is_static: bool,
+	pub chain_id: u64,
 }
 
 // similar to the normal `finalize` function, but ignoring NeedsReturn.
@@ -98,6 +99,13 @@ impl FakeExt {
 		ext
 	}
 
+	/// New fake externalities with istanbul schedule rules
+	pub fn new_istanbul() -> Self {
+		let mut ext = FakeExt::default();
+		ext.schedule = Schedule::new_istanbul();
+		ext
+	}
+
 	/// Alter fake externalities to allow wasm
 	pub fn with_wasm(mut self) -> Self {
 		self.schedule.wasm = Some(Default::default());
@@ -223,6 +231,10 @@ impl Ext for FakeExt {
 		&self.info
 	}
 
+	fn chain_id(&self) -> u64 {
+		self.chain_id
+	}
+
 	fn depth(&self) -> usize {
 		self.depth
 	}
@@ -19,7 +19,7 @@
 use std::collections::HashMap;
 use std::mem;
 
-use ethereum_types::{U256, H256};
+use ethereum_types::{U256, H256, Address};
 use bytes::ToPretty;
 use ethcore::trace;
 
@@ -168,7 +168,7 @@ impl trace::VMTracer for Informant {
 		});
 	}
 
-	fn prepare_subtrace(&mut self, code: &[u8]) {
+	fn prepare_subtrace(&mut self, _code_address: &Address, code: &[u8]) {
 		let subdepth = self.subdepth;
 		Self::with_informant_in_depth(self, subdepth, |informant: &mut Informant| {
 			let mut vm = Informant::default();
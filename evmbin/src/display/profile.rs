@@ -0,0 +1,336 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! VM profiling output.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ethereum_types::{Address, U256};
+use bytes::ToPretty;
+use ethcore::trace;
+
+use display;
+use info as vm;
+
+/// Default number of hot spots to display.
+pub const DEFAULT_TOP: usize = 20;
+
+/// Cost accumulated by an opcode or a code location.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cost {
+	/// Number of times executed.
+	pub count: u64,
+	/// Gas used, excluding gas used by subcalls.
+	pub gas: U256,
+	/// Wall time, excluding time spent in subcalls.
+	pub time: Duration,
+}
+
+impl Cost {
+	fn add(&mut self, gas: U256, time: Duration) {
+		self.count += 1;
+		self.gas = self.gas.saturating_add(gas);
+		self.time += time;
+	}
+}
+
+/// Execution profile.
+#[derive(Debug, Default)]
+pub struct Profile {
+	/// Cost per opcode.
+	pub opcodes: BTreeMap<u8, Cost>,
+	/// Cost per code address and program counter, along with the opcode at that location.
+	pub locations: HashMap<(Address, usize), (u8, Cost)>,
+	/// Gas used per call stack, as `depth:address` frames separated by `;`.
+	pub stacks: BTreeMap<String, U256>,
+	folded: Option<PathBuf>,
+	top: usize,
+}
+
+impl Profile {
+	/// Writes opcode and hot spot tables.
+	pub fn write_tables<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+		opcodes.sort_by(|a, b| b.1.gas.cmp(&a.1.gas).then(a.0.cmp(b.0)));
+
+		writeln!(out, "Opcodes:")?;
+		writeln!(out, "{:<16} {:>10} {:>14} {:>16}", "OPCODE", "COUNT", "GAS", "TIME")?;
+		for (opcode, cost) in opcodes {
+			writeln!(out, "{:<16} {:>10} {:>14} {:>16}", opcode_name(*opcode), cost.count, cost.gas.to_string(), display::format_time(&cost.time))?;
+		}
+
+		let mut locations: Vec<_> = self.locations.iter().collect();
+		locations.sort_by(|a, b| (b.1).1.gas.cmp(&(a.1).1.gas).then(a.0.cmp(b.0)));
+
+		writeln!(out, "")?;
+		writeln!(out, "Hot spots:")?;
+		writeln!(out, "{:<42} {:>6} {:<16} {:>10} {:>14} {:>16}", "ADDRESS", "PC", "OPCODE", "COUNT", "GAS", "TIME")?;
+		for (&(ref address, pc), &(opcode, ref cost)) in locations.into_iter().take(self.top) {
+			writeln!(
+				out, "{:<42} {:>6} {:<16} {:>10} {:>14} {:>16}",
+				format!("0x{:x}", address), pc, opcode_name(opcode), cost.count, cost.gas.to_string(), display::format_time(&cost.time),
+			)?;
+		}
+
+		Ok(())
+	}
+
+	/// Writes call stacks in the folded format understood by flamegraph tools, weighted by gas used.
+	pub fn write_folded<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		for (stack, gas) in self.stacks.iter().filter(|&(_, gas)| !gas.is_zero()) {
+			writeln!(out, "{} {}", stack, gas)?;
+		}
+
+		Ok(())
+	}
+}
+
+fn opcode_name(opcode: u8) -> &'static str {
+	::evm::Instruction::from_u8(opcode).map(|i| i.info().name).unwrap_or("INVALID")
+}
+
+struct Pending {
+	pc: usize,
+	instruction: u8,
+	gas: U256,
+	started: Instant,
+}
+
+struct Frame {
+	address: Address,
+	stack: String,
+	pending: Option<Pending>,
+	// gas and time spent in subcalls of the pending instruction.
+	child_gas: U256,
+	child_time: Duration,
+	// gas and time spent in this frame, including subcalls.
+	total_gas: U256,
+	total_time: Duration,
+}
+
+/// Profiling informant.
+pub struct Informant {
+	profile: Profile,
+	frames: Vec<Frame>,
+}
+
+impl Default for Informant {
+	fn default() -> Self {
+		Self::new(None, DEFAULT_TOP)
+	}
+}
+
+impl Informant {
+	/// Creates a new profiling informant. Folded call stacks are appended to `folded`, if given,
+	/// and at most `top` hot spots are displayed.
+	pub fn new(folded: Option<PathBuf>, top: usize) -> Self {
+		Informant {
+			profile: Profile {
+				folded,
+				top,
+				..Default::default()
+			},
+			frames: Vec::new(),
+		}
+	}
+
+	// record the pending instruction of the current frame, which used `gas` in `time` including subcalls.
+	fn record(&mut self, gas: U256, time: Duration) {
+		let frame = match self.frames.last_mut() {
+			Some(frame) => frame,
+			None => return,
+		};
+		let pending = match frame.pending.take() {
+			Some(pending) => pending,
+			None => return,
+		};
+
+		let self_gas = gas.saturating_sub(frame.child_gas);
+		let self_time = time.checked_sub(frame.child_time).unwrap_or_else(|| Duration::from_secs(0));
+		frame.child_gas = U256::zero();
+		frame.child_time = Duration::from_secs(0);
+		frame.total_gas = frame.total_gas.saturating_add(gas);
+		frame.total_time += time;
+
+		self.profile.opcodes.entry(pending.instruction).or_insert_with(Default::default).add(self_gas, self_time);
+		self.profile.locations.entry((frame.address, pending.pc))
+			.or_insert_with(|| (pending.instruction, Default::default()))
+			.1.add(self_gas, self_time);
+		let stack_gas = self.profile.stacks.entry(frame.stack.clone()).or_insert_with(U256::zero);
+		*stack_gas = stack_gas.saturating_add(self_gas);
+	}
+}
+
+impl vm::Informant for Informant {
+	fn before_test(&mut self, name: &str, action: &str) {
+		println!("Test: {} ({})", name, action);
+	}
+
+	fn finish(result: vm::RunResult<Self::Output>) {
+		let profile = match result {
+			Ok(success) => {
+				println!("Output: 0x{}", success.output.to_hex());
+				println!("Gas used: {:x}", success.gas_used);
+				println!("Time: {}", display::format_time(&success.time));
+				success.traces
+			},
+			Err(failure) => {
+				println!("Error: {}", failure.error);
+				println!("Time: {}", display::format_time(&failure.time));
+				failure.traces
+			},
+		};
+
+		let profile = match profile {
+			Some(profile) => profile,
+			None => return,
+		};
+
+		println!("");
+		profile.write_tables(&mut io::stdout()).expect("The sink must be writeable.");
+
+		if let Some(ref path) = profile.folded {
+			let written = OpenOptions::new().create(true).append(true).open(path)
+				.and_then(|mut file| profile.write_folded(&mut file));
+			if let Err(e) = written {
+				println!("Unable to write folded stacks to {}: {}", path.display(), e);
+			}
+		}
+	}
+}
+
+impl trace::VMTracer for Informant {
+	type Output = Profile;
+
+	fn trace_next_instruction(&mut self, pc: usize, instruction: u8, current_gas: U256) -> bool {
+		if let Some(frame) = self.frames.last_mut() {
+			frame.pending = Some(Pending {
+				pc,
+				instruction,
+				gas: current_gas,
+				started: Instant::now(),
+			});
+		}
+		true
+	}
+
+	fn trace_executed(&mut self, gas_left: U256, _stack_push: &[U256], _mem: &[u8]) {
+		let used = self.frames.last().and_then(|frame| frame.pending.as_ref())
+			.map(|pending| (pending.gas.saturating_sub(gas_left), pending.started.elapsed()));
+
+		if let Some((gas, time)) = used {
+			self.record(gas, time);
+		}
+	}
+
+	fn prepare_subtrace(&mut self, code_address: &Address, _code: &[u8]) {
+		let depth = self.frames.len() + 1;
+		let name = format!("{}:0x{:x}", depth, code_address);
+		let stack = match self.frames.last() {
+			Some(parent) => format!("{};{}", parent.stack, name),
+			None => name,
+		};
+
+		self.frames.push(Frame {
+			address: *code_address,
+			stack,
+			pending: None,
+			child_gas: U256::zero(),
+			child_time: Duration::from_secs(0),
+			total_gas: U256::zero(),
+			total_time: Duration::from_secs(0),
+		});
+	}
+
+	fn done_subtrace(&mut self) {
+		// an instruction which did not finish executing failed and consumed all the remaining gas.
+		let failed = self.frames.last().and_then(|frame| frame.pending.as_ref())
+			.map(|pending| (pending.gas, pending.started.elapsed()));
+
+		if let Some((gas, time)) = failed {
+			self.record(gas, time);
+		}
+
+		if let Some(frame) = self.frames.pop() {
+			if let Some(parent) = self.frames.last_mut() {
+				parent.child_gas = parent.child_gas.saturating_add(frame.total_gas);
+				parent.child_time += frame.total_time;
+			}
+		}
+	}
+
+	fn drain(self) -> Option<Profile> {
+		Some(self.profile)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use info::tests::run_test;
+
+	fn assert_profile<F: FnOnce(&Profile)>(code: &str, f: F) {
+		run_test(Informant::default(), |profile: Option<Profile>, _| f(&profile.unwrap()), code, 0xffff, "");
+	}
+
+	#[test]
+	fn should_aggregate_costs_per_opcode_and_location() {
+		// PUSH1 0x01 PUSH1 0x00 SSTORE PUSH1 0x01 PUSH1 0x00 SSTORE PUSH1 0x00
+		assert_profile("600160005560016000556000", |profile| {
+			let push1 = &profile.opcodes[&0x60];
+			assert_eq!(push1.count, 5);
+			assert_eq!(push1.gas, 15.into());
+
+			let sstore = &profile.opcodes[&0x55];
+			assert_eq!(sstore.count, 2);
+			assert_eq!(sstore.gas, (20000 + 5000).into());
+
+			let (opcode, ref cost) = profile.locations[&(Address::default(), 4)];
+			assert_eq!(opcode, 0x55);
+			assert_eq!(cost.count, 1);
+			assert_eq!(cost.gas, 20000.into());
+
+			assert_eq!(profile.stacks.len(), 1);
+			assert_eq!(profile.stacks["1:0x0000000000000000000000000000000000000000"], (15 + 25000).into());
+		});
+	}
+
+	#[test]
+	fn should_charge_remaining_gas_to_failing_instruction() {
+		// PUSH1 0x00 <undefined>
+		assert_profile("6000ef", |profile| {
+			let undefined = &profile.opcodes[&0xef];
+			assert_eq!(undefined.count, 1);
+			assert_eq!(undefined.gas, (0xffff - 3).into());
+		});
+	}
+
+	#[test]
+	fn should_write_folded_stacks() {
+		let mut profile = Profile::default();
+		profile.stacks.insert("1:0x01".into(), 100.into());
+		profile.stacks.insert("1:0x01;2:0x02".into(), 30.into());
+		profile.stacks.insert("1:0x01;2:0x03".into(), 0.into());
+
+		let mut out = Vec::new();
+		profile.write_folded(&mut out).unwrap();
+		assert_eq!(String::from_utf8(out).unwrap(), "1:0x01 100\n1:0x01;2:0x02 30\n");
+	}
+}
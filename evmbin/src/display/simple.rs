@@ -16,6 +16,7 @@
 
 //! Simple VM output.
 
+use ethereum_types::Address;
 use ethcore::trace;
 use bytes::ToPretty;
 
@@ -49,7 +50,7 @@ impl vm::Informant for Informant {
 impl trace::VMTracer for Informant {
 	type Output = ();
 
-	fn prepare_subtrace(&mut self, _code: &[u8]) { Default::default() }
+	fn prepare_subtrace(&mut self, _code_address: &Address, _code: &[u8]) { Default::default() }
 	fn done_subtrace(&mut self) {}
 	fn drain(self) -> Option<()> { None }
 }
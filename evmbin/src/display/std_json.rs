@@ -19,7 +19,7 @@
 use std::collections::HashMap;
 use std::io;
 
-use ethereum_types::{H256, U256};
+use ethereum_types::{H256, U256, Address};
 use bytes::ToPretty;
 use ethcore::trace;
 
@@ -180,7 +180,7 @@ impl<Trace: Writer, Out: Writer> trace::VMTracer for Informant<Trace, Out> {
 		});
 	}
 
-	fn prepare_subtrace(&mut self, code: &[u8]) {
+	fn prepare_subtrace(&mut self, _code_address: &Address, code: &[u8]) {
 		let subdepth = self.subdepth;
 		Self::with_informant_in_depth(self, subdepth, |informant: &mut Informant<Trace, Out>| {
 			let mut vm = Informant::new(informant.trace_sink.clone(), informant.out_sink.clone());
@@ -65,6 +65,8 @@ Usage:
     parity-evm state-test <file> [--json --std-json --only NAME --chain CHAIN]
     parity-evm stats [options]
     parity-evm stats-jsontests-vm <file>
+    parity-evm profile state-test <file> [--only NAME --chain CHAIN --folded FILE --top N]
+    parity-evm profile [--folded FILE --top N] [options]
     parity-evm [options]
     parity-evm [-h | --help]
 
@@ -73,6 +75,8 @@ Commands:
     stats              Execute EVM runtime code and return the statistics.
     stats-jsontests-vm Execute standard json-tests format VMTests and return
                        timing statistics in tsv format.
+    profile            Execute EVM runtime code or a state test and display
+                       gas and time spent per opcode and per code location.
 
 Transaction options:
     --code CODE        Contract code as hex (without 0x).
@@ -86,6 +90,11 @@ State test options:
     --only NAME        Runs only a single test matching the name.
     --chain CHAIN      Run only tests from specific chain.
 
+Profile options:
+    --folded FILE      Write call stacks weighted by gas used to FILE, in the
+                       folded format understood by flamegraph tools.
+    --top N            Number of hot spots to display [default: 20].
+
 General options:
     --json             Display verbose results in JSON.
     --std-json         Display results in standardized JSON format.
@@ -99,10 +108,21 @@ fn main() {
 
 	let args: Args = Docopt::new(USAGE).and_then(|d| d.deserialize()).unwrap_or_else(|e| e.exit());
 
+	if args.cmd_profile {
+		if let Some(ref folded) = args.flag_folded {
+			if let Err(err) = fs::File::create(folded) {
+				die(format!("Unable to create: {}: {}", folded, err));
+			}
+		}
+	}
+
 	if args.cmd_state_test {
 		run_state_test(args)
 	} else if args.cmd_stats_jsontests_vm {
 		run_stats_jsontests_vm(args)
+	} else if args.cmd_profile {
+		let informant = args.profile_informant();
+		run_call(args, informant)
 	} else if args.flag_json {
 		run_call(args, display::json::Informant::default())
 	} else if args.flag_std_json {
@@ -149,7 +169,7 @@ fn run_stats_jsontests_vm(args: Args) {
 fn run_state_test(args: Args) {
 	use ethjson::state::test::Test;
 
-	let file = args.arg_file.expect("FILE is required");
+	let file = args.arg_file.as_ref().expect("FILE is required");
 	let mut file = match fs::File::open(&file) {
 		Err(err) => die(format!("Unable to open: {:?}: {}", file, err)),
 		Ok(file) => file,
@@ -158,8 +178,8 @@ fn run_state_test(args: Args) {
 		Err(err) => die(format!("Unable to load the test file: {}", err)),
 		Ok(test) => test,
 	};
-	let only_test = args.flag_only.map(|s| s.to_lowercase());
-	let only_chain = args.flag_chain.map(|s| s.to_lowercase());
+	let only_test = args.flag_only.as_ref().map(|s| s.to_lowercase());
+	let only_chain = args.flag_chain.as_ref().map(|s| s.to_lowercase());
 
 	for (name, test) in state_test {
 		if let Some(false) = only_test.as_ref().map(|only_test| &name.to_lowercase() == only_test) {
@@ -179,7 +199,10 @@ fn run_state_test(args: Args) {
 				let post_root = state.hash.into();
 				let transaction = multitransaction.select(&state.indexes).into();
 
-				if args.flag_json {
+				if args.cmd_profile {
+					let i = args.profile_informant();
+					info::run_transaction(&name, idx, &spec, &pre, post_root, &env_info, transaction, i)
+				} else if args.flag_json {
 					let i = display::json::Informant::default();
 					info::run_transaction(&name, idx, &spec, &pre, post_root, &env_info, transaction, i)
 				} else if args.flag_std_json {
@@ -227,6 +250,7 @@ struct Args {
 	cmd_stats: bool,
 	cmd_state_test: bool,
 	cmd_stats_jsontests_vm: bool,
+	cmd_profile: bool,
 	arg_file: Option<PathBuf>,
 	flag_only: Option<String>,
 	flag_from: Option<String>,
@@ -238,6 +262,8 @@ struct Args {
 	flag_chain: Option<String>,
 	flag_json: bool,
 	flag_std_json: bool,
+	flag_folded: Option<String>,
+	flag_top: usize,
 }
 
 impl Args {
@@ -283,6 +309,10 @@ impl Args {
 		}
 	}
 
+	pub fn profile_informant(&self) -> display::profile::Informant {
+		display::profile::Informant::new(self.flag_folded.as_ref().map(PathBuf::from), self.flag_top)
+	}
+
 	pub fn spec(&self) -> Result<spec::Spec, String> {
 		Ok(match self.flag_chain {
 			Some(ref filename) =>  {
@@ -363,4 +393,39 @@ mod tests {
 		assert_eq!(args.flag_chain, Some("homestead".to_owned()));
 		assert_eq!(args.flag_only, Some("add11".to_owned()));
 	}
+
+	#[test]
+	fn should_parse_profile_command() {
+		let args = run(&[
+			"parity-evm",
+			"profile",
+			"--folded", "./stacks.folded",
+			"--top", "5",
+			"--code", "05",
+		]);
+
+		assert_eq!(args.cmd_profile, true);
+		assert_eq!(args.cmd_state_test, false);
+		assert_eq!(args.flag_folded, Some("./stacks.folded".to_owned()));
+		assert_eq!(args.flag_top, 5);
+		assert_eq!(args.code(), Ok(Some(vec![05])));
+	}
+
+	#[test]
+	fn should_parse_profile_state_test_command() {
+		let args = run(&[
+			"parity-evm",
+			"profile",
+			"state-test",
+			"./file.json",
+			"--only=add11",
+		]);
+
+		assert_eq!(args.cmd_profile, true);
+		assert_eq!(args.cmd_state_test, true);
+		assert!(args.arg_file.is_some());
+		assert_eq!(args.flag_only, Some("add11".to_owned()));
+		assert_eq!(args.flag_folded, None);
+		assert_eq!(args.flag_top, 20);
+	}
 }
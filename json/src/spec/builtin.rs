@@ -43,6 +43,13 @@ pub struct AltBn128Pairing {
 	pub pair: usize,
 }
 
+/// Pricing for the BLAKE2b compression function.
+#[derive(Debug, PartialEq, Deserialize, Clone)]
+pub struct Blake2F {
+	/// Price per round of the compression function.
+	pub gas_per_round: u64,
+}
+
 /// Pricing variants.
 #[derive(Debug, PartialEq, Deserialize, Clone)]
 pub enum Pricing {
@@ -55,6 +62,9 @@ pub enum Pricing {
 	/// Pricing for alt_bn128_pairing exponentiation.
 	#[serde(rename="alt_bn128_pairing")]
 	AltBn128Pairing(AltBn128Pairing),
+	/// Pricing for the BLAKE2b compression function.
+	#[serde(rename="blake2_f")]
+	Blake2F(Blake2F),
 }
 
 /// Spec builtin.
@@ -71,7 +81,7 @@ pub struct Builtin {
 #[cfg(test)]
 mod tests {
 	use serde_json;
-	use spec::builtin::{Builtin, Pricing, Linear, Modexp};
+	use spec::builtin::{Builtin, Pricing, Linear, Modexp, Blake2F};
 	use uint::Uint;
 
 	#[test]
@@ -99,4 +109,18 @@ mod tests {
 		assert_eq!(deserialized.pricing, Pricing::Modexp(Modexp { divisor: 5 }));
 		assert_eq!(deserialized.activate_at, Some(Uint(100000.into())));
 	}
+
+	#[test]
+	fn blake2_f_deserialization() {
+		let s = r#"{
+			"name": "blake2_f",
+			"activate_at": "0xffffff",
+			"pricing": { "blake2_f": { "gas_per_round": 123 } }
+		}"#;
+
+		let deserialized: Builtin = serde_json::from_str(s).unwrap();
+		assert_eq!(deserialized.name, "blake2_f");
+		assert_eq!(deserialized.pricing, Pricing::Blake2F(Blake2F { gas_per_round: 123 }));
+		assert_eq!(deserialized.activate_at, Some(Uint(0xffffff.into())));
+	}
 }
@@ -114,6 +114,15 @@ pub struct Params {
 	#[serde(rename="eip1014Transition")]
 	pub eip1014_transition: Option<Uint>,
 	/// See `CommonParams` docs.
+	#[serde(rename="eip1344Transition")]
+	pub eip1344_transition: Option<Uint>,
+	/// See `CommonParams` docs.
+	#[serde(rename="eip1884Transition")]
+	pub eip1884_transition: Option<Uint>,
+	/// See `CommonParams` docs.
+	#[serde(rename="eip2200Transition")]
+	pub eip2200_transition: Option<Uint>,
+	/// See `CommonParams` docs.
 	#[serde(rename="dustProtectionTransition")]
 	pub dust_protection_transition: Option<Uint>,
 	/// See `CommonParams` docs.
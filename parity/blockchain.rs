@@ -37,10 +37,15 @@ use informant::{Informant, FullNodeInformantData, MillisecondDuration};
 use params::{SpecType, Pruning, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 use helpers::{to_client_config, execute_upgrades};
 use dir::Directories;
+use journaldb::Algorithm;
+use kvdb_logdb;
 use user_defaults::UserDefaults;
 use ethcore_private_tx;
 use db;
 
+/// Number of blocks indexed between progress reports of `parity db index-logs`.
+const INDEX_LOGS_BATCH_BLOCKS: u64 = 10_000;
+
 #[derive(Debug, PartialEq)]
 pub enum DataFormat {
 	Hex,
@@ -68,6 +73,9 @@ impl FromStr for DataFormat {
 #[derive(Debug, PartialEq)]
 pub enum BlockchainCmd {
 	Kill(KillBlockchain),
+	IndexLogs(IndexLogs),
+	MigrateBackend(MigrateBackend),
+	ConvertPruning(ConvertPruning),
 	Import(ImportBlockchain),
 	Export(ExportBlockchain),
 	ExportState(ExportState),
@@ -80,6 +88,44 @@ pub struct KillBlockchain {
 	pub pruning: Pruning,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct IndexLogs {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub pruning_history: u64,
+	pub pruning_memory: usize,
+	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
+	pub tracing: Switch,
+	pub fat_db: Switch,
+	pub rebuild: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MigrateBackend {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub to: db::Backend,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConvertPruning {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub pruning_history: u64,
+	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
+	pub fat_db: Switch,
+	pub to: Algorithm,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ImportBlockchain {
 	pub spec: SpecType,
@@ -91,6 +137,7 @@ pub struct ImportBlockchain {
 	pub pruning_history: u64,
 	pub pruning_memory: usize,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 	pub tracing: Switch,
 	pub fat_db: Switch,
 	pub vm_type: VMType,
@@ -111,6 +158,7 @@ pub struct ExportBlockchain {
 	pub pruning_history: u64,
 	pub pruning_memory: usize,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 	pub fat_db: Switch,
 	pub tracing: Switch,
 	pub from_block: BlockId,
@@ -129,6 +177,7 @@ pub struct ExportState {
 	pub pruning_history: u64,
 	pub pruning_memory: usize,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 	pub fat_db: Switch,
 	pub tracing: Switch,
 	pub at: BlockId,
@@ -141,6 +190,9 @@ pub struct ExportState {
 pub fn execute(cmd: BlockchainCmd) -> Result<(), String> {
 	match cmd {
 		BlockchainCmd::Kill(kill_cmd) => kill_db(kill_cmd),
+		BlockchainCmd::IndexLogs(index_cmd) => execute_index_logs(index_cmd),
+		BlockchainCmd::MigrateBackend(migrate_cmd) => execute_migrate_backend(migrate_cmd),
+		BlockchainCmd::ConvertPruning(convert_cmd) => execute_convert_pruning(convert_cmd),
 		BlockchainCmd::Import(import_cmd) => {
 			if import_cmd.light {
 				execute_import_light(import_cmd)
@@ -205,7 +257,8 @@ fn execute_import_light(cmd: ImportBlockchain) -> Result<(), String> {
 	// initialize database.
 	let db = db::open_db(&client_path.to_str().expect("DB path could not be converted to string."),
 						 &cmd.cache_config,
-						 &cmd.compaction).map_err(|e| format!("Failed to open database: {:?}", e))?;
+						 &cmd.compaction,
+						 cmd.db_backend).map_err(|e| format!("Failed to open database: {:?}", e))?;
 
 	// TODO: could epoch signals be avilable at the end of the file?
 	let fetch = ::light::client::fetch::unavailable();
@@ -358,7 +411,7 @@ fn execute_import(cmd: ImportBlockchain) -> Result<(), String> {
 
 	client_config.queue.verifier_settings = cmd.verifier_settings;
 
-	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config);
+	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config, cmd.db_backend);
 	let client_db = restoration_db_handler.open(&client_path)
 		.map_err(|e| format!("Failed to open database {:?}", e))?;
 
@@ -409,6 +462,7 @@ fn execute_import(cmd: ImportBlockchain) -> Result<(), String> {
 			client: client.clone(),
 			sync: None,
 			net: None,
+			private_tx: None,
 		},
 		None,
 		None,
@@ -491,8 +545,10 @@ fn start_client(
 	tracing: Switch,
 	fat_db: Switch,
 	compaction: DatabaseCompactionProfile,
+	db_backend: db::Backend,
 	cache_config: CacheConfig,
 	require_fat_db: bool,
+	log_index: bool,
 ) -> Result<ClientService, String> {
 
 	// load spec file
@@ -533,7 +589,7 @@ fn start_client(
 	dirs.create_dirs(false, false)?;
 
 	// prepare client config
-	let client_config = to_client_config(
+	let mut client_config = to_client_config(
 		&cache_config,
 		spec.name.to_lowercase(),
 		Mode::Active,
@@ -547,8 +603,9 @@ fn start_client(
 		pruning_memory,
 		true,
 	);
+	client_config.blockchain.log_index = log_index;
 
-	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config);
+	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config, db_backend);
 	let client_db = restoration_db_handler.open(&client_path)
 		.map_err(|e| format!("Failed to open database {:?}", e))?;
 
@@ -581,8 +638,10 @@ fn execute_export(cmd: ExportBlockchain) -> Result<(), String> {
 		cmd.tracing,
 		cmd.fat_db,
 		cmd.compaction,
+		cmd.db_backend,
 		cmd.cache_config,
 		false,
+		false,
 	)?;
 	let format = cmd.format.unwrap_or_default();
 
@@ -625,8 +684,10 @@ fn execute_export_state(cmd: ExportState) -> Result<(), String> {
 		cmd.tracing,
 		cmd.fat_db,
 		cmd.compaction,
+		cmd.db_backend,
 		cmd.cache_config,
-		true
+		true,
+		false,
 	)?;
 
 	let client = service.client();
@@ -701,6 +762,111 @@ fn execute_export_state(cmd: ExportState) -> Result<(), String> {
 	Ok(())
 }
 
+fn execute_index_logs(cmd: IndexLogs) -> Result<(), String> {
+	let service = start_client(
+		cmd.dirs,
+		cmd.spec,
+		cmd.pruning,
+		cmd.pruning_history,
+		cmd.pruning_memory,
+		cmd.tracing,
+		cmd.fat_db,
+		cmd.compaction,
+		cmd.db_backend,
+		cmd.cache_config,
+		false,
+		true,
+	)?;
+
+	let client = service.client();
+
+	if cmd.rebuild {
+		info!("Removing existing log index");
+		client.clear_log_index();
+	}
+
+	loop {
+		match client.backfill_log_index(INDEX_LOGS_BATCH_BLOCKS) {
+			Some(0) => break,
+			Some(remaining) => info!("Indexing logs, {} blocks remaining", remaining),
+			None => return Err("Receipts of older blocks are not available. Blocks below a restored snapshot can only be indexed once ancient blocks are synced.".into()),
+		}
+	}
+
+	info!("Log index completed.");
+	Ok(())
+}
+
+fn execute_migrate_backend(cmd: MigrateBackend) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir);
+	let user_defaults = UserDefaults::load(&db_dirs.user_defaults_path())?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+
+	// the copy is made at the current version, so upgrade first.
+	execute_upgrades(&cmd.dirs.base, &db_dirs, algorithm, &cmd.compaction)?;
+
+	db::migrate_backend(&db_dirs.client_path(algorithm), &cmd.cache_config, &cmd.compaction, cmd.to)?;
+	info!("Database migrated to {}.", cmd.to);
+	Ok(())
+}
+
+fn execute_convert_pruning(cmd: ConvertPruning) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir.clone());
+	let user_defaults_path = db_dirs.user_defaults_path();
+	let mut user_defaults = UserDefaults::load(&user_defaults_path)?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+
+	match (algorithm, cmd.to) {
+		(from, to) if from == to => return Err(format!("Database already uses {} pruning.", to)),
+		(_, Algorithm::Archive) => return Err("Pruned state can't be recovered, so a database can't be converted to archive. \
+			Use `parity restore --pruning=archive <FILE>` to start an archive database from a snapshot instead.".into()),
+		(Algorithm::Archive, Algorithm::OverlayRecent) => {},
+		(from, to) => return Err(format!("Conversion from {} to {} pruning is not supported. Only archive databases can be converted, to fast.", from, to)),
+	}
+
+	if fatdb_switch_to_bool(cmd.fat_db, &user_defaults, algorithm)? {
+		return Err("Databases with --fat-db on can't be converted, as pruning would remove the preimages of state keys.".into());
+	}
+
+	let target_path = db_dirs.db_path(cmd.to);
+	if target_path.exists() {
+		return Err(format!("A database with {} pruning already exists at {}. Remove it with `parity db kill --pruning={}` first.", cmd.to, target_path.display(), cmd.to));
+	}
+
+	execute_upgrades(&cmd.dirs.base, &db_dirs, algorithm, &cmd.compaction)?;
+
+	// progress is kept next to the database, so that an interrupted conversion can be resumed.
+	let progress_path = db_dirs.db_path(algorithm).join("pruning-conversion");
+	{
+		let client_path = db_dirs.client_path(algorithm);
+		let db = db::open_db(&client_path.to_str().expect("DB path could not be converted to string."),
+			&cmd.cache_config, &cmd.compaction, cmd.db_backend)
+			.map_err(|e| format!("Failed to open database: {:?}", e))?;
+		let progress = kvdb_logdb::Database::open(
+			&kvdb_logdb::DatabaseConfig::with_columns(::ethcore::pruning::NUM_PROGRESS_COLUMNS),
+			&progress_path.to_string_lossy(),
+		).map_err(|e| format!("Failed to open conversion progress: {:?}", e))?;
+
+		::ethcore::pruning::archive_to_overlay_recent(db, &progress, &spec.genesis_block(), cmd.pruning_history)
+			.map_err(|e| format!("Database conversion failed: {}. Run the command again to resume it.", e))?;
+	}
+
+	fs::remove_dir_all(&progress_path)
+		.map_err(|e| format!("Failed to remove conversion progress at {}: {}", progress_path.display(), e))?;
+
+	fs::rename(db_dirs.db_path(algorithm), &target_path)
+		.map_err(|e| format!("Failed to move converted database to {}: {}", target_path.display(), e))?;
+
+	user_defaults.pruning = cmd.to;
+	user_defaults.save(&user_defaults_path)?;
+	info!("Database converted to {} pruning.", cmd.to);
+	Ok(())
+}
+
 pub fn kill_db(cmd: KillBlockchain) -> Result<(), String> {
 	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
 	let genesis_hash = spec.genesis_header().hash();
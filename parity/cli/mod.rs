@@ -178,6 +178,15 @@ usage! {
 		{
 			"Make a snapshot of the database of the given --chain (default: mainnet)",
 
+			CMD cmd_snapshot_verify
+			{
+				"Verify a snapshot file of the given --chain (default: mainnet) without touching the database",
+
+				ARG arg_snapshot_verify_file: (Option<String>) = None,
+				"<FILE>",
+				"Path to the snapshot file to verify",
+			}
+
 			ARG arg_snapshot_at: (String) = "latest",
 			"--at=[BLOCK]",
 			"Take a snapshot at the given block, which may be an index, hash, or latest. Note that taking snapshots at non-recent blocks will only work with --pruning archive",
@@ -217,6 +226,30 @@ usage! {
 			CMD cmd_db_kill {
 				"Clean the database of the given --chain (default: mainnet)",
 			}
+
+			CMD cmd_db_index_logs {
+				"Index logs of the given --chain (default: mainnet) which were imported before --log-index was enabled",
+
+				FLAG flag_db_index_logs_rebuild: (bool) = false,
+				"--rebuild",
+				"Remove the existing log index and index logs of all blocks again.",
+			}
+
+			CMD cmd_db_migrate {
+				"Copy the database of the given --chain (default: mainnet) to another key-value backend",
+
+				ARG arg_db_migrate_to: (Option<String>) = None,
+				"--to=<BACKEND>",
+				"Backend to copy the database to. BACKEND may be one of: rocksdb, logdb.",
+			}
+
+			CMD cmd_db_convert_pruning {
+				"Convert the database of the given --chain (default: mainnet) to another pruning method without resyncing",
+
+				ARG arg_db_convert_pruning_to: (Option<String>) = None,
+				"--to=<METHOD>",
+				"Pruning method to convert the database to. METHOD may be one of: fast, archive. Only archive databases can be converted, to fast, keeping the state of the latest --pruning-history blocks. An interrupted conversion is resumed by running the command again.",
+			}
 		}
 
 		CMD cmd_export_hardcoded_sync
@@ -383,6 +416,10 @@ usage! {
 			"--no-warp",
 			"Disable syncing from the snapshot over the network.",
 
+			FLAG flag_state_sync: (bool) = false, or |c: &Config| c.network.as_ref()?.state_sync.clone(),
+			"--state-sync",
+			"Sync the state of a recent block by downloading its trie nodes from peers. With warp sync enabled it is used when no snapshots are found.",
+
 			FLAG flag_no_discovery: (bool) = false, or |c: &Config| c.network.as_ref()?.discovery.map(|d| !d).clone(),
 			"--no-discovery",
 			"Disable new peer discovery.",
@@ -435,13 +472,33 @@ usage! {
 			"--max-pending-peers=[NUM]",
 			"Allow up to NUM pending connections.",
 
+			ARG arg_max_upload_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.max_upload_rate.clone(),
+			"--max-upload-rate=[KBPS]",
+			"Limit the total upload rate to all peers to KBPS kilobytes per second.",
+
+			ARG arg_max_download_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.max_download_rate.clone(),
+			"--max-download-rate=[KBPS]",
+			"Limit the total download rate from all peers to KBPS kilobytes per second.",
+
+			ARG arg_peer_max_upload_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.peer_max_upload_rate.clone(),
+			"--peer-max-upload-rate=[KBPS]",
+			"Limit the upload rate to any single peer to KBPS kilobytes per second.",
+
+			ARG arg_peer_max_download_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.peer_max_download_rate.clone(),
+			"--peer-max-download-rate=[KBPS]",
+			"Limit the download rate from any single peer to KBPS kilobytes per second.",
+
 			ARG arg_network_id: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.id.clone(),
 			"--network-id=[INDEX]",
 			"Override the network identifier from the chain we are on.",
 
 			ARG arg_bootnodes: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.bootnodes.as_ref().map(|vec| vec.join(",")),
 			"--bootnodes=[NODES]",
-			"Override the bootnodes from our chain. NODES should be comma-delimited enodes.",
+			"Override the bootnodes from our chain. NODES should be comma-delimited enodes or enrtree:// DNS node list URLs.",
+
+			ARG arg_dns_zone_file: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.dns_zone_file.clone(),
+			"--dns-zone-file=[FILE]",
+			"Provide a zone file with the TXT records of the enrtree:// node lists given as bootnodes. The file is re-read whenever the node lists are refreshed.",
 
 			ARG arg_node_key: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.node_key.clone(),
 			"--node-key=[KEY]",
@@ -451,6 +508,14 @@ usage! {
 			"--reserved-peers=[FILE]",
 			"Provide a file containing enodes, one per line. These nodes will always have a reserved slot on top of the normal maximum peers.",
 
+			ARG arg_node_record: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.node_record.as_ref().map(|vec| vec.join(",")),
+			"--node-record=[ENTRIES]",
+			"Advertise custom entries in the node record (ENR) of this node. ENTRIES should be comma-delimited KEY=VALUE pairs, values starting with 0x are taken as hex-encoded bytes.",
+
+			ARG arg_node_record_filter: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.node_record_filter.as_ref().map(|vec| vec.join(",")),
+			"--node-record-filter=[ENTRIES]",
+			"Only connect to non-reserved nodes whose node record (ENR) contains all of the given entries. ENTRIES should be comma-delimited KEY=VALUE pairs, values starting with 0x are taken as hex-encoded bytes.",
+
 			CHECK |args: &Args| {
 				if let (Some(max_peers), Some(min_peers)) = (args.arg_max_peers, args.arg_min_peers) {
 					if min_peers > max_peers {
@@ -820,6 +885,14 @@ usage! {
 			"--tracing=[BOOL]",
 			"Indicates if full transaction tracing should be enabled. Works only if client had been fully synced with tracing enabled. BOOL may be one of auto, on, off. auto uses last used value of this option (off if it does not exist).", // footprint option
 
+			FLAG flag_tracing_address_index: (bool) = false, or |c: &Config| c.footprint.as_ref()?.tracing_address_index.clone(),
+			"--tracing-address-index",
+			"Index traces by sender and recipient address to speed up trace_filter queries. Blocks imported before the index was enabled are indexed in the background. Requires tracing to be enabled.",
+
+			FLAG flag_log_index: (bool) = false, or |c: &Config| c.footprint.as_ref()?.log_index.clone(),
+			"--log-index",
+			"Index logs by address and topic to speed up selective eth_getLogs queries. Only blocks imported while the index is enabled are indexed; use `parity db index-logs` to index older blocks.",
+
 			ARG arg_pruning: (String) = "auto", or |c: &Config| c.footprint.as_ref()?.pruning.clone(),
 			"--pruning=[METHOD]",
 			"Configure pruning of the state/storage trie. METHOD may be one of auto, archive, fast: archive - keep all state trie data. No pruning. fast - maintain journal overlay. Fast but 50MB used. auto - use the method most recently synced or default to fast if none synced.",
@@ -852,10 +925,18 @@ usage! {
 			"--db-compaction=[TYPE]",
 			"Database compaction type. TYPE may be one of: ssd - suitable for SSDs and fast HDDs; hdd - suitable for slow HDDs; auto - determine automatically.",
 
+			ARG arg_db_backend: (String) = "auto", or |c: &Config| c.footprint.as_ref()?.db_backend.clone(),
+			"--db-backend=[BACKEND]",
+			"Key-value store used for newly created databases. BACKEND may be one of: rocksdb; logdb - log-structured store written in pure Rust; auto - rocksdb if this build supports it, logdb otherwise. Existing databases are opened with the backend they were created with, use `parity db migrate` to convert them.",
+
 			ARG arg_fat_db: (String) = "auto", or |c: &Config| c.footprint.as_ref()?.fat_db.clone(),
 			"--fat-db=[BOOL]",
 			"Build appropriate information to allow enumeration of all accounts and storage keys. Doubles the size of the state database. BOOL may be one of on, off or auto.",
 
+			FLAG flag_state_history: (bool) = false, or |c: &Config| c.footprint.as_ref()?.state_history.clone(),
+			"--state-history",
+			"Keep reverse state diffs of imported blocks, allowing balance, nonce, code and storage queries against blocks whose state has been pruned.",
+
 			ARG arg_cache_size: (Option<u32>) = None, or |c: &Config| c.footprint.as_ref()?.cache_size.clone(),
 			"--cache-size=[MB]",
 			"Set total amount of discretionary memory to use for the entire system, overrides other cache and queue options.",
@@ -1186,21 +1267,29 @@ struct Ui {
 struct Network {
 	warp: Option<bool>,
 	warp_barrier: Option<u64>,
+	state_sync: Option<bool>,
 	port: Option<u16>,
 	interface: Option<String>,
 	min_peers: Option<u16>,
 	max_peers: Option<u16>,
 	snapshot_peers: Option<u16>,
 	max_pending_peers: Option<u16>,
+	max_upload_rate: Option<u64>,
+	max_download_rate: Option<u64>,
+	peer_max_upload_rate: Option<u64>,
+	peer_max_download_rate: Option<u64>,
 	nat: Option<String>,
 	allow_ips: Option<String>,
 	id: Option<u64>,
 	bootnodes: Option<Vec<String>>,
+	dns_zone_file: Option<String>,
 	discovery: Option<bool>,
 	node_key: Option<String>,
 	reserved_peers: Option<String>,
 	reserved_only: Option<bool>,
 	no_serve_light: Option<bool>,
+	node_record: Option<Vec<String>>,
+	node_record_filter: Option<Vec<String>>,
 }
 
 #[derive(Default, Debug, PartialEq, Deserialize)]
@@ -1340,6 +1429,8 @@ struct Stratum {
 #[serde(deny_unknown_fields)]
 struct Footprint {
 	tracing: Option<String>,
+	tracing_address_index: Option<bool>,
+	log_index: Option<bool>,
 	pruning: Option<String>,
 	pruning_history: Option<u64>,
 	pruning_memory: Option<usize>,
@@ -1350,7 +1441,9 @@ struct Footprint {
 	cache_size_queue: Option<u32>,
 	cache_size_state: Option<u32>,
 	db_compaction: Option<String>,
+	db_backend: Option<String>,
 	fat_db: Option<String>,
+	state_history: Option<bool>,
 	scale_verifiers: Option<bool>,
 	num_verifiers: Option<usize>,
 }
@@ -1442,6 +1535,29 @@ mod tests {
 
 		let args = Args::parse(&["parity", "export", "state", "--min-balance","123"]).unwrap();
 		assert_eq!(args.arg_export_state_min_balance, Some("123".to_string()));
+
+		let args = Args::parse(&["parity", "db", "index-logs", "--rebuild"]).unwrap();
+		assert_eq!(args.cmd_db_index_logs, true);
+		assert_eq!(args.flag_db_index_logs_rebuild, true);
+
+		let args = Args::parse(&["parity", "db", "migrate", "--to", "logdb"]).unwrap();
+		assert_eq!(args.cmd_db_migrate, true);
+		assert_eq!(args.arg_db_migrate_to, Some("logdb".into()));
+		assert!(Args::parse(&["parity", "db", "migrate"]).is_err());
+
+		let args = Args::parse(&["parity", "db", "convert-pruning", "--to", "fast"]).unwrap();
+		assert_eq!(args.cmd_db_convert_pruning, true);
+		assert_eq!(args.arg_db_convert_pruning_to, Some("fast".into()));
+		assert!(Args::parse(&["parity", "db", "convert-pruning"]).is_err());
+
+		let args = Args::parse(&["parity", "snapshot", "verify", "file.dump"]).unwrap();
+		assert_eq!(args.cmd_snapshot_verify, true);
+		assert_eq!(args.arg_snapshot_verify_file, Some("file.dump".into()));
+		assert_eq!(args.arg_snapshot_file, None);
+
+		let args = Args::parse(&["parity", "snapshot", "file.dump"]).unwrap();
+		assert_eq!(args.cmd_snapshot_verify, false);
+		assert_eq!(args.arg_snapshot_file, Some("file.dump".into()));
 	}
 
 	#[test]
@@ -1573,11 +1689,15 @@ mod tests {
 			cmd_signer_reject: false,
 			cmd_signer_new_token: false,
 			cmd_snapshot: false,
+			cmd_snapshot_verify: false,
 			cmd_restore: false,
 			cmd_tools: false,
 			cmd_tools_hash: false,
 			cmd_db: false,
 			cmd_db_kill: false,
+			cmd_db_index_logs: false,
+			cmd_db_migrate: false,
+			cmd_db_convert_pruning: false,
 			cmd_export_hardcoded_sync: false,
 
 			// Arguments
@@ -1589,6 +1709,7 @@ mod tests {
 			arg_export_state_file: None,
 			arg_export_state_format: None,
 			arg_snapshot_file: None,
+			arg_snapshot_verify_file: None,
 			arg_restore_file: None,
 			arg_tools_hash_file: None,
 
@@ -1651,19 +1772,27 @@ mod tests {
 
 			// -- Networking Options
 			flag_no_warp: false,
+			flag_state_sync: false,
 			arg_port: 30303u16,
 			arg_interface: "all".into(),
 			arg_min_peers: Some(25u16),
 			arg_max_peers: Some(50u16),
 			arg_max_pending_peers: 64u16,
+			arg_max_upload_rate: None,
+			arg_max_download_rate: None,
+			arg_peer_max_upload_rate: None,
+			arg_peer_max_download_rate: None,
 			arg_snapshot_peers: 0u16,
 			arg_allow_ips: "all".into(),
 			arg_nat: "any".into(),
 			arg_network_id: Some(1),
 			arg_bootnodes: Some("".into()),
+			arg_dns_zone_file: Some("./path_to_zone_file".into()),
 			flag_no_discovery: false,
 			arg_node_key: None,
 			arg_reserved_peers: Some("./path_to_file".into()),
+			arg_node_record: Some("".into()),
+			arg_node_record_filter: Some("".into()),
 			flag_reserved_only: false,
 			flag_no_ancient_blocks: false,
 			flag_no_serve_light: false,
@@ -1766,6 +1895,8 @@ mod tests {
 
 			// -- Footprint Options
 			arg_tracing: "auto".into(),
+			flag_tracing_address_index: false,
+			flag_log_index: false,
 			arg_pruning: "auto".into(),
 			arg_pruning_history: 64u64,
 			arg_pruning_memory: 500usize,
@@ -1776,7 +1907,9 @@ mod tests {
 			arg_cache_size: Some(128),
 			flag_fast_and_loose: false,
 			arg_db_compaction: "ssd".into(),
+			arg_db_backend: "rocksdb".into(),
 			arg_fat_db: "auto".into(),
+			flag_state_history: false,
 			flag_scale_verifiers: true,
 			arg_num_verifiers: Some(6),
 
@@ -1787,6 +1920,9 @@ mod tests {
 			flag_export_state_no_code: false,
 			flag_export_state_no_storage: false,
 			arg_export_state_min_balance: None,
+			flag_db_index_logs_rebuild: false,
+			arg_db_migrate_to: None,
+			arg_db_convert_pruning_to: None,
 			arg_export_state_max_balance: None,
 
 			// -- Snapshot Optons
@@ -1917,21 +2053,29 @@ mod tests {
 			network: Some(Network {
 				warp: Some(false),
 				warp_barrier: None,
+				state_sync: None,
 				port: None,
 				interface: None,
 				min_peers: Some(10),
 				max_peers: Some(20),
 				max_pending_peers: Some(30),
+				max_upload_rate: None,
+				max_download_rate: None,
+				peer_max_upload_rate: None,
+				peer_max_download_rate: None,
 				snapshot_peers: Some(40),
 				allow_ips: Some("public".into()),
 				nat: Some("any".into()),
 				id: None,
 				bootnodes: None,
+				dns_zone_file: None,
 				discovery: Some(true),
 				node_key: None,
 				reserved_peers: Some("./path/to/reserved_peers".into()),
 				reserved_only: Some(true),
 				no_serve_light: None,
+				node_record: None,
+				node_record_filter: None,
 			}),
 			websockets: Some(Ws {
 				disable: Some(true),
@@ -2032,6 +2176,8 @@ mod tests {
 			}),
 			footprint: Some(Footprint {
 				tracing: Some("on".into()),
+				tracing_address_index: None,
+				log_index: None,
 				pruning: Some("fast".into()),
 				pruning_history: Some(64),
 				pruning_memory: None,
@@ -2042,7 +2188,9 @@ mod tests {
 				cache_size_queue: Some(100),
 				cache_size_state: Some(25),
 				db_compaction: Some("ssd".into()),
+				db_backend: None,
 				fat_db: Some("off".into()),
+				state_history: None,
 				scale_verifiers: Some(false),
 				num_verifiers: None,
 			}),
@@ -421,17 +421,18 @@ macro_rules! usage {
 							);
 						)*
 
-						// Print the subcommand on its own only if it has no subsubcommands
-						if !subc_subc_exist {
-							let subc_usages : Vec<&str> = vec![
-								$(
-									concat!("[",$subc_flag_usage,"]"),
-								)*
-								$(
-									$subc_arg_usage,
-								)*
-							];
+						let subc_usages : Vec<&str> = vec![
+							$(
+								concat!("[",$subc_flag_usage,"]"),
+							)*
+							$(
+								$subc_arg_usage,
+							)*
+						];
 
+						// Print the subcommand on its own only if it has no subsubcommands
+						// or can be run with arguments of its own
+						if !subc_subc_exist || !subc_usages.is_empty() {
 							help.push_str(&subcommands_wrapper.fill(
 								format!(
 									"parity [options] {} {}\n",
@@ -633,7 +634,12 @@ macro_rules! usage {
 								.about($subc_help)
 								.args(&subc_usages.get(stringify!($subc)).unwrap().iter().map(|u| Arg::from_usage(u).use_delimiter(false).allow_hyphen_values(true)).collect::<Vec<Arg>>())
 								$(
-									.setting(AppSettings::SubcommandRequired) // prevent from running `parity account`
+									// prevent from running `parity account`, but keep commands with
+									// arguments of their own, like `parity snapshot <FILE>`, runnable
+									.setting(match subc_usages.get(stringify!($subc)).unwrap().is_empty() {
+										true => AppSettings::SubcommandRequired,
+										false => AppSettings::SubcommandsNegateReqs,
+									})
 									.subcommand(
 										SubCommand::with_name(&underscore_to_hyphen!(&stringify!($subc_subc)[stringify!($subc).len()+1..]))
 										.about($subc_subc_help)
@@ -38,7 +38,7 @@ use num_cpus;
 use rpc::{IpcConfiguration, HttpConfiguration, WsConfiguration};
 use parity_rpc::NetworkSettings;
 use cache::CacheConfig;
-use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_queue_strategy, to_queue_penalization, passwords_from_files};
+use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_node_record_entries, to_addresses, to_address, to_queue_strategy, to_queue_penalization, passwords_from_files};
 use dir::helpers::{replace_home, replace_home_and_local};
 use params::{ResealPolicy, AccountsConfig, GasPricerConfig, MinerExtras, SpecType};
 use ethcore_logger::Config as LogConfig;
@@ -48,7 +48,7 @@ use ethcore_private_tx::{ProviderConfig, EncryptorConfig};
 use secretstore::{NodeSecretKey, Configuration as SecretStoreConfiguration, ContractAddress as SecretStoreContractAddress};
 use updater::{UpdatePolicy, UpdateFilter, ReleaseTrack};
 use run::RunCmd;
-use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, ExportState, DataFormat};
+use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, IndexLogs, MigrateBackend, ConvertPruning, ExportState, DataFormat};
 use export_hardcoded_sync::ExportHsyncCmd;
 use presale::ImportWallet;
 use account::{AccountCmd, NewAccount, ListAccounts, ImportAccounts, ImportFromGethAccounts};
@@ -136,6 +136,7 @@ impl Configuration {
 		let tracing = self.args.arg_tracing.parse()?;
 		let fat_db = self.args.arg_fat_db.parse()?;
 		let compaction = self.args.arg_db_compaction.parse()?;
+		let db_backend = self.args.arg_db_backend.parse()?;
 		let warp_sync = !self.args.flag_no_warp;
 		let geth_compatibility = self.args.flag_geth;
 		let ipfs_conf = self.ipfs_config();
@@ -181,6 +182,41 @@ impl Configuration {
 				dirs: dirs,
 				pruning: pruning,
 			}))
+		} else if self.args.cmd_db && self.args.cmd_db_index_logs {
+			Cmd::Blockchain(BlockchainCmd::IndexLogs(IndexLogs {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				pruning_history: pruning_history,
+				pruning_memory: self.args.arg_pruning_memory,
+				compaction: compaction,
+				db_backend: db_backend,
+				tracing: tracing,
+				fat_db: fat_db,
+				rebuild: self.args.flag_db_index_logs_rebuild,
+			}))
+		} else if self.args.cmd_db && self.args.cmd_db_migrate {
+			Cmd::Blockchain(BlockchainCmd::MigrateBackend(MigrateBackend {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				compaction: compaction,
+				to: self.args.arg_db_migrate_to.clone().expect("CLI argument is required; qed").parse()?,
+			}))
+		} else if self.args.cmd_db && self.args.cmd_db_convert_pruning {
+			Cmd::Blockchain(BlockchainCmd::ConvertPruning(ConvertPruning {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				pruning_history: pruning_history,
+				compaction: compaction,
+				db_backend: db_backend,
+				fat_db: fat_db,
+				to: self.args.arg_db_convert_pruning_to.clone().expect("CLI argument is required; qed").parse()?,
+			}))
 		} else if self.args.cmd_account {
 			let account_cmd = if self.args.cmd_account_new {
 				let new_acc = NewAccount {
@@ -236,6 +272,7 @@ impl Configuration {
 				pruning_history: pruning_history,
 				pruning_memory: self.args.arg_pruning_memory,
 				compaction: compaction,
+				db_backend: db_backend,
 				tracing: tracing,
 				fat_db: fat_db,
 				vm_type: vm_type,
@@ -257,6 +294,7 @@ impl Configuration {
 					pruning_history: pruning_history,
 					pruning_memory: self.args.arg_pruning_memory,
 					compaction: compaction,
+					db_backend: db_backend,
 					tracing: tracing,
 					fat_db: fat_db,
 					from_block: to_block_id(&self.args.arg_export_blocks_from)?,
@@ -275,6 +313,7 @@ impl Configuration {
 					pruning_history: pruning_history,
 					pruning_memory: self.args.arg_pruning_memory,
 					compaction: compaction,
+					db_backend: db_backend,
 					tracing: tracing,
 					fat_db: fat_db,
 					at: to_block_id(&self.args.arg_export_state_at)?,
@@ -298,8 +337,15 @@ impl Configuration {
 				tracing: tracing,
 				fat_db: fat_db,
 				compaction: compaction,
-				file_path: self.args.arg_snapshot_file.clone(),
-				kind: snapshot::Kind::Take,
+				db_backend: db_backend,
+				file_path: match self.args.cmd_snapshot_verify {
+					true => self.args.arg_snapshot_verify_file.clone(),
+					false => self.args.arg_snapshot_file.clone(),
+				},
+				kind: match self.args.cmd_snapshot_verify {
+					true => snapshot::Kind::Verify,
+					false => snapshot::Kind::Take,
+				},
 				block_at: to_block_id(&self.args.arg_snapshot_at)?,
 				snapshot_conf: snapshot_conf,
 			};
@@ -315,6 +361,7 @@ impl Configuration {
 				tracing: tracing,
 				fat_db: fat_db,
 				compaction: compaction,
+				db_backend: db_backend,
 				file_path: self.args.arg_restore_file.clone(),
 				kind: snapshot::Kind::Restore,
 				block_at: to_block_id("latest")?, // unimportant.
@@ -328,6 +375,7 @@ impl Configuration {
 				spec: spec,
 				pruning: pruning,
 				compaction: compaction,
+				db_backend: db_backend,
 			};
 			Cmd::ExportHardcodedSync(export_hs_cmd)
 		} else {
@@ -367,10 +415,15 @@ impl Configuration {
 				mode: mode,
 				tracing: tracing,
 				fat_db: fat_db,
+				state_history: self.args.flag_state_history,
+				tracing_address_index: self.args.flag_tracing_address_index,
+				log_index: self.args.flag_log_index,
 				compaction: compaction,
+				db_backend: db_backend,
 				vm_type: vm_type,
 				warp_sync: warp_sync,
 				warp_barrier: self.args.arg_warp_barrier,
+				state_sync: self.args.flag_state_sync,
 				geth_compatibility: geth_compatibility,
 				net_settings: self.network_settings()?,
 				ipfs_conf: ipfs_conf,
@@ -701,6 +754,7 @@ impl Configuration {
 		let mut ret = NetworkConfiguration::new();
 		ret.nat_enabled = self.args.arg_nat == "any" || self.args.arg_nat == "upnp";
 		ret.boot_nodes = to_bootnodes(&self.args.arg_bootnodes)?;
+		ret.dns_zone_file = self.args.arg_dns_zone_file.as_ref().map(|path| replace_home(&self.directories().base, path));
 		let (listen, public) = self.net_addresses()?;
 		ret.listen_address = Some(format!("{}", listen));
 		ret.public_address = public.map(|p| format!("{}", p));
@@ -717,11 +771,17 @@ impl Configuration {
 		ret.snapshot_peers = self.snapshot_peers();
 		ret.ip_filter = self.ip_filter()?;
 		ret.max_pending_peers = self.max_pending_peers();
+		ret.max_upload_rate = self.args.arg_max_upload_rate.map(|rate| rate * 1024);
+		ret.max_download_rate = self.args.arg_max_download_rate.map(|rate| rate * 1024);
+		ret.max_peer_upload_rate = self.args.arg_peer_max_upload_rate.map(|rate| rate * 1024);
+		ret.max_peer_download_rate = self.args.arg_peer_max_download_rate.map(|rate| rate * 1024);
 		let mut net_path = PathBuf::from(self.directories().base);
 		net_path.push("network");
 		ret.config_path = Some(net_path.to_str().unwrap().to_owned());
 		ret.reserved_nodes = self.init_reserved_nodes()?;
 		ret.allow_non_reserved = !self.args.flag_reserved_only;
+		ret.node_record_entries = to_node_record_entries(&self.args.arg_node_record)?;
+		ret.node_record_filter = to_node_record_entries(&self.args.arg_node_record_filter)?;
 		ret.client_version = {
 			let mut client_version = version();
 			if !self.args.arg_identity.is_empty() {
@@ -1161,10 +1221,11 @@ mod tests {
 	use updater::{UpdatePolicy, UpdateFilter, ReleaseTrack};
 
 	use account::{AccountCmd, NewAccount, ImportAccounts, ListAccounts};
-	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, DataFormat, ExportState};
+	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, IndexLogs, DataFormat, ExportState};
 	use cli::Args;
 	use dir::{Directories, default_hypervisor_path};
 	use helpers::{default_network_config};
+	use journaldb::Algorithm;
 	use params::SpecType;
 	use presale::ImportWallet;
 	use rpc::WsConfiguration;
@@ -1256,6 +1317,7 @@ mod tests {
 			pruning_history: 64,
 			pruning_memory: 32,
 			compaction: Default::default(),
+			db_backend: Default::default(),
 			tracing: Default::default(),
 			fat_db: Default::default(),
 			vm_type: VMType::Interpreter,
@@ -1280,6 +1342,7 @@ mod tests {
 			pruning_memory: 32,
 			format: Default::default(),
 			compaction: Default::default(),
+			db_backend: Default::default(),
 			tracing: Default::default(),
 			fat_db: Default::default(),
 			from_block: BlockId::Number(1),
@@ -1302,6 +1365,7 @@ mod tests {
 			pruning_memory: 32,
 			format: Default::default(),
 			compaction: Default::default(),
+			db_backend: Default::default(),
 			tracing: Default::default(),
 			fat_db: Default::default(),
 			at: BlockId::Latest,
@@ -1312,6 +1376,59 @@ mod tests {
 		})));
 	}
 
+	#[test]
+	fn test_command_db_index_logs() {
+		let args = vec!["parity", "db", "index-logs", "--rebuild"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::IndexLogs(IndexLogs {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			pruning_history: 64,
+			pruning_memory: 32,
+			compaction: Default::default(),
+			db_backend: Default::default(),
+			tracing: Default::default(),
+			fat_db: Default::default(),
+			rebuild: true,
+		})));
+	}
+
+	#[test]
+	fn test_command_db_migrate() {
+		let args = vec!["parity", "db", "migrate", "--to", "logdb"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::MigrateBackend(MigrateBackend {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			compaction: Default::default(),
+			to: ::db::Backend::LogDB,
+		})));
+	}
+
+	#[test]
+	fn test_command_db_convert_pruning() {
+		let args = vec!["parity", "db", "convert-pruning", "--to", "fast"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::ConvertPruning(ConvertPruning {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			pruning_history: 64,
+			compaction: Default::default(),
+			db_backend: Default::default(),
+			fat_db: Default::default(),
+			to: Algorithm::OverlayRecent,
+		})));
+
+		let args = vec!["parity", "db", "convert-pruning", "--to", "everything"];
+		assert!(parse(&args).into_command().is_err());
+	}
+
 	#[test]
 	fn test_command_blockchain_export_with_custom_format() {
 		let args = vec!["parity", "export", "blocks", "--format", "hex", "blockchain.json"];
@@ -1326,6 +1443,7 @@ mod tests {
 			pruning_memory: 32,
 			format: Some(DataFormat::Hex),
 			compaction: Default::default(),
+			db_backend: Default::default(),
 			tracing: Default::default(),
 			fat_db: Default::default(),
 			from_block: BlockId::Number(1),
@@ -1390,6 +1508,7 @@ mod tests {
 			network_id: None,
 			warp_sync: true,
 			warp_barrier: None,
+			state_sync: false,
 			acc_conf: Default::default(),
 			gas_pricer_conf: Default::default(),
 			miner_extras: Default::default(),
@@ -1406,6 +1525,7 @@ mod tests {
 			mode: Default::default(),
 			tracing: Default::default(),
 			compaction: Default::default(),
+			db_backend: Default::default(),
 			vm_type: Default::default(),
 			geth_compatibility: false,
 			net_settings: Default::default(),
@@ -1417,6 +1537,9 @@ mod tests {
 			name: "".into(),
 			custom_bootnodes: false,
 			fat_db: Default::default(),
+			state_history: false,
+			tracing_address_index: false,
+			log_index: false,
 			snapshot_conf: Default::default(),
 			stratum: None,
 			check_seal: true,
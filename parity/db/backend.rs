@@ -0,0 +1,117 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use kvdb_logdb;
+
+/// Key-value store backing the client database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+	/// RocksDB.
+	RocksDB,
+	/// Log-structured database written in pure Rust.
+	LogDB,
+}
+
+impl Default for Backend {
+	#[cfg(feature = "rocksdb")]
+	fn default() -> Self {
+		Backend::RocksDB
+	}
+
+	#[cfg(not(feature = "rocksdb"))]
+	fn default() -> Self {
+		Backend::LogDB
+	}
+}
+
+impl FromStr for Backend {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"auto" => Ok(Backend::default()),
+			"rocksdb" => Ok(Backend::RocksDB),
+			"logdb" => Ok(Backend::LogDB),
+			_ => Err(format!("Invalid database backend given: {}. Expected auto/rocksdb/logdb.", s)),
+		}
+	}
+}
+
+impl fmt::Display for Backend {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Backend::RocksDB => write!(f, "rocksdb"),
+			Backend::LogDB => write!(f, "logdb"),
+		}
+	}
+}
+
+impl Backend {
+	/// Returns the backend of the database at given path, if there is one.
+	pub fn detect(path: &Path) -> Option<Backend> {
+		if path.join(kvdb_logdb::FILE_NAME).exists() {
+			Some(Backend::LogDB)
+		} else if path.join("CURRENT").exists() {
+			Some(Backend::RocksDB)
+		} else {
+			None
+		}
+	}
+
+	/// Returns the backend which should be used to open the database at given path.
+	/// Existing databases are always opened with the backend they were created with.
+	pub fn resolve(self, path: &Path) -> Backend {
+		match Backend::detect(path) {
+			Some(existing) if existing != self => {
+				warn!("Database at {} uses the {} backend, ignoring --db-backend {}. Run `parity db migrate --to {}` to convert it.",
+					path.display(), existing, self, self);
+				existing
+			},
+			Some(existing) => existing,
+			None => self,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use tempdir::TempDir;
+	use super::Backend;
+
+	#[test]
+	fn test_backend_parsing() {
+		assert_eq!(Backend::RocksDB, "rocksdb".parse().unwrap());
+		assert_eq!(Backend::LogDB, "logdb".parse().unwrap());
+		assert_eq!(Backend::default(), "auto".parse().unwrap());
+		assert!("leveldb".parse::<Backend>().is_err());
+		assert_eq!(Backend::LogDB.to_string(), "logdb");
+	}
+
+	#[test]
+	fn test_backend_detection() {
+		let tempdir = TempDir::new("").unwrap();
+		assert_eq!(Backend::detect(tempdir.path()), None);
+		assert_eq!(Backend::LogDB.resolve(tempdir.path()), Backend::LogDB);
+
+		fs::File::create(tempdir.path().join("CURRENT")).unwrap();
+		assert_eq!(Backend::detect(tempdir.path()), Some(Backend::RocksDB));
+		assert_eq!(Backend::LogDB.resolve(tempdir.path()), Backend::RocksDB);
+	}
+}
@@ -0,0 +1,88 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database implementation for builds without RocksDB support.
+
+use std::io;
+use std::sync::Arc;
+use std::path::Path;
+use ethcore::{BlockChainDBHandler, BlockChainDB};
+use ethcore::db::NUM_COLUMNS;
+use ethcore::client::{ClientConfig, DatabaseCompactionProfile};
+use kvdb_logdb::{Database, DatabaseConfig};
+
+use cache::CacheConfig;
+use super::{open_app_db, Backend};
+use super::migration::Error;
+
+/// Open a secret store DB using the given secret store data path. The DB path is one level beneath the data path.
+#[cfg(feature = "secretstore")]
+pub fn open_secretstore_db(data_path: &str) -> Result<Arc<::kvdb::KeyValueDB>, String> {
+	use std::path::PathBuf;
+
+	let mut db_path = PathBuf::from(data_path);
+	db_path.push("db");
+	let db_path = db_path.to_str().ok_or_else(|| "Invalid secretstore path".to_string())?;
+	Ok(Arc::new(Database::open(&DatabaseConfig::with_columns(None), &db_path).map_err(|e| format!("Error opening database: {:?}", e))?))
+}
+
+/// Create a restoration db handler using the config generated by `client_path` and `client_config`.
+///
+/// Restored databases replace the one at `client_path`, so they are created with its backend.
+pub fn restoration_db_handler(client_path: &Path, _client_config: &ClientConfig, backend: Backend) -> Box<BlockChainDBHandler> {
+	struct RestorationDBHandler {
+		backend: Backend,
+	}
+
+	impl BlockChainDBHandler for RestorationDBHandler {
+		fn open(&self, db_path: &Path) -> io::Result<Arc<BlockChainDB>> {
+			open_database(&db_path.to_string_lossy(), self.backend)
+		}
+	}
+
+	Box::new(RestorationDBHandler {
+		backend: backend.resolve(client_path),
+	})
+}
+
+/// Open a new main DB.
+pub fn open_db(client_path: &str, _cache_config: &CacheConfig, _compaction: &DatabaseCompactionProfile, backend: Backend) -> io::Result<Arc<BlockChainDB>> {
+	open_database(client_path, backend.resolve(Path::new(client_path)))
+}
+
+fn open_database(client_path: &str, backend: Backend) -> io::Result<Arc<BlockChainDB>> {
+	match backend {
+		Backend::LogDB => {
+			let key_value = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), client_path)?);
+			open_app_db(Path::new(client_path), key_value)
+		},
+		Backend::RocksDB => Err(io::Error::new(io::ErrorKind::Other, format!(
+			"Database at {} uses the rocksdb backend, but this build was compiled without RocksDB support", client_path
+		))),
+	}
+}
+
+/// Copy the main DB at `client_path` to another backend and replace it with the copy.
+///
+/// Builds without RocksDB support only know a single backend, so there is nothing to copy to.
+pub fn migrate_backend(_client_path: &Path, _cache_config: &CacheConfig, _compaction: &DatabaseCompactionProfile, to: Backend) -> Result<(), String> {
+	Err(format!("Cannot migrate to the {} backend, this build was compiled without RocksDB support", to))
+}
+
+/// Column migrations are only implemented for RocksDB databases.
+pub fn migrate_columns(_version: u32, _db_path: &Path, _compaction_profile: &DatabaseCompactionProfile) -> Result<(), Error> {
+	Err(Error::MigrationImpossible)
+}
@@ -0,0 +1,145 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database version handling, common to all backends.
+
+use std::fs;
+use std::io::{Read, Write, Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::fmt::{Display, Formatter, Error as FmtError};
+use ethcore::client::DatabaseCompactionProfile;
+use ethcore;
+
+use super::Backend;
+use super::impls::migrate_columns;
+
+/// Database is assumed to be at default version, when no version file is found.
+const DEFAULT_VERSION: u32 = 5;
+/// Current version of database models.
+const CURRENT_VERSION: u32 = 14;
+/// Version file name.
+const VERSION_FILE_NAME: &'static str = "db_version";
+
+/// Migration related erorrs.
+#[derive(Debug)]
+pub enum Error {
+	/// Returned when current version cannot be read or guessed.
+	UnknownDatabaseVersion,
+	/// Existing DB is newer than the known one.
+	FutureDBVersion,
+	/// Migration is not possible.
+	MigrationImpossible,
+	/// Blooms-db migration error.
+	BloomsDB(ethcore::error::Error),
+	/// Migration was completed succesfully,
+	/// but there was a problem with io.
+	Io(IoError),
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+		let out = match *self {
+			Error::UnknownDatabaseVersion => "Current database version cannot be read".into(),
+			Error::FutureDBVersion => "Database was created with newer client version. Upgrade your client or delete DB and resync.".into(),
+			Error::MigrationImpossible => format!("Database migration to version {} is not possible.", CURRENT_VERSION),
+			Error::BloomsDB(ref err) => format!("blooms-db migration error: {}", err),
+			Error::Io(ref err) => format!("Unexpected io error on DB migration: {}.", err),
+		};
+
+		write!(f, "{}", out)
+	}
+}
+
+impl From<IoError> for Error {
+	fn from(err: IoError) -> Self {
+		Error::Io(err)
+	}
+}
+
+/// Returns the version file path.
+fn version_file_path(path: &Path) -> PathBuf {
+	let mut file_path = path.to_owned();
+	file_path.push(VERSION_FILE_NAME);
+	file_path
+}
+
+/// Reads current database version from the file at given path.
+/// If the file does not exist returns `DEFAULT_VERSION`.
+fn current_version(path: &Path) -> Result<u32, Error> {
+	match fs::File::open(version_file_path(path)) {
+		Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(DEFAULT_VERSION),
+		Err(_) => Err(Error::UnknownDatabaseVersion),
+		Ok(mut file) => {
+			let mut s = String::new();
+			file.read_to_string(&mut s).map_err(|_| Error::UnknownDatabaseVersion)?;
+			u32::from_str_radix(&s, 10).map_err(|_| Error::UnknownDatabaseVersion)
+		},
+	}
+}
+
+/// Writes current database version to the file.
+/// Creates a new file if the version file does not exist yet.
+fn update_version(path: &Path) -> Result<(), Error> {
+	fs::create_dir_all(path)?;
+	let mut file = fs::File::create(version_file_path(path))?;
+	file.write_all(format!("{}", CURRENT_VERSION).as_bytes())?;
+	Ok(())
+}
+
+/// Consolidated database path
+fn consolidated_database_path(path: &Path) -> PathBuf {
+	let mut state_path = path.to_owned();
+	state_path.push("db");
+	state_path
+}
+
+fn exists(path: &Path) -> bool {
+	fs::metadata(path).is_ok()
+}
+
+/// Migrates the database.
+pub fn migrate(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> Result<(), Error> {
+	// read version file.
+	let version = current_version(path)?;
+
+	// migrate the databases.
+	// main db directory may already exists, so let's check if we have blocks dir
+	if version > CURRENT_VERSION {
+		return Err(Error::FutureDBVersion);
+	}
+
+	// We are in the latest version, yay!
+	if version == CURRENT_VERSION {
+		return Ok(())
+	}
+
+	let db_path = consolidated_database_path(path);
+
+	// Further migrations
+	if version < CURRENT_VERSION && exists(&db_path) {
+		// column migrations are only implemented for rocksdb
+		if Backend::detect(&db_path) == Some(Backend::LogDB) {
+			return Err(Error::MigrationImpossible);
+		}
+
+		println!("Migrating database from version {} to {}", version, CURRENT_VERSION);
+		migrate_columns(version, &db_path, compaction_profile)?;
+		println!("Migration finished");
+	}
+
+	// update version file.
+	update_version(path)
+}
@@ -15,11 +15,131 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Database-related operations.
+//!
+//! Builds with the `rocksdb` feature can use both RocksDB and the log database as the key-value
+//! store of the client database. Without it only the log database is available.
 
+use std::{io, fs};
+use std::path::Path;
+use std::sync::Arc;
+use blooms_db;
+use ethcore::BlockChainDB;
+use kvdb::KeyValueDB;
+
+mod backend;
+mod migration;
+
+#[cfg(feature = "rocksdb")]
 #[path="rocksdb/mod.rs"]
 mod impls;
 
-pub use self::impls::{open_db, restoration_db_handler, migrate};
+#[cfg(not(feature = "rocksdb"))]
+#[path="logdb/mod.rs"]
+mod impls;
+
+pub use self::backend::Backend;
+pub use self::migration::migrate;
+pub use self::impls::{open_db, restoration_db_handler, migrate_backend};
 
 #[cfg(feature = "secretstore")]
 pub use self::impls::open_secretstore_db;
+
+struct AppDB {
+	key_value: Arc<KeyValueDB>,
+	blooms: blooms_db::Database,
+	trace_blooms: blooms_db::Database,
+}
+
+impl BlockChainDB for AppDB {
+	fn key_value(&self) -> &Arc<KeyValueDB> {
+		&self.key_value
+	}
+
+	fn blooms(&self) -> &blooms_db::Database {
+		&self.blooms
+	}
+
+	fn trace_blooms(&self) -> &blooms_db::Database {
+		&self.trace_blooms
+	}
+}
+
+/// Opens the blooms of the client database at given path, next to its key-value store.
+fn open_app_db(client_path: &Path, key_value: Arc<KeyValueDB>) -> io::Result<Arc<BlockChainDB>> {
+	let blooms_path = client_path.join("blooms");
+	let trace_blooms_path = client_path.join("trace_blooms");
+	fs::create_dir_all(&blooms_path)?;
+	fs::create_dir_all(&trace_blooms_path)?;
+
+	let db = AppDB {
+		key_value,
+		blooms: blooms_db::Database::open(blooms_path)?,
+		trace_blooms: blooms_db::Database::open(trace_blooms_path)?,
+	};
+
+	Ok(Arc::new(db))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use tempdir::TempDir;
+	use ethcore::client::{BlockChainClient, ChainInfo, Client, ClientConfig, DatabaseCompactionProfile};
+	use ethcore::db::NUM_COLUMNS;
+	use ethcore::ids::BlockId;
+	use ethcore::miner::Miner;
+	use ethcore::spec::Spec;
+	use ethcore::test_helpers::push_blocks_to_client;
+	use io::IoChannel;
+	use kvdb::DBTransaction;
+	use cache::CacheConfig;
+	use super::{open_db, Backend};
+
+	#[test]
+	fn client_runs_on_logdb() {
+		let tempdir = TempDir::new("").unwrap();
+		let client_path = tempdir.path().join("db");
+		let open = || open_db(client_path.to_str().unwrap(), &CacheConfig::default(), &DatabaseCompactionProfile::default(), Backend::LogDB).unwrap();
+		let new_client = |spec: &Spec| Client::new(
+			ClientConfig::default(),
+			spec,
+			open(),
+			Arc::new(Miner::new_for_tests(spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+
+		// every column of the client database is available.
+		{
+			let db = open();
+			assert_eq!(Backend::detect(&client_path), Some(Backend::LogDB));
+
+			let columns = NUM_COLUMNS.expect("client database has columns; qed");
+			let mut transaction = DBTransaction::new();
+			for col in 0..columns {
+				transaction.put(Some(col), b"logdb-test", &[col as u8]);
+			}
+			db.key_value().write(transaction).unwrap();
+
+			let mut transaction = DBTransaction::new();
+			for col in 0..columns {
+				assert_eq!(&*db.key_value().get(Some(col), b"logdb-test").unwrap().unwrap(), &[col as u8]);
+				transaction.delete(Some(col), b"logdb-test");
+			}
+			db.key_value().write(transaction).unwrap();
+		}
+
+		let spec = Spec::new_test();
+		let client = new_client(&spec);
+		push_blocks_to_client(&client, 53, 1, 5);
+		client.flush_queue();
+		client.import_verified_blocks();
+		let chain_info = client.chain_info();
+		assert_eq!(chain_info.best_block_number, 5);
+		drop(client);
+
+		let client = new_client(&spec);
+		assert_eq!(client.chain_info().best_block_hash, chain_info.best_block_hash);
+		assert!(client.block(BlockId::Number(5)).is_some());
+		assert!(client.state_at(BlockId::Latest).is_some());
+	}
+}
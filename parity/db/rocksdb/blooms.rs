@@ -21,11 +21,11 @@ use ethereum_types::Bloom;
 use ethcore::error::Error;
 use rlp;
 use super::kvdb_rocksdb::DatabaseConfig;
-use super::open_database;
+use super::{open_database, Backend};
 
 pub fn migrate_blooms<P: AsRef<Path>>(path: P, config: &DatabaseConfig) -> Result<(), Error> {
 	// init
-	let db = open_database(&path.as_ref().to_string_lossy(), config)?;
+	let db = open_database(&path.as_ref().to_string_lossy(), config, Backend::RocksDB)?;
 
 	// possible optimization:
 	// pre-allocate space on disk for faster migration
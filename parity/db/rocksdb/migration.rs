@@ -15,14 +15,13 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::fs;
-use std::io::{Read, Write, Error as IoError, ErrorKind};
 use std::path::{Path, PathBuf};
-use std::fmt::{Display, Formatter, Error as FmtError};
 use super::migration_rocksdb::{Manager as MigrationManager, Config as MigrationConfig, ChangeColumns};
 use super::kvdb_rocksdb::{CompactionProfile, DatabaseConfig};
 use ethcore::client::DatabaseCompactionProfile;
-use ethcore::{self, db};
+use ethcore::db::NUM_COLUMNS;
 
+use db::migration::Error;
 use super::helpers;
 use super::blooms::migrate_blooms;
 
@@ -42,89 +41,18 @@ pub const TO_V12: ChangeColumns = ChangeColumns {
 	version: 12,
 };
 
-/// Database is assumed to be at default version, when no version file is found.
-const DEFAULT_VERSION: u32 = 5;
-/// Current version of database models.
-const CURRENT_VERSION: u32 = 13;
+/// The migration from v13 to v14.
+/// Adds a column for the log index.
+pub const TO_V14: ChangeColumns = ChangeColumns {
+	pre_columns: Some(8),
+	post_columns: Some(9),
+	version: 14,
+};
+
 /// A version of database at which blooms-db was introduced
 const BLOOMS_DB_VERSION: u32 = 13;
 /// Defines how many items are migrated to the new version of database at once.
 const BATCH_SIZE: usize = 1024;
-/// Version file name.
-const VERSION_FILE_NAME: &'static str = "db_version";
-
-/// Migration related erorrs.
-#[derive(Debug)]
-pub enum Error {
-	/// Returned when current version cannot be read or guessed.
-	UnknownDatabaseVersion,
-	/// Existing DB is newer than the known one.
-	FutureDBVersion,
-	/// Migration is not possible.
-	MigrationImpossible,
-	/// Blooms-db migration error.
-	BloomsDB(ethcore::error::Error),
-	/// Migration was completed succesfully,
-	/// but there was a problem with io.
-	Io(IoError),
-}
-
-impl Display for Error {
-	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
-		let out = match *self {
-			Error::UnknownDatabaseVersion => "Current database version cannot be read".into(),
-			Error::FutureDBVersion => "Database was created with newer client version. Upgrade your client or delete DB and resync.".into(),
-			Error::MigrationImpossible => format!("Database migration to version {} is not possible.", CURRENT_VERSION),
-			Error::BloomsDB(ref err) => format!("blooms-db migration error: {}", err),
-			Error::Io(ref err) => format!("Unexpected io error on DB migration: {}.", err),
-		};
-
-		write!(f, "{}", out)
-	}
-}
-
-impl From<IoError> for Error {
-	fn from(err: IoError) -> Self {
-		Error::Io(err)
-	}
-}
-
-/// Returns the version file path.
-fn version_file_path(path: &Path) -> PathBuf {
-	let mut file_path = path.to_owned();
-	file_path.push(VERSION_FILE_NAME);
-	file_path
-}
-
-/// Reads current database version from the file at given path.
-/// If the file does not exist returns `DEFAULT_VERSION`.
-fn current_version(path: &Path) -> Result<u32, Error> {
-	match fs::File::open(version_file_path(path)) {
-		Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(DEFAULT_VERSION),
-		Err(_) => Err(Error::UnknownDatabaseVersion),
-		Ok(mut file) => {
-			let mut s = String::new();
-			file.read_to_string(&mut s).map_err(|_| Error::UnknownDatabaseVersion)?;
-			u32::from_str_radix(&s, 10).map_err(|_| Error::UnknownDatabaseVersion)
-		},
-	}
-}
-
-/// Writes current database version to the file.
-/// Creates a new file if the version file does not exist yet.
-fn update_version(path: &Path) -> Result<(), Error> {
-	fs::create_dir_all(path)?;
-	let mut file = fs::File::create(version_file_path(path))?;
-	file.write_all(format!("{}", CURRENT_VERSION).as_bytes())?;
-	Ok(())
-}
-
-/// Consolidated database path
-fn consolidated_database_path(path: &Path) -> PathBuf {
-	let mut state_path = path.to_owned();
-	state_path.push("db");
-	state_path
-}
 
 /// Database backup
 fn backup_database_path(path: &Path) -> PathBuf {
@@ -147,6 +75,7 @@ fn consolidated_database_migrations(compaction_profile: &CompactionProfile) -> R
 	let mut manager = MigrationManager::new(default_migration_settings(compaction_profile));
 	manager.add_migration(TO_V11).map_err(|_| Error::MigrationImpossible)?;
 	manager.add_migration(TO_V12).map_err(|_| Error::MigrationImpossible)?;
+	manager.add_migration(TO_V14).map_err(|_| Error::MigrationImpossible)?;
 	Ok(manager)
 }
 
@@ -182,50 +111,22 @@ fn migrate_database(version: u32, db_path: &Path, mut migrations: MigrationManag
 	fs::remove_dir_all(&backup_path).map_err(Into::into)
 }
 
-fn exists(path: &Path) -> bool {
-	fs::metadata(path).is_ok()
-}
-
-/// Migrates the database.
-pub fn migrate(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> Result<(), Error> {
-	let compaction_profile = helpers::compaction_profile(&compaction_profile, path);
-
-	// read version file.
-	let version = current_version(path)?;
-
-	// migrate the databases.
-	// main db directory may already exists, so let's check if we have blocks dir
-	if version > CURRENT_VERSION {
-		return Err(Error::FutureDBVersion);
-	}
-
-	// We are in the latest version, yay!
-	if version == CURRENT_VERSION {
-		return Ok(())
-	}
-
-	let db_path = consolidated_database_path(path);
-
-	// Further migrations
-	if version < CURRENT_VERSION && exists(&db_path) {
-		println!("Migrating database from version {} to {}", version, CURRENT_VERSION);
-		migrate_database(version, &db_path, consolidated_database_migrations(&compaction_profile)?)?;
-
-		if version < BLOOMS_DB_VERSION {
-			println!("Migrating blooms to blooms-db...");
-			let db_config = DatabaseConfig {
-				max_open_files: 64,
-				memory_budget: None,
-				compaction: compaction_profile,
-				columns: db::NUM_COLUMNS,
-			};
-
-			migrate_blooms(&db_path, &db_config).map_err(Error::BloomsDB)?;
-		}
+/// Migrates the columns of the consolidated database at `db_path` from given version.
+pub fn migrate_columns(version: u32, db_path: &Path, compaction_profile: &DatabaseCompactionProfile) -> Result<(), Error> {
+	let compaction_profile = helpers::compaction_profile(&compaction_profile, db_path);
+	migrate_database(version, db_path, consolidated_database_migrations(&compaction_profile)?)?;
+
+	if version < BLOOMS_DB_VERSION {
+		println!("Migrating blooms to blooms-db...");
+		let db_config = DatabaseConfig {
+			max_open_files: 64,
+			memory_budget: None,
+			compaction: compaction_profile,
+			columns: NUM_COLUMNS,
+		};
 
-		println!("Migration finished");
+		migrate_blooms(db_path, &db_config).map_err(Error::BloomsDB)?;
 	}
 
-	// update version file.
-	update_version(path)
+	Ok(())
 }
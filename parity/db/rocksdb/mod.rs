@@ -17,43 +17,27 @@
 extern crate kvdb_rocksdb;
 extern crate migration_rocksdb;
 
-use std::{io, fs};
+use std::{io, fs, iter, mem};
 use std::sync::Arc;
-use std::path::Path;
-use blooms_db;
+use std::path::{Path, PathBuf};
 use ethcore::{BlockChainDBHandler, BlockChainDB};
 use ethcore::db::NUM_COLUMNS;
 use ethcore::client::{ClientConfig, DatabaseCompactionProfile};
-use kvdb::KeyValueDB;
+use kvdb::{KeyValueDB, DBTransaction};
+use kvdb_logdb;
 use self::kvdb_rocksdb::{Database, DatabaseConfig};
 
 use cache::CacheConfig;
+use super::{open_app_db, Backend};
 
 mod blooms;
 mod migration;
 mod helpers;
 
-pub use self::migration::migrate;
+pub use self::migration::migrate_columns;
 
-struct AppDB {
-	key_value: Arc<KeyValueDB>,
-	blooms: blooms_db::Database,
-	trace_blooms: blooms_db::Database,
-}
-
-impl BlockChainDB for AppDB {
-	fn key_value(&self) -> &Arc<KeyValueDB> {
-		&self.key_value
-	}
-
-	fn blooms(&self) -> &blooms_db::Database {
-		&self.blooms
-	}
-
-	fn trace_blooms(&self) -> &blooms_db::Database {
-		&self.trace_blooms
-	}
-}
+/// Size of the batches written when copying a database to another backend.
+const BACKEND_MIGRATION_BATCH_SIZE: usize = 16 * 1024 * 1024;
 
 /// Open a secret store DB using the given secret store data path. The DB path is one level beneath the data path.
 #[cfg(feature = "secretstore")]
@@ -67,50 +51,133 @@ pub fn open_secretstore_db(data_path: &str) -> Result<Arc<KeyValueDB>, String> {
 }
 
 /// Create a restoration db handler using the config generated by `client_path` and `client_config`.
-pub fn restoration_db_handler(client_path: &Path, client_config: &ClientConfig) -> Box<BlockChainDBHandler> {
+///
+/// Restored databases replace the one at `client_path`, so they are created with its backend.
+pub fn restoration_db_handler(client_path: &Path, client_config: &ClientConfig, backend: Backend) -> Box<BlockChainDBHandler> {
 	let client_db_config = helpers::client_db_config(client_path, client_config);
 
 	struct RestorationDBHandler {
 		config: DatabaseConfig,
+		backend: Backend,
 	}
 
 	impl BlockChainDBHandler for RestorationDBHandler {
 		fn open(&self, db_path: &Path) -> io::Result<Arc<BlockChainDB>> {
-			open_database(&db_path.to_string_lossy(), &self.config)
+			open_database(&db_path.to_string_lossy(), &self.config, self.backend)
 		}
 	}
 
 	Box::new(RestorationDBHandler {
 		config: client_db_config,
+		backend: backend.resolve(client_path),
 	})
 }
 
 /// Open a new main DB.
-pub fn open_db(client_path: &str, cache_config: &CacheConfig, compaction: &DatabaseCompactionProfile) -> io::Result<Arc<BlockChainDB>> {
+pub fn open_db(client_path: &str, cache_config: &CacheConfig, compaction: &DatabaseCompactionProfile, backend: Backend) -> io::Result<Arc<BlockChainDB>> {
 	let path = Path::new(client_path);
+	let db_config = db_config(path, cache_config, compaction);
+
+	open_database(client_path, &db_config, backend.resolve(path))
+}
+
+pub fn open_database(client_path: &str, config: &DatabaseConfig, backend: Backend) -> io::Result<Arc<BlockChainDB>> {
+	open_app_db(Path::new(client_path), open_key_value(client_path, config, backend)?)
+}
+
+/// Copy the main DB at `client_path` to another backend and replace it with the copy.
+pub fn migrate_backend(client_path: &Path, cache_config: &CacheConfig, compaction: &DatabaseCompactionProfile, to: Backend) -> Result<(), String> {
+	let from = Backend::detect(client_path)
+		.ok_or_else(|| format!("No database found at {}", client_path.display()))?;
+	if from == to {
+		return Err(format!("Database at {} already uses the {} backend", client_path.display(), to));
+	}
+
+	let db_config = db_config(client_path, cache_config, compaction);
+	let migrated_path = sibling_path(client_path, "migrated");
+	if migrated_path.exists() {
+		fs::remove_dir_all(&migrated_path).map_err(|e| format!("Error removing incomplete migration: {}", e))?;
+	}
+
+	info!("Copying database from {} to {}", from, to);
+	{
+		let source = open_key_value(&client_path.to_string_lossy(), &db_config, from)
+			.map_err(|e| format!("Failed to open database: {:?}", e))?;
+		let target = open_key_value(&migrated_path.to_string_lossy(), &db_config, to)
+			.map_err(|e| format!("Failed to create database: {:?}", e))?;
+
+		let mut copied = 0usize;
+		for col in iter::once(None).chain((0..NUM_COLUMNS.unwrap_or(0)).map(Some)) {
+			let mut transaction = DBTransaction::new();
+			let mut batch_size = 0;
+
+			for (key, value) in source.iter(col) {
+				batch_size += key.len() + value.len();
+				transaction.put(col, &key, &value);
+				copied += 1;
+
+				if batch_size >= BACKEND_MIGRATION_BATCH_SIZE {
+					target.write(mem::replace(&mut transaction, DBTransaction::new()))
+						.map_err(|e| format!("Failed to write database: {}", e))?;
+					batch_size = 0;
+					info!("Copied {} entries", copied);
+				}
+			}
+
+			target.write(transaction).map_err(|e| format!("Failed to write database: {}", e))?;
+		}
+
+		info!("Copied {} entries", copied);
+	}
+
+	// blooms are stored in their own files, independent of the backend. they are copied,
+	// so that the database stays complete until it is replaced by the migrated one.
+	for blooms in &["blooms", "trace_blooms"] {
+		let path = client_path.join(blooms);
+		if path.exists() {
+			copy_dir(&path, &migrated_path.join(blooms)).map_err(|e| format!("Error copying {}: {}", blooms, e))?;
+		}
+	}
+
+	let old_path = sibling_path(client_path, "old");
+	fs::rename(client_path, &old_path).map_err(|e| format!("Error replacing database: {}", e))?;
+	fs::rename(&migrated_path, client_path).map_err(|e| format!("Error replacing database: {}", e))?;
+	fs::remove_dir_all(&old_path).map_err(|e| format!("Error removing old database: {}", e))?;
+
+	Ok(())
+}
 
-	let db_config = DatabaseConfig {
+fn db_config(path: &Path, cache_config: &CacheConfig, compaction: &DatabaseCompactionProfile) -> DatabaseConfig {
+	DatabaseConfig {
 		memory_budget: Some(cache_config.blockchain() as usize * 1024 * 1024),
 		compaction: helpers::compaction_profile(&compaction, path),
 		.. DatabaseConfig::with_columns(NUM_COLUMNS)
+	}
+}
+
+fn open_key_value(path: &str, config: &DatabaseConfig, backend: Backend) -> io::Result<Arc<KeyValueDB>> {
+	let db: Arc<KeyValueDB> = match backend {
+		Backend::RocksDB => Arc::new(Database::open(config, path)?),
+		Backend::LogDB => Arc::new(kvdb_logdb::Database::open(&kvdb_logdb::DatabaseConfig::with_columns(config.columns), path)?),
 	};
 
-	open_database(client_path, &db_config)
+	Ok(db)
 }
 
-pub fn open_database(client_path: &str, config: &DatabaseConfig) -> io::Result<Arc<BlockChainDB>> {
-	let path = Path::new(client_path);
-
-	let blooms_path = path.join("blooms");
-	let trace_blooms_path = path.join("trace_blooms");
-	fs::create_dir_all(&blooms_path)?;
-	fs::create_dir_all(&trace_blooms_path)?;
+/// Copies the files of a directory without subdirectories.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+	fs::create_dir_all(to)?;
+	for entry in fs::read_dir(from)? {
+		let entry = entry?;
+		fs::copy(entry.path(), to.join(entry.file_name()))?;
+	}
 
-	let db = AppDB {
-		key_value: Arc::new(Database::open(&config, client_path)?),
-		blooms: blooms_db::Database::open(blooms_path)?,
-		trace_blooms: blooms_db::Database::open(trace_blooms_path)?,
-	};
+	Ok(())
+}
 
-	Ok(Arc::new(db))
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.as_os_str().to_owned();
+	name.push(".");
+	name.push(suffix);
+	PathBuf::from(name)
 }
@@ -40,6 +40,7 @@ pub struct ExportHsyncCmd {
 	pub spec: SpecType,
 	pub pruning: Pruning,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 }
 
 pub fn execute(cmd: ExportHsyncCmd) -> Result<String, String> {
@@ -88,7 +89,8 @@ pub fn execute(cmd: ExportHsyncCmd) -> Result<String, String> {
 	// initialize database.
 	let db = db::open_db(&db_dirs.client_path(algorithm).to_str().expect("DB path could not be converted to string."),
 						 &cmd.cache_config,
-						 &cmd.compaction).map_err(|e| format!("Failed to open database {:?}", e))?;
+						 &cmd.compaction,
+						 cmd.db_backend).map_err(|e| format!("Failed to open database {:?}", e))?;
 
 	let service = light_client::Service::start(config, &spec, UnavailableDataFetcher, db, cache)
 		.map_err(|e| format!("Error starting light client: {}", e))?;
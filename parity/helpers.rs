@@ -16,6 +16,7 @@
 
 use std::io;
 use std::io::{Write, BufReader, BufRead};
+use std::collections::BTreeMap;
 use std::time::Duration;
 use std::fs::File;
 use ethereum_types::{U256, clean_0x, Address};
@@ -27,10 +28,11 @@ use cache::CacheConfig;
 use dir::DatabaseDirectories;
 use dir::helpers::replace_home;
 use upgrade::{upgrade, upgrade_data_paths};
-use sync::{validate_node_url, self};
+use sync::{validate_node_url, validate_node_list_url, self};
 use db::migrate;
 use path;
 use ethkey::Password;
+use rustc_hex::FromHex;
 
 pub fn to_duration(s: &str) -> Result<Duration, String> {
 	to_seconds(s).map(Duration::from_secs)
@@ -167,9 +169,11 @@ pub fn parity_ipc_path(base: &str, path: &str, shift: u16) -> String {
 pub fn to_bootnodes(bootnodes: &Option<String>) -> Result<Vec<String>, String> {
 	match *bootnodes {
 		Some(ref x) if !x.is_empty() => x.split(',').map(|s| {
-			match validate_node_url(s).map(Into::into) {
+			let error = if s.starts_with("enrtree://") { validate_node_list_url(s) } else { validate_node_url(s) };
+			match error.map(Into::into) {
 				None => Ok(s.to_owned()),
 				Some(sync::ErrorKind::AddressResolve(_)) => Err(format!("Failed to resolve hostname of a boot node: {}", s)),
+				Some(sync::ErrorKind::InvalidNodeList(reason)) => Err(format!("Invalid node list given as a boot node: {}", reason)),
 				Some(_) => Err(format!("Invalid node address format given for a boot node: {}", s)),
 			}
 		}).collect(),
@@ -178,6 +182,28 @@ pub fn to_bootnodes(bootnodes: &Option<String>) -> Result<Vec<String>, String> {
 	}
 }
 
+/// Parses comma-delimited KEY=VALUE node record entries. Values prefixed with 0x are hex-decoded,
+/// any other value is taken as UTF-8 text.
+pub fn to_node_record_entries(entries: &Option<String>) -> Result<BTreeMap<String, Vec<u8>>, String> {
+	match *entries {
+		Some(ref x) if !x.is_empty() => x.split(',').map(|entry| {
+			let mut parts = entry.splitn(2, '=');
+			match (parts.next(), parts.next()) {
+				(Some(key), Some(value)) if !key.is_empty() => {
+					let value = if value.starts_with("0x") {
+						value[2..].from_hex().map_err(|e| format!("Invalid hex value of node record entry {}: {}", key, e))?
+					} else {
+						value.as_bytes().to_vec()
+					};
+					Ok((key.to_owned(), value))
+				},
+				_ => Err(format!("Invalid node record entry given: {}. Expected KEY=VALUE.", entry)),
+			}
+		}).collect(),
+		_ => Ok(BTreeMap::new()),
+	}
+}
+
 #[cfg(test)]
 pub fn default_network_config() -> ::sync::NetworkConfiguration {
 	use sync::{NetworkConfiguration};
@@ -191,6 +217,7 @@ pub fn default_network_config() -> ::sync::NetworkConfiguration {
 		nat_enabled: true,
 		discovery_enabled: true,
 		boot_nodes: Vec::new(),
+		dns_zone_file: None,
 		use_secret: None,
 		max_peers: 50,
 		min_peers: 25,
@@ -200,6 +227,12 @@ pub fn default_network_config() -> ::sync::NetworkConfiguration {
 		reserved_nodes: Vec::new(),
 		allow_non_reserved: true,
 		client_version: ::parity_version::version(),
+		node_record_entries: Default::default(),
+		node_record_filter: Default::default(),
+		max_upload_rate: None,
+		max_download_rate: None,
+		max_peer_upload_rate: None,
+		max_peer_download_rate: None,
 	}
 }
 
@@ -330,7 +363,7 @@ mod tests {
 	use ethcore::client::{Mode, BlockId};
 	use ethcore::miner::PendingSet;
 	use ethkey::Password;
-	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes, password_from_file};
+	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes, to_node_record_entries, password_from_file};
 
 	#[test]
 	fn test_to_duration() {
@@ -469,5 +502,24 @@ but the first password is trimmed
 		assert_eq!(to_bootnodes(&None), Ok(vec![]));
 		assert_eq!(to_bootnodes(&Some(one_bootnode.into())), Ok(vec![one_bootnode.into()]));
 		assert_eq!(to_bootnodes(&Some(two_bootnodes.into())), Ok(vec![one_bootnode.into(), one_bootnode.into()]));
+
+		let node_list = "enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2@nodes.example.org";
+		assert_eq!(to_bootnodes(&Some(format!("{},{}", node_list, one_bootnode))), Ok(vec![node_list.into(), one_bootnode.into()]));
+		assert!(to_bootnodes(&Some("enrtree://nodes.example.org".into())).is_err());
+	}
+
+	#[test]
+	fn test_to_node_record_entries() {
+		assert_eq!(to_node_record_entries(&None), Ok(Default::default()));
+		assert_eq!(to_node_record_entries(&Some("".into())), Ok(Default::default()));
+
+		let entries = to_node_record_entries(&Some("chain=consortium,eth=0xfc64ec04".into())).unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries["chain"], b"consortium".to_vec());
+		assert_eq!(entries["eth"], vec![0xfc, 0x64, 0xec, 0x04]);
+
+		assert!(to_node_record_entries(&Some("chain".into())).is_err());
+		assert!(to_node_record_entries(&Some("=consortium".into())).is_err());
+		assert!(to_node_record_entries(&Some("eth=0xzz".into())).is_err());
 	}
 }
@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 extern crate ansi_term;
-use self::ansi_term::Colour::{White, Yellow, Green, Cyan, Blue};
+use self::ansi_term::Colour::{White, Yellow, Green, Cyan, Blue, Purple};
 use self::ansi_term::{Colour, Style};
 
 use std::sync::{Arc};
@@ -30,7 +30,7 @@ use ethcore::client::{
 use ethcore::header::BlockNumber;
 use ethcore::snapshot::{RestorationStatus, SnapshotService as SS};
 use ethcore::snapshot::service::Service as SnapshotService;
-use sync::{LightSyncProvider, LightSync, SyncProvider, ManageNetwork};
+use sync::{LightSyncProvider, LightSync, SyncProvider, ManageNetwork, PrivateTxProtocol, PrivateTxStats};
 use io::{TimerToken, IoContext, IoHandler};
 use light::Cache as LightDataCache;
 use light::client::{LightChainClient, LightChainNotify};
@@ -102,6 +102,7 @@ pub struct Report {
 	queue_info: BlockQueueInfo,
 	cache_sizes: CacheSizes,
 	sync_info: Option<SyncInfo>,
+	private_tx_stats: Option<PrivateTxStats>,
 }
 
 /// Something which can provide data to the informant.
@@ -121,6 +122,7 @@ pub struct FullNodeInformantData {
 	pub client: Arc<Client>,
 	pub sync: Option<Arc<SyncProvider>>,
 	pub net: Option<Arc<ManageNetwork>>,
+	pub private_tx: Option<Arc<PrivateTxProtocol>>,
 }
 
 impl InformantData for FullNodeInformantData {
@@ -169,6 +171,7 @@ impl InformantData for FullNodeInformantData {
 			queue_info,
 			cache_sizes,
 			sync_info,
+			private_tx_stats: self.private_tx.as_ref().map(|private_tx| private_tx.stats()),
 		}
 	}
 }
@@ -211,6 +214,7 @@ impl InformantData for LightNodeInformantData {
 			queue_info,
 			cache_sizes,
 			sync_info,
+			private_tx_stats: None,
 		}
 	}
 }
@@ -275,6 +279,7 @@ impl<T: InformantData> Informant<T> {
 			queue_info,
 			cache_sizes,
 			sync_info,
+			private_tx_stats,
 			..
 		} = full_report;
 
@@ -296,7 +301,7 @@ impl<T: InformantData> Informant<T> {
 			false => t,
 		};
 
-		info!(target: "import", "{}  {}  {}  {}",
+		info!(target: "import", "{}  {}  {}  {}  {}",
 			match importing {
 				true => match snapshot_sync {
 					false => format!("Syncing {} {}  {}  {}+{} Qed",
@@ -362,6 +367,16 @@ impl<T: InformantData> Informant<T> {
 				),
 				_ => String::new(),
 			},
+			match private_tx_stats {
+				Some(ref stats) => format!(
+					"Private txs: {} sent, {} undelivered, {} imported, {} rejected",
+					paint(Purple.bold(), format!("{}", stats.sent)),
+					paint(Purple.bold(), format!("{}", stats.undelivered + stats.send_failures)),
+					paint(Purple.bold(), format!("{}", stats.imported)),
+					paint(Purple.bold(), format!("{}", stats.rejected)),
+				),
+				_ => String::new(),
+			},
 		);
 	}
 }
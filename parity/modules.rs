@@ -21,7 +21,7 @@ use sync::{self, AttachedProtocol, SyncConfig, NetworkConfiguration, Params, Con
 use ethcore::snapshot::SnapshotService;
 use light::Provider;
 
-pub use sync::{EthSync, SyncProvider, ManageNetwork, PrivateTxHandler};
+pub use sync::{EthSync, SyncProvider, ManageNetwork};
 pub use ethcore::client::ChainNotify;
 use ethcore_logger::Config as LogConfig;
 
@@ -32,7 +32,6 @@ pub fn sync(
 	net_cfg: NetworkConfiguration,
 	client: Arc<BlockChainClient>,
 	snapshot_service: Arc<SnapshotService>,
-	private_tx_handler: Arc<PrivateTxHandler>,
 	provider: Arc<Provider>,
 	_log_settings: &LogConfig,
 	attached_protos: Vec<AttachedProtocol>,
@@ -43,7 +42,6 @@ pub fn sync(
 		chain: client,
 		provider: provider,
 		snapshot_service: snapshot_service,
-		private_tx_handler,
 		network_config: net_cfg,
 		attached_protos: attached_protos,
 	},
@@ -31,7 +31,7 @@ use ethcore::verification::queue::VerifierSettings;
 use ethcore_logger::{Config as LogConfig, RotatingLogger};
 use ethcore_service::ClientService;
 use ethereum_types::Address;
-use sync::{self, SyncConfig};
+use sync::{self, SyncConfig, AttachedProtocol, PrivateTxProtocol};
 use miner::work_notify::WorkPoster;
 use futures::IntoFuture;
 use futures_cpupool::CpuPool;
@@ -106,6 +106,7 @@ pub struct RunCmd {
 	pub network_id: Option<u64>,
 	pub warp_sync: bool,
 	pub warp_barrier: Option<u64>,
+	pub state_sync: bool,
 	pub acc_conf: AccountsConfig,
 	pub gas_pricer_conf: GasPricerConfig,
 	pub miner_extras: MinerExtras,
@@ -113,7 +114,11 @@ pub struct RunCmd {
 	pub mode: Option<Mode>,
 	pub tracing: Switch,
 	pub fat_db: Switch,
+	pub state_history: bool,
+	pub tracing_address_index: bool,
+	pub log_index: bool,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 	pub vm_type: VMType,
 	pub geth_compatibility: bool,
 	pub net_settings: NetworkSettings,
@@ -231,7 +236,8 @@ fn execute_light_impl(cmd: RunCmd, logger: Arc<RotatingLogger>) -> Result<Runnin
 	// initialize database.
 	let db = db::open_db(&db_dirs.client_path(algorithm).to_str().expect("DB path could not be converted to string."),
 						 &cmd.cache_config,
-						 &cmd.compaction).map_err(|e| format!("Failed to open database {:?}", e))?;
+						 &cmd.compaction,
+						 cmd.db_backend).map_err(|e| format!("Failed to open database {:?}", e))?;
 
 	let service = light_client::Service::start(config, &spec, fetch, db, cache.clone())
 		.map_err(|e| format!("Error starting light client: {}", e))?;
@@ -449,7 +455,12 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		sync_config.subprotocol_name.clone_from_slice(spec.subprotocol_name().as_bytes());
 	}
 
+	if cmd.tracing_address_index && !tracing {
+		warn!("Warning: Trace address index is disabled because tracing is turned off.");
+	}
+
 	sync_config.fork_block = spec.fork_block();
+	sync_config.hard_forks = spec.hard_forks.iter().cloned().collect();
 	let mut warp_sync = spec.engine.supports_warp() && cmd.warp_sync;
 	if warp_sync {
 		// Logging is not initialized yet, so we print directly to stderr
@@ -469,6 +480,20 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		(true, _) => sync::WarpSync::Enabled,
 		_ => sync::WarpSync::Disabled,
 	};
+	let mut state_sync = cmd.state_sync;
+	if state_sync {
+		if fat_db {
+			warn!("Warning: State Sync is disabled because Fat DB is turned on.");
+			state_sync = false;
+		} else if tracing {
+			warn!("Warning: State Sync is disabled because tracing is turned on.");
+			state_sync = false;
+		} else if algorithm != Algorithm::OverlayRecent {
+			warn!("Warning: State Sync is disabled because of non-default pruning mode.");
+			state_sync = false;
+		}
+	}
+	sync_config.state_sync = state_sync;
 	sync_config.download_old_blocks = cmd.download_old_blocks;
 	sync_config.serve_light = cmd.serve_light;
 
@@ -546,6 +571,9 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	client_config.queue.verifier_settings = cmd.verifier_settings;
 	client_config.transaction_verification_queue_size = ::std::cmp::max(2048, txpool_size / 4);
 	client_config.snapshot = cmd.snapshot_conf.clone();
+	client_config.state_history = cmd.state_history;
+	client_config.tracing.address_index = cmd.tracing_address_index;
+	client_config.blockchain.log_index = cmd.log_index;
 
 	// set up bootnodes
 	let mut net_conf = cmd.net_conf;
@@ -556,7 +584,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	// set network path.
 	net_conf.net_config_path = Some(db_dirs.network_path().to_string_lossy().into_owned());
 
-	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config);
+	let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config, cmd.db_backend);
 	let client_db = restoration_db_handler.open(&client_path)
 		.map_err(|e| format!("Failed to open database {:?}", e))?;
 
@@ -647,13 +675,25 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		None
 	};
 
+	// private transactions are only exchanged with validators over a dedicated protocol
+	let private_tx_protocol = if cmd.private_tx_enabled {
+		let protocol = Arc::new(PrivateTxProtocol::new(private_tx_service.clone()));
+		attached_protos.push(AttachedProtocol {
+			handler: protocol.clone(),
+			protocol_id: sync::PRIVATE_TX_PROTOCOL_ID,
+			versions: &[sync::PRIVATE_TX_PROTOCOL_VERSION_1],
+		});
+		Some(protocol)
+	} else {
+		None
+	};
+
 	// create sync object
 	let (sync_provider, manage_network, chain_notify) = modules::sync(
 		sync_config,
 		net_conf.clone().into(),
 		client.clone(),
 		snapshot_service.clone(),
-		private_tx_service.clone(),
 		client.clone(),
 		&cmd.logger_config,
 		attached_protos,
@@ -664,11 +704,12 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 
 	// provider not added to a notification center is effectively disabled
 	// TODO [debris] refactor it later on
-	if cmd.private_tx_enabled {
+	if let Some(ref private_tx_protocol) = private_tx_protocol {
 		service.add_notify(private_tx_provider.clone());
+		private_tx_protocol.set_network(Arc::downgrade(&manage_network));
 		// TODO [ToDr] PrivateTX should use separate notifications
 		// re-using ChainNotify for this is a bit abusive.
-		private_tx_provider.add_notify(chain_notify.clone());
+		private_tx_provider.add_notify(private_tx_protocol.clone());
 	}
 
 	// start network
@@ -768,6 +809,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 			client: service.client(),
 			sync: Some(sync_provider.clone()),
 			net: Some(manage_network.clone()),
+			private_tx: private_tx_protocol.clone(),
 		},
 		Some(snapshot_service.clone()),
 		Some(rpc_stats.clone()),
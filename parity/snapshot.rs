@@ -16,13 +16,20 @@
 
 //! Snapshot and restoration commands.
 
+use std::collections::HashSet;
+use std::fs;
 use std::time::Duration;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use bytes::Bytes;
 use hash::keccak;
+use ethereum_types::H256;
+use parking_lot::Mutex;
+use journaldb::Algorithm;
 use ethcore::account_provider::AccountProvider;
-use ethcore::snapshot::{Progress, RestorationStatus, SnapshotConfiguration, SnapshotService as SS};
+use ethcore::snapshot::{self, Progress, RestorationStatus, SnapshotConfiguration, SnapshotService as SS};
 use ethcore::snapshot::io::{SnapshotReader, PackedReader, PackedWriter};
 use ethcore::snapshot::service::Service as SnapshotService;
 use ethcore::client::{Mode, DatabaseCompactionProfile, VMType};
@@ -44,7 +51,9 @@ pub enum Kind {
 	/// Take a snapshot.
 	Take,
 	/// Restore a snapshot.
-	Restore
+	Restore,
+	/// Verify a snapshot file.
+	Verify,
 }
 
 /// Command for snapshot creation or restoration.
@@ -59,6 +68,7 @@ pub struct SnapshotCommand {
 	pub tracing: Switch,
 	pub fat_db: Switch,
 	pub compaction: DatabaseCompactionProfile,
+	pub db_backend: db::Backend,
 	pub file_path: Option<String>,
 	pub kind: Kind,
 	pub block_at: BlockId,
@@ -66,8 +76,8 @@ pub struct SnapshotCommand {
 }
 
 // helper for reading chunks from arbitrary reader and feeding them into the
-// service.
-fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R, recover: bool) -> Result<(), String> {
+// service. state chunks are fed from `threads` threads, which rebuild them concurrently.
+fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R, recover: bool, threads: usize) -> Result<(), String> {
 	let manifest = reader.manifest();
 
 	info!("Restoring to block #{} (0x{:?})", manifest.block_number, manifest.block_hash);
@@ -78,6 +88,13 @@ fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R,
 
 	let (num_state, num_blocks) = (manifest.state_hashes.len(), manifest.block_hashes.len());
 
+	// chunks fed before an interrupted restoration of the same snapshot are
+	// imported again on initialization and don't need to be read another time.
+	let completed: HashSet<H256> = snapshot.completed_chunks().unwrap_or_default().into_iter().collect();
+	if !completed.is_empty() {
+		info!("Resuming restoration with {} of {} chunks already restored.", completed.len(), num_state + num_blocks);
+	}
+
 	let informant_handle = snapshot.clone();
 	::std::thread::spawn(move || {
  		while let RestorationStatus::Ongoing { state_chunks_done, block_chunks_done, .. } = informant_handle.status() {
@@ -88,7 +105,22 @@ fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R,
  	});
 
  	info!("Restoring state");
- 	for &state_hash in &manifest.state_hashes {
+	let (chunk_tx, chunk_rx) = mpsc::sync_channel::<(H256, Bytes)>(threads);
+	let chunk_rx = Arc::new(Mutex::new(chunk_rx));
+	let state_workers: Vec<_> = (0..threads).map(|_| {
+		let (snapshot, chunk_rx) = (snapshot.clone(), chunk_rx.clone());
+		::std::thread::spawn(move || {
+			loop {
+				let next = chunk_rx.lock().recv();
+				match next {
+					Ok((hash, chunk)) => snapshot.feed_state_chunk(hash, &chunk),
+					Err(_) => break,
+				}
+			}
+		})
+	}).collect();
+
+ 	for &state_hash in manifest.state_hashes.iter().filter(|hash| !completed.contains(hash)) {
  		if snapshot.status() == RestorationStatus::Failed {
  			return Err("Restoration failed".into());
  		}
@@ -101,11 +133,16 @@ fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R,
 			return Err(format!("Mismatched chunk hash. Expected {:?}, got {:?}", state_hash, hash));
 		}
 
- 		snapshot.feed_state_chunk(state_hash, &chunk);
+		chunk_tx.send((state_hash, chunk)).map_err(|_| "State restoration threads stopped unexpectedly.".to_owned())?;
  	}
 
+	drop(chunk_tx);
+	for worker in state_workers {
+		worker.join().map_err(|_| "failed to join state restoration thread")?;
+	}
+
 	info!("Restoring blocks");
-	for &block_hash in &manifest.block_hashes {
+	for &block_hash in manifest.block_hashes.iter().filter(|hash| !completed.contains(hash)) {
 		if snapshot.status() == RestorationStatus::Failed {
 			return Err("Restoration failed".into());
 		}
@@ -183,7 +220,7 @@ impl SnapshotCommand {
 
 		client_config.snapshot = self.snapshot_conf;
 
-		let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config);
+		let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config, self.db_backend);
 		let client_db = restoration_db_handler.open(&client_path)
 			.map_err(|e| format!("Failed to open database {:?}", e))?;
 
@@ -207,6 +244,7 @@ impl SnapshotCommand {
 	/// restore from a snapshot
 	pub fn restore(self) -> Result<(), String> {
 		let file = self.file_path.clone();
+		let threads = ::std::cmp::max(1, self.snapshot_conf.processing_threads);
 		let service = self.start_service()?;
 
 		warn!("Snapshot restoration is experimental and the format may be subject to change.");
@@ -222,14 +260,14 @@ impl SnapshotCommand {
 				.and_then(|x| x.ok_or("Snapshot file has invalid format.".into()));
 
 			let reader = reader?;
-			restore_using(snapshot, &reader, true)?;
+			restore_using(snapshot, &reader, true, threads)?;
 		} else {
 			info!("Attempting to restore from local snapshot.");
 
 			// attempting restoration with recovery will lead to deadlock
 			// as we currently hold a read lock on the service's reader.
 			match *snapshot.reader() {
-				Some(ref reader) => restore_using(snapshot.clone(), reader, false)?,
+				Some(ref reader) => restore_using(snapshot.clone(), reader, false, threads)?,
 				None => return Err("No local snapshot found.".into()),
 			}
 		}
@@ -237,6 +275,60 @@ impl SnapshotCommand {
 		Ok(())
 	}
 
+	/// Verify a snapshot file by restoring it into a temporary database.
+	pub fn verify(self) -> Result<(), String> {
+		let file = self.file_path.clone().ok_or("No file path provided.".to_owned())?;
+
+		// load spec file
+		let spec = self.spec.spec(&self.dirs.cache)?;
+
+		// the temporary database is kept apart from the client database.
+		let db_dirs = self.dirs.database(spec.genesis_header().hash(), None, spec.data_dir.clone());
+		let verification_path = db_dirs.snapshot_path().join("verification");
+		let _ = fs::remove_dir_all(&verification_path);
+
+		info!("Verifying snapshot at '{}'", file);
+
+		let reader = PackedReader::new(Path::new(&file))
+			.map_err(|e| format!("Couldn't open snapshot file: {}", e))
+			.and_then(|x| x.ok_or("Snapshot file has invalid format.".into()))?;
+
+		let manifest = reader.manifest().clone();
+		info!("Snapshot of block #{} (0x{:?}) with {} state chunks and {} block chunks",
+			manifest.block_number, manifest.block_hash, manifest.state_hashes.len(), manifest.block_hashes.len());
+
+		let db = db::open_db(&verification_path.to_str().expect("DB path could not be converted to string."), &self.cache_config, &self.compaction, self.db_backend)
+			.map_err(|e| format!("Failed to open verification database: {:?}", e))?;
+
+		let num_chunks = manifest.state_hashes.len() + manifest.block_hashes.len();
+		let chunks_done = Arc::new(AtomicUsize::new(0));
+		let informant_handle = chunks_done.clone();
+		::std::thread::spawn(move || {
+			loop {
+				::std::thread::sleep(Duration::from_secs(5));
+				let done = informant_handle.load(Ordering::SeqCst);
+				if done >= num_chunks { break }
+				info!("Verified {}/{} chunks.", done, num_chunks);
+			}
+		});
+
+		let res = snapshot::verify_snapshot(
+			&reader,
+			&*spec.engine,
+			&spec.genesis_block(),
+			Algorithm::Archive,
+			db,
+			&*chunks_done,
+		);
+		chunks_done.store(num_chunks, Ordering::SeqCst);
+		let _ = fs::remove_dir_all(&verification_path);
+
+		res.map_err(|e| format!("Snapshot verification failed: {}", e))?;
+		info!("Snapshot is valid.");
+
+		Ok(())
+	}
+
 	/// Take a snapshot from the head of the chain.
 	pub fn take_snapshot(self) -> Result<(), String> {
 		let file_path = self.file_path.clone().ok_or("No file path provided.".to_owned())?;
@@ -267,7 +359,12 @@ impl SnapshotCommand {
 			}
  		});
 
-		if let Err(e) = service.client().take_snapshot(writer, block_at, &*progress) {
+		// chunks of state parts which didn't change are taken from the local snapshot.
+		let snapshot = service.snapshot_service();
+		let previous = snapshot.reader();
+		let previous = previous.as_ref().map(|reader| reader as &(SnapshotReader + Sync));
+
+		if let Err(e) = service.client().take_snapshot(writer, block_at, &*progress, previous) {
 			let _ = ::std::fs::remove_file(&file_path);
 			return Err(format!("Encountered fatal error while creating snapshot: {}", e));
 		}
@@ -286,7 +383,107 @@ pub fn execute(cmd: SnapshotCommand) -> Result<String, String> {
 	match cmd.kind {
 		Kind::Take => cmd.take_snapshot()?,
 		Kind::Restore => cmd.restore()?,
+		Kind::Verify => cmd.verify()?,
 	}
 
 	Ok(String::new())
 }
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use std::io;
+	use std::sync::Arc;
+
+	use bytes::Bytes;
+	use ethereum_types::H256;
+	use parking_lot::Mutex;
+	use tempdir::TempDir;
+	use ethcore::client::{BlockInfo, ChainInfo, Client, ClientConfig};
+	use ethcore::ids::BlockId;
+	use ethcore::miner::Miner;
+	use ethcore::snapshot::{ManifestData, Progress, SnapshotService as SS};
+	use ethcore::snapshot::io::{LooseReader, LooseWriter, SnapshotReader, StatePart};
+	use ethcore::snapshot::service::{Service, ServiceParams};
+	use ethcore::spec::Spec;
+	use ethcore::test_helpers::generate_dummy_client_with_spec_and_data;
+	use io::IoChannel;
+	use db::{self, Backend};
+	use super::restore_using;
+
+	// reader recording the chunks read from it.
+	struct RecordingReader<'a> {
+		inner: &'a SnapshotReader,
+		read: Mutex<Vec<H256>>,
+	}
+
+	impl<'a> SnapshotReader for RecordingReader<'a> {
+		fn manifest(&self) -> &ManifestData { self.inner.manifest() }
+
+		fn chunk(&self, hash: H256) -> io::Result<Bytes> {
+			self.read.lock().push(hash);
+			self.inner.chunk(hash)
+		}
+
+		fn state_parts(&self) -> &[StatePart] { self.inner.state_parts() }
+	}
+
+	#[test]
+	fn resumes_interrupted_restoration() {
+		let tempdir = TempDir::new("").unwrap();
+		let spec = Spec::new_null();
+
+		let client = generate_dummy_client_with_spec_and_data(Spec::new_null, 20, 5, &[1.into(), 2.into()]);
+		let snapshot_path = tempdir.path().join("taken");
+		let writer = LooseWriter::new(snapshot_path.clone()).unwrap();
+		client.take_snapshot(writer, BlockId::Number(20), &Progress::default(), None).unwrap();
+		let reader = LooseReader::new(snapshot_path).unwrap();
+		let manifest = reader.manifest().clone();
+
+		let client_path = tempdir.path().join("client");
+		let client_config = ClientConfig::default();
+		let pruning = client_config.pruning;
+		let restoration_db_handler = db::restoration_db_handler(&client_path, &client_config, Backend::default());
+		let client_db = restoration_db_handler.open(&client_path).unwrap();
+		let restored = Client::new(
+			client_config,
+			&spec,
+			client_db,
+			Arc::new(Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+
+		let service = Arc::new(Service::new(ServiceParams {
+			engine: spec.engine.clone(),
+			genesis_block: spec.genesis_block(),
+			restoration_db_handler: restoration_db_handler,
+			pruning: pruning,
+			channel: IoChannel::disconnected(),
+			snapshot_root: tempdir.path().join("snapshot"),
+			db_restore: restored.clone(),
+		}).unwrap());
+
+		// feed about half of the state chunks and stop, as if the node was shut down.
+		let fed: Vec<H256> = manifest.state_hashes[..(manifest.state_hashes.len() + 1) / 2].to_vec();
+		service.init_restore(manifest.clone(), true).unwrap();
+		for &hash in &fed {
+			service.feed_state_chunk(hash, &reader.chunk(hash).unwrap());
+		}
+		service.abort_restore();
+
+		let recording = RecordingReader { inner: &reader, read: Mutex::new(Vec::new()) };
+		restore_using(service.clone(), &recording, true, 2).unwrap();
+
+		let read = recording.read.lock().clone();
+		let unique: HashSet<H256> = read.iter().cloned().collect();
+		assert_eq!(unique.len(), read.len());
+		for hash in manifest.state_hashes.iter().chain(&manifest.block_hashes) {
+			assert_eq!(unique.contains(hash), !fed.contains(hash));
+		}
+
+		assert_eq!(restored.chain_info().best_block_hash, manifest.block_hash);
+		let header = restored.block_header(BlockId::Hash(manifest.block_hash)).unwrap();
+		assert_eq!(header.state_root(), manifest.state_root);
+		assert!(restored.state_at(BlockId::Hash(manifest.block_hash)).is_some());
+	}
+}
@@ -16,9 +16,11 @@
 
 use transaction::{Transaction, SignedTransaction, Action};
 
+use ethcore::client::StateOverride as EthStateOverride;
 use ethereum_types::U256;
 use jsonrpc_core::Error;
-use v1::helpers::CallRequest;
+use v1::helpers::{errors, CallRequest};
+use v1::types::StateOverride;
 
 pub fn sign_call(request: CallRequest) -> Result<SignedTransaction, Error> {
 	let max_gas = U256::from(50_000_000);
@@ -37,3 +39,14 @@ pub fn sign_call(request: CallRequest) -> Result<SignedTransaction, Error> {
 		data: request.data.unwrap_or_default(),
 	}.fake_sign(from))
 }
+
+pub fn state_override(overrides: StateOverride) -> Result<EthStateOverride, Error> {
+	overrides.into_iter()
+		.map(|(address, account)| {
+			if account.state.is_some() && account.state_diff.is_some() {
+				return Err(errors::invalid_params("Both `state` and `stateDiff` overrides given for account", address));
+			}
+			Ok((address.into(), account.into()))
+		})
+		.collect()
+}
@@ -19,6 +19,7 @@
 use std::cmp;
 use std::sync::Arc;
 
+use bytes::Bytes;
 use light::on_demand::error::Error as OnDemandError;
 use ethcore::basic_account::BasicAccount;
 use ethcore::encoded;
@@ -42,7 +43,7 @@ use light::request::Field;
 
 use sync::LightSync;
 use ethereum_types::{U256, Address};
-use hash::H256;
+use hash::{H256, KECCAK_EMPTY, KECCAK_NULL_RLP};
 use parking_lot::Mutex;
 use fastmap::H256FastMap;
 use transaction::{Action, Transaction as EthTransaction, SignedTransaction, LocalizedTransaction};
@@ -190,6 +191,56 @@ impl LightFetch {
 		}))
 	}
 
+	/// Helper for getting an account along with its merkle proof and the proofs
+	/// of the given storage keys at a given block.
+	/// Yields the account proof, the account (with the engine's starting nonce if it doesn't
+	/// exist) and a `(key, proof, value)` triple for every requested storage key.
+	pub fn account_proof(&self, address: Address, keys: Vec<H256>, id: BlockId)
+		-> impl Future<Item = (Vec<Bytes>, BasicAccount, Vec<(H256, Vec<Bytes>, H256)>), Error = Error> + Send
+	{
+		let (fetcher, storage_fetcher) = (self.clone(), self.clone());
+		let engine = self.client.engine().clone();
+
+		self.header(id).and_then(move |header| {
+			let req = request::AccountProof { header: header.clone().into(), address: address };
+			fetcher.send_requests(vec![req.into()], |mut res| match res.pop() {
+				Some(OnDemandResponse::AccountProof(acc)) => acc,
+				_ => panic!(WRONG_RESPONSE_AMOUNT_TYPE_PROOF),
+			}).map(move |acc| (header, acc))
+		}).and_then(move |(header, (account_proof, account))| {
+			let account = account.unwrap_or_else(|| BasicAccount {
+				nonce: engine.account_start_nonce(header.number()),
+				balance: 0.into(),
+				storage_root: KECCAK_NULL_RLP,
+				code_hash: KECCAK_EMPTY,
+			});
+
+			// nothing to request from an empty storage trie, every key is absent.
+			if account.storage_root == KECCAK_NULL_RLP {
+				let storage = keys.into_iter().map(|key| (key, Vec::new(), H256::zero())).collect();
+				return Either::A(future::ok((account_proof, account, storage)));
+			}
+
+			// the storage proofs are verified against the storage root proven above.
+			let storage_root = account.storage_root;
+			let reqs = keys.iter().map(|key| request::Storage {
+				header: header.clone().into(),
+				address: address,
+				key: *key,
+				storage_root: storage_root,
+			}.into()).collect();
+
+			Either::B(storage_fetcher.send_requests(reqs, move |res| {
+				let storage = res.into_iter().zip(keys).map(|(res, key)| match res {
+					OnDemandResponse::Storage((proof, value)) => (key, proof, value),
+					_ => panic!(WRONG_RESPONSE_AMOUNT_TYPE_PROOF),
+				}).collect();
+
+				(account_proof, account, storage)
+			}))
+		})
+	}
+
 	/// Helper for getting proved execution.
 	pub fn proved_execution(&self, req: CallRequest, num: Trailing<BlockNumber>) -> impl Future<Item = ExecutionResult, Error = Error> + Send {
 		const DEFAULT_GAS_PRICE: u64 = 21_000;
@@ -18,12 +18,18 @@
 
 use std::sync::Arc;
 
-use ethcore::client::BlockChainClient;
+use ethcore::client::{BlockChainClient, BlockId, TransactionId, CallAnalytics};
+use ethereum_types::H256;
 use transaction::LocalizedTransaction;
 
 use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
+use v1::helpers::errors;
 use v1::traits::Debug;
-use v1::types::{Block, Bytes, RichBlock, BlockTransactions, Transaction};
+use v1::types::{
+	Block, Bytes, RichBlock, BlockTransactions, Transaction, BlockNumber, block_number_to_id,
+	DebugTraceOptions, DebugTracer, GethTrace, TransactionTrace, CallFrame, H256 as RpcH256,
+};
 
 /// Debug rpc implementation.
 pub struct DebugClient<C> {
@@ -39,6 +45,41 @@ impl<C> DebugClient<C> {
 	}
 }
 
+impl<C: BlockChainClient + 'static> DebugClient<C> {
+	fn trace_block(&self, block: BlockId, options: DebugTraceOptions) -> Result<Box<Iterator<Item = (H256, GethTrace)>>> {
+		Ok(match options.tracer {
+			None => Box::new(self.client.replay_block_transactions_with_struct_logs(block, options.struct_logger_config())
+				.map_err(errors::call)?
+				.map(|(hash, executed)| (hash, GethTrace::StructLogs(executed.into()))
+			)),
+			Some(DebugTracer::CallTracer) => {
+				let analytics = CallAnalytics {
+					transaction_tracing: true,
+					vm_tracing: false,
+					state_diffing: false,
+				};
+				Box::new(self.client.replay_block_transactions(block, analytics)
+					.map_err(errors::call)?
+					.map(|(hash, executed)| (hash, GethTrace::Call(CallFrame::from_executed(executed))))
+				)
+			},
+			Some(DebugTracer::PrestateTracer) => Box::new(self.client.replay_block_transactions_prestate(block)
+				.map_err(errors::call)?
+				.map(|(hash, prestate)| (hash, GethTrace::Prestate(prestate.into_iter().map(|(a, acc)| (a.into(), acc.into())).collect())))
+			),
+		})
+	}
+
+	fn trace_block_transactions(&self, block: BlockId, options: Trailing<DebugTraceOptions>) -> Result<Vec<TransactionTrace>> {
+		Ok(self.trace_block(block, options.unwrap_or_default())?
+			.map(|(hash, result)| TransactionTrace {
+				tx_hash: hash.into(),
+				result,
+			})
+			.collect())
+	}
+}
+
 impl<C: BlockChainClient + 'static> Debug for DebugClient<C> {
 	fn bad_blocks(&self) -> Result<Vec<RichBlock>> {
 		fn cast<O, T: Copy + Into<O>>(t: &T) -> O {
@@ -89,6 +130,43 @@ impl<C: BlockChainClient + 'static> Debug for DebugClient<C> {
 			}
 		}).collect())
 	}
+
+	fn trace_transaction(&self, hash: RpcH256, options: Trailing<DebugTraceOptions>) -> Result<GethTrace> {
+		let id = TransactionId::Hash(hash.into());
+		let options = options.unwrap_or_default();
+
+		// only the transaction itself is traced, the preceding ones are replayed without tracing.
+		Ok(match options.tracer {
+			None => GethTrace::StructLogs(self.client.replay_with_struct_logs(id, options.struct_logger_config())
+				.map_err(errors::call)?
+				.into()),
+			Some(DebugTracer::CallTracer) => {
+				let analytics = CallAnalytics {
+					transaction_tracing: true,
+					vm_tracing: false,
+					state_diffing: false,
+				};
+				GethTrace::Call(CallFrame::from_executed(self.client.replay(id, analytics).map_err(errors::call)?))
+			},
+			Some(DebugTracer::PrestateTracer) => GethTrace::Prestate(self.client.replay_prestate(id)
+				.map_err(errors::call)?
+				.into_iter()
+				.map(|(a, acc)| (a.into(), acc.into()))
+				.collect()),
+		})
+	}
+
+	fn trace_block_by_number(&self, num: BlockNumber, options: Trailing<DebugTraceOptions>) -> Result<Vec<TransactionTrace>> {
+		if num == BlockNumber::Pending {
+			return Err(errors::unsupported("Tracing the pending block is not supported", None));
+		}
+
+		self.trace_block_transactions(block_number_to_id(num), options)
+	}
+
+	fn trace_block_by_hash(&self, hash: RpcH256, options: Trailing<DebugTraceOptions>) -> Result<Vec<TransactionTrace>> {
+		self.trace_block_transactions(BlockId::Hash(hash.into()), options)
+	}
 }
 
 fn serialize<T: ::serde::Serialize>(t: &T) -> String {
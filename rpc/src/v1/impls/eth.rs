@@ -22,11 +22,12 @@ use std::sync::Arc;
 
 use rlp::Rlp;
 use ethereum_types::{U256, H256, Address};
+use hash::keccak;
 use parking_lot::Mutex;
 
 use ethash::{self, SeedHashCompute};
 use ethcore::account_provider::AccountProvider;
-use ethcore::client::{BlockChainClient, BlockId, TransactionId, UncleId, StateOrBlock, StateClient, StateInfo, Call, EngineInfo};
+use ethcore::client::{BlockChainClient, BlockId, TransactionId, UncleId, StateOrBlock, StateClient, StateInfo, Call, EngineInfo, ProvingBlockChainClient};
 use ethcore::filter::Filter as EthcoreFilter;
 use ethcore::header::{BlockNumber as EthBlockNumber};
 use ethcore::miner::{self, MinerService};
@@ -46,7 +47,7 @@ use v1::helpers::block_import::is_major_importing;
 use v1::traits::Eth;
 use v1::types::{
 	RichBlock, Block, BlockTransactions, BlockNumber, Bytes, SyncStatus, SyncInfo,
-	Transaction, CallRequest, Index, Filter, Log, Receipt, Work,
+	Transaction, CallRequest, Index, Filter, Log, Receipt, Work, EthAccount, StorageProof, StateOverride,
 	H64 as RpcH64, H256 as RpcH256, H160 as RpcH160, U256 as RpcU256, block_number_to_id,
 };
 use v1::metadata::Metadata;
@@ -452,7 +453,7 @@ fn check_known<C>(client: &C, number: BlockNumber) -> Result<()> where C: BlockC
 const MAX_QUEUE_SIZE_TO_MINE_ON: usize = 4;	// because uncles go back 6.
 
 impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<C, SN, S, M, EM> where
-	C: miner::BlockChainClient + BlockChainClient + StateClient<State=T> + Call<State=T> + EngineInfo + 'static,
+	C: miner::BlockChainClient + BlockChainClient + ProvingBlockChainClient + StateClient<State=T> + Call<State=T> + EngineInfo + 'static,
 	SN: SnapshotService + 'static,
 	S: SyncProvider + 'static,
 	M: MinerService<State=T> + 'static,
@@ -560,6 +561,42 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 		Box::new(future::done(res))
 	}
 
+	fn proof(&self, address: RpcH160, values: Vec<RpcH256>, num: Trailing<BlockNumber>) -> BoxFuture<EthAccount> {
+		let address: Address = RpcH160::into(address);
+		let address_hash = keccak(&address);
+
+		let id = match num.unwrap_or_default() {
+			BlockNumber::Pending => return Box::new(future::err(errors::unsupported("Proofs are not available for the pending block", None))),
+			number => {
+				try_bf!(check_known(&*self.client, number.clone()));
+				block_number_to_id(number)
+			}
+		};
+
+		let (account_proof, account) = try_bf!(self.client.prove_account(address_hash, id).ok_or(errors::state_pruned()));
+
+		let storage_proof = try_bf!(values.into_iter().map(|key| {
+			let key: H256 = RpcH256::into(key);
+			self.client.prove_storage(address_hash, keccak(&key), id)
+				.map(|(proof, value)| StorageProof {
+					key: U256::from(&*key).into(),
+					value: U256::from(&*value).into(),
+					proof: proof.into_iter().map(Bytes::new).collect(),
+				})
+				.ok_or(errors::state_pruned())
+		}).collect::<Result<Vec<_>>>());
+
+		Box::new(future::ok(EthAccount {
+			address: address.into(),
+			balance: account.balance.into(),
+			nonce: account.nonce.into(),
+			code_hash: account.code_hash.into(),
+			storage_hash: account.storage_root.into(),
+			account_proof: account_proof.into_iter().map(Bytes::new).collect(),
+			storage_proof: storage_proof,
+		}))
+	}
+
 	fn transaction_count(&self, address: RpcH160, num: Trailing<BlockNumber>) -> BoxFuture<RpcU256> {
 		let address: Address = RpcH160::into(address);
 
@@ -819,9 +856,10 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 		self.send_raw_transaction(raw)
 	}
 
-	fn call(&self, request: CallRequest, num: Trailing<BlockNumber>) -> BoxFuture<Bytes> {
+	fn call(&self, request: CallRequest, num: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> BoxFuture<Bytes> {
 		let request = CallRequest::into(request);
 		let signed = try_bf!(fake_sign::sign_call(request));
+		let overrides = try_bf!(fake_sign::state_override(overrides.unwrap_or_default()));
 
 		let num = num.unwrap_or_default();
 
@@ -845,6 +883,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 			(state, header)
 		};
 
+		try_bf!(self.client.override_state(&mut state, &overrides).map_err(errors::call));
 		let result = self.client.call(&signed, Default::default(), &mut state, &header);
 
 		Box::new(future::done(result
@@ -859,12 +898,13 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 		))
 	}
 
-	fn estimate_gas(&self, request: CallRequest, num: Trailing<BlockNumber>) -> BoxFuture<RpcU256> {
+	fn estimate_gas(&self, request: CallRequest, num: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> BoxFuture<RpcU256> {
 		let request = CallRequest::into(request);
 		let signed = try_bf!(fake_sign::sign_call(request));
+		let overrides = try_bf!(fake_sign::state_override(overrides.unwrap_or_default()));
 		let num = num.unwrap_or_default();
 
-		let (state, header) = if num == BlockNumber::Pending {
+		let (mut state, header) = if num == BlockNumber::Pending {
 			let info = self.client.chain_info();
 			let state = try_bf!(self.miner.pending_state(info.best_block_number).ok_or(errors::state_pruned()));
 			let header = try_bf!(self.miner.pending_block_header(info.best_block_number).ok_or(errors::state_pruned()));
@@ -884,6 +924,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 			(state, header)
 		};
 
+		try_bf!(self.client.override_state(&mut state, &overrides).map_err(errors::call));
 		Box::new(future::done(self.client.estimate_gas(&signed, &state, &header)
 			.map(Into::into)
 			.map_err(errors::call)
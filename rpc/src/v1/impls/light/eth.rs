@@ -47,8 +47,8 @@ use v1::helpers::light_fetch::{self, LightFetch};
 use v1::traits::Eth;
 use v1::types::{
 	RichBlock, Block, BlockTransactions, BlockNumber, LightBlockNumber, Bytes, SyncStatus, SyncInfo,
-	Transaction, CallRequest, Index, Filter, Log, Receipt, Work,
-	H64 as RpcH64, H256 as RpcH256, H160 as RpcH160, U256 as RpcU256,
+	Transaction, CallRequest, Index, Filter, Log, Receipt, Work, EthAccount, StorageProof,
+	StateOverride, H64 as RpcH64, H256 as RpcH256, H160 as RpcH160, U256 as RpcU256,
 };
 use v1::metadata::Metadata;
 
@@ -284,6 +284,31 @@ impl<T: LightChainClient + 'static> Eth for EthClient<T> {
 		Box::new(self.rich_block(num.to_block_id(), include_txs).map(Some))
 	}
 
+	fn proof(&self, address: RpcH160, values: Vec<RpcH256>, num: Trailing<BlockNumber>) -> BoxFuture<EthAccount> {
+		let id = match num.unwrap_or_default() {
+			BlockNumber::Pending => return Box::new(future::err(errors::unsupported("Proofs are not available for the pending block", None))),
+			number => number.to_block_id(),
+		};
+		let keys = values.into_iter().map(Into::into).collect();
+
+		Box::new(self.fetcher().account_proof(address.into(), keys, id)
+			.map(move |(account_proof, account, storage)| {
+				EthAccount {
+					address: address,
+					balance: account.balance.into(),
+					nonce: account.nonce.into(),
+					code_hash: account.code_hash.into(),
+					storage_hash: account.storage_root.into(),
+					account_proof: account_proof.into_iter().map(Bytes::new).collect(),
+					storage_proof: storage.into_iter().map(|(key, proof, value)| StorageProof {
+						key: U256::from(&*key).into(),
+						value: U256::from(&*value).into(),
+						proof: proof.into_iter().map(Bytes::new).collect(),
+					}).collect(),
+				}
+			}))
+	}
+
 	fn transaction_count(&self, address: RpcH160, num: Trailing<BlockNumber>) -> BoxFuture<RpcU256> {
 		Box::new(self.fetcher().account(address.into(), num.unwrap_or_default().to_block_id())
 			.map(|acc| acc.map_or(0.into(), |a| a.nonce).into()))
@@ -380,7 +405,11 @@ impl<T: LightChainClient + 'static> Eth for EthClient<T> {
 		self.send_raw_transaction(raw)
 	}
 
-	fn call(&self, req: CallRequest, num: Trailing<BlockNumber>) -> BoxFuture<Bytes> {
+	fn call(&self, req: CallRequest, num: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> BoxFuture<Bytes> {
+		if !overrides.unwrap_or_default().is_empty() {
+			return Box::new(future::err(errors::light_unimplemented(Some("State overrides cannot be applied to proved execution".into()))));
+		}
+
 		Box::new(self.fetcher().proved_execution(req, num).and_then(|res| {
 			match res {
 				Ok(exec) => Ok(exec.output.into()),
@@ -389,7 +418,11 @@ impl<T: LightChainClient + 'static> Eth for EthClient<T> {
 		}))
 	}
 
-	fn estimate_gas(&self, req: CallRequest, num: Trailing<BlockNumber>) -> BoxFuture<RpcU256> {
+	fn estimate_gas(&self, req: CallRequest, num: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> BoxFuture<RpcU256> {
+		if !overrides.unwrap_or_default().is_empty() {
+			return Box::new(future::err(errors::light_unimplemented(Some("State overrides cannot be applied to proved execution".into()))));
+		}
+
 		// TODO: binary chop for more accurate estimates.
 		Box::new(self.fetcher().proved_execution(req, num).and_then(|res| {
 			match res {
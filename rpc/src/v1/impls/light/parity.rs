@@ -39,11 +39,12 @@ use v1::metadata::Metadata;
 use v1::traits::Parity;
 use v1::types::{
 	Bytes, U256, U64, H64, H160, H256, H512, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, BandwidthInfo, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, LightBlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
 	AccountInfo, HwAccountInfo, Header, RichHeader, Receipt,
+	BlockOverride, BundleSimulation,
 };
 use Host;
 
@@ -186,6 +187,10 @@ impl Parity for ParityClient {
 		})
 	}
 
+	fn net_bandwidth(&self) -> Result<BandwidthInfo> {
+		self.light_dispatch.sync.bandwidth().map(Into::into).ok_or_else(errors::network_disabled)
+	}
+
 	fn net_port(&self) -> Result<u16> {
 		Ok(self.settings.network_port)
 	}
@@ -340,6 +345,10 @@ impl Parity for ParityClient {
 		self.light_dispatch.sync.enode().ok_or_else(errors::network_disabled)
 	}
 
+	fn enr(&self) -> Result<String> {
+		self.light_dispatch.sync.enr().ok_or_else(errors::network_disabled)
+	}
+
 	fn consensus_capability(&self) -> Result<ConsensusCapability> {
 		Err(errors::light_unimplemented(None))
 	}
@@ -419,6 +428,10 @@ impl Parity for ParityClient {
 		Err(errors::light_unimplemented(None))
 	}
 
+	fn simulate_bundle(&self, _transactions: Vec<Bytes>, _block: BlockNumber, _env: Trailing<BlockOverride>) -> Result<BundleSimulation> {
+		Err(errors::light_unimplemented(None))
+	}
+
 	fn submit_work_detail(&self, _nonce: H64, _pow_hash: H256, _mix_hash: H256) -> Result<H256> {
 		Err(errors::light_unimplemented(None))
 	}
@@ -19,12 +19,13 @@ use std::sync::Arc;
 use std::str::FromStr;
 use std::collections::{BTreeMap, HashSet};
 
-use ethereum_types::Address;
+use ethereum_types::{Address, U256 as EthU256};
 use version::version_data;
 
 use crypto::DEFAULT_MAC;
 use ethkey::{crypto::ecies, Brain, Generator};
 use ethstore::random_phrase;
+use rlp::Rlp;
 use sync::{SyncProvider, ManageNetwork};
 use ethcore::account_provider::AccountProvider;
 use ethcore::client::{BlockChainClient, StateClient, Call};
@@ -32,6 +33,7 @@ use ethcore::ids::BlockId;
 use ethcore::miner::{self, MinerService};
 use ethcore::state::StateInfo;
 use ethcore_logger::RotatingLogger;
+use transaction::SignedTransaction;
 use updater::{Service as UpdateService};
 use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_core::futures::future;
@@ -41,11 +43,12 @@ use v1::metadata::Metadata;
 use v1::traits::Parity;
 use v1::types::{
 	Bytes, U256, U64, H64, H160, H256, H512, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, BandwidthInfo, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
 	AccountInfo, HwAccountInfo, RichHeader, Receipt,
+	BlockOverride, BundleSimulation, SimulatedTransaction,
 	block_number_to_id
 };
 use Host;
@@ -195,6 +198,10 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		})
 	}
 
+	fn net_bandwidth(&self) -> Result<BandwidthInfo> {
+		self.sync.bandwidth().map(Into::into).ok_or_else(errors::network_disabled)
+	}
+
 	fn net_port(&self) -> Result<u16> {
 		Ok(self.settings.network_port)
 	}
@@ -349,6 +356,10 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		self.sync.enode().ok_or_else(errors::network_disabled)
 	}
 
+	fn enr(&self) -> Result<String> {
+		self.sync.enr().ok_or_else(errors::network_disabled)
+	}
+
 	fn consensus_capability(&self) -> Result<ConsensusCapability> {
 		Ok(self.updater.capability().into())
 	}
@@ -472,6 +483,63 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 				.map_err(errors::call)
 	}
 
+	fn simulate_bundle(&self, transactions: Vec<Bytes>, num: BlockNumber, env: Trailing<BlockOverride>) -> Result<BundleSimulation> {
+		let transactions = transactions
+			.into_iter()
+			.map(|raw| Rlp::new(&raw.into_vec()).as_val()
+				.map_err(errors::rlp)
+				.and_then(|tx| SignedTransaction::new(tx).map_err(errors::transaction))
+			)
+			.collect::<Result<Vec<_>>>()?;
+
+		let (mut state, mut header) = if num == BlockNumber::Pending {
+			let info = self.client.chain_info();
+			let state = self.miner.pending_state(info.best_block_number).ok_or_else(errors::state_pruned)?;
+			let header = self.miner.pending_block_header(info.best_block_number).ok_or_else(errors::state_pruned)?;
+
+			(state, header)
+		} else {
+			// the bundle runs in a new block on top of the requested one.
+			let id = block_number_to_id(num);
+			let state = self.client.state_at(id).ok_or_else(errors::state_pruned)?;
+			let parent = self.client.block_header(id).ok_or_else(errors::state_pruned)?.decode().map_err(errors::decode)?;
+
+			(state, self.client.child_header(&parent))
+		};
+
+		let env = env.unwrap_or_default();
+		if let Some(number) = env.number {
+			header.set_number(number.into());
+		}
+		if let Some(timestamp) = env.timestamp {
+			header.set_timestamp(timestamp.into());
+		}
+		if let Some(coinbase) = env.coinbase {
+			header.set_author(coinbase.into());
+		}
+		if let Some(gas_limit) = env.gas_limit {
+			header.set_gas_limit(gas_limit.into());
+		}
+		if let Some(difficulty) = env.difficulty {
+			header.set_difficulty(difficulty.into());
+		}
+
+		let simulated = self.client.simulate_transactions(&transactions, &mut state, &header).map_err(errors::call)?;
+
+		let gas_used = simulated.last().map_or_else(EthU256::zero, |s| s.receipt.cumulative_gas_used);
+		let coinbase_profit = simulated.iter().fold(EthU256::zero(), |acc, s| acc + s.coinbase_profit);
+		let number = header.number();
+
+		Ok(BundleSimulation {
+			gas_used: gas_used.into(),
+			coinbase_profit: coinbase_profit.into(),
+			transactions: transactions.iter()
+				.zip(simulated)
+				.map(|(t, s)| SimulatedTransaction::new(t, number, s))
+				.collect(),
+		})
+	}
+
 	fn submit_work_detail(&self, nonce: H64, pow_hash: H256, mix_hash: H256) -> Result<H256> {
 		helpers::submit_work_detail(&self.client, &self.miner, nonce, pow_hash, mix_hash)
 	}
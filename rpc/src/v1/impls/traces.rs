@@ -27,7 +27,7 @@ use jsonrpc_macros::Trailing;
 use v1::Metadata;
 use v1::traits::Traces;
 use v1::helpers::{errors, fake_sign};
-use v1::types::{TraceFilter, LocalizedTrace, BlockNumber, Index, CallRequest, Bytes, TraceResults, TraceResultsWithTransactionHash, TraceOptions, H256, block_number_to_id};
+use v1::types::{TraceFilter, LocalizedTrace, BlockNumber, Index, CallRequest, Bytes, TraceResults, TraceResultsWithTransactionHash, TraceOptions, H256, StateOverride, block_number_to_id};
 
 fn to_call_analytics(flags: TraceOptions) -> CallAnalytics {
 	CallAnalytics {
@@ -87,11 +87,12 @@ impl<C, S> Traces for TracesClient<C> where
 			.map(LocalizedTrace::from))
 	}
 
-	fn call(&self, request: CallRequest, flags: TraceOptions, block: Trailing<BlockNumber>) -> Result<TraceResults> {
+	fn call(&self, request: CallRequest, flags: TraceOptions, block: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> Result<TraceResults> {
 		let block = block.unwrap_or_default();
 
 		let request = CallRequest::into(request);
 		let signed = fake_sign::sign_call(request)?;
+		let overrides = fake_sign::state_override(overrides.unwrap_or_default())?;
 
 		let id = match block {
 			BlockNumber::Num(num) => BlockId::Number(num),
@@ -103,14 +104,16 @@ impl<C, S> Traces for TracesClient<C> where
 
 		let mut state = self.client.state_at(id).ok_or(errors::state_pruned())?;
 		let header = self.client.block_header(id).ok_or(errors::state_pruned())?;
+		self.client.override_state(&mut state, &overrides).map_err(errors::call)?;
 
 		self.client.call(&signed, to_call_analytics(flags), &mut state, &header.decode().map_err(errors::decode)?)
 			.map(TraceResults::from)
 			.map_err(errors::call)
 	}
 
-	fn call_many(&self, requests: Vec<(CallRequest, TraceOptions)>, block: Trailing<BlockNumber>) -> Result<Vec<TraceResults>> {
+	fn call_many(&self, requests: Vec<(CallRequest, TraceOptions)>, block: Trailing<BlockNumber>, overrides: Trailing<StateOverride>) -> Result<Vec<TraceResults>> {
 		let block = block.unwrap_or_default();
+		let overrides = fake_sign::state_override(overrides.unwrap_or_default())?;
 
 		let requests = requests.into_iter()
 			.map(|(request, flags)| {
@@ -130,6 +133,7 @@ impl<C, S> Traces for TracesClient<C> where
 
 		let mut state = self.client.state_at(id).ok_or(errors::state_pruned())?;
 		let header = self.client.block_header(id).ok_or(errors::state_pruned())?;
+		self.client.override_state(&mut state, &overrides).map_err(errors::call)?;
 
 		self.client.call_many(&requests, &mut state, &header.decode().map_err(errors::decode)?)
 			.map(|results| results.into_iter().map(TraceResults::from).collect())
@@ -19,7 +19,7 @@
 use std::collections::BTreeMap;
 use ethereum_types::H256;
 use parking_lot::RwLock;
-use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats};
+use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats, BandwidthInfo, TrafficStats};
 
 /// TestSyncProvider config.
 pub struct Config {
@@ -79,6 +79,8 @@ impl SyncProvider for TestSyncProvider {
 				capabilities: vec!["eth/62".to_owned(), "eth/63".to_owned()],
 				remote_address: "127.0.0.1:7777".to_owned(),
 				local_address: "127.0.0.1:8888".to_owned(),
+				enr: Some("enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8".to_owned()),
+				reputation: Some(10),
 				eth_info: Some(EthProtocolInfo {
 					version: 62,
 					difficulty: Some(40.into()),
@@ -92,6 +94,8 @@ impl SyncProvider for TestSyncProvider {
 				capabilities: vec!["eth/63".to_owned(), "eth/64".to_owned()],
 				remote_address: "Handshake".to_owned(),
 				local_address: "127.0.0.1:3333".to_owned(),
+				enr: None,
+				reputation: None,
 				eth_info: Some(EthProtocolInfo {
 					version: 64,
 					difficulty: None,
@@ -106,6 +110,23 @@ impl SyncProvider for TestSyncProvider {
 		None
 	}
 
+	fn enr(&self) -> Option<String> {
+		None
+	}
+
+	fn bandwidth(&self) -> Option<BandwidthInfo> {
+		Some(BandwidthInfo {
+			total: TrafficStats { sent: 1200, received: 3400 },
+			protocols: map![
+				*b"eth" => TrafficStats { sent: 1000, received: 3000 },
+				*b"p2p" => TrafficStats { sent: 200, received: 400 }
+			],
+			peers: map![
+				1.into() => TrafficStats { sent: 120, received: 340 }
+			],
+		})
+	}
+
 	fn transactions_stats(&self) -> BTreeMap<H256, TransactionStats> {
 		map![
 			1.into() => TransactionStats {
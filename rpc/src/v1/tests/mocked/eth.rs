@@ -474,6 +474,21 @@ fn rpc_eth_storage_at() {
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_eth_get_proof_pending() {
+	let tester = EthTester::default();
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_getProof",
+		"params": ["0x0000000000000000000000000000000000000001", ["0x0000000000000000000000000000000000000000000000000000000000000004"], "pending"],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Proofs are not available for the pending block"},"id":1}"#;
+
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_eth_transaction_count() {
 	let request = r#"{
@@ -170,6 +170,26 @@ fn rpc_trace_call() {
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_trace_call_state_override() {
+	let tester = io();
+
+	let request = r#"{"jsonrpc":"2.0","method":"trace_call","params":[{}, ["trace"], "latest", {"0x0000000000000000000000000000000000000001": {"balance": "0x1"}}],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"output":"0x010203","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
+
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_trace_call_conflicting_state_override() {
+	let tester = io();
+
+	let request = r#"{"jsonrpc":"2.0","method":"trace_call","params":[{}, ["trace"], "latest", {"0x0000000000000000000000000000000000000001": {"state": {}, "stateDiff": {}}}],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: Both `state` and `stateDiff` overrides given for account","data":"0000000000000000000000000000000000000001"},"id":1}"#;
+
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_trace_multi_call() {
 	let tester = io();
@@ -17,8 +17,9 @@
 //! Debug RPC interface.
 
 use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
 
-use v1::types::RichBlock;
+use v1::types::{RichBlock, BlockNumber, H256, DebugTraceOptions, GethTrace, TransactionTrace};
 
 build_rpc_trait! {
 	/// Debug RPC interface.
@@ -26,5 +27,17 @@ build_rpc_trait! {
 		/// Returns recently seen bad blocks.
 		#[rpc(name = "debug_getBadBlocks")]
 		fn bad_blocks(&self) -> Result<Vec<RichBlock>>;
+
+		/// Replays a transaction, returning geth-compatible struct logs or the output of a built-in tracer.
+		#[rpc(name = "debug_traceTransaction")]
+		fn trace_transaction(&self, H256, Trailing<DebugTraceOptions>) -> Result<GethTrace>;
+
+		/// Replays all transactions of the block with given number.
+		#[rpc(name = "debug_traceBlockByNumber")]
+		fn trace_block_by_number(&self, BlockNumber, Trailing<DebugTraceOptions>) -> Result<Vec<TransactionTrace>>;
+
+		/// Replays all transactions of the block with given hash.
+		#[rpc(name = "debug_traceBlockByHash")]
+		fn trace_block_by_hash(&self, H256, Trailing<DebugTraceOptions>) -> Result<Vec<TransactionTrace>>;
 	}
 }
@@ -18,9 +18,9 @@
 use jsonrpc_core::{Result, BoxFuture};
 use jsonrpc_macros::Trailing;
 
-use v1::types::{RichBlock, BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index};
+use v1::types::{RichBlock, BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index, EthAccount};
 use v1::types::{Log, Receipt, SyncStatus, Transaction, Work};
-use v1::types::{H64, H160, H256, U256};
+use v1::types::{H64, H160, H256, U256, StateOverride};
 
 build_rpc_trait! {
 	/// Eth rpc interface.
@@ -75,6 +75,10 @@ build_rpc_trait! {
 		#[rpc(name = "eth_getBlockByNumber")]
 		fn block_by_number(&self, BlockNumber, bool) -> BoxFuture<Option<RichBlock>>;
 
+		/// Returns the account and storage values of the given account including their merkle proofs.
+		#[rpc(name = "eth_getProof")]
+		fn proof(&self, H160, Vec<H256>, Trailing<BlockNumber>) -> BoxFuture<EthAccount>;
+
 		/// Returns the number of transactions sent from given address at given time (block number).
 		#[rpc(name = "eth_getTransactionCount")]
 		fn transaction_count(&self, H160, Trailing<BlockNumber>) -> BoxFuture<U256>;
@@ -109,11 +113,11 @@ build_rpc_trait! {
 
 		/// Call contract, returning the output data.
 		#[rpc(name = "eth_call")]
-		fn call(&self, CallRequest, Trailing<BlockNumber>) -> BoxFuture<Bytes>;
+		fn call(&self, CallRequest, Trailing<BlockNumber>, Trailing<StateOverride>) -> BoxFuture<Bytes>;
 
 		/// Estimate gas needed for execution of given contract.
 		#[rpc(name = "eth_estimateGas")]
-		fn estimate_gas(&self, CallRequest, Trailing<BlockNumber>) -> BoxFuture<U256>;
+		fn estimate_gas(&self, CallRequest, Trailing<BlockNumber>, Trailing<StateOverride>) -> BoxFuture<U256>;
 
 		/// Get transaction by its hash.
 		#[rpc(name = "eth_getTransactionByHash")]
@@ -23,11 +23,12 @@ use jsonrpc_macros::Trailing;
 
 use v1::types::{
 	H64, H160, H256, H512, U256, U64, Bytes, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, BandwidthInfo, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
 	AccountInfo, HwAccountInfo, RichHeader, Receipt,
+	BlockOverride, BundleSimulation,
 };
 
 build_rpc_trait! {
@@ -87,6 +88,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_netPeers")]
 		fn net_peers(&self) -> Result<Peers>;
 
+		/// Returns bytes sent and received in total, per subprotocol and per connected peer
+		#[rpc(name = "parity_netBandwidth")]
+		fn net_bandwidth(&self) -> Result<BandwidthInfo>;
+
 		/// Returns network port
 		#[rpc(name = "parity_netPort")]
 		fn net_port(&self) -> Result<u16>;
@@ -186,6 +191,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_enode")]
 		fn enode(&self) -> Result<String>;
 
+		/// Get the node record (ENR) of this node.
+		#[rpc(name = "parity_enr")]
+		fn enr(&self) -> Result<String>;
+
 		/// Returns information on current consensus capability.
 		#[rpc(name = "parity_consensusCapability")]
 		fn consensus_capability(&self) -> Result<ConsensusCapability>;
@@ -225,6 +234,12 @@ build_rpc_trait! {
 		#[rpc(name = "parity_call")]
 		fn call(&self, Vec<CallRequest>, Trailing<BlockNumber>) -> Result<Vec<Bytes>>;
 
+		/// Executes signed raw transactions in order in a new block on top of given block (or in
+		/// the pending block), with optional overrides of the block environment. Nothing is
+		/// committed or propagated.
+		#[rpc(name = "parity_simulateBundle")]
+		fn simulate_bundle(&self, Vec<Bytes>, BlockNumber, Trailing<BlockOverride>) -> Result<BundleSimulation>;
+
 		/// Used for submitting a proof-of-work solution (similar to `eth_submitWork`,
 		/// but returns block hash on success, and returns an explicit error message on failure).
 		#[rpc(name = "parity_submitWorkDetail")]
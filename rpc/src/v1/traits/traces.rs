@@ -18,7 +18,7 @@
 
 use jsonrpc_core::Result;
 use jsonrpc_macros::Trailing;
-use v1::types::{TraceFilter, LocalizedTrace, BlockNumber, Index, CallRequest, Bytes, TraceResults, TraceResultsWithTransactionHash, H256, TraceOptions};
+use v1::types::{TraceFilter, LocalizedTrace, BlockNumber, Index, CallRequest, Bytes, TraceResults, TraceResultsWithTransactionHash, H256, TraceOptions, StateOverride};
 
 build_rpc_trait! {
 	/// Traces specific rpc interface.
@@ -43,11 +43,11 @@ build_rpc_trait! {
 
 		/// Executes the given call and returns a number of possible traces for it.
 		#[rpc(name = "trace_call")]
-		fn call(&self, CallRequest, TraceOptions, Trailing<BlockNumber>) -> Result<TraceResults>;
+		fn call(&self, CallRequest, TraceOptions, Trailing<BlockNumber>, Trailing<StateOverride>) -> Result<TraceResults>;
 
 		/// Executes all given calls and returns a number of possible traces for each of it.
 		#[rpc(name = "trace_callMany")]
-		fn call_many(&self, Vec<(CallRequest, TraceOptions)>, Trailing<BlockNumber>) -> Result<Vec<TraceResults>>;
+		fn call_many(&self, Vec<(CallRequest, TraceOptions)>, Trailing<BlockNumber>, Trailing<StateOverride>) -> Result<Vec<TraceResults>>;
 
 		/// Executes the given raw transaction and returns a number of possible traces for it.
 		#[rpc(name = "trace_rawTransaction")]
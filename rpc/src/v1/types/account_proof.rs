@@ -0,0 +1,81 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Account and storage merkle proofs (EIP-1186).
+
+use v1::types::{Bytes, H160, H256, U256};
+
+/// Account information along with merkle proofs, returned by `eth_getProof`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EthAccount {
+	/// Account address
+	pub address: H160,
+	/// Account balance
+	pub balance: U256,
+	/// Account nonce
+	pub nonce: U256,
+	/// Hash of the account's code
+	#[serde(rename="codeHash")]
+	pub code_hash: H256,
+	/// Root of the account's storage trie
+	#[serde(rename="storageHash")]
+	pub storage_hash: H256,
+	/// Raw state trie nodes, from the root down to the account
+	#[serde(rename="accountProof")]
+	pub account_proof: Vec<Bytes>,
+	/// Proofs for each of the requested storage keys
+	#[serde(rename="storageProof")]
+	pub storage_proof: Vec<StorageProof>,
+}
+
+/// A single storage value along with its merkle proof.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageProof {
+	/// Storage key
+	pub key: U256,
+	/// Storage value
+	pub value: U256,
+	/// Raw storage trie nodes, from the storage root down to the value
+	pub proof: Vec<Bytes>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::{Bytes, H160, H256, U256};
+	use super::{EthAccount, StorageProof};
+
+	#[test]
+	fn account_proof_serialization() {
+		let account = EthAccount {
+			address: H160::from(1),
+			balance: U256::from(2),
+			nonce: U256::from(3),
+			code_hash: H256::from(4),
+			storage_hash: H256::from(5),
+			account_proof: vec![Bytes::new(vec![0xde, 0xad])],
+			storage_proof: vec![StorageProof {
+				key: U256::from(6),
+				value: U256::from(7),
+				proof: vec![Bytes::new(vec![0xbe, 0xef])],
+			}],
+		};
+
+		let serialized = serde_json::to_string(&account).unwrap();
+		assert_eq!(serialized, r#"{"address":"0x0000000000000000000000000000000000000001","balance":"0x2","nonce":"0x3","codeHash":"0x0000000000000000000000000000000000000000000000000000000000000004","storageHash":"0x0000000000000000000000000000000000000000000000000000000000000005","accountProof":["0xdead"],"storageProof":[{"key":"0x6","value":"0x7","proof":["0xbeef"]}]}"#);
+	}
+}
@@ -0,0 +1,115 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use ethcore::client::SimulatedTransaction as EthSimulatedTransaction;
+use transaction::{Action, SignedTransaction};
+use v1::types::{Bytes, Receipt, H160, U256, U64};
+use v1::types::trace::StateDiff;
+
+/// Block environment overrides used when simulating a bundle.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockOverride {
+	/// Block number
+	pub number: Option<U64>,
+	/// Timestamp
+	pub timestamp: Option<U64>,
+	/// Block author
+	pub coinbase: Option<H160>,
+	/// Gas limit
+	#[serde(rename="gasLimit")]
+	pub gas_limit: Option<U256>,
+	/// Difficulty
+	pub difficulty: Option<U256>,
+}
+
+/// Outcome of a single transaction of a simulated bundle.
+#[derive(Debug, Serialize)]
+pub struct SimulatedTransaction {
+	/// Receipt the transaction would produce
+	pub receipt: Receipt,
+	/// Output of the call or create
+	pub output: Bytes,
+	/// VM error, if any
+	pub error: Option<String>,
+	/// State changes made by the transaction
+	#[serde(rename="stateDiff")]
+	pub state_diff: Option<StateDiff>,
+	/// Balance gained by the block author
+	#[serde(rename="coinbaseProfit")]
+	pub coinbase_profit: U256,
+}
+
+impl SimulatedTransaction {
+	/// Convert the simulation outcome of given transaction.
+	pub fn new(transaction: &SignedTransaction, block_number: u64, simulated: EthSimulatedTransaction) -> Self {
+		let mut receipt = Receipt::from(simulated.receipt);
+		receipt.from = Some(transaction.sender().into());
+		receipt.to = match transaction.action {
+			Action::Call(to) => Some(to.into()),
+			Action::Create => None,
+		};
+		receipt.block_number = Some(block_number.into());
+
+		SimulatedTransaction {
+			receipt,
+			output: simulated.executed.output.into(),
+			error: simulated.executed.exception.map(|e| e.to_string()),
+			state_diff: simulated.executed.state_diff.map(Into::into),
+			coinbase_profit: simulated.coinbase_profit.into(),
+		}
+	}
+}
+
+/// Outcome of a simulated bundle.
+#[derive(Debug, Serialize)]
+pub struct BundleSimulation {
+	/// Total gas used by the bundle
+	#[serde(rename="gasUsed")]
+	pub gas_used: U256,
+	/// Total balance gained by the block author
+	#[serde(rename="coinbaseProfit")]
+	pub coinbase_profit: U256,
+	/// Per-transaction outcomes, in bundle order
+	pub transactions: Vec<SimulatedTransaction>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::{H160, U256, U64};
+	use super::BlockOverride;
+
+	#[test]
+	fn block_override_deserialize() {
+		let s = r#"{
+			"number": "0x10",
+			"timestamp": "0x5c000000",
+			"coinbase": "0x0000000000000000000000000000000000000001",
+			"gasLimit": "0x7a1200"
+		}"#;
+		let deserialized: BlockOverride = serde_json::from_str(s).unwrap();
+
+		assert_eq!(deserialized, BlockOverride {
+			number: Some(U64::from(0x10u64)),
+			timestamp: Some(U64::from(0x5c000000u64)),
+			coinbase: Some(H160::from(1)),
+			gas_limit: Some(U256::from(0x7a1200)),
+			difficulty: None,
+		});
+	}
+}
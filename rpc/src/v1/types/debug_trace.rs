@@ -0,0 +1,369 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Geth-compatible `debug_trace*` types.
+
+use std::collections::BTreeMap;
+use ethcore::client::Executed;
+use ethcore::pod_account::PodAccount;
+use ethcore::trace::{FlatTrace, StructLog, StructLoggerConfig, TraceError, trace};
+use rustc_hex::ToHex;
+use vm::CallType;
+use v1::types::{Bytes, H160, H256, U256};
+
+/// Built-in tracers, selected with the `tracer` option.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum DebugTracer {
+	/// Nested call frames of the transaction.
+	#[serde(rename="callTracer")]
+	CallTracer,
+	/// Accounts modified by the transaction, as they were before its execution.
+	#[serde(rename="prestateTracer")]
+	PrestateTracer,
+}
+
+/// Options of `debug_traceTransaction` and `debug_traceBlock*`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct DebugTraceOptions {
+	/// Built-in tracer to use instead of the struct logger.
+	pub tracer: Option<DebugTracer>,
+	/// Do not record the storage in struct logs.
+	#[serde(rename="disableStorage", default)]
+	pub disable_storage: bool,
+	/// Do not record the memory in struct logs.
+	#[serde(rename="disableMemory", default)]
+	pub disable_memory: bool,
+	/// Do not record the stack in struct logs.
+	#[serde(rename="disableStack", default)]
+	pub disable_stack: bool,
+}
+
+impl DebugTraceOptions {
+	/// Struct logger configuration described by these options.
+	pub fn struct_logger_config(&self) -> StructLoggerConfig {
+		StructLoggerConfig {
+			disable_stack: self.disable_stack,
+			disable_memory: self.disable_memory,
+			disable_storage: self.disable_storage,
+		}
+	}
+}
+
+/// Result of a debug trace, shaped by the tracer used.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum GethTrace {
+	/// Output of the struct logger.
+	StructLogs(StructLogs),
+	/// Output of the `callTracer`.
+	Call(CallFrame),
+	/// Output of the `prestateTracer`.
+	Prestate(BTreeMap<H160, PrestateAccount>),
+}
+
+/// Debug trace of a single transaction within a block.
+#[derive(Debug, Serialize)]
+pub struct TransactionTrace {
+	/// Transaction hash
+	#[serde(rename="txHash")]
+	pub tx_hash: H256,
+	/// Trace of the transaction
+	pub result: GethTrace,
+}
+
+/// Output of the struct logger.
+#[derive(Debug, Serialize)]
+pub struct StructLogs {
+	/// Gas used by the transaction
+	pub gas: u64,
+	/// Whether the transaction failed
+	pub failed: bool,
+	/// Output of the transaction, hex-encoded without prefix
+	#[serde(rename="returnValue")]
+	pub return_value: String,
+	/// Executed steps
+	#[serde(rename="structLogs")]
+	pub struct_logs: Vec<StructLogItem>,
+}
+
+impl From<Executed<FlatTrace, Vec<StructLog>>> for StructLogs {
+	fn from(e: Executed<FlatTrace, Vec<StructLog>>) -> Self {
+		StructLogs {
+			gas: e.gas_used.low_u64(),
+			failed: e.exception.is_some(),
+			return_value: e.output.to_hex(),
+			struct_logs: e.vm_trace.unwrap_or_default().into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// A single step of the struct logger.
+#[derive(Debug, Serialize)]
+pub struct StructLogItem {
+	/// Program counter
+	pub pc: usize,
+	/// Instruction name
+	pub op: String,
+	/// Gas remaining before the step
+	pub gas: u64,
+	/// Gas cost of the step
+	#[serde(rename="gasCost")]
+	pub gas_cost: u64,
+	/// Call depth
+	pub depth: usize,
+	/// Stack before the step
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub stack: Option<Vec<U256>>,
+	/// Memory before the step, in 32-byte words
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub memory: Option<Vec<String>>,
+	/// Storage of the executing contract
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub storage: Option<BTreeMap<String, String>>,
+}
+
+impl From<StructLog> for StructLogItem {
+	fn from(l: StructLog) -> Self {
+		StructLogItem {
+			pc: l.pc,
+			op: l.op_name().map_or_else(|| format!("opcode {:#x} not defined", l.instruction), Into::into),
+			gas: l.gas.low_u64(),
+			gas_cost: l.gas_cost.low_u64(),
+			depth: l.depth,
+			stack: l.stack.map(|stack| stack.into_iter().map(Into::into).collect()),
+			memory: l.memory.map(|memory| memory.chunks(32).map(|word| word.to_hex()).collect()),
+			storage: l.storage.map(|storage| storage.into_iter().map(|(k, v)| (k.to_hex(), v.to_hex())).collect()),
+		}
+	}
+}
+
+/// Call frame produced by the `callTracer`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct CallFrame {
+	/// Kind of the frame: `CALL`, `CREATE`, `SELFDESTRUCT`, ...
+	#[serde(rename="type")]
+	pub frame_type: String,
+	/// Sender
+	pub from: H160,
+	/// Recipient, or the created contract
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub to: Option<H160>,
+	/// Transferred value
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub value: Option<U256>,
+	/// Gas provided
+	pub gas: U256,
+	/// Gas used
+	#[serde(rename="gasUsed")]
+	pub gas_used: U256,
+	/// Input data or init code
+	pub input: Bytes,
+	/// Output data or created code
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub output: Option<Bytes>,
+	/// Error message, if the frame failed
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub error: Option<String>,
+	/// Nested frames
+	#[serde(skip_serializing_if="Vec::is_empty")]
+	pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+	/// Builds the call tree of an executed transaction from its flat traces.
+	/// The outermost frame accounts for the whole transaction gas, like geth does.
+	pub fn from_executed(e: Executed) -> Self {
+		let mut root = Self::from_flat_traces(e.trace).unwrap_or_default();
+		root.gas = e.gas.into();
+		root.gas_used = e.gas_used.into();
+		root
+	}
+
+	/// Nest flat traces, given in execution order, into a call tree.
+	pub fn from_flat_traces(traces: Vec<FlatTrace>) -> Option<Self> {
+		let mut root: Option<CallFrame> = None;
+
+		for t in traces {
+			let depth = t.trace_address.len();
+			let frame = match CallFrame::from_flat_trace(t) {
+				Some(frame) => frame,
+				None => continue,
+			};
+
+			if depth == 0 {
+				if root.is_none() {
+					root = Some(frame);
+				}
+			} else if let Some(ref mut root) = root {
+				root.push_at_depth(depth, frame);
+			}
+		}
+
+		root
+	}
+
+	// flat traces come in execution order, so the parent is always the last frame at each level.
+	fn push_at_depth(&mut self, depth: usize, frame: CallFrame) {
+		match self.calls.last_mut() {
+			Some(ref mut last) if depth > 1 => last.push_at_depth(depth - 1, frame),
+			_ => self.calls.push(frame),
+		}
+	}
+
+	fn from_flat_trace(t: FlatTrace) -> Option<Self> {
+		let mut frame = match t.action {
+			trace::Action::Call(call) => CallFrame {
+				frame_type: match call.call_type {
+					CallType::CallCode => "CALLCODE",
+					CallType::DelegateCall => "DELEGATECALL",
+					CallType::StaticCall => "STATICCALL",
+					CallType::Call | CallType::None => "CALL",
+				}.into(),
+				from: call.from.into(),
+				to: Some(call.to.into()),
+				value: match call.call_type {
+					CallType::DelegateCall | CallType::StaticCall => None,
+					_ => Some(call.value.into()),
+				},
+				gas: call.gas.into(),
+				input: call.input.into(),
+				..Default::default()
+			},
+			trace::Action::Create(create) => CallFrame {
+				frame_type: "CREATE".into(),
+				from: create.from.into(),
+				value: Some(create.value.into()),
+				gas: create.gas.into(),
+				input: create.init.into(),
+				..Default::default()
+			},
+			trace::Action::Suicide(suicide) => CallFrame {
+				frame_type: "SELFDESTRUCT".into(),
+				from: suicide.address.into(),
+				to: Some(suicide.refund_address.into()),
+				value: Some(suicide.balance.into()),
+				..Default::default()
+			},
+			trace::Action::Reward(_) => return None,
+		};
+
+		match t.result {
+			trace::Res::Call(res) => {
+				frame.gas_used = res.gas_used.into();
+				frame.output = Some(res.output.into());
+			},
+			trace::Res::Create(res) => {
+				frame.gas_used = res.gas_used.into();
+				frame.to = Some(res.address.into());
+				frame.output = Some(res.code.into());
+			},
+			trace::Res::FailedCall(err) | trace::Res::FailedCreate(err) => {
+				frame.gas_used = frame.gas.clone();
+				frame.error = Some(match err {
+					TraceError::Reverted => "execution reverted".into(),
+					err => err.to_string(),
+				});
+			},
+			trace::Res::None => {},
+		}
+
+		Some(frame)
+	}
+}
+
+/// Account state produced by the `prestateTracer`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrestateAccount {
+	/// Balance
+	pub balance: U256,
+	/// Nonce
+	pub nonce: u64,
+	/// Code, if any
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub code: Option<Bytes>,
+	/// Storage slots modified by the transaction
+	#[serde(skip_serializing_if="BTreeMap::is_empty")]
+	pub storage: BTreeMap<H256, H256>,
+}
+
+impl From<PodAccount> for PrestateAccount {
+	fn from(a: PodAccount) -> Self {
+		PrestateAccount {
+			balance: a.balance.into(),
+			nonce: a.nonce.low_u64(),
+			code: a.code.and_then(|code| if code.is_empty() { None } else { Some(code.into()) }),
+			storage: a.storage.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use ethcore::trace::{FlatTrace, TraceError, trace};
+	use vm::CallType;
+	use super::{CallFrame, DebugTraceOptions, DebugTracer};
+
+	fn call(trace_address: Vec<usize>, res: trace::Res) -> FlatTrace {
+		FlatTrace {
+			action: trace::Action::Call(trace::Call {
+				from: 1.into(),
+				to: 2.into(),
+				value: 3.into(),
+				gas: 4.into(),
+				input: vec![],
+				call_type: CallType::Call,
+			}),
+			result: res,
+			subtraces: 0,
+			trace_address: trace_address.into_iter().collect(),
+		}
+	}
+
+	#[test]
+	fn debug_trace_options_deserialization() {
+		let s = r#"{"tracer":"callTracer","disableStorage":true}"#;
+		let options: DebugTraceOptions = serde_json::from_str(s).unwrap();
+		assert_eq!(options, DebugTraceOptions {
+			tracer: Some(DebugTracer::CallTracer),
+			disable_storage: true,
+			..Default::default()
+		});
+
+		assert!(serde_json::from_str::<DebugTraceOptions>(r#"{"tracer":"{ step: function() {} }"}"#).is_err());
+	}
+
+	#[test]
+	fn call_frames_nesting() {
+		let ok = || trace::Res::Call(trace::CallResult { gas_used: 1.into(), output: vec![] });
+		let traces = vec![
+			call(vec![], ok()),
+			call(vec![0], ok()),
+			call(vec![0, 0], trace::Res::FailedCall(TraceError::Reverted)),
+			call(vec![1], ok()),
+		];
+
+		let root = CallFrame::from_flat_traces(traces).unwrap();
+		assert_eq!(root.calls.len(), 2);
+		assert_eq!(root.calls[0].calls.len(), 1);
+		assert_eq!(root.calls[0].calls[0].error, Some("execution reverted".into()));
+		assert!(root.calls[1].calls.is_empty());
+
+		let serialized = serde_json::to_string(&root.calls[1]).unwrap();
+		assert_eq!(serialized, r#"{"type":"CALL","from":"0x0000000000000000000000000000000000000001","to":"0x0000000000000000000000000000000000000002","value":"0x3","gas":"0x4","gasUsed":"0x1","input":"0x","output":"0x"}"#);
+	}
+}
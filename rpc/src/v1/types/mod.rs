@@ -17,12 +17,15 @@
 //! RPC types
 
 mod account_info;
+mod account_proof;
 mod block;
 mod block_number;
+mod bundle;
 mod bytes;
 mod call_request;
 mod confirmations;
 mod consensus_status;
+mod debug_trace;
 mod derivation;
 mod filter;
 mod hash;
@@ -34,6 +37,7 @@ mod provenance;
 mod receipt;
 mod rpc_settings;
 mod secretstore;
+mod state_override;
 mod sync;
 mod trace;
 mod trace_filter;
@@ -47,6 +51,8 @@ mod private_receipt;
 pub mod pubsub;
 
 pub use self::account_info::{AccountInfo, ExtAccountInfo, HwAccountInfo};
+pub use self::account_proof::{EthAccount, StorageProof};
+pub use self::bundle::{BlockOverride, BundleSimulation, SimulatedTransaction};
 pub use self::bytes::Bytes;
 pub use self::block::{RichBlock, Block, BlockTransactions, Header, RichHeader, Rich};
 pub use self::block_number::{BlockNumber, LightBlockNumber, block_number_to_id};
@@ -56,6 +62,10 @@ pub use self::confirmations::{
 	TransactionModification, SignRequest, DecryptRequest, Either
 };
 pub use self::consensus_status::*;
+pub use self::debug_trace::{
+	DebugTraceOptions, DebugTracer, GethTrace, TransactionTrace, StructLogs, StructLogItem,
+	CallFrame, PrestateAccount,
+};
 pub use self::derivation::{DeriveHash, DeriveHierarchical, Derive};
 pub use self::filter::{Filter, FilterChanges};
 pub use self::hash::{H64, H160, H256, H512, H520, H2048};
@@ -67,9 +77,11 @@ pub use self::provenance::Origin;
 pub use self::receipt::Receipt;
 pub use self::rpc_settings::RpcSettings;
 pub use self::secretstore::EncryptedDocumentKey;
+pub use self::state_override::{AccountOverride, StateOverride};
 pub use self::sync::{
 	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
 	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo,
+	BandwidthInfo, Traffic,
 };
 pub use self::trace::{LocalizedTrace, TraceResults, TraceResultsWithTransactionHash};
 pub use self::trace_filter::TraceFilter;
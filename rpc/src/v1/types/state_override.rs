@@ -0,0 +1,93 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::collections::BTreeMap;
+
+use ethcore::client::AccountOverride as EthAccountOverride;
+use v1::types::{Bytes, H160, H256, U256};
+
+/// Account state override
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountOverride {
+	/// Balance
+	pub balance: Option<U256>,
+	/// Nonce
+	pub nonce: Option<U256>,
+	/// Code
+	pub code: Option<Bytes>,
+	/// Full storage replacement
+	pub state: Option<BTreeMap<H256, H256>>,
+	/// Storage slots to change
+	#[serde(rename="stateDiff")]
+	pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+/// Per-address state overrides
+pub type StateOverride = BTreeMap<H160, AccountOverride>;
+
+impl Into<EthAccountOverride> for AccountOverride {
+	fn into(self) -> EthAccountOverride {
+		fn storage(s: BTreeMap<H256, H256>) -> BTreeMap<::ethereum_types::H256, ::ethereum_types::H256> {
+			s.into_iter().map(|(k, v)| (k.into(), v.into())).collect()
+		}
+
+		EthAccountOverride {
+			balance: self.balance.map(Into::into),
+			nonce: self.nonce.map(Into::into),
+			code: self.code.map(Into::into),
+			state: self.state.map(storage),
+			state_diff: self.state_diff.map(storage),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::{H160, H256, U256};
+	use super::{AccountOverride, StateOverride};
+
+	#[test]
+	fn state_override_deserialize() {
+		let s = r#"{
+			"0x0000000000000000000000000000000000000001": {
+				"balance": "0x1",
+				"nonce": "0x2",
+				"code": "0x6000",
+				"stateDiff": {
+					"0x0000000000000000000000000000000000000000000000000000000000000003": "0x0000000000000000000000000000000000000000000000000000000000000004"
+				}
+			}
+		}"#;
+		let deserialized: StateOverride = serde_json::from_str(s).unwrap();
+
+		assert_eq!(deserialized, vec![(H160::from(1), AccountOverride {
+			balance: Some(U256::from(1)),
+			nonce: Some(U256::from(2)),
+			code: Some(vec![0x60, 0x00].into()),
+			state: None,
+			state_diff: Some(vec![(H256::from(3), H256::from(4))].into_iter().collect()),
+		})].into_iter().collect());
+	}
+
+	#[test]
+	fn account_override_deserialize_unknown_field() {
+		let s = r#"{"balance": "0x1", "storage": {}}"#;
+		assert!(serde_json::from_str::<AccountOverride>(s).is_err());
+	}
+}
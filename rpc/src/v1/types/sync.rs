@@ -15,7 +15,8 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
-use sync::{self, PeerInfo as SyncPeerInfo, TransactionStats as SyncTransactionStats};
+use sync::{self, PeerInfo as SyncPeerInfo, TransactionStats as SyncTransactionStats,
+	BandwidthInfo as SyncBandwidthInfo, TrafficStats};
 use serde::{Serialize, Serializer};
 use v1::types::{U256, H512};
 
@@ -61,10 +62,14 @@ pub struct PeerInfo {
 	pub name: String,
 	/// Capabilities
 	pub caps: Vec<String>,
+	/// Node record (ENR), if known
+	pub enr: Option<String>,
 	/// Network information
 	pub network: PeerNetworkInfo,
 	/// Protocols information
 	pub protocols: PeerProtocolsInfo,
+	/// Reputation score
+	pub reputation: Option<i32>,
 }
 
 /// Peer network information
@@ -165,6 +170,7 @@ impl From<SyncPeerInfo> for PeerInfo {
 			id: p.id,
 			name: p.client_version,
 			caps: p.capabilities,
+			enr: p.enr,
 			network: PeerNetworkInfo {
 				remote_address: p.remote_address,
 				local_address: p.local_address,
@@ -173,6 +179,7 @@ impl From<SyncPeerInfo> for PeerInfo {
 				eth: p.eth_info.map(Into::into),
 				pip: p.pip_info.map(Into::into),
 			},
+			reputation: p.reputation,
 		}
 	}
 }
@@ -189,6 +196,51 @@ impl From<SyncTransactionStats> for TransactionStats {
 	}
 }
 
+/// Bytes sent and received.
+#[derive(Default, Debug, Serialize)]
+pub struct Traffic {
+	/// Bytes sent
+	pub sent: u64,
+	/// Bytes received
+	pub received: u64,
+}
+
+impl From<TrafficStats> for Traffic {
+	fn from(t: TrafficStats) -> Self {
+		Traffic {
+			sent: t.sent,
+			received: t.received,
+		}
+	}
+}
+
+/// Bandwidth usage of the network.
+#[derive(Default, Debug, Serialize)]
+pub struct BandwidthInfo {
+	/// Traffic of all sessions
+	pub total: Traffic,
+	/// Traffic per subprotocol
+	pub protocols: BTreeMap<String, Traffic>,
+	/// Traffic per connected peer
+	pub peers: BTreeMap<H512, Traffic>,
+}
+
+impl From<SyncBandwidthInfo> for BandwidthInfo {
+	fn from(b: SyncBandwidthInfo) -> Self {
+		BandwidthInfo {
+			total: b.total.into(),
+			protocols: b.protocols
+				.into_iter()
+				.map(|(protocol, traffic)| (String::from_utf8_lossy(&protocol).into_owned(), traffic.into()))
+				.collect(),
+			peers: b.peers
+				.into_iter()
+				.map(|(id, traffic)| (id.into(), traffic.into()))
+				.collect(),
+		}
+	}
+}
+
 /// Chain status.
 #[derive(Default, Debug, Serialize)]
 pub struct ChainStatus {
@@ -0,0 +1,407 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-disk format of the database log.
+//!
+//! The file starts with a 24 byte header (magic, format version and generation) followed by batches.
+//! Every batch starts with a header holding the length of its records, a checksum of that length
+//! and a checksum of the records. Since batches are only ever appended, a damaged batch which is
+//! followed by no valid batch is an interrupted write and is discarded, while damage anywhere
+//! else in the log is reported as corruption.
+
+use std::{cmp, io, fs};
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use parking_lot::Mutex;
+
+/// Name of the log file inside the database directory.
+pub const FILE_NAME: &str = "logdb.data";
+
+const MAGIC: &[u8; 12] = b"PARITY-LOGDB";
+const VERSION: u32 = 1;
+/// Length of the file header.
+pub const HEADER_LEN: u64 = 24;
+/// Length of the header of every batch.
+const BATCH_HEADER_LEN: usize = 24;
+
+const TAG_INSERT: u8 = 0;
+const TAG_DELETE: u8 = 1;
+
+/// Column id of the default column.
+const NO_COLUMN: u32 = ::std::u32::MAX;
+
+/// Location of a value inside the log file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+	pub offset: u64,
+	pub len: u32,
+}
+
+/// Committed record read back from the log.
+pub enum Record {
+	Insert { col: Option<u32>, key: Vec<u8>, location: Location },
+	Delete { col: Option<u32>, key: Vec<u8> },
+}
+
+/// Batch of records encoded in memory before being appended to the log.
+pub struct Batch {
+	buf: Vec<u8>,
+}
+
+impl Batch {
+	pub fn new() -> Self {
+		Batch { buf: vec![0u8; BATCH_HEADER_LEN] }
+	}
+
+	/// Encodes an insertion. Returns location of the value relative to the start of the batch.
+	pub fn insert(&mut self, col: Option<u32>, key: &[u8], value: &[u8]) -> Location {
+		self.buf.push(TAG_INSERT);
+		self.push_u32(col.unwrap_or(NO_COLUMN));
+		self.push_u32(key.len() as u32);
+		self.push_u32(value.len() as u32);
+		self.buf.extend_from_slice(key);
+		let offset = self.buf.len() as u64;
+		self.buf.extend_from_slice(value);
+
+		Location { offset, len: value.len() as u32 }
+	}
+
+	/// Encodes a deletion.
+	pub fn delete(&mut self, col: Option<u32>, key: &[u8]) {
+		self.buf.push(TAG_DELETE);
+		self.push_u32(col.unwrap_or(NO_COLUMN));
+		self.push_u32(key.len() as u32);
+		self.buf.extend_from_slice(key);
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.buf.len() == BATCH_HEADER_LEN
+	}
+
+	pub fn len(&self) -> usize {
+		self.buf.len()
+	}
+
+	/// Fills in the batch header and returns the encoding of the batch.
+	pub fn finish(mut self) -> Vec<u8> {
+		let len = (self.buf.len() - BATCH_HEADER_LEN) as u64;
+		let checksum = fnv(&self.buf[BATCH_HEADER_LEN..]);
+		encode_batch_header(len, checksum, &mut self.buf[..BATCH_HEADER_LEN]);
+		self.buf
+	}
+
+	fn push_u32(&mut self, value: u32) {
+		self.buf.write_u32::<LittleEndian>(value).expect("writing to a vec never fails; qed");
+	}
+}
+
+/// 64-bit FNV-1a hash.
+pub fn fnv(data: &[u8]) -> u64 {
+	data.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn encode_batch_header(len: u64, checksum: u64, header: &mut [u8]) {
+	LittleEndian::write_u64(&mut header[0..8], len);
+	let len_checksum = fnv(&header[0..8]);
+	LittleEndian::write_u64(&mut header[8..16], len_checksum);
+	LittleEndian::write_u64(&mut header[16..24], checksum);
+}
+
+/// Returns length and checksum of the records of a batch, if the header is intact.
+fn decode_batch_header(header: &[u8]) -> Option<(u64, u64)> {
+	if LittleEndian::read_u64(&header[8..16]) != fnv(&header[0..8]) {
+		return None;
+	}
+
+	Some((LittleEndian::read_u64(&header[0..8]), LittleEndian::read_u64(&header[16..24])))
+}
+
+/// Reads exactly `buf.len()` bytes at given offset, without moving the cursor shared by other readers.
+pub fn read_exact_at(file: &fs::File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+	while !buf.is_empty() {
+		match read_at(file, buf, offset) {
+			Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+			Ok(n) => {
+				let tmp = buf;
+				buf = &mut tmp[n..];
+				offset += n as u64;
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+fn write_all_at(file: &fs::File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+	while !buf.is_empty() {
+		match write_at(file, buf, offset) {
+			Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+			Ok(n) => {
+				buf = &buf[n..];
+				offset += n as u64;
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+#[cfg(unix)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+	use std::os::unix::fs::FileExt;
+	file.read_at(buf, offset)
+}
+
+#[cfg(unix)]
+fn write_at(file: &fs::File, buf: &[u8], offset: u64) -> io::Result<usize> {
+	use std::os::unix::fs::FileExt;
+	file.write_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+	use std::os::windows::fs::FileExt;
+	file.seek_read(buf, offset)
+}
+
+#[cfg(windows)]
+fn write_at(file: &fs::File, buf: &[u8], offset: u64) -> io::Result<usize> {
+	use std::os::windows::fs::FileExt;
+	file.seek_write(buf, offset)
+}
+
+/// Append-only log file.
+///
+/// Reads are positional, so any number of threads may read from the log while it is appended to.
+pub struct File {
+	file: fs::File,
+	generation: u64,
+	/// End of the last committed batch.
+	end: Mutex<u64>,
+}
+
+impl File {
+	/// Creates an empty log at given path, replacing the file if it exists.
+	///
+	/// The generation identifies the log, so that indexes built for a previous log at the same
+	/// path are not used with this one.
+	pub fn create<P: AsRef<Path>>(path: P, generation: u64) -> io::Result<File> {
+		let mut file = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(path)?;
+
+		file.write_all(MAGIC)?;
+		file.write_u32::<LittleEndian>(VERSION)?;
+		file.write_u64::<LittleEndian>(generation)?;
+		file.sync_all()?;
+
+		Ok(File { file, generation, end: Mutex::new(HEADER_LEN) })
+	}
+
+	/// Opens an existing log. Batches have to be read with `replay` before anything is appended.
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+		let path = path.as_ref();
+		let mut file = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(path)?;
+
+		let mut magic = [0u8; 12];
+		let header = file.read_exact(&mut magic)
+			.and_then(|_| file.read_u32::<LittleEndian>())
+			.and_then(|version| file.read_u64::<LittleEndian>().map(|generation| (version, generation)));
+
+		match header {
+			Ok((version, generation)) if &magic == MAGIC && version == VERSION => {
+				let len = file.metadata()?.len();
+				Ok(File { file, generation, end: Mutex::new(len) })
+			},
+			Ok(_) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a log database", path.display()))),
+			Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof =>
+				Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a log database", path.display()))),
+			Err(e) => Err(e),
+		}
+	}
+
+	/// Reads all batches starting at `from`, which has to be the start of a batch.
+	///
+	/// The records of every batch are passed to `apply` together with the end offset of the batch.
+	/// A damaged batch at the end of the log is an interrupted write and is discarded.
+	/// A damaged batch followed by intact ones means the log is corrupted and is an error.
+	pub fn replay<F>(&self, from: u64, mut apply: F) -> io::Result<()> where F: FnMut(u64, Vec<Record>) -> io::Result<()> {
+		let mut end = self.end.lock();
+		let len = *end;
+		let mut pos = from;
+
+		while pos < len {
+			match self.read_batch(pos, len)? {
+				Some(records) => {
+					let batch_end = pos + (BATCH_HEADER_LEN + records.len()) as u64;
+					apply(batch_end, decode_records(&records, pos)?)?;
+					pos = batch_end;
+				},
+				None => {
+					if let Some(next) = self.find_batch(pos + 1, len)? {
+						return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+							"Log database is corrupted: damaged batch at offset {} is followed by a batch at offset {}", pos, next
+						)));
+					}
+
+					warn!(target: "logdb", "Discarding {} bytes of an incomplete write", len - pos);
+					self.file.set_len(pos)?;
+					self.file.sync_all()?;
+					break;
+				},
+			}
+		}
+
+		*end = pos;
+		Ok(())
+	}
+
+	/// Returns the records of the batch at given offset, or `None` if the batch is damaged.
+	fn read_batch(&self, pos: u64, len: u64) -> io::Result<Option<Vec<u8>>> {
+		if pos + BATCH_HEADER_LEN as u64 > len {
+			return Ok(None);
+		}
+
+		let mut header = [0u8; BATCH_HEADER_LEN];
+		read_exact_at(&self.file, &mut header, pos)?;
+		let (records_len, checksum) = match decode_batch_header(&header) {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+
+		if records_len > len - pos - BATCH_HEADER_LEN as u64 {
+			return Ok(None);
+		}
+
+		let mut records = vec![0u8; records_len as usize];
+		read_exact_at(&self.file, &mut records, pos + BATCH_HEADER_LEN as u64)?;
+		if fnv(&records) != checksum {
+			return Ok(None);
+		}
+
+		Ok(Some(records))
+	}
+
+	/// Looks for an intact batch starting anywhere in `[from, len)`.
+	fn find_batch(&self, from: u64, len: u64) -> io::Result<Option<u64>> {
+		const CHUNK_LEN: u64 = 1024 * 1024;
+
+		let mut chunk = Vec::new();
+		let mut start = from;
+		while start + BATCH_HEADER_LEN as u64 <= len {
+			let chunk_len = cmp::min(CHUNK_LEN + BATCH_HEADER_LEN as u64, len - start) as usize;
+			chunk.resize(chunk_len, 0);
+			read_exact_at(&self.file, &mut chunk, start)?;
+
+			for i in 0..chunk_len - BATCH_HEADER_LEN + 1 {
+				if decode_batch_header(&chunk[i..i + BATCH_HEADER_LEN]).is_some() && self.read_batch(start + i as u64, len)?.is_some() {
+					return Ok(Some(start + i as u64));
+				}
+			}
+
+			start += (chunk_len - BATCH_HEADER_LEN + 1) as u64;
+		}
+
+		Ok(None)
+	}
+
+	/// Appends a finished batch and syncs it to disk. Returns the offset it was written at.
+	pub fn append(&self, batch: &[u8]) -> io::Result<u64> {
+		let mut end = self.end.lock();
+		let offset = *end;
+		let result = write_all_at(&self.file, batch, offset).and_then(|_| self.file.sync_data());
+		if let Err(e) = result {
+			// drop whatever part of the batch made it to disk, so that the next append
+			// doesn't leave it behind. anything left is discarded on replay as the tail.
+			let _ = self.file.set_len(offset);
+			return Err(e);
+		}
+
+		*end += batch.len() as u64;
+		Ok(offset)
+	}
+
+	/// Reads the value at given location.
+	pub fn read(&self, location: Location) -> io::Result<Vec<u8>> {
+		let mut value = vec![0u8; location.len as usize];
+		read_exact_at(&self.file, &mut value, location.offset)?;
+		Ok(value)
+	}
+
+	/// Size of the committed part of the log.
+	pub fn len(&self) -> u64 {
+		*self.end.lock()
+	}
+
+	pub fn generation(&self) -> u64 {
+		self.generation
+	}
+}
+
+/// Decodes the records of an intact batch starting at given offset.
+fn decode_records(records: &[u8], batch_offset: u64) -> io::Result<Vec<Record>> {
+	let malformed = || io::Error::new(io::ErrorKind::InvalidData, format!("Log database is corrupted: malformed batch at offset {}", batch_offset));
+	let base = batch_offset + BATCH_HEADER_LEN as u64;
+	let mut cursor = Cursor::new(records);
+	let mut decoded = Vec::new();
+
+	while (cursor.position() as usize) < records.len() {
+		let tag = cursor.read_u8()?;
+		let col = match cursor.read_u32::<LittleEndian>().map_err(|_| malformed())? {
+			NO_COLUMN => None,
+			col => Some(col),
+		};
+		let key_len = cursor.read_u32::<LittleEndian>().map_err(|_| malformed())? as usize;
+
+		match tag {
+			TAG_INSERT => {
+				let value_len = cursor.read_u32::<LittleEndian>().map_err(|_| malformed())?;
+				let key_start = cursor.position() as usize;
+				let value_start = key_start + key_len;
+				if value_start + value_len as usize > records.len() {
+					return Err(malformed());
+				}
+
+				let key = records[key_start..value_start].to_vec();
+				let location = Location { offset: base + value_start as u64, len: value_len };
+				cursor.set_position((value_start + value_len as usize) as u64);
+				decoded.push(Record::Insert { col, key, location });
+			},
+			TAG_DELETE => {
+				let key_start = cursor.position() as usize;
+				if key_start + key_len > records.len() {
+					return Err(malformed());
+				}
+
+				let key = records[key_start..key_start + key_len].to_vec();
+				cursor.set_position((key_start + key_len) as u64);
+				decoded.push(Record::Delete { col, key });
+			},
+			_ => return Err(malformed()),
+		}
+	}
+
+	Ok(decoded)
+}
@@ -0,0 +1,438 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-disk index of the values in the log.
+//!
+//! The index is a set of immutable tables, each holding sorted `key -> location` entries of every
+//! column. Entries are grouped into blocks of about `BLOCK_SIZE` bytes and only the first key of
+//! every block is kept in memory, so a lookup reads a single block from every table.
+//!
+//! The manifest lists the tables in use, newest first, and the part of the log they cover.
+//! It is replaced atomically whenever the set of tables changes.
+
+use std::{io, fs, vec};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use file::{fnv, read_exact_at, Location};
+
+const MAGIC: &[u8; 12] = b"PARITY-INDEX";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 48;
+
+const MANIFEST_MAGIC: &[u8; 12] = b"PARITY-LOGMF";
+const MANIFEST_NAME: &str = "logdb.index";
+const TABLE_PREFIX: &str = "logdb.table.";
+
+/// Offset marking a deleted key.
+const DELETED: u64 = ::std::u64::MAX;
+
+/// Size after which a new block is started.
+#[cfg(not(test))]
+const BLOCK_SIZE: u64 = 4096;
+#[cfg(test)]
+const BLOCK_SIZE: u64 = 64;
+
+/// Size of the buffer used when writing tables.
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Sorted entries of a table, read from a single block.
+type Entries = Vec<(Vec<u8>, Option<Location>)>;
+
+struct Block {
+	/// First key of the block.
+	key: Vec<u8>,
+	offset: u64,
+	end: u64,
+}
+
+/// Immutable sorted table of index entries. Deleted keys have no location.
+pub struct Table {
+	number: u64,
+	file: fs::File,
+	entries: u64,
+	live: u64,
+	/// Blocks of every column.
+	blocks: Vec<Vec<Block>>,
+}
+
+impl Table {
+	/// Opens the table with given number.
+	pub fn open(dir: &Path, number: u64, columns: usize) -> io::Result<Table> {
+		let mut file = fs::File::open(table_path(dir, number))?;
+		let malformed = || io::Error::new(io::ErrorKind::InvalidData, format!("Index table {} is malformed", number));
+
+		let mut magic = [0u8; 12];
+		file.read_exact(&mut magic)?;
+		let version = file.read_u32::<LittleEndian>()?;
+		if &magic != MAGIC || version != VERSION {
+			return Err(malformed());
+		}
+
+		let entries = file.read_u64::<LittleEndian>()?;
+		let live = file.read_u64::<LittleEndian>()?;
+		let blocks_offset = file.read_u64::<LittleEndian>()?;
+		let checksum = file.read_u64::<LittleEndian>()?;
+
+		let len = file.metadata()?.len();
+		if blocks_offset < HEADER_LEN || blocks_offset > len {
+			return Err(malformed());
+		}
+
+		let mut encoded = vec![0u8; (len - blocks_offset) as usize];
+		read_exact_at(&file, &mut encoded, blocks_offset)?;
+		if fnv(&encoded) != checksum {
+			return Err(malformed());
+		}
+
+		let mut cursor = Cursor::new(&encoded[..]);
+		let count = cursor.read_u64::<LittleEndian>()?;
+		let mut blocks = Vec::new();
+		for _ in 0..count {
+			let c = cursor.read_u32::<LittleEndian>()?;
+			let offset = cursor.read_u64::<LittleEndian>()?;
+			let key_len = cursor.read_u32::<LittleEndian>()? as usize;
+			let mut key = vec![0u8; key_len];
+			cursor.read_exact(&mut key)?;
+			blocks.push((c, offset, key));
+		}
+
+		Ok(Table::new(number, file, entries, live, blocks_offset, blocks, columns))
+	}
+
+	fn new(number: u64, file: fs::File, entries: u64, live: u64, blocks_offset: u64, blocks: Vec<(u32, u64, Vec<u8>)>, columns: usize) -> Table {
+		let ends: Vec<u64> = blocks.iter().skip(1).map(|&(_, offset, _)| offset).chain(Some(blocks_offset)).collect();
+		let mut column_blocks: Vec<Vec<Block>> = (0..columns).map(|_| Vec::new()).collect();
+		for ((c, offset, key), end) in blocks.into_iter().zip(ends) {
+			// columns unknown to the database are skipped, like their records in the log.
+			if let Some(column) = column_blocks.get_mut(c as usize) {
+				column.push(Block { key, offset, end });
+			}
+		}
+
+		Table { number, file, entries, live, blocks: column_blocks }
+	}
+
+	pub fn number(&self) -> u64 {
+		self.number
+	}
+
+	/// Number of entries, including deletions.
+	pub fn entries(&self) -> u64 {
+		self.entries
+	}
+
+	/// Total length of the values referenced by the table.
+	pub fn live(&self) -> u64 {
+		self.live
+	}
+
+	/// Returns the entry of given key. `Some(None)` means the key is deleted.
+	pub fn get(&self, c: usize, key: &[u8]) -> io::Result<Option<Option<Location>>> {
+		let blocks = &self.blocks[c];
+		let block = match blocks.binary_search_by(|block| block.key[..].cmp(key)) {
+			Ok(i) => i,
+			Err(0) => return Ok(None),
+			Err(i) => i - 1,
+		};
+
+		for (entry_key, location) in self.read_block(&blocks[block])? {
+			if &entry_key[..] == key {
+				return Ok(Some(location));
+			}
+			if &entry_key[..] > key {
+				break;
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Returns iterator over the entries of given column, starting from given key.
+	pub fn iter_from(table: &Arc<Table>, c: usize, from: &[u8]) -> TableIter {
+		let block = match table.blocks[c].binary_search_by(|block| block.key[..].cmp(from)) {
+			Ok(i) => i,
+			Err(0) => 0,
+			Err(i) => i - 1,
+		};
+
+		TableIter {
+			table: table.clone(),
+			c,
+			block,
+			entries: Vec::new().into_iter(),
+			from: Some(from.to_vec()),
+		}
+	}
+
+	fn read_block(&self, block: &Block) -> io::Result<Entries> {
+		let mut encoded = vec![0u8; (block.end - block.offset) as usize];
+		read_exact_at(&self.file, &mut encoded, block.offset)?;
+
+		let mut cursor = Cursor::new(&encoded[..]);
+		let mut entries = Vec::new();
+		while (cursor.position() as usize) < encoded.len() {
+			let key_len = cursor.read_u32::<LittleEndian>()? as usize;
+			let mut key = vec![0u8; key_len];
+			cursor.read_exact(&mut key)?;
+			let offset = cursor.read_u64::<LittleEndian>()?;
+			let len = cursor.read_u32::<LittleEndian>()?;
+			let location = match offset {
+				DELETED => None,
+				offset => Some(Location { offset, len }),
+			};
+			entries.push((key, location));
+		}
+
+		Ok(entries)
+	}
+}
+
+/// Iterator over the entries of a single column of a table.
+pub struct TableIter {
+	table: Arc<Table>,
+	c: usize,
+	/// Next block to read.
+	block: usize,
+	entries: vec::IntoIter<(Vec<u8>, Option<Location>)>,
+	/// Entries before this key are skipped.
+	from: Option<Vec<u8>>,
+}
+
+impl Iterator for TableIter {
+	type Item = io::Result<(Vec<u8>, Option<Location>)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some((key, location)) = self.entries.next() {
+				match self.from {
+					Some(ref from) if key < *from => continue,
+					_ => {},
+				}
+				self.from = None;
+				return Some(Ok((key, location)));
+			}
+
+			let blocks = &self.table.blocks[self.c];
+			if self.block >= blocks.len() {
+				return None;
+			}
+
+			match self.table.read_block(&blocks[self.block]) {
+				Ok(entries) => {
+					self.entries = entries.into_iter();
+					self.block += 1;
+				},
+				Err(e) => {
+					self.block = blocks.len();
+					return Some(Err(e));
+				},
+			}
+		}
+	}
+}
+
+/// Writes a new table. Entries have to be pushed in order of columns and keys.
+pub struct TableWriter {
+	number: u64,
+	file: fs::File,
+	buf: Vec<u8>,
+	pos: u64,
+	blocks: Vec<(u32, u64, Vec<u8>)>,
+	entries: u64,
+	live: u64,
+}
+
+impl TableWriter {
+	pub fn create(dir: &Path, number: u64) -> io::Result<TableWriter> {
+		let file = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(table_path(dir, number))?;
+
+		Ok(TableWriter {
+			number,
+			file,
+			buf: vec![0u8; HEADER_LEN as usize],
+			pos: HEADER_LEN,
+			blocks: Vec::new(),
+			entries: 0,
+			live: 0,
+		})
+	}
+
+	pub fn push(&mut self, c: usize, key: &[u8], location: Option<Location>) -> io::Result<()> {
+		let new_block = match self.blocks.last() {
+			Some(&(col, offset, _)) => col != c as u32 || self.pos - offset >= BLOCK_SIZE,
+			None => true,
+		};
+		if new_block {
+			self.blocks.push((c as u32, self.pos, key.to_vec()));
+		}
+
+		let (offset, len) = match location {
+			Some(location) => (location.offset, location.len),
+			None => (DELETED, 0),
+		};
+
+		self.buf.write_u32::<LittleEndian>(key.len() as u32)?;
+		self.buf.extend_from_slice(key);
+		self.buf.write_u64::<LittleEndian>(offset)?;
+		self.buf.write_u32::<LittleEndian>(len)?;
+		self.pos += 16 + key.len() as u64;
+		self.entries += 1;
+		self.live += len as u64;
+
+		if self.buf.len() >= WRITE_BUFFER_SIZE {
+			self.file.write_all(&self.buf)?;
+			self.buf.clear();
+		}
+
+		Ok(())
+	}
+
+	/// Writes the in-memory part of the table and syncs it to disk.
+	pub fn finish(mut self, columns: usize) -> io::Result<Table> {
+		let blocks_offset = self.pos;
+		let mut encoded = Vec::new();
+		encoded.write_u64::<LittleEndian>(self.blocks.len() as u64)?;
+		for &(c, offset, ref key) in &self.blocks {
+			encoded.write_u32::<LittleEndian>(c)?;
+			encoded.write_u64::<LittleEndian>(offset)?;
+			encoded.write_u32::<LittleEndian>(key.len() as u32)?;
+			encoded.extend_from_slice(key);
+		}
+		self.buf.extend_from_slice(&encoded);
+		self.file.write_all(&self.buf)?;
+
+		let mut header = Vec::with_capacity(HEADER_LEN as usize);
+		header.extend_from_slice(MAGIC);
+		header.write_u32::<LittleEndian>(VERSION)?;
+		header.write_u64::<LittleEndian>(self.entries)?;
+		header.write_u64::<LittleEndian>(self.live)?;
+		header.write_u64::<LittleEndian>(blocks_offset)?;
+		header.write_u64::<LittleEndian>(fnv(&encoded))?;
+
+		self.file.seek(SeekFrom::Start(0))?;
+		self.file.write_all(&header)?;
+		self.file.sync_all()?;
+
+		Ok(Table::new(self.number, self.file, self.entries, self.live, blocks_offset, self.blocks, columns))
+	}
+}
+
+/// List of the tables in use.
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+	/// Generation of the log the tables index.
+	pub generation: u64,
+	/// End of the part of the log covered by the tables.
+	pub log_end: u64,
+	/// Number of the next table to write.
+	pub next_table: u64,
+	/// Numbers of the tables, newest first.
+	pub tables: Vec<u64>,
+}
+
+impl Manifest {
+	/// Reads the manifest of the database in given directory, if there is one.
+	pub fn read(dir: &Path) -> io::Result<Option<Manifest>> {
+		let mut encoded = Vec::new();
+		match fs::File::open(dir.join(MANIFEST_NAME)) {
+			Ok(mut file) => file.read_to_end(&mut encoded)?,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e),
+		};
+
+		let malformed = || io::Error::new(io::ErrorKind::InvalidData, "Index manifest is malformed");
+		if encoded.len() < 8 + MANIFEST_MAGIC.len() {
+			return Err(malformed());
+		}
+
+		let (content, checksum) = encoded.split_at(encoded.len() - 8);
+		if fnv(content) != Cursor::new(checksum).read_u64::<LittleEndian>()? || &content[..MANIFEST_MAGIC.len()] != MANIFEST_MAGIC {
+			return Err(malformed());
+		}
+
+		let mut cursor = Cursor::new(&content[MANIFEST_MAGIC.len()..]);
+		let generation = cursor.read_u64::<LittleEndian>()?;
+		let log_end = cursor.read_u64::<LittleEndian>()?;
+		let next_table = cursor.read_u64::<LittleEndian>()?;
+		let count = cursor.read_u32::<LittleEndian>()?;
+		let tables = (0..count).map(|_| cursor.read_u64::<LittleEndian>()).collect::<io::Result<_>>()?;
+
+		Ok(Some(Manifest { generation, log_end, next_table, tables }))
+	}
+
+	/// Replaces the manifest of the database in given directory.
+	pub fn write(&self, dir: &Path) -> io::Result<()> {
+		let mut encoded = Vec::new();
+		encoded.extend_from_slice(MANIFEST_MAGIC);
+		encoded.write_u64::<LittleEndian>(self.generation)?;
+		encoded.write_u64::<LittleEndian>(self.log_end)?;
+		encoded.write_u64::<LittleEndian>(self.next_table)?;
+		encoded.write_u32::<LittleEndian>(self.tables.len() as u32)?;
+		for table in &self.tables {
+			encoded.write_u64::<LittleEndian>(*table)?;
+		}
+		let checksum = fnv(&encoded);
+		encoded.write_u64::<LittleEndian>(checksum)?;
+
+		let tmp_path = dir.join(format!("{}.tmp", MANIFEST_NAME));
+		{
+			let mut file = fs::File::create(&tmp_path)?;
+			file.write_all(&encoded)?;
+			file.sync_all()?;
+		}
+		fs::rename(&tmp_path, dir.join(MANIFEST_NAME))
+	}
+}
+
+pub fn table_path(dir: &Path, number: u64) -> PathBuf {
+	dir.join(format!("{}{}", TABLE_PREFIX, number))
+}
+
+/// Removes table files which are not listed in the manifest, left behind by an interrupted write.
+pub fn remove_stale_tables(dir: &Path, tables: &[u64]) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		let number = path.file_name()
+			.and_then(|name| name.to_str())
+			.and_then(|name| if name.starts_with(TABLE_PREFIX) { name[TABLE_PREFIX.len()..].parse::<u64>().ok() } else { None });
+
+		match number {
+			Some(number) if !tables.contains(&number) => fs::remove_file(&path)?,
+			_ => {},
+		}
+	}
+
+	Ok(())
+}
+
+/// Removes the files of tables which are no longer used.
+///
+/// Tables may still be read by open iterators, which keep their files open.
+pub fn remove_tables(dir: &Path, tables: &[u64]) {
+	for number in tables {
+		if let Err(e) = fs::remove_file(table_path(dir, *number)) {
+			debug!(target: "logdb", "Error removing index table {}: {}", number, e);
+		}
+	}
+}
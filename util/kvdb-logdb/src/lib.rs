@@ -0,0 +1,1320 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Log-structured key-value database written in pure Rust.
+//!
+//! Values are appended to a single log file. Writes are buffered until `flush`, which appends
+//! them as one atomic batch. Values are located through an on-disk index of sorted tables
+//! (see `index`) and the index changes made since the tables were last written, which are
+//! kept in memory and rebuilt from the end of the log on open.
+//!
+//! When the log is mostly made of stale values it is rewritten with live values only. The rewrite
+//! runs in a background thread against a snapshot of the index; writes made meanwhile are copied
+//! to the rewritten log by the next `flush` after the thread is done, before the log is replaced.
+
+extern crate byteorder;
+extern crate kvdb;
+extern crate parking_lot;
+
+#[macro_use]
+extern crate log;
+
+#[cfg(test)]
+extern crate tempdir;
+
+mod file;
+mod index;
+
+use std::collections::{BTreeMap, HashMap};
+use std::collections::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{io, fs, mem, thread};
+use parking_lot::{Mutex, RwLock};
+use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB};
+
+use file::{Batch, File, Location, Record, HEADER_LEN};
+use index::{Manifest, Table, TableWriter};
+
+pub use file::FILE_NAME;
+
+/// Logs larger than this are compacted if most of their data is stale.
+#[cfg(not(test))]
+const COMPACTION_THRESHOLD: u64 = 64 * 1024 * 1024;
+#[cfg(test)]
+const COMPACTION_THRESHOLD: u64 = 16 * 1024;
+
+/// Maximal size of a single batch written during compaction.
+const COMPACTION_BATCH_SIZE: usize = 4 * 1024 * 1024;
+
+/// Number of index changes kept in memory before they are written to a table.
+#[cfg(not(test))]
+const DELTA_LIMIT: usize = 64 * 1024;
+#[cfg(test)]
+const DELTA_LIMIT: usize = 16;
+
+/// Size of the log written after which the index is written, bounding the replay on open.
+#[cfg(not(test))]
+const CHECKPOINT_INTERVAL: u64 = 64 * 1024 * 1024;
+#[cfg(test)]
+const CHECKPOINT_INTERVAL: u64 = 4 * 1024;
+
+/// A table is merged into the next older one, unless that one is this many times larger.
+/// Keeps the number of tables logarithmic in the number of keys.
+const MERGE_RATIO: u64 = 4;
+
+/// Database configuration
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseConfig {
+	/// Number of columns, not counting the default one.
+	pub columns: Option<u32>,
+}
+
+impl DatabaseConfig {
+	/// Create new `DatabaseConfig` with default parameters and specified set of columns.
+	pub fn with_columns(columns: Option<u32>) -> Self {
+		DatabaseConfig { columns }
+	}
+}
+
+enum KeyState {
+	Insert(DBValue),
+	Delete,
+}
+
+type Overlay = Vec<HashMap<Vec<u8>, KeyState>>;
+
+/// Index changes not written to a table yet. Deleted keys have no location.
+type Delta = Vec<BTreeMap<Vec<u8>, Option<Location>>>;
+
+/// Value of a key in one of the layers of the database.
+enum Entry {
+	Memory(DBValue),
+	Disk(Location),
+	Deleted,
+}
+
+/// Sorted entries of a single layer.
+type Source = Box<Iterator<Item=io::Result<(Vec<u8>, Entry)>>>;
+
+/// Log and its index.
+///
+/// Cloned by readers which must not hold the lock, the tables and the log are never modified
+/// and the delta is copied on write.
+#[derive(Clone)]
+struct Index {
+	log: Arc<File>,
+	/// Tables, newest first.
+	tables: Vec<Arc<Table>>,
+	delta: Arc<Delta>,
+	/// End of the part of the log covered by the tables.
+	log_end: u64,
+	next_table: u64,
+}
+
+impl Index {
+	/// Returns location of the value of given key.
+	fn get(&self, c: usize, key: &[u8]) -> io::Result<Option<Location>> {
+		if let Some(location) = self.delta[c].get(key) {
+			return Ok(*location);
+		}
+
+		for table in &self.tables {
+			if let Some(location) = table.get(c, key)? {
+				return Ok(location);
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Returns the layers of the index, newest first.
+	fn sources(&self, c: usize, from: &[u8]) -> Vec<Source> {
+		let mut sources: Vec<Source> = vec![Box::new(DeltaIter {
+			delta: self.delta.clone(),
+			c,
+			next: Bound::Included(from.to_vec()),
+		})];
+		sources.extend(table_sources(&self.tables, c, from));
+		sources
+	}
+
+	fn delta_len(&self) -> usize {
+		self.delta.iter().map(BTreeMap::len).sum()
+	}
+
+	/// Estimated length of the live values. Values overwritten by entries of a newer table
+	/// are counted until the tables are merged.
+	fn live(&self) -> u64 {
+		let tables: u64 = self.tables.iter().map(|table| table.live()).sum();
+		let delta: u64 = self.delta.iter()
+			.flat_map(|column| column.values())
+			.filter_map(|location| location.map(|location| location.len as u64))
+			.sum();
+		tables + delta
+	}
+
+	fn needs_checkpoint(&self) -> bool {
+		self.delta_len() >= DELTA_LIMIT || self.log.len() - self.log_end >= CHECKPOINT_INTERVAL
+	}
+
+	fn needs_compaction(&self) -> bool {
+		let len = self.log.len();
+		len > COMPACTION_THRESHOLD && self.live() < len / 2
+	}
+}
+
+fn table_sources(tables: &[Arc<Table>], c: usize, from: &[u8]) -> Vec<Source> {
+	tables.iter()
+		.map(|table| Box::new(Table::iter_from(table, c, from).map(|item| item.map(|(key, location)| match location {
+			Some(location) => (key, Entry::Disk(location)),
+			None => (key, Entry::Deleted),
+		}))) as Source)
+		.collect()
+}
+
+/// Iterator over a column of the delta, which doesn't borrow it.
+struct DeltaIter {
+	delta: Arc<Delta>,
+	c: usize,
+	next: Bound<Vec<u8>>,
+}
+
+impl Iterator for DeltaIter {
+	type Item = io::Result<(Vec<u8>, Entry)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (key, location) = {
+			let from = match self.next {
+				Bound::Included(ref key) => Bound::Included(&key[..]),
+				Bound::Excluded(ref key) => Bound::Excluded(&key[..]),
+				Bound::Unbounded => Bound::Unbounded,
+			};
+
+			match self.delta[self.c].range::<[u8], _>((from, Bound::Unbounded)).next() {
+				Some((key, location)) => (key.clone(), *location),
+				None => return None,
+			}
+		};
+
+		self.next = Bound::Excluded(key.clone());
+		Some(Ok((key, location.map_or(Entry::Deleted, Entry::Disk))))
+	}
+}
+
+/// Merges sorted sources. Of the entries of a key present in several of them, the one of
+/// the earliest source is returned.
+struct Merge {
+	sources: Vec<Source>,
+	heads: Vec<Option<(Vec<u8>, Entry)>>,
+	started: bool,
+}
+
+impl Merge {
+	fn new(sources: Vec<Source>) -> Self {
+		let heads = sources.iter().map(|_| None).collect();
+		Merge { sources, heads, started: false }
+	}
+
+	fn advance(&mut self, i: usize) -> io::Result<()> {
+		self.heads[i] = match self.sources[i].next() {
+			Some(item) => Some(item?),
+			None => None,
+		};
+		Ok(())
+	}
+}
+
+impl Iterator for Merge {
+	type Item = io::Result<(Vec<u8>, Entry)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if !self.started {
+			self.started = true;
+			for i in 0..self.sources.len() {
+				if let Err(e) = self.advance(i) {
+					return Some(Err(e));
+				}
+			}
+		}
+
+		let first = {
+			let mut first: Option<(usize, &[u8])> = None;
+			for (i, head) in self.heads.iter().enumerate() {
+				if let Some((ref key, _)) = *head {
+					if first.map_or(true, |(_, first_key)| &key[..] < first_key) {
+						first = Some((i, &key[..]));
+					}
+				}
+			}
+
+			first?.0
+		};
+
+		let (key, entry) = self.heads[first].take().expect("head was found above; qed");
+		for i in 0..self.sources.len() {
+			let shadowed = i == first || self.heads[i].as_ref().map_or(false, |&(ref head, _)| *head == key);
+			if shadowed {
+				if let Err(e) = self.advance(i) {
+					return Some(Err(e));
+				}
+			}
+		}
+
+		Some(Ok((key, entry)))
+	}
+}
+
+/// Rewrite of the log running in a background thread.
+struct Compaction {
+	/// Length of the log covered by the snapshot being rewritten.
+	log_end: u64,
+	/// Number reserved for the table indexing the rewritten log.
+	number: u64,
+	cancel: Arc<AtomicBool>,
+	done: Arc<AtomicBool>,
+	thread: thread::JoinHandle<io::Result<(File, Table)>>,
+}
+
+/// Key-value database backed by an append-only log.
+pub struct Database {
+	path: PathBuf,
+	/// Number of columns, including the default one.
+	columns: usize,
+	index: RwLock<Index>,
+	/// Writes not flushed yet.
+	overlay: RwLock<Overlay>,
+	/// Writes being flushed.
+	flushing: RwLock<Overlay>,
+	flushing_lock: Mutex<()>,
+	compaction: Mutex<Option<Compaction>>,
+}
+
+impl Database {
+	/// Open database with default settings.
+	pub fn open_default(path: &str) -> io::Result<Database> {
+		Database::open(&DatabaseConfig::default(), path)
+	}
+
+	/// Open database file. Creates if it does not exist.
+	pub fn open(config: &DatabaseConfig, path: &str) -> io::Result<Database> {
+		let columns = config.columns.unwrap_or(0) as usize + 1;
+		let path = PathBuf::from(path);
+		let index = open_index(&path, columns)?;
+
+		Ok(Database {
+			path,
+			columns,
+			index: RwLock::new(index),
+			overlay: RwLock::new(new_overlay(columns)),
+			flushing: RwLock::new(new_overlay(columns)),
+			flushing_lock: Mutex::new(()),
+			compaction: Mutex::new(None),
+		})
+	}
+
+	/// Flushes pending writes and rewrites the log keeping only live values.
+	pub fn compact(&self) -> io::Result<()> {
+		self.flush()?;
+		let _guard = self.flushing_lock.lock();
+		self.finish_compaction(true)?;
+		let index = self.index.read().clone();
+		let compacted = compact(&self.path, self.columns, &index)?;
+		*self.index.write() = compacted;
+		Ok(())
+	}
+
+	fn column(&self, col: Option<u32>) -> Option<usize> {
+		column_index(self.columns, col)
+	}
+
+	fn write_flushing(&self) -> io::Result<()> {
+		let flushing = self.flushing.read();
+		let mut batch = Batch::new();
+		let mut updates = Vec::new();
+
+		for (c, layer) in flushing.iter().enumerate() {
+			for (key, state) in layer {
+				let location = match *state {
+					KeyState::Insert(ref value) => Some(batch.insert(column_id(c), key, value)),
+					KeyState::Delete => {
+						batch.delete(column_id(c), key);
+						None
+					},
+				};
+				updates.push((c, key, location));
+			}
+		}
+
+		if batch.is_empty() {
+			return Ok(());
+		}
+
+		// the log is only replaced under `flushing_lock`, which is held by the caller.
+		let log = self.index.read().log.clone();
+		let offset = log.append(&batch.finish())?;
+
+		let mut index = self.index.write();
+		// copies the delta if a reader holds a clone of the index. the copy is bounded by
+		// `DELTA_LIMIT` and the size of a single flush, as `maintain` writes larger deltas to a table.
+		let delta = Arc::make_mut(&mut index.delta);
+		for (c, key, location) in updates {
+			let location = location.map(|location| Location { offset: offset + location.offset, len: location.len });
+			delta[c].insert(key.clone(), location);
+		}
+
+		Ok(())
+	}
+
+	/// Installs a finished compaction, writes the index changes to a table once there are enough
+	/// of them and starts compacting the log if most of it is stale. Must be called with
+	/// `flushing_lock` held.
+	fn maintain(&self) -> io::Result<()> {
+		self.finish_compaction(false)?;
+
+		let index = self.index.read().clone();
+		if !index.needs_checkpoint() {
+			return Ok(());
+		}
+
+		let log_end = index.log.len();
+		let index = checkpoint(&self.path, self.columns, &index, log_end)?;
+		*self.index.write() = index.clone();
+
+		if index.needs_compaction() {
+			self.start_compaction(index)?;
+		}
+
+		Ok(())
+	}
+
+	/// Starts rewriting the log covered by given index in a background thread, unless a rewrite
+	/// is running already. Must be called with `flushing_lock` held.
+	fn start_compaction(&self, index: Index) -> io::Result<()> {
+		let mut compaction = self.compaction.lock();
+		if compaction.is_some() {
+			return Ok(());
+		}
+
+		// reserved, so that checkpoints made meanwhile don't reuse the number.
+		let number = {
+			let mut index = self.index.write();
+			index.next_table += 1;
+			index.next_table - 1
+		};
+
+		info!(target: "logdb", "Compacting {}", self.path.display());
+		let log_end = index.log.len();
+		let cancel = Arc::new(AtomicBool::new(false));
+		let done = Arc::new(AtomicBool::new(false));
+		let thread = {
+			let path = self.path.clone();
+			let columns = self.columns;
+			let cancel = cancel.clone();
+			let done = done.clone();
+			thread::Builder::new().name("logdb-compaction".into()).spawn(move || {
+				let result = write_compacted(&path, columns, &index, number, &cancel);
+				done.store(true, Ordering::Release);
+				result
+			})?
+		};
+
+		*compaction = Some(Compaction { log_end, number, cancel, done, thread });
+		Ok(())
+	}
+
+	/// Replaces the log with the rewritten one once the background compaction is done, or waits
+	/// for it if `wait` is set. Must be called with `flushing_lock` held.
+	fn finish_compaction(&self, wait: bool) -> io::Result<()> {
+		let compaction = {
+			let mut compaction = self.compaction.lock();
+			let finished = compaction.as_ref().map_or(false, |compaction| wait || compaction.done.load(Ordering::Acquire));
+			match finished {
+				true => compaction.take(),
+				false => None,
+			}
+		};
+
+		let Compaction { log_end, number, thread, .. } = match compaction {
+			Some(compaction) => compaction,
+			None => return Ok(()),
+		};
+
+		let result = thread.join()
+			.unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "Compaction thread panicked")))
+			.and_then(|(log, table)| {
+				let index = self.index.read().clone();
+				install_compacted(&self.path, self.columns, &index, log, table, number, log_end)
+			});
+
+		match result {
+			Ok(index) => {
+				*self.index.write() = index;
+				Ok(())
+			},
+			Err(e) => {
+				let _ = fs::remove_file(compacted_log_path(&self.path));
+				index::remove_tables(&self.path, &[number]);
+				Err(e)
+			},
+		}
+	}
+
+	/// Stops the background compaction, discarding its result.
+	fn cancel_compaction(compaction: Option<Compaction>) {
+		if let Some(compaction) = compaction {
+			compaction.cancel.store(true, Ordering::Release);
+			let _ = compaction.thread.join();
+		}
+	}
+}
+
+impl KeyValueDB for Database {
+	fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+		let c = self.column(col)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::Other, format!("No such column family: {:?}", col)))?;
+
+		let overlay = self.overlay.read();
+		let flushing = self.flushing.read();
+		match overlay[c].get(key).or_else(|| flushing[c].get(key)) {
+			Some(&KeyState::Insert(ref value)) => return Ok(Some(value.clone())),
+			Some(&KeyState::Delete) => return Ok(None),
+			None => {},
+		}
+
+		// readers share the lock, only writes to the delta wait for the disk reads.
+		let index = self.index.read();
+		drop(flushing);
+		drop(overlay);
+
+		match index.get(c, key)? {
+			Some(location) => index.log.read(location).map(|value| Some(DBValue::from_vec(value))),
+			None => Ok(None),
+		}
+	}
+
+	fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.iter_from_prefix(col, prefix)
+			.take_while(|&(ref key, _)| key.starts_with(prefix))
+			.next()
+			.map(|(_, value)| value)
+	}
+
+	fn write_buffered(&self, transaction: DBTransaction) {
+		let mut overlay = self.overlay.write();
+		for op in transaction.ops {
+			match op {
+				DBOp::Insert { col, key, value } => {
+					if let Some(c) = self.column(col) {
+						overlay[c].insert(key.into_vec(), KeyState::Insert(value));
+					}
+				},
+				DBOp::Delete { col, key } => {
+					if let Some(c) = self.column(col) {
+						overlay[c].insert(key.into_vec(), KeyState::Delete);
+					}
+				},
+			}
+		}
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		let _guard = self.flushing_lock.lock();
+		{
+			let mut overlay = self.overlay.write();
+			let mut flushing = self.flushing.write();
+			mem::swap(&mut *overlay, &mut *flushing);
+		}
+
+		let result = self.write_flushing();
+
+		{
+			let mut overlay = self.overlay.write();
+			let mut flushing = self.flushing.write();
+			for (c, layer) in flushing.iter_mut().enumerate() {
+				if result.is_err() {
+					// keep the writes around, unless they have been overwritten in the meantime.
+					for (key, state) in layer.drain() {
+						overlay[c].entry(key).or_insert(state);
+					}
+				} else {
+					layer.clear();
+				}
+			}
+		}
+
+		result?;
+
+		// the writes are durable already, a failure only delays writing the index.
+		if let Err(e) = self.maintain() {
+			warn!(target: "logdb", "Error writing index of {}: {}", self.path.display(), e);
+		}
+
+		Ok(())
+	}
+
+	fn iter<'a>(&'a self, col: Option<u32>) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		self.iter_from_prefix(col, &[])
+	}
+
+	fn iter_from_prefix<'a>(&'a self, col: Option<u32>, prefix: &'a [u8]) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		let c = match self.column(col) {
+			Some(c) => c,
+			None => return Box::new(::std::iter::empty()),
+		};
+
+		// only the pending writes are copied, the index is read as the iterator advances.
+		let (pending, index) = {
+			let overlay = self.overlay.read();
+			let flushing = self.flushing.read();
+			let mut pending = BTreeMap::new();
+			for layer in &[&flushing[c], &overlay[c]] {
+				for (key, state) in layer.iter().filter(|&(key, _)| &key[..] >= prefix) {
+					let entry = match *state {
+						KeyState::Insert(ref value) => Entry::Memory(value.clone()),
+						KeyState::Delete => Entry::Deleted,
+					};
+					pending.insert(key.clone(), entry);
+				}
+			}
+
+			(pending, self.index.read().clone())
+		};
+
+		let mut sources: Vec<Source> = vec![Box::new(pending.into_iter().map(Ok))];
+		sources.extend(index.sources(c, prefix));
+
+		Box::new(Merge::new(sources).filter_map(move |item| {
+			let item = item.and_then(|(key, entry)| match entry {
+				Entry::Memory(value) => Ok(Some((key, value.into_vec()))),
+				Entry::Disk(location) => index.log.read(location).map(|value| Some((key, value))),
+				Entry::Deleted => Ok(None),
+			});
+
+			match item {
+				Ok(entry) => entry.map(|(key, value)| (key.into_boxed_slice(), value.into_boxed_slice())),
+				Err(e) => {
+					warn!(target: "logdb", "Error reading from {}: {}", self.path.display(), e);
+					None
+				},
+			}
+		}))
+	}
+
+	fn restore(&self, new_db: &str) -> io::Result<()> {
+		let _guard = self.flushing_lock.lock();
+		Database::cancel_compaction(self.compaction.lock().take());
+		let mut index = self.index.write();
+
+		let mut backup = self.path.clone().into_os_string();
+		backup.push(".old");
+		let backup = PathBuf::from(backup);
+
+		if backup.exists() {
+			fs::remove_dir_all(&backup)?;
+		}
+		fs::rename(&self.path, &backup)?;
+		if let Err(e) = fs::rename(new_db, &self.path) {
+			fs::rename(&backup, &self.path)?;
+			return Err(e);
+		}
+
+		*index = open_index(&self.path, self.columns)?;
+		*self.overlay.write() = new_overlay(self.columns);
+
+		// ignore errors
+		let _ = fs::remove_dir_all(&backup);
+		Ok(())
+	}
+}
+
+impl Drop for Database {
+	fn drop(&mut self) {
+		// files left behind by the compaction are removed on open.
+		Database::cancel_compaction(self.compaction.get_mut().take());
+	}
+}
+
+fn new_overlay(columns: usize) -> Overlay {
+	(0..columns).map(|_| HashMap::new()).collect()
+}
+
+fn new_delta(columns: usize) -> Arc<Delta> {
+	Arc::new((0..columns).map(|_| BTreeMap::new()).collect())
+}
+
+/// Maps column to its position in the index. The default column comes first.
+fn column_index(columns: usize, col: Option<u32>) -> Option<usize> {
+	match col {
+		None => Some(0),
+		Some(col) if (col as usize) + 1 < columns => Some(col as usize + 1),
+		Some(_) => None,
+	}
+}
+
+fn column_id(c: usize) -> Option<u32> {
+	match c {
+		0 => None,
+		c => Some(c as u32 - 1),
+	}
+}
+
+fn compacted_log_path(path: &Path) -> PathBuf {
+	path.join(format!("{}.compact", FILE_NAME))
+}
+
+/// Opens the log in given directory and its index.
+///
+/// Only the part of the log written after the index is replayed. The whole log is replayed
+/// if the index is missing or doesn't belong to the log.
+fn open_index(path: &Path, columns: usize) -> io::Result<Index> {
+	fs::create_dir_all(path)?;
+
+	// left behind by an interrupted compaction.
+	let compacted_path = compacted_log_path(path);
+	if compacted_path.exists() {
+		fs::remove_file(&compacted_path)?;
+	}
+
+	let log_path = path.join(FILE_NAME);
+	let log = match log_path.exists() {
+		true => File::open(&log_path)?,
+		false => File::create(&log_path, 0)?,
+	};
+
+	let manifest = match Manifest::read(path) {
+		Ok(Some(manifest)) => {
+			if manifest.generation == log.generation() && manifest.log_end <= log.len() {
+				Some(manifest)
+			} else {
+				warn!(target: "logdb", "Index of {} doesn't match the log, rebuilding", path.display());
+				None
+			}
+		},
+		Ok(None) => None,
+		Err(e) => {
+			warn!(target: "logdb", "Error reading index of {}: {}, rebuilding", path.display(), e);
+			None
+		},
+	};
+
+	let tables = match manifest {
+		Some(ref manifest) => match manifest.tables.iter().map(|number| Table::open(path, *number, columns).map(Arc::new)).collect() {
+			Ok(tables) => Some(tables),
+			Err(e) => {
+				warn!(target: "logdb", "Error reading index of {}: {}, rebuilding", path.display(), e);
+				None
+			},
+		},
+		None => None,
+	};
+
+	let mut index = match (manifest, tables) {
+		(Some(manifest), Some(tables)) => Index {
+			log: Arc::new(log),
+			tables,
+			delta: new_delta(columns),
+			log_end: manifest.log_end,
+			next_table: manifest.next_table,
+		},
+		_ => Index {
+			log: Arc::new(log),
+			tables: Vec::new(),
+			delta: new_delta(columns),
+			log_end: HEADER_LEN,
+			next_table: 0,
+		},
+	};
+
+	let tables: Vec<u64> = index.tables.iter().map(|table| table.number()).collect();
+	index::remove_stale_tables(path, &tables)?;
+
+	let log = index.log.clone();
+	log.replay(index.log_end, |batch_end, records| {
+		{
+			let delta = Arc::make_mut(&mut index.delta);
+			for record in records {
+				match record {
+					Record::Insert { col, key, location } => {
+						if let Some(c) = column_index(columns, col) {
+							delta[c].insert(key, Some(location));
+						}
+					},
+					Record::Delete { col, key } => {
+						if let Some(c) = column_index(columns, col) {
+							delta[c].insert(key, None);
+						}
+					},
+				}
+			}
+		}
+
+		if index.delta_len() >= DELTA_LIMIT || batch_end - index.log_end >= CHECKPOINT_INTERVAL {
+			index = checkpoint(path, columns, &index, batch_end)?;
+		}
+
+		Ok(())
+	})?;
+
+	if index.needs_compaction() {
+		info!(target: "logdb", "Compacting {}", path.display());
+		index = compact(path, columns, &index)?;
+	}
+
+	Ok(index)
+}
+
+/// Writes the entries of given sources to a new table.
+///
+/// Deletions are only needed to hide older entries, so they are dropped if `keep_deleted` is false.
+fn write_table<F>(path: &Path, number: u64, columns: usize, keep_deleted: bool, sources: F) -> io::Result<Table>
+	where F: Fn(usize) -> Vec<Source>
+{
+	let mut writer = TableWriter::create(path, number)?;
+	for c in 0..columns {
+		for item in Merge::new(sources(c)) {
+			let (key, entry) = item?;
+			match entry {
+				Entry::Disk(location) => writer.push(c, &key, Some(location))?,
+				Entry::Deleted if keep_deleted => writer.push(c, &key, None)?,
+				_ => {},
+			}
+		}
+	}
+
+	writer.finish(columns)
+}
+
+/// Writes the index changes covering the log up to `log_end` to a table, merging tables
+/// of similar size.
+fn checkpoint(path: &Path, columns: usize, index: &Index, log_end: u64) -> io::Result<Index> {
+	let mut next_table = index.next_table;
+	let mut tables = index.tables.clone();
+	let mut removed = Vec::new();
+
+	let push_table = |tables: &mut Vec<Arc<Table>>, removed: &mut Vec<u64>, table: Table| {
+		if table.entries() == 0 {
+			removed.push(table.number());
+		} else {
+			tables.insert(0, Arc::new(table));
+		}
+	};
+
+	if index.delta_len() > 0 {
+		let delta = index.delta.clone();
+		let table = write_table(path, next_table, columns, !tables.is_empty(), |c| vec![Box::new(DeltaIter {
+			delta: delta.clone(),
+			c,
+			next: Bound::Unbounded,
+		}) as Source])?;
+		next_table += 1;
+		push_table(&mut tables, &mut removed, table);
+	}
+
+	while tables.len() > 1 && tables[1].entries() < MERGE_RATIO * tables[0].entries() {
+		let merged: Vec<_> = tables.drain(..2).collect();
+		let table = write_table(path, next_table, columns, !tables.is_empty(), |c| table_sources(&merged, c, &[]))?;
+		next_table += 1;
+		removed.extend(merged.iter().map(|table| table.number()));
+		push_table(&mut tables, &mut removed, table);
+	}
+
+	Manifest {
+		generation: index.log.generation(),
+		log_end,
+		next_table,
+		tables: tables.iter().map(|table| table.number()).collect(),
+	}.write(path)?;
+	index::remove_tables(path, &removed);
+
+	Ok(Index {
+		log: index.log.clone(),
+		tables,
+		delta: new_delta(columns),
+		log_end,
+		next_table,
+	})
+}
+
+/// Rewrites the log with live values only, indexed by a single table.
+fn compact(path: &Path, columns: usize, index: &Index) -> io::Result<Index> {
+	let number = index.next_table;
+	let (log, table) = write_compacted(path, columns, index, number, &AtomicBool::new(false))?;
+	let index = Index { next_table: number + 1, ..index.clone() };
+	let log_end = index.log.len();
+	install_compacted(path, columns, &index, log, table, number, log_end)
+}
+
+/// Writes the live values of given index to a new log next to the current one and indexes
+/// them with a table of given number. Stops with `ErrorKind::Interrupted` once `cancel` is set.
+fn write_compacted(path: &Path, columns: usize, index: &Index, number: u64, cancel: &AtomicBool) -> io::Result<(File, Table)> {
+	let log = File::create(compacted_log_path(path), index.log.generation() + 1)?;
+	let mut writer = TableWriter::create(path, number)?;
+
+	{
+		let mut batch = Batch::new();
+		let mut written = Vec::new();
+
+		// table entries have to be written in order, so they are pushed once their batch is appended.
+		let write_batch = |batch: Batch, written: &mut Vec<(usize, Vec<u8>, Location)>, writer: &mut TableWriter| -> io::Result<()> {
+			let offset = log.append(&batch.finish())?;
+			for (c, key, location) in written.drain(..) {
+				writer.push(c, &key, Some(Location { offset: offset + location.offset, len: location.len }))?;
+			}
+			Ok(())
+		};
+
+		for c in 0..columns {
+			for item in Merge::new(index.sources(c, &[])) {
+				if cancel.load(Ordering::Acquire) {
+					return Err(io::Error::new(io::ErrorKind::Interrupted, "Compaction cancelled"));
+				}
+
+				let (key, location) = match item? {
+					(key, Entry::Disk(location)) => (key, location),
+					_ => continue,
+				};
+
+				let value = index.log.read(location)?;
+				let location = batch.insert(column_id(c), &key, &value);
+				written.push((c, key, location));
+
+				if batch.len() >= COMPACTION_BATCH_SIZE {
+					write_batch(mem::replace(&mut batch, Batch::new()), &mut written, &mut writer)?;
+				}
+			}
+		}
+
+		if !batch.is_empty() {
+			write_batch(batch, &mut written, &mut writer)?;
+		}
+	}
+
+	let table = writer.finish(columns)?;
+	Ok((log, table))
+}
+
+/// Replaces the log of given index with the rewritten one, which covers the current log up to
+/// `from`. Batches written to the current log after `from` are copied to the rewritten log first
+/// and indexed by the delta of the returned index.
+fn install_compacted(path: &Path, columns: usize, index: &Index, log: File, table: Table, number: u64, from: u64) -> io::Result<Index> {
+	let log_end = log.len();
+	let mut delta = new_delta(columns);
+
+	index.log.replay(from, |_, records| {
+		let mut batch = Batch::new();
+		let mut updates = Vec::with_capacity(records.len());
+		for record in records {
+			match record {
+				Record::Insert { col, key, location } => {
+					let value = index.log.read(location)?;
+					let location = batch.insert(col, &key, &value);
+					updates.push((col, key, Some(location)));
+				},
+				Record::Delete { col, key } => {
+					batch.delete(col, &key);
+					updates.push((col, key, None));
+				},
+			}
+		}
+
+		if batch.is_empty() {
+			return Ok(());
+		}
+
+		let offset = log.append(&batch.finish())?;
+		let delta = Arc::make_mut(&mut delta);
+		for (col, key, location) in updates {
+			if let Some(c) = column_index(columns, col) {
+				let location = location.map(|location| Location { offset: offset + location.offset, len: location.len });
+				delta[c].insert(key, location);
+			}
+		}
+		Ok(())
+	})?;
+
+	// a crash before the log is renamed leaves a manifest of the new generation next to the old
+	// log, which is then reindexed from scratch.
+	Manifest {
+		generation: log.generation(),
+		log_end,
+		next_table: index.next_table,
+		tables: vec![number],
+	}.write(path)?;
+	fs::rename(compacted_log_path(path), path.join(FILE_NAME))?;
+	index::remove_tables(path, &index.tables.iter().map(|table| table.number()).collect::<Vec<_>>());
+
+	Ok(Index {
+		log: Arc::new(log),
+		tables: vec![Arc::new(table)],
+		delta,
+		log_end,
+		next_table: index.next_table,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+	use std::fs::OpenOptions;
+	use std::io::{ErrorKind, Write};
+	use std::sync::Arc;
+	use std::thread;
+	use tempdir::TempDir;
+	use kvdb::{DBTransaction, KeyValueDB};
+	use file::Batch;
+	use super::{Database, DatabaseConfig, FILE_NAME, DELTA_LIMIT};
+
+	fn open(tempdir: &TempDir) -> Database {
+		let path = tempdir.path().join("db");
+		Database::open(&DatabaseConfig::with_columns(Some(2)), path.to_str().unwrap()).unwrap()
+	}
+
+	fn collect<'a>(iter: Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a>) -> Vec<(Vec<u8>, Vec<u8>)> {
+		iter.map(|(k, v)| (k.into_vec(), v.into_vec())).collect()
+	}
+
+	fn append_to_log(tempdir: &TempDir, data: &[u8]) {
+		let mut file = OpenOptions::new().append(true).open(tempdir.path().join("db").join(FILE_NAME)).unwrap();
+		file.write_all(data).unwrap();
+	}
+
+	#[test]
+	fn test_db() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+
+		let mut batch = DBTransaction::new();
+		batch.put(None, b"default", b"value");
+		batch.put(Some(0), b"key1", b"cat");
+		batch.put(Some(1), b"key1", b"dog");
+		db.write(batch).unwrap();
+
+		assert_eq!(&*db.get(None, b"default").unwrap().unwrap(), b"value");
+		assert_eq!(&*db.get(Some(0), b"key1").unwrap().unwrap(), b"cat");
+		assert_eq!(&*db.get(Some(1), b"key1").unwrap().unwrap(), b"dog");
+		assert!(db.get(Some(2), b"key1").is_err());
+
+		let mut batch = DBTransaction::new();
+		batch.delete(Some(0), b"key1");
+		batch.put(Some(1), b"key1", b"horse");
+		db.write_buffered(batch);
+
+		// pending writes are visible before flush
+		assert!(db.get(Some(0), b"key1").unwrap().is_none());
+		assert_eq!(&*db.get(Some(1), b"key1").unwrap().unwrap(), b"horse");
+		db.flush().unwrap();
+
+		drop(db);
+		let db = open(&tempdir);
+		assert_eq!(&*db.get(None, b"default").unwrap().unwrap(), b"value");
+		assert!(db.get(Some(0), b"key1").unwrap().is_none());
+		assert_eq!(&*db.get(Some(1), b"key1").unwrap().unwrap(), b"horse");
+	}
+
+	#[test]
+	fn test_iteration() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), &[1, 1], b"a");
+		batch.put(Some(0), &[1, 3], b"b");
+		batch.put(Some(0), &[2, 1], b"c");
+		batch.put(Some(0), &[3, 1], b"d");
+		db.write(batch).unwrap();
+
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), &[1, 2], b"e");
+		batch.delete(Some(0), &[1, 1]);
+		batch.delete(Some(0), &[2, 1]);
+		db.write_buffered(batch);
+
+		assert_eq!(collect(db.iter(Some(0))), vec![
+			(vec![1, 2], b"e".to_vec()),
+			(vec![1, 3], b"b".to_vec()),
+			(vec![3, 1], b"d".to_vec()),
+		]);
+		assert_eq!(collect(db.iter_from_prefix(Some(0), &[2])), vec![(vec![3, 1], b"d".to_vec())]);
+		assert!(db.iter(Some(1)).next().is_none());
+
+		assert_eq!(&*db.get_by_prefix(Some(0), &[1]).unwrap(), b"e");
+		assert!(db.get_by_prefix(Some(0), &[2]).is_none());
+
+		db.flush().unwrap();
+		assert_eq!(&*db.get_by_prefix(Some(0), &[1]).unwrap(), b"e");
+		assert_eq!(collect(db.iter(Some(0))).len(), 3);
+	}
+
+	#[test]
+	fn test_index_tables() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+		let mut expected = BTreeMap::new();
+
+		// enough writes for many index tables of different sizes, merged with each other.
+		for round in 0..40u32 {
+			let mut batch = DBTransaction::new();
+			for i in 0..(DELTA_LIMIT as u32 / 2) {
+				let key = [(i * 7 + round) as u8 % 64, i as u8 % 3];
+				if (i + round) % 5 == 0 {
+					batch.delete(Some(1), &key);
+					expected.remove(&key.to_vec());
+				} else {
+					let value = vec![round as u8; 1 + i as usize % 20];
+					batch.put(Some(1), &key, &value);
+					expected.insert(key.to_vec(), value);
+				}
+			}
+			db.write(batch).unwrap();
+
+			if round % 10 == 9 {
+				let expected: Vec<_> = expected.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+				assert_eq!(collect(db.iter(Some(1))), expected);
+			}
+		}
+
+		assert!(tempdir.path().join("db").join("logdb.index").exists());
+		let check = |db: &Database| {
+			for (key, value) in &expected {
+				assert_eq!(&*db.get(Some(1), key).unwrap().unwrap(), &value[..]);
+			}
+			for i in 0..64u8 {
+				for j in 0..3u8 {
+					let key = vec![i, j];
+					assert_eq!(db.get(Some(1), &key).unwrap().map(|value| value.into_vec()), expected.get(&key).cloned());
+				}
+				let from_prefix = collect(db.iter_from_prefix(Some(1), &[i]));
+				let expected_from_prefix: Vec<_> = expected.range(vec![i]..).map(|(k, v)| (k.clone(), v.clone())).collect();
+				assert_eq!(from_prefix, expected_from_prefix);
+			}
+			assert!(db.iter(Some(0)).next().is_none());
+		};
+
+		check(&db);
+		drop(db);
+		let db = open(&tempdir);
+		check(&db);
+	}
+
+	#[test]
+	fn test_incomplete_write_is_discarded() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), b"key", b"value");
+		db.write(batch).unwrap();
+		drop(db);
+
+		// half of a batch, as left by a crash during the write
+		let mut batch = Batch::new();
+		batch.insert(Some(0), b"key", b"other value");
+		let batch = batch.finish();
+		append_to_log(&tempdir, &batch[..batch.len() / 2]);
+
+		let db = open(&tempdir);
+		assert_eq!(&*db.get(Some(0), b"key").unwrap().unwrap(), b"value");
+
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), b"key2", b"value2");
+		db.write(batch).unwrap();
+		drop(db);
+
+		let db = open(&tempdir);
+		assert_eq!(&*db.get(Some(0), b"key").unwrap().unwrap(), b"value");
+		assert_eq!(&*db.get(Some(0), b"key2").unwrap().unwrap(), b"value2");
+	}
+
+	#[test]
+	fn test_corruption_is_reported() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+
+		for key in &[b"key1", b"key2"] {
+			let mut batch = DBTransaction::new();
+			batch.put(Some(0), *key, b"value");
+			db.write(batch).unwrap();
+		}
+		drop(db);
+
+		// damage the first batch, which is followed by an intact one.
+		let log_path = tempdir.path().join("db").join(FILE_NAME);
+		let mut log = ::std::fs::read(&log_path).unwrap();
+		log[60] ^= 1;
+		::std::fs::write(&log_path, &log).unwrap();
+
+		let path = tempdir.path().join("db");
+		match Database::open(&DatabaseConfig::with_columns(Some(2)), path.to_str().unwrap()) {
+			Err(ref e) if e.kind() == ErrorKind::InvalidData => {},
+			Err(e) => panic!("unexpected error: {}", e),
+			Ok(_) => panic!("corrupted database was opened"),
+		}
+		assert_eq!(::std::fs::read(&log_path).unwrap(), log);
+	}
+
+	#[test]
+	fn test_compaction() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+		let file_len = || tempdir.path().join("db").join(FILE_NAME).metadata().unwrap().len();
+
+		for i in 0..60u32 {
+			let mut batch = DBTransaction::new();
+			batch.put(Some(1), b"key", &[i as u8; 100]);
+			batch.put(Some(1), &[i as u8], b"value");
+			db.write(batch).unwrap();
+		}
+
+		let before = file_len();
+		db.compact().unwrap();
+		assert!(file_len() < before / 4);
+		assert_eq!(&*db.get(Some(1), b"key").unwrap().unwrap(), &[59u8; 100][..]);
+
+		drop(db);
+		let db = open(&tempdir);
+		assert_eq!(&*db.get(Some(1), b"key").unwrap().unwrap(), &[59u8; 100][..]);
+		assert_eq!(collect(db.iter(Some(1))).len(), 61);
+	}
+
+	#[test]
+	fn test_online_compaction() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+		let file_len = || tempdir.path().join("db").join(FILE_NAME).metadata().unwrap().len();
+
+		let mut written = 0;
+		for i in 0..200u32 {
+			let mut batch = DBTransaction::new();
+			batch.put(Some(1), b"key", &[i as u8; 200]);
+			batch.put(Some(1), &[i as u8], b"value");
+			db.write(batch).unwrap();
+			written += 200;
+		}
+
+		// the log was compacted while writing, and the old values are not kept around.
+		{
+			let _guard = db.flushing_lock.lock();
+			db.finish_compaction(true).unwrap();
+		}
+		assert!(file_len() < written / 2);
+		let iter = db.iter(Some(1));
+		assert_eq!(&*db.get(Some(1), b"key").unwrap().unwrap(), &[199u8; 200][..]);
+		assert_eq!(collect(iter).len(), 201);
+
+		drop(db);
+		let db = open(&tempdir);
+		assert_eq!(&*db.get(Some(1), b"key").unwrap().unwrap(), &[199u8; 200][..]);
+		assert_eq!(collect(db.iter(Some(1))).len(), 201);
+	}
+
+	#[test]
+	fn test_background_compaction_keeps_later_writes() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+
+		for i in 0..100u8 {
+			let mut batch = DBTransaction::new();
+			batch.put(Some(0), &[i], &[i; 50]);
+			db.write(batch).unwrap();
+		}
+
+		{
+			let _guard = db.flushing_lock.lock();
+			let index = db.index.read().clone();
+			db.start_compaction(index).unwrap();
+		}
+
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), b"later", b"value");
+		batch.delete(Some(0), &[0]);
+		db.write(batch).unwrap();
+
+		{
+			let _guard = db.flushing_lock.lock();
+			db.finish_compaction(true).unwrap();
+		}
+		assert!(db.compaction.lock().is_none());
+
+		let check = |db: &Database| {
+			assert_eq!(&*db.get(Some(0), b"later").unwrap().unwrap(), b"value");
+			assert!(db.get(Some(0), &[0]).unwrap().is_none());
+			assert_eq!(&*db.get(Some(0), &[99]).unwrap().unwrap(), &[99; 50][..]);
+			assert_eq!(collect(db.iter(Some(0))).len(), 100);
+		};
+
+		check(&db);
+		drop(db);
+		check(&open(&tempdir));
+	}
+
+	#[test]
+	fn test_concurrent_reads() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = Arc::new(open(&tempdir));
+
+		let mut batch = DBTransaction::new();
+		for i in 0..100u8 {
+			batch.put(Some(0), &[i], &[i; 50]);
+		}
+		db.write(batch).unwrap();
+
+		let readers: Vec<_> = (0..4).map(|_| {
+			let db = db.clone();
+			thread::spawn(move || for _ in 0..20 {
+				for i in 0..100u8 {
+					assert_eq!(&*db.get(Some(0), &[i]).unwrap().unwrap(), &[i; 50][..]);
+				}
+				assert_eq!(collect(db.iter(Some(0))).len(), 100);
+			})
+		}).collect();
+
+		// writes to another column cause checkpoints and compactions meanwhile.
+		for i in 0..200u32 {
+			let mut batch = DBTransaction::new();
+			batch.put(Some(1), b"key", &[i as u8; 200]);
+			batch.put(Some(1), &[i as u8], b"value");
+			db.write(batch).unwrap();
+		}
+
+		for reader in readers {
+			reader.join().unwrap();
+		}
+	}
+
+	#[test]
+	fn test_restore() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open(&tempdir);
+		let mut batch = DBTransaction::new();
+		batch.put(Some(0), b"old", b"value");
+		db.write(batch).unwrap();
+
+		let new_path = tempdir.path().join("new");
+		{
+			let new_db = Database::open(&DatabaseConfig::with_columns(Some(2)), new_path.to_str().unwrap()).unwrap();
+			let mut batch = DBTransaction::new();
+			batch.put(Some(0), b"new", b"value");
+			new_db.write(batch).unwrap();
+		}
+
+		db.restore(new_path.to_str().unwrap()).unwrap();
+		assert!(db.get(Some(0), b"old").unwrap().is_none());
+		assert_eq!(&*db.get(Some(0), b"new").unwrap().unwrap(), b"value");
+		assert!(!new_path.exists());
+	}
+}
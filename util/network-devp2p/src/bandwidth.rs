@@ -0,0 +1,188 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bandwidth accounting and rate limiting.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use parking_lot::Mutex;
+use network::{NetworkConfiguration, ProtocolId, TrafficStats};
+
+/// Protocol id base RLPx packets are accounted under.
+pub const P2P_PROTOCOL: ProtocolId = *b"p2p";
+
+/// Token bucket holding at most one second worth of transfer. A transfer may overdraw the bucket,
+/// no further transfers are allowed until it has been refilled.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+	rate: u64,
+	available: i64,
+	updated: Instant,
+}
+
+impl RateLimiter {
+	/// Create a limiter for `rate` bytes per second.
+	pub fn new(rate: u64) -> Self {
+		RateLimiter {
+			rate,
+			available: rate as i64,
+			updated: Instant::now(),
+		}
+	}
+
+	/// Whether a transfer is allowed now.
+	pub fn allows(&mut self) -> bool {
+		self.allows_at(Instant::now())
+	}
+
+	/// Account for transferred bytes.
+	pub fn consume(&mut self, bytes: usize) {
+		self.available -= bytes as i64;
+	}
+
+	fn allows_at(&mut self, now: Instant) -> bool {
+		let elapsed = now.duration_since(self.updated);
+		let refill = elapsed.as_secs().saturating_mul(self.rate)
+			.saturating_add(u64::from(elapsed.subsec_nanos()).saturating_mul(self.rate) / 1_000_000_000);
+		// Keep the elapsed time until it amounts to at least one byte.
+		if refill > 0 {
+			self.available = (self.available.saturating_add(refill as i64)).min(self.rate as i64);
+			self.updated = now;
+		}
+		self.available > 0
+	}
+}
+
+struct State {
+	upload: Option<RateLimiter>,
+	download: Option<RateLimiter>,
+	total: TrafficStats,
+	protocols: HashMap<ProtocolId, TrafficStats>,
+}
+
+/// Traffic counters and global rate limits shared by all sessions.
+pub struct Bandwidth {
+	peer_upload_rate: Option<u64>,
+	peer_download_rate: Option<u64>,
+	state: Mutex<State>,
+}
+
+impl Bandwidth {
+	/// Create with the limits given in the network configuration.
+	pub fn new(config: &NetworkConfiguration) -> Self {
+		Bandwidth {
+			peer_upload_rate: config.max_peer_upload_rate,
+			peer_download_rate: config.max_peer_download_rate,
+			state: Mutex::new(State {
+				upload: config.max_upload_rate.map(RateLimiter::new),
+				download: config.max_download_rate.map(RateLimiter::new),
+				total: TrafficStats::default(),
+				protocols: HashMap::new(),
+			}),
+		}
+	}
+
+	/// Upload limiter for a new session.
+	pub fn peer_upload_limiter(&self) -> Option<RateLimiter> {
+		self.peer_upload_rate.map(RateLimiter::new)
+	}
+
+	/// Download limiter for a new session.
+	pub fn peer_download_limiter(&self) -> Option<RateLimiter> {
+		self.peer_download_rate.map(RateLimiter::new)
+	}
+
+	/// Whether the global upload limit allows sending now.
+	pub fn can_send(&self) -> bool {
+		self.state.lock().upload.as_mut().map_or(true, RateLimiter::allows)
+	}
+
+	/// Whether the global download limit allows receiving now.
+	pub fn can_receive(&self) -> bool {
+		self.state.lock().download.as_mut().map_or(true, RateLimiter::allows)
+	}
+
+	/// Account for bytes sent for `protocol`.
+	pub fn note_sent(&self, protocol: ProtocolId, bytes: usize) {
+		let mut state = self.state.lock();
+		if let Some(ref mut upload) = state.upload {
+			upload.consume(bytes);
+		}
+		state.total.sent += bytes as u64;
+		state.protocols.entry(protocol).or_insert_with(TrafficStats::default).sent += bytes as u64;
+	}
+
+	/// Account for bytes received for `protocol`.
+	pub fn note_received(&self, protocol: ProtocolId, bytes: usize) {
+		let mut state = self.state.lock();
+		if let Some(ref mut download) = state.download {
+			download.consume(bytes);
+		}
+		state.total.received += bytes as u64;
+		state.protocols.entry(protocol).or_insert_with(TrafficStats::default).received += bytes as u64;
+	}
+
+	/// Traffic of all sessions.
+	pub fn total(&self) -> TrafficStats {
+		self.state.lock().total
+	}
+
+	/// Traffic per protocol.
+	pub fn protocols(&self) -> BTreeMap<ProtocolId, TrafficStats> {
+		self.state.lock().protocols.iter().map(|(p, t)| (*p, *t)).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn limiter_refills() {
+		let mut limiter = RateLimiter::new(1000);
+		let now = limiter.updated;
+		assert!(limiter.allows_at(now));
+		limiter.consume(1500);
+		assert!(!limiter.allows_at(now));
+		assert!(!limiter.allows_at(now + Duration::from_millis(500)));
+		assert!(limiter.allows_at(now + Duration::from_millis(501)));
+		// The bucket never holds more than a second worth of bytes.
+		limiter.allows_at(now + Duration::from_secs(10));
+		limiter.consume(1000);
+		assert!(!limiter.allows_at(now + Duration::from_secs(10)));
+	}
+
+	#[test]
+	fn counts_traffic() {
+		let mut config = NetworkConfiguration::new();
+		config.max_upload_rate = Some(100);
+		let bandwidth = Bandwidth::new(&config);
+		assert!(bandwidth.peer_upload_limiter().is_none());
+
+		bandwidth.note_sent(*b"eth", 60);
+		assert!(bandwidth.can_send());
+		bandwidth.note_sent(*b"eth", 140);
+		bandwidth.note_received(P2P_PROTOCOL, 10);
+		assert!(!bandwidth.can_send());
+		assert!(bandwidth.can_receive());
+
+		assert_eq!(bandwidth.total(), TrafficStats { sent: 200, received: 10 });
+		let protocols = bandwidth.protocols();
+		assert_eq!(protocols[b"eth"], TrafficStats { sent: 200, received: 0 });
+		assert_eq!(protocols[&P2P_PROTOCOL], TrafficStats { sent: 0, received: 10 });
+	}
+}
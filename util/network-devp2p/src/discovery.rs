@@ -24,6 +24,7 @@ use hash::keccak;
 use ethereum_types::{H256, H520};
 use rlp::{Rlp, RlpStream};
 use node_table::*;
+use enr::NodeRecord;
 use network::{Error, ErrorKind};
 use ethkey::{Secret, KeyPair, sign, recover};
 use network::IpFilter;
@@ -41,10 +42,13 @@ const PACKET_PING: u8 = 1;
 const PACKET_PONG: u8 = 2;
 const PACKET_FIND_NODE: u8 = 3;
 const PACKET_NEIGHBOURS: u8 = 4;
+const PACKET_ENR_REQUEST: u8 = 5;
+const PACKET_ENR_RESPONSE: u8 = 6;
 
 const PING_TIMEOUT: Duration = Duration::from_millis(500);
 const FIND_NODE_TIMEOUT: Duration = Duration::from_secs(2);
 const EXPIRY_TIME: Duration = Duration::from_secs(20);
+const BOND_EXPIRATION: Duration = Duration::from_secs(24 * 60 * 60);	// Time a Pong proves the endpoint of a node for.
 const MAX_NODES_PING: usize = 32; // Max nodes to add/ping at once
 const REQUEST_BACKOFF: [Duration; 4] = [
 	Duration::from_secs(1),
@@ -65,6 +69,8 @@ pub struct BucketEntry {
 	pub last_seen: Instant,
 	backoff_until: Instant,
 	fail_count: usize,
+	// Sequence number of the latest record we have received from the node
+	record_seq: Option<u64>,
 }
 
 impl BucketEntry {
@@ -76,6 +82,7 @@ impl BucketEntry {
 			last_seen: now,
 			backoff_until: now,
 			fail_count: 0,
+			record_seq: None,
 		}
 	}
 }
@@ -101,6 +108,13 @@ struct PingRequest {
 	deprecated_echo_hash: H256,
 }
 
+struct EnrRequest {
+	// Time when the request was sent
+	sent_at: Instant,
+	// The hash of the request packet, echoed in the response
+	packet_hash: H256,
+}
+
 pub struct NodeBucket {
 	nodes: VecDeque<BucketEntry>, //sorted by last active
 }
@@ -136,6 +150,8 @@ pub struct Discovery<'a> {
 	node_buckets: Vec<NodeBucket>,
 	in_flight_pings: HashMap<NodeId, PingRequest>,
 	in_flight_find_nodes: HashMap<NodeId, FindNodeRequest>,
+	in_flight_enr_requests: HashMap<NodeId, EnrRequest>,
+	node_record: Option<NodeRecord>,
 	send_queue: VecDeque<Datagram>,
 	check_timestamps: bool,
 	adding_nodes: Vec<NodeEntry>,
@@ -146,6 +162,7 @@ pub struct Discovery<'a> {
 pub struct TableUpdates {
 	pub added: HashMap<NodeId, NodeEntry>,
 	pub removed: HashSet<NodeId>,
+	pub records: HashMap<NodeId, NodeRecord>,
 }
 
 impl<'a> Discovery<'a> {
@@ -162,6 +179,8 @@ impl<'a> Discovery<'a> {
 			node_buckets: (0..ADDRESS_BITS).map(|_| NodeBucket::new()).collect(),
 			in_flight_pings: HashMap::new(),
 			in_flight_find_nodes: HashMap::new(),
+			in_flight_enr_requests: HashMap::new(),
+			node_record: None,
 			send_queue: VecDeque::new(),
 			check_timestamps: true,
 			adding_nodes: Vec::new(),
@@ -170,6 +189,12 @@ impl<'a> Discovery<'a> {
 		}
 	}
 
+	/// Set our own node record. Its sequence number is announced in Ping and Pong packets and the
+	/// record itself is served to ENRRequest packets.
+	pub fn set_node_record(&mut self, record: NodeRecord) {
+		self.node_record = Some(record);
+	}
+
 	/// Add a new node to discovery table. Pings the node.
 	pub fn add_node(&mut self, e: NodeEntry) {
 		// If distance returns None, then we are trying to add ourself.
@@ -223,7 +248,7 @@ impl<'a> Discovery<'a> {
 		if let Some(node) = ping {
 			self.try_ping(node);
 		}
-		Some(TableUpdates { added: added_map, removed: HashSet::new() })
+		Some(TableUpdates { added: added_map, removed: HashSet::new(), records: HashMap::new() })
 	}
 
 	/// Starts the discovery process at round 0
@@ -323,11 +348,14 @@ impl<'a> Discovery<'a> {
 	}
 
 	fn ping(&mut self, node: &NodeEntry) -> Result<(), Error> {
-		let mut rlp = RlpStream::new_list(4);
+		let mut rlp = RlpStream::new_list(if self.node_record.is_some() { 5 } else { 4 });
 		rlp.append(&PROTOCOL_VERSION);
 		self.public_endpoint.to_rlp_list(&mut rlp);
 		node.endpoint.to_rlp_list(&mut rlp);
 		append_expiration(&mut rlp);
+		if let Some(ref record) = self.node_record {
+			rlp.append(&record.seq());
+		}
 		let old_parity_hash = keccak(rlp.as_raw());
 		let hash = self.send_packet(PACKET_PING, &node.endpoint.udp_address(), &rlp.drain())?;
 
@@ -358,6 +386,40 @@ impl<'a> Discovery<'a> {
 		Ok(())
 	}
 
+	fn send_enr_request(&mut self, node: &NodeEntry) -> Result<(), Error> {
+		let mut rlp = RlpStream::new_list(1);
+		append_expiration(&mut rlp);
+		let hash = self.send_packet(PACKET_ENR_REQUEST, &node.endpoint.udp_address(), &rlp.drain())?;
+
+		self.in_flight_enr_requests.insert(node.id, EnrRequest {
+			sent_at: Instant::now(),
+			packet_hash: hash,
+		});
+
+		trace!(target: "discovery", "Sent ENRRequest to {:?}", &node.endpoint);
+		Ok(())
+	}
+
+	/// Request the record of a node in our table if it announced a newer one than we know of.
+	fn request_record_if_newer(&mut self, node: &NodeEntry, seq: u64) {
+		let id_hash = keccak(node.id);
+		let dist = match Discovery::distance(&self.id_hash, &id_hash) {
+			Some(dist) => dist,
+			None => return,
+		};
+		let known_seq = match self.node_buckets[dist].nodes.iter().find(|n| n.id_hash == id_hash) {
+			Some(entry) => entry.record_seq,
+			None => return,
+		};
+		if known_seq.map_or(false, |known| known >= seq) || self.in_flight_enr_requests.contains_key(&node.id) {
+			return;
+		}
+		self.send_enr_request(node)
+			.unwrap_or_else(|e| {
+				warn!(target: "discovery", "Error sending ENRRequest packet: {:?}", e);
+			});
+	}
+
 	fn send_packet(&mut self, packet_id: u8, address: &SocketAddr, payload: &[u8]) -> Result<H256, Error> {
 		let packet = assemble_packet(packet_id, payload, &self.secret)?;
 		let hash = H256::from(&packet[0..32]);
@@ -443,6 +505,8 @@ impl<'a> Discovery<'a> {
 			PACKET_PONG => self.on_pong(&rlp, &node_id, &from),
 			PACKET_FIND_NODE => self.on_find_node(&rlp, &node_id, &from),
 			PACKET_NEIGHBOURS => self.on_neighbours(&rlp, &node_id, &from),
+			PACKET_ENR_REQUEST => self.on_enr_request(&rlp, &node_id, &from, &hash_signed),
+			PACKET_ENR_RESPONSE => self.on_enr_response(&rlp, &node_id, &from),
 			_ => {
 				debug!(target: "discovery", "Unknown UDP packet: {}", packet_id);
 				Ok(None)
@@ -464,14 +528,27 @@ impl<'a> Discovery<'a> {
 		entry.endpoint.is_allowed(&self.ip_filter) && entry.id != self.id
 	}
 
+	/// Checks whether the node answered our Ping from given address recently.
+	fn is_bonded(&self, node_id: &NodeId, from: &SocketAddr) -> bool {
+		let id_hash = keccak(node_id);
+		let dist = match Discovery::distance(&self.id_hash, &id_hash) {
+			Some(dist) => dist,
+			None => return false,
+		};
+		self.node_buckets[dist].nodes.iter()
+			.find(|n| n.id_hash == id_hash)
+			.map_or(false, |n| n.address.endpoint.address.ip() == from.ip() && n.last_seen.elapsed() < BOND_EXPIRATION)
+	}
+
 	fn on_ping(&mut self, rlp: &Rlp, node_id: &NodeId, from: &SocketAddr, echo_hash: &[u8]) -> Result<Option<TableUpdates>, Error> {
 		trace!(target: "discovery", "Got Ping from {:?}", &from);
 		let ping_from = NodeEndpoint::from_rlp(&rlp.at(1)?)?;
 		let ping_to = NodeEndpoint::from_rlp(&rlp.at(2)?)?;
 		let timestamp: u64 = rlp.val_at(3)?;
 		self.check_timestamp(timestamp)?;
+		let record_seq: Option<u64> = rlp.val_at(4).ok();
 
-		let mut response = RlpStream::new_list(3);
+		let mut response = RlpStream::new_list(if self.node_record.is_some() { 4 } else { 3 });
 		let pong_to = NodeEndpoint {
 			address: from.clone(),
 			udp_port: ping_from.udp_port
@@ -486,6 +563,9 @@ impl<'a> Discovery<'a> {
 
 		response.append(&echo_hash);
 		append_expiration(&mut response);
+		if let Some(ref record) = self.node_record {
+			response.append(&record.seq());
+		}
 		self.send_packet(PACKET_PONG, from, &response.drain())?;
 
 		let entry = NodeEntry { id: *node_id, endpoint: pong_to.clone() };
@@ -495,6 +575,9 @@ impl<'a> Discovery<'a> {
 			debug!(target: "discovery", "Address not allowed: {:?}", entry);
 		} else {
 			self.add_node(entry.clone());
+			if let Some(seq) = record_seq {
+				self.request_record_if_newer(&entry, seq);
+			}
 		}
 		Ok(None)
 	}
@@ -505,6 +588,7 @@ impl<'a> Discovery<'a> {
 		let echo_hash: H256 = rlp.val_at(1)?;
 		let timestamp: u64 = rlp.val_at(2)?;
 		self.check_timestamp(timestamp)?;
+		let record_seq: Option<u64> = rlp.val_at(3).ok();
 
 		let expected_node = match self.in_flight_pings.entry(*node_id) {
 			Entry::Occupied(entry) => {
@@ -532,7 +616,11 @@ impl<'a> Discovery<'a> {
 		};
 
 		if let Some(node) = expected_node {
-			Ok(self.update_node(node))
+			let updates = self.update_node(node.clone());
+			if let Some(seq) = record_seq {
+				self.request_record_if_newer(&node, seq);
+			}
+			Ok(updates)
 		} else {
 			debug!(target: "discovery", "Got unexpected Pong from {:?} ; request not found", &from);
 			Ok(None)
@@ -626,6 +714,55 @@ impl<'a> Discovery<'a> {
 		Ok(None)
 	}
 
+	fn on_enr_request(&mut self, rlp: &Rlp, node_id: &NodeId, from: &SocketAddr, request_hash: &H256) -> Result<Option<TableUpdates>, Error> {
+		trace!(target: "discovery", "Got ENRRequest from {:?}", &from);
+		let timestamp: u64 = rlp.val_at(0)?;
+		self.check_timestamp(timestamp)?;
+		// The response is much larger than the request, only answer nodes which proved their endpoint.
+		if !self.is_bonded(node_id, from) {
+			debug!(target: "discovery", "Got ENRRequest from unbonded node {:?} ; node_id={:#x}", &from, node_id);
+			return Ok(None);
+		}
+		let mut response = match self.node_record {
+			Some(ref record) => {
+				let mut response = RlpStream::new_list(2);
+				response.append(request_hash);
+				response.append(record);
+				response
+			},
+			None => return Ok(None),
+		};
+		self.send_packet(PACKET_ENR_RESPONSE, from, &response.drain())?;
+		Ok(None)
+	}
+
+	fn on_enr_response(&mut self, rlp: &Rlp, node_id: &NodeId, from: &SocketAddr) -> Result<Option<TableUpdates>, Error> {
+		trace!(target: "discovery", "Got ENRResponse from {:?} ; node_id={:#x}", &from, node_id);
+		let request_hash: H256 = rlp.val_at(0)?;
+		let is_expected = self.in_flight_enr_requests.get(node_id).map_or(false, |request| request.packet_hash == request_hash);
+		if !is_expected {
+			debug!(target: "discovery", "Got unexpected ENRResponse from {:?} ; node_id={:#x}", &from, node_id);
+			return Ok(None);
+		}
+		self.in_flight_enr_requests.remove(node_id);
+
+		let record: NodeRecord = rlp.val_at(1)?;
+		if record.id() != node_id {
+			debug!(target: "discovery", "Got record of another node from {:?} ; node_id={:#x}", &from, node_id);
+			return Ok(None);
+		}
+		let id_hash = keccak(node_id);
+		if let Some(dist) = Discovery::distance(&self.id_hash, &id_hash) {
+			if let Some(entry) = self.node_buckets[dist].nodes.iter_mut().find(|n| n.id_hash == id_hash) {
+				entry.record_seq = Some(record.seq());
+			}
+		}
+
+		let mut records = HashMap::new();
+		records.insert(*node_id, record);
+		Ok(Some(TableUpdates { added: HashMap::new(), removed: HashSet::new(), records }))
+	}
+
 	fn check_expired(&mut self, time: Instant) {
 		let mut nodes_to_expire = Vec::new();
 		self.in_flight_pings.retain(|node_id, ping_request| {
@@ -648,6 +785,14 @@ impl<'a> Discovery<'a> {
 				true
 			}
 		});
+		self.in_flight_enr_requests.retain(|node_id, enr_request| {
+			if time.duration_since(enr_request.sent_at) > PING_TIMEOUT {
+				debug!(target: "discovery", "Removing expired ENR request for node_id={:#x}", node_id);
+				false
+			} else {
+				true
+			}
+		});
 		for node_id in nodes_to_expire {
 			self.expire_node_request(node_id);
 		}
@@ -763,6 +908,7 @@ mod tests {
 	use std::net::{IpAddr,Ipv4Addr};
 	use node_table::{Node, NodeId, NodeEndpoint};
 
+	use std::collections::BTreeMap;
 	use std::str::FromStr;
 	use rustc_hex::FromHex;
 	use ethkey::{Random, Generator};
@@ -1186,4 +1332,77 @@ mod tests {
 			panic!("Expected no changes to discovery1's table for unexpected pong");
 		}
 	}
+
+	#[test]
+	fn test_enr_request() {
+		let key1 = Random.generate().unwrap();
+		let key2 = Random.generate().unwrap();
+		let ep1 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40354").unwrap(), udp_port: 40354 };
+		let ep2 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40355").unwrap(), udp_port: 40355 };
+		let mut discovery1 = Discovery::new(&key1, ep1.clone(), IpFilter::default());
+		let mut discovery2 = Discovery::new(&key2, ep2.clone(), IpFilter::default());
+		discovery1.set_node_record(NodeRecord::new(1, &key1, &ep1, &BTreeMap::new()).unwrap());
+		discovery2.set_node_record(NodeRecord::new(3, &key2, &ep2, &BTreeMap::new()).unwrap());
+
+		discovery1.ping(&NodeEntry { id: discovery2.id, endpoint: ep2.clone() }).unwrap();
+		let ping_data = discovery1.dequeue_send().unwrap();
+		let rlp = Rlp::new(&ping_data.payload[(32 + 65 + 1)..]);
+		assert_eq!(rlp.val_at::<u64>(4).unwrap(), 1);
+		discovery2.on_packet(&ping_data.payload, ep1.address.clone()).unwrap();
+		let pong_data = discovery2.dequeue_send().unwrap();
+		let ping_back = discovery2.dequeue_send().unwrap();
+		assert_eq!(ping_back.payload[32 + 65], PACKET_PING);
+
+		// The Pong announces a record discovery1 doesn't know yet, so it asks for it.
+		discovery1.on_packet(&pong_data.payload, ep2.address.clone()).unwrap();
+		let enr_request = discovery1.dequeue_send().unwrap();
+		assert!(!discovery1.any_sends_queued());
+		assert_eq!(enr_request.payload[32 + 65], PACKET_ENR_REQUEST);
+
+		// discovery2 only answers once discovery1 has answered its Ping, and asks for its record meanwhile.
+		discovery1.on_packet(&ping_back.payload, ep2.address.clone()).unwrap();
+		let pong_back = discovery1.dequeue_send().unwrap();
+		discovery2.on_packet(&pong_back.payload, ep1.address.clone()).unwrap();
+		let enr_request_back = discovery2.dequeue_send().unwrap();
+		assert_eq!(enr_request_back.payload[32 + 65], PACKET_ENR_REQUEST);
+
+		discovery2.on_packet(&enr_request.payload, ep1.address.clone()).unwrap();
+		let enr_response = discovery2.dequeue_send().unwrap();
+		assert_eq!(enr_response.payload[32 + 65], PACKET_ENR_RESPONSE);
+
+		let table_updates = discovery1.on_packet(&enr_response.payload, ep2.address.clone()).unwrap()
+			.expect("a valid ENRResponse updates the node table");
+		let record = &table_updates.records[&discovery2.id];
+		assert_eq!(record.seq(), 3);
+		assert_eq!(record.endpoint(), Some(ep2.clone()));
+		assert!(discovery1.in_flight_enr_requests.is_empty());
+
+		// A response that wasn't asked for is ignored.
+		assert!(discovery1.on_packet(&enr_response.payload, ep2.address.clone()).unwrap().is_none());
+
+		// The record is not requested again while the announced sequence number is unchanged.
+		discovery1.ping(&NodeEntry { id: discovery2.id, endpoint: ep2.clone() }).unwrap();
+		let ping_data = discovery1.dequeue_send().unwrap();
+		discovery2.on_packet(&ping_data.payload, ep1.address.clone()).unwrap();
+		let pong_data = discovery2.dequeue_send().unwrap();
+		discovery1.on_packet(&pong_data.payload, ep2.address.clone()).unwrap();
+		assert!(!discovery1.any_sends_queued());
+	}
+
+	#[test]
+	fn test_enr_request_from_unbonded_node_is_ignored() {
+		let key1 = Random.generate().unwrap();
+		let key2 = Random.generate().unwrap();
+		let ep1 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40356").unwrap(), udp_port: 40356 };
+		let ep2 = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40357").unwrap(), udp_port: 40357 };
+		let mut discovery2 = Discovery::new(&key2, ep2.clone(), IpFilter::default());
+		discovery2.set_node_record(NodeRecord::new(1, &key2, &ep2, &BTreeMap::new()).unwrap());
+
+		let mut rlp = RlpStream::new_list(1);
+		append_expiration(&mut rlp);
+		let enr_request = assemble_packet(PACKET_ENR_REQUEST, &rlp.drain(), key1.secret()).unwrap();
+
+		assert!(discovery2.on_packet(&enr_request, ep1.address.clone()).unwrap().is_none());
+		assert!(!discovery2.any_sends_queued());
+	}
 }
@@ -0,0 +1,471 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! DNS node lists (EIP-1459).
+//!
+//! A node list is a Merkle tree of node records published as TXT records below a domain.
+//! Its root is signed by the list operator and the tree is referred to by an
+//! `enrtree://<base32 compressed public key>@<domain>` url. TXT records are looked up through
+//! a `DnsResolver`; `Zone` answers them from a local zone file.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use ethereum_types::H256;
+use ethkey::{Signature, recover};
+use hash::keccak;
+use network::{Error, ErrorKind};
+use enr::{NodeRecord, compress, from_base64_url};
+
+const TREE_PREFIX: &str = "enrtree://";
+const ROOT_PREFIX: &str = "enrtree-root:v1";
+const BRANCH_PREFIX: &str = "enrtree-branch:";
+const RECORD_PREFIX: &str = "enr:";
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Maximum number of entries fetched from a single tree.
+const MAX_TREE_ENTRIES: usize = 4096;
+/// Maximum number of trees visited when following links.
+const MAX_LINKED_TREES: usize = 16;
+
+/// Source of DNS TXT records.
+pub trait DnsResolver: Send + Sync {
+	/// TXT records of `name`, with the character strings of each record joined.
+	fn txt(&self, name: &str) -> Result<Vec<String>, Error>;
+}
+
+/// In-memory DNS zone holding TXT records only.
+#[derive(Debug, Default, Clone)]
+pub struct Zone {
+	records: HashMap<String, Vec<String>>,
+}
+
+impl Zone {
+	/// Load a zone file. See `Zone::from_str` for the accepted format.
+	pub fn load(path: &Path) -> Result<Zone, Error> {
+		let mut content = String::new();
+		File::open(path)?.read_to_string(&mut content)?;
+		content.parse()
+	}
+
+	/// Add a TXT record for `name`.
+	pub fn insert(&mut self, name: &str, text: &str) {
+		self.records.entry(normalize(name)).or_insert_with(Vec::new).push(text.to_owned());
+	}
+}
+
+impl DnsResolver for Zone {
+	fn txt(&self, name: &str) -> Result<Vec<String>, Error> {
+		Ok(self.records.get(&normalize(name)).cloned().unwrap_or_default())
+	}
+}
+
+impl FromStr for Zone {
+	type Err = Error;
+
+	/// Parse zone file lines of the form `<name> [<ttl>] [IN] TXT "<text>" ["<text>" ...]`.
+	/// Names are fully qualified, empty lines and `;` comments are skipped.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut zone = Zone::default();
+		for (number, line) in s.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with(';') {
+				continue;
+			}
+			let invalid = || ErrorKind::InvalidNodeList(format!("malformed zone file line {}", number + 1));
+			let quote = line.find('"').ok_or_else(&invalid)?;
+			let mut fields = line[..quote].split_whitespace();
+			let name = fields.next().ok_or_else(&invalid)?;
+			if fields.last() != Some("TXT") {
+				bail!(invalid());
+			}
+			let text = join_strings(&line[quote..]).ok_or_else(&invalid)?;
+			zone.insert(name, &text);
+		}
+		Ok(zone)
+	}
+}
+
+/// Location of a node list: the domain of the tree and the key signing its root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeListUrl {
+	public: Vec<u8>,
+	domain: String,
+}
+
+impl NodeListUrl {
+	/// Domain the tree is published under.
+	pub fn domain(&self) -> &str {
+		&self.domain
+	}
+}
+
+impl FromStr for NodeListUrl {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || ErrorKind::InvalidNodeList(format!("invalid url {}", s));
+		if !s.starts_with(TREE_PREFIX) {
+			bail!(invalid());
+		}
+		let mut parts = s[TREE_PREFIX.len()..].splitn(2, '@');
+		let public = parts.next().and_then(from_base32).ok_or_else(&invalid)?;
+		let domain = normalize(parts.next().ok_or_else(&invalid)?);
+		if public.len() != 33 || (public[0] != 0x02 && public[0] != 0x03) || domain.is_empty() {
+			bail!(invalid());
+		}
+		Ok(NodeListUrl { public, domain })
+	}
+}
+
+impl fmt::Display for NodeListUrl {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}@{}", TREE_PREFIX, to_base32(&self.public), self.domain)
+	}
+}
+
+/// Whether `url` refers to a DNS node list rather than a single node.
+pub fn is_node_list_url(url: &str) -> bool {
+	url.starts_with(TREE_PREFIX)
+}
+
+/// Check if node list url is valid
+pub fn validate_node_list_url(url: &str) -> Option<Error> {
+	NodeListUrl::from_str(url).err()
+}
+
+/// Resolve the node records of a node list and of the lists it links to.
+pub fn resolve(resolver: &DnsResolver, url: &NodeListUrl) -> Result<Vec<NodeRecord>, Error> {
+	let mut records = Vec::new();
+	let mut links = VecDeque::new();
+	let mut visited = HashSet::new();
+	visited.insert(url.domain.clone());
+	resolve_tree(resolver, url, &mut records, &mut links)?;
+	while let Some(link) = links.pop_front() {
+		if !visited.insert(link.domain.clone()) {
+			continue;
+		}
+		if visited.len() > MAX_LINKED_TREES {
+			debug!(target: "network", "Not following more than {} node list links from {}", MAX_LINKED_TREES, url);
+			break;
+		}
+		if let Err(e) = resolve_tree(resolver, &link, &mut records, &mut links) {
+			debug!(target: "network", "Error resolving linked node list {}: {}", link, e);
+		}
+	}
+	Ok(records)
+}
+
+enum Entry {
+	Branch(Vec<String>),
+	Record(NodeRecord),
+	Link(NodeListUrl),
+}
+
+impl FromStr for Entry {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s.starts_with(BRANCH_PREFIX) {
+			Ok(Entry::Branch(s[BRANCH_PREFIX.len()..].split(',')
+				.map(str::trim)
+				.filter(|hash| !hash.is_empty())
+				.map(ToOwned::to_owned)
+				.collect()))
+		} else if s.starts_with(RECORD_PREFIX) {
+			Ok(Entry::Record(s.parse()?))
+		} else if s.starts_with(TREE_PREFIX) {
+			Ok(Entry::Link(s.parse()?))
+		} else {
+			bail!(ErrorKind::InvalidNodeList(format!("unknown entry {}", s)))
+		}
+	}
+}
+
+struct Root {
+	enr_root: String,
+	link_root: String,
+}
+
+impl Root {
+	/// Fetch the root of the tree and verify its signature against the key in the url.
+	fn fetch(resolver: &DnsResolver, url: &NodeListUrl) -> Result<Root, Error> {
+		let texts = resolver.txt(&url.domain)?;
+		let text = texts.iter().find(|text| text.starts_with(ROOT_PREFIX)).ok_or_else(|| invalid(url, "missing root"))?;
+		let sig_pos = text.find(" sig=").ok_or_else(|| invalid(url, "unsigned root"))?;
+		let content = &text[..sig_pos];
+		let sig = match from_base64_url(text[sig_pos + 5..].trim()) {
+			Some(ref sig) if sig.len() == 65 => {
+				let v = if sig[64] >= 27 { sig[64] - 27 } else { sig[64] };
+				Signature::from_rsv(&H256::from_slice(&sig[0..32]), &H256::from_slice(&sig[32..64]), v)
+			},
+			_ => return Err(invalid(url, "malformed root signature")),
+		};
+		let public = recover(&sig, &keccak(content)).map_err(|_| invalid(url, "invalid root signature"))?;
+		if compress(&public)[..] != url.public[..] {
+			return Err(invalid(url, "root is not signed by the list key"));
+		}
+
+		let (mut enr_root, mut link_root) = (None, None);
+		for field in content[ROOT_PREFIX.len()..].split_whitespace() {
+			if field.starts_with("e=") {
+				enr_root = Some(field[2..].to_owned());
+			} else if field.starts_with("l=") {
+				link_root = Some(field[2..].to_owned());
+			}
+		}
+		match (enr_root, link_root) {
+			(Some(enr_root), Some(link_root)) => Ok(Root { enr_root, link_root }),
+			_ => Err(invalid(url, "incomplete root")),
+		}
+	}
+}
+
+fn resolve_tree(resolver: &DnsResolver, url: &NodeListUrl, records: &mut Vec<NodeRecord>, links: &mut VecDeque<NodeListUrl>) -> Result<(), Error> {
+	let root = Root::fetch(resolver, url)?;
+	for entry in leaves(resolver, url, &root.enr_root)? {
+		match entry {
+			Entry::Record(record) => records.push(record),
+			_ => return Err(invalid(url, "link in the record tree")),
+		}
+	}
+	for entry in leaves(resolver, url, &root.link_root)? {
+		match entry {
+			Entry::Link(link) => links.push_back(link),
+			_ => return Err(invalid(url, "record in the link tree")),
+		}
+	}
+	Ok(())
+}
+
+/// Fetch all leaves below the branch with the given hash.
+fn leaves(resolver: &DnsResolver, url: &NodeListUrl, hash: &str) -> Result<Vec<Entry>, Error> {
+	let mut pending = vec![hash.to_owned()];
+	let mut leaves = Vec::new();
+	let mut fetched = 0;
+	while let Some(hash) = pending.pop() {
+		fetched += 1;
+		if fetched > MAX_TREE_ENTRIES {
+			return Err(invalid(url, "too many entries"));
+		}
+		let name = format!("{}.{}", hash, url.domain);
+		let text = resolver.txt(&name)?.into_iter()
+			.find(|text| entry_hash(text).eq_ignore_ascii_case(&hash))
+			.ok_or_else(|| invalid(url, &format!("missing entry {}", hash)))?;
+		match text.parse::<Entry>()? {
+			Entry::Branch(children) => pending.extend(children),
+			leaf => leaves.push(leaf),
+		}
+	}
+	Ok(leaves)
+}
+
+fn invalid(url: &NodeListUrl, reason: &str) -> Error {
+	ErrorKind::InvalidNodeList(format!("{}: {}", url.domain, reason)).into()
+}
+
+/// Subdomain of a tree entry: the base32 form of the first 16 bytes of its hash.
+fn entry_hash(text: &str) -> String {
+	to_base32(&keccak(text)[0..16])
+}
+
+fn normalize(name: &str) -> String {
+	name.trim_right_matches('.').to_lowercase()
+}
+
+/// Join the quoted character strings of a TXT record.
+fn join_strings(s: &str) -> Option<String> {
+	let mut text = String::new();
+	let mut chars = s.chars();
+	loop {
+		match chars.next() {
+			None | Some(';') => return Some(text),
+			Some('"') => loop {
+				match chars.next()? {
+					'"' => break,
+					'\\' => text.push(chars.next()?),
+					c => text.push(c),
+				}
+			},
+			Some(c) if c.is_whitespace() => {},
+			Some(_) => return None,
+		}
+	}
+}
+
+/// Unpadded base32 (RFC 4648).
+fn to_base32(data: &[u8]) -> String {
+	let mut out = String::with_capacity((data.len() * 8 + 4) / 5);
+	let (mut buffer, mut bits) = (0u32, 0);
+	for byte in data {
+		buffer = (buffer << 8 | *byte as u32) & 0xfff;
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			out.push(BASE32_ALPHABET[(buffer >> bits) as usize & 0x1f] as char);
+		}
+	}
+	if bits > 0 {
+		out.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 0x1f] as char);
+	}
+	out
+}
+
+fn from_base32(s: &str) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(s.len() * 5 / 8);
+	let (mut buffer, mut bits) = (0u32, 0);
+	for c in s.trim_right_matches('=').bytes() {
+		let value = BASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_uppercase())? as u32;
+		buffer = (buffer << 5 | value) & 0xfff;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((buffer >> bits) as u8);
+		}
+	}
+	Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::BTreeMap;
+	use std::net::SocketAddr;
+	use ethkey::{Random, Generator, KeyPair, sign};
+	use enr::to_base64_url;
+	use node_table::NodeEndpoint;
+
+	fn record(port: u16) -> NodeRecord {
+		let key = Random.generate().unwrap();
+		let endpoint = NodeEndpoint { address: SocketAddr::from_str(&format!("10.0.0.1:{}", port)).unwrap(), udp_port: port };
+		NodeRecord::new(1, &key, &endpoint, &BTreeMap::new()).unwrap()
+	}
+
+	fn url(key: &KeyPair, domain: &str) -> NodeListUrl {
+		format!("{}{}@{}", TREE_PREFIX, to_base32(&compress(key.public())), domain).parse().unwrap()
+	}
+
+	/// Publish `texts` below a new branch, returning the hash of the branch.
+	fn publish(zone: &mut Zone, domain: &str, texts: Vec<String>) -> String {
+		let hashes: Vec<String> = texts.iter().map(|text| {
+			let hash = entry_hash(text);
+			zone.insert(&format!("{}.{}", hash, domain), text);
+			hash
+		}).collect();
+		let branch = format!("{}{}", BRANCH_PREFIX, hashes.join(","));
+		let hash = entry_hash(&branch);
+		zone.insert(&format!("{}.{}", hash, domain), &branch);
+		hash
+	}
+
+	fn publish_tree(zone: &mut Zone, key: &KeyPair, domain: &str, records: &[NodeRecord], links: &[NodeListUrl]) -> NodeListUrl {
+		let enr_root = publish(zone, domain, records.iter().map(ToString::to_string).collect());
+		let link_root = publish(zone, domain, links.iter().map(ToString::to_string).collect());
+		let content = format!("{} e={} l={} seq=1", ROOT_PREFIX, enr_root, link_root);
+		let signature = sign(key.secret(), &keccak(&content)).unwrap();
+		zone.insert(domain, &format!("{} sig={}", content, to_base64_url(&signature[..])));
+		url(key, domain)
+	}
+
+	#[test]
+	fn parses_node_list_url() {
+		let text = "enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2@nodes.example.org";
+		let url = NodeListUrl::from_str(text).unwrap();
+		assert_eq!(url.domain(), "nodes.example.org");
+		assert_eq!(url.public.len(), 33);
+		assert_eq!(url.public[0], 0x03);
+		assert_eq!(url.to_string(), text);
+
+		assert!(is_node_list_url(text));
+		assert!(validate_node_list_url(text).is_none());
+		assert!(validate_node_list_url("enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2").is_some());
+		assert!(validate_node_list_url("enrtree://AM5FCQ@nodes.example.org").is_some());
+		assert!(!is_node_list_url("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770"));
+	}
+
+	#[test]
+	fn base32_roundtrip() {
+		for len in 0..12 {
+			let data: Vec<u8> = (0..len).map(|i| (i * 97 + 251) as u8).collect();
+			assert_eq!(from_base32(&to_base32(&data)), Some(data));
+		}
+		assert_eq!(to_base32(b"foobar"), "MZXW6YTBOI");
+		assert_eq!(from_base32("mzxw6ytboi"), Some(b"foobar".to_vec()));
+		assert_eq!(from_base32("MZ1"), None);
+	}
+
+	#[test]
+	fn parses_zone_file() {
+		let zone: Zone = r#"
+			; node list
+			nodes.example.org.  86900  IN  TXT  "enrtree-root:v1 e=A l=B" " seq=1"
+			a.nodes.example.org TXT "enrtree-branch:" ; empty
+			b.nodes.example.org TXT "with \"quotes\""
+		"#.parse().unwrap();
+		assert_eq!(zone.txt("NODES.example.org").unwrap(), vec!["enrtree-root:v1 e=A l=B seq=1".to_owned()]);
+		assert_eq!(zone.txt("a.nodes.example.org.").unwrap(), vec!["enrtree-branch:".to_owned()]);
+		assert_eq!(zone.txt("b.nodes.example.org").unwrap(), vec!["with \"quotes\"".to_owned()]);
+		assert!(zone.txt("c.nodes.example.org").unwrap().is_empty());
+
+		assert!(Zone::from_str("nodes.example.org A \"1.2.3.4\"").is_err());
+		assert!(Zone::from_str("nodes.example.org TXT \"unterminated").is_err());
+	}
+
+	#[test]
+	fn resolves_linked_trees() {
+		let key = Random.generate().unwrap();
+		let linked_key = Random.generate().unwrap();
+		let mut zone = Zone::default();
+
+		let linked_records = vec![record(30303)];
+		let root_url = url(&key, "nodes.example.org");
+		let linked = publish_tree(&mut zone, &linked_key, "linked.example.org", &linked_records, &[root_url.clone()]);
+		let records = vec![record(30301), record(30302)];
+		let root = publish_tree(&mut zone, &key, "nodes.example.org", &records, &[linked]);
+		assert_eq!(root, root_url);
+
+		let resolved = resolve(&zone, &root).unwrap();
+		assert_eq!(resolved.len(), 3);
+		for record in records.iter().chain(linked_records.iter()) {
+			assert!(resolved.contains(record));
+		}
+	}
+
+	#[test]
+	fn rejects_invalid_trees() {
+		let key = Random.generate().unwrap();
+		let mut zone = Zone::default();
+		let root = publish_tree(&mut zone, &key, "nodes.example.org", &[record(30301)], &[]);
+		assert_eq!(resolve(&zone, &root).unwrap().len(), 1);
+
+		// Root signed by a different key.
+		let forged = url(&Random.generate().unwrap(), "nodes.example.org");
+		assert!(resolve(&zone, &forged).is_err());
+
+		// Record not matching the hash it is published under.
+		let mut tampered = zone.clone();
+		for texts in tampered.records.values_mut() {
+			if texts[0].starts_with(RECORD_PREFIX) {
+				texts[0] = record(30302).to_string();
+			}
+		}
+		assert!(resolve(&tampered, &root).is_err());
+
+		assert!(resolve(&Zone::default(), &root).is_err());
+	}
+}
@@ -0,0 +1,335 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Ethereum Node Records (EIP-778) using the "v4" identity scheme.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use ethereum_types::{H256, H512};
+use ethkey::{KeyPair, Signature, sign, recover};
+use hash::keccak;
+use network::{Error, ErrorKind};
+use node_table::{NodeId, NodeEndpoint};
+use parity_bytes::Bytes;
+use rlp::{self, Rlp, RlpStream, Encodable, Decodable, DecoderError};
+
+/// Maximum size of an RLP-encoded record.
+pub const MAX_RECORD_SIZE: usize = 300;
+
+const RECORD_PREFIX: &str = "enr:";
+const IDENTITY_SCHEME: &[u8] = b"v4";
+/// Keys with a meaning defined by EIP-778. Custom entries may not use them.
+const RESERVED_KEYS: [&str; 8] = ["id", "secp256k1", "ip", "tcp", "udp", "ip6", "tcp6", "udp6"];
+const BASE64_URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A signed node record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeRecord {
+	seq: u64,
+	/// Keys mapped to their RLP-encoded values, in the order required by the encoding.
+	pairs: BTreeMap<Vec<u8>, Bytes>,
+	signature: H512,
+	id: NodeId,
+}
+
+impl NodeRecord {
+	/// Create and sign a record advertising `endpoint` together with custom `entries`.
+	pub fn new(seq: u64, key: &KeyPair, endpoint: &NodeEndpoint, entries: &BTreeMap<String, Bytes>) -> Result<NodeRecord, Error> {
+		let mut pairs = BTreeMap::new();
+		for (k, v) in entries {
+			if RESERVED_KEYS.contains(&k.as_str()) {
+				bail!(ErrorKind::InvalidNodeRecord);
+			}
+			pairs.insert(k.as_bytes().to_vec(), encoded(v));
+		}
+		pairs.insert(b"id".to_vec(), encoded(&IDENTITY_SCHEME));
+		pairs.insert(b"secp256k1".to_vec(), encoded(&&compress(key.public())[..]));
+		match endpoint.address {
+			SocketAddr::V4(a) => pairs.insert(b"ip".to_vec(), encoded(&&a.ip().octets()[..])),
+			SocketAddr::V6(a) => pairs.insert(b"ip6".to_vec(), encoded(&&a.ip().octets()[..])),
+		};
+		pairs.insert(b"tcp".to_vec(), encoded(&endpoint.address.port()));
+		pairs.insert(b"udp".to_vec(), encoded(&endpoint.udp_port));
+
+		let mut record = NodeRecord { seq, pairs, signature: H512::new(), id: *key.public() };
+		let signature = sign(key.secret(), &keccak(record.content()))?;
+		record.signature.copy_from_slice(&signature[0..64]);
+		if rlp::encode(&record).len() > MAX_RECORD_SIZE {
+			bail!(ErrorKind::InvalidNodeRecord);
+		}
+		Ok(record)
+	}
+
+	/// Create a record for the given key, endpoint and entries. `previous` is returned as is
+	/// if it already has this content, otherwise the new record supersedes it.
+	pub fn refresh(previous: Option<NodeRecord>, key: &KeyPair, endpoint: &NodeEndpoint, entries: &BTreeMap<String, Bytes>) -> Result<NodeRecord, Error> {
+		let seq = previous.as_ref().map_or(1, |r| r.seq);
+		let record = NodeRecord::new(seq, key, endpoint, entries)?;
+		match previous {
+			Some(previous) => if previous.id == record.id && previous.pairs == record.pairs {
+				Ok(previous)
+			} else {
+				NodeRecord::new(seq + 1, key, endpoint, entries)
+			},
+			None => Ok(record),
+		}
+	}
+
+	/// Sequence number of the record. Higher numbers supersede lower ones.
+	pub fn seq(&self) -> u64 {
+		self.seq
+	}
+
+	/// Id of the node that signed the record.
+	pub fn id(&self) -> &NodeId {
+		&self.id
+	}
+
+	/// Decode the value stored under `key`, if present and well-formed.
+	pub fn get<T: Decodable>(&self, key: &str) -> Option<T> {
+		self.pairs.get(key.as_bytes()).and_then(|v| Rlp::new(v).as_val().ok())
+	}
+
+	/// The endpoint advertised in the record, if it has one.
+	pub fn endpoint(&self) -> Option<NodeEndpoint> {
+		let tcp_port: u16 = self.get("tcp")?;
+		let udp_port: u16 = self.get("udp").unwrap_or(tcp_port);
+		let address = match self.get::<Vec<u8>>("ip") {
+			Some(ref ip) if ip.len() == 4 =>
+				SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]), tcp_port)),
+			Some(_) => return None,
+			None => {
+				let ip: Vec<u8> = self.get("ip6")?;
+				if ip.len() != 16 {
+					return None;
+				}
+				let mut octets = [0u8; 16];
+				octets.copy_from_slice(&ip);
+				SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(octets), tcp_port, 0, 0))
+			},
+		};
+		Some(NodeEndpoint { address, udp_port })
+	}
+
+	/// Check that the record holds every one of the `required` entries as a byte string value.
+	pub fn matches(&self, required: &BTreeMap<String, Bytes>) -> bool {
+		required.iter().all(|(k, v)| self.get::<Bytes>(k).map_or(false, |value| value == *v))
+	}
+
+	/// The signed part of the record.
+	fn content(&self) -> Bytes {
+		let mut s = RlpStream::new_list(1 + self.pairs.len() * 2);
+		s.append(&self.seq);
+		self.append_pairs(&mut s);
+		s.out()
+	}
+
+	fn append_pairs(&self, s: &mut RlpStream) {
+		for (k, v) in &self.pairs {
+			s.append(k);
+			s.append_raw(v, 1);
+		}
+	}
+
+	/// Check the signature against the public key in the record, returning the node id.
+	fn verify(&self) -> Option<NodeId> {
+		if self.get::<Vec<u8>>("id").map_or(true, |scheme| scheme != IDENTITY_SCHEME) {
+			return None;
+		}
+		let compressed: Vec<u8> = self.get("secp256k1")?;
+		let message = keccak(self.content());
+		let r = H256::from_slice(&self.signature[0..32]);
+		let s = H256::from_slice(&self.signature[32..64]);
+		// The record only carries `r` and `s`, so try both recovery ids.
+		(0..2).filter_map(|v| recover(&Signature::from_rsv(&r, &s, v), &message).ok())
+			.find(|public| compress(public)[..] == compressed[..])
+	}
+}
+
+impl Encodable for NodeRecord {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(2 + self.pairs.len() * 2);
+		s.append(&self.signature);
+		s.append(&self.seq);
+		self.append_pairs(s);
+	}
+}
+
+impl Decodable for NodeRecord {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		if rlp.as_raw().len() > MAX_RECORD_SIZE {
+			return Err(DecoderError::Custom("Node record is too large"));
+		}
+		let item_count = rlp.item_count()?;
+		if item_count < 2 || item_count % 2 != 0 {
+			return Err(DecoderError::RlpIncorrectListLen);
+		}
+		let signature: H512 = rlp.val_at(0)?;
+		let seq: u64 = rlp.val_at(1)?;
+		let mut pairs = BTreeMap::new();
+		for i in 0..(item_count - 2) / 2 {
+			let key: Vec<u8> = rlp.val_at(2 + i * 2)?;
+			if pairs.keys().next_back().map_or(false, |last| *last >= key) {
+				return Err(DecoderError::Custom("Node record keys are not sorted"));
+			}
+			pairs.insert(key, rlp.at(3 + i * 2)?.as_raw().to_vec());
+		}
+		let mut record = NodeRecord { seq, pairs, signature, id: NodeId::new() };
+		record.id = record.verify().ok_or(DecoderError::Custom("Invalid node record signature"))?;
+		Ok(record)
+	}
+}
+
+impl fmt::Display for NodeRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", RECORD_PREFIX, to_base64_url(&rlp::encode(self)))
+	}
+}
+
+impl FromStr for NodeRecord {
+	type Err = Error;
+
+	/// Parse the textual "enr:" form of a record and verify its signature.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if !s.starts_with(RECORD_PREFIX) {
+			bail!(ErrorKind::InvalidNodeRecord);
+		}
+		let bytes = from_base64_url(&s[RECORD_PREFIX.len()..]).ok_or(ErrorKind::InvalidNodeRecord)?;
+		rlp::decode(&bytes).map_err(|_| ErrorKind::InvalidNodeRecord.into())
+	}
+}
+
+fn encoded<E: Encodable>(value: &E) -> Bytes {
+	rlp::encode(value).into_vec()
+}
+
+/// Compressed form of a secp256k1 public key.
+pub fn compress(public: &NodeId) -> [u8; 33] {
+	let mut compressed = [0u8; 33];
+	compressed[0] = if public[63] & 1 == 0 { 0x02 } else { 0x03 };
+	compressed[1..].copy_from_slice(&public[0..32]);
+	compressed
+}
+
+/// Unpadded base64 with the URL-safe alphabet.
+pub fn to_base64_url(data: &[u8]) -> String {
+	let mut out = String::with_capacity((data.len() * 4 + 2) / 3);
+	for chunk in data.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+		for i in 0..(chunk.len() + 1) {
+			out.push(BASE64_URL_ALPHABET[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+		}
+	}
+	out
+}
+
+pub fn from_base64_url(s: &str) -> Option<Bytes> {
+	let s = s.trim_right_matches('=');
+	if s.len() % 4 == 1 {
+		return None;
+	}
+	let mut out = Vec::with_capacity(s.len() * 3 / 4);
+	for chunk in s.as_bytes().chunks(4) {
+		let mut n = 0u32;
+		for (i, c) in chunk.iter().enumerate() {
+			let value = BASE64_URL_ALPHABET.iter().position(|a| a == c)? as u32;
+			n |= value << (18 - i * 6);
+		}
+		for i in 0..(chunk.len() - 1) {
+			out.push((n >> (16 - i * 8)) as u8);
+		}
+	}
+	Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethkey::{Random, Generator, Secret};
+
+	#[test]
+	fn decodes_eip_example_record() {
+		let text = "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8";
+		let record = NodeRecord::from_str(text).unwrap();
+		let key = Secret::from_str("b71c71a67e1177ad4e901695e1b4b9ee17ae16c6668d313eac2f96dbcda3f291")
+			.and_then(KeyPair::from_secret)
+			.unwrap();
+
+		assert_eq!(record.seq(), 1);
+		assert_eq!(record.id(), key.public());
+		assert_eq!(keccak(record.id()), H256::from_str("a448f24c6d18e575453db13171562b71999873db5b286df957af199ec94617f7").unwrap());
+		assert_eq!(record.get::<Vec<u8>>("ip"), Some(vec![127, 0, 0, 1]));
+		assert_eq!(record.get::<u16>("udp"), Some(30303));
+		assert_eq!(record.endpoint(), None);
+		assert_eq!(record.to_string(), text);
+	}
+
+	#[test]
+	fn signs_and_verifies_records() {
+		let key = Random.generate().unwrap();
+		let endpoint = NodeEndpoint { address: SocketAddr::from_str("10.0.0.1:30303").unwrap(), udp_port: 30301 };
+		let mut entries = BTreeMap::new();
+		entries.insert("chain".to_owned(), b"consortium".to_vec());
+
+		let record = NodeRecord::new(7, &key, &endpoint, &entries).unwrap();
+		let parsed = NodeRecord::from_str(&record.to_string()).unwrap();
+		assert_eq!(parsed, record);
+		assert_eq!(parsed.seq(), 7);
+		assert_eq!(parsed.id(), key.public());
+		assert_eq!(parsed.endpoint(), Some(endpoint.clone()));
+		assert!(parsed.matches(&entries));
+		entries.insert("chain".to_owned(), b"mainnet".to_vec());
+		assert!(!parsed.matches(&entries));
+
+		// Changing the content invalidates the signature.
+		let mut tampered = record.clone();
+		tampered.seq = 8;
+		assert!(NodeRecord::from_str(&tampered.to_string()).is_err());
+
+		entries.insert("ip".to_owned(), vec![1, 2, 3, 4]);
+		assert!(NodeRecord::new(1, &key, &endpoint, &entries).is_err());
+	}
+
+	#[test]
+	fn refresh_bumps_seq_on_change() {
+		let key = Random.generate().unwrap();
+		let endpoint = NodeEndpoint { address: SocketAddr::from_str("10.0.0.1:30303").unwrap(), udp_port: 30303 };
+		let entries = BTreeMap::new();
+
+		let record = NodeRecord::refresh(None, &key, &endpoint, &entries).unwrap();
+		assert_eq!(record.seq(), 1);
+		let unchanged = NodeRecord::refresh(Some(record.clone()), &key, &endpoint, &entries).unwrap();
+		assert_eq!(unchanged, record);
+
+		let moved = NodeEndpoint { address: SocketAddr::from_str("10.0.0.2:30303").unwrap(), udp_port: 30303 };
+		let updated = NodeRecord::refresh(Some(record), &key, &moved, &entries).unwrap();
+		assert_eq!(updated.seq(), 2);
+		assert_eq!(updated.endpoint(), Some(moved));
+	}
+
+	#[test]
+	fn base64_url_roundtrip() {
+		for len in 0..8 {
+			let data: Vec<u8> = (0..len).map(|i| (i * 97 + 251) as u8).collect();
+			assert_eq!(from_base64_url(&to_base64_url(&data)), Some(data));
+		}
+		assert_eq!(to_base64_url(b"\xfb\xff"), "-_8");
+		assert_eq!(from_base64_url("A"), None);
+		assert_eq!(from_base64_url("A*"), None);
+	}
+}
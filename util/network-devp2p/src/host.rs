@@ -38,10 +38,14 @@ use session::{Session, SessionData};
 use io::*;
 use PROTOCOL_VERSION;
 use node_table::*;
+use reputation::Verdict;
 use network::{NetworkConfiguration, NetworkIoMessage, ProtocolId, PeerId, PacketId};
 use network::{NonReservedPeerMode, NetworkContext as NetworkContextTrait};
-use network::{SessionInfo, Error, ErrorKind, DisconnectReason, NetworkProtocolHandler};
+use network::{SessionInfo, Error, ErrorKind, DisconnectReason, NetworkProtocolHandler, PeerBehaviour, BandwidthInfo};
 use discovery::{Discovery, TableUpdates, NodeEntry, MAX_DATAGRAM_SIZE};
+use enr::NodeRecord;
+use bandwidth::Bandwidth;
+use dns::{self, DnsResolver, NodeListUrl, Zone};
 use ip_utils::{map_external_address, select_public_address};
 use parity_path::restrict_permissions_owner;
 use parking_lot::{Mutex, RwLock};
@@ -62,6 +66,7 @@ const DISCOVERY_REFRESH: TimerToken = SYS_TIMER + 4;
 const FAST_DISCOVERY_REFRESH: TimerToken = SYS_TIMER + 5;
 const DISCOVERY_ROUND: TimerToken = SYS_TIMER + 6;
 const NODE_TABLE: TimerToken = SYS_TIMER + 7;
+const NODE_LISTS: TimerToken = SYS_TIMER + 8;
 const FIRST_SESSION: StreamToken = 0;
 const LAST_SESSION: StreamToken = FIRST_SESSION + MAX_SESSIONS - 1;
 const USER_TIMER: TimerToken = LAST_SESSION + 256;
@@ -78,6 +83,8 @@ const FAST_DISCOVERY_REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
 const DISCOVERY_ROUND_TIMEOUT: Duration = Duration::from_millis(300);
 // for NODE_TABLE TimerToken
 const NODE_TABLE_TIMEOUT: Duration = Duration::from_secs(300);
+// for NODE_LISTS TimerToken
+const NODE_LISTS_TIMEOUT: Duration = Duration::from_secs(1800);
 
 #[derive(Debug, PartialEq, Eq)]
 /// Protocol info
@@ -166,6 +173,11 @@ impl<'s> NetworkContextTrait for NetworkContext<'s> {
 			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
 	}
 
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour) {
+		self.io.message(NetworkIoMessage::ReportPeer(peer, behaviour))
+			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
+	}
+
 	fn is_expired(&self) -> bool {
 		self.session.as_ref().map_or(false, |s| s.lock().expired())
 	}
@@ -218,6 +230,10 @@ pub struct HostInfo {
 	pub local_endpoint: NodeEndpoint,
 	/// Public address + discovery port
 	pub public_endpoint: Option<NodeEndpoint>,
+	/// Our signed node record, created along with the public endpoint
+	pub node_record: Option<NodeRecord>,
+	/// Traffic counters and rate limits shared by all sessions
+	pub bandwidth: Arc<Bandwidth>,
 }
 
 impl HostInfo {
@@ -255,6 +271,7 @@ pub struct Host {
 	sessions: Arc<RwLock<Slab<SharedSession>>>,
 	discovery: Mutex<Option<Discovery<'static>>>,
 	nodes: RwLock<NodeTable>,
+	node_lists: Vec<NodeListUrl>,
 	handlers: RwLock<HashMap<ProtocolId, Arc<NetworkProtocolHandler + Sync>>>,
 	timers: RwLock<HashMap<TimerToken, ProtocolTimer>>,
 	timer_counter: RwLock<usize>,
@@ -295,6 +312,7 @@ impl Host {
 		let boot_nodes = config.boot_nodes.clone();
 		let reserved_nodes = config.reserved_nodes.clone();
 		config.max_handshakes = min(config.max_handshakes, MAX_HANDSHAKES as u32);
+		let bandwidth = Arc::new(Bandwidth::new(&config));
 
 		let mut host = Host {
 			info: RwLock::new(HostInfo {
@@ -304,6 +322,8 @@ impl Host {
 				protocol_version: PROTOCOL_VERSION,
 				capabilities: Vec::new(),
 				public_endpoint: None,
+				node_record: None,
+				bandwidth,
 				local_endpoint,
 			}),
 			discovery: Mutex::new(None),
@@ -311,6 +331,7 @@ impl Host {
 			tcp_listener: Mutex::new(tcp_listener),
 			sessions: Arc::new(RwLock::new(Slab::new_starting_at(FIRST_SESSION, MAX_SESSIONS))),
 			nodes: RwLock::new(NodeTable::new(path)),
+			node_lists: Vec::new(),
 			handlers: RwLock::new(HashMap::new()),
 			timers: RwLock::new(HashMap::new()),
 			timer_counter: RwLock::new(USER_TIMER),
@@ -320,7 +341,14 @@ impl Host {
 		};
 
 		for n in boot_nodes {
-			host.add_node(&n);
+			if dns::is_node_list_url(&n) {
+				match NodeListUrl::from_str(&n) {
+					Ok(url) => host.node_lists.push(url),
+					Err(e) => debug!(target: "network", "Could not add node list {}: {:?}", n, e),
+				}
+			} else {
+				host.add_node(&n);
+			}
 		}
 
 		for n in reserved_nodes {
@@ -401,6 +429,36 @@ impl Host {
 		info.public_endpoint.as_ref().map(|e| format!("{}", Node::new(*info.id(), e.clone())))
 	}
 
+	pub fn external_node_record(&self) -> Option<String> {
+		self.info.read().node_record.as_ref().map(|r| r.to_string())
+	}
+
+	pub fn node_record(&self, id: &NodeId) -> Option<String> {
+		self.nodes.read().record(id).map(|r| r.to_string())
+	}
+
+	pub fn bandwidth(&self) -> BandwidthInfo {
+		let bandwidth = self.info.read().bandwidth.clone();
+		let peers = self.sessions.read().iter()
+			.filter_map(|e| {
+				let s = e.lock();
+				match s.id() {
+					Some(id) if s.is_ready() => Some((*id, s.info.traffic)),
+					_ => None,
+				}
+			})
+			.collect();
+		BandwidthInfo {
+			total: bandwidth.total(),
+			protocols: bandwidth.protocols(),
+			peers,
+		}
+	}
+
+	pub fn node_reputation(&self, id: &NodeId) -> Option<i32> {
+		self.nodes.read().reputation(id)
+	}
+
 	pub fn local_url(&self) -> String {
 		let info = self.info.read();
 		format!("{}", Node::new(*info.id(), info.local_endpoint.clone()))
@@ -462,11 +520,14 @@ impl Host {
 		};
 
 		self.info.write().public_endpoint = Some(public_endpoint.clone());
+		let node_record = self.init_node_record(&public_endpoint);
 
 		if let Some(url) = self.external_url() {
 			io.message(NetworkIoMessage::NetworkStarted(url)).unwrap_or_else(|e| warn!("Error sending IO notification: {:?}", e));
 		}
 
+		self.refresh_node_lists();
+
 		// Initialize discovery.
 		let discovery = {
 			let info = self.info.read();
@@ -481,6 +542,10 @@ impl Host {
 			let socket = UdpSocket::bind(&udp_addr).expect("Error binding UDP socket");
 			*self.udp_socket.lock() = Some(socket);
 
+			if let Some(node_record) = node_record {
+				discovery.set_node_record(node_record);
+			}
+
 			discovery.add_node_list(self.nodes.read().entries());
 			*self.discovery.lock() = Some(discovery);
 			io.register_stream(DISCOVERY)?;
@@ -489,15 +554,93 @@ impl Host {
 			io.register_timer(DISCOVERY_ROUND, DISCOVERY_ROUND_TIMEOUT)?;
 		}
 		io.register_timer(NODE_TABLE, NODE_TABLE_TIMEOUT)?;
+		if !self.node_lists.is_empty() {
+			io.register_timer(NODE_LISTS, NODE_LISTS_TIMEOUT)?;
+		}
 		io.register_stream(TCP_ACCEPT)?;
 		Ok(())
 	}
 
+	/// Resolve the configured DNS node lists, reloading the zone file answering them.
+	fn refresh_node_lists(&self) {
+		if self.node_lists.is_empty() {
+			return;
+		}
+		let zone_file = self.info.read().config.dns_zone_file.clone();
+		match zone_file {
+			Some(path) => match Zone::load(Path::new(&path)) {
+				Ok(zone) => self.add_node_lists(&zone),
+				Err(e) => warn!(target: "network", "Error loading DNS zone file {}: {}", path, e),
+			},
+			None => warn!(target: "network", "No DNS zone file configured, node lists are not resolved"),
+		}
+	}
+
+	/// Add the nodes of all node lists to the node table and discovery.
+	fn add_node_lists(&self, resolver: &DnsResolver) {
+		for url in &self.node_lists {
+			let records = match dns::resolve(resolver, url) {
+				Ok(records) => records,
+				Err(e) => {
+					warn!(target: "network", "Error resolving node list {}: {}", url, e);
+					continue;
+				}
+			};
+			debug!(target: "network", "Resolved {} nodes from node list {}", records.len(), url);
+			for record in records {
+				let endpoint = match record.endpoint() {
+					Some(endpoint) => endpoint,
+					None => continue,
+				};
+				let entry = NodeEntry { endpoint, id: *record.id() };
+				let mut node = Node::new(entry.id, entry.endpoint.clone());
+				node.record = Some(record);
+				self.nodes.write().add_node(node);
+				if let Some(ref mut discovery) = *self.discovery.lock() {
+					discovery.add_node(entry);
+				}
+			}
+		}
+	}
+
+	/// Sign our node record for the public endpoint, keeping the stored record if it is up to date.
+	fn init_node_record(&self, public_endpoint: &NodeEndpoint) -> Option<NodeRecord> {
+		let mut info = self.info.write();
+		let path = info.config.net_config_path.clone();
+		let previous = path.as_ref().and_then(|p| load_node_record(Path::new(p)));
+		let record = match NodeRecord::refresh(previous.clone(), &info.keys, public_endpoint, &info.config.node_record_entries) {
+			Ok(record) => record,
+			Err(e) => {
+				warn!(target: "network", "Error creating node record: {:?}", e);
+				return None;
+			}
+		};
+		if previous.as_ref() != Some(&record) {
+			if let Some(ref path) = path {
+				save_node_record(Path::new(path), &record);
+			}
+		}
+		info.node_record = Some(record.clone());
+		Some(record)
+	}
+
 	fn maintain_network(&self, io: &IoContext<NetworkIoMessage>) {
 		self.keep_alive(io);
+		self.resume_throttled(io);
 		self.connect_peers(io);
 	}
 
+	/// Re-register sessions which skipped reading or writing because of a rate limit,
+	/// so that the event loop reports them again.
+	fn resume_throttled(&self, io: &IoContext<NetworkIoMessage>) {
+		for e in self.sessions.read().iter() {
+			let mut s = e.lock();
+			if s.take_throttled() {
+				io.update_registration(s.token()).unwrap_or_else(|e| debug!(target: "network", "Token registration error: {:?}", e));
+			}
+		}
+	}
+
 	fn have_session(&self, id: &NodeId) -> bool {
 		self.sessions.read().iter().any(|e| e.lock().info.id == Some(*id))
 	}
@@ -549,14 +692,14 @@ impl Host {
 	}
 
 	fn connect_peers(&self, io: &IoContext<NetworkIoMessage>) {
-		let (min_peers, mut pin, max_handshakes, allow_ips, self_id) = {
+		let (min_peers, mut pin, max_handshakes, allow_ips, record_filter, self_id) = {
 			let info = self.info.read();
 			if info.capabilities.is_empty() {
 				return;
 			}
 			let config = &info.config;
 
-			(config.min_peers, config.non_reserved_mode == NonReservedPeerMode::Deny, config.max_handshakes as usize, config.ip_filter.clone(), *info.id())
+			(config.min_peers, config.non_reserved_mode == NonReservedPeerMode::Deny, config.max_handshakes as usize, config.ip_filter.clone(), config.node_record_filter.clone(), *info.id())
 		};
 
 		let (handshake_count, egress_count, ingress_count) = self.session_count();
@@ -579,7 +722,7 @@ impl Host {
 		// iterate over all nodes, reserved ones coming first.
 		// if we are pinned to only reserved nodes, ignore all others.
 		let nodes = reserved_nodes.iter().cloned().chain(if !pin {
-			self.nodes.read().nodes(&allow_ips)
+			self.nodes.read().nodes_with_entries(&allow_ips, &record_filter)
 		} else {
 			Vec::new()
 		});
@@ -598,6 +741,36 @@ impl Host {
 		debug!(target: "network", "Connecting peers: {} sessions, {} pending + {} started", egress_count + ingress_count, handshake_count, started);
 	}
 
+	/// Disconnect the incoming peer with the worst reputation, if it is worse than the reputation of `id`.
+	/// Returns `false` if no peer was disconnected.
+	fn evict_ingress_peer(&self, id: &NodeId, token: StreamToken, io: &IoContext<NetworkIoMessage>) -> bool {
+		let victim = {
+			let nodes = self.nodes.read();
+			let reserved = self.reserved_nodes.read();
+			let sessions = self.sessions.read();
+			sessions.iter()
+				.filter_map(|session| {
+					let s = session.try_lock()?;
+					let peer_id = s.id()?;
+					if s.token() == token || !s.is_ready() || s.expired() || s.info.originated || reserved.contains(peer_id) {
+						return None;
+					}
+					Some((nodes.reputation(peer_id).unwrap_or(0), s.token()))
+				})
+				.min()
+		};
+
+		match victim {
+			Some((score, victim)) if score < self.nodes.read().reputation(id).unwrap_or(0) => {
+				trace!(target: "network", "Evicting peer {} with reputation {} for {:?}", victim, score, id);
+				io.message(NetworkIoMessage::Disconnect(victim))
+					.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
+				true
+			},
+			_ => false,
+		}
+	}
+
 	fn connect_peer(&self, id: &NodeId, io: &IoContext<NetworkIoMessage>) {
 		if self.have_session(id) {
 			trace!(target: "network", "Aborted connect. Node already connected.");
@@ -745,14 +918,31 @@ impl Host {
 
 							let id = *s.id().expect("Ready session always has id");
 
+							if self.nodes.read().is_banned(&id) && !self.reserved_nodes.read().contains(&id) {
+								trace!(target: "network", "Disconnecting banned peer {:?}", id);
+								s.disconnect(io, DisconnectReason::UselessPeer);
+								kill = true;
+								break;
+							}
+
+							if !self.filter.as_ref().map_or(true, |f| f.connection_allowed(&self_id, &id, ConnectionDirection::Inbound)) {
+								trace!(target: "network", "Inbound connection not allowed for {:?}", id);
+								s.disconnect(io, DisconnectReason::UnexpectedIdentity);
+								kill = true;
+								break;
+							}
+
 							// Check for the session limit.
 							// Outgoing connections are allowed as long as their count is <= min_peers
 							// Incoming connections are allowed to take all of the max_peers reserve, or at most half of the slots.
+							// Once those are taken, an incoming connection replaces the incoming peer with the worst reputation,
+							// if it is worse than its own.
 							let max_ingress = max(max_peers - min_peers, min_peers / 2);
 							if reserved_only ||
 								(s.info.originated && egress_count > min_peers) ||
 								(!s.info.originated && ingress_count > max_ingress) {
-								if !self.reserved_nodes.read().contains(&id) {
+								if !self.reserved_nodes.read().contains(&id) &&
+									(reserved_only || s.info.originated || !self.evict_ingress_peer(&id, token, io)) {
 									// only proceed if the connecting peer is reserved.
 									trace!(target: "network", "Disconnecting non-reserved peer {:?}", id);
 									s.disconnect(io, DisconnectReason::TooManyPeers);
@@ -761,13 +951,6 @@ impl Host {
 								}
 							}
 
-							if !self.filter.as_ref().map_or(true, |f| f.connection_allowed(&self_id, &id, ConnectionDirection::Inbound)) {
-								trace!(target: "network", "Inbound connection not allowed for {:?}", id);
-								s.disconnect(io, DisconnectReason::UnexpectedIdentity);
-								kill = true;
-								break;
-							}
-
 							ready_id = Some(id);
 
 							// Add it to the node table
@@ -1056,6 +1239,7 @@ impl IoHandler<NetworkIoMessage> for Host {
 				self.nodes.write().clear_useless();
 				self.nodes.write().save();
 			},
+			NODE_LISTS => self.refresh_node_lists(),
 			_ => match self.timers.read().get(&token).cloned() {
 				Some(timer) => match self.handlers.read().get(&timer.protocol).cloned() {
 					None => { warn!(target: "network", "No handler found for protocol: {:?}", timer.protocol) },
@@ -1130,6 +1314,26 @@ impl IoHandler<NetworkIoMessage> for Host {
 				trace!(target: "network", "Disabling peer {}", peer);
 				self.kill_connection(*peer, io, false);
 			},
+			NetworkIoMessage::ReportPeer(ref peer, ref behaviour) => {
+				let session = { self.sessions.read().get(*peer).cloned() };
+				let id = session.as_ref().and_then(|s| s.lock().id().cloned());
+				if let (Some(session), Some(id)) = (session, id) {
+					let verdict = self.nodes.write().note_behaviour(&id, *behaviour);
+					trace!(target: "network", "Peer {} reported: {:?}, reputation {:?}", peer, behaviour, self.nodes.read().reputation(&id));
+					if verdict != Verdict::Keep && !self.reserved_nodes.read().contains(&id) {
+						if verdict == Verdict::Ban {
+							debug!(target: "network", "Banning peer {} ({:?})", peer, id);
+						} else {
+							debug!(target: "network", "Disconnecting peer {} ({:?}) with a bad reputation", peer, id);
+							let mut nodes = self.nodes.write();
+							nodes.note_failure(&id);
+							nodes.mark_as_useless(&id);
+						}
+						session.lock().disconnect(io, DisconnectReason::UselessPeer);
+						self.kill_connection(*peer, io, false);
+					}
+				}
+			},
 			NetworkIoMessage::InitPublicInterface =>
 				self.init_public_interface(io).unwrap_or_else(|e| warn!("Error initializing public interface: {:?}", e)),
 			_ => {}	// ignore others.
@@ -1249,6 +1453,49 @@ fn load_key(path: &Path) -> Option<Secret> {
 	}
 }
 
+fn save_node_record(path: &Path, record: &NodeRecord) {
+	let mut path_buf = PathBuf::from(path);
+	if let Err(e) = fs::create_dir_all(path_buf.as_path()) {
+		warn!(target: "network", "Error creating node record directory: {:?}", e);
+		return;
+	};
+	path_buf.push("enr");
+	let mut file = match fs::File::create(path_buf.as_path()) {
+		Ok(file) => file,
+		Err(e) => {
+			warn!(target: "network", "Error creating node record file: {:?}", e);
+			return;
+		}
+	};
+	if let Err(e) = file.write_all(record.to_string().as_bytes()) {
+		warn!(target: "network", "Error writing node record file: {:?}", e);
+	}
+}
+
+fn load_node_record(path: &Path) -> Option<NodeRecord> {
+	let mut path_buf = PathBuf::from(path);
+	path_buf.push("enr");
+	let mut file = match fs::File::open(path_buf.as_path()) {
+		Ok(file) => file,
+		Err(e) => {
+			debug!(target: "network", "Error opening node record file: {:?}", e);
+			return None;
+		}
+	};
+	let mut buf = String::new();
+	if let Err(e) = file.read_to_string(&mut buf) {
+		warn!(target: "network", "Error reading node record file: {:?}", e);
+		return None;
+	}
+	match NodeRecord::from_str(buf.trim()) {
+		Ok(record) => Some(record),
+		Err(e) => {
+			warn!(target: "network", "Error parsing node record file: {:?}", e);
+			None
+		}
+	}
+}
+
 #[test]
 fn key_save_load() {
 	use tempdir::TempDir;
@@ -1260,6 +1507,19 @@ fn key_save_load() {
 	assert_eq!(key, r.unwrap());
 }
 
+#[test]
+fn node_record_save_load() {
+	use tempdir::TempDir;
+	use std::collections::BTreeMap;
+
+	let tempdir = TempDir::new("").unwrap();
+	let key = Random.generate().unwrap();
+	let endpoint = NodeEndpoint::from_str("127.0.0.1:30303").unwrap();
+	let record = NodeRecord::new(1, &key, &endpoint, &BTreeMap::new()).unwrap();
+	save_node_record(tempdir.path(), &record);
+	assert_eq!(load_node_record(tempdir.path()), Some(record));
+}
+
 #[test]
 fn host_client_url() {
 	let mut config = NetworkConfiguration::new_local();
@@ -1268,3 +1528,20 @@ fn host_client_url() {
 	let host: Host = Host::new(config, None).unwrap();
 	assert!(host.local_url().starts_with("enode://101b3ef5a4ea7a1c7928e24c4c75fd053c235d7b80c22ae5c03d145d0ac7396e2a4ffff9adee3133a7b05044a5cee08115fd65145e5165d646bde371010d803c@"));
 }
+
+#[test]
+fn host_separates_node_lists() {
+	let mut config = NetworkConfiguration::new_local();
+	config.boot_nodes = vec![
+		"enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2@nodes.example.org".into(),
+		"enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770".into(),
+	];
+	let host: Host = Host::new(config, None).unwrap();
+	assert_eq!(host.node_lists.len(), 1);
+	assert_eq!(host.node_lists[0].domain(), "nodes.example.org");
+	assert_eq!(host.nodes.read().entries().len(), 1);
+
+	// Without a resolver the node lists add nothing.
+	host.add_node_lists(&Zone::default());
+	assert_eq!(host.nodes.read().entries().len(), 1);
+}
@@ -105,6 +105,10 @@ mod session;
 mod discovery;
 mod service;
 mod node_table;
+mod enr;
+mod dns;
+mod reputation;
+mod bandwidth;
 mod ip_utils;
 
 pub use service::NetworkService;
@@ -112,5 +116,6 @@ pub use host::NetworkContext;
 
 pub use io::TimerToken;
 pub use node_table::{validate_node_url, NodeId};
+pub use dns::validate_node_list_url;
 
 const PROTOCOL_VERSION: u32 = 5;
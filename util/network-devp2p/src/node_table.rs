@@ -15,12 +15,14 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use discovery::{TableUpdates, NodeEntry};
+use enr::NodeRecord;
 use ethereum_types::H512;
 use ip_utils::*;
-use network::{Error, ErrorKind, AllowIP, IpFilter};
+use network::{Error, ErrorKind, AllowIP, IpFilter, PeerBehaviour};
+use reputation::{self, Reputation, Verdict};
 use rlp::{Rlp, RlpStream, DecoderError};
 use serde_json;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{self, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::net::{SocketAddr, ToSocketAddrs, SocketAddrV4, SocketAddrV6, Ipv4Addr, Ipv6Addr};
@@ -179,6 +181,8 @@ pub struct Node {
 	pub endpoint: NodeEndpoint,
 	pub peer_type: PeerType,
 	pub last_contact: Option<NodeContact>,
+	pub record: Option<NodeRecord>,
+	pub reputation: Reputation,
 }
 
 impl Node {
@@ -188,6 +192,8 @@ impl Node {
 			endpoint,
 			peer_type: PeerType::Optional,
 			last_contact: None,
+			record: None,
+			reputation: Reputation::default(),
 		}
 	}
 }
@@ -218,6 +224,8 @@ impl FromStr for Node {
 			endpoint,
 			peer_type: PeerType::Optional,
 			last_contact: None,
+			record: None,
+			reputation: Reputation::default(),
 		})
 	}
 }
@@ -256,8 +264,14 @@ impl NodeTable {
 
 	/// Add a node to table
 	pub fn add_node(&mut self, mut node: Node) {
-		// preserve node last_contact
+		// preserve node last_contact, reputation and the newest record
 		node.last_contact = self.nodes.get(&node.id).and_then(|n| n.last_contact);
+		node.reputation = self.nodes.get(&node.id).map_or_else(Reputation::default, |n| n.reputation);
+		if let Some(existing) = self.nodes.get(&node.id).and_then(|n| n.record.as_ref()) {
+			if node.record.as_ref().map_or(true, |record| record.seq() < existing.seq()) {
+				node.record = Some(existing.clone());
+			}
+		}
 		self.nodes.insert(node.id, node);
 	}
 
@@ -268,14 +282,15 @@ impl NodeTable {
 	/// - (1) Nodes with a successful contact are ordered (most recent success first)
 	/// - (2) Nodes with unknown contact (older than 1 week or new nodes) are randomly shuffled
 	/// - (3) Nodes with a failed contact are ordered (oldest failure first)
-	/// - The final result is the concatenation of (1), (2) and (3)
+	/// - The final result is the concatenation of (1), (2) and (3), stably sorted by reputation (highest first)
+	/// - Banned nodes are left out
 	fn ordered_entries(&self) -> Vec<&Node> {
 		let mut success = Vec::new();
 		let mut failures = Vec::new();
 		let mut unknown = Vec::new();
 
 		let nodes = self.nodes.values()
-			.filter(|n| !self.useless_nodes.contains(&n.id));
+			.filter(|n| !self.useless_nodes.contains(&n.id) && !n.reputation.is_banned());
 
 		for node in nodes {
 			// discard contact points older that aren't recent
@@ -310,14 +325,22 @@ impl NodeTable {
 
 		success.append(&mut unknown);
 		success.append(&mut failures);
+		success.sort_by_key(|n| -n.reputation.score());
 		success
 	}
 
 	/// Returns node ids sorted by failure percentage, for nodes with the same failure percentage the absolute number of
 	/// failures is considered.
 	pub fn nodes(&self, filter: &IpFilter) -> Vec<NodeId> {
+		self.nodes_with_entries(filter, &BTreeMap::new())
+	}
+
+	/// Same as `nodes`, but when `required` is not empty only nodes with a known record holding all of
+	/// the `required` entries are returned.
+	pub fn nodes_with_entries(&self, filter: &IpFilter, required: &BTreeMap<String, Vec<u8>>) -> Vec<NodeId> {
 		self.ordered_entries().iter()
 			.filter(|n| n.endpoint.is_allowed(&filter))
+			.filter(|n| required.is_empty() || n.record.as_ref().map_or(false, |r| r.matches(required)))
 			.map(|n| n.id)
 			.collect()
 	}
@@ -336,6 +359,11 @@ impl NodeTable {
 		self.nodes.get_mut(id)
 	}
 
+	/// Get the record of a particular node, if known
+	pub fn record(&self, id: &NodeId) -> Option<&NodeRecord> {
+		self.nodes.get(id).and_then(|n| n.record.as_ref())
+	}
+
 	/// Check if a node exists in the table.
 	pub fn contains(&self, id: &NodeId) -> bool {
 		self.nodes.contains_key(id)
@@ -347,6 +375,13 @@ impl NodeTable {
 			let entry = self.nodes.entry(node.id).or_insert_with(|| Node::new(node.id, node.endpoint.clone()));
 			entry.endpoint = node.endpoint;
 		}
+		for (id, record) in update.records.drain() {
+			if let Some(node) = self.nodes.get_mut(&id) {
+				if node.record.as_ref().map_or(true, |r| r.seq() < record.seq()) {
+					node.record = Some(record);
+				}
+			}
+		}
 		for r in update.removed {
 			if !reserved.contains(&r) {
 				self.nodes.remove(&r);
@@ -354,6 +389,21 @@ impl NodeTable {
 		}
 	}
 
+	/// Adjust the reputation of a node. Returns what to do with the peer.
+	pub fn note_behaviour(&mut self, id: &NodeId, behaviour: PeerBehaviour) -> Verdict {
+		self.nodes.get_mut(id).map_or(Verdict::Keep, |node| node.reputation.note(behaviour))
+	}
+
+	/// Current reputation score of a node
+	pub fn reputation(&self, id: &NodeId) -> Option<i32> {
+		self.nodes.get(id).map(|n| n.reputation.score())
+	}
+
+	/// Check if a node is currently banned.
+	pub fn is_banned(&self, id: &NodeId) -> bool {
+		self.nodes.get(id).map_or(false, |n| n.reputation.is_banned())
+	}
+
 	/// Set last contact as failure for a node
 	pub fn note_failure(&mut self, id: &NodeId) {
 		if let Some(node) = self.nodes.get_mut(id) {
@@ -389,8 +439,10 @@ impl NodeTable {
 			return;
 		}
 		path.push(NODES_FILE);
-		let node_ids = self.nodes(&IpFilter::default());
-		let nodes = node_ids.into_iter()
+		// keep banned nodes, so that bans outlive restarts
+		let banned = self.nodes.values().filter(|n| n.reputation.is_banned()).map(|n| n.id);
+		let node_ids = self.nodes(&IpFilter::default()).into_iter().chain(banned);
+		let nodes = node_ids
 			.map(|id| self.nodes.get(&id).expect("self.nodes() only returns node IDs from self.nodes"))
 			.take(MAX_NODES)
 			.map(Into::into)
@@ -485,6 +537,9 @@ mod json {
 	pub struct Node {
 		pub url: String,
 		pub last_contact: Option<NodeContact>,
+		pub enr: Option<String>,
+		pub reputation: Option<i32>,
+		pub banned_until: Option<u64>,
 	}
 
 	impl Node {
@@ -492,6 +547,8 @@ mod json {
 			match super::Node::from_str(&self.url) {
 				Ok(mut node) => {
 					node.last_contact = self.last_contact.map(|c| c.into_node_contact());
+					node.record = self.enr.and_then(|enr| enr.parse().ok());
+					node.reputation = Reputation::new(self.reputation.unwrap_or(0), self.banned_until.map(reputation::from_unix_time));
 					Some(node)
 				},
 				_ => None,
@@ -512,7 +569,10 @@ mod json {
 
 			Node {
 				url: format!("{}", node),
-				last_contact
+				last_contact,
+				enr: node.record.as_ref().map(|r| r.to_string()),
+				reputation: Some(node.reputation.score()),
+				banned_until: node.reputation.banned_until().map(reputation::to_unix_time),
 			}
 		}
 	}
@@ -526,6 +586,7 @@ mod tests {
 	use std::str::FromStr;
 	use tempdir::TempDir;
 	use ipnetwork::IpNetwork;
+	use ethkey::{Random, Generator};
 
 	#[test]
 	fn endpoint_parse() {
@@ -678,6 +739,73 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn table_record_filter() {
+		let key1 = Random.generate().unwrap();
+		let key2 = Random.generate().unwrap();
+		let endpoint = NodeEndpoint::from_str("22.99.55.44:7770").unwrap();
+		let mut consortium = BTreeMap::new();
+		consortium.insert("chain".to_owned(), b"consortium".to_vec());
+		let mut other = BTreeMap::new();
+		other.insert("chain".to_owned(), b"other".to_vec());
+
+		let mut table = NodeTable::new(None);
+		table.add_node(Node::new(*key1.public(), endpoint.clone()));
+		table.add_node(Node::new(*key2.public(), endpoint.clone()));
+		table.add_node(Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap());
+
+		let mut records = HashMap::new();
+		records.insert(*key1.public(), NodeRecord::new(1, &key1, &endpoint, &consortium).unwrap());
+		records.insert(*key2.public(), NodeRecord::new(1, &key2, &endpoint, &other).unwrap());
+		table.update(TableUpdates { added: HashMap::new(), removed: HashSet::new(), records }, &HashSet::new());
+
+		assert_eq!(table.nodes(&IpFilter::default()).len(), 3);
+		assert_eq!(table.nodes_with_entries(&IpFilter::default(), &consortium), vec![*key1.public()]);
+		assert_eq!(table.record(key2.public()).map(|r| r.seq()), Some(1));
+
+		// Records with an older sequence number are ignored.
+		let mut records = HashMap::new();
+		records.insert(*key1.public(), NodeRecord::new(0, &key1, &endpoint, &other).unwrap());
+		table.update(TableUpdates { added: HashMap::new(), removed: HashSet::new(), records }, &HashSet::new());
+		assert_eq!(table.nodes_with_entries(&IpFilter::default(), &consortium), vec![*key1.public()]);
+	}
+
+	#[test]
+	fn table_reputation() {
+		let tempdir = TempDir::new("").unwrap();
+		let id1 = H512::from_str("a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let id2 = H512::from_str("b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let id3 = H512::from_str("c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let endpoint = NodeEndpoint::from_str("22.99.55.44:7770").unwrap();
+
+		{
+			let mut table = NodeTable::new(Some(tempdir.path().to_str().unwrap().to_owned()));
+			for id in &[id1, id2, id3] {
+				table.add_node(Node::new(*id, endpoint.clone()));
+			}
+			table.note_success(&id1);
+			assert_eq!(table.note_behaviour(&id1, PeerBehaviour::UselessResponse), Verdict::Keep);
+			assert_eq!(table.note_behaviour(&id2, PeerBehaviour::GoodBlock), Verdict::Keep);
+			assert_eq!(table.note_behaviour(&id3, PeerBehaviour::BadBlock), Verdict::Disconnect);
+			assert_eq!(table.note_behaviour(&id3, PeerBehaviour::BadBlock), Verdict::Ban);
+			assert!(table.is_banned(&id3));
+
+			// Re-adding a node keeps its reputation.
+			table.add_node(Node::new(id2, endpoint.clone()));
+			assert_eq!(table.reputation(&id2), Some(10));
+			assert_eq!(table.nodes(&IpFilter::default()), vec![id2, id1]);
+			table.save();
+		}
+
+		{
+			let table = NodeTable::new(Some(tempdir.path().to_str().unwrap().to_owned()));
+			assert_eq!(table.reputation(&id1), Some(-50));
+			assert_eq!(table.reputation(&id2), Some(10));
+			assert!(table.is_banned(&id3));
+			assert_eq!(table.nodes(&IpFilter::default()), vec![id2, id1]);
+		}
+	}
+
 	#[test]
 	fn custom_allow() {
 		let filter = IpFilter {
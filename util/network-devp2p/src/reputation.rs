@@ -0,0 +1,194 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Node reputation. Scores are adjusted by the behaviour protocol handlers report and decay towards
+//! zero over time. Peers falling below `DISCONNECT_THRESHOLD` are disconnected, nodes falling below
+//! `BAN_THRESHOLD` are banned for `BAN_DURATION`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use network::PeerBehaviour;
+
+/// Time it takes for a score to decay to half its value.
+const HALF_LIFE: Duration = Duration::from_secs(60 * 60);
+/// Score at or below which a peer is disconnected.
+const DISCONNECT_THRESHOLD: i32 = -100;
+/// Score at or below which a node is banned.
+const BAN_THRESHOLD: i32 = -1000;
+/// Length of a ban.
+const BAN_DURATION: Duration = Duration::from_secs(60 * 60);
+const MAX_SCORE: i32 = 1000;
+const MIN_SCORE: i32 = -2000;
+
+/// Score change for a reported behaviour.
+fn score_change(behaviour: PeerBehaviour) -> i32 {
+	match behaviour {
+		PeerBehaviour::GoodBlock => 10,
+		PeerBehaviour::SlowResponse => -20,
+		PeerBehaviour::Unresponsive => -100,
+		PeerBehaviour::UselessResponse => -50,
+		PeerBehaviour::InvalidResponse => -200,
+		PeerBehaviour::BadBlock => -500,
+	}
+}
+
+/// What to do with a peer after its reputation changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+	/// Keep the peer connected.
+	Keep,
+	/// Disconnect the peer.
+	Disconnect,
+	/// The node just got banned, disconnect the peer.
+	Ban,
+}
+
+/// Reputation of a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reputation {
+	score: i32,
+	updated: SystemTime,
+	banned_until: Option<SystemTime>,
+}
+
+impl Default for Reputation {
+	fn default() -> Self {
+		Reputation::new(0, None)
+	}
+}
+
+impl Reputation {
+	/// Create a reputation with the given score, as of now.
+	pub fn new(score: i32, banned_until: Option<SystemTime>) -> Self {
+		Reputation {
+			score: score.max(MIN_SCORE).min(MAX_SCORE),
+			updated: SystemTime::now(),
+			banned_until,
+		}
+	}
+
+	/// Current score.
+	pub fn score(&self) -> i32 {
+		self.score_at(SystemTime::now())
+	}
+
+	/// Whether the node is currently banned.
+	pub fn is_banned(&self) -> bool {
+		self.banned_until.map_or(false, |until| until > SystemTime::now())
+	}
+
+	/// End of the current ban.
+	pub fn banned_until(&self) -> Option<SystemTime> {
+		match self.banned_until {
+			Some(until) if until > SystemTime::now() => Some(until),
+			_ => None,
+		}
+	}
+
+	/// Apply the score change for `behaviour`.
+	pub fn note(&mut self, behaviour: PeerBehaviour) -> Verdict {
+		self.note_at(behaviour, SystemTime::now())
+	}
+
+	fn note_at(&mut self, behaviour: PeerBehaviour, now: SystemTime) -> Verdict {
+		self.score = (self.score_at(now) + score_change(behaviour)).max(MIN_SCORE).min(MAX_SCORE);
+		self.updated = now;
+		let banned = self.banned_until.map_or(false, |until| until > now);
+		if !banned && self.score <= BAN_THRESHOLD {
+			self.banned_until = Some(now + BAN_DURATION);
+			return Verdict::Ban;
+		}
+		if self.score <= DISCONNECT_THRESHOLD {
+			Verdict::Disconnect
+		} else {
+			Verdict::Keep
+		}
+	}
+
+	fn score_at(&self, now: SystemTime) -> i32 {
+		let elapsed = now.duration_since(self.updated).unwrap_or_default();
+		let half_lives = (elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9) / HALF_LIFE.as_secs() as f64;
+		(self.score as f64 * 0.5f64.powf(half_lives)).round() as i32
+	}
+}
+
+/// Seconds since the epoch, for persisting ban times.
+pub fn to_unix_time(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Inverse of `to_unix_time`.
+pub fn from_unix_time(secs: u64) -> SystemTime {
+	UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn score_decays() {
+		let now = SystemTime::now();
+		let mut reputation = Reputation::default();
+		reputation.note_at(PeerBehaviour::BadBlock, now);
+		assert_eq!(reputation.score_at(now), -500);
+		assert_eq!(reputation.score_at(now + HALF_LIFE), -250);
+		assert_eq!(reputation.score_at(now + HALF_LIFE * 2), -125);
+
+		reputation.note_at(PeerBehaviour::GoodBlock, now + HALF_LIFE);
+		assert_eq!(reputation.score_at(now + HALF_LIFE), -240);
+	}
+
+	#[test]
+	fn disconnects_below_threshold() {
+		let now = SystemTime::now();
+		let mut reputation = Reputation::default();
+		assert_eq!(reputation.note_at(PeerBehaviour::UselessResponse, now), Verdict::Keep);
+		assert_eq!(reputation.note_at(PeerBehaviour::UselessResponse, now), Verdict::Disconnect);
+		assert!(!reputation.is_banned());
+
+		// A good record outweighs an invalid response.
+		let mut reputation = Reputation::default();
+		for _ in 0..20 {
+			reputation.note_at(PeerBehaviour::GoodBlock, now);
+		}
+		assert_eq!(reputation.note_at(PeerBehaviour::InvalidResponse, now), Verdict::Keep);
+	}
+
+	#[test]
+	fn bans_below_threshold() {
+		let now = SystemTime::now();
+		let mut reputation = Reputation::default();
+		assert_eq!(reputation.note_at(PeerBehaviour::BadBlock, now), Verdict::Disconnect);
+		assert!(!reputation.is_banned());
+		assert_eq!(reputation.note_at(PeerBehaviour::BadBlock, now), Verdict::Ban);
+		assert!(reputation.is_banned());
+		assert_eq!(reputation.banned_until, Some(now + BAN_DURATION));
+		// Already banned.
+		assert_eq!(reputation.note_at(PeerBehaviour::BadBlock, now), Verdict::Disconnect);
+		assert_eq!(reputation.score_at(now), -1500);
+	}
+
+	#[test]
+	fn score_is_bounded() {
+		let now = SystemTime::now();
+		let mut reputation = Reputation::default();
+		for _ in 0..200 {
+			reputation.note_at(PeerBehaviour::GoodBlock, now);
+		}
+		assert_eq!(reputation.score_at(now), MAX_SCORE);
+		assert_eq!(Reputation::new(-5000, None).score, MIN_SCORE);
+	}
+}
@@ -15,8 +15,9 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use network::{Error, NetworkConfiguration, NetworkProtocolHandler, NonReservedPeerMode};
-use network::{NetworkContext, PeerId, ProtocolId, NetworkIoMessage};
+use network::{NetworkContext, PeerId, ProtocolId, NetworkIoMessage, BandwidthInfo};
 use host::Host;
+use node_table::NodeId;
 use io::*;
 use parking_lot::RwLock;
 use std::net::SocketAddr;
@@ -109,6 +110,30 @@ impl NetworkService {
 		host.as_ref().and_then(|h| h.external_url())
 	}
 
+	/// Returns our signed node record in its "enr:" text form if available.
+	pub fn external_node_record(&self) -> Option<String> {
+		let host = self.host.read();
+		host.as_ref().and_then(|h| h.external_node_record())
+	}
+
+	/// Returns the known node record of the given node in its "enr:" text form.
+	pub fn node_record(&self, id: &NodeId) -> Option<String> {
+		let host = self.host.read();
+		host.as_ref().and_then(|h| h.node_record(id))
+	}
+
+	/// Returns bytes sent and received, in total, per subprotocol and per connected peer.
+	pub fn bandwidth(&self) -> Option<BandwidthInfo> {
+		let host = self.host.read();
+		host.as_ref().map(|h| h.bandwidth())
+	}
+
+	/// Returns the current reputation score of the given node.
+	pub fn node_reputation(&self, id: &NodeId) -> Option<i32> {
+		let host = self.host.read();
+		host.as_ref().and_then(|h| h.node_reputation(id))
+	}
+
 	/// Returns external url if available.
 	pub fn local_url(&self) -> Option<String> {
 		let host = self.host.read();
@@ -17,6 +17,7 @@
 use std::{str, io};
 use std::net::SocketAddr;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use mio::*;
@@ -24,10 +25,11 @@ use mio::deprecated::{Handler, EventLoop};
 use mio::tcp::*;
 use ethereum_types::H256;
 use rlp::{Rlp, RlpStream, EMPTY_LIST_RLP};
+use bandwidth::{Bandwidth, RateLimiter, P2P_PROTOCOL};
 use connection::{EncryptedConnection, Packet, Connection, MAX_PAYLOAD_SIZE};
 use handshake::Handshake;
 use io::{IoContext, StreamToken};
-use network::{Error, ErrorKind, DisconnectReason, SessionInfo, ProtocolId, PeerCapabilityInfo};
+use network::{Error, ErrorKind, DisconnectReason, SessionInfo, ProtocolId, PeerCapabilityInfo, TrafficStats};
 use network::SessionCapabilityInfo;
 use host::*;
 use node_table::NodeId;
@@ -63,6 +65,13 @@ pub struct Session {
 	// Protocol states -- accumulates pending packets until signaled as ready.
 	protocol_states: HashMap<ProtocolId, ProtocolState>,
 	compression: bool,
+	// Global traffic counters and limits.
+	bandwidth: Arc<Bandwidth>,
+	// Limits of this session.
+	upload: Option<RateLimiter>,
+	download: Option<RateLimiter>,
+	// Reading or writing was skipped because of a rate limit.
+	throttled: bool,
 }
 
 enum State {
@@ -120,12 +129,17 @@ impl Session {
 				originated,
 				remote_address: "Handshake".to_owned(),
 				local_address: local_addr,
+				traffic: TrafficStats::default(),
 			},
 			ping_time: Instant::now(),
 			pong_time: None,
 			expired: false,
 			protocol_states: HashMap::new(),
 			compression: false,
+			bandwidth: host.bandwidth.clone(),
+			upload: host.bandwidth.peer_upload_limiter(),
+			download: host.bandwidth.peer_download_limiter(),
+			throttled: false,
 		})
 	}
 
@@ -187,6 +201,10 @@ impl Session {
 		if self.expired() {
 			return Ok(SessionData::None)
 		}
+		if self.is_ready() && !self.can_receive() {
+			self.throttled = true;
+			return Ok(SessionData::None)
+		}
 		let mut create_session = false;
 		let mut packet_data = None;
 		match self.state {
@@ -215,6 +233,10 @@ impl Session {
 
 	/// Writable IO handler. Sends pending packets.
 	pub fn writable<Message>(&mut self, io: &IoContext<Message>, _host: &HostInfo) -> Result<(), Error> where Message: Send + Sync + Clone {
+		if self.is_ready() && !self.can_send() {
+			self.throttled = true;
+			return Ok(())
+		}
 		match self.state {
 			State::Handshake(ref mut h) => h.writable(io),
 			State::Session(ref mut s) => s.writable(io),
@@ -289,7 +311,38 @@ impl Session {
 			payload = &compressed[0..len];
 		}
 		rlp.append_raw(payload, 1);
-		self.send(io, &rlp.drain())
+		let data = rlp.drain();
+		self.note_sent(protocol.unwrap_or(P2P_PROTOCOL), data.len());
+		self.send(io, &data)
+	}
+
+	/// Returns `true` if reading or writing was skipped because of a rate limit since the last call.
+	pub fn take_throttled(&mut self) -> bool {
+		::std::mem::replace(&mut self.throttled, false)
+	}
+
+	fn can_send(&mut self) -> bool {
+		self.upload.as_mut().map_or(true, RateLimiter::allows) && self.bandwidth.can_send()
+	}
+
+	fn can_receive(&mut self) -> bool {
+		self.download.as_mut().map_or(true, RateLimiter::allows) && self.bandwidth.can_receive()
+	}
+
+	fn note_sent(&mut self, protocol: ProtocolId, bytes: usize) {
+		self.info.traffic.sent += bytes as u64;
+		if let Some(ref mut upload) = self.upload {
+			upload.consume(bytes);
+		}
+		self.bandwidth.note_sent(protocol, bytes);
+	}
+
+	fn note_received(&mut self, protocol: ProtocolId, bytes: usize) {
+		self.info.traffic.received += bytes as u64;
+		if let Some(ref mut download) = self.download {
+			download.consume(bytes);
+		}
+		self.bandwidth.note_received(protocol, bytes);
 	}
 
 	/// Keep this session alive. Returns false if ping timeout happened
@@ -338,6 +391,13 @@ impl Session {
 		if packet_id != PACKET_HELLO && packet_id != PACKET_DISCONNECT && !self.had_hello {
 			return Err(ErrorKind::BadProtocol.into());
 		}
+		let protocol = match packet_id {
+			PACKET_USER ... PACKET_LAST => self.info.capabilities.iter()
+				.find(|c| packet_id >= c.id_offset && packet_id < c.id_offset + c.packet_count)
+				.map_or(P2P_PROTOCOL, |c| c.protocol),
+			_ => P2P_PROTOCOL,
+		};
+		self.note_received(protocol, packet.data.len());
 		let data = if self.compression {
 			let compressed = &packet.data[1..];
 			if snappy::decompressed_len(&compressed)? > MAX_PAYLOAD_SIZE {
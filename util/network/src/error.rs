@@ -136,6 +136,18 @@ error_chain! {
 			display("Invalid node id"),
 		}
 
+		#[doc = "Invalid node record"]
+		InvalidNodeRecord {
+			description("Invalid node record"),
+			display("Invalid node record"),
+		}
+
+		#[doc = "Invalid DNS node list"]
+		InvalidNodeList(reason: String) {
+			description("Invalid DNS node list"),
+			display("Invalid DNS node list: {}", reason),
+		}
+
 		#[doc = "Packet size is over the protocol limit"]
 		OversizedPacket {
 			description("Packet is too large"),
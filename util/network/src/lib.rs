@@ -39,7 +39,7 @@ pub use io::TimerToken;
 pub use error::{Error, ErrorKind, DisconnectReason};
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
 use std::str::{self, FromStr};
 use std::sync::Arc;
@@ -87,10 +87,29 @@ pub enum NetworkIoMessage {
 	Disconnect(PeerId),
 	/// Disconnect and temporary disable peer.
 	DisablePeer(PeerId),
+	/// Adjust the reputation of a peer.
+	ReportPeer(PeerId, PeerBehaviour),
 	/// Network has been started with the host as the given enode.
 	NetworkStarted(String),
 }
 
+/// Peer behaviour reported by protocol handlers, adjusting the reputation of the peer's node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerBehaviour {
+	/// Peer sent a block which was successfully imported.
+	GoodBlock,
+	/// Peer sent a block which failed verification.
+	BadBlock,
+	/// Peer sent a response we could not use.
+	UselessResponse,
+	/// Peer sent an invalid response.
+	InvalidResponse,
+	/// Peer did not respond to a request in time.
+	SlowResponse,
+	/// Peer did not respond to a request, or to the handshake, at all.
+	Unresponsive,
+}
+
 /// Shared session information
 #[derive(Debug, Clone)]
 pub struct SessionInfo {
@@ -112,6 +131,28 @@ pub struct SessionInfo {
 	pub remote_address: String,
 	/// Local endpoint address of the session
 	pub local_address: String,
+	/// Bytes exchanged over the session
+	pub traffic: TrafficStats,
+}
+
+/// Number of bytes sent and received. RLPx framing is not included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficStats {
+	/// Bytes sent
+	pub sent: u64,
+	/// Bytes received
+	pub received: u64,
+}
+
+/// Bandwidth used by the network, split up by subprotocol and by connected peer.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BandwidthInfo {
+	/// Traffic of all sessions since the network started
+	pub total: TrafficStats,
+	/// Traffic per subprotocol since the network started. Base RLPx packets are accounted under `p2p`.
+	pub protocols: BTreeMap<ProtocolId, TrafficStats>,
+	/// Traffic of each connected peer since it connected
+	pub peers: BTreeMap<NodeId, TrafficStats>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -183,8 +224,10 @@ pub struct NetworkConfiguration {
 	pub nat_enabled: bool,
 	/// Enable discovery
 	pub discovery_enabled: bool,
-	/// List of initial node addresses
+	/// List of initial node addresses, either `enode://` urls or `enrtree://` DNS node lists
 	pub boot_nodes: Vec<String>,
+	/// Zone file answering TXT queries for `enrtree://` node lists
+	pub dns_zone_file: Option<String>,
 	/// Use provided node key instead of default
 	pub use_secret: Option<Secret>,
 	/// Minimum number of connected peers to maintain
@@ -203,6 +246,18 @@ pub struct NetworkConfiguration {
 	pub ip_filter: IpFilter,
 	/// Client identifier
 	pub client_version: String,
+	/// Custom key/value pairs advertised in our node record.
+	pub node_record_entries: BTreeMap<String, Vec<u8>>,
+	/// Key/value pairs a node record must contain for a non-reserved node to be connected to.
+	pub node_record_filter: BTreeMap<String, Vec<u8>>,
+	/// Maximum total upload rate in bytes per second
+	pub max_upload_rate: Option<u64>,
+	/// Maximum total download rate in bytes per second
+	pub max_download_rate: Option<u64>,
+	/// Maximum upload rate to a single peer in bytes per second
+	pub max_peer_upload_rate: Option<u64>,
+	/// Maximum download rate from a single peer in bytes per second
+	pub max_peer_download_rate: Option<u64>,
 }
 
 impl Default for NetworkConfiguration {
@@ -223,6 +278,7 @@ impl NetworkConfiguration {
 			nat_enabled: true,
 			discovery_enabled: true,
 			boot_nodes: Vec::new(),
+			dns_zone_file: None,
 			use_secret: None,
 			min_peers: 25,
 			max_peers: 50,
@@ -232,6 +288,12 @@ impl NetworkConfiguration {
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
 			client_version: "Parity-network".into(),
+			node_record_entries: BTreeMap::new(),
+			node_record_filter: BTreeMap::new(),
+			max_upload_rate: None,
+			max_download_rate: None,
+			max_peer_upload_rate: None,
+			max_peer_download_rate: None,
 		}
 	}
 
@@ -268,6 +330,10 @@ pub trait NetworkContext {
 	/// Disconnect peer. Reconnect can be attempted later.
 	fn disconnect_peer(&self, peer: PeerId);
 
+	/// Adjust the reputation of a peer. Peers with a bad enough reputation are disconnected, and banned for a while
+	/// if it gets even worse.
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour);
+
 	/// Check if the session is still active.
 	fn is_expired(&self) -> bool;
 
@@ -311,6 +377,10 @@ impl<'a, T> NetworkContext for &'a T where T: ?Sized + NetworkContext {
 		(**self).disconnect_peer(peer)
 	}
 
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour) {
+		(**self).report_peer(peer, behaviour)
+	}
+
 	fn is_expired(&self) -> bool {
 		(**self).is_expired()
 	}